@@ -17,7 +17,7 @@ use std::panic;
 use std::sync::atomic::AtomicUsize;
 use std::sync::Once;
 use std::thread;
-use crate::{Delimiter, Level, LineColumn, Spacing};
+use crate::{Applicability, Delimiter, Level, LineColumn, Spacing};
 
 /// Higher-order macro describing the server RPC API, allowing automatic
 /// generation of type-safe Rust APIs, both client-side and server-side.
@@ -142,6 +142,16 @@ macro_rules! with_api {
                     msg: &str,
                     span: $S::MultiSpan,
                 );
+                fn suggestion(
+                    $self: &mut $S::Diagnostic,
+                    msg: &str,
+                    applicability: Applicability,
+                );
+                fn suggestion_part(
+                    $self: &mut $S::Diagnostic,
+                    span: $S::Span,
+                    replacement: &str,
+                );
                 fn emit($self: $S::Diagnostic);
             },
             Span {
@@ -332,6 +342,7 @@ mark_noop! {
     &'a [u8],
     &'a str,
     String,
+    Applicability,
     Delimiter,
     Level,
     LineColumn,
@@ -355,6 +366,14 @@ rpc_encode_decode!(
         Help,
     }
 );
+rpc_encode_decode!(
+    enum Applicability {
+        MachineApplicable,
+        MaybeIncorrect,
+        HasPlaceholders,
+        Unspecified,
+    }
+);
 rpc_encode_decode!(struct LineColumn { line, column });
 rpc_encode_decode!(
     enum Spacing {
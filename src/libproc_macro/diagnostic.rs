@@ -15,6 +15,24 @@ pub enum Level {
     Help,
 }
 
+/// An enum representing how confident a diagnostic is in one of its suggestions, which tells
+/// tools whether they can apply it without asking.
+#[unstable(feature = "proc_macro_diagnostic", issue = "54140")]
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
+pub enum Applicability {
+    /// The suggestion is definitely what the user intended. It can be applied automatically.
+    MachineApplicable,
+    /// The suggestion may be what the user intended, but it is uncertain. It should compile
+    /// if applied.
+    MaybeIncorrect,
+    /// The suggestion contains placeholders like `(...)` or `{ /* fields */ }`, and won't
+    /// compile until the user replaces them.
+    HasPlaceholders,
+    /// The applicability of the suggestion is unknown.
+    Unspecified,
+}
+
 /// Trait implemented by types that can be converted into a set of `Span`s.
 #[unstable(feature = "proc_macro_diagnostic", issue = "54140")]
 pub trait MultiSpan {
@@ -51,7 +69,16 @@ pub struct Diagnostic {
     level: Level,
     message: String,
     spans: Vec<Span>,
-    children: Vec<Diagnostic>
+    children: Vec<Diagnostic>,
+    suggestions: Vec<Suggestion>,
+}
+
+/// A suggestion of a `Diagnostic`, replacing the code at each of the spans of its parts.
+#[derive(Clone, Debug)]
+struct Suggestion {
+    message: String,
+    parts: Vec<(Span, String)>,
+    applicability: Applicability,
 }
 
 macro_rules! diagnostic_child_methods {
@@ -100,7 +127,8 @@ impl Diagnostic {
             level: level,
             message: message.into(),
             spans: vec![],
-            children: vec![]
+            children: vec![],
+            suggestions: vec![],
         }
     }
 
@@ -114,7 +142,8 @@ impl Diagnostic {
             level: level,
             message: message.into(),
             spans: spans.into_spans(),
-            children: vec![]
+            children: vec![],
+            suggestions: vec![],
         }
     }
 
@@ -123,6 +152,35 @@ impl Diagnostic {
     diagnostic_child_methods!(span_note, note, Level::Note);
     diagnostic_child_methods!(span_help, help, Level::Help);
 
+    /// Adds a suggestion to `self` to replace the code at `span` with `replacement`, described
+    /// by `message`.
+    #[unstable(feature = "proc_macro_diagnostic", issue = "54140")]
+    pub fn span_suggestion<T, U>(
+        self,
+        span: Span,
+        message: T,
+        replacement: U,
+        applicability: Applicability,
+    ) -> Diagnostic
+        where T: Into<String>, U: Into<String>
+    {
+        self.multipart_suggestion(message, vec![(span, replacement.into())], applicability)
+    }
+
+    /// Adds a suggestion to `self` to replace the code at each of the spans of `parts` with
+    /// the replacement it is paired with, all at once, described by `message`. Suggestions
+    /// without any part are left out.
+    #[unstable(feature = "proc_macro_diagnostic", issue = "54140")]
+    pub fn multipart_suggestion<T: Into<String>>(
+        mut self,
+        message: T,
+        parts: Vec<(Span, String)>,
+        applicability: Applicability,
+    ) -> Diagnostic {
+        self.suggestions.push(Suggestion { message: message.into(), parts, applicability });
+        self
+    }
+
     /// Returns the diagnostic `level` for `self`.
     #[unstable(feature = "proc_macro_diagnostic", issue = "54140")]
     pub fn level(&self) -> Level {
@@ -184,6 +242,15 @@ impl Diagnostic {
         for c in self.children {
             diag.sub(c.level, &c.message[..], to_internal(c.spans));
         }
+        for suggestion in self.suggestions {
+            if suggestion.parts.is_empty() {
+                continue;
+            }
+            diag.suggestion(&suggestion.message[..], suggestion.applicability);
+            for (span, replacement) in suggestion.parts {
+                diag.suggestion_part(span.0, &replacement[..]);
+            }
+        }
         diag.emit();
     }
 }
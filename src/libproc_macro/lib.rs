@@ -39,7 +39,7 @@ pub mod bridge;
 mod diagnostic;
 
 #[unstable(feature = "proc_macro_diagnostic", issue = "54140")]
-pub use diagnostic::{Diagnostic, Level, MultiSpan};
+pub use diagnostic::{Applicability, Diagnostic, Level, MultiSpan};
 
 use std::{fmt, iter, mem};
 use std::ops::{Bound, RangeBounds};
@@ -11,6 +11,7 @@ use crate::ty::TyCtxt;
 use crate::ty::query::Providers;
 
 use std::fmt::{self, Display};
+use syntax::attr;
 use syntax::symbol::sym;
 use syntax_pos::Span;
 
@@ -159,6 +160,9 @@ impl CheckAttrVisitor<'tcx> {
 
     /// Checks if the `#[repr]` attributes on `item` are valid.
     fn check_repr(&self, item: &hir::Item, target: Target) {
+        // Reports the hints that conflict whatever the item is.
+        attr::parse_repr(&self.tcx.sess.parse_sess, &item.attrs);
+
         // Extract the names of all repr hints, e.g., [foo, bar, align] for:
         // ```
         // #[repr(foo)]
@@ -207,9 +207,13 @@ enum ImplTraitContext<'a> {
 /// Position in which `impl Trait` is disallowed.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum ImplTraitPosition {
-    /// Disallowed in `let` / `const` / `static` bindings.
+    /// Disallowed in `const` / `static` bindings.
     Binding,
 
+    /// Disallowed in `let` bindings, function pointers and closure signatures, where it is
+    /// reported by `syntax::validate` already.
+    Validated,
+
     /// All other posiitons.
     Other,
 }
@@ -1228,7 +1232,13 @@ impl<'a> LoweringContext<'a> {
                                 ),
                                 unsafety: this.lower_unsafety(f.unsafety),
                                 abi: f.abi,
-                                decl: this.lower_fn_decl(&f.decl, None, false, None),
+                                decl: this.lower_fn_decl(
+                                    &f.decl,
+                                    None,
+                                    false,
+                                    None,
+                                    ImplTraitPosition::Validated,
+                                ),
                                 param_names: this.lower_fn_params_to_names(&f.decl),
                             }))
                         },
@@ -1339,6 +1349,9 @@ impl<'a> LoweringContext<'a> {
                             }),
                         ))
                     }
+                    ImplTraitContext::Disallowed(ImplTraitPosition::Validated) => {
+                        hir::TyKind::Err
+                    }
                     ImplTraitContext::Disallowed(pos) => {
                         let allowed_in = if self.sess.features_untracked()
                                                 .impl_trait_in_bindings {
@@ -2103,7 +2116,7 @@ impl<'a> LoweringContext<'a> {
                     if self.sess.features_untracked().impl_trait_in_bindings {
                         ImplTraitContext::OpaqueTy(Some(parent_def_id))
                     } else {
-                        ImplTraitContext::Disallowed(ImplTraitPosition::Binding)
+                        ImplTraitContext::Disallowed(ImplTraitPosition::Validated)
                     }
                 )),
             pat: self.lower_pat(&l.pat),
@@ -2143,12 +2156,14 @@ impl<'a> LoweringContext<'a> {
     // `make_ret_async`: if `Some`, converts `-> T` into `-> impl Future<Output = T>` in the
     //      return type. This is used for `async fn` declarations. The `NodeId` is the ID of the
     //      return type `impl Trait` item.
+    // `impl_trait_position`: the position `impl Trait` is disallowed in, if it is.
     fn lower_fn_decl(
         &mut self,
         decl: &FnDecl,
         mut in_band_ty_params: Option<(DefId, &mut Vec<hir::GenericParam>)>,
         impl_trait_return_allow: bool,
         make_ret_async: Option<NodeId>,
+        impl_trait_position: ImplTraitPosition,
     ) -> P<hir::FnDecl> {
         let lt_mode = if make_ret_async.is_some() {
             // In `async fn`, argument-position elided lifetimes
@@ -2168,7 +2183,10 @@ impl<'a> LoweringContext<'a> {
                     if let Some((_, ibty)) = &mut in_band_ty_params {
                         this.lower_ty_direct(&param.ty, ImplTraitContext::Universal(ibty))
                     } else {
-                        this.lower_ty_direct(&param.ty, ImplTraitContext::disallowed())
+                        this.lower_ty_direct(
+                            &param.ty,
+                            ImplTraitContext::Disallowed(impl_trait_position),
+                        )
                     }
                 })
                 .collect::<HirVec<_>>()
@@ -2189,7 +2207,8 @@ impl<'a> LoweringContext<'a> {
                         ))
                     }
                     _ => {
-                        hir::Return(self.lower_ty(ty, ImplTraitContext::disallowed()))
+                        let itctx = ImplTraitContext::Disallowed(impl_trait_position);
+                        hir::Return(self.lower_ty(ty, itctx))
                     }
                 },
                 FunctionRetTy::Default(span) => hir::DefaultReturn(span),
@@ -1,4 +1,5 @@
-use super::{LoweringContext, ParamMode, ParenthesizedGenericArgs, ImplTraitContext};
+use super::{LoweringContext, ParamMode, ParenthesizedGenericArgs};
+use super::{ImplTraitContext, ImplTraitPosition};
 use crate::hir::{self, HirVec};
 use crate::hir::def::Res;
 use crate::hir::ptr::P;
@@ -462,7 +463,13 @@ impl LoweringContext<'_> {
             output,
             c_variadic: false
         };
-        let decl = self.lower_fn_decl(&ast_decl, None, /* impl trait allowed */ false, None);
+        let decl = self.lower_fn_decl(
+            &ast_decl,
+            None,
+            /* impl trait allowed */ false,
+            None,
+            ImplTraitPosition::Other,
+        );
         let body_id = self.lower_fn_body(&ast_decl, |this| {
             this.generator_kind = Some(hir::GeneratorKind::Async);
             body(this)
@@ -663,7 +670,7 @@ impl LoweringContext<'_> {
         fn_decl_span: Span,
     ) -> hir::ExprKind {
         // Lower outside new scope to preserve `is_in_loop_condition`.
-        let fn_decl = self.lower_fn_decl(decl, None, false, None);
+        let fn_decl = self.lower_fn_decl(decl, None, false, None, ImplTraitPosition::Validated);
 
         self.with_new_scopes(|this| {
             let prev = this.current_item;
@@ -755,7 +762,8 @@ impl LoweringContext<'_> {
         // We need to lower the declaration outside the new scope, because we
         // have to conserve the state of being inside a loop condition for the
         // closure argument types.
-        let fn_decl = self.lower_fn_decl(&outer_decl, None, false, None);
+        let fn_decl =
+            self.lower_fn_decl(&outer_decl, None, false, None, ImplTraitPosition::Validated);
 
         self.with_new_scopes(|this| {
             // FIXME(cramertj): allow `async` non-`move` closures with arguments.
@@ -329,7 +329,8 @@ impl LoweringContext<'_> {
                             &decl,
                             Some((fn_def_id, idty)),
                             true,
-                            header.asyncness.node.opt_return_id()
+                            header.asyncness.node.opt_return_id(),
+                            ImplTraitPosition::Other,
                         ),
                     );
 
@@ -719,7 +720,13 @@ impl LoweringContext<'_> {
                         |this, _| {
                             (
                                 // Disallow `impl Trait` in foreign items.
-                                this.lower_fn_decl(fdec, None, false, None),
+                                this.lower_fn_decl(
+                                    fdec,
+                                    None,
+                                    false,
+                                    None,
+                                    ImplTraitPosition::Other,
+                                ),
                                 this.lower_fn_params_to_names(fdec),
                             )
                         },
@@ -892,7 +899,7 @@ impl LoweringContext<'_> {
             id: hir::TraitItemId { hir_id: self.lower_node_id(i.id) },
             ident: i.ident,
             span: i.span,
-            defaultness: self.lower_defaultness(Defaultness::Default, has_default),
+            defaultness: hir::Defaultness::Default { has_value: has_default },
             kind,
         }
     }
@@ -1011,7 +1018,7 @@ impl LoweringContext<'_> {
 
     fn lower_defaultness(&self, d: Defaultness, has_value: bool) -> hir::Defaultness {
         match d {
-            Defaultness::Default => hir::Defaultness::Default {
+            Defaultness::Default(_) => hir::Defaultness::Default {
                 has_value: has_value,
             },
             Defaultness::Final => {
@@ -1276,6 +1283,7 @@ impl LoweringContext<'_> {
                 Some((fn_def_id, idty)),
                 impl_trait_return_allow,
                 is_async,
+                ImplTraitPosition::Other,
             ),
         );
         (generics, hir::MethodSig { header, decl })
@@ -2,9 +2,9 @@ use rustc_target::spec::abi::Abi;
 use syntax::ast;
 use syntax::source_map::{SourceMap, Spanned};
 use syntax::parse::ParseSess;
-use syntax::print::pp::{self, Breaks};
-use syntax::print::pp::Breaks::{Consistent, Inconsistent};
-use syntax::print::pprust::{self, Comments, PrintState};
+use syntax::print::pp::{self, BoxKind};
+use syntax::print::pp::BoxKind::{Consistent, Inconsistent};
+use syntax::print::pprust::{self, Comments, PrintConfig, PrintState};
 use syntax::symbol::kw;
 use syntax::util::parser::{self, AssocOp, Fixity};
 use syntax_pos::{self, BytePos, FileName};
@@ -91,6 +91,10 @@ impl<'a> PrintState<'a> for State<'a> {
         &mut self.comments
     }
 
+    fn config(&self) -> PrintConfig {
+        PrintConfig::default()
+    }
+
     fn print_ident(&mut self, ident: ast::Ident) {
         self.s.word(pprust::ast_ident_to_string(ident, ident.is_raw_guess()));
         self.ann.post(self, AnnNode::Name(&ident.name))
@@ -232,7 +236,7 @@ impl<'a> State<'a> {
     }
 
     pub fn commasep_cmnt<T, F, G>(&mut self,
-                                  b: Breaks,
+                                  b: BoxKind,
                                   elts: &[T],
                                   mut op: F,
                                   mut get_span: G)
@@ -255,7 +259,7 @@ impl<'a> State<'a> {
         self.end();
     }
 
-    pub fn commasep_exprs(&mut self, b: Breaks, exprs: &[hir::Expr]) {
+    pub fn commasep_exprs(&mut self, b: BoxKind, exprs: &[hir::Expr]) {
         self.commasep_cmnt(b, exprs, |s, e| s.print_expr(&e), |e| e.span)
     }
 
@@ -994,6 +994,26 @@ impl<'a, 'tcx> InferCtxt<'a, 'tcx> {
                         self.push_comma(&mut values.0, &mut values.1, len, i);
                     }
 
+                    // Const arguments come last, and are compared the same way.
+                    //     Foo<_, 3usize>
+                    //     Foo<_, N>
+                    let consts_start = regions_len + sub1.types().count();
+                    let const_arguments = sub1.consts().zip(sub2.consts());
+                    for (i, (ca1, ca2)) in const_arguments.enumerate() {
+                        let i = i + consts_start;
+                        if i >= len {
+                            break;
+                        }
+                        if ca1 == ca2 {
+                            values.0.push_normal("_");
+                            values.1.push_normal("_");
+                        } else {
+                            values.0.push_highlighted(ca1.to_string());
+                            values.1.push_highlighted(ca2.to_string());
+                        }
+                        self.push_comma(&mut values.0, &mut values.1, len, i);
+                    }
+
                     // Close the type argument bracket.
                     // Only draw `<...>` if there're lifetime/type arguments.
                     if len > 0 {
@@ -1087,6 +1107,61 @@ impl<'a, 'tcx> InferCtxt<'a, 'tcx> {
     }
 
     pub fn note_type_err(
+        &self,
+        diag: &mut DiagnosticBuilder<'tcx>,
+        cause: &ObligationCause<'tcx>,
+        secondary_span: Option<(Span, String)>,
+        values: Option<ValuePairs<'tcx>>,
+        terr: &TypeError<'tcx>,
+    ) {
+        let trimmed = match values {
+            Some(ValuePairs::Types(ref exp_found))
+                if self.tcx.sess.opts.debugging_opts.trim_diagnostic_paths =>
+            {
+                self.trimmable_paths(exp_found)
+            }
+            _ => vec![],
+        };
+        if trimmed.is_empty() {
+            return self.note_type_err_inner(diag, cause, secondary_span, values, terr);
+        }
+
+        let def_ids = trimmed.iter().map(|&(def_id, _)| def_id).collect();
+        ty::print::with_trimmed_paths(def_ids, || {
+            self.note_type_err_inner(diag, cause, secondary_span, values, terr)
+        });
+        for (def_id, path) in trimmed {
+            diag.note(&format!("`{}` is `{}`", self.tcx.item_name(def_id), path));
+        }
+    }
+
+    /// Returns the ADTs mentioned in `exp_found` that can be printed by name alone, because no
+    /// other definition mentioned there has the same name, along with their full paths.
+    fn trimmable_paths(
+        &self,
+        exp_found: &ty::error::ExpectedFound<Ty<'tcx>>,
+    ) -> Vec<(DefId, String)> {
+        let exp_found = self.resolve_vars_if_possible(exp_found);
+        let mut def_ids = vec![];
+        for ty in exp_found.expected.walk().chain(exp_found.found.walk()) {
+            if let ty::Adt(def, _) = ty.sty {
+                if !def_ids.contains(&def.did) {
+                    def_ids.push(def.did);
+                }
+            }
+        }
+
+        def_ids.iter().filter(|&&def_id| {
+            let name = self.tcx.item_name(def_id);
+            def_ids.iter().all(|&other| other == def_id || self.tcx.item_name(other) != name)
+        }).filter_map(|&def_id| {
+            let path = self.tcx.def_path_str(def_id);
+            // Paths that are just the name have nothing to trim.
+            if path.contains("::") { Some((def_id, path)) } else { None }
+        }).collect()
+    }
+
+    fn note_type_err_inner(
         &self,
         diag: &mut DiagnosticBuilder<'tcx>,
         cause: &ObligationCause<'tcx>,
@@ -1134,8 +1209,10 @@ impl<'a, 'tcx> InferCtxt<'a, 'tcx> {
             diag.span_label(sp, msg);
         }
 
-        if let Some((expected, found)) = expected_found {
-            match (terr, is_simple_error, expected == found) {
+        if let Some((mut expected, mut found)) = expected_found {
+            let is_same = expected == found;
+            let long_type_path = self.tcx.shorten_expected_found(&mut expected, &mut found);
+            match (terr, is_simple_error, is_same) {
                 (&TypeError::Sorts(ref values), false, true) => {
                     let sort_string = | a_type: Ty<'tcx> |
                         if let ty::Opaque(def_id, _) = a_type.sty {
@@ -1161,10 +1238,24 @@ impl<'a, 'tcx> InferCtxt<'a, 'tcx> {
                 }
                 _ => (),
             }
+            if let Some(path) = long_type_path {
+                diag.note(&format!(
+                    "the full type names have been written to `{}`",
+                    path.display(),
+                ));
+            }
         }
 
         self.check_and_note_conflicting_crates(diag, terr, span);
         self.tcx.note_and_explain_type_err(diag, terr, span);
+        let resolved_exp_found = exp_found.map(|exp_found| {
+            self.resolve_vars_if_possible(&exp_found)
+        });
+        self.tcx.note_regions_outlive(diag, terr, resolved_exp_found.as_ref());
+        if let Some(exp_found) = resolved_exp_found {
+            self.tcx.suggest_intrinsic_closure(diag, terr, &exp_found, span);
+            self.tcx.note_arg_count_mismatch(diag, terr, &exp_found);
+        }
 
         // It reads better to have the error origin as the final
         // thing.
@@ -32,7 +32,7 @@ impl NiceRegionError<'me, 'tcx> {
                 _,
                 SubregionOrigin::Subtype(box TypeTrace {
                     cause,
-                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found }),
+                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found, .. }),
                 }),
                 sub_placeholder @ ty::RePlaceholder(_),
                 _,
@@ -52,7 +52,7 @@ impl NiceRegionError<'me, 'tcx> {
                 _,
                 SubregionOrigin::Subtype(box TypeTrace {
                     cause,
-                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found }),
+                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found, .. }),
                 }),
                 sub_placeholder @ ty::RePlaceholder(_),
                 _,
@@ -72,7 +72,7 @@ impl NiceRegionError<'me, 'tcx> {
                 _,
                 SubregionOrigin::Subtype(box TypeTrace {
                     cause,
-                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found }),
+                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found, .. }),
                 }),
                 _,
                 _,
@@ -94,7 +94,7 @@ impl NiceRegionError<'me, 'tcx> {
                 _,
                 SubregionOrigin::Subtype(box TypeTrace {
                     cause,
-                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found }),
+                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found, .. }),
                 }),
                 sup_placeholder @ ty::RePlaceholder(_),
             )) if expected.def_id == found.def_id => Some(self.try_report_placeholders_trait(
@@ -110,7 +110,7 @@ impl NiceRegionError<'me, 'tcx> {
             Some(RegionResolutionError::ConcreteFailure(
                 SubregionOrigin::Subtype(box TypeTrace {
                     cause,
-                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found }),
+                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found, .. }),
                 }),
                 sub_region @ ty::RePlaceholder(_),
                 sup_region @ ty::RePlaceholder(_),
@@ -127,7 +127,7 @@ impl NiceRegionError<'me, 'tcx> {
             Some(RegionResolutionError::ConcreteFailure(
                 SubregionOrigin::Subtype(box TypeTrace {
                     cause,
-                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found }),
+                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found, .. }),
                 }),
                 sub_region @ ty::RePlaceholder(_),
                 sup_region,
@@ -144,7 +144,7 @@ impl NiceRegionError<'me, 'tcx> {
             Some(RegionResolutionError::ConcreteFailure(
                 SubregionOrigin::Subtype(box TypeTrace {
                     cause,
-                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found }),
+                    values: ValuePairs::TraitRefs(ExpectedFound { expected, found, .. }),
                 }),
                 sub_region,
                 sup_region @ ty::RePlaceholder(_),
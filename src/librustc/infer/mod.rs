@@ -548,16 +548,12 @@ impl<'tcx> InferCtxtBuilder<'tcx> {
 
 impl<T> ExpectedFound<T> {
     pub fn new(a_is_expected: bool, a: T, b: T) -> Self {
-        if a_is_expected {
-            ExpectedFound {
-                expected: a,
-                found: b,
-            }
-        } else {
-            ExpectedFound {
-                expected: b,
-                found: a,
-            }
+        let (expected, found) = if a_is_expected { (a, b) } else { (b, a) };
+        ExpectedFound {
+            expected,
+            found,
+            expected_span: None,
+            found_span: None,
         }
     }
 }
@@ -1681,6 +1677,8 @@ impl<'tcx> TypeTrace<'tcx> {
             values: Types(ExpectedFound {
                 expected: tcx.types.err,
                 found: tcx.types.err,
+                expected_span: None,
+                found_span: None,
             }),
         }
     }
@@ -9,6 +9,7 @@ use crate::middle::stability;
 use crate::session::Session;
 use errors::{Applicability, DiagnosticBuilder, pluralise};
 use syntax::ast;
+use syntax::early_buffered_lints::BufferedEarlyLintDiagnostics;
 use syntax::source_map::Span;
 use syntax::symbol::Symbol;
 
@@ -368,6 +369,30 @@ pub mod parser {
         Allow,
         "possible meta-variable misuse at macro definition"
     }
+
+    declare_lint! {
+        pub CONTRADICTORY_CFG_PREDICATES,
+        Warn,
+        "`cfg` predicates that can never be satisfied"
+    }
+
+    declare_lint! {
+        pub DUPLICATE_CFG_PREDICATES,
+        Warn,
+        "`any` and `all` `cfg` predicates with duplicate clauses"
+    }
+
+    declare_lint! {
+        pub MISUSED_MACRO_SEPARATORS,
+        Deny,
+        "separators of `macro_rules!` repetitions that are likely mistakes"
+    }
+
+    declare_lint! {
+        pub MALFORMED_SUPPRESSION_MARKERS,
+        Warn,
+        "`// rustc:` comments that aren't well-formed diagnostic suppression markers"
+    }
 }
 
 declare_lint! {
@@ -461,6 +486,10 @@ declare_lint_pass! {
         MACRO_EXPANDED_MACRO_EXPORTS_ACCESSED_BY_ABSOLUTE_PATHS,
         parser::ILL_FORMED_ATTRIBUTE_INPUT,
         parser::META_VARIABLE_MISUSE,
+        parser::CONTRADICTORY_CFG_PREDICATES,
+        parser::DUPLICATE_CFG_PREDICATES,
+        parser::MISUSED_MACRO_SEPARATORS,
+        parser::MALFORMED_SUPPRESSION_MARKERS,
         DEPRECATED_IN_FUTURE,
         AMBIGUOUS_ASSOCIATED_ITEMS,
         NESTED_IMPL_TRAIT,
@@ -486,6 +515,9 @@ pub enum BuiltinLintDiagnostics {
     NestedImplTrait { outer_impl_trait_span: Span, inner_impl_trait_span: Span },
     RedundantImport(Vec<(Span, bool)>, ast::Ident),
     DeprecatedMacro(Option<Symbol>, Span),
+    CfgPredicate(Vec<(Span, String)>, Option<(Span, String)>),
+    MacroSeparator(Vec<(Span, String)>, Vec<String>, Option<(Span, String, String)>),
+    MalformedSuppressionMarker,
 }
 
 pub(crate) fn add_elided_lifetime_in_path_suggestion(
@@ -531,6 +563,23 @@ pub(crate) fn add_elided_lifetime_in_path_suggestion(
 }
 
 impl BuiltinLintDiagnostics {
+    /// Returns the `BuiltinLintDiagnostics` for a
+    /// `syntax::early_buffered_lints::BufferedEarlyLintDiagnostics`.
+    pub fn from_parser_lint_diagnostics(diagnostics: BufferedEarlyLintDiagnostics) -> Self {
+        match diagnostics {
+            BufferedEarlyLintDiagnostics::Normal => BuiltinLintDiagnostics::Normal,
+            BufferedEarlyLintDiagnostics::CfgPredicate(labels, suggestion) => {
+                BuiltinLintDiagnostics::CfgPredicate(labels, suggestion)
+            }
+            BufferedEarlyLintDiagnostics::MacroSeparator(labels, notes, suggestion) => {
+                BuiltinLintDiagnostics::MacroSeparator(labels, notes, suggestion)
+            }
+            BufferedEarlyLintDiagnostics::MalformedSuppressionMarker => {
+                BuiltinLintDiagnostics::MalformedSuppressionMarker
+            }
+        }
+    }
+
     pub fn run(self, sess: &Session, db: &mut DiagnosticBuilder<'_>) {
         match self {
             BuiltinLintDiagnostics::Normal => (),
@@ -613,6 +662,34 @@ impl BuiltinLintDiagnostics {
             }
             BuiltinLintDiagnostics::DeprecatedMacro(suggestion, span) =>
                 stability::deprecation_suggestion(db, suggestion, span),
+            BuiltinLintDiagnostics::CfgPredicate(labels, suggestion) => {
+                for (span, label) in labels {
+                    db.span_label(span, label);
+                }
+                if let Some((span, suggestion)) = suggestion {
+                    db.span_suggestion(
+                        span,
+                        "simplify the predicate",
+                        suggestion,
+                        Applicability::MachineApplicable,
+                    );
+                }
+            }
+            BuiltinLintDiagnostics::MacroSeparator(labels, notes, suggestion) => {
+                for (span, label) in labels {
+                    db.span_label(span, label);
+                }
+                for note in notes {
+                    db.note(&note);
+                }
+                if let Some((span, msg, suggestion)) = suggestion {
+                    db.span_suggestion(span, &msg, suggestion, Applicability::MaybeIncorrect);
+                }
+            }
+            BuiltinLintDiagnostics::MalformedSuppressionMarker => {
+                db.help("markers look like `// rustc: allow(E0308)`, optionally followed by \
+                         `next-line`");
+            }
         }
     }
 }
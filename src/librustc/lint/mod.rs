@@ -28,6 +28,8 @@ use crate::hir::intravisit;
 use crate::hir;
 use crate::lint::builtin::BuiltinLintDiagnostics;
 use crate::lint::builtin::parser::{ILL_FORMED_ATTRIBUTE_INPUT, META_VARIABLE_MISUSE};
+use crate::lint::builtin::parser::{CONTRADICTORY_CFG_PREDICATES, DUPLICATE_CFG_PREDICATES};
+use crate::lint::builtin::parser::{MISUSED_MACRO_SEPARATORS, MALFORMED_SUPPRESSION_MARKERS};
 use crate::session::{Session, DiagnosticMessageId};
 use crate::ty::TyCtxt;
 use crate::ty::query::Providers;
@@ -83,6 +85,10 @@ impl Lint {
         match lint_id {
             BufferedEarlyLintId::IllFormedAttributeInput => ILL_FORMED_ATTRIBUTE_INPUT,
             BufferedEarlyLintId::MetaVariableMisuse => META_VARIABLE_MISUSE,
+            BufferedEarlyLintId::ContradictoryCfgPredicates => CONTRADICTORY_CFG_PREDICATES,
+            BufferedEarlyLintId::DuplicateCfgPredicates => DUPLICATE_CFG_PREDICATES,
+            BufferedEarlyLintId::MisusedMacroSeparators => MISUSED_MACRO_SEPARATORS,
+            BufferedEarlyLintId::MalformedSuppressionMarkers => MALFORMED_SUPPRESSION_MARKERS,
         }
     }
 
@@ -717,7 +723,12 @@ pub fn struct_lint_level<'a>(sess: &'a Session,
         }
     }
 
-    err.code(DiagnosticId::Lint(name));
+    // Tool lints, e.g. `clippy::needless_return`, are namespaced by their tool.
+    let code = match name.find("::") {
+        Some(i) => DiagnosticId::tool(&name[..i], &name[i + 2..]),
+        None => DiagnosticId::Lint(name),
+    };
+    err.code(code);
 
     if let Some(future_incompatible) = future_incompatible {
         const STANDARD_MESSAGE: &str =
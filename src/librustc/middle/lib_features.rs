@@ -7,8 +7,9 @@
 use crate::ty::TyCtxt;
 use crate::hir::intravisit::{self, NestedVisitorMap, Visitor};
 use syntax::symbol::Symbol;
-use syntax::ast::{Attribute, MetaItem, MetaItemKind};
-use syntax_pos::{Span, sym};
+use syntax::attr::{self, StabilityAttr, StabilityLevel};
+use syntax::ast::Attribute;
+use syntax_pos::Span;
 use rustc_data_structures::fx::{FxHashSet, FxHashMap};
 use rustc_macros::HashStable;
 use errors::DiagnosticId;
@@ -51,43 +52,18 @@ impl LibFeatureCollector<'tcx> {
     }
 
     fn extract(&self, attr: &Attribute) -> Option<(Symbol, Option<Symbol>, Span)> {
-        let stab_attrs = [sym::stable, sym::unstable, sym::rustc_const_unstable];
-
         // Find a stability attribute (i.e., `#[stable (..)]`, `#[unstable (..)]`,
-        // `#[rustc_const_unstable (..)]`).
-        if let Some(stab_attr) = stab_attrs.iter().find(|stab_attr| {
-            attr.check_name(**stab_attr)
-        }) {
-            let meta_item = attr.meta();
-            if let Some(MetaItem { node: MetaItemKind::List(ref metas), .. }) = meta_item {
-                let mut feature = None;
-                let mut since = None;
-                for meta in metas {
-                    if let Some(mi) = meta.meta_item() {
-                        // Find the `feature = ".."` meta-item.
-                        match (mi.name_or_empty(), mi.value_str()) {
-                            (sym::feature, val) => feature = val,
-                            (sym::since, val) => since = val,
-                            _ => {}
-                        }
-                    }
-                }
-                if let Some(feature) = feature {
-                    // This additional check for stability is to make sure we
-                    // don't emit additional, irrelevant errors for malformed
-                    // attributes.
-                    if *stab_attr != sym::stable || since.is_some() {
-                        return Some((feature, since, attr.span));
-                    }
-                }
-                // We need to iterate over the other attributes, because
-                // `rustc_const_unstable` is not mutually exclusive with
-                // the other stability attributes, so we can't just `break`
-                // here.
+        // `#[rustc_const_unstable (..)]`). Malformed ones are reported when the
+        // stability index is built, so they're skipped silently here to make sure
+        // we don't emit additional, irrelevant errors.
+        match attr::parse_stability_attr(None, attr)? {
+            StabilityAttr::Level { feature, level: StabilityLevel::Stable { since } } => {
+                Some((feature, Some(since), attr.span))
             }
+            StabilityAttr::Level { feature, level: StabilityLevel::Unstable { .. } } |
+            StabilityAttr::ConstUnstable { feature } => Some((feature, None, attr.span)),
+            _ => None,
         }
-
-        None
     }
 
     fn collect_feature(&mut self, feature: Symbol, since: Option<Symbol>, span: Span) {
@@ -17,7 +17,7 @@ use syntax::symbol::{Symbol, sym};
 use syntax_pos::{Span, MultiSpan};
 use syntax::ast::{Attribute, CRATE_NODE_ID};
 use syntax::errors::Applicability;
-use syntax::feature_gate::{GateIssue, emit_feature_err};
+use syntax::feature_gate::{GateIssue, emit_feature_err, record_detected_feature};
 use syntax::attr::{self, Stability, Deprecation, RustcDeprecation};
 use crate::ty::{self, TyCtxt};
 use crate::util::nodemap::{FxHashSet, FxHashMap};
@@ -507,6 +507,7 @@ pub fn report_unstable(
     let fresh = sess.one_time_diagnostics.borrow_mut().insert(error_id);
     if fresh {
         if is_soft {
+            record_detected_feature(&sess.parse_sess, feature, span);
             sess.buffer_lint(lint::builtin::SOFT_UNSTABLE, CRATE_NODE_ID, span, &msg);
         } else {
             emit_feature_err(
@@ -776,7 +777,17 @@ impl<'tcx> TyCtxt<'tcx> {
     /// not `None`, a deprecated lint attached to `id` will be emitted.
     pub fn check_stability(self, def_id: DefId, id: Option<HirId>, span: Span) {
         match self.eval_stability(def_id, id, span) {
-            EvalResult::Allow => {}
+            EvalResult::Allow => {
+                // The uses of enabled library features are recorded like the ones of language
+                // features, the uses of features that aren't enabled are when reported.
+                if let Some(&Stability { level: attr::Unstable { .. }, feature, .. }) =
+                    self.lookup_stability(def_id)
+                {
+                    if self.stability().active_features.contains(&feature) {
+                        record_detected_feature(&self.sess.parse_sess, feature, span);
+                    }
+                }
+            }
             EvalResult::Deny { feature, reason, issue, is_soft } =>
                 report_unstable(self.sess, feature, reason, issue, is_soft, span),
             EvalResult::Unmarked => {
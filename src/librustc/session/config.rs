@@ -444,6 +444,23 @@ top_level_options!(
         // `true` if we're emitting JSON blobs about each artifact produced
         // by the compiler.
         json_artifact_notifications: bool [TRACKED],
+
+        // `true` if we're emitting a JSON blob summarizing the diagnostics
+        // once the session ends.
+        json_session_summary: bool [UNTRACKED],
+
+        // `true` if JSON diagnostics leave out their rendering and children.
+        json_compact: bool [UNTRACKED],
+
+        // `true` if diagnostics are emitted as LSP `textDocument/publishDiagnostics` parameters.
+        json_lsp: bool [UNTRACKED],
+
+        // `true` if the spans of JSON diagnostics also have their zero-based columns in bytes.
+        json_byte_columns: bool [UNTRACKED],
+
+        // `true` if the spans of JSON diagnostics also have their zero-based columns in UTF-16
+        // code units.
+        json_utf16_columns: bool [UNTRACKED],
     }
 );
 
@@ -633,6 +650,11 @@ impl Default for Options {
             remap_path_prefix: Vec::new(),
             edition: DEFAULT_EDITION,
             json_artifact_notifications: false,
+            json_session_summary: false,
+            json_compact: false,
+            json_lsp: false,
+            json_byte_columns: false,
+            json_utf16_columns: false,
         }
     }
 }
@@ -1281,6 +1303,8 @@ options! {DebuggingOptions, DebuggingSetter, basic_debugging_options,
          Use with RUST_REGION_GRAPH=help for more info"),
     parse_only: bool = (false, parse_bool, [UNTRACKED],
         "parse only; do not compile, assemble, or link"),
+    parse_expr_depth_limit: Option<usize> = (None, parse_opt_uint, [UNTRACKED],
+        "set how deeply expressions may be nested before parsing fails"),
     dual_proc_macros: bool = (false, parse_bool, [TRACKED],
         "load proc macros for both target and host, but only link to the target"),
     no_codegen: bool = (false, parse_bool, [TRACKED],
@@ -1295,6 +1319,12 @@ options! {DebuggingOptions, DebuggingSetter, basic_debugging_options,
         "show extended diagnostic help"),
     terminal_width: Option<usize> = (None, parse_opt_uint, [UNTRACKED],
         "set the current terminal width"),
+    long_type_threshold: Option<usize> = (None, parse_opt_uint, [UNTRACKED],
+        "shorten types longer than `val` characters in type mismatch errors, and write the \
+         full types to a file"),
+    trim_diagnostic_paths: bool = (false, parse_bool, [UNTRACKED],
+        "print types in type mismatch errors by name alone where that is unambiguous, \
+         with a note giving their full paths"),
     continue_parse_after_error: bool = (false, parse_bool, [TRACKED],
         "attempt to recover from parse errors (experimental)"),
     dep_tasks: bool = (false, parse_bool, [UNTRACKED],
@@ -1331,6 +1361,8 @@ options! {DebuggingOptions, DebuggingSetter, basic_debugging_options,
         "force overflow checks on or off"),
     trace_macros: bool = (false, parse_bool, [UNTRACKED],
         "for every macro invocation, print its name and arguments"),
+    dump_expansion_graph: bool = (false, parse_bool, [UNTRACKED],
+        "print the graph of macro expansions in the crate in graphviz format"),
     debug_macros: bool = (false, parse_bool, [TRACKED],
         "emit line numbers debug info inside macros"),
     keep_hygiene_data: bool = (false, parse_bool, [UNTRACKED],
@@ -1401,6 +1433,8 @@ options! {DebuggingOptions, DebuggingSetter, basic_debugging_options,
         "in match codegen, do not include FakeRead statements (used by mir-borrowck)"),
     dont_buffer_diagnostics: bool = (false, parse_bool, [UNTRACKED],
         "emit diagnostics rather than buffering (breaks NLL error downgrading, sorting)."),
+    audit_suggestions: bool = (false, parse_bool, [UNTRACKED],
+        "record where suggestions are built and report those of unspecified applicability"),
     polonius: bool = (false, parse_bool, [UNTRACKED],
         "enable polonius-based borrow-checker"),
     codegen_time_graph: bool = (false, parse_bool, [UNTRACKED],
@@ -1435,6 +1469,10 @@ options! {DebuggingOptions, DebuggingSetter, basic_debugging_options,
         "run `dsymutil` and delete intermediate object files"),
     ui_testing: bool = (false, parse_bool, [UNTRACKED],
         "format compiler diagnostics in a way that's better suitable for UI testing"),
+    item_headers: bool = (false, parse_bool, [UNTRACKED],
+        "show the item a diagnostic points into, like `in fn main`, after its location"),
+    collapse_notes: bool = (false, parse_bool, [UNTRACKED],
+        "collapse the notes of low importance of a diagnostic into a single line"),
     embed_bitcode: bool = (false, parse_bool, [TRACKED],
         "embed LLVM bitcode in object files"),
     strip_debuginfo_if_disabled: Option<bool> = (None, parse_opt_bool, [TRACKED],
@@ -1949,13 +1987,23 @@ pub fn parse_color(matches: &getopts::Matches) -> ColorConfig {
 
 /// Parse the `--json` flag.
 ///
-/// The first value returned is how to render JSON diagnostics, and the second
-/// is whether or not artifact notifications are enabled.
-pub fn parse_json(matches: &getopts::Matches) -> (HumanReadableErrorType, bool) {
+/// The first value returned is how to render JSON diagnostics, the second
+/// is whether or not artifact notifications are enabled, the third is
+/// whether or not the session summary is enabled, the fourth is whether
+/// or not diagnostics are compact, and the fifth is whether or not they are
+/// emitted in the format of the Language Server Protocol.
+pub fn parse_json(
+    matches: &getopts::Matches,
+) -> (HumanReadableErrorType, bool, bool, bool, bool, bool, bool) {
     let mut json_rendered: fn(ColorConfig) -> HumanReadableErrorType =
         HumanReadableErrorType::Default;
     let mut json_color = ColorConfig::Never;
     let mut json_artifact_notifications = false;
+    let mut json_session_summary = false;
+    let mut json_compact = false;
+    let mut json_lsp = false;
+    let mut json_byte_columns = false;
+    let mut json_utf16_columns = false;
     for option in matches.opt_strs("json") {
         // For now conservatively forbid `--color` with `--json` since `--json`
         // won't actually be emitting any colors and anything colorized is
@@ -1972,6 +2020,11 @@ pub fn parse_json(matches: &getopts::Matches) -> (HumanReadableErrorType, bool)
                 "diagnostic-short" => json_rendered = HumanReadableErrorType::Short,
                 "diagnostic-rendered-ansi" => json_color = ColorConfig::Always,
                 "artifacts" => json_artifact_notifications = true,
+                "summary" => json_session_summary = true,
+                "compact" => json_compact = true,
+                "lsp" => json_lsp = true,
+                "byte-columns" => json_byte_columns = true,
+                "utf16-columns" => json_utf16_columns = true,
                 s => {
                     early_error(
                         ErrorOutputType::default(),
@@ -1981,7 +2034,15 @@ pub fn parse_json(matches: &getopts::Matches) -> (HumanReadableErrorType, bool)
             }
         }
     }
-    (json_rendered(json_color), json_artifact_notifications)
+    (
+        json_rendered(json_color),
+        json_artifact_notifications,
+        json_session_summary,
+        json_compact,
+        json_lsp,
+        json_byte_columns,
+        json_utf16_columns,
+    )
 }
 
 /// Parses the `--error-format` flag.
@@ -2004,6 +2065,9 @@ pub fn parse_error_format(
             Some("json") => ErrorOutputType::Json { pretty: false, json_rendered },
             Some("pretty-json") => ErrorOutputType::Json { pretty: true, json_rendered },
             Some("short") => ErrorOutputType::HumanReadable(HumanReadableErrorType::Short(color)),
+            Some("short-fixes") => {
+                ErrorOutputType::HumanReadable(HumanReadableErrorType::ShortWithFixes(color))
+            },
 
             Some(arg) => early_error(
                 ErrorOutputType::HumanReadable(HumanReadableErrorType::Default(color)),
@@ -2068,7 +2132,15 @@ pub fn build_session_options_and_crate_config(
         )
     }
 
-    let (json_rendered, json_artifact_notifications) = parse_json(matches);
+    let (
+        json_rendered,
+        json_artifact_notifications,
+        json_session_summary,
+        json_compact,
+        json_lsp,
+        json_byte_columns,
+        json_utf16_columns,
+    ) = parse_json(matches);
 
     let error_format = parse_error_format(matches, color, json_rendered);
 
@@ -2095,6 +2167,13 @@ pub fn build_session_options_and_crate_config(
                 "`--error-format=human-annotate-rs` is unstable",
             );
         }
+        if let ErrorOutputType::HumanReadable(HumanReadableErrorType::ShortWithFixes(color)) =
+            error_format {
+            early_error(
+                ErrorOutputType::HumanReadable(HumanReadableErrorType::Short(color)),
+                "`--error-format=short-fixes` is unstable",
+            );
+        }
     }
 
     let mut output_types = BTreeMap::new();
@@ -2507,6 +2586,11 @@ pub fn build_session_options_and_crate_config(
             remap_path_prefix,
             edition,
             json_artifact_notifications,
+            json_session_summary,
+            json_compact,
+            json_lsp,
+            json_byte_columns,
+            json_utf16_columns,
         },
         cfg,
     )
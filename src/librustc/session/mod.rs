@@ -1066,7 +1066,10 @@ fn default_emitter(
                         external_macro_backtrace,
                     ),
                 };
-                Box::new(emitter.ui_testing(sopts.debugging_opts.ui_testing))
+                Box::new(emitter.ui_testing(sopts.debugging_opts.ui_testing)
+                    .mark_fixes(kind.marks_fixes())
+                    .item_headers(sopts.debugging_opts.item_headers)
+                    .collapse_notes(sopts.debugging_opts.collapse_notes))
             }
         },
         (config::ErrorOutputType::Json { pretty, json_rendered }, None) => Box::new(
@@ -1076,7 +1079,12 @@ fn default_emitter(
                 pretty,
                 json_rendered,
                 external_macro_backtrace,
-            ).ui_testing(sopts.debugging_opts.ui_testing),
+            ).ui_testing(sopts.debugging_opts.ui_testing)
+                .compact(sopts.json_compact)
+                .lsp(sopts.json_lsp)
+                .byte_columns(sopts.json_byte_columns)
+                .utf16_columns(sopts.json_utf16_columns)
+                .session_summary(sopts.json_session_summary),
         ),
         (config::ErrorOutputType::Json { pretty, json_rendered }, Some(dst)) => Box::new(
             JsonEmitter::new(
@@ -1086,7 +1094,12 @@ fn default_emitter(
                 pretty,
                 json_rendered,
                 external_macro_backtrace,
-            ).ui_testing(sopts.debugging_opts.ui_testing),
+            ).ui_testing(sopts.debugging_opts.ui_testing)
+                .compact(sopts.json_compact)
+                .lsp(sopts.json_lsp)
+                .byte_columns(sopts.json_byte_columns)
+                .utf16_columns(sopts.json_utf16_columns)
+                .session_summary(sopts.json_session_summary),
         ),
     }
 }
@@ -1121,6 +1134,7 @@ pub fn build_session_with_source_map(
     let treat_err_as_bug = sopts.debugging_opts.treat_err_as_bug;
     let dont_buffer_diagnostics = sopts.debugging_opts.dont_buffer_diagnostics;
     let report_delayed_bugs = sopts.debugging_opts.report_delayed_bugs;
+    let audit_suggestions = sopts.debugging_opts.audit_suggestions;
 
     let external_macro_backtrace = sopts.debugging_opts.external_macro_backtrace;
 
@@ -1139,6 +1153,7 @@ pub fn build_session_with_source_map(
             report_delayed_bugs,
             dont_buffer_diagnostics,
             external_macro_backtrace,
+            audit_suggestions,
             ..Default::default()
         },
     );
@@ -1187,10 +1202,13 @@ fn build_session_(
     );
     let target_cfg = config::build_target_config(&sopts, &span_diagnostic);
 
-    let parse_sess = parse::ParseSess::with_span_handler(
+    let mut parse_sess = parse::ParseSess::with_span_handler(
         span_diagnostic,
         source_map,
     );
+    if let Some(limit) = sopts.debugging_opts.parse_expr_depth_limit {
+        parse_sess.expr_depth_limit = limit;
+    }
     let sysroot = match &sopts.maybe_sysroot {
         Some(sysroot) => sysroot.clone(),
         None => filesearch::get_or_default_sysroot(),
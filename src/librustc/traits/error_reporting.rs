@@ -232,6 +232,8 @@ impl<'a, 'tcx> InferCtxt<'a, 'tcx> {
                     values = Some(infer::ValuePairs::Types(ExpectedFound {
                         expected: normalized_ty,
                         found: data.ty,
+                        expected_span: None,
+                        found_span: None,
                     }));
                     err_buf = error;
                     err = &err_buf;
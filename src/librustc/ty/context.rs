@@ -1710,7 +1710,7 @@ pub mod tls {
     use std::mem;
     use syntax_pos;
     use crate::ty::query;
-    use errors::{Diagnostic, TRACK_DIAGNOSTICS};
+    use errors::{subscribers, Diagnostic};
     use rustc_data_structures::OnDrop;
     use rustc_data_structures::sync::{self, Lrc, Lock};
     use rustc_data_structures::thin_vec::ThinVec;
@@ -1828,16 +1828,7 @@ pub mod tls {
                 span_dbg.set(original_span_debug);
             });
 
-            TRACK_DIAGNOSTICS.with(|current| {
-                let original = current.get();
-                current.set(track_diagnostic);
-
-                let _on_drop = OnDrop(move || {
-                    current.set(original);
-                });
-
-                f()
-            })
+            subscribers::with_subscriber(track_diagnostic, f)
         })
     }
 
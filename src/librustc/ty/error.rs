@@ -1,19 +1,33 @@
-use crate::hir::def_id::DefId;
-use crate::ty::{self, BoundRegion, Region, Ty, TyCtxt};
+use crate::hir::def::DefKind;
+use crate::hir::def_id::{DefId, LOCAL_CRATE};
+use crate::mir::interpret::ConstValue;
+use crate::ty::relate::{self, Relate, RelateResult, TypeRelation};
+use crate::ty::{self, BoundRegion, DefIdTree, Region, Ty, TyCtxt, TypeFoldable};
 use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
 use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
 use rustc_target::spec::abi;
 use syntax::ast;
 use syntax::errors::pluralise;
-use errors::{Applicability, DiagnosticBuilder};
+use syntax::symbol::kw;
+use errors::{Applicability, DiagnosticBuilder, DiagnosticStyledString};
+use errors::markup::Markup;
 use syntax_pos::Span;
 
-use crate::hir;
+use crate::hir::{self, Node};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct ExpectedFound<T> {
     pub expected: T,
     pub found: T,
+    /// The span of whatever established the expected value, e.g., the type annotation of the
+    /// `let` whose initializer has the found type, if it's worth pointing out.
+    pub expected_span: Option<Span>,
+    /// Like `expected_span`, for the found value.
+    pub found_span: Option<Span>,
 }
 
 // Data structures used in type unification
@@ -25,6 +39,8 @@ pub enum TypeError<'tcx> {
     Mutability,
     TupleSize(ExpectedFound<usize>),
     FixedArraySize(ExpectedFound<u64>),
+    /// The lengths of two array types differ, and at least one of them isn't known.
+    ArraySizeMismatch(ExpectedFound<&'tcx ty::Const<'tcx>>),
     ArgCount,
 
     RegionsDoesNotOutlive(Region<'tcx>, Region<'tcx>),
@@ -173,9 +189,16 @@ impl<'tcx> fmt::Display for TypeError<'tcx> {
                 report_maybe_different(f, &format!("trait `{}`", values.expected),
                                        &format!("trait `{}`", values.found))
             }
-            ConstMismatch(ref values) => {
-                write!(f, "expected `{}`, found `{}`", values.expected, values.found)
-            }
+            ArraySizeMismatch(ref values) => ty::tls::with(|tcx| {
+                write!(f, "expected an array with a size of {}, found one with a size of {}",
+                       values.expected.sort_string(tcx),
+                       values.found.sort_string(tcx))
+            }),
+            ConstMismatch(ref values) => ty::tls::with(|tcx| {
+                report_maybe_different(f,
+                                       &values.expected.sort_string(tcx),
+                                       &values.found.sort_string(tcx))
+            }),
             IntrinsicCast => {
                 write!(f, "cannot coerce intrinsics to function pointers")
             }
@@ -183,6 +206,21 @@ impl<'tcx> fmt::Display for TypeError<'tcx> {
     }
 }
 
+impl<'tcx> TypeError<'tcx> {
+    /// Records where the expected and found types of a `Sorts` error come from, so that
+    /// `note_and_explain_type_err` can point at them. Other errors are returned unchanged.
+    pub fn with_spans(self, expected_span: Option<Span>, found_span: Option<Span>) -> Self {
+        match self {
+            TypeError::Sorts(values) => TypeError::Sorts(ExpectedFound {
+                expected_span: expected_span.or(values.expected_span),
+                found_span: found_span.or(values.found_span),
+                ..values
+            }),
+            err => err,
+        }
+    }
+}
+
 impl<'tcx> ty::TyS<'tcx> {
     pub fn sort_string(&self, tcx: TyCtxt<'_>) -> Cow<'static, str> {
         match self.sty {
@@ -248,6 +286,36 @@ impl<'tcx> ty::TyS<'tcx> {
     }
 }
 
+impl<'tcx> ty::Const<'tcx> {
+    pub fn sort_string(&'tcx self, tcx: TyCtxt<'_>) -> Cow<'static, str> {
+        match self.val {
+            ConstValue::Param(p) => format!("const parameter `{}`", p.name).into(),
+            ConstValue::Infer(_) => "inferred constant".into(),
+            ConstValue::Placeholder(_) => "placeholder constant".into(),
+            ConstValue::Unevaluated(def_id, _) => match tcx.def_kind(def_id) {
+                Some(DefKind::Const) | Some(DefKind::AssocConst) => {
+                    format!("constant `{}`", tcx.def_path_str(def_id)).into()
+                }
+                _ => {
+                    let snippet = if def_id.is_local() {
+                        tcx.sess.source_map().span_to_snippet(tcx.def_span(def_id)).ok()
+                    } else {
+                        None
+                    };
+                    match (snippet, tcx.parent(def_id)) {
+                        (Some(snippet), _) => format!("constant expression `{}`", snippet).into(),
+                        (None, Some(parent)) => {
+                            format!("constant expression in `{}`", tcx.def_path_str(parent)).into()
+                        }
+                        (None, None) => "constant expression".into(),
+                    }
+                }
+            },
+            _ => format!("`{}`", self).into(),
+        }
+    }
+}
+
 impl<'tcx> TyCtxt<'tcx> {
     pub fn note_and_explain_type_err(
         self,
@@ -259,6 +327,12 @@ impl<'tcx> TyCtxt<'tcx> {
 
         match err {
             Sorts(values) => {
+                if let Some(expected_span) = values.expected_span.filter(|&s| s != sp) {
+                    db.span_label(expected_span, "expected due to this");
+                }
+                if let Some(found_span) = values.found_span.filter(|&s| s != sp) {
+                    db.span_label(found_span, "found due to this");
+                }
                 let expected_str = values.expected.sort_string(self);
                 let found_str = values.found.sort_string(self);
                 if expected_str == found_str && expected_str == "closure" {
@@ -288,6 +362,22 @@ impl<'tcx> TyCtxt<'tcx> {
                             );
                         }
                     },
+                    (ty::FnDef(..), ty::FnDef(..)) => {
+                        let expected_sig = self.anonymize_late_bound_regions(
+                            &values.expected.fn_sig(self),
+                        );
+                        let found_sig = self.anonymize_late_bound_regions(
+                            &values.found.fn_sig(self),
+                        );
+                        if expected_sig == found_sig {
+                            db.note("different fn items have unique types, even if their \
+                                     signatures are the same");
+                            db.help(&format!(
+                                "consider casting both fn items to fn pointers using `as {}`",
+                                self.mk_fn_ptr(expected_sig),
+                            ));
+                        }
+                    }
                     (ty::Param(_), ty::Param(_)) => {
                         db.note("a type parameter was expected, but a different one was found; \
                                  you might be missing a type parameter or trait bound");
@@ -304,29 +394,26 @@ impl<'tcx> TyCtxt<'tcx> {
                     (ty::Param(_), _) | (_, ty::Param(_)) => {
                         db.help("type parameters must be constrained to match other types");
                         if self.sess.teach(&db.get_code().unwrap()) {
-                            db.help("given a type parameter `T` and a method `foo`:
-```
-trait Trait<T> { fn foo(&self) -> T; }
-```
-the only ways to implement method `foo` are:
-- constrain `T` with an explicit type:
-```
+                            db.help_markup(Markup::new()
+                                .text("given a type parameter `T` and a method `foo`:")
+                                .code_block("trait Trait<T> { fn foo(&self) -> T; }")
+                                .text("the only ways to implement method `foo` are:")
+                                .item("constrain `T` with an explicit type:")
+                                .code_block("\
 impl Trait<String> for X {
     fn foo(&self) -> String { String::new() }
-}
-```
-- add a trait bound to `T` and call a method on that trait that returns `Self`:
-```
+}")
+                                .item("add a trait bound to `T` and call a method on that trait \
+                                       that returns `Self`:")
+                                .code_block("\
 impl<T: std::default::Default> Trait<T> for X {
     fn foo(&self) -> T { <T as std::default::Default>::default() }
-}
-```
-- change `foo` to return an argument of type `T`:
-```
+}")
+                                .item("change `foo` to return an argument of type `T`:")
+                                .code_block("\
 impl<T> Trait<T> for X {
     fn foo(&self, x: T) -> T { x }
-}
-```");
+}"));
                         }
                         db.note("for more information, visit \
                                  https://doc.rust-lang.org/book/ch10-02-traits.html\
@@ -341,20 +428,20 @@ impl<T> Trait<T> for X {
                             values.expected,
                         ));
                         if self.sess.teach(&db.get_code().unwrap()) {
-                            db.help("given an associated type `T` and a method `foo`:
-```
+                            db.help_markup(Markup::new()
+                                .text("given an associated type `T` and a method `foo`:")
+                                .code_block("\
 trait Trait {
     type T;
     fn foo(&self) -> Self::T;
-}
-```
-the only way of implementing method `foo` is to constrain `T` with an explicit associated type:
-```
+}")
+                                .text("the only way of implementing method `foo` is to \
+                                       constrain `T` with an explicit associated type:")
+                                .code_block("\
 impl Trait for X {
     type T = String;
     fn foo(&self) -> Self::T { String::new() }
-}
-```");
+}"));
                         }
                         db.note("for more information, visit \
                                  https://doc.rust-lang.org/book/ch19-03-advanced-traits.html");
@@ -378,6 +465,9 @@ impl Trait for X {
                     values.found.sty,
                 );
             },
+            ArraySizeMismatch(values) | ConstMismatch(values) => {
+                self.note_const_mismatch(db, values);
+            }
             CyclicTy(ty) => {
                 // Watch out for various cases of cyclic types and try to explain.
                 if ty.is_closure() || ty.is_generator() {
@@ -389,4 +479,391 @@ impl Trait for X {
             _ => {}
         }
     }
+
+    /// Suggests wrapping an intrinsic in a closure when it couldn't be coerced to the expected fn
+    /// pointer type.
+    pub fn suggest_intrinsic_closure(
+        self,
+        db: &mut DiagnosticBuilder<'_>,
+        err: &TypeError<'tcx>,
+        exp_found: &ExpectedFound<Ty<'tcx>>,
+        sp: Span,
+    ) {
+        let expected_sig = match (err, &exp_found.expected.sty, &exp_found.found.sty) {
+            (TypeError::IntrinsicCast, ty::FnPtr(sig), ty::FnDef(..)) => sig,
+            _ => return,
+        };
+        // Closures only coerce to fn pointers with the Rust ABI.
+        if expected_sig.abi() != abi::Abi::Rust {
+            return;
+        }
+        let found_sig = exp_found.found.fn_sig(self);
+        let snippet = match self.sess.source_map().span_to_snippet(sp) {
+            Ok(snippet) => snippet,
+            Err(_) => return,
+        };
+        let args = (b'a'..=b'z')
+            .take(found_sig.inputs().skip_binder().len())
+            .map(|c| (c as char).to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let call = format!("{}({})", snippet, args);
+        let body = match found_sig.unsafety() {
+            hir::Unsafety::Unsafe => format!("unsafe {{ {} }}", call),
+            hir::Unsafety::Normal => call,
+        };
+        db.span_suggestion(
+            sp,
+            "wrap the intrinsic in a closure, which can be coerced to a function pointer",
+            format!("|{}| {}", args, body),
+            Applicability::MaybeIncorrect,
+        );
+    }
+
+    /// Lines up the parameters of two function types that don't take the same number of them,
+    /// and points at the parameters of the found function's definition that weren't expected.
+    pub fn note_arg_count_mismatch(
+        self,
+        db: &mut DiagnosticBuilder<'_>,
+        err: &TypeError<'tcx>,
+        exp_found: &ExpectedFound<Ty<'tcx>>,
+    ) {
+        match err {
+            TypeError::ArgCount => {}
+            _ => return,
+        }
+        let inputs = |ty: Ty<'tcx>| match ty.sty {
+            ty::FnDef(..) | ty::FnPtr(_) => {
+                let sig = ty.fn_sig(self);
+                Some(sig.skip_binder().inputs().to_vec())
+            }
+            _ => None,
+        };
+        let (expected, found) = match (inputs(exp_found.expected), inputs(exp_found.found)) {
+            (Some(expected), Some(found)) => (expected, found),
+            _ => return,
+        };
+
+        let cell = |inputs: &[Ty<'tcx>], i: usize| {
+            inputs.get(i).map_or("-".to_string(), |ty| format!("`{}`", ty))
+        };
+        let rows = expected.len().max(found.len());
+        let width = (0..rows).map(|i| cell(&expected, i).len()).max().unwrap_or(0);
+        let mut msg = DiagnosticStyledString::normal(format!(
+            "expected {} parameter{}, found {}",
+            expected.len(),
+            pluralise!(expected.len()),
+            found.len(),
+        ));
+        for i in 0..rows {
+            let (expected_cell, found_cell) = (cell(&expected, i), cell(&found, i));
+            let padding = " ".repeat(width - expected_cell.len() + 1);
+            msg.push_normal(format!("\n#{}: ", i + 1));
+            if i < found.len() {
+                msg.push_normal(expected_cell);
+            } else {
+                msg.push_highlighted(expected_cell);
+            }
+            msg.push_normal(padding);
+            if i < expected.len() {
+                msg.push_normal(found_cell);
+            } else {
+                msg.push_highlighted(found_cell);
+            }
+        }
+        db.note_styled(msg);
+
+        if let ty::FnDef(def_id, _) = exp_found.found.sty {
+            let body_id = self.hir().as_local_hir_id(def_id)
+                .and_then(|hir_id| self.hir().maybe_body_owned_by(hir_id));
+            if let Some(body_id) = body_id {
+                let params = &self.hir().body(body_id).params;
+                for param in params.iter().skip(expected.len()) {
+                    db.span_label(param.span, "this parameter isn't expected");
+                }
+                if params.len() < expected.len() {
+                    db.span_label(self.def_span(def_id), format!(
+                        "this function takes {} parameter{}",
+                        params.len(),
+                        pluralise!(params.len()),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Labels the definitions of the lifetimes of a `RegionsDoesNotOutlive` error and suggests how
+    /// to fix it: declaring the outlives requirement if both lifetimes are parameters of the same
+    /// item that doesn't relate them yet, or avoiding the reference if `'static` is required.
+    /// Neither helps if `exp_found` requires the lifetimes to be the same, e.g. because they are
+    /// related in an invariant position.
+    pub fn note_regions_outlive(
+        self,
+        db: &mut DiagnosticBuilder<'_>,
+        err: &TypeError<'tcx>,
+        exp_found: Option<&ExpectedFound<Ty<'tcx>>>,
+    ) {
+        let (sup, sub) = match *err {
+            TypeError::RegionsDoesNotOutlive(sup, sub) => (sup, sub),
+            _ => return,
+        };
+        let param = |region: Region<'tcx>| {
+            let (def_id, name) = match *region {
+                ty::ReEarlyBound(data) => (data.def_id, data.name),
+                ty::ReFree(ty::FreeRegion {
+                    bound_region: ty::BrNamed(def_id, name), ..
+                }) => (def_id, name),
+                _ => return None,
+            };
+            if name == kw::UnderscoreLifetime.as_interned_str() {
+                None
+            } else {
+                Some((def_id, name))
+            }
+        };
+        let sup_param = param(sup);
+        let sub_param = param(sub);
+        if let (Some((_, sup_name)), Some((_, sub_name))) = (sup_param, sub_param) {
+            // Lifetimes from different items may share a name, and there's no telling them apart.
+            if sup_name == sub_name {
+                return;
+            }
+        }
+        for &(def_id, name) in sup_param.iter().chain(sub_param.iter()) {
+            if let Some(span) = self.hir().span_if_local(def_id) {
+                db.span_label(span, format!("lifetime `{}` defined here", name));
+            }
+        }
+
+        if let Some(exp_found) = exp_found {
+            // The types may mention the parameters with other regions than the error, e.g. as
+            // free regions of the function body rather than early bound ones.
+            let is = |a: Region<'tcx>, b: Region<'tcx>| match (param(a), param(b)) {
+                (Some((_, a)), Some((_, b))) => a == b,
+                _ => a == b,
+            };
+            let mut relation = RegionVariances {
+                tcx: self,
+                ambient_variance: ty::Covariant,
+                variances: vec![],
+            };
+            let _ = relation.relate(&exp_found.expected, &exp_found.found);
+            // The variance of the positions relating the two lifetimes tells which one has to
+            // outlive the other, flipped where they appear the other way around.
+            let mut variances = relation.variances.iter().filter_map(|&(a, b, variance)| {
+                if is(a, sup) && is(b, sub) {
+                    Some(variance)
+                } else if is(a, sub) && is(b, sup) {
+                    Some(variance.xform(ty::Contravariant))
+                } else {
+                    None
+                }
+            }).filter(|&variance| variance != ty::Bivariant);
+            let must_be_equal = match variances.next() {
+                Some(ty::Invariant) => true,
+                Some(first) => variances.any(|variance| variance != first),
+                None => false,
+            };
+            if must_be_equal {
+                return;
+            }
+        }
+
+        let (sup_def_id, sup_name) = match sup_param {
+            Some(param) => param,
+            None => return,
+        };
+        if let ty::ReStatic = *sub {
+            db.help(&format!(
+                "consider using an owned type instead of a reference with lifetime `{}`",
+                sup_name,
+            ));
+            return;
+        }
+        let (sub_def_id, sub_name) = match sub_param {
+            Some(param) => param,
+            None => return,
+        };
+        // The bound can only go where both lifetimes are in scope.
+        let item_def_id = match self.parent(sup_def_id) {
+            Some(item_def_id) if self.parent(sub_def_id) == Some(item_def_id) => item_def_id,
+            _ => return,
+        };
+        // If the where clauses already bound either lifetime, e.g. with `'max: 'min` when `'min`
+        // is required to outlive `'max`, adding the bound would likely be wrong.
+        let is_bounded = self.predicates_of(item_def_id).predicates.iter().any(|(predicate, _)| {
+            match *predicate {
+                ty::Predicate::RegionOutlives(ref outlives) => {
+                    let ty::OutlivesPredicate(a, b) = *outlives.skip_binder();
+                    [a, b].iter().filter_map(|&region| param(region)).any(|(_, name)| {
+                        name == sup_name || name == sub_name
+                    })
+                }
+                _ => false,
+            }
+        });
+        if is_bounded {
+            return;
+        }
+        let hir_id = match self.hir().as_local_hir_id(sup_def_id) {
+            Some(hir_id) => hir_id,
+            None => return,
+        };
+        if let Node::GenericParam(param) = self.hir().get(hir_id) {
+            let is_explicit = match param.kind {
+                hir::GenericParamKind::Lifetime { kind: hir::LifetimeParamKind::Explicit } => true,
+                _ => false,
+            };
+            if is_explicit && param.bounds.is_empty() && !param.span.from_expansion() {
+                db.span_suggestion(
+                    param.span,
+                    &format!(
+                        "consider adding an explicit bound requiring `{}` to outlive `{}`",
+                        sup_name,
+                        sub_name,
+                    ),
+                    format!("{}: {}", sup_name, sub_name),
+                    Applicability::MaybeIncorrect,
+                );
+            }
+        }
+    }
+
+    /// Explains why two constants that may well have the same value at runtime aren't
+    /// considered equal here.
+    fn note_const_mismatch(
+        self,
+        db: &mut DiagnosticBuilder<'_>,
+        values: &ExpectedFound<&'tcx ty::Const<'tcx>>,
+    ) {
+        let is_concrete = |ct: &ty::Const<'_>| match ct.val {
+            ConstValue::Scalar(_) | ConstValue::Slice { .. } | ConstValue::ByRef { .. } => true,
+            _ => false,
+        };
+        match (values.expected.val, values.found.val) {
+            (ConstValue::Param(_), ConstValue::Param(_)) => {
+                db.note("a const parameter was expected, but a different one was found; \
+                         they may be instantiated with different values");
+            }
+            (ConstValue::Param(p), _) | (_, ConstValue::Param(p))
+                if is_concrete(values.expected) || is_concrete(values.found) =>
+            {
+                db.note(&format!("`{}` is a const parameter, so it may be instantiated with any \
+                                  value, not only the one it is compared with here", p.name));
+            }
+            _ => {}
+        }
+        for ct in &[values.expected, values.found] {
+            if let ConstValue::Unevaluated(..) = ct.val {
+                if ct.needs_subst() {
+                    db.note("constant expressions that depend on generic parameters are only \
+                             equal to themselves, even if other expressions are written the same");
+                } else {
+                    db.note(&format!("{} could not be evaluated", ct.sort_string(self)));
+                }
+                break;
+            }
+        }
+    }
+
+    /// Shortens `expected` and `found` to the `-Z long-type-threshold` length if either is longer,
+    /// writing both in full to a file in the output directory. Returns the path of that file, or
+    /// `None` (leaving the types as they are) if they're short enough or the file can't be written.
+    pub fn shorten_expected_found(
+        self,
+        expected: &mut DiagnosticStyledString,
+        found: &mut DiagnosticStyledString,
+    ) -> Option<PathBuf> {
+        let max_len = self.sess.opts.debugging_opts.long_type_threshold?;
+        let (short_expected, short_found) =
+            match (expected.shortened(max_len), found.shortened(max_len)) {
+                (None, None) => return None,
+                (short_expected, short_found) => (short_expected, short_found),
+            };
+
+        let contents = format!(
+            "expected `{}`\n   found `{}`\n",
+            expected.content(),
+            found.content(),
+        );
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        let path = self.output_filenames(LOCAL_CRATE)
+            .temp_path_ext(&format!("long-type-{:016x}.txt", hasher.finish()), None);
+        if let Err(e) = fs::write(&path, contents) {
+            debug!("shorten_expected_found: failed to write {}: {}", path.display(), e);
+            return None;
+        }
+
+        if let Some(short_expected) = short_expected {
+            *expected = short_expected;
+        }
+        if let Some(short_found) = short_found {
+            *found = short_found;
+        }
+        Some(path)
+    }
+}
+
+/// Collects the pairs of regions that relating two types would relate, along with the variance of
+/// their position, for `note_regions_outlive`.
+struct RegionVariances<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    ambient_variance: ty::Variance,
+    variances: Vec<(Region<'tcx>, Region<'tcx>, ty::Variance)>,
+}
+
+impl TypeRelation<'tcx> for RegionVariances<'tcx> {
+    fn tag(&self) -> &'static str { "RegionVariances" }
+    fn tcx(&self) -> TyCtxt<'tcx> { self.tcx }
+    fn param_env(&self) -> ty::ParamEnv<'tcx> { ty::ParamEnv::empty() }
+    fn a_is_expected(&self) -> bool { true }
+
+    fn relate_with_variance<T: Relate<'tcx>>(
+        &mut self,
+        variance: ty::Variance,
+        a: &T,
+        b: &T,
+    ) -> RelateResult<'tcx, T> {
+        let old_ambient_variance = self.ambient_variance;
+        self.ambient_variance = self.ambient_variance.xform(variance);
+        let result = self.relate(a, b);
+        self.ambient_variance = old_ambient_variance;
+        result
+    }
+
+    fn tys(&mut self, a: Ty<'tcx>, b: Ty<'tcx>) -> RelateResult<'tcx, Ty<'tcx>> {
+        match (&a.sty, &b.sty) {
+            (ty::Infer(_), _) | (_, ty::Infer(_)) |
+            (ty::Bound(..), _) | (_, ty::Bound(..)) |
+            (ty::Error, _) | (_, ty::Error) => Ok(a),
+            _ => relate::super_relate_tys(self, a, b),
+        }
+    }
+
+    fn regions(
+        &mut self,
+        a: Region<'tcx>,
+        b: Region<'tcx>,
+    ) -> RelateResult<'tcx, Region<'tcx>> {
+        if a != b {
+            self.variances.push((a, b, self.ambient_variance));
+        }
+        Ok(a)
+    }
+
+    fn consts(
+        &mut self,
+        a: &'tcx ty::Const<'tcx>,
+        _: &'tcx ty::Const<'tcx>,
+    ) -> RelateResult<'tcx, &'tcx ty::Const<'tcx>> {
+        Ok(a)
+    }
+
+    fn binders<T>(&mut self, a: &ty::Binder<T>, b: &ty::Binder<T>)
+                  -> RelateResult<'tcx, ty::Binder<T>>
+        where T: Relate<'tcx>
+    {
+        Ok(ty::Binder::bind(self.relate(a.skip_binder(), b.skip_binder())?))
+    }
 }
@@ -8,6 +8,7 @@ use crate::ty::{self, DefIdTree, ParamConst, Ty, TyCtxt, TypeFoldable};
 use crate::ty::subst::{Kind, Subst, UnpackedKind};
 use crate::ty::layout::{Integer, IntegerExt, Size};
 use crate::mir::interpret::{ConstValue, sign_extend, Scalar, truncate};
+use crate::util::nodemap::DefIdSet;
 
 use rustc_apfloat::ieee::{Double, Single};
 use rustc_apfloat::Float;
@@ -16,7 +17,7 @@ use syntax::ast;
 use syntax::attr::{SignedInt, UnsignedInt};
 use syntax::symbol::{kw, InternedString};
 
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::fmt::{self, Write as _};
 use std::ops::{Deref, DerefMut};
 
@@ -49,6 +50,7 @@ macro_rules! define_scoped_cx {
 thread_local! {
     static FORCE_IMPL_FILENAME_LINE: Cell<bool> = Cell::new(false);
     static SHOULD_PREFIX_WITH_CRATE: Cell<bool> = Cell::new(false);
+    static TRIMMED_PATHS: RefCell<DefIdSet> = RefCell::new(DefIdSet::default());
 }
 
 /// Force us to name impls with just the filename/line number. We
@@ -76,6 +78,18 @@ pub fn with_crate_prefix<F: FnOnce() -> R, R>(f: F) -> R {
     })
 }
 
+/// Prints the paths of the definitions in `def_ids` as just their name, e.g., `HashMap` rather
+/// than `std::collections::HashMap`. The caller is responsible for making sure the names are
+/// unambiguous in the message being printed, and for telling the user the full paths.
+pub fn with_trimmed_paths<F: FnOnce() -> R, R>(def_ids: DefIdSet, f: F) -> R {
+    TRIMMED_PATHS.with(|trimmed| {
+        let old = trimmed.replace(def_ids);
+        let result = f();
+        trimmed.replace(old);
+        result
+    })
+}
+
 /// The "region highlights" are used to control region printing during
 /// specific error messages. When a "region highlight" is enabled, it
 /// gives an alternate way to print specific regions. For now, we
@@ -1086,6 +1100,11 @@ impl<F: fmt::Write> Printer<'tcx> for FmtPrinter<'_, 'tcx, F> {
         define_scoped_cx!(self);
 
         if substs.is_empty() {
+            if TRIMMED_PATHS.with(|trimmed| trimmed.borrow().contains(&def_id)) {
+                p!(write("{}", self.tcx.item_name(def_id)));
+                return Ok(self);
+            }
+
             match self.try_print_visible_def_path(def_id)? {
                 (cx, true) => return Ok(cx),
                 (cx, false) => self = cx,
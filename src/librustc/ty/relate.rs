@@ -470,13 +470,16 @@ pub fn super_relate_tys<R: TypeRelation<'tcx>>(
                     // but are unequal, for better diagnostics.
                     let sz_a = sz_a.try_eval_usize(tcx, relation.param_env());
                     let sz_b = sz_b.try_eval_usize(tcx, relation.param_env());
-                    match (sz_a, sz_b) {
-                        (Some(sz_a_val), Some(sz_b_val)) => {
+                    match (sz_a, sz_b, err) {
+                        (Some(sz_a_val), Some(sz_b_val), _) => {
                             Err(TypeError::FixedArraySize(
                                 expected_found(relation, &sz_a_val, &sz_b_val)
                             ))
                         }
-                        _ => return Err(err),
+                        (_, _, TypeError::ConstMismatch(values)) => {
+                            Err(TypeError::ArraySizeMismatch(values))
+                        }
+                        (_, _, err) => return Err(err),
                     }
                 }
             }
@@ -550,18 +553,21 @@ pub fn super_relate_consts<R: TypeRelation<'tcx>>(
 ) -> RelateResult<'tcx, &'tcx ty::Const<'tcx>> {
     let tcx = relation.tcx();
 
+    let param_env = relation.param_env();
     let eagerly_eval = |x: &'tcx ty::Const<'tcx>| {
         if !x.val.has_local_value() {
-            return x.eval(tcx, relation.param_env()).val;
+            return x.eval(tcx, param_env);
         }
-        x.val
+        x
     };
+    // Mismatches are reported with the evaluated values, when there are any.
+    let (a_eval, b_eval) = (eagerly_eval(a), eagerly_eval(b));
 
     // Currently, the values that can be unified are those that
     // implement both `PartialEq` and `Eq`, corresponding to
     // `structural_match` types.
     // FIXME(const_generics): check for `structural_match` synthetic attribute.
-    match (eagerly_eval(a), eagerly_eval(b)) {
+    match (a_eval.val, b_eval.val) {
         (ConstValue::Infer(_), _) | (_, ConstValue::Infer(_)) => {
             // The caller should handle these cases!
             bug!("var types encountered in super_relate_consts: {:?} {:?}", a, b)
@@ -597,7 +603,7 @@ pub fn super_relate_consts<R: TypeRelation<'tcx>>(
                 }))
             }
 
-        _ => Err(TypeError::ConstMismatch(expected_found(relation, &a, &b))),
+        _ => Err(TypeError::ConstMismatch(expected_found(relation, &a_eval, &b_eval))),
     }
 }
 
@@ -1009,11 +1015,5 @@ pub fn expected_found_bool<T>(a_is_expected: bool,
                               -> ExpectedFound<T>
     where T: Clone
 {
-    let a = a.clone();
-    let b = b.clone();
-    if a_is_expected {
-        ExpectedFound {expected: a, found: b}
-    } else {
-        ExpectedFound {expected: b, found: a}
-    }
+    ExpectedFound::new(a_is_expected, a.clone(), b.clone())
 }
@@ -709,6 +709,8 @@ impl<'tcx, T: Lift<'tcx>> Lift<'tcx> for ty::error::ExpectedFound<T> {
                 ty::error::ExpectedFound {
                     expected,
                     found,
+                    expected_span: self.expected_span,
+                    found_span: self.found_span,
                 }
             })
         })
@@ -747,6 +749,7 @@ impl<'a, 'tcx> Lift<'tcx> for ty::error::TypeError<'a> {
             ProjectionBoundsLength(x) => ProjectionBoundsLength(x),
             Sorts(ref x) => return tcx.lift(x).map(Sorts),
             ExistentialMismatch(ref x) => return tcx.lift(x).map(ExistentialMismatch),
+            ArraySizeMismatch(ref x) => return tcx.lift(x).map(ArraySizeMismatch),
             ConstMismatch(ref x) => return tcx.lift(x).map(ConstMismatch),
             IntrinsicCast => IntrinsicCast,
         })
@@ -1302,7 +1305,7 @@ BraceStructTypeFoldableImpl! {
 
 BraceStructTypeFoldableImpl! {
     impl<'tcx, T> TypeFoldable<'tcx> for ty::error::ExpectedFound<T> {
-        expected, found
+        expected, found, expected_span, found_span
     } where T: TypeFoldable<'tcx>
 }
 
@@ -1324,6 +1327,7 @@ EnumTypeFoldableImpl! {
         (ty::error::TypeError::Mutability),
         (ty::error::TypeError::TupleSize)(x),
         (ty::error::TypeError::FixedArraySize)(x),
+        (ty::error::TypeError::ArraySizeMismatch)(x),
         (ty::error::TypeError::ArgCount),
         (ty::error::TypeError::RegionsDoesNotOutlive)(a, b),
         (ty::error::TypeError::RegionsInsufficientlyPolymorphic)(a, b),
@@ -10,7 +10,6 @@ use std::time::{Duration, Instant};
 use std::sync::mpsc::{Sender};
 use syntax_pos::{SpanData};
 use syntax::symbol::{Symbol, sym};
-use rustc_macros::HashStable;
 use crate::dep_graph::{DepNode};
 use crate::session::Session;
 
@@ -21,9 +20,9 @@ mod tests;
 pub const FN_OUTPUT_NAME: Symbol = sym::Output;
 
 // Useful type to use with `Result<>` indicate that an error has already
-// been reported to the user, so no need to continue checking.
-#[derive(Clone, Copy, Debug, RustcEncodable, RustcDecodable, HashStable)]
-pub struct ErrorReported;
+// been reported to the user, so no need to continue checking. The same
+// type is returned by handlers in error-as-value mode.
+pub use errors::ErrorReported;
 
 thread_local!(static TIME_DEPTH: Cell<usize> = Cell::new(0));
 
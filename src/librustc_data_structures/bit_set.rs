@@ -4,7 +4,9 @@ use std::fmt;
 use std::iter;
 use std::marker::PhantomData;
 use std::mem;
+use std::ptr;
 use std::slice;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
 
 #[cfg(test)]
 mod tests;
@@ -678,6 +680,100 @@ impl<T: Idx> GrowableBitSet<T> {
     }
 }
 
+/// The number of elements that fit in an `AtomicBitSet`.
+pub const ATOMIC_BIT_SET_MAX_DOMAIN_SIZE: usize = ATOMIC_CHUNKS * ATOMIC_CHUNK_BITS;
+
+const ATOMIC_WORD_BITS: usize = mem::size_of::<usize>() * 8;
+const ATOMIC_CHUNK_WORDS: usize = 1024;
+const ATOMIC_CHUNK_BITS: usize = ATOMIC_CHUNK_WORDS * ATOMIC_WORD_BITS;
+const ATOMIC_CHUNKS: usize = (1 << 28) / ATOMIC_CHUNK_BITS;
+
+/// A growable bitset that can be modified through a shared reference, without
+/// locking. The bits are split into chunks, which are allocated when the first
+/// element in them is inserted, so that threads can insert elements at the same
+/// time while the set grows.
+///
+/// `T` is an index type, typically a newtyped `usize` wrapper, but it can also
+/// just be `usize`.
+///
+/// All operations that involve an element will panic if the element is equal
+/// to or greater than `ATOMIC_BIT_SET_MAX_DOMAIN_SIZE`.
+pub struct AtomicBitSet<T: Idx> {
+    chunks: Box<[AtomicPtr<AtomicChunk>]>,
+    marker: PhantomData<T>,
+}
+
+struct AtomicChunk([AtomicUsize; ATOMIC_CHUNK_WORDS]);
+
+impl<T: Idx> AtomicBitSet<T> {
+    pub fn new_empty() -> AtomicBitSet<T> {
+        AtomicBitSet {
+            chunks: (0..ATOMIC_CHUNKS).map(|_| AtomicPtr::new(ptr::null_mut())).collect(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns `true` if the set has changed.
+    #[inline]
+    pub fn insert(&self, elem: T) -> bool {
+        let (chunk_index, word_index, mask) = atomic_chunk_word_index_and_mask(elem);
+        let chunk = match self.chunk(chunk_index) {
+            Some(chunk) => chunk,
+            None => self.alloc_chunk(chunk_index),
+        };
+        chunk.0[word_index].fetch_or(mask, Ordering::Relaxed) & mask == 0
+    }
+
+    #[inline]
+    pub fn contains(&self, elem: T) -> bool {
+        let (chunk_index, word_index, mask) = atomic_chunk_word_index_and_mask(elem);
+        match self.chunk(chunk_index) {
+            Some(chunk) => chunk.0[word_index].load(Ordering::Relaxed) & mask != 0,
+            None => false,
+        }
+    }
+
+    #[inline]
+    fn chunk(&self, chunk_index: usize) -> Option<&AtomicChunk> {
+        let chunk = self.chunks[chunk_index].load(Ordering::Acquire);
+        // Chunks are only freed when the set is dropped.
+        unsafe { chunk.as_ref() }
+    }
+
+    #[cold]
+    fn alloc_chunk(&self, chunk_index: usize) -> &AtomicChunk {
+        // All zeros is a valid, empty chunk.
+        let new_chunk = Box::into_raw(Box::new(unsafe { mem::zeroed::<AtomicChunk>() }));
+        let result = self.chunks[chunk_index].compare_exchange(
+            ptr::null_mut(),
+            new_chunk,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+        match result {
+            Ok(_) => unsafe { &*new_chunk },
+            Err(chunk) => {
+                // Another thread allocated the chunk in the meantime.
+                unsafe {
+                    drop(Box::from_raw(new_chunk));
+                    &*chunk
+                }
+            }
+        }
+    }
+}
+
+impl<T: Idx> Drop for AtomicBitSet<T> {
+    fn drop(&mut self) {
+        for chunk in self.chunks.iter_mut() {
+            let chunk = *chunk.get_mut();
+            if !chunk.is_null() {
+                unsafe { drop(Box::from_raw(chunk)) }
+            }
+        }
+    }
+}
+
 /// A fixed-size 2D bit matrix type with a dense representation.
 ///
 /// `R` and `C` are index types used to identify rows and columns respectively;
@@ -982,3 +1078,12 @@ fn word_index_and_mask<T: Idx>(elem: T) -> (usize, Word) {
     let mask = 1 << (elem % WORD_BITS);
     (word_index, mask)
 }
+
+#[inline]
+fn atomic_chunk_word_index_and_mask<T: Idx>(elem: T) -> (usize, usize, usize) {
+    let elem = elem.index();
+    let chunk_index = elem / ATOMIC_CHUNK_BITS;
+    let word_index = elem % ATOMIC_CHUNK_BITS / ATOMIC_WORD_BITS;
+    let mask = 1 << (elem % ATOMIC_WORD_BITS);
+    (chunk_index, word_index, mask)
+}
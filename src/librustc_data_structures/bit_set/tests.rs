@@ -153,6 +153,21 @@ fn grow() {
     }
 }
 
+#[test]
+fn atomic_insert_and_contains() {
+    let set: AtomicBitSet<usize> = AtomicBitSet::new_empty();
+    let elems = [0, 1, 63, 64, 65, 100_000, ATOMIC_BIT_SET_MAX_DOMAIN_SIZE - 1];
+    for &elem in &elems {
+        assert!(!set.contains(elem));
+        assert!(set.insert(elem));
+        assert!(!set.insert(elem));
+        assert!(set.contains(elem));
+    }
+    for elem in 0..200 {
+        assert_eq!(set.contains(elem), elems.contains(&elem));
+    }
+}
+
 #[test]
 fn matrix_intersection() {
     let mut matrix: BitMatrix<usize, usize> = BitMatrix::new(200, 200);
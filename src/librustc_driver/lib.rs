@@ -531,7 +531,7 @@ fn stdout_isatty() -> bool {
 fn handle_explain(code: &str,
                   output: ErrorOutputType) {
     let descriptions = rustc_interface::util::diagnostics_registry();
-    let normalised = if code.starts_with("E") {
+    let normalised = if code.starts_with("E") || code.contains("::") {
         code.to_string()
     } else {
         format!("E{0:0>4}", code)
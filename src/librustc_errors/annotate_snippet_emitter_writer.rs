@@ -90,11 +90,7 @@ impl<'a>  DiagnosticConverter<'a> {
             Some(Snippet {
                 title: Some(Annotation {
                     label: Some(self.message.to_string()),
-                    id: self.code.clone().map(|c| {
-                        match c {
-                            DiagnosticId::Error(val) | DiagnosticId::Lint(val) => val
-                        }
-                    }),
+                    id: self.code.as_ref().map(|c| c.to_string()),
                     annotation_type: Self::annotation_type_for_level(self.level),
                 }),
                 footer: vec![],
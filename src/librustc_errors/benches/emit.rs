@@ -0,0 +1,71 @@
+use rustc_data_structures::sync;
+use rustc_errors::emitter::Emitter;
+use rustc_errors::{Diagnostic, DiagnosticId, Handler, Level};
+use syntax_pos::edition::Edition;
+use syntax_pos::{BytePos, Globals, Span, GLOBALS};
+use test::Bencher;
+
+// Front end work running in parallel emits the diagnostics of different files from different
+// threads, and many of them are duplicates, like the errors from a macro invoked in every file.
+// These benchmarks emit the diagnostics of each simulated file from a task of its own, which
+// runs in parallel when the compiler is built with `parallel_compiler`, so that the threads
+// contend on the counting and deduplication they go through before rendering.
+
+/// An emitter that drops everything, so that only the handler is timed.
+struct Sink;
+
+impl Emitter for Sink {
+    fn emit_diagnostic(&mut self, _: &Diagnostic) {}
+}
+
+fn error(code: &str, message: &str, lo: u32) -> Diagnostic {
+    let code = DiagnosticId::Error(code.to_string());
+    let mut diagnostic = Diagnostic::new_with_code(Level::Error, Some(code), message);
+    diagnostic.set_span(Span::with_root_ctxt(BytePos(lo), BytePos(lo + 5)));
+    diagnostic
+}
+
+/// The diagnostics of `files` simulated files. Each file has `errors` errors of its own, and
+/// the same `duplicates` errors as every other file, interleaved with them.
+fn simulated_files(files: u32, errors: u32, duplicates: u32) -> Vec<Vec<Diagnostic>> {
+    (0..files).map(|file| {
+        let own = (0..errors).map(|i| {
+            error("E0308", "mismatched types", (file * errors + i) * 10)
+        }).collect::<Vec<_>>();
+        let shared = (0..duplicates).map(|i| {
+            error("E0433", "failed to resolve: use of undeclared type", i * 10)
+        }).collect::<Vec<_>>();
+        let len = own.len().max(shared.len());
+        (0..len).flat_map(|i| own.get(i).into_iter().chain(shared.get(i))).cloned().collect()
+    }).collect()
+}
+
+fn bench_emit(b: &mut Bencher, files: u32, errors: u32, duplicates: u32) {
+    GLOBALS.set(&Globals::new(Edition::Edition2018), || {
+        let files = simulated_files(files, errors, duplicates);
+        b.iter(|| {
+            let handler = Handler::with_emitter(true, None, Box::new(Sink));
+            sync::par_for_each_in(&files, |file| {
+                for diagnostic in file {
+                    handler.emit_diagnostic(diagnostic);
+                }
+            });
+            handler.err_count()
+        });
+    });
+}
+
+#[bench]
+fn emit_distinct_errors(b: &mut Bencher) {
+    bench_emit(b, 1, 2000, 0);
+}
+
+#[bench]
+fn emit_simulated_parallel_parsing(b: &mut Bencher) {
+    bench_emit(b, 16, 100, 100);
+}
+
+#[bench]
+fn emit_simulated_parallel_duplicates(b: &mut Bencher) {
+    bench_emit(b, 16, 0, 200);
+}
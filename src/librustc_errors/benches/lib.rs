@@ -0,0 +1,5 @@
+#![feature(test)]
+
+extern crate test;
+
+mod emit;
@@ -0,0 +1,133 @@
+//! Emits diagnostics in the formats of GCC and MSVC, one line per message.
+//!
+//! Build systems and editors often recognize compiler output with hardcoded patterns for these
+//! two formats. Tools embedding a `Handler` can use a `CompatEmitter` to have their
+//! diagnostics picked up by them without custom parsing.
+
+use crate::{Diagnostic, DiagnosticId, Level, SourceMapperDyn};
+use crate::emitter::Emitter;
+use crate::lock;
+use crate::markup;
+use crate::snippet::Style;
+
+use rustc_data_structures::sync::Lrc;
+use syntax_pos::MultiSpan;
+
+use std::io::{self, Write};
+
+/// The compiler whose diagnostic format a `CompatEmitter` follows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompatStyle {
+    /// `file:line:col: error: message [CODE]`
+    Gcc,
+    /// `file(line,col): error CODE: message`
+    Msvc,
+}
+
+/// Emits every diagnostic, and every note and help attached to it, on a line of its own in the
+/// format of another compiler. Source snippets and suggestions are left out.
+pub struct CompatEmitter {
+    dst: Box<dyn Write + Send>,
+    sm: Option<Lrc<SourceMapperDyn>>,
+    style: CompatStyle,
+}
+
+impl CompatEmitter {
+    pub fn new(
+        dst: Box<dyn Write + Send>,
+        source_map: Option<Lrc<SourceMapperDyn>>,
+        style: CompatStyle,
+    ) -> CompatEmitter {
+        CompatEmitter { dst, sm: source_map, style }
+    }
+
+    pub fn stderr(source_map: Option<Lrc<SourceMapperDyn>>, style: CompatStyle) -> CompatEmitter {
+        CompatEmitter::new(Box::new(io::stderr()), source_map, style)
+    }
+
+    /// Formats the location of the primary span of `msp`. Spans pointing into the expansion
+    /// of a macro from another crate point at its invocation instead, as editors can't open
+    /// the file they would point into.
+    fn location(&self, msp: &MultiSpan) -> Option<String> {
+        let sm = self.sm.as_ref()?;
+        let mut span = msp.primary_span()?;
+        if span.is_dummy() {
+            return None;
+        }
+        if sm.span_to_filename(span).is_macros() {
+            span = span.source_callsite();
+        }
+        let loc = sm.lookup_char_pos(span.lo());
+        let line = sm.doctest_offset_line(&loc.file.name, loc.line);
+        let col = loc.col.0 + 1;
+        Some(match self.style {
+            CompatStyle::Gcc => format!("{}:{}:{}", loc.file.name, line, col),
+            CompatStyle::Msvc => format!("{}({},{})", loc.file.name, line, col),
+        })
+    }
+
+    fn format_line(
+        &self,
+        level: Level,
+        code: Option<&DiagnosticId>,
+        msg: &[(String, Style)],
+        msp: &MultiSpan,
+    ) -> Option<String> {
+        let level = match level {
+            Level::Bug | Level::Fatal | Level::Error => "error",
+            Level::Warning => "warning",
+            // Neither compiler has a separate level for help.
+            Level::Note | Level::Help => "note",
+            Level::FailureNote => "",
+            Level::Cancelled => return None,
+        };
+        let msg = markup::terminal_parts(msg).into_iter()
+            .map(|(text, _)| text.replace('\n', " "))
+            .collect::<String>();
+        if level.is_empty() {
+            return Some(msg);
+        }
+
+        let mut line = String::new();
+        if let Some(location) = self.location(msp) {
+            line.push_str(&location);
+            line.push_str(": ");
+        }
+        match (self.style, code) {
+            (CompatStyle::Gcc, Some(code)) => {
+                line.push_str(&format!("{}: {} [{}]", level, msg, code))
+            }
+            (CompatStyle::Msvc, Some(code)) => {
+                line.push_str(&format!("{} {}: {}", level, code, msg))
+            }
+            (_, None) => line.push_str(&format!("{}: {}", level, msg)),
+        }
+        Some(line)
+    }
+
+    fn emit_lines(&mut self, lines: &[String]) -> io::Result<()> {
+        let _output_lock = lock::acquire_diagnostic_output_lock();
+        for line in lines {
+            writeln!(self.dst, "{}", line)?;
+        }
+        self.dst.flush()
+    }
+}
+
+impl Emitter for CompatEmitter {
+    fn emit_diagnostic(&mut self, db: &Diagnostic) {
+        let mut lines = Vec::new();
+        lines.extend(self.format_line(db.level, db.code.as_ref(), db.styled_message(), &db.span));
+        for child in &db.children {
+            let span = child.render_span.as_ref().unwrap_or(&child.span);
+            lines.extend(self.format_line(child.level, None, child.styled_message(), span));
+        }
+        if let Err(e) = self.emit_lines(&lines) {
+            panic!("failed to emit error: {}", e)
+        }
+    }
+
+    fn should_show_explain(&self) -> bool {
+        false
+    }
+}
@@ -1,11 +1,14 @@
+use crate::AuditedSuggestion;
 use crate::CodeSuggestion;
 use crate::SuggestionStyle;
 use crate::SubstitutionPart;
 use crate::Substitution;
 use crate::Applicability;
 use crate::Level;
+use crate::markup::{self, Markup};
 use crate::snippet::Style;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use syntax_pos::{MultiSpan, Span};
 
 #[must_use]
@@ -17,12 +20,81 @@ pub struct Diagnostic {
     pub span: MultiSpan,
     pub children: Vec<SubDiagnostic>,
     pub suggestions: Vec<CodeSuggestion>,
+    /// The unstable feature this diagnostic is about, if it's a feature gate error or warning.
+    pub feature_gate: Option<FeatureGate>,
+    /// Where the suggestions were constructed, if a handler audits suggestions, see
+    /// `HandlerFlags::audit_suggestions`.
+    crate audited_suggestions: AuditedSuggestions,
+}
+
+/// The suggestions of a diagnostic recorded in suggestion audit mode. Where its suggestions
+/// were constructed doesn't tell a diagnostic apart from another, so these are ignored when
+/// comparing and hashing diagnostics, e.g., to deduplicate them.
+#[derive(Clone, Debug, Default, RustcEncodable, RustcDecodable)]
+crate struct AuditedSuggestions(crate Vec<AuditedSuggestion>);
+
+impl PartialEq for AuditedSuggestions {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Hash for AuditedSuggestions {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, RustcEncodable, RustcDecodable)]
 pub enum DiagnosticId {
     Error(String),
     Lint(String),
+    /// The code of a diagnostic emitted by an external tool, namespaced by the name of the
+    /// tool, e.g. `clippy::needless_return`.
+    Tool { tool: String, name: String },
+}
+
+impl DiagnosticId {
+    pub fn tool(tool: &str, name: &str) -> DiagnosticId {
+        DiagnosticId::Tool { tool: tool.to_string(), name: name.to_string() }
+    }
+
+    /// The name of the tool the diagnostic comes from, if it doesn't come from rustc.
+    pub fn tool_name(&self) -> Option<&str> {
+        match *self {
+            DiagnosticId::Tool { ref tool, .. } => Some(tool),
+            DiagnosticId::Error(_) | DiagnosticId::Lint(_) => None,
+        }
+    }
+}
+
+/// Formats the code as it's shown to users and tools, i.e., as `tool::name` for the
+/// diagnostics of external tools.
+impl fmt::Display for DiagnosticId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DiagnosticId::Error(ref code) | DiagnosticId::Lint(ref code) => f.write_str(code),
+            DiagnosticId::Tool { ref tool, ref name } => write!(f, "{}::{}", tool, name),
+        }
+    }
+}
+
+/// Describes the feature a feature gate diagnostic is about, for tools that read diagnostics.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, RustcEncodable, RustcDecodable)]
+pub struct FeatureGate {
+    /// The name to enable the feature with in `#![feature(...)]`.
+    pub feature: String,
+    pub state: FeatureState,
+    /// The version the feature was introduced, accepted or removed in, if known.
+    pub since: Option<String>,
+    /// The number of the feature's tracking issue, if it has one.
+    pub issue: Option<u32>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, RustcEncodable, RustcDecodable)]
+pub enum FeatureState {
+    Active,
+    Accepted,
+    Removed,
+    Stabilized,
 }
 
 /// For example a note attached to an error.
@@ -32,6 +104,16 @@ pub struct SubDiagnostic {
     pub message: Vec<(String, Style)>,
     pub span: MultiSpan,
     pub render_span: Option<MultiSpan>,
+    pub importance: ImportanceHint,
+}
+
+/// How important a `SubDiagnostic` is to understand the diagnostic it's attached to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, RustcEncodable, RustcDecodable)]
+pub enum ImportanceHint {
+    Normal,
+    /// Background information most readers don't need, like where to learn more about a
+    /// feature. Emitters may collapse several such children into a single line.
+    Low,
 }
 
 #[derive(PartialEq, Eq)]
@@ -58,6 +140,29 @@ impl DiagnosticStyledString {
     pub fn content(&self) -> String {
         self.0.iter().map(|x| x.content()).collect::<String>()
     }
+
+    /// Returns the first `max_len` characters of this string followed by `...`, keeping the
+    /// highlighting of the parts that remain, or `None` if it is no longer than `max_len`.
+    pub fn shortened(&self, max_len: usize) -> Option<DiagnosticStyledString> {
+        if self.content().chars().count() <= max_len {
+            return None;
+        }
+        let mut shortened = DiagnosticStyledString::new();
+        let mut remaining = max_len;
+        for part in &self.0 {
+            if remaining == 0 {
+                break;
+            }
+            let s: String = part.content().chars().take(remaining).collect();
+            remaining -= s.chars().count();
+            match part {
+                StringPart::Normal(_) => shortened.push_normal(s),
+                StringPart::Highlighted(_) => shortened.push_highlighted(s),
+            }
+        }
+        shortened.push_normal("...");
+        Some(shortened)
+    }
 }
 
 #[derive(PartialEq, Eq)]
@@ -87,6 +192,8 @@ impl Diagnostic {
             span: MultiSpan::new(),
             children: vec![],
             suggestions: vec![],
+            feature_gate: None,
+            audited_suggestions: Default::default(),
         }
     }
 
@@ -132,6 +239,25 @@ impl Diagnostic {
         self
     }
 
+    /// Replaces every span of this diagnostic, including those of its children and
+    /// suggestions, with `f(span)`.
+    pub fn map_spans(&mut self, mut f: impl FnMut(Span) -> Span) {
+        self.span.map_spans(&mut f);
+        for child in &mut self.children {
+            child.span.map_spans(&mut f);
+            if let Some(ref mut render_span) = child.render_span {
+                render_span.map_spans(&mut f);
+            }
+        }
+        for suggestion in &mut self.suggestions {
+            for substitution in &mut suggestion.substitutions {
+                for part in &mut substitution.parts {
+                    part.span = f(part.span);
+                }
+            }
+        }
+    }
+
     pub fn replace_span_with(&mut self, after: Span) -> &mut Self {
         let before = self.span.clone();
         self.set_span(after);
@@ -193,11 +319,34 @@ impl Diagnostic {
         self
     }
 
+    /// Adds a note of low importance, see `ImportanceHint::Low`.
+    pub fn low_importance_note(&mut self, msg: &str) -> &mut Self {
+        self.sub(Level::Note, msg, MultiSpan::new(), None);
+        self.children.last_mut().unwrap().importance = ImportanceHint::Low;
+        self
+    }
+
     pub fn highlighted_note(&mut self, msg: Vec<(String, Style)>) -> &mut Self {
         self.sub_with_highlights(Level::Note, msg, MultiSpan::new(), None);
         self
     }
 
+    /// Adds a note whose highlighted parts are emphasized, like the types in
+    /// `note_expected_found`.
+    pub fn note_styled(&mut self, msg: DiagnosticStyledString) -> &mut Self {
+        let msg = msg.0.into_iter().map(|part| match part {
+            StringPart::Normal(s) => (s, Style::NoStyle),
+            StringPart::Highlighted(s) => (s, Style::Highlight),
+        }).collect();
+        self.highlighted_note(msg)
+    }
+
+    /// Adds a note containing inline code, bullet lists or code blocks.
+    pub fn note_markup(&mut self, markup: Markup) -> &mut Self {
+        self.sub_with_highlights(Level::Note, markup.into_parts(), MultiSpan::new(), None);
+        self
+    }
+
     /// Prints the span with a note above it.
     pub fn span_note<S: Into<MultiSpan>>(&mut self,
                                          sp: S,
@@ -226,6 +375,19 @@ impl Diagnostic {
         self
     }
 
+    /// Adds a help message of low importance, see `ImportanceHint::Low`.
+    pub fn low_importance_help(&mut self, msg: &str) -> &mut Self {
+        self.sub(Level::Help, msg, MultiSpan::new(), None);
+        self.children.last_mut().unwrap().importance = ImportanceHint::Low;
+        self
+    }
+
+    /// Adds a help message containing inline code, bullet lists or code blocks.
+    pub fn help_markup(&mut self, markup: Markup) -> &mut Self {
+        self.sub_with_highlights(Level::Help, markup.into_parts(), MultiSpan::new(), None);
+        self
+    }
+
     /// Prints the span with some help above it.
     pub fn span_help<S: Into<MultiSpan>>(&mut self,
                                          sp: S,
@@ -241,7 +403,7 @@ impl Diagnostic {
         suggestion: Vec<(Span, String)>,
         applicability: Applicability,
     ) -> &mut Self {
-        self.suggestions.push(CodeSuggestion {
+        self.push_suggestion(CodeSuggestion {
             substitutions: vec![Substitution {
                 parts: suggestion
                     .into_iter()
@@ -267,7 +429,7 @@ impl Diagnostic {
         suggestion: Vec<(Span, String)>,
         applicability: Applicability,
     ) -> &mut Self {
-        self.suggestions.push(CodeSuggestion {
+        self.push_suggestion(CodeSuggestion {
             substitutions: vec![Substitution {
                 parts: suggestion
                     .into_iter()
@@ -301,7 +463,7 @@ impl Diagnostic {
     pub fn span_suggestion(&mut self, sp: Span, msg: &str,
                                        suggestion: String,
                                        applicability: Applicability) -> &mut Self {
-        self.suggestions.push(CodeSuggestion {
+        self.push_suggestion(CodeSuggestion {
             substitutions: vec![Substitution {
                 parts: vec![SubstitutionPart {
                     snippet: suggestion,
@@ -319,7 +481,7 @@ impl Diagnostic {
     pub fn span_suggestions(&mut self, sp: Span, msg: &str,
         suggestions: impl Iterator<Item = String>, applicability: Applicability) -> &mut Self
     {
-        self.suggestions.push(CodeSuggestion {
+        self.push_suggestion(CodeSuggestion {
             substitutions: suggestions.map(|snippet| Substitution {
                 parts: vec![SubstitutionPart {
                     snippet,
@@ -340,7 +502,7 @@ impl Diagnostic {
     pub fn span_suggestion_short(
         &mut self, sp: Span, msg: &str, suggestion: String, applicability: Applicability
     ) -> &mut Self {
-        self.suggestions.push(CodeSuggestion {
+        self.push_suggestion(CodeSuggestion {
             substitutions: vec![Substitution {
                 parts: vec![SubstitutionPart {
                     snippet: suggestion,
@@ -363,7 +525,7 @@ impl Diagnostic {
     pub fn span_suggestion_hidden(
         &mut self, sp: Span, msg: &str, suggestion: String, applicability: Applicability
     ) -> &mut Self {
-        self.suggestions.push(CodeSuggestion {
+        self.push_suggestion(CodeSuggestion {
             substitutions: vec![Substitution {
                 parts: vec![SubstitutionPart {
                     snippet: suggestion,
@@ -384,7 +546,7 @@ impl Diagnostic {
     pub fn tool_only_span_suggestion(
         &mut self, sp: Span, msg: &str, suggestion: String, applicability: Applicability
     ) -> &mut Self {
-        self.suggestions.push(CodeSuggestion {
+        self.push_suggestion(CodeSuggestion {
             substitutions: vec![Substitution {
                 parts: vec![SubstitutionPart {
                     snippet: suggestion,
@@ -398,6 +560,18 @@ impl Diagnostic {
         self
     }
 
+    /// Adds `suggestion`, recording where it was constructed if suggestions are audited.
+    fn push_suggestion(&mut self, suggestion: CodeSuggestion) {
+        if crate::audits_suggestions() {
+            self.audited_suggestions.0.push(AuditedSuggestion {
+                site: crate::suggestion_site(),
+                msg: suggestion.msg.clone(),
+                applicability: suggestion.applicability,
+            });
+        }
+        self.suggestions.push(suggestion);
+    }
+
     pub fn set_span<S: Into<MultiSpan>>(&mut self, sp: S) -> &mut Self {
         self.span = sp.into();
         self
@@ -412,8 +586,14 @@ impl Diagnostic {
         self.code.clone()
     }
 
+    pub fn set_feature_gate(&mut self, feature_gate: FeatureGate) -> &mut Self {
+        self.feature_gate = Some(feature_gate);
+        self
+    }
+
+    /// Returns the message, with any markup rendered as Markdown.
     pub fn message(&self) -> String {
-        self.message.iter().map(|i| i.0.as_str()).collect::<String>()
+        markup::to_markdown(&self.message)
     }
 
     pub fn styled_message(&self) -> &Vec<(String, Style)> {
@@ -440,6 +620,7 @@ impl Diagnostic {
             message: vec![(message.to_owned(), Style::NoStyle)],
             span,
             render_span,
+            importance: ImportanceHint::Normal,
         };
         self.children.push(sub);
     }
@@ -456,14 +637,16 @@ impl Diagnostic {
             message,
             span,
             render_span,
+            importance: ImportanceHint::Normal,
         };
         self.children.push(sub);
     }
 }
 
 impl SubDiagnostic {
+    /// Returns the message, with any markup rendered as Markdown.
     pub fn message(&self) -> String {
-        self.message.iter().map(|i| i.0.as_str()).collect::<String>()
+        markup::to_markdown(&self.message)
     }
 
     pub fn styled_message(&self) -> &Vec<(String, Style)> {
@@ -1,10 +1,13 @@
 use crate::Diagnostic;
 use crate::DiagnosticId;
 use crate::DiagnosticStyledString;
+use crate::FeatureGate;
 use crate::Applicability;
+use crate::DiagnosticToken;
 
 use crate::Level;
 use crate::Handler;
+use crate::markup::Markup;
 use std::fmt::{self, Debug};
 use std::ops::{Deref, DerefMut};
 use std::thread::panicking;
@@ -119,12 +122,32 @@ impl<'a> DiagnosticBuilder<'a> {
 
     /// Buffers the diagnostic for later emission, unless handler
     /// has disabled such buffering.
-    pub fn buffer(mut self, buffered_diagnostics: &mut Vec<Diagnostic>) {
+    pub fn buffer(self, buffered_diagnostics: &mut Vec<Diagnostic>) {
+        if let Some(diagnostic) = self.into_buffered() {
+            buffered_diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Like `buffer`, but tags the diagnostic with `token`, so that diagnostics buffered by
+    /// several threads can be emitted in a deterministic order with `Handler::emit_ordered`.
+    pub fn buffer_ordered(
+        self,
+        token: DiagnosticToken,
+        buffered_diagnostics: &mut Vec<(DiagnosticToken, Diagnostic)>,
+    ) {
+        if let Some(diagnostic) = self.into_buffered() {
+            buffered_diagnostics.push((token, diagnostic));
+        }
+    }
+
+    /// Takes the diagnostic out of the builder for buffering, or emits it right away and
+    /// returns `None` if the handler has disabled buffering.
+    fn into_buffered(mut self) -> Option<Diagnostic> {
         if self.0.handler.flags.dont_buffer_diagnostics ||
             self.0.handler.flags.treat_err_as_bug.is_some()
         {
             self.emit();
-            return;
+            return None;
         }
 
         // We need to use `ptr::read` because `DiagnosticBuilder`
@@ -137,7 +160,7 @@ impl<'a> DiagnosticBuilder<'a> {
         // Logging here is useful to help track down where in logs an error was
         // actually emitted.
         debug!("buffer: diagnostic={:?}", diagnostic);
-        buffered_diagnostics.push(diagnostic);
+        Some(diagnostic)
     }
 
     /// Convenience function for internal use, clients should use one of the
@@ -195,6 +218,9 @@ impl<'a> DiagnosticBuilder<'a> {
                                               ) -> &mut Self);
 
     forward!(pub fn note(&mut self, msg: &str) -> &mut Self);
+    forward!(pub fn low_importance_note(&mut self, msg: &str) -> &mut Self);
+    forward!(pub fn note_styled(&mut self, msg: DiagnosticStyledString) -> &mut Self);
+    forward!(pub fn note_markup(&mut self, markup: Markup) -> &mut Self);
     forward!(pub fn span_note<S: Into<MultiSpan>>(&mut self,
                                                   sp: S,
                                                   msg: &str,
@@ -202,6 +228,8 @@ impl<'a> DiagnosticBuilder<'a> {
     forward!(pub fn warn(&mut self, msg: &str) -> &mut Self);
     forward!(pub fn span_warn<S: Into<MultiSpan>>(&mut self, sp: S, msg: &str) -> &mut Self);
     forward!(pub fn help(&mut self, msg: &str) -> &mut Self);
+    forward!(pub fn low_importance_help(&mut self, msg: &str) -> &mut Self);
+    forward!(pub fn help_markup(&mut self, markup: Markup) -> &mut Self);
     forward!(pub fn span_help<S: Into<MultiSpan>>(&mut self,
                                                   sp: S,
                                                   msg: &str,
@@ -336,8 +364,41 @@ impl<'a> DiagnosticBuilder<'a> {
         self
     }
 
+    /// Adds a suggestion that is definitely what the user intended, so that
+    /// tools can apply it automatically.
+    pub fn machine_applicable_suggestion(
+        &mut self,
+        sp: Span,
+        msg: &str,
+        suggestion: String,
+    ) -> &mut Self {
+        self.span_suggestion(sp, msg, suggestion, Applicability::MachineApplicable)
+    }
+
+    /// Adds a suggestion that results in valid code, but may not be what the
+    /// user intended.
+    pub fn maybe_incorrect_suggestion(
+        &mut self,
+        sp: Span,
+        msg: &str,
+        suggestion: String,
+    ) -> &mut Self {
+        self.span_suggestion(sp, msg, suggestion, Applicability::MaybeIncorrect)
+    }
+
+    /// Adds a suggestion containing placeholders the user has to fill in.
+    pub fn placeholder_suggestion(
+        &mut self,
+        sp: Span,
+        msg: &str,
+        suggestion: String,
+    ) -> &mut Self {
+        self.span_suggestion(sp, msg, suggestion, Applicability::HasPlaceholders)
+    }
+
     forward!(pub fn set_span<S: Into<MultiSpan>>(&mut self, sp: S) -> &mut Self);
     forward!(pub fn code(&mut self, s: DiagnosticId) -> &mut Self);
+    forward!(pub fn set_feature_gate(&mut self, feature_gate: FeatureGate) -> &mut Self);
 
     pub fn allow_suggestions(&mut self, allow: bool) -> &mut Self {
         self.0.allow_suggestions = allow;
@@ -12,20 +12,24 @@ use Destination::*;
 use syntax_pos::{SourceFile, Span, MultiSpan};
 
 use crate::{
-    Level, CodeSuggestion, Diagnostic, SubDiagnostic,
-    SuggestionStyle, SourceMapperDyn, DiagnosticId,
+    Applicability, Level, CodeSuggestion, Diagnostic, ImportanceHint, SubDiagnostic,
+    SuggestionStyle, SourceMapperDyn, DiagnosticId, Progress, SessionSummary,
 };
 use crate::Level::Error;
 use crate::snippet::{Annotation, AnnotationType, Line, MultilineAnnotation, StyledString, Style};
 use crate::styled_buffer::StyledBuffer;
+use crate::lock::{self, TerminalKind};
+use crate::markup;
 
 use rustc_data_structures::fx::FxHashMap;
-use rustc_data_structures::sync::Lrc;
+use rustc_data_structures::sync::{self, Lrc};
 use std::borrow::Cow;
 use std::io::prelude::*;
 use std::io;
 use std::cmp::{min, max, Reverse};
+use std::mem;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 use termcolor::{StandardStream, ColorChoice, ColorSpec, BufferWriter, Ansi};
 use termcolor::{WriteColor, Color, Buffer};
 
@@ -35,6 +39,9 @@ pub enum HumanReadableErrorType {
     Default(ColorConfig),
     AnnotateSnippet(ColorConfig),
     Short(ColorConfig),
+    /// Like `Short`, marking the diagnostics that come with a fix which can be applied
+    /// automatically.
+    ShortWithFixes(ColorConfig),
 }
 
 impl HumanReadableErrorType {
@@ -43,9 +50,19 @@ impl HumanReadableErrorType {
         match self {
             HumanReadableErrorType::Default(cc) => (false, cc),
             HumanReadableErrorType::Short(cc) => (true, cc),
+            HumanReadableErrorType::ShortWithFixes(cc) => (true, cc),
             HumanReadableErrorType::AnnotateSnippet(cc) => (false, cc),
         }
     }
+
+    /// Returns `true` if diagnostics with a machine applicable suggestion get a
+    /// `[fix available]` marker.
+    pub fn marks_fixes(self) -> bool {
+        match self {
+            HumanReadableErrorType::ShortWithFixes(_) => true,
+            _ => false,
+        }
+    }
     pub fn new_emitter(
         self,
         dst: Box<dyn Write + Send>,
@@ -57,7 +74,7 @@ impl HumanReadableErrorType {
         let (short, color_config) = self.unzip();
         let color = color_config.suggests_using_colors();
         EmitterWriter::new(dst, source_map, short, teach, color, terminal_width,
-            external_macro_backtrace)
+            external_macro_backtrace).mark_fixes(self.marks_fixes())
     }
 }
 
@@ -189,6 +206,25 @@ pub trait Emitter {
     /// other formats can, and will, simply ignore it.
     fn emit_artifact_notification(&mut self, _path: &Path, _artifact_type: &str) {}
 
+    /// Emit a notification about the progress of a long-running session.
+    /// This is currently only supported for the JSON format, terminal UIs
+    /// draw progress themselves, see `lock::OutputInterleaver`.
+    fn emit_progress(&mut self, _progress: &Progress) {}
+
+    /// Emit the summary of what a session emitted, once it ends.
+    /// By default, this emits the diagnostics that end the human readable
+    /// output, the JSON format can also emit the summary itself.
+    fn emit_session_summary(&mut self, summary: &SessionSummary) {
+        for diagnostic in summary.diagnostics(self.should_show_explain()) {
+            self.emit_diagnostic(&diagnostic);
+        }
+    }
+
+    /// Emit the diagnostics held back until the end of the session.
+    /// This is currently only supported for the JSON format, whose LSP mode
+    /// publishes the diagnostics of each file at once.
+    fn flush_diagnostics(&mut self) {}
+
     /// Checks if should show explanations about "rustc --explain"
     fn should_show_explain(&self) -> bool {
         true
@@ -286,6 +322,7 @@ pub trait Emitter {
                 ],
                 span: MultiSpan::new(),
                 render_span: None,
+                importance: ImportanceHint::Normal,
             });
         }
     }
@@ -382,6 +419,12 @@ impl Emitter for EmitterWriter {
     fn emit_diagnostic(&mut self, db: &Diagnostic) {
         let mut children = db.children.clone();
         let (mut primary_span, suggestions) = self.primary_span_formatted(&db);
+        let mut message = db.styled_message().clone();
+        if self.short_message && self.mark_fixes && db.suggestions.iter().any(|sugg| {
+            sugg.applicability == Applicability::MachineApplicable
+        }) {
+            message.push((" [fix available]".to_string(), Style::NoStyle));
+        }
 
         self.fix_multispans_in_std_macros(&self.sm,
                                           &mut primary_span,
@@ -390,7 +433,7 @@ impl Emitter for EmitterWriter {
                                           self.external_macro_backtrace);
 
         self.emit_messages_default(&db.level,
-                                   &db.styled_message(),
+                                   &message,
                                    &db.code,
                                    &primary_span,
                                    &children,
@@ -402,6 +445,54 @@ impl Emitter for EmitterWriter {
     }
 }
 
+/// Renders `diagnostic` with the emitter `make_emitter` returns for an in-memory destination.
+///
+/// This lets tools store diagnostics in their serialized form, e.g. along with the outputs
+/// of a build, and only render them in the format they're shown in once they're needed.
+pub fn render_with<F>(diagnostic: &Diagnostic, make_emitter: F) -> String
+where
+    F: FnOnce(Box<dyn Write + Send>) -> Box<dyn Emitter + sync::Send>,
+{
+    let buffer = SharedBuffer::default();
+    make_emitter(Box::new(buffer.clone())).emit_diagnostic(diagnostic);
+    buffer.take()
+}
+
+/// A diagnostic in a form it can be stored in, from which it can be rendered later with
+/// `EmitterWriter::render_to_string`. Besides `Diagnostic` itself, in its `RustcEncodable`
+/// form, this is implemented by the diagnostics the JSON emitter emits.
+pub trait StoredDiagnostic {
+    /// Returns the diagnostic, with its spans resolved in `source_map`.
+    fn to_diagnostic(&self, source_map: Option<&SourceMapperDyn>) -> Cow<'_, Diagnostic>;
+}
+
+impl StoredDiagnostic for Diagnostic {
+    fn to_diagnostic(&self, _: Option<&SourceMapperDyn>) -> Cow<'_, Diagnostic> {
+        Cow::Borrowed(self)
+    }
+}
+
+/// An in-memory sink whose contents can be taken while an emitter is writing to it.
+#[derive(Clone, Default)]
+crate struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    crate fn take(&self) -> String {
+        let bytes = mem::take(&mut *self.0.lock().unwrap());
+        String::from_utf8(bytes).expect("diagnostics rendered to invalid UTF-8")
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 /// maximum number of lines we will print for each error; arbitrary.
 pub const MAX_HIGHLIGHT_LINES: usize = 6;
 /// maximum number of suggestions to be shown
@@ -418,19 +509,16 @@ pub enum ColorConfig {
 
 impl ColorConfig {
     fn to_color_choice(self) -> ColorChoice {
-        match self {
-            ColorConfig::Always => {
-                if atty::is(atty::Stream::Stderr) {
-                    ColorChoice::Always
-                } else {
-                    ColorChoice::AlwaysAnsi
-                }
-            }
-            ColorConfig::Never => ColorChoice::Never,
-            ColorConfig::Auto if atty::is(atty::Stream::Stderr) => {
-                ColorChoice::Auto
-            }
-            ColorConfig::Auto => ColorChoice::Never,
+        match (self, lock::stderr_terminal()) {
+            (ColorConfig::Never, _) |
+            (ColorConfig::Auto, TerminalKind::NotATerminal) => ColorChoice::Never,
+            // Legacy Windows consoles are styled through the console API.
+            (_, TerminalKind::WindowsConsole) => ColorChoice::Always,
+            // On Windows, `ColorChoice::Auto` would only ever style through the console
+            // API, which mintty and Cygwin ptys don't support.
+            (ColorConfig::Auto, TerminalKind::Ansi) if !cfg!(windows) => ColorChoice::Auto,
+            (ColorConfig::Auto, TerminalKind::Ansi) |
+            (ColorConfig::Always, _) => ColorChoice::AlwaysAnsi,
         }
     }
     fn suggests_using_colors(self) -> bool {
@@ -448,8 +536,11 @@ pub struct EmitterWriter {
     dst: Destination,
     sm: Option<Lrc<SourceMapperDyn>>,
     short_message: bool,
+    mark_fixes: bool,
     teach: bool,
     ui_testing: bool,
+    item_headers: bool,
+    collapse_notes: bool,
     terminal_width: Option<usize>,
 
     external_macro_backtrace: bool,
@@ -476,8 +567,11 @@ impl EmitterWriter {
             dst,
             sm: source_map,
             short_message,
+            mark_fixes: false,
             teach,
             ui_testing: false,
+            item_headers: false,
+            collapse_notes: false,
             terminal_width,
             external_macro_backtrace,
         }
@@ -496,8 +590,36 @@ impl EmitterWriter {
             dst: Raw(dst, colored),
             sm: source_map,
             short_message,
+            mark_fixes: false,
             teach,
             ui_testing: false,
+            item_headers: false,
+            collapse_notes: false,
+            terminal_width,
+            external_macro_backtrace,
+        }
+    }
+
+    /// Creates an emitter writing to `dst`, which is styled through its `WriteColor`
+    /// implementation. This lets embedders render diagnostics into their own terminal
+    /// abstraction, or into a buffer honoring the color settings they chose.
+    pub fn with_write_color(
+        dst: Box<dyn WriteColor + Send>,
+        source_map: Option<Lrc<SourceMapperDyn>>,
+        short_message: bool,
+        teach: bool,
+        terminal_width: Option<usize>,
+        external_macro_backtrace: bool,
+    ) -> EmitterWriter {
+        EmitterWriter {
+            dst: Colored(dst),
+            sm: source_map,
+            short_message,
+            mark_fixes: false,
+            teach,
+            ui_testing: false,
+            item_headers: false,
+            collapse_notes: false,
             terminal_width,
             external_macro_backtrace,
         }
@@ -508,6 +630,42 @@ impl EmitterWriter {
         self
     }
 
+    /// Shows the item the primary span of a diagnostic points into, like `in fn main`, after
+    /// its location, for the items registered with the source map.
+    pub fn item_headers(mut self, item_headers: bool) -> Self {
+        self.item_headers = item_headers;
+        self
+    }
+
+    /// Collapses the notes and help messages of low importance of a diagnostic into a single
+    /// line if there are several of them.
+    pub fn collapse_notes(mut self, collapse_notes: bool) -> Self {
+        self.collapse_notes = collapse_notes;
+        self
+    }
+
+    /// In short mode, ends the line of every diagnostic with a machine applicable suggestion
+    /// with a `[fix available]` marker.
+    pub fn mark_fixes(mut self, mark_fixes: bool) -> Self {
+        self.mark_fixes = mark_fixes;
+        self
+    }
+
+    /// Renders `diagnostic` as it would be emitted to a destination without colors, with the
+    /// snippets of its spans looked up in `source_map`. The diagnostic doesn't have to come
+    /// from this session. A `Diagnostic` stored in its `RustcEncodable` form needs `source_map`
+    /// to have the files its spans point into at the same positions, while one emitted by the
+    /// JSON emitter only needs the files to be in it or on disk, unchanged.
+    pub fn render_to_string<D: StoredDiagnostic + ?Sized>(
+        diagnostic: &D,
+        source_map: Option<Lrc<SourceMapperDyn>>,
+    ) -> String {
+        let diagnostic = diagnostic.to_diagnostic(source_map.as_ref().map(|sm| &**sm));
+        render_with(&diagnostic, |dst| {
+            Box::new(EmitterWriter::new(dst, source_map, false, false, false, None, false))
+        })
+    }
+
     fn maybe_anonymized(&self, line_num: usize) -> String {
         if self.ui_testing {
             ANONYMIZED_LINE_NUM.to_string()
@@ -627,11 +785,7 @@ impl EmitterWriter {
             if let Some(ref ann) = line.annotations.get(0) {
                 if let AnnotationType::MultilineStart(depth) = ann.annotation_type {
                     if source_string.chars().take(ann.start_col).all(|c| c.is_whitespace()) {
-                        let style = if ann.is_primary {
-                            Style::UnderlinePrimary
-                        } else {
-                            Style::UnderlineSecondary
-                        };
+                        let (_, style) = ann.underline();
                         buffer.putc(line_offset, width_offset + depth - 1, '/', style);
                         return vec![(depth, style)];
                     }
@@ -844,11 +998,7 @@ impl EmitterWriter {
         // 4 |   }
         //   |  _
         for &(pos, annotation) in &annotations_position {
-            let style = if annotation.is_primary {
-                Style::UnderlinePrimary
-            } else {
-                Style::UnderlineSecondary
-            };
+            let (_, style) = annotation.underline();
             let pos = pos + 1;
             match annotation.annotation_type {
                 AnnotationType::MultilineStart(depth) |
@@ -887,11 +1037,7 @@ impl EmitterWriter {
         // 4 | | }
         //   | |_
         for &(pos, annotation) in &annotations_position {
-            let style = if annotation.is_primary {
-                Style::UnderlinePrimary
-            } else {
-                Style::UnderlineSecondary
-            };
+            let (_, style) = annotation.underline();
             let pos = pos + 1;
 
             if pos > 1 && (annotation.has_label() || annotation.takes_space()) {
@@ -935,11 +1081,7 @@ impl EmitterWriter {
         // 4 |   }
         //   |  _  test
         for &(pos, annotation) in &annotations_position {
-            let style = if annotation.is_primary {
-                Style::LabelPrimary
-            } else {
-                Style::LabelSecondary
-            };
+            let style = annotation.label_style();
             let (pos, col) = if pos == 0 {
                 (pos + 1, if annotation.end_col + 1 > left {
                     annotation.end_col + 1 - left
@@ -983,11 +1125,7 @@ impl EmitterWriter {
         // 4 |   }
         //   |  _^  test
         for &(_, annotation) in &annotations_position {
-            let (underline, style) = if annotation.is_primary {
-                ('^', Style::UnderlinePrimary)
-            } else {
-                ('-', Style::UnderlineSecondary)
-            };
+            let (underline, style) = annotation.underline();
             for p in annotation.start_col..annotation.end_col {
                 buffer.putc(
                     line_offset + 1,
@@ -1004,12 +1142,7 @@ impl EmitterWriter {
         annotations_position.iter().filter_map(|&(_, annotation)| {
             match annotation.annotation_type {
                 AnnotationType::MultilineStart(p) | AnnotationType::MultilineEnd(p) => {
-                    let style = if annotation.is_primary {
-                        Style::LabelPrimary
-                    } else {
-                        Style::LabelSecondary
-                    };
-                    Some((p, style))
+                    Some((p, annotation.label_style()))
                 }
                 _ => None
             }
@@ -1092,6 +1225,7 @@ impl EmitterWriter {
             style
         }
 
+        let msg = markup::terminal_parts(msg);
         let mut line_number = 0;
 
         // Provided the following diagnostic message:
@@ -1166,17 +1300,25 @@ impl EmitterWriter {
             if *level != Level::FailureNote && !level_str.is_empty() {
                 buffer.append(0, &level_str, Style::Level(level.clone()));
             }
-            // only render error codes, not lint codes
-            if let Some(DiagnosticId::Error(ref code)) = *code {
+            // only render error codes, not lint codes, except in short mode, where the code
+            // is all there is to tell the diagnostics apart
+            let render_code = match *code {
+                Some(DiagnosticId::Error(_)) => true,
+                Some(_) => self.short_message,
+                None => false,
+            };
+            if let (true, Some(code)) = (render_code, code) {
                 buffer.append(0, "[", Style::Level(level.clone()));
-                buffer.append(0, &code, Style::Level(level.clone()));
+                buffer.append(0, &code.to_string(), Style::Level(level.clone()));
                 buffer.append(0, "]", Style::Level(level.clone()));
             }
             if *level != Level::FailureNote && !level_str.is_empty() {
                 buffer.append(0, ": ", header_style);
             }
-            for &(ref text, _) in msg.iter() {
-                buffer.append(0, text, header_style);
+            for (text, _) in markup::terminal_parts(msg) {
+                // keep short diagnostics on a single line
+                let text = if self.short_message { text.replace('\n', " ") } else { text };
+                buffer.append(0, &text, header_style);
             }
         }
 
@@ -1228,6 +1370,13 @@ impl EmitterWriter {
                         ),
                         Style::LineAndColumn,
                     );
+                    let item = msp.primary_span()
+                        .filter(|_| self.item_headers)
+                        .and_then(|sp| sm.enclosing_item(sp));
+                    if let Some(item) = item {
+                        buffer.append(buffer_msg_line_offset, &format!(" in {}", item),
+                                      Style::NoStyle);
+                    }
                     for _ in 0..max_line_num_len {
                         buffer.prepend(buffer_msg_line_offset, " ", Style::NoStyle);
                     }
@@ -1482,6 +1631,8 @@ impl EmitterWriter {
 
             let mut row_num = 2;
             for &(ref complete, ref parts) in suggestions.iter().take(MAX_SUGGESTIONS) {
+                // The line break the lines end with in the file isn't shown.
+                let complete = complete.trim_end_matches(|c| c == '\r' || c == '\n');
                 // Only show underline if the suggestion spans a single line and doesn't cover the
                 // entirety of the code output. If you have multiple replacements in the same line
                 // of code, show the underline.
@@ -1611,7 +1762,13 @@ impl EmitterWriter {
                     }
                 }
                 if !self.short_message {
+                    let is_low = |child: &&SubDiagnostic| child.importance == ImportanceHint::Low;
+                    let low_importance = children.iter().filter(is_low).count();
+                    let collapse = self.collapse_notes && low_importance > 1;
                     for child in children {
+                        if collapse && is_low(&child) {
+                            continue;
+                        }
                         let span = child.render_span.as_ref().unwrap_or(&child.span);
                         match self.emit_message_default(
                             &span,
@@ -1625,6 +1782,23 @@ impl EmitterWriter {
                             _ => ()
                         }
                     }
+                    if collapse {
+                        let msg = format!(
+                            "… {} more notes (run without `-Z collapse-notes` to see them)",
+                            low_importance,
+                        );
+                        match self.emit_message_default(
+                            &MultiSpan::new(),
+                            &[(msg, Style::NoStyle)],
+                            &None,
+                            &Level::Note,
+                            max_line_num_len,
+                            true,
+                        ) {
+                            Err(e) => panic!("failed to emit error: {}", e),
+                            _ => ()
+                        }
+                    }
                     for sugg in suggestions {
                         if sugg.style == SuggestionStyle::CompletelyHidden {
                             // do not display this suggestion, it is meant only for tools
@@ -1742,6 +1916,7 @@ impl FileWithAnnotatedLines {
                         end_col: hi.col_display,
                         is_primary: span_label.is_primary,
                         label: span_label.label.clone(),
+                        role: span_label.role,
                         overlaps_exactly: false,
                     };
                     multiline_annotations.push((lo.file.clone(), ml.clone()));
@@ -1754,6 +1929,7 @@ impl FileWithAnnotatedLines {
                     end_col: hi.col_display,
                     is_primary: span_label.is_primary,
                     label: span_label.label.clone(),
+                    role: span_label.role,
                     annotation_type: ann_type,
                 };
 
@@ -1892,8 +2068,11 @@ fn emit_to_destination(rendered_buffer: &[Vec<StyledString>],
                        dst: &mut Destination,
                        short_message: bool)
                        -> io::Result<()> {
-    use crate::lock;
-
+    // Only legacy Windows consoles need the global lock, see below.
+    let needs_global_lock = match dst {
+        Terminal(_) => true,
+        _ => false,
+    };
     let mut dst = dst.writable();
 
     // In order to prevent error message interleaving, where multiple error lines get intermixed
@@ -1905,10 +2084,20 @@ fn emit_to_destination(rendered_buffer: &[Vec<StyledString>],
     // one shot.  Because the Unix systems use ANSI for the colors, which is a text-based styling
     // scheme, this buffered approach works and maintains the styling.
     //
-    // On Windows, styling happens through calls to a terminal API. This prevents us from using the
-    // same buffering approach.  Instead, we use a global Windows mutex, which we acquire long
-    // enough to output the full error message, then we release.
-    let _buffer_lock = lock::acquire_global_lock("rustc_errors");
+    // On Windows, terminals understanding ANSI escape sequences, such as mintty or consoles
+    // supporting virtual terminal sequences, are written to the same way. On legacy consoles
+    // however, styling happens through calls to a terminal API. This prevents us from using
+    // the same buffering approach.  Instead, we use a global Windows mutex, which we acquire
+    // long enough to output the full error message, then we release.
+    //
+    // Within this process, we also hold the output lock, so that terminal UIs drawing a
+    // progress bar don't write in the middle of the error message.
+    let _output_lock = lock::acquire_diagnostic_output_lock();
+    let _buffer_lock = if needs_global_lock {
+        Some(lock::acquire_global_lock("rustc_errors"))
+    } else {
+        None
+    };
     for (pos, line) in rendered_buffer.iter().enumerate() {
         for part in line {
             dst.apply_style(lvl.clone(), part.style)?;
@@ -1928,6 +2117,8 @@ pub enum Destination {
     Buffered(BufferWriter),
     // The bool denotes whether we should be emitting ansi color codes or not
     Raw(Box<(dyn Write + Send)>, bool),
+    // A destination supplied by an embedder, which handles styling itself
+    Colored(Box<dyn WriteColor + Send>),
 }
 
 pub enum WritableDst<'a> {
@@ -1935,18 +2126,19 @@ pub enum WritableDst<'a> {
     Buffered(&'a mut BufferWriter, Buffer),
     Raw(&'a mut (dyn Write + Send)),
     ColoredRaw(Ansi<&'a mut (dyn Write + Send)>),
+    Colored(&'a mut (dyn WriteColor + Send)),
 }
 
 impl Destination {
     fn from_stderr(color: ColorConfig) -> Destination {
         let choice = color.to_color_choice();
-        // On Windows we'll be performing global synchronization on the entire
-        // system for emitting rustc errors, so there's no need to buffer
-        // anything.
+        // On legacy Windows consoles we'll be performing global synchronization
+        // on the entire system for emitting rustc errors, so there's no need to
+        // buffer anything.
         //
-        // On non-Windows we rely on the atomicity of `write` to ensure errors
+        // Everywhere else we rely on the atomicity of `write` to ensure errors
         // don't get all jumbled up.
-        if cfg!(windows) {
+        if lock::stderr_terminal() == TerminalKind::WindowsConsole {
             Terminal(StandardStream::stderr(choice))
         } else {
             Buffered(BufferWriter::stderr(choice))
@@ -1962,6 +2154,7 @@ impl Destination {
             }
             Destination::Raw(ref mut t, false) => WritableDst::Raw(t),
             Destination::Raw(ref mut t, true) => WritableDst::ColoredRaw(Ansi::new(t)),
+            Destination::Colored(ref mut t) => WritableDst::Colored(&mut **t),
         }
     }
 }
@@ -1992,6 +2185,17 @@ impl<'a> WritableDst<'a> {
                 spec = lvl.color();
                 spec.set_bold(true);
             }
+            Style::Role(0) => {
+                spec = lvl.color();
+                spec.set_bold(true);
+            }
+            Style::Role(role) => {
+                // Later roles cycle through colors distinct from the level colors.
+                let colors = [Color::Magenta, Color::Green];
+                spec.set_bold(true)
+                    .set_intense(true)
+                    .set_fg(Some(colors[(role - 1) % colors.len()]));
+            }
             Style::UnderlineSecondary |
             Style::LabelSecondary => {
                 spec.set_bold(true)
@@ -2003,7 +2207,10 @@ impl<'a> WritableDst<'a> {
                 }
             }
             Style::HeaderMsg |
-            Style::NoStyle => {}
+            Style::NoStyle |
+            Style::InlineCode |
+            Style::CodeBlock |
+            Style::ListItem => {}
             Style::Level(lvl) => {
                 spec = lvl.color();
                 spec.set_bold(true);
@@ -2020,6 +2227,7 @@ impl<'a> WritableDst<'a> {
             WritableDst::Terminal(ref mut t) => t.set_color(color),
             WritableDst::Buffered(_, ref mut t) => t.set_color(color),
             WritableDst::ColoredRaw(ref mut t) => t.set_color(color),
+            WritableDst::Colored(ref mut t) => t.set_color(color),
             WritableDst::Raw(_) => Ok(())
         }
     }
@@ -2029,6 +2237,7 @@ impl<'a> WritableDst<'a> {
             WritableDst::Terminal(ref mut t) => t.reset(),
             WritableDst::Buffered(_, ref mut t) => t.reset(),
             WritableDst::ColoredRaw(ref mut t) => t.reset(),
+            WritableDst::Colored(ref mut t) => t.reset(),
             WritableDst::Raw(_) => Ok(()),
         }
     }
@@ -2041,6 +2250,7 @@ impl<'a> Write for WritableDst<'a> {
             WritableDst::Buffered(_, ref mut buf) => buf.write(bytes),
             WritableDst::Raw(ref mut w) => w.write(bytes),
             WritableDst::ColoredRaw(ref mut t) => t.write(bytes),
+            WritableDst::Colored(ref mut t) => t.write(bytes),
         }
     }
 
@@ -2050,6 +2260,7 @@ impl<'a> Write for WritableDst<'a> {
             WritableDst::Buffered(_, ref mut buf) => buf.flush(),
             WritableDst::Raw(ref mut w) => w.flush(),
             WritableDst::ColoredRaw(ref mut w) => w.flush(),
+            WritableDst::Colored(ref mut w) => w.flush(),
         }
     }
 }
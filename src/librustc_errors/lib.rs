@@ -15,16 +15,22 @@ use Level::*;
 
 use emitter::{Emitter, EmitterWriter};
 use registry::Registry;
+use replay::DiagnosticReplayCache;
+use suppression::SuppressionTable;
 
-use rustc_data_structures::sync::{self, Lrc, Lock};
+use rustc_data_structures::sync::{self, AtomicUsize, Lrc, Lock, SeqCst};
+use rustc_data_structures::sharded::Sharded;
 use rustc_data_structures::fx::FxHashSet;
 use rustc_data_structures::stable_hasher::StableHasher;
 
 use std::borrow::Cow;
-use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::hash::Hash;
 use std::{error, fmt};
 use std::panic;
+use std::sync::atomic::AtomicBool;
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 use termcolor::{ColorSpec, Color};
 
@@ -32,10 +38,16 @@ mod diagnostic;
 mod diagnostic_builder;
 pub mod emitter;
 pub mod annotate_snippet_emitter_writer;
+pub mod compat_emitter;
 mod snippet;
 pub mod registry;
 mod styled_buffer;
-mod lock;
+pub mod test_emitter;
+pub mod lock;
+pub mod markup;
+pub mod subscribers;
+pub mod replay;
+pub mod suppression;
 
 use syntax_pos::{BytePos,
                  Loc,
@@ -141,19 +153,57 @@ pub struct SubstitutionPart {
 
 pub type SourceMapperDyn = dyn SourceMapper + sync::Send + sync::Sync;
 
+/// Why `SourceMapper::try_merge_spans` can't merge two spans.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpanMergeError {
+    /// One of the spans is dummy.
+    DummySpan,
+    /// The spans come from different macro expansions, or only one of them does.
+    DifferentContexts,
+    /// The spans are in different files.
+    DifferentFiles,
+    /// The spans are in a file without lines, as it's empty.
+    NoLine,
+    /// The left-hand span ends on another line than the one the right-hand span starts on.
+    DifferentLines,
+    /// The left-hand span doesn't end before the right-hand span starts.
+    Overlapping,
+}
+
 pub trait SourceMapper {
     fn lookup_char_pos(&self, pos: BytePos) -> Loc;
+    fn lookup_byte_column(&self, pos: BytePos) -> usize;
+    fn lookup_utf16_column(&self, pos: BytePos) -> usize;
     fn span_to_lines(&self, sp: Span) -> FileLinesResult;
     fn span_to_string(&self, sp: Span) -> String;
     fn span_to_filename(&self, sp: Span) -> FileName;
     fn merge_spans(&self, sp_lhs: Span, sp_rhs: Span) -> Option<Span>;
+    fn try_merge_spans(&self, sp_lhs: Span, sp_rhs: Span) -> Result<Span, SpanMergeError>;
+    /// Merges the runs of consecutive spans of `spans` that can be merged into single spans.
+    fn try_merge_ordered(&self, spans: Vec<Span>) -> Vec<Span>;
     fn call_span_if_macro(&self, sp: Span) -> Span;
     fn ensure_source_file_source_present(&self, source_file: Lrc<SourceFile>) -> bool;
     fn doctest_offset_line(&self, file: &FileName, line: usize) -> usize;
+    /// Describes the item, like a function or an `impl`, `sp` points into, if it's known.
+    fn enclosing_item(&self, sp: Span) -> Option<String>;
+    /// Returns the span from the `start` to the `end` 1-based line and 1-based character
+    /// column of the file named `file_name`, as its `FileName` displays, loading the file if
+    /// it isn't in the source map yet.
+    fn span_from_line_columns(
+        &self,
+        file_name: &str,
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> Option<Span>;
 }
 
 impl CodeSuggestion {
     /// Returns the assembled code suggestions and whether they should be shown with an underline.
+    ///
+    /// The assembled lines keep the line endings of the file the suggestion applies to, including
+    /// the one of their last line if it has one, so that they can replace the original lines in
+    /// the file as it is on disk. The lines inserted by the suggestion are indented like the rest
+    /// of the file, see `SourceFile::reindent`.
     pub fn splice_lines(&self, cm: &SourceMapperDyn)
                         -> Vec<(String, Vec<SubstitutionPart>)> {
         use syntax_pos::{CharPos, Pos};
@@ -161,7 +211,8 @@ impl CodeSuggestion {
         fn push_trailing(buf: &mut String,
                          line_opt: Option<&Cow<'_, str>>,
                          lo: &Loc,
-                         hi_opt: Option<&Loc>) {
+                         hi_opt: Option<&Loc>,
+                         line_break: &str) {
             let (lo, hi_opt) = (lo.col.to_usize(), hi_opt.map(|hi| hi.col.to_usize()));
             if let Some(line) = line_opt {
                 if let Some(lo) = line.char_indices().map(|(i, _)| i).nth(lo) {
@@ -173,7 +224,7 @@ impl CodeSuggestion {
                     }
                 }
                 if let None = hi_opt {
-                    buf.push('\n');
+                    buf.push_str(line_break);
                 }
             }
         }
@@ -202,6 +253,11 @@ impl CodeSuggestion {
             //
             // Finally push the trailing line segment of the last span
             let fm = &lines.file;
+            for part in &mut substitution.parts {
+                part.snippet = fm.reindent(part.span.lo(), &part.snippet);
+            }
+            // The line break of the line with the given 1-based number.
+            let line_break = |line: usize| fm.line_ending(line - 1).map_or("", |le| le.as_str());
             let mut prev_hi = cm.lookup_char_pos(bounding_span.lo());
             prev_hi.col = CharPos::from_usize(0);
 
@@ -211,14 +267,18 @@ impl CodeSuggestion {
             for part in &substitution.parts {
                 let cur_lo = cm.lookup_char_pos(part.span.lo());
                 if prev_hi.line == cur_lo.line {
-                    push_trailing(&mut buf, prev_line.as_ref(), &prev_hi, Some(&cur_lo));
+                    push_trailing(&mut buf, prev_line.as_ref(), &prev_hi, Some(&cur_lo), "");
                 } else {
-                    push_trailing(&mut buf, prev_line.as_ref(), &prev_hi, None);
+                    push_trailing(&mut buf,
+                                  prev_line.as_ref(),
+                                  &prev_hi,
+                                  None,
+                                  line_break(prev_hi.line));
                     // push lines between the previous and current span (if any)
                     for idx in prev_hi.line..(cur_lo.line - 1) {
                         if let Some(line) = fm.get_line(idx) {
                             buf.push_str(line.as_ref());
-                            buf.push('\n');
+                            buf.push_str(line_break(idx + 1));
                         }
                     }
                     if let Some(cur_line) = fm.get_line(cur_lo.line - 1) {
@@ -226,17 +286,17 @@ impl CodeSuggestion {
                         buf.push_str(&cur_line[..end]);
                     }
                 }
-                buf.push_str(&part.snippet);
+                buf.push_str(&fm.restore_line_endings(part.span.lo(), &part.snippet));
                 prev_hi = cm.lookup_char_pos(part.span.hi());
                 prev_line = fm.get_line(prev_hi.line - 1);
             }
             // if the replacement already ends with a newline, don't print the next line
             if !buf.ends_with('\n') {
-                push_trailing(&mut buf, prev_line.as_ref(), &prev_hi, None);
-            }
-            // remove trailing newlines
-            while buf.ends_with('\n') {
-                buf.pop();
+                push_trailing(&mut buf,
+                              prev_line.as_ref(),
+                              &prev_hi,
+                              None,
+                              line_break(prev_hi.line));
             }
             (buf, substitution.parts)
         }).collect()
@@ -274,6 +334,24 @@ impl error::Error for FatalError {
     }
 }
 
+/// Used as a return value to signify that a fatal error has been reported to the user, so
+/// there is no point in continuing. Returned instead of raising `FatalError` by handlers in
+/// error-as-value mode, see `HandlerFlags::errors_as_values`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, RustcEncodable, RustcDecodable)]
+pub struct ErrorReported;
+
+impl fmt::Display for ErrorReported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "an error has been reported")
+    }
+}
+
+impl error::Error for ErrorReported {
+    fn description(&self) -> &str {
+        "An error has been reported"
+    }
+}
+
 /// Signifies that the compiler died with an explicit call to `.bug`
 /// or `.span_bug` rather than a failed assertion, etc.
 #[derive(Copy, Clone, Debug)]
@@ -292,6 +370,7 @@ impl error::Error for ExplicitBug {
 }
 
 pub use diagnostic::{Diagnostic, SubDiagnostic, DiagnosticStyledString, DiagnosticId};
+pub use diagnostic::{FeatureGate, FeatureState, ImportanceHint};
 pub use diagnostic_builder::DiagnosticBuilder;
 
 /// A handler deals with errors and other compiler output.
@@ -299,39 +378,307 @@ pub use diagnostic_builder::DiagnosticBuilder;
 /// others log errors for later reporting.
 pub struct Handler {
     flags: HandlerFlags,
+    state: Lrc<EmissionState>,
     inner: Lock<HandlerInner>,
+    /// The suppression markers found in the source, see `Handler::suppressions`.
+    suppressions: Lrc<Lock<SuppressionTable>>,
 }
 
-struct HandlerInner {
-    flags: HandlerFlags,
+/// The parts of a handler's state that are updated for every diagnostic. They're kept out of
+/// `HandlerInner`, so that threads emitting diagnostics at the same time only contend for its
+/// lock to render the diagnostics that haven't been emitted before.
+struct EmissionState {
     /// The number of errors that have been emitted, including duplicates.
     ///
     /// This is not necessarily the count that's reported to the user once
     /// compilation ends.
-    err_count: usize,
-    deduplicated_err_count: usize,
+    err_count: AtomicUsize,
+    deduplicated_err_count: AtomicUsize,
+    /// The number of diagnostics that were left out as duplicates.
+    duplicates: AtomicUsize,
+    /// The value of the next `DiagnosticToken` to hand out.
+    next_token: AtomicUsize,
+
+    /// Used to suggest rustc --explain <error code>
+    emitted_diagnostic_codes: Sharded<FxHashSet<DiagnosticId>>,
+
+    /// This set contains a hash of every diagnostic that has been emitted by
+    /// this handler. These hashes is used to avoid emitting the same error
+    /// twice.
+    emitted_diagnostics: Sharded<FxHashSet<u128>>,
+}
+
+impl EmissionState {
+    fn new() -> EmissionState {
+        EmissionState {
+            err_count: AtomicUsize::new(0),
+            deduplicated_err_count: AtomicUsize::new(0),
+            duplicates: AtomicUsize::new(0),
+            next_token: AtomicUsize::new(0),
+            emitted_diagnostic_codes: Default::default(),
+            emitted_diagnostics: Default::default(),
+        }
+    }
+
+    fn err_count(&self) -> usize {
+        self.err_count.load(SeqCst)
+    }
+
+    /// Records that `diagnostic` is emitted. Returns `false` if an equivalent diagnostic has
+    /// already been emitted, in which case it mustn't be emitted again.
+    fn record(&self, diagnostic: &Diagnostic) -> bool {
+        if let Some(ref code) = diagnostic.code {
+            let mut codes = self.emitted_diagnostic_codes.get_shard_by_value(code).lock();
+            if !codes.contains(code) {
+                codes.insert(code.clone());
+            }
+        }
+
+        let diagnostic_hash = {
+            let mut hasher = StableHasher::new();
+            diagnostic.hash(&mut hasher);
+            hasher.finish()
+        };
+        let is_new = self.emitted_diagnostics
+            .get_shard_by_value(&diagnostic_hash)
+            .lock()
+            .insert(diagnostic_hash);
+        if !is_new {
+            self.duplicates.fetch_add(1, SeqCst);
+        }
+        is_new
+    }
+
+    fn emitted_codes(&self) -> Vec<DiagnosticId> {
+        self.emitted_diagnostic_codes.lock_shards().iter()
+            .flat_map(|shard| shard.iter().cloned())
+            .collect()
+    }
+}
+
+/// Orders diagnostics buffered by parallel producers, see `Handler::diagnostic_token`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DiagnosticToken(usize);
+
+/// Empties `sets`, returning everything that was in them.
+fn take_sharded<T: Hash + Eq>(sets: &Sharded<FxHashSet<T>>) -> FxHashSet<T> {
+    sets.lock_shards().iter_mut().flat_map(|shard| shard.drain()).collect()
+}
+
+/// Replaces the contents of `sets` with `values`.
+fn restore_sharded<T: Hash + Eq>(sets: &Sharded<FxHashSet<T>>, values: FxHashSet<T>) {
+    for mut shard in sets.lock_shards() {
+        shard.clear();
+    }
+    for value in values {
+        sets.get_shard_by_value(&value).lock().insert(value);
+    }
+}
+
+struct HandlerInner {
+    flags: HandlerFlags,
+    state: Lrc<EmissionState>,
     emitter: Box<dyn Emitter + sync::Send>,
     continue_after_error: bool,
-    delayed_span_bugs: Vec<Diagnostic>,
+    delayed_span_bugs: Vec<DelayedBug>,
 
     /// This set contains the `DiagnosticId` of all emitted diagnostics to avoid
     /// emitting the same diagnostic with extended help (`--teach`) twice, which
     /// would be uneccessary repetition.
     taught_diagnostics: FxHashSet<DiagnosticId>,
 
-    /// Used to suggest rustc --explain <error code>
-    emitted_diagnostic_codes: FxHashSet<DiagnosticId>,
+    /// The suggestions emitted so far, if `HandlerFlags::audit_suggestions` is set.
+    audited_suggestions: Vec<AuditedSuggestion>,
+
+    warning_rate_limit: Option<WarningRateLimit>,
+    /// The tools whose warnings are suppressed, see `Handler::set_tool_warnings_suppressed`.
+    suppressed_tools: FxHashSet<String>,
+    /// The start of the current rate limiting interval, and the number of warnings
+    /// emitted in it.
+    warning_interval: Option<(Instant, usize)>,
+    /// The number of warnings suppressed by rate limiting and not reported yet.
+    suppressed_warnings: usize,
+
+    /// What has been emitted so far, except for the explained error codes, which are only
+    /// looked up once the summary is requested.
+    summary: SessionSummary,
+
+    /// The cache recording the diagnostics emitted, if `record_for_replay` was called, and the
+    /// source map to find the files of their spans in.
+    replay_recording: Option<(Lrc<SourceMapperDyn>, DiagnosticReplayCache)>,
+}
 
-    /// This set contains a hash of every diagnostic that has been emitted by
-    /// this handler. These hashes is used to avoid emitting the same error
-    /// twice.
+/// Limits the rate at which warnings are emitted, so that a flood of warnings doesn't
+/// drown the other output of a long-running session. The number of suppressed warnings
+/// is reported once the flood is over.
+#[derive(Copy, Clone, Debug)]
+pub struct WarningRateLimit {
+    /// The number of warnings emitted per interval, before further ones are suppressed.
+    pub max_warnings: usize,
+    pub interval: Duration,
+}
+
+/// A notification about the progress of a long-running session.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    /// What is being done, e.g. "codegen".
+    pub activity: String,
+    /// The number of units of work done so far.
+    pub done: usize,
+    /// The total number of units of work, if known.
+    pub total: Option<usize>,
+}
+
+/// What a session emitted, as summarized once it ends.
+#[derive(Clone, Debug, Default, RustcEncodable)]
+pub struct SessionSummary {
+    /// The number of diagnostics emitted at each level, not counting duplicates.
+    pub levels: BTreeMap<String, usize>,
+    /// The number of errors emitted, not counting duplicates.
+    pub errors: usize,
+    /// The number of diagnostics emitted with each error or lint code, not counting duplicates.
+    pub codes: BTreeMap<String, usize>,
+    /// The emitted error codes that have an extended explanation, in order.
+    pub explained_codes: Vec<String>,
+    /// The number of suggestions emitted with each applicability.
+    pub suggestions: BTreeMap<String, usize>,
+    /// The number of diagnostics that weren't emitted because an identical one already was.
+    pub duplicates: usize,
+    /// The number of warnings that weren't emitted because warnings are disabled or their
+    /// rate is limited.
+    pub suppressed_warnings: usize,
+    /// The number of diagnostics that weren't emitted because of a suppression marker in the
+    /// source.
+    pub silenced_by_markers: usize,
+    /// How many of `silenced_by_markers` were errors.
+    pub silenced_errors: usize,
+    /// The number of errors that were emitted as warnings instead, for backwards compatibility.
+    pub downgraded_errors: usize,
+}
+
+impl SessionSummary {
+    /// The diagnostics that end the human readable output of a session: how many errors it
+    /// aborted due to, and which of them `rustc --explain` can tell more about if
+    /// `show_explain` is set.
+    pub fn diagnostics(&self, show_explain: bool) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        if self.downgraded_errors > 0 {
+            diagnostics.push(Diagnostic::new(Note, &match self.downgraded_errors {
+                1 => "1 error was downgraded to a warning".to_string(),
+                count => format!("{} errors were downgraded to warnings", count),
+            }));
+        }
+        let aborting = match self.errors {
+            // The only errors were silenced by suppression markers.
+            0 if self.silenced_errors > 0 => "aborting due to silenced errors".to_string(),
+            0 => return diagnostics,
+            1 => "aborting due to previous error".to_string(),
+            count => format!("aborting due to {} previous errors", count)
+        };
+        diagnostics.push(Diagnostic::new(Fatal, &aborting));
+
+        let error_codes = &self.explained_codes;
+        if show_explain && !error_codes.is_empty() {
+            if error_codes.len() > 1 {
+                let limit = if error_codes.len() > 9 { 9 } else { error_codes.len() };
+                diagnostics.push(Diagnostic::new(FailureNote, &format!(
+                    "Some errors have detailed explanations: {}{}",
+                    error_codes[..limit].join(", "),
+                    if error_codes.len() > 9 { "..." } else { "." },
+                )));
+                diagnostics.push(Diagnostic::new(FailureNote, &format!(
+                    "For more information about an error, try `rustc --explain {}`.",
+                    &error_codes[0],
+                )));
+            } else {
+                diagnostics.push(Diagnostic::new(FailureNote, &format!(
+                    "For more information about this error, try `rustc --explain {}`.",
+                    &error_codes[0],
+                )));
+            }
+        }
+        diagnostics
+    }
+}
+
+/// The state of a `Handler` from before a session started, see `Handler::begin_session`.
+#[must_use = "the handler's state is only restored by passing this to `Handler::end_session`"]
+pub struct HandlerSession(SessionState);
+
+/// Everything a `Handler` remembers about the diagnostics it emitted, as opposed to its
+/// configuration.
+#[derive(Default)]
+struct SessionState {
+    err_count: usize,
+    deduplicated_err_count: usize,
+    delayed_span_bugs: Vec<DelayedBug>,
+    taught_diagnostics: FxHashSet<DiagnosticId>,
+    emitted_diagnostic_codes: FxHashSet<DiagnosticId>,
     emitted_diagnostics: FxHashSet<u128>,
+    audited_suggestions: Vec<AuditedSuggestion>,
+    warning_interval: Option<(Instant, usize)>,
+    suppressed_warnings: usize,
+    summary: SessionSummary,
+}
+
+/// A bug delayed by `delay_as_bug`, to be reported if no errors are.
+struct DelayedBug {
+    diagnostic: Diagnostic,
+    /// Where the bug was delayed from, captured if bugs were tracked then, see
+    /// `subscribers::subscribe_bugs`.
+    backtrace: Option<backtrace::Backtrace>,
+}
+
+/// Passes `diagnostic`, which is about to be emitted, to the subscribers and bug trackers.
+/// `delayed_from` is the backtrace of where it was delayed from, if it's a delayed bug;
+/// otherwise, bugs are tracked with the backtrace of where they are emitted from.
+fn track_diagnostic(diagnostic: &Diagnostic, delayed_from: Option<&backtrace::Backtrace>) {
+    subscribers::notify(diagnostic);
+
+    if diagnostic.level == Bug && subscribers::tracks_bugs() {
+        match delayed_from {
+            Some(backtrace) => subscribers::notify_bug(diagnostic, backtrace),
+            None => subscribers::notify_bug(diagnostic, &backtrace::Backtrace::new()),
+        }
+    }
 }
 
-fn default_track_diagnostic(_: &Diagnostic) {}
+/// A suggestion recorded in suggestion audit mode.
+#[derive(Clone, Debug, RustcEncodable, RustcDecodable)]
+pub struct AuditedSuggestion {
+    /// The compiler function that constructed the suggestion, with its location if known.
+    pub site: String,
+    pub msg: String,
+    pub applicability: Applicability,
+}
+
+/// Set once a handler audits suggestions, from which point on diagnostics record where their
+/// suggestions are constructed. Diagnostics don't know which handler emits them.
+static AUDIT_SUGGESTIONS: AtomicBool = AtomicBool::new(false);
 
-thread_local!(pub static TRACK_DIAGNOSTICS: Cell<fn(&Diagnostic)> =
-                Cell::new(default_track_diagnostic));
+fn audits_suggestions() -> bool {
+    AUDIT_SUGGESTIONS.load(SeqCst)
+}
+
+/// Describes the innermost caller outside of this crate, which is the code
+/// that asked for a suggestion to be added.
+fn suggestion_site() -> String {
+    let backtrace = backtrace::Backtrace::new();
+    for symbol in backtrace.frames().iter().flat_map(|frame| frame.symbols()) {
+        let name = match symbol.name() {
+            Some(name) => format!("{:#}", name),
+            None => continue,
+        };
+        if name.contains("rustc_errors::") || name.starts_with("backtrace::") {
+            continue;
+        }
+        return match (symbol.filename(), symbol.lineno()) {
+            (Some(file), Some(line)) => format!("`{}` at {}:{}", name, file.display(), line),
+            _ => format!("`{}`", name),
+        };
+    }
+    "an unknown location".to_string()
+}
 
 #[derive(Copy, Clone, Default)]
 pub struct HandlerFlags {
@@ -350,15 +697,23 @@ pub struct HandlerFlags {
     /// show macro backtraces even for non-local macros.
     /// (rustc: see `-Z external-macro-backtrace`)
     pub external_macro_backtrace: bool,
+    /// If true, record where every emitted suggestion was constructed, and report
+    /// those with an unspecified applicability.
+    /// (rustc: see `-Z audit-suggestions`)
+    pub audit_suggestions: bool,
+    /// If true, fatal errors are returned as `ErrorReported` values instead of
+    /// unwinding with `FatalError`, for consumers embedding the parser as a library.
+    /// Only paths going through `Handler::fatal_error` respect this.
+    pub errors_as_values: bool,
 }
 
 impl Drop for HandlerInner {
     fn drop(&mut self) {
-        if self.err_count == 0 {
+        if self.state.err_count() == 0 {
             let bugs = std::mem::replace(&mut self.delayed_span_bugs, Vec::new());
             let has_bugs = !bugs.is_empty();
             for bug in bugs {
-                self.emit_diagnostic(&bug);
+                self.emit_delayed_bug(&bug);
             }
             if has_bugs {
                 panic!("no errors encountered even though `delay_span_bug` issued");
@@ -407,19 +762,29 @@ impl Handler {
 
     pub fn with_emitter_and_flags(e: Box<dyn Emitter + sync::Send>, flags: HandlerFlags) -> Handler
     {
+        if flags.audit_suggestions {
+            AUDIT_SUGGESTIONS.store(true, SeqCst);
+        }
+        let state = Lrc::new(EmissionState::new());
         Handler {
             flags,
+            state: state.clone(),
             inner: Lock::new(HandlerInner {
                 flags,
-                err_count: 0,
-                deduplicated_err_count: 0,
+                state,
                 emitter: e,
                 continue_after_error: true,
                 delayed_span_bugs: Vec::new(),
                 taught_diagnostics: Default::default(),
-                emitted_diagnostic_codes: Default::default(),
-                emitted_diagnostics: Default::default(),
+                audited_suggestions: Vec::new(),
+                warning_rate_limit: None,
+                suppressed_tools: Default::default(),
+                warning_interval: None,
+                suppressed_warnings: 0,
+                summary: Default::default(),
+                replay_recording: None,
             }),
+            suppressions: Default::default(),
         }
     }
 
@@ -427,24 +792,125 @@ impl Handler {
         self.inner.borrow_mut().continue_after_error = continue_after_error;
     }
 
+    pub fn set_warning_rate_limit(&self, limit: Option<WarningRateLimit>) {
+        let mut inner = self.inner.borrow_mut();
+        inner.report_suppressed_warnings();
+        inner.warning_rate_limit = limit;
+        inner.warning_interval = None;
+    }
+
+    /// Returns the table of the suppression markers found in the source, which the parser
+    /// records them in. Diagnostics they silence aren't emitted, but silenced errors still
+    /// count as errors, as compilation can't go on past them.
+    pub fn suppressions(&self) -> Lrc<Lock<SuppressionTable>> {
+        self.suppressions.clone()
+    }
+
+    /// Sets whether the warnings emitted with a `DiagnosticId::Tool` code of `tool` are
+    /// suppressed, like all warnings are without `HandlerFlags::can_emit_warnings`.
+    pub fn set_tool_warnings_suppressed(&self, tool: &str, suppressed: bool) {
+        let mut inner = self.inner.borrow_mut();
+        if suppressed {
+            inner.suppressed_tools.insert(tool.to_string());
+        } else {
+            inner.suppressed_tools.remove(tool);
+        }
+    }
+
     // This is here to not allow mutation of flags;
     // as of this writing it's only used in tests in librustc.
     pub fn can_emit_warnings(&self) -> bool {
         self.flags.can_emit_warnings
     }
 
-    /// Resets the diagnostic error count as well as the cached emitted diagnostics.
+    /// Resets everything the handler remembers about emitted diagnostics: the error counts,
+    /// which diagnostics and codes were emitted and taught, delayed bugs, and so on. The
+    /// configuration of the handler is kept.
     ///
     /// NOTE: *do not* call this function from rustc. It is only meant to be called from external
     /// tools that want to reuse a `Parser` cleaning the previously emitted diagnostics as well as
-    /// the overall count of emitted error diagnostics.
-    // FIXME: this does not clear inner entirely
+    /// the overall count of emitted error diagnostics. Prefer `begin_session` and `end_session`,
+    /// which don't lose the previous state.
     pub fn reset_err_count(&self) {
+        self.inner.borrow_mut().take_session_state();
+    }
+
+    /// Starts a new session on this handler, for tools that reuse it for many inputs. The
+    /// handler starts out as if nothing had been emitted yet, and the state it had before is
+    /// saved in the returned `HandlerSession`, to be restored by `end_session`.
+    pub fn begin_session(&self) -> HandlerSession {
+        HandlerSession(self.inner.borrow_mut().take_session_state())
+    }
+
+    /// Ends the session started by the `begin_session` call that returned `session`, restoring
+    /// the state the handler had then. If no errors were emitted during the session, its delayed
+    /// bugs are emitted and this panics once the state is restored, as dropping the handler would.
+    pub fn end_session(&self, session: HandlerSession) {
         let mut inner = self.inner.borrow_mut();
-        // actually frees the underlying memory (which `clear` would not do)
-        inner.emitted_diagnostics = Default::default();
-        inner.deduplicated_err_count = 0;
-        inner.err_count = 0;
+        let ended = inner.take_session_state();
+        let has_bugs = ended.err_count == 0 && !ended.delayed_span_bugs.is_empty();
+        if has_bugs {
+            for bug in &ended.delayed_span_bugs {
+                inner.emit_delayed_bug(bug);
+            }
+        }
+        inner.restore_session_state(session.0);
+        drop(inner);
+
+        if has_bugs {
+            panic!("no errors encountered even though `delay_span_bug` issued");
+        }
+    }
+
+    /// Starts recording the diagnostics emitted from now on into `cache`, to be replayed in a
+    /// later session. See the `replay` module.
+    pub fn record_for_replay(
+        &self,
+        source_map: Lrc<SourceMapperDyn>,
+        cache: DiagnosticReplayCache,
+    ) {
+        self.inner.borrow_mut().replay_recording = Some((source_map, cache));
+    }
+
+    /// Marks `source_file` as compiled in the cache being recorded, if any, meaning that all
+    /// its diagnostics have been emitted.
+    pub fn mark_compiled_for_replay(&self, source_file: &SourceFile) {
+        if let Some((_, ref mut cache)) = self.inner.borrow_mut().replay_recording {
+            cache.mark_compiled(source_file);
+        }
+    }
+
+    /// Stops recording diagnostics for replay, and returns the cache they were recorded into.
+    pub fn take_replay_cache(&self) -> Option<DiagnosticReplayCache> {
+        self.inner.borrow_mut().replay_recording.take().map(|(_, cache)| cache)
+    }
+
+    /// Emits the diagnostics recorded for `source_file` in `cache`, with their spans pointing
+    /// into `source_file`. Returns `false`, without emitting anything, if the diagnostics of
+    /// `source_file` weren't recorded, in which case it has to be compiled instead.
+    ///
+    /// If diagnostics are being recorded, `source_file` is marked as compiled in that cache too.
+    pub fn replay_diagnostics(
+        &self,
+        cache: &DiagnosticReplayCache,
+        source_file: &SourceFile,
+    ) -> bool {
+        let diagnostics = match cache.diagnostics(source_file) {
+            Some(diagnostics) => diagnostics,
+            None => return false,
+        };
+        for diagnostic in &diagnostics {
+            self.emit_diagnostic(diagnostic);
+        }
+        self.mark_compiled_for_replay(source_file);
+        true
+    }
+
+    /// Emits the diagnostics recorded in `cache` that aren't about any file of the crate.
+    pub fn replay_crate_diagnostics(&self, cache: &DiagnosticReplayCache) {
+        for diagnostic in cache.crate_diagnostics() {
+            self.emit_diagnostic(diagnostic);
+        }
     }
 
     pub fn struct_dummy(&self) -> DiagnosticBuilder<'_> {
@@ -617,21 +1083,100 @@ impl Handler {
     }
 
     pub fn err_count(&self) -> usize {
-        self.inner.borrow().err_count
+        self.state.err_count()
     }
 
     pub fn has_errors(&self) -> bool {
         self.err_count() > 0
     }
 
-    pub fn print_error_count(&self, registry: &Registry) {
-        self.inner.borrow_mut().print_error_count(registry)
+    /// Summarizes what has been emitted so far.
+    pub fn session_summary(&self, registry: &Registry) -> SessionSummary {
+        self.inner.borrow().session_summary(registry)
+    }
+
+    /// Passes the summary of what has been emitted so far to the emitter, which ends its output
+    /// with it, see `SessionSummary::diagnostics`.
+    pub fn emit_session_summary(&self, registry: &Registry) {
+        self.inner.borrow_mut().emit_session_summary(registry)
+    }
+
+    /// Records that an error was emitted as a warning instead, for the session summary.
+    pub fn record_downgraded_error(&self) {
+        self.inner.borrow_mut().summary.downgraded_errors += 1;
+    }
+
+    /// Lets the emitter emit the diagnostics it held back until the end of the session.
+    pub fn flush_diagnostics(&self) {
+        self.inner.borrow_mut().emitter.flush_diagnostics();
+    }
+
+    /// Returns the suggestions of the diagnostics emitted so far, in emission order.
+    /// Only suggestions constructed in audit mode are recorded.
+    pub fn audited_suggestions(&self) -> Vec<AuditedSuggestion> {
+        self.inner.borrow().audited_suggestions.clone()
+    }
+
+    /// Returns the recorded suggestions that were given `Applicability::Unspecified`.
+    pub fn unspecified_suggestions(&self) -> Vec<AuditedSuggestion> {
+        self.inner.borrow().audited_suggestions.iter()
+            .filter(|suggestion| suggestion.applicability == Applicability::Unspecified)
+            .cloned()
+            .collect()
+    }
+
+    /// Emits a note for every recorded suggestion with an unspecified applicability.
+    pub fn print_suggestion_audit(&self) {
+        for suggestion in self.unspecified_suggestions() {
+            let mut db = DiagnosticBuilder::new(self, Note, &format!(
+                "suggestion with unspecified applicability constructed in {}",
+                suggestion.site,
+            ));
+            db.note(&format!("the suggestion message is: {}", suggestion.msg));
+            db.emit();
+        }
     }
 
     pub fn abort_if_errors(&self) {
         self.inner.borrow().abort_if_errors()
     }
 
+    /// Like `abort_if_errors`, but returns `ErrorReported` instead of unwinding if the
+    /// handler operates in error-as-value mode.
+    pub fn try_abort_if_errors(&self) -> Result<(), ErrorReported> {
+        if self.has_errors() {
+            Err(self.fatal_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Signals that a fatal error has been reported. Raises `FatalError`, unless the handler
+    /// operates in error-as-value mode, in which case the caller has to propagate the
+    /// returned `ErrorReported`.
+    pub fn fatal_error(&self) -> ErrorReported {
+        if !self.flags.errors_as_values {
+            FatalError.raise();
+        }
+        ErrorReported
+    }
+
+    /// Runs `f`, turning fatal errors raised within it into `ErrorReported` if the handler
+    /// operates in error-as-value mode. This covers the code paths that can't propagate
+    /// `ErrorReported` themselves yet, and doesn't involve the panic hook.
+    pub fn catch_fatal_errors<F: FnOnce() -> R, R>(&self, f: F) -> Result<R, ErrorReported> {
+        if !self.flags.errors_as_values {
+            return Ok(f());
+        }
+        panic::catch_unwind(panic::AssertUnwindSafe(f)).map_err(|value| {
+            if value.is::<FatalErrorMarker>() {
+                ErrorReported
+            } else {
+                panic::resume_unwind(value)
+            }
+        })
+    }
+
     pub fn abort_if_errors_and_should_abort(&self) {
         self.inner.borrow().abort_if_errors_and_should_abort()
     }
@@ -645,19 +1190,116 @@ impl Handler {
     }
 
     pub fn emit_diagnostic(&self, diagnostic: &Diagnostic) {
-        self.inner.borrow_mut().emit_diagnostic(diagnostic)
+        if !diagnostic.cancelled() && diagnostic.level != Bug &&
+            self.suppressions.borrow().suppresses(diagnostic) {
+            if diagnostic.is_error() {
+                self.state.err_count.fetch_add(1, SeqCst);
+            }
+            let mut inner = self.inner.borrow_mut();
+            inner.summary.silenced_by_markers += 1;
+            if diagnostic.is_error() {
+                inner.summary.silenced_errors += 1;
+            }
+            return;
+        }
+
+        // Warnings are subject to rate limiting, which takes the lock anyway.
+        if diagnostic.cancelled() || diagnostic.level == Warning {
+            return self.inner.borrow_mut().emit_diagnostic(diagnostic);
+        }
+
+        track_diagnostic(diagnostic, None);
+        if self.state.record(diagnostic) {
+            self.inner.borrow_mut().emit_new_diagnostic(diagnostic);
+        }
+        if diagnostic.is_error() {
+            self.state.err_count.fetch_add(1, SeqCst);
+            if self.flags.treat_err_as_bug.is_some() {
+                self.inner.borrow().panic_if_treat_err_as_bug();
+            }
+        }
+    }
+
+    /// Hands out a token that's ordered after all the tokens handed out before by this handler.
+    ///
+    /// Work that may run in parallel takes a token for each of its units before starting them,
+    /// in a deterministic order, and tags the diagnostics of each unit with its token using
+    /// `DiagnosticBuilder::buffer_ordered`. Once the units are done, `emit_ordered` emits the
+    /// diagnostics in the same order as if the units had run one after the other, while the
+    /// units never have to wait for each other to buffer a diagnostic.
+    pub fn diagnostic_token(&self) -> DiagnosticToken {
+        DiagnosticToken(self.state.next_token.fetch_add(1, SeqCst))
+    }
+
+    /// Emits diagnostics buffered with `DiagnosticBuilder::buffer_ordered`, in the order of
+    /// their tokens. Diagnostics with the same token are emitted in the order they're given in.
+    pub fn emit_ordered(
+        &self,
+        buffered: impl IntoIterator<Item = (DiagnosticToken, Diagnostic)>,
+    ) {
+        let mut buffered: Vec<_> = buffered.into_iter().collect();
+        // A stable sort, to keep the order of the diagnostics of each token.
+        buffered.sort_by_key(|&(token, _)| token);
+        for (_, diagnostic) in buffered {
+            self.emit_diagnostic(&diagnostic);
+        }
     }
 
     pub fn emit_artifact_notification(&self, path: &Path, artifact_type: &str) {
         self.inner.borrow_mut().emit_artifact_notification(path, artifact_type)
     }
 
+    pub fn emit_progress(&self, progress: &Progress) {
+        self.inner.borrow_mut().emit_progress(progress)
+    }
+
+    /// Reports the number of warnings suppressed by rate limiting so far, if any.
+    pub fn report_suppressed_warnings(&self) {
+        self.inner.borrow_mut().report_suppressed_warnings()
+    }
+
     pub fn delay_as_bug(&self, diagnostic: Diagnostic) {
         self.inner.borrow_mut().delay_as_bug(diagnostic)
     }
 }
 
 impl HandlerInner {
+    /// Takes the state of the current session, leaving that of a fresh one behind.
+    fn take_session_state(&mut self) -> SessionState {
+        fn take<T: Default>(value: &mut T) -> T {
+            std::mem::replace(value, T::default())
+        }
+        SessionState {
+            err_count: self.state.err_count.swap(0, SeqCst),
+            deduplicated_err_count: self.state.deduplicated_err_count.swap(0, SeqCst),
+            delayed_span_bugs: take(&mut self.delayed_span_bugs),
+            taught_diagnostics: take(&mut self.taught_diagnostics),
+            emitted_diagnostic_codes: take_sharded(&self.state.emitted_diagnostic_codes),
+            emitted_diagnostics: take_sharded(&self.state.emitted_diagnostics),
+            audited_suggestions: take(&mut self.audited_suggestions),
+            warning_interval: take(&mut self.warning_interval),
+            suppressed_warnings: take(&mut self.suppressed_warnings),
+            summary: SessionSummary {
+                duplicates: self.state.duplicates.swap(0, SeqCst),
+                ..take(&mut self.summary)
+            },
+        }
+    }
+
+    fn restore_session_state(&mut self, state: SessionState) {
+        self.state.err_count.store(state.err_count, SeqCst);
+        self.state.deduplicated_err_count.store(state.deduplicated_err_count, SeqCst);
+        self.delayed_span_bugs = state.delayed_span_bugs;
+        self.taught_diagnostics = state.taught_diagnostics;
+        restore_sharded(&self.state.emitted_diagnostic_codes, state.emitted_diagnostic_codes);
+        restore_sharded(&self.state.emitted_diagnostics, state.emitted_diagnostics);
+        self.audited_suggestions = state.audited_suggestions;
+        self.warning_interval = state.warning_interval;
+        self.suppressed_warnings = state.suppressed_warnings;
+        self.state.duplicates.store(state.summary.duplicates, SeqCst);
+        self.summary = state.summary;
+    }
+
     /// `true` if we haven't taught a diagnostic with this code already.
     /// The caller must then teach the user about such a diagnostic.
     ///
@@ -672,102 +1314,163 @@ impl HandlerInner {
     }
 
     fn emit_diagnostic(&mut self, diagnostic: &Diagnostic) {
+        self.emit_diagnostic_delayed_from(diagnostic, None)
+    }
+
+    /// Emits a bug delayed by `delay_as_bug`, tracking it with where it was delayed from.
+    fn emit_delayed_bug(&mut self, bug: &DelayedBug) {
+        self.emit_diagnostic_delayed_from(&bug.diagnostic, bug.backtrace.as_ref())
+    }
+
+    fn emit_diagnostic_delayed_from(
+        &mut self,
+        diagnostic: &Diagnostic,
+        delayed_from: Option<&backtrace::Backtrace>,
+    ) {
         if diagnostic.cancelled() {
             return;
         }
 
         if diagnostic.level == Warning && !self.flags.can_emit_warnings {
+            self.summary.suppressed_warnings += 1;
             return;
         }
 
-        TRACK_DIAGNOSTICS.with(|track_diagnostics| {
-            track_diagnostics.get()(diagnostic);
-        });
-
-        if let Some(ref code) = diagnostic.code {
-            self.emitted_diagnostic_codes.insert(code.clone());
+        let tool = diagnostic.code.as_ref().and_then(|code| code.tool_name());
+        if diagnostic.level == Warning && tool.map_or(false, |tool| {
+            self.suppressed_tools.contains(tool)
+        }) {
+            self.summary.suppressed_warnings += 1;
+            return;
         }
 
-        let diagnostic_hash = {
-            use std::hash::Hash;
-            let mut hasher = StableHasher::new();
-            diagnostic.hash(&mut hasher);
-            hasher.finish()
-        };
+        if diagnostic.level == Warning && self.rate_limit_warning() {
+            self.summary.suppressed_warnings += 1;
+            return;
+        }
 
-        // Only emit the diagnostic if we haven't already emitted an equivalent
-        // one:
-        if self.emitted_diagnostics.insert(diagnostic_hash) {
-            self.emitter.emit_diagnostic(diagnostic);
-            if diagnostic.is_error() {
-                self.deduplicated_err_count += 1;
-            }
+        track_diagnostic(diagnostic, delayed_from);
+        if self.state.record(diagnostic) {
+            self.emit_new_diagnostic(diagnostic);
         }
         if diagnostic.is_error() {
             self.bump_err_count();
         }
     }
 
-    fn emit_artifact_notification(&mut self, path: &Path, artifact_type: &str) {
-        self.emitter.emit_artifact_notification(path, artifact_type);
+    /// Renders a diagnostic that `EmissionState::record` has found not to have been emitted
+    /// before.
+    fn emit_new_diagnostic(&mut self, diagnostic: &Diagnostic) {
+        self.emitter.emit_diagnostic(diagnostic);
+        if let Some((ref source_map, ref mut cache)) = self.replay_recording {
+            cache.record(&**source_map, diagnostic);
+        }
+        if diagnostic.is_error() {
+            self.state.deduplicated_err_count.fetch_add(1, SeqCst);
+        }
+        self.summarize(diagnostic);
+        if self.flags.audit_suggestions {
+            self.audited_suggestions.extend(diagnostic.audited_suggestions.0.iter().cloned());
+        }
     }
 
-    fn treat_err_as_bug(&self) -> bool {
-        self.flags.treat_err_as_bug.map(|c| self.err_count >= c).unwrap_or(false)
+    /// Counts `diagnostic` in the session summary.
+    fn summarize(&mut self, diagnostic: &Diagnostic) {
+        let level = match diagnostic.level {
+            Bug => "bug",
+            level => level.to_str(),
+        };
+        *self.summary.levels.entry(level.to_string()).or_insert(0) += 1;
+        if let Some(ref code) = diagnostic.code {
+            *self.summary.codes.entry(code.to_string()).or_insert(0) += 1;
+        }
+        for suggestion in &diagnostic.suggestions {
+            let applicability = format!("{:?}", suggestion.applicability);
+            *self.summary.suggestions.entry(applicability).or_insert(0) += 1;
+        }
     }
 
-    fn print_error_count(&mut self, registry: &Registry) {
-        let s = match self.deduplicated_err_count {
-            0 => return,
-            1 => "aborting due to previous error".to_string(),
-            count => format!("aborting due to {} previous errors", count)
-        };
+    fn session_summary(&self, registry: &Registry) -> SessionSummary {
+        let mut explained_codes = self.state.emitted_codes().into_iter()
+            .filter_map(|code| match code {
+                DiagnosticId::Error(_) | DiagnosticId::Tool { .. }
+                    if registry.describe(&code).is_some() => Some(code.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        explained_codes.sort();
+        SessionSummary {
+            errors: self.state.deduplicated_err_count.load(SeqCst),
+            explained_codes,
+            duplicates: self.state.duplicates.load(SeqCst),
+            ..self.summary.clone()
+        }
+    }
+
+    fn emit_session_summary(&mut self, registry: &Registry) {
+        self.report_suppressed_warnings();
         if self.treat_err_as_bug() {
             return;
         }
+        let summary = self.session_summary(registry);
+        self.emitter.emit_session_summary(&summary);
+    }
+
+    fn emit_artifact_notification(&mut self, path: &Path, artifact_type: &str) {
+        self.emitter.emit_artifact_notification(path, artifact_type);
+    }
 
-        let _ = self.fatal(&s);
+    fn emit_progress(&mut self, progress: &Progress) {
+        self.emitter.emit_progress(progress);
+    }
 
-        let can_show_explain = self.emitter.should_show_explain();
-        let are_there_diagnostics = !self.emitted_diagnostic_codes.is_empty();
-        if can_show_explain && are_there_diagnostics {
-            let mut error_codes = self
-                .emitted_diagnostic_codes
-                .iter()
-                .filter_map(|x| match &x {
-                    DiagnosticId::Error(s) if registry.find_description(s).is_some() => {
-                        Some(s.clone())
-                    }
-                    _ => None,
-                })
-                .collect::<Vec<_>>();
-            if !error_codes.is_empty() {
-                error_codes.sort();
-                if error_codes.len() > 1 {
-                    let limit = if error_codes.len() > 9 { 9 } else { error_codes.len() };
-                    self.failure(&format!("Some errors have detailed explanations: {}{}",
-                                          error_codes[..limit].join(", "),
-                                          if error_codes.len() > 9 { "..." } else { "." }));
-                    self.failure(&format!("For more information about an error, try \
-                                           `rustc --explain {}`.",
-                                          &error_codes[0]));
-                } else {
-                    self.failure(&format!("For more information about this error, try \
-                                           `rustc --explain {}`.",
-                                          &error_codes[0]));
+    /// Returns whether the warning about to be emitted has to be suppressed because of
+    /// the warning rate limit.
+    fn rate_limit_warning(&mut self) -> bool {
+        let limit = match self.warning_rate_limit {
+            Some(limit) => limit,
+            None => return false,
+        };
+        let now = Instant::now();
+        match self.warning_interval {
+            Some((start, ref mut count)) if now.duration_since(start) < limit.interval => {
+                if *count >= limit.max_warnings {
+                    self.suppressed_warnings += 1;
+                    return true;
                 }
+                *count += 1;
+            }
+            _ => {
+                self.report_suppressed_warnings();
+                self.warning_interval = Some((now, 1));
             }
         }
+        false
+    }
+
+    fn report_suppressed_warnings(&mut self) {
+        let suppressed = std::mem::replace(&mut self.suppressed_warnings, 0);
+        if suppressed > 0 {
+            self.emitter.emit_diagnostic(&Diagnostic::new(Note, &format!(
+                "{} warning{} suppressed to limit the rate of warnings",
+                suppressed,
+                crate::pluralise!(suppressed),
+            )));
+        }
+    }
+
+    fn treat_err_as_bug(&self) -> bool {
+        self.flags.treat_err_as_bug.map(|c| self.state.err_count() >= c).unwrap_or(false)
     }
 
     fn abort_if_errors_and_should_abort(&self) {
-        if self.err_count > 0 && !self.continue_after_error {
+        if self.state.err_count() > 0 && !self.continue_after_error {
             FatalError.raise();
         }
     }
 
     fn abort_if_errors(&self) {
-        if self.err_count > 0 {
+        if self.state.err_count() > 0 {
             FatalError.raise();
         }
     }
@@ -816,17 +1519,22 @@ impl HandlerInner {
         if self.flags.report_delayed_bugs {
             self.emit_diagnostic(&diagnostic);
         }
-        self.delayed_span_bugs.push(diagnostic);
+        let backtrace = if subscribers::tracks_bugs() {
+            Some(backtrace::Backtrace::new())
+        } else {
+            None
+        };
+        self.delayed_span_bugs.push(DelayedBug { diagnostic, backtrace });
     }
 
     fn bump_err_count(&mut self) {
-        self.err_count += 1;
+        self.state.err_count.fetch_add(1, SeqCst);
         self.panic_if_treat_err_as_bug();
     }
 
     fn panic_if_treat_err_as_bug(&self) {
         if self.treat_err_as_bug() {
-            let s = match (self.err_count, self.flags.treat_err_as_bug.unwrap_or(0)) {
+            let s = match (self.state.err_count(), self.flags.treat_err_as_bug.unwrap_or(0)) {
                 (0, _) => return,
                 (1, 1) => "aborting due to `-Z treat-err-as-bug=1`".to_string(),
                 (1, _) => return,
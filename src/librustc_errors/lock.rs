@@ -8,8 +8,139 @@
 //! only when targeting 32-bit MSVC.
 //!
 //! For more information about why this is necessary, see where this is called.
+//! The lock is only taken when writing to a legacy Windows console, which is
+//! styled through the console API and can't be written to atomically. Whether
+//! that's the case is determined by `stderr_terminal`.
+//!
+//! Within a single process, writes to the terminal are additionally serialized by
+//! `acquire_output_lock`, so that a terminal UI drawing a progress bar from another thread
+//! can interleave it with diagnostics without corrupting either.
+
+use lazy_static::lazy_static;
 
 use std::any::Any;
+use std::sync::{Mutex, MutexGuard};
+
+/// Lets a terminal UI sharing the output with the emitted diagnostics, e.g. to draw a
+/// progress bar, get out of the way while diagnostics are written.
+pub trait OutputInterleaver: Send {
+    /// Called before diagnostics are written, e.g. to clear the progress bar.
+    fn suspend(&mut self);
+    /// Called after diagnostics have been written, e.g. to redraw the progress bar.
+    fn resume(&mut self);
+}
+
+lazy_static! {
+    static ref OUTPUT_LOCK: Mutex<Option<Box<dyn OutputInterleaver>>> = Mutex::new(None);
+}
+
+/// Holds the output lock of this process, see `acquire_output_lock`.
+pub struct OutputLock {
+    interleaver: MutexGuard<'static, Option<Box<dyn OutputInterleaver>>>,
+}
+
+/// Acquires the lock serializing writes to the terminal within this process. Terminal UIs
+/// should hold it while drawing to the terminal the diagnostics are written to.
+pub fn acquire_output_lock() -> OutputLock {
+    // A panic while writing doesn't leave anything inconsistent behind.
+    let interleaver = OUTPUT_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    OutputLock { interleaver }
+}
+
+/// Installs the interleaver which is suspended while diagnostics are written in this process,
+/// replacing the previous one.
+pub fn set_output_interleaver(interleaver: Option<Box<dyn OutputInterleaver>>) {
+    *acquire_output_lock().interleaver = interleaver;
+}
+
+/// Holds the output lock while diagnostics are written, with the interleaver suspended.
+crate struct DiagnosticOutputLock(OutputLock);
+
+impl Drop for DiagnosticOutputLock {
+    fn drop(&mut self) {
+        if let Some(interleaver) = (self.0).interleaver.as_mut() {
+            interleaver.resume();
+        }
+    }
+}
+
+/// Acquires the output lock of this process to write diagnostics, suspending the
+/// interleaver until the returned guard is dropped.
+crate fn acquire_diagnostic_output_lock() -> DiagnosticOutputLock {
+    let mut lock = acquire_output_lock();
+    if let Some(interleaver) = lock.interleaver.as_mut() {
+        interleaver.suspend();
+    }
+    DiagnosticOutputLock(lock)
+}
+
+/// The kind of terminal standard error is connected to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TerminalKind {
+    /// Not a terminal, e.g. a pipe or a file.
+    NotATerminal,
+    /// A terminal understanding ANSI escape sequences. On Windows, this includes mintty
+    /// and other Cygwin/MSYS ptys, as well as consoles which support virtual terminal
+    /// sequences, like those hosted by ConPTY.
+    Ansi,
+    /// A legacy Windows console, which is styled through the console API.
+    WindowsConsole,
+}
+
+lazy_static! {
+    static ref STDERR_TERMINAL: TerminalKind = detect_stderr_terminal();
+}
+
+/// Returns the kind of terminal standard error is connected to. On Windows consoles, this
+/// enables ANSI escape sequences if the console supports them.
+pub fn stderr_terminal() -> TerminalKind {
+    *STDERR_TERMINAL
+}
+
+#[cfg(windows)]
+#[allow(nonstandard_style)]
+fn detect_stderr_terminal() -> TerminalKind {
+    type BOOL = i32;
+    type DWORD = u32;
+    type HANDLE = *mut u8;
+
+    const STD_ERROR_HANDLE: DWORD = -12i32 as DWORD;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: DWORD = 0x0004;
+
+    extern "system" {
+        fn GetStdHandle(nStdHandle: DWORD) -> HANDLE;
+        fn GetConsoleMode(hConsoleHandle: HANDLE, lpMode: *mut DWORD) -> BOOL;
+        fn SetConsoleMode(hConsoleHandle: HANDLE, dwMode: DWORD) -> BOOL;
+    }
+
+    if !atty::is(atty::Stream::Stderr) {
+        return TerminalKind::NotATerminal;
+    }
+    unsafe {
+        let handle = GetStdHandle(STD_ERROR_HANDLE);
+        let mut mode = 0;
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            // `atty` recognizes the named pipes of mintty and other Cygwin/MSYS ptys
+            // as terminals, but they aren't consoles.
+            return TerminalKind::Ansi;
+        }
+        if mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0 ||
+           SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0 {
+            TerminalKind::Ansi
+        } else {
+            TerminalKind::WindowsConsole
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn detect_stderr_terminal() -> TerminalKind {
+    if atty::is(atty::Stream::Stderr) {
+        TerminalKind::Ansi
+    } else {
+        TerminalKind::NotATerminal
+    }
+}
 
 #[cfg(windows)]
 #[allow(nonstandard_style)]
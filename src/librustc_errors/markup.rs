@@ -0,0 +1,97 @@
+//! Lightweight markup in diagnostic messages: inline code, bullet lists and code blocks.
+//!
+//! A marked up message is stored as styled parts, like a highlighted message. The human
+//! readable emitter renders it with `terminal_parts`, while `to_markdown` renders it as
+//! Markdown, which is what `Diagnostic::message` returns and the JSON emitter reports.
+
+use crate::snippet::Style;
+
+/// A message built from paragraphs, bullet list items and code blocks, each starting on a
+/// new line.
+#[derive(Clone, Debug, Default)]
+pub struct Markup {
+    parts: Vec<(String, Style)>,
+}
+
+impl Markup {
+    pub fn new() -> Markup {
+        Markup::default()
+    }
+
+    /// Appends a paragraph, in which text between backticks is inline code.
+    pub fn text(mut self, text: &str) -> Markup {
+        self.start_block();
+        self.push_inline(text);
+        self
+    }
+
+    /// Appends a bullet list item, in which text between backticks is inline code.
+    pub fn item(mut self, text: &str) -> Markup {
+        self.start_block();
+        self.parts.push(("- ".to_string(), Style::ListItem));
+        self.push_inline(text);
+        self
+    }
+
+    /// Appends a block of code.
+    pub fn code_block(mut self, code: &str) -> Markup {
+        self.start_block();
+        self.parts.push((code.trim_matches('\n').to_string(), Style::CodeBlock));
+        self
+    }
+
+    pub fn into_parts(self) -> Vec<(String, Style)> {
+        self.parts
+    }
+
+    fn start_block(&mut self) {
+        if !self.parts.is_empty() {
+            self.parts.push(("\n".to_string(), Style::NoStyle));
+        }
+    }
+
+    fn push_inline(&mut self, text: &str) {
+        // Every other segment is enclosed in backticks.
+        for (i, segment) in text.split('`').enumerate() {
+            if !segment.is_empty() {
+                let style = if i % 2 == 1 { Style::InlineCode } else { Style::NoStyle };
+                self.parts.push((segment.to_string(), style));
+            }
+        }
+    }
+}
+
+/// Renders the markup in `msg` for terminals: inline code is quoted with backticks, like
+/// in other messages, and code blocks are indented.
+crate fn terminal_parts(msg: &[(String, Style)]) -> Vec<(String, Style)> {
+    msg.iter().map(|&(ref text, style)| match style {
+        Style::InlineCode => (format!("`{}`", text), Style::NoStyle),
+        Style::ListItem => (text.clone(), Style::NoStyle),
+        Style::CodeBlock => {
+            let lines = text.lines().map(|line| format!("    {}", line)).collect::<Vec<_>>();
+            (lines.join("\n"), Style::NoStyle)
+        }
+        _ => (text.clone(), style),
+    }).collect()
+}
+
+/// Renders `msg` as Markdown. Messages without markup are returned as is.
+pub fn to_markdown(msg: &[(String, Style)]) -> String {
+    let mut markdown = String::new();
+    for &(ref text, style) in msg {
+        match style {
+            Style::InlineCode => {
+                markdown.push('`');
+                markdown.push_str(text);
+                markdown.push('`');
+            }
+            Style::CodeBlock => {
+                markdown.push_str("```\n");
+                markdown.push_str(text);
+                markdown.push_str("\n```");
+            }
+            _ => markdown.push_str(text),
+        }
+    }
+    markdown
+}
@@ -1,16 +1,50 @@
+use crate::DiagnosticId;
 use rustc_data_structures::fx::FxHashMap;
 
 #[derive(Clone)]
 pub struct Registry {
     descriptions: FxHashMap<&'static str, &'static str>,
+    /// The descriptions of the codes of external tools, by tool.
+    tool_descriptions: FxHashMap<&'static str, FxHashMap<&'static str, &'static str>>,
 }
 
 impl Registry {
     pub fn new(descriptions: &[(&'static str, &'static str)]) -> Registry {
-        Registry { descriptions: descriptions.iter().cloned().collect() }
+        Registry {
+            descriptions: descriptions.iter().cloned().collect(),
+            tool_descriptions: Default::default(),
+        }
     }
 
+    /// Adds the descriptions of the codes `tool` emits its diagnostics with, as
+    /// `DiagnosticId::Tool` codes.
+    pub fn register_tool(
+        &mut self,
+        tool: &'static str,
+        descriptions: &[(&'static str, &'static str)],
+    ) {
+        self.tool_descriptions.entry(tool).or_default().extend(descriptions.iter().cloned());
+    }
+
+    /// Finds the description of `code`, which is either one of rustc's codes or a namespaced
+    /// `tool::name` code.
     pub fn find_description(&self, code: &str) -> Option<&'static str> {
-        self.descriptions.get(code).cloned()
+        match code.find("::") {
+            Some(i) => self.find_tool_description(&code[..i], &code[i + 2..]),
+            None => self.descriptions.get(code).cloned(),
+        }
+    }
+
+    pub fn find_tool_description(&self, tool: &str, name: &str) -> Option<&'static str> {
+        self.tool_descriptions.get(tool)?.get(name).cloned()
+    }
+
+    pub fn describe(&self, code: &DiagnosticId) -> Option<&'static str> {
+        match *code {
+            DiagnosticId::Error(ref code) | DiagnosticId::Lint(ref code) => {
+                self.descriptions.get(&**code).cloned()
+            }
+            DiagnosticId::Tool { ref tool, ref name } => self.find_tool_description(tool, name),
+        }
     }
 }
@@ -0,0 +1,156 @@
+//! Replaying the diagnostics of source files that haven't changed since they were compiled.
+//!
+//! While a `Handler` records for replay, every diagnostic it emits is stored under the source
+//! file its first span points into, keyed by the stable id of that file and the hash of its
+//! source. Once the front end is done with a file, it's marked as compiled, and the cache can
+//! be persisted. A later session that finds the same file with the same hash can then replay
+//! its diagnostics with `Handler::replay_diagnostics` instead of running the front end on it
+//! again.
+//!
+//! Spans are stored relative to the start of their file, so replayed diagnostics point at the
+//! same code no matter where the file ends up in the `SourceMap`. Their syntax contexts aren't
+//! stored, so replayed diagnostics don't have macro backtraces. A diagnostic with spans in
+//! more than one file can't be replayed on its own, so the file it's stored under is never
+//! considered compiled. Diagnostics without spans are stored for the whole crate, and are
+//! replayed with `Handler::replay_crate_diagnostics`. Diagnostics pointing first into a file
+//! imported from another crate make the whole crate unreplayable.
+
+use crate::{Diagnostic, SourceMapperDyn};
+
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_data_structures::sync::Lrc;
+use syntax_pos::{BytePos, SourceFile, Span, StableSourceFileId, DUMMY_SP};
+
+/// Identifies a version of a source file: its stable id and the hash of its source.
+type FileKey = (StableSourceFileId, u128);
+
+fn file_key(source_file: &SourceFile) -> FileKey {
+    (StableSourceFileId::new(source_file), source_file.src_hash)
+}
+
+/// The diagnostics of compiled source files, keyed by their id and the hash of their source.
+#[derive(Clone, Debug, Default, RustcEncodable, RustcDecodable)]
+pub struct DiagnosticReplayCache {
+    /// The diagnostics of each file marked as compiled, or seen so far for files that aren't
+    /// marked yet. Their spans are stored by `relative_span`.
+    diagnostics: FxHashMap<FileKey, Vec<Diagnostic>>,
+    compiled: FxHashSet<FileKey>,
+    /// Files with diagnostics that can't be replayed.
+    unreplayable: FxHashSet<FileKey>,
+    /// Diagnostics without spans, which are about the crate rather than any of its files.
+    crate_diagnostics: Vec<Diagnostic>,
+    /// Whether a diagnostic that can't be stored under any file of the crate was emitted.
+    crate_unreplayable: bool,
+}
+
+impl DiagnosticReplayCache {
+    pub fn new() -> DiagnosticReplayCache {
+        Default::default()
+    }
+
+    /// Returns `true` if the diagnostics of `source_file` can be replayed, because a file
+    /// with the same source was compiled while the cache was recorded.
+    pub fn contains(&self, source_file: &SourceFile) -> bool {
+        self.compiled.contains(&file_key(source_file))
+    }
+
+    /// Returns `false` if some diagnostics of the crate weren't stored under any of its files,
+    /// so that replaying the diagnostics of all its files would lose them.
+    pub fn is_crate_replayable(&self) -> bool {
+        !self.crate_unreplayable
+    }
+
+    /// Marks `source_file` as compiled, meaning that all its diagnostics have been recorded.
+    /// Does nothing if some of its diagnostics can't be replayed.
+    pub fn mark_compiled(&mut self, source_file: &SourceFile) {
+        let key = file_key(source_file);
+        if !self.unreplayable.contains(&key) {
+            self.compiled.insert(key);
+        }
+    }
+
+    /// Forgets about `source_file`, e.g. because it's about to be compiled again.
+    pub fn remove(&mut self, source_file: &SourceFile) {
+        let key = file_key(source_file);
+        self.diagnostics.remove(&key);
+        self.compiled.remove(&key);
+        self.unreplayable.remove(&key);
+    }
+
+    /// Stores `diagnostic` under the file its first span points into, or for the whole crate
+    /// if it has no spans.
+    crate fn record(&mut self, source_map: &SourceMapperDyn, diagnostic: &Diagnostic) {
+        let mut source_file: Option<Lrc<SourceFile>> = None;
+        let mut replayable = true;
+        let mut relative = diagnostic.clone();
+        relative.map_spans(|span| {
+            if span.is_dummy() {
+                return DUMMY_SP;
+            }
+            let data = span.data();
+            let file = source_file.get_or_insert_with(|| source_map.lookup_char_pos(data.lo).file);
+            if !file.contains(data.lo) || !file.contains(data.hi) {
+                replayable = false;
+                return span;
+            }
+            relative_span(file, span)
+        });
+
+        let source_file = match source_file {
+            Some(source_file) => source_file,
+            None => {
+                self.crate_diagnostics.push(relative);
+                return;
+            }
+        };
+        if source_file.is_imported() {
+            self.crate_unreplayable = true;
+            return;
+        }
+        let key = file_key(&source_file);
+        if !replayable {
+            self.unreplayable.insert(key);
+            self.compiled.remove(&key);
+            self.diagnostics.remove(&key);
+        } else if !self.unreplayable.contains(&key) {
+            self.diagnostics.entry(key).or_default().push(relative);
+        }
+    }
+
+    /// Returns the diagnostics of `source_file`, with spans pointing into it, or `None` if it
+    /// isn't marked as compiled.
+    crate fn diagnostics(&self, source_file: &SourceFile) -> Option<Vec<Diagnostic>> {
+        if !self.contains(source_file) {
+            return None;
+        }
+        let diagnostics = self.diagnostics.get(&file_key(source_file)).map_or(&[][..], |d| &d[..]);
+        Some(diagnostics.iter().map(|diagnostic| {
+            let mut diagnostic = diagnostic.clone();
+            diagnostic.map_spans(|span| absolute_span(source_file, span));
+            diagnostic
+        }).collect())
+    }
+
+    /// Returns the diagnostics without spans.
+    crate fn crate_diagnostics(&self) -> &[Diagnostic] {
+        &self.crate_diagnostics
+    }
+}
+
+/// Makes `span` relative to the start of `source_file`. Positions are stored one past their
+/// offset in the file, so that a span at its very start isn't mistaken for `DUMMY_SP`.
+fn relative_span(source_file: &SourceFile, span: Span) -> Span {
+    let data = span.data();
+    let offset = |pos: BytePos| pos - source_file.start_pos + BytePos(1);
+    Span::with_root_ctxt(offset(data.lo), offset(data.hi))
+}
+
+/// Reverses `relative_span`.
+fn absolute_span(source_file: &SourceFile, span: Span) -> Span {
+    if span.is_dummy() {
+        return DUMMY_SP;
+    }
+    let data = span.data();
+    let pos = |offset: BytePos| offset - BytePos(1) + source_file.start_pos;
+    Span::with_root_ctxt(pos(data.lo), pos(data.hi))
+}
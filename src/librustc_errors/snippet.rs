@@ -18,6 +18,7 @@ pub struct MultilineAnnotation {
     pub end_col: usize,
     pub is_primary: bool,
     pub label: Option<String>,
+    pub role: Option<usize>,
     pub overlaps_exactly: bool,
 }
 
@@ -38,6 +39,7 @@ impl MultilineAnnotation {
             end_col: self.start_col + 1,
             is_primary: self.is_primary,
             label: None,
+            role: self.role,
             annotation_type: AnnotationType::MultilineStart(self.depth)
         }
     }
@@ -48,6 +50,7 @@ impl MultilineAnnotation {
             end_col: self.end_col,
             is_primary: self.is_primary,
             label: self.label.clone(),
+            role: self.role,
             annotation_type: AnnotationType::MultilineEnd(self.depth)
         }
     }
@@ -58,6 +61,7 @@ impl MultilineAnnotation {
             end_col: 0,
             is_primary: self.is_primary,
             label: None,
+            role: self.role,
             annotation_type: AnnotationType::MultilineLine(self.depth)
         }
     }
@@ -92,6 +96,10 @@ pub enum AnnotationType {
     MultilineLine(usize),
 }
 
+/// The markers underlining primary spans with a role, by role index. The
+/// first role is marked like any other primary span.
+const ROLE_MARKERS: [char; 4] = ['^', '~', '+', '*'];
+
 #[derive(Clone, Debug, PartialOrd, Ord, PartialEq, Eq)]
 pub struct Annotation {
     /// Start column, 0-based indexing -- counting *characters*, not
@@ -109,6 +117,9 @@ pub struct Annotation {
     /// Optional label to display adjacent to the annotation.
     pub label: Option<String>,
 
+    /// The role of the primary span this annotation is derived from, if any.
+    pub role: Option<usize>,
+
     /// Is this a single line, multiline or multiline span minimized down to a
     /// smaller span.
     pub annotation_type: AnnotationType,
@@ -161,6 +172,24 @@ impl Annotation {
         }
     }
 
+    /// The character and style the annotation is underlined with.
+    pub fn underline(&self) -> (char, Style) {
+        match self.role {
+            Some(role) => (ROLE_MARKERS[role % ROLE_MARKERS.len()], Style::Role(role)),
+            None if self.is_primary => ('^', Style::UnderlinePrimary),
+            None => ('-', Style::UnderlineSecondary),
+        }
+    }
+
+    /// The style the label of the annotation is written with.
+    pub fn label_style(&self) -> Style {
+        match self.role {
+            Some(role) => Style::Role(role),
+            None if self.is_primary => Style::LabelPrimary,
+            None => Style::LabelSecondary,
+        }
+    }
+
     pub fn takes_space(&self) -> bool {
         // Multiline annotations always have to keep vertical space.
         match self.annotation_type {
@@ -188,7 +217,15 @@ pub enum Style {
     UnderlineSecondary,
     LabelPrimary,
     LabelSecondary,
+    /// The underline and label of a primary span with the given role.
+    Role(usize),
     NoStyle,
     Level(Level),
     Highlight,
+    /// Inline code in a marked up message, see `markup::Markup`.
+    InlineCode,
+    /// A code block in a marked up message.
+    CodeBlock,
+    /// The marker of a bullet list item in a marked up message.
+    ListItem,
 }
@@ -0,0 +1,166 @@
+//! Callbacks observing every diagnostic as it is emitted.
+//!
+//! Subscribers are called before the diagnostic is deduplicated or printed, and regardless of
+//! which `Handler` emits it. The incremental compilation machinery uses them to record the
+//! diagnostics of the current query; test harnesses and IDE servers can use them to collect
+//! diagnostics alongside it.
+//!
+//! Subscribers registered with `subscribe` only see diagnostics emitted on the thread that
+//! registered them, while those registered with `subscribe_global` see the diagnostics of all
+//! threads. Subscribers may run while the emitting `Handler` is borrowed, so they must not emit
+//! diagnostics themselves. They may register and unregister subscribers, which takes effect
+//! from the next diagnostic on.
+//!
+//! `with_subscriber` registers a function for the duration of a call instead, and only once
+//! per thread, so that it sees each diagnostic once even when such calls are nested.
+//!
+//! Bug subscribers, registered with `subscribe_bugs` and `subscribe_bugs_global`, are only
+//! called with the bugs that are emitted, i.e., for `bug!`, `span_bug!` and the delayed bugs
+//! that end up being reported, along with a backtrace of where they were emitted from, or
+//! delayed from for delayed bugs. They let embedders report internal compiler errors in their
+//! own way, without having to pick them out of panics. No backtrace is captured unless one is
+//! registered.
+
+use crate::Diagnostic;
+
+use backtrace::Backtrace;
+use lazy_static::lazy_static;
+use rustc_data_structures::OnDrop;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Identifies a registered subscriber, so it can be unregistered with `unsubscribe`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SubscriberId(usize);
+
+type GlobalSubscriber = Arc<dyn Fn(&Diagnostic) + Send + Sync>;
+type BugSubscriber = Rc<dyn Fn(&Diagnostic, &Backtrace)>;
+type GlobalBugSubscriber = Arc<dyn Fn(&Diagnostic, &Backtrace) + Send + Sync>;
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static SUBSCRIBERS: RefCell<Vec<(SubscriberId, Rc<dyn Fn(&Diagnostic)>)>> =
+        RefCell::new(Vec::new());
+
+    /// The addresses of the functions registered by the `with_subscriber` calls in progress.
+    static SCOPED_SUBSCRIBERS: RefCell<Vec<usize>> = RefCell::new(Vec::new());
+
+    static BUG_SUBSCRIBERS: RefCell<Vec<(SubscriberId, BugSubscriber)>> =
+        RefCell::new(Vec::new());
+}
+
+lazy_static! {
+    static ref GLOBAL_SUBSCRIBERS: Mutex<Vec<(SubscriberId, GlobalSubscriber)>> =
+        Mutex::new(Vec::new());
+    static ref GLOBAL_BUG_SUBSCRIBERS: Mutex<Vec<(SubscriberId, GlobalBugSubscriber)>> =
+        Mutex::new(Vec::new());
+}
+
+fn next_id() -> SubscriberId {
+    SubscriberId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Calls `f` with every diagnostic emitted on the current thread, until it's unregistered.
+pub fn subscribe(f: impl Fn(&Diagnostic) + 'static) -> SubscriberId {
+    let id = next_id();
+    SUBSCRIBERS.with(|subscribers| subscribers.borrow_mut().push((id, Rc::new(f))));
+    id
+}
+
+/// Calls `f` with every diagnostic emitted on the current thread during the call to `body`.
+/// Does nothing more than calling `body` if an outer call already registered `f`.
+pub fn with_subscriber<R>(f: fn(&Diagnostic), body: impl FnOnce() -> R) -> R {
+    let address = f as usize;
+    let nested = SCOPED_SUBSCRIBERS.with(|scoped| scoped.borrow().contains(&address));
+    if nested {
+        return body();
+    }
+
+    let id = subscribe(f);
+    SCOPED_SUBSCRIBERS.with(|scoped| scoped.borrow_mut().push(address));
+    let _on_drop = OnDrop(move || {
+        SCOPED_SUBSCRIBERS.with(|scoped| scoped.borrow_mut().retain(|&other| other != address));
+        unsubscribe(id);
+    });
+    body()
+}
+
+/// Calls `f` with every diagnostic emitted on any thread, until it's unregistered.
+pub fn subscribe_global(f: impl Fn(&Diagnostic) + Send + Sync + 'static) -> SubscriberId {
+    let id = next_id();
+    global_subscribers().push((id, Arc::new(f)));
+    id
+}
+
+/// Calls `f` with every bug emitted on the current thread and its backtrace, until it's
+/// unregistered.
+pub fn subscribe_bugs(f: impl Fn(&Diagnostic, &Backtrace) + 'static) -> SubscriberId {
+    let id = next_id();
+    BUG_SUBSCRIBERS.with(|subscribers| subscribers.borrow_mut().push((id, Rc::new(f))));
+    id
+}
+
+/// Calls `f` with every bug emitted on any thread and its backtrace, until it's unregistered.
+pub fn subscribe_bugs_global(
+    f: impl Fn(&Diagnostic, &Backtrace) + Send + Sync + 'static,
+) -> SubscriberId {
+    let id = next_id();
+    lock(&GLOBAL_BUG_SUBSCRIBERS).push((id, Arc::new(f)));
+    id
+}
+
+/// Unregisters a subscriber registered with either `subscribe` or `subscribe_bugs`, on the same
+/// thread, or `subscribe_global` or `subscribe_bugs_global`. Does nothing if it isn't registered
+/// (anymore).
+pub fn unsubscribe(id: SubscriberId) {
+    SUBSCRIBERS.with(|subscribers| subscribers.borrow_mut().retain(|&(other, _)| other != id));
+    global_subscribers().retain(|&(other, _)| other != id);
+    BUG_SUBSCRIBERS.with(|subscribers| subscribers.borrow_mut().retain(|&(other, _)| other != id));
+    lock(&GLOBAL_BUG_SUBSCRIBERS).retain(|&(other, _)| other != id);
+}
+
+fn global_subscribers() -> MutexGuard<'static, Vec<(SubscriberId, GlobalSubscriber)>> {
+    lock(&GLOBAL_SUBSCRIBERS)
+}
+
+fn lock<T>(list: &'static Mutex<Vec<T>>) -> MutexGuard<'static, Vec<T>> {
+    // Subscribers can't leave the list inconsistent by panicking.
+    list.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Passes `diagnostic` to the subscribers of the current thread, and then to the global ones.
+pub(crate) fn notify(diagnostic: &Diagnostic) {
+    // Work on copies of the lists, so that subscribers can (un)subscribe.
+    let subscribers = SUBSCRIBERS.with(|subscribers| subscribers.borrow().clone());
+    for (_, subscriber) in subscribers {
+        subscriber(diagnostic);
+    }
+    let global = global_subscribers().clone();
+    for (_, subscriber) in global {
+        subscriber(diagnostic);
+    }
+}
+
+/// Whether bugs emitted on the current thread are passed to any subscriber, in which case their
+/// backtrace should be captured.
+pub(crate) fn tracks_bugs() -> bool {
+    BUG_SUBSCRIBERS.with(|subscribers| !subscribers.borrow().is_empty()) ||
+        !lock(&GLOBAL_BUG_SUBSCRIBERS).is_empty()
+}
+
+/// Passes the bug `diagnostic` and `backtrace` to the bug subscribers of the current thread, and
+/// then to the global ones.
+pub(crate) fn notify_bug(diagnostic: &Diagnostic, backtrace: &Backtrace) {
+    let subscribers = BUG_SUBSCRIBERS.with(|subscribers| subscribers.borrow().clone());
+    for (_, subscriber) in subscribers {
+        subscriber(diagnostic, backtrace);
+    }
+    let global = lock(&GLOBAL_BUG_SUBSCRIBERS).clone();
+    for (_, subscriber) in global {
+        subscriber(diagnostic, backtrace);
+    }
+}
@@ -0,0 +1,87 @@
+//! Diagnostics silenced by markers in the source code they point into.
+//!
+//! Generated code and teaching material sometimes contain code that's expected to trigger
+//! particular diagnostics. A marker comment like `// rustc: allow(E0308) next-line` silences
+//! the diagnostics with the given codes on the line it applies to, without a global flag. The
+//! parser records the markers it finds in a `SuppressionTable` shared with the `Handler`,
+//! which consults it before emitting a diagnostic.
+
+use crate::Diagnostic;
+
+use syntax_pos::BytePos;
+
+use std::collections::BTreeMap;
+
+/// A suppression marker, silencing the diagnostics with one of `codes` whose primary span
+/// starts on the line it applies to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suppression {
+    /// The start of the line the marker applies to.
+    pub lo: BytePos,
+    /// The end of the line the marker applies to, without its line break.
+    pub hi: BytePos,
+    /// The codes of the diagnostics to silence, e.g. `E0308` or `unused_variables`.
+    pub codes: Vec<String>,
+}
+
+/// The suppression markers found in the files of a session.
+#[derive(Clone, Debug, Default)]
+pub struct SuppressionTable {
+    /// The markers of each file, keyed by the start position of the file and sorted by the
+    /// lines they apply to.
+    files: BTreeMap<BytePos, Vec<Suppression>>,
+}
+
+impl SuppressionTable {
+    /// Records `suppression` for the file starting at `file_start`. The codes of several
+    /// markers applying to the same line are merged.
+    pub fn add(&mut self, file_start: BytePos, suppression: Suppression) {
+        let suppressions = self.files.entry(file_start).or_default();
+        match suppressions.binary_search_by_key(&suppression.lo, |s| s.lo) {
+            Ok(idx) => {
+                let codes = &mut suppressions[idx].codes;
+                for code in suppression.codes {
+                    if !codes.contains(&code) {
+                        codes.push(code);
+                    }
+                }
+            }
+            Err(idx) => suppressions.insert(idx, suppression),
+        }
+    }
+
+    /// Returns the markers of the file starting at `file_start`.
+    pub fn file_suppressions(&self, file_start: BytePos) -> &[Suppression] {
+        self.files.get(&file_start).map_or(&[], |suppressions| &suppressions[..])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Returns `true` if `diagnostic` has a code that's silenced on the line its primary span
+    /// starts on.
+    pub fn suppresses(&self, diagnostic: &Diagnostic) -> bool {
+        let code = match diagnostic.code {
+            Some(ref code) => code.to_string(),
+            None => return false,
+        };
+        let pos = match diagnostic.span.primary_span() {
+            Some(span) if !span.is_dummy() => span.lo(),
+            _ => return false,
+        };
+        // The markers of the last file starting before the span, which may still not be the
+        // file containing it if that one has none.
+        let suppressions = match self.files.range(..=pos).next_back() {
+            Some((_, suppressions)) => suppressions,
+            None => return false,
+        };
+        let idx = match suppressions.binary_search_by_key(&pos, |s| s.lo) {
+            Ok(idx) => idx,
+            Err(0) => return false,
+            Err(idx) => idx - 1,
+        };
+        let suppression = &suppressions[idx];
+        pos <= suppression.hi && suppression.codes.iter().any(|c| *c == code)
+    }
+}
@@ -0,0 +1,190 @@
+//! An emitter capturing diagnostics together with their rendered forms, with assertion
+//! helpers for unit tests.
+//!
+//! The JSON emitter lives in `libsyntax`, so the JSON form is only captured if the test
+//! supplies a JSON emitter through `TestEmitter::with_json`.
+
+use crate::{Diagnostic, DiagnosticId, SourceMapperDyn, Substitution};
+use crate::emitter::{Emitter, EmitterWriter, SharedBuffer};
+
+use rustc_data_structures::sync::{self, Lrc, Lock};
+use syntax_pos::Pos;
+
+use std::io::Write;
+
+/// A diagnostic emitted through a `TestEmitter`.
+#[derive(Clone, Debug)]
+pub struct CapturedDiagnostic {
+    pub diagnostic: Diagnostic,
+    /// The human readable rendering of the diagnostic.
+    pub rendered: String,
+    /// The JSON rendering of the diagnostic, if the emitter was given a JSON emitter.
+    pub json: Option<String>,
+}
+
+pub struct TestEmitter {
+    source_map: Option<Lrc<SourceMapperDyn>>,
+    colored: bool,
+    ui_testing: bool,
+    json: Option<(Box<dyn Emitter + sync::Send>, SharedBuffer)>,
+    captured: Lrc<Lock<Vec<CapturedDiagnostic>>>,
+    flushed_json: Lrc<Lock<String>>,
+}
+
+impl TestEmitter {
+    pub fn new(source_map: Option<Lrc<SourceMapperDyn>>) -> TestEmitter {
+        TestEmitter {
+            source_map,
+            colored: false,
+            ui_testing: false,
+            json: None,
+            captured: Default::default(),
+            flushed_json: Default::default(),
+        }
+    }
+
+    /// Keeps the ANSI color codes in the human readable rendering.
+    pub fn colored(mut self, colored: bool) -> Self {
+        self.colored = colored;
+        self
+    }
+
+    /// Anonymizes line numbers in the human readable rendering, like UI tests do.
+    pub fn ui_testing(mut self, ui_testing: bool) -> Self {
+        self.ui_testing = ui_testing;
+        self
+    }
+
+    /// Also captures the JSON rendering, produced by the emitter `make_emitter` returns
+    /// for the given destination.
+    pub fn with_json<F>(mut self, make_emitter: F) -> Self
+    where
+        F: FnOnce(Box<dyn Write + Send>) -> Box<dyn Emitter + sync::Send>,
+    {
+        let buffer = SharedBuffer::default();
+        self.json = Some((make_emitter(Box::new(buffer.clone())), buffer));
+        self
+    }
+
+    /// Returns a handle to the diagnostics captured by this emitter, which stays usable
+    /// once the emitter has been moved into a `Handler`.
+    pub fn output(&self) -> TestOutput {
+        TestOutput {
+            source_map: self.source_map.clone(),
+            captured: self.captured.clone(),
+            flushed_json: self.flushed_json.clone(),
+        }
+    }
+}
+
+impl Emitter for TestEmitter {
+    fn emit_diagnostic(&mut self, diagnostic: &Diagnostic) {
+        let buffer = SharedBuffer::default();
+        EmitterWriter::new(
+            Box::new(buffer.clone()),
+            self.source_map.clone(),
+            false,
+            false,
+            self.colored,
+            None,
+            false,
+        ).ui_testing(self.ui_testing).emit_diagnostic(diagnostic);
+
+        let json = self.json.as_mut().map(|(emitter, buffer)| {
+            emitter.emit_diagnostic(diagnostic);
+            buffer.take()
+        });
+
+        self.captured.lock().push(CapturedDiagnostic {
+            diagnostic: diagnostic.clone(),
+            rendered: buffer.take(),
+            json,
+        });
+    }
+
+    fn flush_diagnostics(&mut self) {
+        if let Some((emitter, buffer)) = self.json.as_mut() {
+            emitter.flush_diagnostics();
+            self.flushed_json.lock().push_str(&buffer.take());
+        }
+    }
+}
+
+/// The diagnostics captured by a `TestEmitter`.
+#[derive(Clone)]
+pub struct TestOutput {
+    source_map: Option<Lrc<SourceMapperDyn>>,
+    captured: Lrc<Lock<Vec<CapturedDiagnostic>>>,
+    flushed_json: Lrc<Lock<String>>,
+}
+
+impl TestOutput {
+    pub fn diagnostics(&self) -> Vec<CapturedDiagnostic> {
+        self.captured.borrow().clone()
+    }
+
+    /// The human readable rendering of all captured diagnostics, in emission order.
+    pub fn rendered(&self) -> String {
+        self.captured.borrow().iter().map(|captured| captured.rendered.as_str()).collect()
+    }
+
+    /// The JSON rendering of all captured diagnostics, in emission order.
+    pub fn json(&self) -> String {
+        self.captured.borrow().iter().filter_map(|captured| captured.json.as_ref())
+            .map(|json| json.as_str()).collect()
+    }
+
+    /// The JSON the JSON emitter emitted once the diagnostics were flushed, see
+    /// `Handler::flush_diagnostics`.
+    pub fn flushed_json(&self) -> String {
+        self.flushed_json.borrow().clone()
+    }
+
+    /// Asserts that an error with the given code, such as `"E0425"`, was emitted.
+    pub fn assert_error_with_code(&self, code: &str) {
+        let expected = DiagnosticId::Error(code.to_string());
+        let found = self.captured.borrow().iter().any(|captured| {
+            captured.diagnostic.is_error() && captured.diagnostic.code.as_ref() == Some(&expected)
+        });
+        assert!(found, "no error with code {} was emitted, got:\n{}", code, self.rendered());
+    }
+
+    /// Asserts that one of the suggestions of the captured diagnostics, applied to the
+    /// source file it points into, turns that file into `expected`.
+    pub fn assert_suggestion_applies_to(&self, expected: &str) {
+        let source_map = self.source_map.as_ref()
+            .expect("applying suggestions requires the emitter to have a source map");
+        let mut results = Vec::new();
+        for captured in self.captured.borrow().iter() {
+            for suggestion in &captured.diagnostic.suggestions {
+                for substitution in &suggestion.substitutions {
+                    match apply_substitution(&**source_map, substitution) {
+                        Some(ref result) if result == expected => return,
+                        Some(result) => results.push(result),
+                        None => {}
+                    }
+                }
+            }
+        }
+        panic!("no suggestion results in:\n{}\nthe suggestions result in:\n{}",
+               expected,
+               results.join("\n-----\n"));
+    }
+}
+
+/// Applies `substitution` to the source of the file its first part points into.
+fn apply_substitution(source_map: &SourceMapperDyn, substitution: &Substitution)
+                      -> Option<String> {
+    let file = source_map.lookup_char_pos(substitution.parts.first()?.span.lo()).file;
+    let mut src = file.src.as_ref()?.to_string();
+
+    let mut parts: Vec<_> = substitution.parts.iter().collect();
+    parts.sort_by_key(|part| part.span.lo());
+    // Replace back to front, so that the offsets of the remaining parts stay valid.
+    for part in parts.iter().rev() {
+        let lo = (part.span.lo() - file.start_pos).to_usize();
+        let hi = (part.span.hi() - file.start_pos).to_usize();
+        src.replace_range(lo..hi, &part.snippet);
+    }
+    Some(src)
+}
@@ -112,7 +112,10 @@ where
     };
 
     let _sess_abort_error = OnDrop(|| {
-        compiler.sess.diagnostic().print_error_count(&util::diagnostics_registry());
+        compiler.sess.diagnostic().print_suggestion_audit();
+        let registry = util::diagnostics_registry();
+        compiler.sess.diagnostic().emit_session_summary(&registry);
+        compiler.sess.diagnostic().flush_diagnostics();
     });
 
     if compiler.sess.profile_queries() {
@@ -8,6 +8,7 @@ use rustc::hir;
 use rustc::hir::lowering::lower_crate;
 use rustc::hir::def_id::{CrateNum, LOCAL_CRATE};
 use rustc::lint;
+use rustc::lint::builtin::BuiltinLintDiagnostics;
 use rustc::middle::{self, reachable, resolve_lifetime, stability};
 use rustc::middle::cstore::CrateStore;
 use rustc::ty::{self, AllArenas, Resolutions, TyCtxt, GlobalCtxt};
@@ -39,9 +40,10 @@ use syntax::early_buffered_lints::BufferedEarlyLint;
 use syntax::ext::base::{NamedSyntaxExtension, ExtCtxt};
 use syntax::mut_visit::MutVisitor;
 use syntax::parse::{self, PResult};
+use syntax::util::item_index::ItemIndex;
 use syntax::util::node_count::NodeCounter;
 use syntax::symbol::Symbol;
-use syntax_pos::FileName;
+use syntax_pos::{FileName, Span};
 use syntax_ext;
 
 use rustc_serialize::json;
@@ -360,6 +362,10 @@ fn configure_and_expand_inner<'a>(
         &mut krate, &mut resolver, plugin_info.syntax_exts, sess.edition()
     );
 
+    time(sess, "embedder items injection", || {
+        syntax_ext::embedder_items::inject(&mut krate, &mut resolver, &sess.parse_sess)
+    });
+
     // Expand all macros
     sess.profiler(|p| p.start_activity("macro expansion"));
     krate = time(sess, "expansion", || {
@@ -401,6 +407,7 @@ fn configure_and_expand_inner<'a>(
             features: Some(&features),
             recursion_limit: *sess.recursion_limit.get(),
             trace_mac: sess.opts.debugging_opts.trace_macros,
+            dump_expansion_graph: sess.opts.debugging_opts.dump_expansion_graph,
             should_test: sess.opts.test,
             ..syntax::ext::expand::ExpansionConfig::default(crate_name.to_string())
         };
@@ -438,6 +445,12 @@ fn configure_and_expand_inner<'a>(
     });
     sess.profiler(|p| p.end_activity("macro expansion"));
 
+    if sess.opts.debugging_opts.item_headers {
+        time(sess, "item indexing", || {
+            *sess.parse_sess.item_index.lock() = ItemIndex::new(&krate);
+        });
+    }
+
     time(sess, "maybe building test harness", || {
         syntax_ext::test_harness::inject(
             &sess.parse_sess,
@@ -455,6 +468,10 @@ fn configure_and_expand_inner<'a>(
         util::ReplaceBodyWithLoop::new(sess).visit_crate(&mut krate);
     }
 
+    time(sess, "syntactic validation", || {
+        syntax::validate::check_crate(&sess.parse_sess, sess.features_untracked(), &krate)
+    });
+
     let has_proc_macro_decls = time(sess, "AST validation", || {
         ast_validation::check_crate(sess, &krate)
     });
@@ -496,6 +513,21 @@ fn configure_and_expand_inner<'a>(
 
     if sess.opts.debugging_opts.input_stats {
         println!("Post-expansion node count: {}", count_nodes(&krate));
+        let interner_stats = Symbol::interner_stats();
+        println!(
+            "Interned symbols:          {} ({} contended of {} interner locks)",
+            interner_stats.symbols,
+            interner_stats.contended_locks,
+            interner_stats.locks,
+        );
+        let span_stats = Span::interner_stats();
+        println!(
+            "Interned spans:            {} of {} ({} bytes, {} cached)",
+            span_stats.spans,
+            span_stats.interned,
+            span_stats.bytes,
+            span_stats.cache_hits,
+        );
     }
 
     if sess.opts.debugging_opts.hir_stats {
@@ -523,9 +555,10 @@ fn configure_and_expand_inner<'a>(
     // Add all buffered lints from the `ParseSess` to the `Session`.
     sess.parse_sess.buffered_lints.with_lock(|buffered_lints| {
         info!("{} parse sess buffered_lints", buffered_lints.len());
-        for BufferedEarlyLint{id, span, msg, lint_id} in buffered_lints.drain(..) {
+        for BufferedEarlyLint{id, span, msg, lint_id, diagnostic} in buffered_lints.drain(..) {
             let lint = lint::Lint::from_parser_lint_id(lint_id);
-            sess.buffer_lint(lint, id, span, &msg);
+            let diagnostic = BuiltinLintDiagnostics::from_parser_lint_diagnostics(diagnostic);
+            sess.buffer_lint_with_diagnostic(lint, id, span, &msg, diagnostic);
         }
     });
 
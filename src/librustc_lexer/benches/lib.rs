@@ -0,0 +1,32 @@
+#![feature(test)]
+
+extern crate test;
+
+use test::Bencher;
+
+// Large files from the tree: one mostly made of doc comments, one of dense code, and one of
+// deeply indented code.
+
+const ITERATOR: &str = include_str!("../../libcore/iter/traits/iterator.rs");
+const PARSER_EXPR: &str = include_str!("../../libsyntax/parse/parser/expr.rs");
+const EMITTER: &str = include_str!("../../librustc_errors/emitter.rs");
+
+fn bench_tokenize(b: &mut Bencher, source: &str) {
+    b.bytes = source.len() as u64;
+    b.iter(|| rustc_lexer::tokenize(source).count());
+}
+
+#[bench]
+fn tokenize_doc_comments(b: &mut Bencher) {
+    bench_tokenize(b, ITERATOR);
+}
+
+#[bench]
+fn tokenize_dense_code(b: &mut Bencher) {
+    bench_tokenize(b, PARSER_EXPR);
+}
+
+#[bench]
+fn tokenize_indented_code(b: &mut Bencher) {
+    bench_tokenize(b, EMITTER);
+}
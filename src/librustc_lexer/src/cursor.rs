@@ -18,6 +18,7 @@ impl<'a> Cursor<'a> {
             prev: EOF_CHAR,
         }
     }
+
     /// For debug assertions only
     pub(crate) fn prev(&self) -> char {
         #[cfg(debug_assertions)]
@@ -30,19 +31,24 @@ impl<'a> Cursor<'a> {
             '\0'
         }
     }
+
     pub(crate) fn nth_char(&self, n: usize) -> char {
         self.chars().nth(n).unwrap_or(EOF_CHAR)
     }
+
     pub(crate) fn is_eof(&self) -> bool {
         self.chars.as_str().is_empty()
     }
+
     pub(crate) fn len_consumed(&self) -> usize {
         self.initial_len - self.chars.as_str().len()
     }
+
     /// Returns an iterator over the remaining characters.
     fn chars(&self) -> Chars<'a> {
         self.chars.clone()
     }
+
     /// Moves to the next character.
     pub(crate) fn bump(&mut self) -> Option<char> {
         let c = self.chars.next()?;
@@ -54,4 +60,95 @@ impl<'a> Cursor<'a> {
 
         Some(c)
     }
+
+    /// Moves past the leading bytes of the remaining input for which `predicate` holds.
+    /// `predicate` must only hold for ASCII bytes, so that this stops at a char boundary.
+    pub(crate) fn eat_ascii_while(&mut self, predicate: impl Fn(u8) -> bool) {
+        let bytes = self.chars.as_str().as_bytes();
+        let n = bytes.iter().position(|&b| !predicate(b)).unwrap_or(bytes.len());
+        self.bump_bytes(n);
+    }
+
+    /// Moves past the leading ASCII whitespace of the remaining input.
+    pub(crate) fn eat_ascii_whitespace(&mut self) {
+        let bytes = self.chars.as_str().as_bytes();
+        // Indentation makes for long runs of spaces, so skip those a word at a time.
+        let mut n = 0;
+        for chunk in bytes.chunks_exact(WORD_SIZE) {
+            if read_word(chunk) != repeat_byte(b' ') {
+                break;
+            }
+            n += WORD_SIZE;
+        }
+        n += bytes[n..].iter().take_while(|&&b| is_ascii_whitespace(b)).count();
+        self.bump_bytes(n);
+    }
+
+    /// Moves to the next occurrence of the ASCII character `c`, or to the end of the input.
+    pub(crate) fn eat_until_ascii(&mut self, c: u8) {
+        debug_assert!(c.is_ascii());
+        let bytes = self.chars.as_str().as_bytes();
+        let n = find_byte(c, bytes).unwrap_or(bytes.len());
+        self.bump_bytes(n);
+    }
+
+    /// Moves past the next `n` bytes, which must end at a char boundary.
+    fn bump_bytes(&mut self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        let rest = self.chars.as_str();
+
+        #[cfg(debug_assertions)]
+        {
+            self.prev = rest[..n].chars().next_back().unwrap();
+        }
+
+        self.chars = rest[n..].chars();
+    }
+}
+
+const WORD_SIZE: usize = std::mem::size_of::<u64>();
+const LO_BITS: u64 = 0x0101_0101_0101_0101;
+const HI_BITS: u64 = 0x8080_8080_8080_8080;
+
+fn read_word(chunk: &[u8]) -> u64 {
+    let mut word = [0; WORD_SIZE];
+    word.copy_from_slice(chunk);
+    u64::from_ne_bytes(word)
+}
+
+/// A word with every byte set to `b`.
+fn repeat_byte(b: u8) -> u64 {
+    LO_BITS * u64::from(b)
+}
+
+/// True if any of the bytes of `word` is zero.
+fn contains_zero_byte(word: u64) -> bool {
+    word.wrapping_sub(LO_BITS) & !word & HI_BITS != 0
+}
+
+/// Returns the index of the first occurrence of `needle` in `haystack`, comparing a word at a
+/// time rather than byte by byte.
+fn find_byte(needle: u8, haystack: &[u8]) -> Option<usize> {
+    let repeated = repeat_byte(needle);
+    let mut offset = 0;
+    for chunk in haystack.chunks_exact(WORD_SIZE) {
+        if contains_zero_byte(read_word(chunk) ^ repeated) {
+            break;
+        }
+        offset += WORD_SIZE;
+    }
+    haystack[offset..].iter().position(|&b| b == needle).map(|i| offset + i)
 }
+
+/// True if `b` is one of the ASCII characters that `is_whitespace` accepts.
+fn is_ascii_whitespace(b: u8) -> bool {
+    match b {
+        b'\t' | b'\n' | b'\x0B' | b'\x0C' | b'\r' | b' ' => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,48 @@
+use super::*;
+
+#[test]
+fn test_find_byte() {
+    fn check(haystack: &str) {
+        let expected = haystack.bytes().position(|b| b == b'\n');
+        assert_eq!(find_byte(b'\n', haystack.as_bytes()), expected, "{:?}", haystack);
+    }
+
+    check("");
+    check("\n");
+    check("// short");
+    check("// short\n");
+    check("// exactly 8\n");
+    check("// a comment that spans more than a few words\nfn main() {}\n");
+    check("// a comment that spans more than a few words, without a newline");
+    check("// ünïcödé ßtüff that is longer than a word\n");
+    check("\u{80}\u{80}\u{80}\u{80}\u{80}\u{80}\u{80}\u{80}\n");
+}
+
+#[test]
+fn test_eat_ascii_whitespace() {
+    fn check(input: &str, expected_len: usize) {
+        let mut cursor = Cursor::new(input);
+        cursor.eat_ascii_whitespace();
+        assert_eq!(cursor.len_consumed(), expected_len, "{:?}", input);
+    }
+
+    check("", 0);
+    check("x", 0);
+    check(" x", 1);
+    check("        x", 8);
+    check("                 \t\r\n  x", 22);
+    check("         \u{2028} ", 9);
+    check(" \t \n \x0B \x0C \r ", 11);
+}
+
+#[test]
+fn test_eat_until_ascii() {
+    let mut cursor = Cursor::new("// ünïcödé comment\nfn");
+    cursor.eat_until_ascii(b'\n');
+    assert_eq!(cursor.nth_char(0), '\n');
+    assert_eq!(cursor.len_consumed(), "// ünïcödé comment".len());
+
+    let mut cursor = Cursor::new("// unterminated");
+    cursor.eat_until_ascii(b'\n');
+    assert!(cursor.is_eof());
+}
@@ -265,15 +265,7 @@ impl Cursor<'_> {
     fn line_comment(&mut self) -> TokenKind {
         debug_assert!(self.prev() == '/' && self.nth_char(0) == '/');
         self.bump();
-        loop {
-            match self.nth_char(0) {
-                '\n' => break,
-                EOF_CHAR if self.is_eof() => break,
-                _ => {
-                    self.bump();
-                }
-            }
-        }
+        self.eat_until_ascii(b'\n');
         LineComment
     }
 
@@ -303,7 +295,12 @@ impl Cursor<'_> {
 
     fn whitespace(&mut self) -> TokenKind {
         debug_assert!(is_whitespace(self.prev()));
-        while is_whitespace(self.nth_char(0)) {
+        loop {
+            self.eat_ascii_whitespace();
+            // Non-ASCII whitespace is rare, so it's skipped char by char.
+            if !is_whitespace(self.nth_char(0)) {
+                break;
+            }
             self.bump();
         }
         Whitespace
@@ -317,18 +314,25 @@ impl Cursor<'_> {
         );
         self.bump();
         self.bump();
-        while is_id_continue(self.nth_char(0)) {
-            self.bump();
-        }
+        self.eat_identifier_tail();
         RawIdent
     }
 
     fn ident(&mut self) -> TokenKind {
         debug_assert!(is_id_start(self.prev()));
-        while is_id_continue(self.nth_char(0)) {
+        self.eat_identifier_tail();
+        Ident
+    }
+
+    fn eat_identifier_tail(&mut self) {
+        loop {
+            self.eat_ascii_while(|b| b.is_ascii_alphanumeric() || b == b'_');
+            // Like non-ASCII whitespace, non-ASCII identifiers are skipped char by char.
+            if !is_id_continue(self.nth_char(0)) {
+                break;
+            }
             self.bump();
         }
-        Ident
     }
 
     fn number(&mut self, first_digit: char) -> LiteralKind {
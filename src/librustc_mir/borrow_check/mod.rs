@@ -18,7 +18,7 @@ use rustc::mir::{Terminator, TerminatorKind};
 use rustc::ty::query::Providers;
 use rustc::ty::{self, TyCtxt};
 
-use rustc_errors::{Applicability, Diagnostic, DiagnosticBuilder, Level};
+use rustc_errors::{Applicability, Diagnostic, DiagnosticBuilder, Handler, Level};
 use rustc_data_structures::bit_set::BitSet;
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
 use rustc_data_structures::graph::dominators::Dominators;
@@ -390,7 +390,7 @@ fn do_mir_borrowck<'a, 'tcx>(
                     // to warnings.
 
                     for err in mbcx.errors_buffer.iter_mut() {
-                        downgrade_if_error(err);
+                        downgrade_if_error(tcx.sess.diagnostic(), err);
                     }
                 }
                 SignalledError::SawSomeError => {
@@ -416,8 +416,9 @@ fn do_mir_borrowck<'a, 'tcx>(
     result
 }
 
-fn downgrade_if_error(diag: &mut Diagnostic) {
+fn downgrade_if_error(handler: &Handler, diag: &mut Diagnostic) {
     if diag.is_error() {
+        handler.record_downgraded_error();
         diag.level = Level::Warning;
         diag.warn(
             "this error has been downgraded to a warning for backwards \
@@ -211,24 +211,6 @@ impl<'a> AstValidator<'a> {
         }
     }
 
-    fn invalid_visibility(&self, vis: &Visibility, note: Option<&str>) {
-        if let VisibilityKind::Inherited = vis.node {
-            return
-        }
-
-        let mut err = struct_span_err!(self.session,
-                                        vis.span,
-                                        E0449,
-                                        "unnecessary visibility qualifier");
-        if vis.node.is_pub() {
-            err.span_label(vis.span, "`pub` not permitted here because it's implied");
-        }
-        if let Some(note) = note {
-            err.note(note);
-        }
-        err.emit();
-    }
-
     fn check_decl_no_pat<ReportFn: Fn(Span, bool)>(&self, decl: &FnDecl, report_err: ReportFn) {
         for arg in &decl.inputs {
             match arg.pat.node {
@@ -539,38 +521,21 @@ impl<'a> Visitor<'a> for AstValidator<'a> {
         }
 
         match item.node {
-            ItemKind::Impl(unsafety, polarity, _, _, Some(..), ref ty, ref impl_items) => {
-                self.invalid_visibility(&item.vis, None);
-                if let TyKind::Err = ty.node {
-                    self.err_handler()
-                        .struct_span_err(item.span, "`impl Trait for .. {}` is an obsolete syntax")
-                        .help("use `auto trait Trait {}` instead").emit();
-                }
+            ItemKind::Impl(unsafety, polarity, _, _, Some(..), _, ref impl_items) => {
                 if unsafety == Unsafety::Unsafe && polarity == ImplPolarity::Negative {
                     span_err!(self.session, item.span, E0198, "negative impls cannot be unsafe");
                 }
                 for impl_item in impl_items {
-                    self.invalid_visibility(&impl_item.vis, None);
                     if let ImplItemKind::Method(ref sig, _) = impl_item.node {
                         self.check_trait_fn_not_const(sig.header.constness);
                         self.check_trait_fn_not_async(impl_item.span, sig.header.asyncness.node);
                     }
                 }
             }
-            ItemKind::Impl(unsafety, polarity, defaultness, _, None, _, _) => {
-                self.invalid_visibility(&item.vis,
-                                        Some("place qualifiers on individual impl items instead"));
+            ItemKind::Impl(unsafety, _, _, _, None, _, _) => {
                 if unsafety == Unsafety::Unsafe {
                     span_err!(self.session, item.span, E0197, "inherent impls cannot be unsafe");
                 }
-                if polarity == ImplPolarity::Negative {
-                    self.err_handler().span_err(item.span, "inherent impls cannot be negative");
-                }
-                if defaultness == Defaultness::Default {
-                    self.err_handler()
-                        .struct_span_err(item.span, "inherent impls cannot be default")
-                        .note("only trait implementations may be annotated with default").emit();
-                }
             }
             ItemKind::Fn(ref decl, ref header, ref generics, _) => {
                 self.visit_fn_header(header);
@@ -594,19 +559,6 @@ impl<'a> Visitor<'a> for AstValidator<'a> {
                     }
                 }
             }
-            ItemKind::ForeignMod(..) => {
-                self.invalid_visibility(
-                    &item.vis,
-                    Some("place qualifiers on individual foreign items instead"),
-                );
-            }
-            ItemKind::Enum(ref def, _) => {
-                for variant in &def.variants {
-                    for field in variant.data.fields() {
-                        self.invalid_visibility(&field.vis, None);
-                    }
-                }
-            }
             ItemKind::Trait(is_auto, _, ref generics, ref bounds, ref trait_items) => {
                 if is_auto == IsAuto::Yes {
                     // Auto traits cannot have generics, super traits nor contain items.
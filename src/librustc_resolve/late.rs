@@ -25,6 +25,7 @@ use syntax::ast::*;
 use syntax::ptr::P;
 use syntax::symbol::{kw, sym};
 use syntax::util::lev_distance::find_best_match_for_name;
+use syntax::util::parser::named_argument;
 use syntax::visit::{self, Visitor, FnKind};
 use syntax_pos::Span;
 
@@ -327,6 +328,16 @@ impl<'a> PathSource<'a> {
     }
 }
 
+/// A call with arguments like `x = 1`, which look like named arguments.
+struct NamedArgs {
+    /// The span of the whole call.
+    span: Span,
+    /// The callee, if it's a path that may name a tuple struct and all the arguments are named.
+    struct_path: Option<Path>,
+    /// The names of the named arguments, with the spans of the arguments and of their values.
+    args: Vec<(Ident, Span, Span)>,
+}
+
 struct LateResolutionVisitor<'a, 'b> {
     r: &'b mut Resolver<'a>,
 
@@ -358,6 +369,9 @@ struct LateResolutionVisitor<'a, 'b> {
 
     /// Only used for better errors on `fn(): fn()`.
     current_type_ascription: Vec<Span>,
+
+    /// Only used for better errors on `f(x = 1)`.
+    current_named_args: Vec<NamedArgs>,
 }
 
 /// Walks the whole crate in DFS order, visiting each item, resolving names as it goes.
@@ -538,6 +552,7 @@ impl<'a, 'b> LateResolutionVisitor<'a, '_> {
             current_self_item: None,
             unused_labels: Default::default(),
             current_type_ascription: Vec::new(),
+            current_named_args: Vec::new(),
         }
     }
 
@@ -1829,6 +1844,48 @@ impl<'a, 'b> LateResolutionVisitor<'a, '_> {
         debug!("(resolving block) leaving block");
     }
 
+    /// Resolves the `arguments` of the call `span` of `callee`, or of a method if it's `None`,
+    /// remembering those that look like named arguments for diagnostics.
+    fn resolve_call_arguments(
+        &mut self,
+        span: Span,
+        callee: Option<&Expr>,
+        arguments: &[P<Expr>],
+    ) {
+        let args = arguments.iter().filter_map(|argument| {
+            let name = named_argument(argument)?;
+            match argument.node {
+                ExprKind::Assign(_, ref value) => Some((name, argument.span, value.span)),
+                _ => None,
+            }
+        }).collect::<Vec<_>>();
+        if args.is_empty() {
+            for argument in arguments {
+                self.resolve_expr(argument, None);
+            }
+            return;
+        }
+
+        // A tuple struct may have been confused with a struct with named fields.
+        let struct_path = match callee {
+            Some(Expr { node: ExprKind::Path(None, path), .. }) => {
+                let name = path.segments.last().unwrap().ident.as_str();
+                let all_named = args.len() == arguments.len();
+                if all_named && name.starts_with(char::is_uppercase) {
+                    Some(path.clone())
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        self.current_named_args.push(NamedArgs { span, struct_path, args });
+        for argument in arguments {
+            self.resolve_expr(argument, None);
+        }
+        self.current_named_args.pop();
+    }
+
     fn resolve_expr(&mut self, expr: &Expr, parent: Option<&Expr>) {
         // First, record candidate traits for this expression if it could
         // result in the invocation of a method call.
@@ -1922,19 +1979,14 @@ impl<'a, 'b> LateResolutionVisitor<'a, '_> {
                 self.resolve_expr(subexpression, Some(expr));
             }
             ExprKind::MethodCall(ref segment, ref arguments) => {
-                let mut arguments = arguments.iter();
-                self.resolve_expr(arguments.next().unwrap(), Some(expr));
-                for argument in arguments {
-                    self.resolve_expr(argument, None);
-                }
+                self.resolve_expr(&arguments[0], Some(expr));
+                self.resolve_call_arguments(expr.span, None, &arguments[1..]);
                 self.visit_path_segment(expr.span, segment);
             }
 
             ExprKind::Call(ref callee, ref arguments) => {
                 self.resolve_expr(callee, Some(expr));
-                for argument in arguments {
-                    self.resolve_expr(argument, None);
-                }
+                self.resolve_call_arguments(expr.span, Some(callee), arguments);
             }
             ExprKind::Type(ref type_expr, _) => {
                 self.current_type_ascription.push(type_expr.span);
@@ -235,6 +235,10 @@ impl<'a> LateResolutionVisitor<'a, '_> {
             }
         }
 
+        if path.len() == 1 && res.is_none() {
+            self.named_argument_suggestion(&mut err, ident_span);
+        }
+
         // Try Levenshtein algorithm.
         let levenshtein_worked = add_typo_suggestion(
             &mut err, self.lookup_typo_candidate(path, ns, is_expected, span), ident_span
@@ -631,6 +635,52 @@ impl<'a> LateResolutionVisitor<'a, '_> {
         start.to(cm.next_point(start))
     }
 
+    /// Explains that the undeclared name `ident_span` of an argument like `x = 1` isn't the name
+    /// of a parameter, as Rust has no named arguments.
+    fn named_argument_suggestion(&self, err: &mut DiagnosticBuilder<'_>, ident_span: Span) {
+        let call = self.current_named_args.iter().rev().find(|call| {
+            call.args.iter().any(|&(name, ..)| name.span == ident_span)
+        });
+        let call = match call {
+            Some(call) => call,
+            None => return,
+        };
+        let &(name, arg_span, value_span) =
+            call.args.iter().find(|&&(name, ..)| name.span == ident_span).unwrap();
+        err.note(&format!(
+            "`{} = ...` is an assignment, of type `()`: Rust has no named arguments",
+            name,
+        ));
+        err.span_suggestion(
+            arg_span.until(value_span),
+            "to pass the value, remove the name",
+            String::new(),
+            Applicability::MaybeIncorrect,
+        );
+
+        // Suggest the struct literal only once, on the first name of the call.
+        let path = match call.struct_path {
+            Some(ref path) if call.args[0].0.span == ident_span => path,
+            _ => return,
+        };
+        let cm = self.r.session.source_map();
+        let fields = call.args.iter().map(|&(name, _, value_span)| {
+            cm.span_to_snippet(value_span).map(|value| format!("{}: {}", name, value))
+        }).collect::<Result<Vec<_>, _>>();
+        if let Ok(fields) = fields {
+            let path = path_names_to_string(path);
+            err.span_suggestion(
+                call.span,
+                &format!(
+                    "if `{}` is a struct, use a struct literal to initialize its fields by name",
+                    path,
+                ),
+                format!("{} {{ {} }}", path, fields.join(", ")),
+                Applicability::MaybeIncorrect,
+            );
+        }
+    }
+
     fn type_ascription_suggestion(
         &self,
         err: &mut DiagnosticBuilder<'_>,
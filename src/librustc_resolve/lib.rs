@@ -39,7 +39,7 @@ use rustc_metadata::cstore::CStore;
 
 use syntax::ext::hygiene::{ExpnId, Transparency, SyntaxContext};
 use syntax::ast::{self, Name, NodeId, Ident, FloatTy, IntTy, UintTy};
-use syntax::ext::base::{SyntaxExtension, MacroKind, SpecialDerives};
+use syntax::ext::base::{SyntaxExtension, MacroKind, SpecialDerives, StalledInvocation};
 use syntax::symbol::{kw, sym};
 
 use syntax::visit::{self, Visitor};
@@ -928,6 +928,10 @@ pub struct Resolver<'a> {
     multi_segment_macro_resolutions: Vec<(Vec<Segment>, Span, MacroKind, ParentScope<'a>,
                                           Option<Res>)>,
     builtin_attrs: Vec<(Ident, ParentScope<'a>)>,
+    /// Invocations waiting for their paths to resolve when expansion last stopped making progress,
+    /// and their description by the expander.
+    stalled_invocations: Vec<StalledInvocation>,
+    stalled_invocations_dump: String,
     /// Some built-in derives mark items they are applied to so they are treated specially later.
     /// Derive macros cannot modify the item themselves and have to store the markers in the global
     /// context, so they attach the markers to derive container IDs using this resolver table.
@@ -1213,6 +1217,8 @@ impl<'a> Resolver<'a> {
             single_segment_macro_resolutions: Default::default(),
             multi_segment_macro_resolutions: Default::default(),
             builtin_attrs: Default::default(),
+            stalled_invocations: Default::default(),
+            stalled_invocations_dump: String::new(),
             special_derives: Default::default(),
             active_features:
                 features.declared_lib_features.iter().map(|(feat, ..)| *feat)
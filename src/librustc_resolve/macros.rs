@@ -15,7 +15,7 @@ use syntax::ast::{self, NodeId, Ident};
 use syntax::attr::StabilityLevel;
 use syntax::edition::Edition;
 use syntax::ext::base::{self, InvocationRes, Indeterminate, SpecialDerives};
-use syntax::ext::base::{MacroKind, SyntaxExtension};
+use syntax::ext::base::{MacroKind, StalledInvocation, SyntaxExtension};
 use syntax::ext::expand::{AstFragment, AstFragmentKind, Invocation, InvocationKind};
 use syntax::ext::hygiene::{self, ExpnId, ExpnData, ExpnKind};
 use syntax::ext::tt::macro_rules;
@@ -264,6 +264,11 @@ impl<'a> base::Resolver for Resolver<'a> {
     fn add_derives(&mut self, expn_id: ExpnId, derives: SpecialDerives) {
         *self.special_derives.entry(expn_id).or_default() |= derives;
     }
+
+    fn note_stalled_invocations(&mut self, invocations: &[StalledInvocation], dump: &str) {
+        self.stalled_invocations = invocations.to_vec();
+        self.stalled_invocations_dump = dump.to_string();
+    }
 }
 
 impl<'a> Resolver<'a> {
@@ -727,7 +732,19 @@ impl<'a> Resolver<'a> {
                     let msg = format!("cannot determine resolution for the {} `{}`",
                                         kind.descr(), Segment::names_to_string(path));
                     let msg_note = "import resolution is stuck, try simplifying macro imports";
-                    this.session.struct_span_err(span, &msg).note(msg_note).emit();
+                    let mut err = this.session.struct_span_err(span, &msg);
+                    err.note(msg_note);
+                    // Point out what else was stuck, it may be what this macro is waiting on.
+                    let others_stalled = this.stalled_invocations.iter()
+                        .any(|invoc| invoc.paths.iter().any(|path| path.span != span));
+                    if others_stalled {
+                        err.note(&format!(
+                            "these invocations were stalled when expansion stopped making \
+                             progress:\n{}",
+                            this.stalled_invocations_dump.trim_end(),
+                        ));
+                    }
+                    err.emit();
                 }
             }
         };
@@ -528,7 +528,7 @@ impl Sig for ast::Item {
                 _,
             ) => {
                 let mut text = String::new();
-                if let ast::Defaultness::Default = defaultness {
+                if defaultness.is_default() {
                     text.push_str("default ");
                 }
                 if unsafety == ast::Unsafety::Unsafe {
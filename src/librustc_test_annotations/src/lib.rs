@@ -0,0 +1,159 @@
+//! Parses the annotations of expected diagnostics in source code, as in the UI tests of the
+//! compiler.
+//!
+//! An annotation is a comment like `//~ ERROR mismatched types` on the line the diagnostic
+//! points at. `//~^ ERROR` points at the line above, with one `^` per line, and `//~| ERROR`
+//! at the same line as the previous annotation. With revisions, annotations only apply to
+//! one of them when written like `//[rev]~ ERROR`.
+//!
+//! This is shared by compiletest and `syntax::test_annotations`, which matches annotations
+//! against the diagnostics of projects embedding the compiler.
+
+// We want to be able to build this crate with a stable compiler, so no
+// `#![feature]` attributes should be added.
+
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+#[cfg(test)]
+mod tests;
+
+/// The level an annotation expects a diagnostic to have.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnnotationKind {
+    Error,
+    Warning,
+    Help,
+    Note,
+    /// The replacement text of a suggestion.
+    Suggestion,
+}
+
+/// Parses the kind of an annotation, like `ERROR` or `WARN:`, ignoring case. This also parses
+/// the levels of diagnostics as the compiler formats them, like `error` or `warning`.
+impl FromStr for AnnotationKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<AnnotationKind, ()> {
+        let s = s.to_uppercase();
+        match s.split(':').next().unwrap() {
+            "ERROR" => Ok(AnnotationKind::Error),
+            "WARN" | "WARNING" => Ok(AnnotationKind::Warning),
+            "HELP" => Ok(AnnotationKind::Help),
+            "NOTE" => Ok(AnnotationKind::Note),
+            "SUGGESTION" => Ok(AnnotationKind::Suggestion),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for AnnotationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            AnnotationKind::Error => "error",
+            AnnotationKind::Warning => "warning",
+            AnnotationKind::Help => "help",
+            AnnotationKind::Note => "note",
+            AnnotationKind::Suggestion => "suggestion",
+        })
+    }
+}
+
+/// A diagnostic expected by an annotation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    /// The 1-based line the diagnostic is expected on.
+    pub line: usize,
+    /// The expected level, or `None` if the annotation doesn't start with one, in which case
+    /// any level matches.
+    pub kind: Option<AnnotationKind>,
+    /// Text the message of the diagnostic is expected to contain.
+    pub msg: String,
+    /// The byte range of the annotation in the source, from its `//~` to the end of its line.
+    pub range: Range<usize>,
+}
+
+/// A malformed annotation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnnotationError {
+    /// The 1-based line the annotation is on.
+    pub line: usize,
+    /// The byte range of the annotation in the source, like `Annotation::range`.
+    pub range: Range<usize>,
+    pub msg: String,
+}
+
+impl fmt::Display for AnnotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+/// Parses the annotations in `src`. With `revision`, only the annotations for it are
+/// returned.
+pub fn parse_annotations(
+    src: &str,
+    revision: Option<&str>,
+) -> Result<Vec<Annotation>, AnnotationError> {
+    let tag = match revision {
+        Some(revision) => format!("//[{}]~", revision),
+        None => "//~".to_string(),
+    };
+
+    let mut annotations = Vec::new();
+    // The line of the last annotation that didn't use `//~|`, which `//~|` applies to.
+    let mut last_line = None;
+    let mut line_start = 0;
+    for (line_index, line) in src.split('\n').enumerate() {
+        let line_pos = line_start;
+        line_start += line.len() + 1;
+        let start = match line.find(&tag) {
+            Some(start) => start,
+            None => continue,
+        };
+        let range = line_pos + start..line_pos + line.len();
+        let error = |msg: &str| {
+            let range = range.clone();
+            AnnotationError { line: line_index + 1, range, msg: msg.to_string() }
+        };
+
+        let rest = &line[start + tag.len()..];
+        let (follow, adjusts) = if rest.starts_with('|') {
+            (true, 0)
+        } else {
+            (false, rest.chars().take_while(|&c| c == '^').count())
+        };
+        let rest = &rest[adjusts + follow as usize..];
+        if follow && rest.starts_with('^') {
+            return Err(error("use either `//~|` or `//~^`, not both"));
+        }
+
+        let rest = rest.trim();
+        let first_word = rest.split_whitespace().next().unwrap_or("");
+        let (kind, msg) = match first_word.parse() {
+            Ok(kind) => (Some(kind), rest[first_word.len()..].trim()),
+            Err(()) => (None, rest),
+        };
+        if kind.is_none() && msg.is_empty() {
+            return Err(error("annotation without a message"));
+        }
+
+        let line = if follow {
+            match last_line {
+                Some(line) => line,
+                None => return Err(error("`//~|` without a preceding annotation")),
+            }
+        } else if adjusts > line_index {
+            return Err(error("annotation points above the start of the file"));
+        } else {
+            // The line is 1-based, and `line_index` 0-based.
+            line_index + 1 - adjusts
+        };
+        if !follow {
+            last_line = Some(line);
+        }
+        annotations.push(Annotation { line, kind, msg: msg.to_string(), range });
+    }
+    Ok(annotations)
+}
@@ -0,0 +1,58 @@
+use super::*;
+
+const SOURCE: &str = r#"fn main() {
+    let x: u8 = ""; //~ ERROR mismatched types
+    //~| NOTE expected `u8`
+    let y = 1;
+    //~^ WARN unused variable
+    //[a]~^^ WARN: only in a
+    z; //~ cannot find value
+    //~| ERROR never emitted
+}
+"#;
+
+fn expected(line: usize, kind: Option<AnnotationKind>, msg: &str)
+    -> (usize, Option<AnnotationKind>, String)
+{
+    (line, kind, msg.to_string())
+}
+
+#[test]
+fn parse() {
+    let annotations = parse_annotations(SOURCE, None).unwrap();
+    let parsed = annotations.iter()
+        .map(|a| (a.line, a.kind, a.msg.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(parsed, vec![
+        expected(2, Some(AnnotationKind::Error), "mismatched types"),
+        expected(2, Some(AnnotationKind::Note), "expected `u8`"),
+        expected(4, Some(AnnotationKind::Warning), "unused variable"),
+        expected(7, None, "cannot find value"),
+        expected(7, Some(AnnotationKind::Error), "never emitted"),
+    ]);
+    assert_eq!(&SOURCE[annotations[0].range.clone()], "//~ ERROR mismatched types");
+
+    let annotations = parse_annotations(SOURCE, Some("a")).unwrap();
+    assert_eq!(annotations.len(), 1);
+    assert_eq!((annotations[0].line, annotations[0].kind), (4, Some(AnnotationKind::Warning)));
+    assert_eq!(annotations[0].msg, "only in a");
+}
+
+#[test]
+fn parse_errors() {
+    let error = |src: &str| parse_annotations(src, None).unwrap_err();
+    assert_eq!(error("x; //~| ERROR a").msg, "`//~|` without a preceding annotation");
+    assert_eq!(error("x; //~ ERROR a\n//~|^ ERROR b").msg, "use either `//~|` or `//~^`, not both");
+    assert_eq!(error("x; //~^ ERROR a").msg, "annotation points above the start of the file");
+    let without_message = error("x;\nx; //~");
+    assert_eq!(without_message.msg, "annotation without a message");
+    assert_eq!((without_message.line, without_message.range), (2, 6..9));
+}
+
+#[test]
+fn parse_levels() {
+    assert_eq!("error".parse(), Ok(AnnotationKind::Error));
+    assert_eq!("error: internal compiler error".parse(), Ok(AnnotationKind::Error));
+    assert_eq!("WARN:".parse(), Ok(AnnotationKind::Warning));
+    assert_eq!("failure-note".parse::<AnnotationKind>(), Err(()));
+}
@@ -337,6 +337,8 @@ fn compare_predicate_entailment<'tcx>(
                                 Some(infer::ValuePairs::Types(ExpectedFound {
                                     expected: trait_fty,
                                     found: impl_fty,
+                                    expected_span: None,
+                                    found_span: None,
                                 })),
                                 &terr);
             diag.emit();
@@ -1041,6 +1043,8 @@ pub fn compare_const_impl<'tcx>(
                                 Some(infer::ValuePairs::Types(ExpectedFound {
                                     expected: trait_ty,
                                     found: impl_ty,
+                                    expected_span: None,
+                                    found_span: None,
                                 })),
                                 &terr);
             diag.emit();
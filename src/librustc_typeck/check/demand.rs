@@ -80,7 +80,10 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
         } else {
             self.misc(cause_span)
         };
-        self.demand_eqtype_with_origin(&cause, expected, actual).map(|mut err| err.emit());
+        if let Some(mut err) = self.demand_eqtype_with_origin(&cause, expected, actual) {
+            self.label_at_binding(&mut err);
+            err.emit();
+        }
     }
 
 
@@ -115,6 +118,7 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
             Err(e) => e
         };
 
+        let e = e.with_spans(self.let_ty_span(expr), None);
         let cause = self.misc(expr.span);
         let expr_ty = self.resolve_type_vars_with_obligations(checked_ty);
         let mut err = self.report_mismatched_types(&cause, expected, expr_ty, e);
@@ -133,6 +137,23 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
         (expected, Some(err))
     }
 
+    /// Returns the span of the type annotation of the `let` statement initialized by `expr`, if
+    /// there is one, since that's where the type `expr` is coerced to comes from.
+    fn let_ty_span(&self, expr: &hir::Expr) -> Option<Span> {
+        let parent = self.tcx.hir().get_parent_node(expr.hir_id);
+        match self.tcx.hir().find(parent) {
+            Some(Node::Local(hir::Local { ty: Some(ty), init: Some(init), .. }))
+                if init.hir_id == expr.hir_id =>
+            {
+                match ty.node {
+                    hir::TyKind::Infer => None,
+                    _ => Some(ty.span),
+                }
+            }
+            _ => None,
+        }
+    }
+
     /// Returns whether the expected type is `bool` and the expression is `x = y`.
     pub fn is_assign_to_bool(&self, expr: &hir::Expr, expected: Ty<'tcx>) -> bool {
         if let hir::ExprKind::Assign(..) = expr.node {
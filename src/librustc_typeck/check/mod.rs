@@ -149,6 +149,7 @@ use self::autoderef::Autoderef;
 use self::callee::DeferredCallResolution;
 use self::coercion::{CoerceMany, DynamicCoerceMany};
 pub use self::compare_method::{compare_impl_method, compare_const_impl};
+pub use self::pat::{pat_shape, PatConst, PatCtor, PatShape};
 use self::method::{MethodCallee, SelfSource};
 use self::TupleArgumentsFlag::*;
 
@@ -602,6 +603,10 @@ pub struct FnCtxt<'a, 'tcx> {
     /// Whether any child nodes have any type errors.
     has_errors: Cell<bool>,
 
+    /// The name and type of the binding `x` of each `x @ subpattern` whose subpattern is being
+    /// checked, innermost last, to point at it when a part of the subpattern doesn't match.
+    at_bindings: RefCell<Vec<(ast::Ident, Ty<'tcx>)>>,
+
     enclosing_breakables: RefCell<EnclosingBreakables<'tcx>>,
 
     inh: &'a Inherited<'a, 'tcx>,
@@ -1913,29 +1918,10 @@ pub fn check_simd(tcx: TyCtxt<'_>, sp: Span, def_id: DefId) {
 
 fn check_packed(tcx: TyCtxt<'_>, sp: Span, def_id: DefId) {
     let repr = tcx.adt_def(def_id).repr;
-    if repr.packed() {
-        for attr in tcx.get_attrs(def_id).iter() {
-            for r in attr::find_repr_attrs(&tcx.sess.parse_sess, attr) {
-                if let attr::ReprPacked(pack) = r {
-                    if let Some(repr_pack) = repr.pack {
-                        if pack as u64 != repr_pack.bytes() {
-                            struct_span_err!(
-                                tcx.sess, sp, E0634,
-                                "type has conflicting packed representation hints"
-                            ).emit();
-                        }
-                    }
-                }
-            }
-        }
-        if repr.align.is_some() {
-            struct_span_err!(tcx.sess, sp, E0587,
-                             "type has conflicting packed and align representation hints").emit();
-        }
-        else if check_packed_inner(tcx, def_id, &mut Vec::new()) {
-            struct_span_err!(tcx.sess, sp, E0588,
-                "packed type cannot transitively contain a `[repr(align)]` type").emit();
-        }
+    // Conflicting `packed` and `align` hints are reported by `attr::parse_repr`.
+    if repr.packed() && repr.align.is_none() && check_packed_inner(tcx, def_id, &mut Vec::new()) {
+        struct_span_err!(tcx.sess, sp, E0588,
+            "packed type cannot transitively contain a `[repr(align)]` type").emit();
     }
 }
 
@@ -2315,6 +2301,7 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
                                                      hir::CRATE_HIR_ID)),
             diverges: Cell::new(Diverges::Maybe),
             has_errors: Cell::new(false),
+            at_bindings: RefCell::new(Vec::new()),
             enclosing_breakables: RefCell::new(EnclosingBreakables {
                 stack: Vec::new(),
                 by_id: Default::default(),
@@ -3193,6 +3180,48 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
         })
     }
 
+    /// Explains that an argument like `x = 1` is an assignment of type `()` rather than a named
+    /// argument, when its type doesn't match the parameter's.
+    fn note_assignment_argument(&self, err: &mut DiagnosticBuilder<'_>, arg: &hir::Expr) {
+        let (lhs, rhs) = match arg.node {
+            ExprKind::Assign(ref lhs, ref rhs) => (lhs, rhs),
+            _ => return,
+        };
+        let name = match lhs.node {
+            ExprKind::Path(QPath::Resolved(None, ref path)) if path.segments.len() == 1 => {
+                path.segments[0].ident
+            }
+            _ => return,
+        };
+        err.note(&format!(
+            "`{} = ...` is an assignment, of type `()`: Rust has no named arguments",
+            name,
+        ));
+        err.span_suggestion(
+            arg.span.until(rhs.span),
+            "to pass the value, remove the name",
+            String::new(),
+            Applicability::MaybeIncorrect,
+        );
+    }
+
+    /// If `arg` is an argument like `x = 1` whose name `x` couldn't be resolved, types the
+    /// assignment as an error and returns its value, to be checked as the argument instead.
+    fn recover_unresolved_named_argument(&self, arg: &'tcx hir::Expr) -> Option<&'tcx hir::Expr> {
+        let (lhs, rhs) = match arg.node {
+            ExprKind::Assign(ref lhs, ref rhs) => (lhs, rhs),
+            _ => return None,
+        };
+        match lhs.node {
+            ExprKind::Path(QPath::Resolved(None, ref path)) if path.res == Res::Err => {
+                self.check_expr(lhs);
+                self.write_ty(arg.hir_id, self.tcx.types.err);
+                Some(&**rhs)
+            }
+            _ => None,
+        }
+    }
+
     /// Generic function that factors out common logic from function calls,
     /// method calls and overloaded operators.
     fn check_argument_types(
@@ -3374,6 +3403,10 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
                 debug!("checking the argument");
                 let formal_ty = formal_tys[i];
 
+                // Resolve already reported the undeclared name of an argument like `x = 1`,
+                // so check its value instead, as if the name was dropped.
+                let arg = self.recover_unresolved_named_argument(arg).unwrap_or(arg);
+
                 // The special-cased logic below has three functions:
                 // 1. Provide as good of an expected type as possible.
                 let expected = Expectation::rvalue_hint(self, expected_arg_tys[i]);
@@ -3386,7 +3419,12 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
                 let coerce_ty = expected.only_has_type(self).unwrap_or(formal_ty);
                 // We're processing function arguments so we definitely want to use
                 // two-phase borrows.
-                self.demand_coerce(&arg, checked_ty, coerce_ty, AllowTwoPhase::Yes);
+                let (_, err) =
+                    self.demand_coerce_diag(&arg, checked_ty, coerce_ty, AllowTwoPhase::Yes);
+                if let Some(mut err) = err {
+                    self.note_assignment_argument(&mut err, &arg);
+                    err.emit();
+                }
                 final_arg_types.push((i, coerce_ty));
 
                 // 3. Relate the expected type and the formal one,
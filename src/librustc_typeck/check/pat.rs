@@ -3,15 +3,19 @@ use crate::util::nodemap::FxHashMap;
 use errors::{Applicability, DiagnosticBuilder, pluralise};
 use rustc::hir::{self, PatKind, Pat, HirId};
 use rustc::hir::def::{Res, DefKind, CtorKind};
+use rustc::hir::def_id::DefId;
+use rustc::hir::intravisit::{NestedVisitorMap, Visitor};
 use rustc::hir::pat_util::EnumerateAndAdjustIterator;
 use rustc::hir::ptr::P;
 use rustc::infer;
 use rustc::infer::type_variable::{TypeVariableOrigin, TypeVariableOriginKind};
-use rustc::ty::{self, Ty, BindingMode, TypeFoldable};
-use rustc::ty::subst::Kind;
+use rustc::traits::ObligationCauseCode;
+use rustc::ty::{self, Ty, TyCtxt, BindingMode, TypeFoldable};
+use rustc::ty::subst::{Kind, SubstsRef};
 use syntax::ast;
-use syntax::util::lev_distance::find_best_match_for_name;
-use syntax_pos::Span;
+use syntax::print::pprust;
+use syntax::util::lev_distance::find_best_matches_for_name_by;
+use syntax_pos::{BytePos, InnerSpan, MultiSpan, Span};
 use syntax_pos::hygiene::DesugaringKind;
 
 use std::collections::hash_map::Entry::{Occupied, Vacant};
@@ -69,15 +73,17 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
         let ty = match &pat.node {
             PatKind::Wild => expected,
             PatKind::Lit(lt) => self.check_pat_lit(pat.span, lt, expected, discrim_span),
-            PatKind::Range(begin, end, _) => {
-                match self.check_pat_range(pat.span, begin, end, expected, discrim_span) {
+            PatKind::Range(begin, end, end_kind) => {
+                match self.check_pat_range(pat, begin, end, *end_kind, expected, discrim_span) {
                     None => return,
                     Some(ty) => ty,
                 }
             }
-            PatKind::Binding(ba, var_id, _, sub) => {
+            PatKind::Binding(ba, var_id, ident, sub) => {
                 let sub = sub.as_deref();
-                self.check_pat_ident(pat, *ba, *var_id, sub, expected, def_bm, discrim_span)
+                self.check_pat_ident(
+                    pat, *ba, *var_id, *ident, sub, expected, def_bm, discrim_span,
+                )
             }
             PatKind::TupleStruct(qpath, subpats, ddpos) => {
                 self.check_pat_tuple_struct(
@@ -242,6 +248,9 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
         mut def_bm: BindingMode,
     ) -> (Ty<'tcx>, BindingMode) {
         let mut expected = self.resolve_type_vars_with_obligations(&expected);
+        if let PatKind::Slice(..) = pat.node {
+            expected = self.resolve_slice_pat_scrutinee(pat.span, expected);
+        }
 
         // Peel off as many `&` or `&mut` from the scrutinee type as possible. For example,
         // for `match &&&mut Some(5)` the loop runs three times, aborting when it reaches
@@ -282,6 +291,22 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
         (expected, def_bm)
     }
 
+    /// Slice patterns look through any references to the array or slice they match, so unlike
+    /// for other patterns, the type they are matched against has to be known up front.
+    fn resolve_slice_pat_scrutinee(&self, span: Span, expected: Ty<'tcx>) -> Ty<'tcx> {
+        if !expected.is_ty_var() {
+            return expected;
+        }
+        if !self.is_tainted_by_errors() {
+            self.need_type_info_err((**self).body_id, span, expected)
+                .note("the type matched by a slice pattern must be known at this point")
+                .help("a slice pattern can match an array, a slice, or a reference to either")
+                .emit();
+        }
+        self.demand_suptype(span, self.tcx.types.err, expected);
+        self.tcx.types.err
+    }
+
     fn check_pat_lit(
         &self,
         span: Span,
@@ -322,6 +347,7 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
         //
         // then that's equivalent to there existing a LUB.
         if let Some(mut err) = self.demand_suptype_diag(span, expected, pat_ty) {
+            self.label_at_binding(&mut err);
             err.emit_unless(discrim_span
                 .filter(|&s| {
                     // In the case of `if`- and `while`-expressions we've already checked
@@ -337,12 +363,14 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
 
     fn check_pat_range(
         &self,
-        span: Span,
+        pat: &'tcx Pat,
         begin: &'tcx hir::Expr,
         end: &'tcx hir::Expr,
+        end_kind: hir::RangeEnd,
         expected: Ty<'tcx>,
         discrim_span: Option<Span>,
     ) -> Option<Ty<'tcx>> {
+        let span = pat.span;
         let lhs_ty = self.check_expr(begin);
         let rhs_ty = self.check_expr(end);
 
@@ -382,6 +410,9 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
                         type between two end-points, you can use a guard."
                     );
             }
+            if !lhs_compat && !rhs_compat {
+                self.suggest_range_guard(&mut err, pat, begin, end, end_kind);
+            }
             err.emit();
             return None;
         }
@@ -396,11 +427,45 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
         Some(common_type)
     }
 
+    /// Suggests replacing the range pattern `pat`, if it's all of a match arm's pattern, with a
+    /// binding and a guard checking that the binding is within the range.
+    fn suggest_range_guard(
+        &self,
+        err: &mut DiagnosticBuilder<'_>,
+        pat: &'tcx Pat,
+        begin: &'tcx hir::Expr,
+        end: &'tcx hir::Expr,
+        end_kind: hir::RangeEnd,
+    ) {
+        let hir = self.tcx.hir();
+        match hir.find(hir.get_parent_node(pat.hir_id)) {
+            Some(hir::Node::Arm(arm)) if arm.guard.is_none() && arm.pats.len() == 1 => {}
+            _ => return,
+        }
+        let source_map = self.tcx.sess.source_map();
+        if let (Ok(begin), Ok(end)) = (
+            source_map.span_to_snippet(begin.span),
+            source_map.span_to_snippet(end.span),
+        ) {
+            let op = match end_kind {
+                hir::RangeEnd::Included => "..=",
+                hir::RangeEnd::Excluded => "..",
+            };
+            err.span_suggestion(
+                pat.span,
+                "use a binding and a guard to match values in this range",
+                format!("x if ({}{}{}).contains(&x)", begin, op, end),
+                Applicability::MaybeIncorrect,
+            );
+        }
+    }
+
     fn check_pat_ident(
         &self,
         pat: &Pat,
         ba: hir::BindingAnnotation,
         var_id: HirId,
+        ident: ast::Ident,
         sub: Option<&'tcx Pat>,
         expected: Ty<'tcx>,
         def_bm: BindingMode,
@@ -444,17 +509,81 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
         // If there are multiple arms, make sure they all agree on
         // what the type of the binding `x` ought to be.
         if var_id != pat.hir_id {
-            let vt = self.local_ty(pat.span, var_id).decl_ty;
-            self.demand_eqtype_pat(pat.span, vt, local_ty, discrim_span);
+            self.check_binding_alt_eq_ty(pat, var_id, local_ty, bm, discrim_span);
         }
 
         if let Some(p) = sub {
+            // The subpattern matches the same value as `x`, keep track of it to point at `x`
+            // if it doesn't.
+            self.at_bindings.borrow_mut().push((ident, expected));
             self.check_pat(&p, expected, def_bm, discrim_span);
+            self.at_bindings.borrow_mut().pop();
         }
 
         local_ty
     }
 
+    /// Points at the binding `x` of the innermost `x @ subpattern` being checked, if any, when a
+    /// part of `subpattern` doesn't match the value bound to `x`.
+    pub(super) fn label_at_binding(&self, err: &mut DiagnosticBuilder<'_>) {
+        let at_binding = self.at_bindings.borrow().last().cloned();
+        if let Some((ident, ty)) = at_binding {
+            err.span_label(ident.span, format!(
+                "`{}` binds the whole value, of type `{}`",
+                ident,
+                self.resolve_vars_if_possible(&ty),
+            ));
+        }
+    }
+
+    /// Requires the binding `pat` to have the same type as the first binding of the same
+    /// variable, `var_id`, in another alternative of an or-pattern, pointing at both if not.
+    fn check_binding_alt_eq_ty(
+        &self,
+        pat: &Pat,
+        var_id: HirId,
+        ty: Ty<'tcx>,
+        bm: BindingMode,
+        discrim_span: Option<Span>,
+    ) {
+        let var_ty = self.local_ty(pat.span, var_id).decl_ty;
+        let cause = match discrim_span {
+            Some(span) => self.cause(
+                pat.span,
+                ObligationCauseCode::MatchExpressionArmPattern { span, ty: var_ty },
+            ),
+            None => self.misc(pat.span),
+        };
+        let mut err = match self.demand_eqtype_with_origin(&cause, var_ty, ty) {
+            Some(err) => err,
+            None => return,
+        };
+        let hir = self.tcx.hir();
+        let var_name = hir.name(var_id);
+        err.span_label(hir.span(var_id), format!(
+            "first binding of `{}` has type `{}`",
+            var_name,
+            self.resolve_vars_if_possible(&var_ty),
+        ));
+        let var_bm = self.inh.tables.borrow().pat_binding_modes().get(var_id).cloned();
+        if let Some(var_bm) = var_bm {
+            let describe = |bm| match bm {
+                ty::BindByValue(_) => "by value",
+                ty::BindByReference(hir::MutImmutable) => "by reference",
+                ty::BindByReference(hir::MutMutable) => "by mutable reference",
+            };
+            if describe(var_bm) != describe(bm) {
+                err.note(&format!(
+                    "`{}` is bound {} here, but {} in its first binding",
+                    var_name,
+                    describe(bm),
+                    describe(var_bm),
+                ));
+            }
+        }
+        err.emit();
+    }
+
     fn borrow_pat_suggestion(
         &self,
         err: &mut DiagnosticBuilder<'_>,
@@ -813,13 +942,14 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
         let mut no_field_errors = true;
 
         let mut inexistent_fields = vec![];
+        let mut duplicate_fields = vec![];
         // Typecheck each field.
-        for field in fields {
+        for (i, field) in fields.iter().enumerate() {
             let span = field.span;
             let ident = tcx.adjust_ident(field.ident, variant.def_id);
             let field_ty = match used_fields.entry(ident) {
                 Occupied(occupied) => {
-                    self.error_field_already_bound(span, field.ident, *occupied.get());
+                    duplicate_fields.push((i, *occupied.get()));
                     no_field_errors = false;
                     tcx.types.err
                 }
@@ -832,7 +962,7 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
                             self.field_ty(span, f, substs)
                         })
                         .unwrap_or_else(|| {
-                            inexistent_fields.push(field.ident);
+                            inexistent_fields.push((field.ident, &*field.pat));
                             no_field_errors = false;
                             tcx.types.err
                         })
@@ -842,71 +972,207 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
             self.check_pat(&field.pat, field_ty, def_bm, None);
         }
 
+        if !duplicate_fields.is_empty() {
+            self.error_fields_already_bound(fields, &duplicate_fields);
+        }
+
         let mut unmentioned_fields = variant.fields
                 .iter()
                 .map(|field| field.ident.modern())
                 .filter(|ident| !used_fields.contains_key(&ident))
                 .collect::<Vec<_>>();
 
+        // The fields missing from the pattern are reported along with the inexistent ones.
+        let mut err = None;
         if inexistent_fields.len() > 0 && !variant.recovered {
-            self.error_inexistent_fields(
+            err = Some(self.error_inexistent_fields(
                 kind_name,
                 &inexistent_fields,
                 &mut unmentioned_fields,
-                variant
-            );
+                variant,
+                substs,
+            ));
+        }
+        let is_union = kind_name == "union";
+        if !is_union && !etc && unmentioned_fields.len() > 0 {
+            err = Some(self.error_unmentioned_fields(err, span, &unmentioned_fields, variant));
+        }
+        if let Some(mut err) = err {
+            err.emit();
         }
 
         // Require `..` if struct has non_exhaustive attribute.
         if variant.is_field_list_non_exhaustive() && !adt.did.is_local() && !etc {
-            span_err!(tcx.sess, span, E0638,
-                      "`..` required with {} marked as non-exhaustive",
-                      kind_name);
+            let mut err = struct_span_err!(tcx.sess, span, E0638,
+                                           "`..` required with {} marked as non-exhaustive",
+                                           kind_name);
+            if let Some((sugg_span, sugg)) = self.rest_pat_suggestion(span, fields) {
+                err.span_suggestion(
+                    sugg_span,
+                    "add `..` at the end of the field list to ignore all other fields",
+                    sugg,
+                    Applicability::MachineApplicable,
+                );
+            }
+            err.emit();
         }
 
         // Report an error if incorrect number of the fields were specified.
-        if kind_name == "union" {
-            if fields.len() != 1 {
-                tcx.sess.span_err(span, "union patterns should have exactly one field");
+        if is_union && (fields.len() != 1 || etc) {
+            self.error_union_pat_fields(span, variant, fields, etc);
+        }
+        no_field_errors
+    }
+
+    /// Returns where and what to insert to add a `..` to the struct pattern at `span`, after
+    /// the last field and its trailing comma, if there is one.
+    fn rest_pat_suggestion(
+        &self,
+        span: Span,
+        fields: &'tcx [hir::FieldPat],
+    ) -> Option<(Span, String)> {
+        let source_map = self.tcx.sess.source_map();
+        let snippet = source_map.span_to_snippet(span).ok()?;
+        if !snippet.ends_with('}') {
+            return None;
+        }
+        let close_brace = span.hi() - BytePos(1);
+        match fields.last() {
+            Some(field) => {
+                let tail = field.span.shrink_to_hi().with_hi(close_brace);
+                let tail = source_map.span_to_snippet(tail).ok()?;
+                if tail.trim_start().starts_with(',') {
+                    let comma = tail.find(',')? + 1;
+                    let at = field.span.hi() + BytePos(comma as u32);
+                    Some((Span::new(at, at, span.ctxt()), " ..".to_string()))
+                } else {
+                    Some((field.span.shrink_to_hi(), ", ..".to_string()))
+                }
             }
-            if etc {
-                tcx.sess.span_err(span, "`..` cannot be used in union patterns");
+            None => {
+                let before_brace = snippet[..snippet.len() - 1].ends_with(' ');
+                let sugg = if before_brace { ".. " } else { " .. " };
+                Some((Span::new(close_brace, close_brace, span.ctxt()), sugg.to_string()))
             }
-        } else if !etc && unmentioned_fields.len() > 0 {
-            self.error_unmentioned_fields(span, &unmentioned_fields, variant);
         }
-        no_field_errors
     }
 
-    fn error_field_already_bound(&self, span: Span, ident: ast::Ident, other_field: Span) {
-        struct_span_err!(
-            self.tcx.sess, span, E0025,
-            "field `{}` bound multiple times in the pattern",
-            ident
-        )
-        .span_label(span, format!("multiple uses of `{}` in pattern", ident))
-        .span_label(other_field, format!("first use of `{}`", ident))
-        .emit();
+    /// Reports a union pattern that doesn't match exactly one field, or that has a `..`.
+    fn error_union_pat_fields(
+        &self,
+        span: Span,
+        variant: &ty::VariantDef,
+        fields: &'tcx [hir::FieldPat],
+        etc: bool,
+    ) {
+        let source_map = self.tcx.sess.source_map();
+        let snippet = source_map.span_to_snippet(span).ok();
+        let mut err = if fields.len() != 1 {
+            self.tcx.sess.struct_span_err(span, "union patterns should have exactly one field")
+        } else {
+            self.tcx.sess.struct_span_err(span, "`..` cannot be used in union patterns")
+        };
+        for field in fields.iter().skip(1) {
+            err.span_label(field.span, "extra field");
+        }
+        if etc {
+            // The `..` isn't recorded in the HIR, find it after the last field.
+            let search_start = fields.last().map_or(0, |field| field.span.hi().0 - span.lo().0);
+            let rest = snippet.as_ref().and_then(|snippet| {
+                let start = search_start as usize + snippet.get(search_start as usize..)?
+                    .find("..")?;
+                Some(span.from_inner(InnerSpan::new(start, start + 2)))
+            });
+            if let Some(rest) = rest {
+                err.span_label(rest, "not allowed in union patterns");
+            }
+        }
+        err.note("all fields of a union share the same memory, so a pattern reads exactly one");
+        if fields.len() > 1 {
+            if let Some(path) = snippet.as_ref().and_then(|snippet| snippet.split('{').next()) {
+                // Only the fields the union has are worth matching on their own.
+                let patterns = fields.iter()
+                    .filter(|field| {
+                        let ident = self.tcx.adjust_ident(field.ident, variant.def_id);
+                        variant.fields.iter().any(|f| f.ident.modern() == ident)
+                    })
+                    .map(|field| source_map.span_to_snippet(field.span).ok()
+                        .map(|field| format!("{}{{ {} }}", path, field)))
+                    .collect::<Option<Vec<_>>>();
+                if let Some(patterns) = patterns.filter(|patterns| !patterns.is_empty()) {
+                    err.span_suggestions(
+                        span,
+                        "match a single field here, and the others in nested matches",
+                        patterns.into_iter(),
+                        Applicability::MaybeIncorrect,
+                    );
+                }
+            }
+        }
+        err.emit();
     }
 
+    /// Reports all the fields bound more than once in a struct pattern in one diagnostic, given
+    /// the index of each repeated field in `fields` and the span of its first use.
+    fn error_fields_already_bound(
+        &self,
+        fields: &'tcx [hir::FieldPat],
+        duplicate_fields: &[(usize, Span)],
+    ) {
+        let mut names = vec![];
+        let mut spans = MultiSpan::new();
+        let mut removals = vec![];
+        for &(i, first_use) in duplicate_fields {
+            let ident = fields[i].ident;
+            if !names.contains(&ident) {
+                names.push(ident);
+                spans.push_primary_role(first_use, format!("first use of `{}`", ident));
+            }
+            spans.push_primary_role(
+                fields[i].span,
+                format!("multiple uses of `{}` in pattern", ident),
+            );
+            // The first use comes before, so there's a previous field to remove the comma after.
+            removals.push((fields[i].span.with_lo(fields[i - 1].span.hi()), String::new()));
+        }
+        let names = names.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>();
+        let mut err = struct_span_err!(
+            self.tcx.sess, spans, E0025,
+            "field{} {} bound multiple times in the pattern",
+            pluralise!(names.len()),
+            names.join(", "),
+        );
+        err.multipart_suggestion(
+            &format!("remove the repeated field{}", pluralise!(removals.len())),
+            removals,
+            Applicability::MaybeIncorrect,
+        );
+        err.emit();
+    }
+
+    /// Reports the fields the variant doesn't have.
+    ///
+    /// Among the fields with a similar name, the ones whose type the pattern bound to the
+    /// inexistent field can match are suggested first.
     fn error_inexistent_fields(
         &self,
         kind_name: &str,
-        inexistent_fields: &[ast::Ident],
+        inexistent_fields: &[(ast::Ident, &'tcx hir::Pat)],
         unmentioned_fields: &mut Vec<ast::Ident>,
         variant: &ty::VariantDef,
-    ) {
+        substs: SubstsRef<'tcx>,
+    ) -> DiagnosticBuilder<'tcx> {
         let tcx = self.tcx;
         let (field_names, t, plural) = if inexistent_fields.len() == 1 {
-            (format!("a field named `{}`", inexistent_fields[0]), "this", "")
+            (format!("a field named `{}`", inexistent_fields[0].0), "this", "")
         } else {
             (format!("fields named {}",
                         inexistent_fields.iter()
-                        .map(|ident| format!("`{}`", ident))
+                        .map(|(ident, _)| format!("`{}`", ident))
                         .collect::<Vec<String>>()
                         .join(", ")), "these", "s")
         };
-        let spans = inexistent_fields.iter().map(|ident| ident.span).collect::<Vec<_>>();
+        let spans = inexistent_fields.iter().map(|(ident, _)| ident.span).collect::<Vec<_>>();
         let mut err = struct_span_err!(tcx.sess,
                                         spans,
                                         E0026,
@@ -914,7 +1180,7 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
                                         kind_name,
                                         tcx.def_path_str(variant.def_id),
                                         field_names);
-        if let Some(ident) = inexistent_fields.last() {
+        if let Some(&(ident, pat)) = inexistent_fields.last() {
             err.span_label(ident.span,
                             format!("{} `{}` does not have {} field{}",
                                     kind_name,
@@ -923,8 +1189,12 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
                                     plural));
             if plural == "" {
                 let input = unmentioned_fields.iter().map(|field| &field.name);
-                let suggested_name =
-                    find_best_match_for_name(input, &ident.as_str(), None);
+                let suggested_name = find_best_matches_for_name_by(
+                    input,
+                    &ident.as_str(),
+                    None,
+                    |name| self.field_fits_pat(variant, substs, name, pat),
+                ).into_iter().next();
                 if let Some(suggested_name) = suggested_name {
                     err.span_suggestion(
                         ident.span,
@@ -950,15 +1220,42 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
                     it explicitly."
             );
         }
-        err.emit();
+        err
+    }
+
+    /// Whether the field `name` of `variant` has a type `pat` can match. Patterns whose type
+    /// isn't known yet fit no field, so that they don't change the order of the suggestions.
+    fn field_fits_pat(
+        &self,
+        variant: &ty::VariantDef,
+        substs: SubstsRef<'tcx>,
+        name: ast::Name,
+        pat: &'tcx hir::Pat,
+    ) -> bool {
+        let pat_ty = match self.tables.borrow().node_type_opt(pat.hir_id) {
+            Some(ty) => self.resolve_vars_if_possible(&ty),
+            None => return false,
+        };
+        if pat_ty.references_error() || pat_ty.is_ty_var() {
+            return false;
+        }
+        variant.fields.iter()
+            .find(|field| field.ident.name == name)
+            .map_or(false, |field| {
+                let field_ty = self.field_ty(pat.span, field, substs);
+                self.can_eq(self.param_env, field_ty, pat_ty).is_ok()
+            })
     }
 
+    /// Reports the fields missing from a pattern without `..`, as a note on `err` if there is
+    /// one.
     fn error_unmentioned_fields(
         &self,
+        err: Option<DiagnosticBuilder<'tcx>>,
         span: Span,
         unmentioned_fields: &[ast::Ident],
         variant: &ty::VariantDef,
-    ) {
+    ) -> DiagnosticBuilder<'tcx> {
         let field_names = if unmentioned_fields.len() == 1 {
             format!("field `{}`", unmentioned_fields[0])
         } else {
@@ -968,6 +1265,13 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
                 .join(", ");
             format!("fields {}", fields)
         };
+        if let Some(mut err) = err {
+            err.note(&format!("the pattern also does not mention {}", field_names));
+            if variant.ctor_kind == CtorKind::Fn {
+                err.note("trying to match a tuple variant with a struct variant pattern");
+            }
+            return err;
+        }
         let mut diag = struct_span_err!(
             self.tcx.sess, span, E0027,
             "pattern does not mention {}",
@@ -985,7 +1289,7 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
                     ignore unwanted fields."
             );
         }
-        diag.emit();
+        diag
     }
 
     fn check_pat_box(
@@ -1179,3 +1483,265 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
         err.emit();
     }
 }
+
+/// The shape of a type-checked pattern: its constructor, and the shapes of the fields of that
+/// constructor. This is the information exhaustiveness checking and other analyses of patterns
+/// start from, without the bindings, binding modes and spellings the HIR also records.
+#[derive(Clone, Debug)]
+pub struct PatShape {
+    pub span: Span,
+    pub ctor: PatCtor,
+    /// One shape per field of `ctor`, with `PatCtor::Wild` for fields left out by `..`.
+    pub fields: Vec<PatShape>,
+}
+
+#[derive(Clone, Debug)]
+pub enum PatCtor {
+    /// `_`, a binding without a subpattern, or a pattern that failed to type-check.
+    Wild,
+    /// A pattern of a struct, union or enum variant, identified by the variant's `DefId`.
+    Variant(DefId),
+    /// The only constructor of a tuple, reference or box. Implicit dereferences of the
+    /// scrutinee, from default binding modes, are included as such constructors too.
+    Single,
+    /// A literal, or a path to a constant.
+    Const(PatConst),
+    /// A range, with its end-points.
+    Range(PatConst, PatConst, hir::RangeEnd),
+    /// A slice or array pattern, with `prefix + suffix` fields and a `..` between them if
+    /// `has_rest`.
+    Slice { prefix: usize, suffix: usize, has_rest: bool },
+    /// An or-pattern, with one field per alternative.
+    Or,
+}
+
+#[derive(Clone, Debug)]
+pub enum PatConst {
+    /// A literal, preceded by a `-` if `negated`.
+    Lit { lit: ast::LitKind, negated: bool },
+    /// A path to a constant or associated constant.
+    Path(DefId),
+    /// An expression that isn't a valid pattern constant, which has already been reported.
+    Err,
+}
+
+impl PatShape {
+    /// Describes the shape in a pattern-like syntax, e.g. `std::option::Option::Some((_, 1))`.
+    /// Single constructors of one field are written like one-element tuples, e.g. `(_,)`.
+    pub fn describe(&self, tcx: TyCtxt<'_>) -> String {
+        let fields = || self.fields.iter().map(|field| field.describe(tcx)).collect::<Vec<_>>();
+        match self.ctor {
+            PatCtor::Wild => "_".to_string(),
+            PatCtor::Variant(def_id) if self.fields.is_empty() => tcx.def_path_str(def_id),
+            PatCtor::Variant(def_id) => {
+                format!("{}({})", tcx.def_path_str(def_id), fields().join(", "))
+            }
+            PatCtor::Single if self.fields.len() == 1 => format!("({},)", fields()[0]),
+            PatCtor::Single => format!("({})", fields().join(", ")),
+            PatCtor::Const(ref value) => value.describe(tcx),
+            PatCtor::Range(ref lo, ref hi, end) => {
+                let end = match end {
+                    hir::RangeEnd::Included => "..=",
+                    hir::RangeEnd::Excluded => "..",
+                };
+                format!("{}{}{}", lo.describe(tcx), end, hi.describe(tcx))
+            }
+            PatCtor::Slice { prefix, has_rest, .. } => {
+                let mut fields = fields();
+                if has_rest {
+                    fields.insert(prefix, "..".to_string());
+                }
+                format!("[{}]", fields.join(", "))
+            }
+            PatCtor::Or => fields().join(" | "),
+        }
+    }
+}
+
+impl PatConst {
+    fn describe(&self, tcx: TyCtxt<'_>) -> String {
+        match *self {
+            PatConst::Lit { ref lit, negated } => {
+                let sign = if negated { "-" } else { "" };
+                format!("{}{}", sign, pprust::literal_to_string(lit.to_lit_token()))
+            }
+            PatConst::Path(def_id) => tcx.def_path_str(def_id),
+            PatConst::Err => "{error}".to_string(),
+        }
+    }
+}
+
+/// Computes the shape of `pat`, using the results of type-checking the body it belongs to.
+pub fn pat_shape<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    tables: &ty::TypeckTables<'tcx>,
+    pat: &hir::Pat,
+) -> PatShape {
+    let leaf = |ctor| PatShape { span: pat.span, ctor, fields: vec![] };
+    let wild = |span| PatShape { span, ctor: PatCtor::Wild, fields: vec![] };
+    let mut shape = match pat.node {
+        PatKind::Wild | PatKind::Binding(.., None) => leaf(PatCtor::Wild),
+        PatKind::Binding(.., Some(ref sub)) => pat_shape(tcx, tables, sub),
+        PatKind::Path(ref qpath) => match tables.qpath_res(qpath, pat.hir_id) {
+            Res::Def(DefKind::Const, def_id) | Res::Def(DefKind::AssocConst, def_id) => {
+                leaf(PatCtor::Const(PatConst::Path(def_id)))
+            }
+            res => match pat_variant(tables, pat, res) {
+                Some(variant) => leaf(PatCtor::Variant(variant.def_id)),
+                None => leaf(PatCtor::Wild),
+            },
+        },
+        PatKind::TupleStruct(ref qpath, ref subpats, ddpos) => {
+            match pat_variant(tables, pat, tables.qpath_res(qpath, pat.hir_id)) {
+                None => leaf(PatCtor::Wild),
+                Some(variant) => {
+                    let mut fields = vec![wild(pat.span); variant.fields.len()];
+                    let subpats = subpats.iter().enumerate_and_adjust(fields.len(), ddpos);
+                    for (i, subpat) in subpats {
+                        if i < fields.len() {
+                            fields[i] = pat_shape(tcx, tables, subpat);
+                        }
+                    }
+                    PatShape { span: pat.span, ctor: PatCtor::Variant(variant.def_id), fields }
+                }
+            }
+        }
+        PatKind::Struct(ref qpath, ref field_pats, _) => {
+            match pat_variant(tables, pat, tables.qpath_res(qpath, pat.hir_id)) {
+                None => leaf(PatCtor::Wild),
+                Some(variant) => {
+                    let mut fields = vec![wild(pat.span); variant.fields.len()];
+                    for field_pat in field_pats {
+                        match tables.field_indices().get(field_pat.hir_id) {
+                            Some(&i) if i < fields.len() => {
+                                fields[i] = pat_shape(tcx, tables, &field_pat.pat);
+                            }
+                            _ => {}
+                        }
+                    }
+                    PatShape { span: pat.span, ctor: PatCtor::Variant(variant.def_id), fields }
+                }
+            }
+        }
+        PatKind::Tuple(ref subpats, ddpos) => {
+            let arity = match tables.node_type(pat.hir_id).sty {
+                ty::Tuple(tys) => tys.len(),
+                _ => subpats.len(),
+            };
+            let mut fields = vec![wild(pat.span); arity];
+            for (i, subpat) in subpats.iter().enumerate_and_adjust(arity, ddpos) {
+                if i < arity {
+                    fields[i] = pat_shape(tcx, tables, subpat);
+                }
+            }
+            PatShape { span: pat.span, ctor: PatCtor::Single, fields }
+        }
+        PatKind::Box(ref inner) | PatKind::Ref(ref inner, _) => PatShape {
+            span: pat.span,
+            ctor: PatCtor::Single,
+            fields: vec![pat_shape(tcx, tables, inner)],
+        },
+        PatKind::Lit(ref expr) => leaf(PatCtor::Const(pat_const(tables, expr))),
+        PatKind::Range(ref lo, ref hi, end) => {
+            leaf(PatCtor::Range(pat_const(tables, lo), pat_const(tables, hi), end))
+        }
+        PatKind::Slice(ref before, ref slice, ref after) => PatShape {
+            span: pat.span,
+            ctor: PatCtor::Slice {
+                prefix: before.len(),
+                suffix: after.len(),
+                has_rest: slice.is_some(),
+            },
+            fields: before.iter().chain(after)
+                .map(|subpat| pat_shape(tcx, tables, subpat))
+                .collect(),
+        },
+        PatKind::Or(ref pats) => PatShape {
+            span: pat.span,
+            ctor: PatCtor::Or,
+            fields: pats.iter().map(|alt| pat_shape(tcx, tables, alt)).collect(),
+        },
+    };
+
+    // The scrutinee is dereferenced before being matched against `pat`, once per adjustment.
+    if let Some(adjustments) = tables.pat_adjustments().get(pat.hir_id) {
+        for _ in adjustments {
+            shape = PatShape { span: pat.span, ctor: PatCtor::Single, fields: vec![shape] };
+        }
+    }
+    shape
+}
+
+/// The variant `pat`, a pattern resolved to `res`, matches, if it type-checked as one. Patterns
+/// can name a variant through a type alias or `Self`, and erroneous ones can name a function, a
+/// static, or a variant of another type than the one they have.
+fn pat_variant<'tcx>(
+    tables: &ty::TypeckTables<'tcx>,
+    pat: &hir::Pat,
+    res: Res,
+) -> Option<&'tcx ty::VariantDef> {
+    let adt = match tables.node_type_opt(pat.hir_id)?.sty {
+        ty::Adt(adt, _) => adt,
+        _ => return None,
+    };
+    match res {
+        Res::Def(DefKind::Variant, def_id) => {
+            adt.variants.iter().find(|variant| variant.def_id == def_id)
+        }
+        Res::Def(DefKind::Ctor(..), def_id) => {
+            adt.variants.iter().find(|variant| variant.ctor_def_id == Some(def_id))
+        }
+        Res::Def(DefKind::Struct, _)
+        | Res::Def(DefKind::Union, _)
+        | Res::Def(DefKind::TyAlias, _)
+        | Res::Def(DefKind::AssocTy, _)
+        | Res::SelfTy(..)
+        | Res::SelfCtor(..) if !adt.is_enum() => Some(adt.non_enum_variant()),
+        _ => None,
+    }
+}
+
+fn pat_const(tables: &ty::TypeckTables<'_>, expr: &hir::Expr) -> PatConst {
+    match expr.node {
+        hir::ExprKind::Lit(ref lit) => PatConst::Lit { lit: lit.node.clone(), negated: false },
+        hir::ExprKind::Unary(hir::UnNeg, ref inner) => match pat_const(tables, inner) {
+            PatConst::Lit { lit, negated: false } => PatConst::Lit { lit, negated: true },
+            _ => PatConst::Err,
+        },
+        hir::ExprKind::Path(ref qpath) => match tables.qpath_res(qpath, expr.hir_id) {
+            Res::Def(DefKind::Const, def_id) | Res::Def(DefKind::AssocConst, def_id) => {
+                PatConst::Path(def_id)
+            }
+            _ => PatConst::Err,
+        },
+        _ => PatConst::Err,
+    }
+}
+
+/// Reports the shape of each pattern of `body` as an error, for the `rustc_dump_pat_shapes`
+/// unit-testing attribute.
+pub fn dump_pat_shapes<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    tables: &ty::TypeckTables<'tcx>,
+    body: &'tcx hir::Body,
+) {
+    struct PatShapeDumper<'a, 'tcx> {
+        tcx: TyCtxt<'tcx>,
+        tables: &'a ty::TypeckTables<'tcx>,
+    }
+
+    impl<'a, 'tcx> Visitor<'tcx> for PatShapeDumper<'a, 'tcx> {
+        fn nested_visit_map<'this>(&'this mut self) -> NestedVisitorMap<'this, 'tcx> {
+            NestedVisitorMap::None
+        }
+
+        fn visit_pat(&mut self, pat: &'tcx hir::Pat) {
+            // The shape includes the subpatterns, so they aren't visited on their own.
+            let shape = pat_shape(self.tcx, self.tables, pat);
+            let msg = format!("pat shape: {}", shape.describe(self.tcx));
+            self.tcx.sess.span_err(pat.span, &msg);
+        }
+    }
+
+    PatShapeDumper { tcx, tables }.visit_body(body);
+}
@@ -2,7 +2,7 @@
 // unresolved type variables and replaces "ty_var" types with their
 // substitutions.
 
-use crate::check::FnCtxt;
+use crate::check::{pat, FnCtxt};
 use rustc::hir;
 use rustc::hir::def_id::{DefId, DefIndex};
 use rustc::hir::intravisit::{self, NestedVisitorMap, Visitor};
@@ -76,6 +76,11 @@ impl<'a, 'tcx> FnCtxt<'a, 'tcx> {
 
         wbcx.tables.tainted_by_errors = self.is_tainted_by_errors();
 
+        // This is a unit-testing mechanism too.
+        if self.tcx.has_attr(item_def_id, sym::rustc_dump_pat_shapes) {
+            pat::dump_pat_shapes(self.tcx, &wbcx.tables, body);
+        }
+
         debug!(
             "writeback: tables for {:?} are {:#?}",
             item_def_id, wbcx.tables
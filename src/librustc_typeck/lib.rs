@@ -112,6 +112,7 @@ use std::iter;
 
 use astconv::{AstConv, Bounds};
 pub use collect::checked_type_of;
+pub use check::{pat_shape, PatConst, PatCtor, PatShape};
 
 pub struct TypeAndSubsts<'tcx> {
     substs: SubstsRef<'tcx>,
@@ -265,7 +265,8 @@ impl Options {
         }
 
         let color = session::config::parse_color(&matches);
-        let (json_rendered, _artifacts) = session::config::parse_json(&matches);
+        let (json_rendered, _artifacts, _summary, _compact, _lsp, _byte_columns, _utf16_columns) =
+            session::config::parse_json(&matches);
         let error_format = session::config::parse_error_format(&matches, color, json_rendered);
 
         let codegen_options = build_codegen_options(matches, error_format);
@@ -194,7 +194,7 @@ pub fn new_handler(error_format: ErrorOutputType,
                     sessopts.debugging_opts.teach,
                     sessopts.debugging_opts.terminal_width,
                     false,
-                ).ui_testing(ui_testing)
+                ).ui_testing(ui_testing).mark_fixes(kind.marks_fixes())
             )
         },
         ErrorOutputType::Json { pretty, json_rendered } => {
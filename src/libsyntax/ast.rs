@@ -1956,10 +1956,20 @@ pub enum Constness {
 
 #[derive(Copy, Clone, PartialEq, RustcEncodable, RustcDecodable, Debug)]
 pub enum Defaultness {
-    Default,
+    /// `default`, with the span of the keyword.
+    Default(Span),
     Final,
 }
 
+impl Defaultness {
+    pub fn is_default(self) -> bool {
+        match self {
+            Defaultness::Default(_) => true,
+            Defaultness::Final => false,
+        }
+    }
+}
+
 impl fmt::Display for Unsafety {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Display::fmt(
@@ -2021,6 +2031,9 @@ pub struct Mod {
     pub items: Vec<P<Item>>,
     /// `true` for `mod foo { .. }`; `false` for `mod foo;`.
     pub inline: bool,
+    /// Outer attributes at the end of the module, which aren't followed by an item they could
+    /// apply to. They have already been reported as an error, and are ignored by later passes.
+    pub dangling_attrs: Vec<Attribute>,
 }
 
 /// Foreign module declaration.
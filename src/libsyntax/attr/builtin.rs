@@ -3,10 +3,12 @@
 use crate::ast::{self, Attribute, MetaItem, NestedMetaItem};
 use crate::early_buffered_lints::BufferedEarlyLintId;
 use crate::ext::base::ExtCtxt;
-use crate::feature_gate::{Features, GatedCfg};
+use crate::feature_gate::{emit_feature_err, Features, GateIssue, GatedCfg};
 use crate::parse::ParseSess;
+use crate::util::lev_distance::find_best_match_for_name;
 
 use errors::{Applicability, Handler};
+use rustc_data_structures::sync;
 use syntax_pos::hygiene::Transparency;
 use syntax_pos::{symbol::Symbol, symbol::sym, Span};
 
@@ -15,8 +17,15 @@ use super::{mark_used, MetaItemKind};
 enum AttrError {
     MultipleItem(String),
     UnknownMetaItem(String, &'static [&'static str]),
+    IncorrectMetaItem(/* in_deprecated */ bool),
     MissingSince,
+    MissingReason,
     MissingFeature,
+    MissingConstUnstableFeature,
+    MissingIssue,
+    IncorrectIssue,
+    SoftWithArguments,
+    MalformedSince(String),
     MultipleStabilityLevels,
     UnsupportedLiteral(&'static str, /* is_bytestr */ bool),
 }
@@ -56,8 +65,28 @@ fn handle_errors(sess: &ParseSess, span: Span, error: AttrError) {
                 .span_label(span, format!("expected one of {}", expected.join(", ")))
                 .emit();
         }
+        AttrError::IncorrectMetaItem(false) => span_err!(diag, span, E0539, "incorrect meta item"),
+        AttrError::IncorrectMetaItem(true) => span_err!(diag, span, E0551, "incorrect meta item"),
         AttrError::MissingSince => span_err!(diag, span, E0542, "missing 'since'"),
+        AttrError::MissingReason => span_err!(diag, span, E0543, "missing 'reason'"),
         AttrError::MissingFeature => span_err!(diag, span, E0546, "missing 'feature'"),
+        AttrError::MissingConstUnstableFeature => span_err!(diag, span, E0629,
+                                                            "missing 'feature'"),
+        AttrError::MissingIssue => span_err!(diag, span, E0547, "missing 'issue'"),
+        AttrError::IncorrectIssue => span_err!(diag, span, E0545, "incorrect 'issue'"),
+        AttrError::SoftWithArguments => {
+            diag.span_err(span, "`soft` should not have any arguments");
+        }
+        AttrError::MalformedSince(version) => {
+            diag.struct_span_err(span, "malformed version in 'since'")
+                .span_suggestion(
+                    span,
+                    "versions are written as `major.minor.patch`",
+                    format!("since = \"{}\"", version),
+                    Applicability::MachineApplicable,
+                )
+                .emit();
+        }
         AttrError::MultipleStabilityLevels => span_err!(diag, span, E0544,
                                                         "multiple stability levels"),
         AttrError::UnsupportedLiteral(
@@ -80,6 +109,45 @@ fn handle_errors(sess: &ParseSess, span: Span, error: AttrError) {
     }
 }
 
+/// Reports `error` at `span`, unless there's no `sess` to report it to.
+fn report_error(sess: Option<&ParseSess>, span: Span, error: AttrError) {
+    if let Some(sess) = sess {
+        handle_errors(sess, span, error);
+    }
+}
+
+/// Stores the value of the `key = "value"` argument `mi` into `item`. Returns `false`, after
+/// reporting why, if `mi` has no string value or `item` was already given by another argument.
+fn get_string_arg(
+    sess: Option<&ParseSess>,
+    mi: &MetaItem,
+    item: &mut Option<Symbol>,
+    in_deprecated: bool,
+) -> bool {
+    if item.is_some() {
+        report_error(sess, mi.span, AttrError::MultipleItem(mi.path.to_string()));
+        return false
+    }
+    if let Some(v) = mi.value_str() {
+        *item = Some(v);
+        return true
+    }
+    match mi.name_value_literal() {
+        Some(lit) if in_deprecated => {
+            report_error(
+                sess,
+                lit.span,
+                AttrError::UnsupportedLiteral(
+                    "literal in `deprecated` value must be a string",
+                    lit.node.is_bytestr()
+                ),
+            );
+        }
+        _ => report_error(sess, mi.span, AttrError::IncorrectMetaItem(in_deprecated)),
+    }
+    false
+}
+
 #[derive(Copy, Clone, Hash, PartialEq, RustcEncodable, RustcDecodable)]
 pub enum InlineAttr {
     None,
@@ -194,6 +262,179 @@ pub fn contains_feature_attr(attrs: &[Attribute], feature_name: Symbol) -> bool
     })
 }
 
+/// A single stability attribute, as parsed by `parse_stability_attr`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StabilityAttr {
+    /// `#[stable(feature = "..", since = "..")]` or
+    /// `#[unstable(feature = "..", reason = "..", issue = "..", soft)]`.
+    Level { feature: Symbol, level: StabilityLevel },
+    /// `#[rustc_deprecated(since = "..", reason = "..", suggestion = "..")]`.
+    RustcDeprecated(RustcDeprecation),
+    /// `#[rustc_const_unstable(feature = "..")]`.
+    ConstUnstable { feature: Symbol },
+    /// `#[rustc_promotable]`.
+    Promotable,
+    /// `#[rustc_allow_const_fn_ptr]`.
+    AllowConstFnPtr,
+}
+
+/// Parses a single stability attribute on its own, without checking how it combines with
+/// the other attributes of its item. Returns `None` if `attr` isn't a stability attribute
+/// or is malformed, in which case each malformed argument is reported to `sess`, if given.
+pub fn parse_stability_attr(sess: Option<&ParseSess>, attr: &Attribute) -> Option<StabilityAttr> {
+    use StabilityLevel::*;
+
+    if attr.path == sym::rustc_promotable {
+        return Some(StabilityAttr::Promotable)
+    }
+    if attr.path == sym::rustc_allow_const_fn_ptr {
+        return Some(StabilityAttr::AllowConstFnPtr)
+    }
+    let name = *[
+        sym::rustc_deprecated,
+        sym::rustc_const_unstable,
+        sym::unstable,
+        sym::stable,
+    ].iter().find(|&&s| attr.path == s)?;
+    let metas = match attr.meta() {
+        Some(MetaItem { node: MetaItemKind::List(metas), .. }) => metas,
+        // Malformed attribute inputs are reported against the attribute's template
+        _ => return None,
+    };
+    let expected: &'static [&'static str] = match name {
+        sym::rustc_deprecated => &["since", "reason", "suggestion"],
+        sym::rustc_const_unstable => &["feature"],
+        sym::unstable => &["feature", "reason", "issue", "soft"],
+        _ => &["feature", "since"],
+    };
+
+    let mut feature = None;
+    let mut since = None;
+    let mut reason = None;
+    let mut suggestion = None;
+    let mut issue = None;
+    let mut issue_number = None;
+    let mut is_soft = false;
+    let mut malformed = false;
+    for meta in &metas {
+        let mi = match meta.meta_item() {
+            Some(mi) => mi,
+            None => {
+                report_error(
+                    sess,
+                    meta.span(),
+                    AttrError::UnsupportedLiteral("unsupported literal", false),
+                );
+                malformed = true;
+                continue
+            }
+        };
+        let key = mi.name_or_empty();
+        let item = match (name, key) {
+            (sym::stable, sym::feature) |
+            (sym::unstable, sym::feature) |
+            (sym::rustc_const_unstable, sym::feature) => &mut feature,
+            (sym::stable, sym::since) | (sym::rustc_deprecated, sym::since) => &mut since,
+            (sym::unstable, sym::reason) | (sym::rustc_deprecated, sym::reason) => &mut reason,
+            (sym::rustc_deprecated, sym::suggestion) => &mut suggestion,
+            (sym::unstable, sym::issue) => &mut issue,
+            (sym::unstable, sym::soft) => {
+                if !mi.is_word() {
+                    report_error(sess, mi.span, AttrError::SoftWithArguments);
+                }
+                is_soft = true;
+                continue
+            }
+            _ => {
+                report_error(
+                    sess,
+                    mi.span,
+                    AttrError::UnknownMetaItem(mi.path.to_string(), expected),
+                );
+                malformed = true;
+                continue
+            }
+        };
+        if !get_string_arg(sess, mi, item, false) {
+            malformed = true;
+        } else if key == sym::since {
+            since = since.map(|since| check_since(sess, mi, since));
+        } else if key == sym::issue {
+            match issue.unwrap().as_str().parse::<u32>() {
+                Ok(number) => issue_number = Some(number),
+                Err(_) => {
+                    report_error(sess, mi.span, AttrError::IncorrectIssue);
+                    malformed = true;
+                }
+            }
+        }
+    }
+    if malformed {
+        return None
+    }
+
+    let missing = |error| {
+        report_error(sess, attr.span, error);
+        None
+    };
+    match name {
+        sym::rustc_deprecated => match (since, reason) {
+            (Some(since), Some(reason)) => {
+                Some(StabilityAttr::RustcDeprecated(RustcDeprecation { since, reason, suggestion }))
+            }
+            (None, _) => missing(AttrError::MissingSince),
+            (_, None) => missing(AttrError::MissingReason),
+        },
+        sym::rustc_const_unstable => match feature {
+            Some(feature) => Some(StabilityAttr::ConstUnstable { feature }),
+            None => missing(AttrError::MissingConstUnstableFeature),
+        },
+        sym::unstable => match (feature, issue_number) {
+            (Some(feature), Some(issue)) => Some(StabilityAttr::Level {
+                feature,
+                level: Unstable { reason, issue, is_soft },
+            }),
+            (None, _) => missing(AttrError::MissingFeature),
+            (_, None) => missing(AttrError::MissingIssue),
+        },
+        _ => match (feature, since) {
+            (Some(feature), Some(since)) => Some(StabilityAttr::Level {
+                feature,
+                level: Stable { since },
+            }),
+            (None, _) => missing(AttrError::MissingFeature),
+            (_, None) => missing(AttrError::MissingSince),
+        },
+    }
+}
+
+/// Checks that the version given by the `since` argument `mi` is written as
+/// `major.minor.patch`, if it's a version at all, and returns it written that way.
+fn check_since(sess: Option<&ParseSess>, mi: &MetaItem, since: Symbol) -> Symbol {
+    match normalize_version(&since.as_str()) {
+        Some(ref version) if *version != *since.as_str() => {
+            report_error(sess, mi.span, AttrError::MalformedSince(version.clone()));
+            Symbol::intern(version)
+        }
+        _ => since,
+    }
+}
+
+/// Returns `version` written as `major.minor.patch`, if it's a version with fewer
+/// components, a leading `v` or surrounding whitespace, e.g. `1.0` or `v1.0.0`. Returns
+/// `None` if it isn't a version at all.
+fn normalize_version(version: &str) -> Option<String> {
+    let version = version.trim();
+    let version = if version.starts_with('v') { &version[1..] } else { version };
+    let mut components: Vec<&str> = version.split('.').collect();
+    let is_number = |c: &&str| !c.is_empty() && c.bytes().all(|b| b.is_ascii_digit());
+    if components.len() > 3 || !components.iter().all(is_number) {
+        return None
+    }
+    components.resize(3, "0");
+    Some(components.join("."))
+}
+
 /// Collects stability info from all stability attributes in `attrs`.
 /// Returns `None` if no stability attributes are found.
 pub fn find_stability(sess: &ParseSess, attrs: &[Attribute],
@@ -207,8 +448,6 @@ fn find_stability_generic<'a, I>(sess: &ParseSess,
                                  -> Option<Stability>
     where I: Iterator<Item = &'a Attribute>
 {
-    use StabilityLevel::*;
-
     let mut stab: Option<Stability> = None;
     let mut rustc_depr: Option<RustcDeprecation> = None;
     let mut rustc_const_unstable: Option<Symbol> = None;
@@ -216,7 +455,7 @@ fn find_stability_generic<'a, I>(sess: &ParseSess,
     let mut allow_const_fn_ptr = false;
     let diagnostic = &sess.span_diagnostic;
 
-    'outer: for attr in attrs_iter {
+    for attr in attrs_iter {
         if ![
             sym::rustc_deprecated,
             sym::rustc_const_unstable,
@@ -230,256 +469,36 @@ fn find_stability_generic<'a, I>(sess: &ParseSess,
 
         mark_used(attr);
 
-        let meta = attr.meta();
-
-        if attr.path == sym::rustc_promotable {
-            promotable = true;
+        // Duplicates are reported before parsing, so that they aren't reported as malformed too
+        if attr.path == sym::rustc_deprecated && rustc_depr.is_some() {
+            span_err!(diagnostic, item_sp, E0540, "multiple rustc_deprecated attributes");
+            continue
         }
-        if attr.path == sym::rustc_allow_const_fn_ptr {
-            allow_const_fn_ptr = true;
+        if attr.path == sym::rustc_const_unstable && rustc_const_unstable.is_some() {
+            span_err!(diagnostic, item_sp, E0553, "multiple rustc_const_unstable attributes");
+            continue
+        }
+        if (attr.path == sym::unstable || attr.path == sym::stable) && stab.is_some() {
+            handle_errors(sess, attr.span, AttrError::MultipleStabilityLevels);
+            break
         }
-        // attributes with data
-        else if let Some(MetaItem { node: MetaItemKind::List(ref metas), .. }) = meta {
-            let meta = meta.as_ref().unwrap();
-            let get = |meta: &MetaItem, item: &mut Option<Symbol>| {
-                if item.is_some() {
-                    handle_errors(sess, meta.span, AttrError::MultipleItem(meta.path.to_string()));
-                    return false
-                }
-                if let Some(v) = meta.value_str() {
-                    *item = Some(v);
-                    true
-                } else {
-                    span_err!(diagnostic, meta.span, E0539, "incorrect meta item");
-                    false
-                }
-            };
-
-            macro_rules! get_meta {
-                ($($name:ident),+) => {
-                    $(
-                        let mut $name = None;
-                    )+
-                    for meta in metas {
-                        if let Some(mi) = meta.meta_item() {
-                            match mi.name_or_empty() {
-                                $(
-                                    sym::$name => if !get(mi, &mut $name) { continue 'outer },
-                                )+
-                                _ => {
-                                    let expected = &[ $( stringify!($name) ),+ ];
-                                    handle_errors(
-                                        sess,
-                                        mi.span,
-                                        AttrError::UnknownMetaItem(mi.path.to_string(), expected),
-                                    );
-                                    continue 'outer
-                                }
-                            }
-                        } else {
-                            handle_errors(
-                                sess,
-                                meta.span(),
-                                AttrError::UnsupportedLiteral(
-                                    "unsupported literal",
-                                    false,
-                                ),
-                            );
-                            continue 'outer
-                        }
-                    }
-                }
-            }
-
-            match meta.name_or_empty() {
-                sym::rustc_deprecated => {
-                    if rustc_depr.is_some() {
-                        span_err!(diagnostic, item_sp, E0540,
-                                  "multiple rustc_deprecated attributes");
-                        continue 'outer
-                    }
-
-                    get_meta!(since, reason, suggestion);
-
-                    match (since, reason) {
-                        (Some(since), Some(reason)) => {
-                            rustc_depr = Some(RustcDeprecation {
-                                since,
-                                reason,
-                                suggestion,
-                            })
-                        }
-                        (None, _) => {
-                            handle_errors(sess, attr.span, AttrError::MissingSince);
-                            continue
-                        }
-                        _ => {
-                            span_err!(diagnostic, attr.span, E0543, "missing 'reason'");
-                            continue
-                        }
-                    }
-                }
-                sym::rustc_const_unstable => {
-                    if rustc_const_unstable.is_some() {
-                        span_err!(diagnostic, item_sp, E0553,
-                                  "multiple rustc_const_unstable attributes");
-                        continue 'outer
-                    }
-
-                    get_meta!(feature);
-                    if let Some(feature) = feature {
-                        rustc_const_unstable = Some(feature);
-                    } else {
-                        span_err!(diagnostic, attr.span, E0629, "missing 'feature'");
-                        continue
-                    }
-                }
-                sym::unstable => {
-                    if stab.is_some() {
-                        handle_errors(sess, attr.span, AttrError::MultipleStabilityLevels);
-                        break
-                    }
-
-                    let mut feature = None;
-                    let mut reason = None;
-                    let mut issue = None;
-                    let mut is_soft = false;
-                    for meta in metas {
-                        if let Some(mi) = meta.meta_item() {
-                            match mi.name_or_empty() {
-                                sym::feature => if !get(mi, &mut feature) { continue 'outer },
-                                sym::reason => if !get(mi, &mut reason) { continue 'outer },
-                                sym::issue => if !get(mi, &mut issue) { continue 'outer },
-                                sym::soft => {
-                                    if !mi.is_word() {
-                                        let msg = "`soft` should not have any arguments";
-                                        sess.span_diagnostic.span_err(mi.span, msg);
-                                    }
-                                    is_soft = true;
-                                }
-                                _ => {
-                                    handle_errors(
-                                        sess,
-                                        meta.span(),
-                                        AttrError::UnknownMetaItem(
-                                            mi.path.to_string(),
-                                            &["feature", "reason", "issue", "soft"]
-                                        ),
-                                    );
-                                    continue 'outer
-                                }
-                            }
-                        } else {
-                            handle_errors(
-                                sess,
-                                meta.span(),
-                                AttrError::UnsupportedLiteral(
-                                    "unsupported literal",
-                                    false,
-                                ),
-                            );
-                            continue 'outer
-                        }
-                    }
-
-                    match (feature, reason, issue) {
-                        (Some(feature), reason, Some(issue)) => {
-                            stab = Some(Stability {
-                                level: Unstable {
-                                    reason,
-                                    issue: {
-                                        if let Ok(issue) = issue.as_str().parse() {
-                                            issue
-                                        } else {
-                                            span_err!(diagnostic, attr.span, E0545,
-                                                      "incorrect 'issue'");
-                                            continue
-                                        }
-                                    },
-                                    is_soft,
-                                },
-                                feature,
-                                rustc_depr: None,
-                                const_stability: None,
-                                promotable: false,
-                                allow_const_fn_ptr: false,
-                            })
-                        }
-                        (None, _, _) => {
-                            handle_errors(sess, attr.span, AttrError::MissingFeature);
-                            continue
-                        }
-                        _ => {
-                            span_err!(diagnostic, attr.span, E0547, "missing 'issue'");
-                            continue
-                        }
-                    }
-                }
-                sym::stable => {
-                    if stab.is_some() {
-                        handle_errors(sess, attr.span, AttrError::MultipleStabilityLevels);
-                        break
-                    }
-
-                    let mut feature = None;
-                    let mut since = None;
-                    for meta in metas {
-                        match meta {
-                            NestedMetaItem::MetaItem(mi) => {
-                                match mi.name_or_empty() {
-                                    sym::feature => if !get(mi, &mut feature) { continue 'outer },
-                                    sym::since => if !get(mi, &mut since) { continue 'outer },
-                                    _ => {
-                                        handle_errors(
-                                            sess,
-                                            meta.span(),
-                                            AttrError::UnknownMetaItem(
-                                                mi.path.to_string(), &["since", "note"],
-                                            ),
-                                        );
-                                        continue 'outer
-                                    }
-                                }
-                            },
-                            NestedMetaItem::Literal(lit) => {
-                                handle_errors(
-                                    sess,
-                                    lit.span,
-                                    AttrError::UnsupportedLiteral(
-                                        "unsupported literal",
-                                        false,
-                                    ),
-                                );
-                                continue 'outer
-                            }
-                        }
-                    }
 
-                    match (feature, since) {
-                        (Some(feature), Some(since)) => {
-                            stab = Some(Stability {
-                                level: Stable {
-                                    since,
-                                },
-                                feature,
-                                rustc_depr: None,
-                                const_stability: None,
-                                promotable: false,
-                                allow_const_fn_ptr: false,
-                            })
-                        }
-                        (None, _) => {
-                            handle_errors(sess, attr.span, AttrError::MissingFeature);
-                            continue
-                        }
-                        _ => {
-                            handle_errors(sess, attr.span, AttrError::MissingSince);
-                            continue
-                        }
-                    }
-                }
-                _ => unreachable!()
+        match parse_stability_attr(Some(sess), attr) {
+            Some(StabilityAttr::Level { feature, level }) => {
+                stab = Some(Stability {
+                    level,
+                    feature,
+                    rustc_depr: None,
+                    const_stability: None,
+                    promotable: false,
+                    allow_const_fn_ptr: false,
+                })
             }
+            Some(StabilityAttr::RustcDeprecated(depr)) => rustc_depr = Some(depr),
+            Some(StabilityAttr::ConstUnstable { feature }) => rustc_const_unstable = Some(feature),
+            Some(StabilityAttr::Promotable) => promotable = true,
+            Some(StabilityAttr::AllowConstFnPtr) => allow_const_fn_ptr = true,
+            None => {}
         }
     }
 
@@ -524,9 +543,39 @@ pub fn find_crate_name(attrs: &[Attribute]) -> Option<Symbol> {
     super::first_attr_value_str_by_name(attrs, sym::crate_name)
 }
 
+/// A `cfg` predicate with arguments, like `version("1.40")`, registered by the embedder with
+/// `ParseSess::register_cfg_predicate` rather than hardcoded into `cfg_matches`.
+pub struct CfgPredicate {
+    /// The language feature that has to be enabled to use the predicate, with the function
+    /// checking whether it is, or `None` if the predicate can be used on stable.
+    pub gate: Option<(Symbol, fn(&Features) -> bool)>,
+    /// Evaluates a use of the predicate, e.g. `version("1.40")`. Malformed uses should be
+    /// reported to the `ParseSess` and evaluate to `false`.
+    pub eval: Box<dyn Fn(&ast::MetaItem, &ParseSess) -> bool + sync::Send + sync::Sync>,
+}
+
 /// Tests if a cfg-pattern matches the cfg set
 pub fn cfg_matches(cfg: &ast::MetaItem, sess: &ParseSess, features: Option<&Features>) -> bool {
-    eval_condition(cfg, sess, &mut |cfg| {
+    let mut eval_predicate = |cfg: &ast::MetaItem| {
+        let name = cfg.name_or_empty();
+        let predicate = match sess.cfg_predicate(name) {
+            Some(predicate) => predicate,
+            None => {
+                let mut candidates = vec![sym::any, sym::all, sym::not];
+                candidates.extend(sess.cfg_predicate_names());
+                report_invalid_predicate(sess, cfg, &candidates);
+                return Some(false);
+            }
+        };
+        if let (Some(feats), Some((feature, has_feature))) = (features, predicate.gate) {
+            if !has_feature(feats) && !cfg.span.allows_unstable(feature) {
+                let explain = format!("`cfg({})` is experimental and subject to change", name);
+                emit_feature_err(sess, feature, cfg.span, GateIssue::Language, &explain);
+            }
+        }
+        Some((predicate.eval)(cfg, sess))
+    };
+    eval_condition_with_predicates(cfg, sess, &mut |cfg| {
         if let (Some(feats), Some(gated_cfg)) = (features, GatedCfg::gate(cfg)) {
             gated_cfg.check_and_emit(sess, feats);
         }
@@ -554,7 +603,7 @@ pub fn cfg_matches(cfg: &ast::MetaItem, sess: &ParseSess, features: Option<&Feat
                 sess.config.contains(&(ident.name, cfg.value_str()))
             }
         }
-    })
+    }, &mut eval_predicate)
 }
 
 /// Evaluate a cfg-like condition (with `any` and `all`), using `eval` to
@@ -562,9 +611,31 @@ pub fn cfg_matches(cfg: &ast::MetaItem, sess: &ParseSess, features: Option<&Feat
 pub fn eval_condition<F>(cfg: &ast::MetaItem, sess: &ParseSess, eval: &mut F)
                          -> bool
     where F: FnMut(&ast::MetaItem) -> bool
+{
+    eval_condition_with_predicates(cfg, sess, eval, &mut |_| None)
+}
+
+/// Like `eval_condition`, but predicates with arguments other than `any`, `all` and `not` are
+/// evaluated by `eval_predicate`, which returns `None` if it doesn't know them either.
+fn eval_condition_with_predicates<F, P>(cfg: &ast::MetaItem,
+                                        sess: &ParseSess,
+                                        eval: &mut F,
+                                        eval_predicate: &mut P)
+                                        -> bool
+    where F: FnMut(&ast::MetaItem) -> bool,
+          P: FnMut(&ast::MetaItem) -> Option<bool>
 {
     match cfg.node {
         ast::MetaItemKind::List(ref mis) => {
+            let name = cfg.name_or_empty();
+            if ![sym::any, sym::all, sym::not].contains(&name) {
+                // The arguments of other predicates don't have to be cfg-patterns.
+                return eval_predicate(cfg).unwrap_or_else(|| {
+                    report_invalid_predicate(sess, cfg, &[sym::any, sym::all, sym::not]);
+                    false
+                });
+            }
+
             for mi in mis.iter() {
                 if !mi.is_meta_item() {
                     handle_errors(
@@ -581,25 +652,19 @@ pub fn eval_condition<F>(cfg: &ast::MetaItem, sess: &ParseSess, eval: &mut F)
 
             // The unwraps below may look dangerous, but we've already asserted
             // that they won't fail with the loop above.
-            match cfg.name_or_empty() {
-                sym::any => mis.iter().any(|mi| {
-                    eval_condition(mi.meta_item().unwrap(), sess, eval)
-                }),
-                sym::all => mis.iter().all(|mi| {
-                    eval_condition(mi.meta_item().unwrap(), sess, eval)
-                }),
-                sym::not => {
+            let mut eval_nested = |mi: &NestedMetaItem| {
+                eval_condition_with_predicates(mi.meta_item().unwrap(), sess, eval, eval_predicate)
+            };
+            match name {
+                sym::any => mis.iter().any(eval_nested),
+                sym::all => mis.iter().all(eval_nested),
+                _ => {
                     if mis.len() != 1 {
                         span_err!(sess.span_diagnostic, cfg.span, E0536, "expected 1 cfg-pattern");
                         return false;
                     }
 
-                    !eval_condition(mis[0].meta_item().unwrap(), sess, eval)
-                },
-                _ => {
-                    span_err!(sess.span_diagnostic, cfg.span, E0537,
-                              "invalid predicate `{}`", cfg.path);
-                    false
+                    !eval_nested(&mis[0])
                 }
             }
         },
@@ -609,6 +674,23 @@ pub fn eval_condition<F>(cfg: &ast::MetaItem, sess: &ParseSess, eval: &mut F)
     }
 }
 
+/// Reports that `cfg` isn't a known predicate, suggesting one of `candidates` if it looks like
+/// a typo.
+fn report_invalid_predicate(sess: &ParseSess, cfg: &ast::MetaItem, candidates: &[Symbol]) {
+    let mut err = struct_span_err!(sess.span_diagnostic, cfg.span, E0537,
+                                   "invalid predicate `{}`", cfg.path);
+    let name = cfg.name_or_empty().as_str();
+    if let Some(candidate) = find_best_match_for_name(candidates.iter(), &name, None) {
+        err.span_suggestion(
+            cfg.path.span,
+            "there is a predicate with a similar name",
+            candidate.to_string(),
+            Applicability::MaybeIncorrect,
+        );
+    }
+    err.emit();
+}
+
 
 #[derive(RustcEncodable, RustcDecodable, PartialEq, PartialOrd, Clone, Debug, Eq, Hash)]
 pub struct Deprecation {
@@ -631,7 +713,7 @@ fn find_deprecation_generic<'a, I>(sess: &ParseSess,
     let mut depr: Option<Deprecation> = None;
     let diagnostic = &sess.span_diagnostic;
 
-    'outer: for attr in attrs_iter {
+    for attr in attrs_iter {
         if !attr.check_name(sym::deprecated) {
             continue;
         }
@@ -641,83 +723,72 @@ fn find_deprecation_generic<'a, I>(sess: &ParseSess,
             break
         }
 
-        let meta = attr.meta().unwrap();
-        depr = match &meta.node {
-            MetaItemKind::Word => Some(Deprecation { since: None, note: None }),
-            MetaItemKind::NameValue(..) => {
-                meta.value_str().map(|note| {
-                    Deprecation { since: None, note: Some(note) }
-                })
-            }
-            MetaItemKind::List(list) => {
-                let get = |meta: &MetaItem, item: &mut Option<Symbol>| {
-                    if item.is_some() {
-                        handle_errors(
-                            sess, meta.span, AttrError::MultipleItem(meta.path.to_string())
-                        );
-                        return false
-                    }
-                    if let Some(v) = meta.value_str() {
-                        *item = Some(v);
-                        true
-                    } else {
-                        if let Some(lit) = meta.name_value_literal() {
-                            handle_errors(
-                                sess,
-                                lit.span,
-                                AttrError::UnsupportedLiteral(
-                                    "literal in `deprecated` \
-                                    value must be a string",
-                                    lit.node.is_bytestr()
-                                ),
-                            );
-                        } else {
-                            span_err!(diagnostic, meta.span, E0551, "incorrect meta item");
-                        }
+        depr = parse_deprecation_attr(Some(sess), attr);
+    }
+
+    depr
+}
+
+/// Parses a single `#[deprecated]` attribute. Returns `None` if `attr` isn't one or is
+/// malformed, in which case each malformed argument is reported to `sess`, if given.
+pub fn parse_deprecation_attr(sess: Option<&ParseSess>, attr: &Attribute) -> Option<Deprecation> {
+    if !attr.check_name(sym::deprecated) {
+        return None
+    }
 
-                        false
+    let meta = attr.meta()?;
+    match &meta.node {
+        MetaItemKind::Word => Some(Deprecation { since: None, note: None }),
+        MetaItemKind::NameValue(..) => {
+            meta.value_str().map(|note| {
+                Deprecation { since: None, note: Some(note) }
+            })
+        }
+        MetaItemKind::List(list) => {
+            let mut since = None;
+            let mut note = None;
+            let mut malformed = false;
+            for meta in list {
+                let mi = match meta {
+                    NestedMetaItem::MetaItem(mi) => mi,
+                    NestedMetaItem::Literal(lit) => {
+                        report_error(
+                            sess,
+                            lit.span,
+                            AttrError::UnsupportedLiteral(
+                                "item in `deprecated` must be a key/value pair",
+                                false,
+                            ),
+                        );
+                        malformed = true;
+                        continue
                     }
                 };
-
-                let mut since = None;
-                let mut note = None;
-                for meta in list {
-                    match meta {
-                        NestedMetaItem::MetaItem(mi) => {
-                            match mi.name_or_empty() {
-                                sym::since => if !get(mi, &mut since) { continue 'outer },
-                                sym::note => if !get(mi, &mut note) { continue 'outer },
-                                _ => {
-                                    handle_errors(
-                                        sess,
-                                        meta.span(),
-                                        AttrError::UnknownMetaItem(mi.path.to_string(),
-                                                                   &["since", "note"]),
-                                    );
-                                    continue 'outer
-                                }
-                            }
-                        }
-                        NestedMetaItem::Literal(lit) => {
-                            handle_errors(
-                                sess,
-                                lit.span,
-                                AttrError::UnsupportedLiteral(
-                                    "item in `deprecated` must be a key/value pair",
-                                    false,
-                                ),
-                            );
-                            continue 'outer
-                        }
+                let item = match mi.name_or_empty() {
+                    sym::since => &mut since,
+                    sym::note => &mut note,
+                    _ => {
+                        report_error(
+                            sess,
+                            meta.span(),
+                            AttrError::UnknownMetaItem(mi.path.to_string(), &["since", "note"]),
+                        );
+                        malformed = true;
+                        continue
                     }
+                };
+                if !get_string_arg(sess, mi, item, true) {
+                    malformed = true;
                 }
+            }
 
+            if malformed {
+                None
+            } else {
                 Some(Deprecation { since, note })
             }
-        };
+        }
     }
-
-    depr
 }
 
 #[derive(PartialEq, Debug, RustcEncodable, RustcDecodable, Copy, Clone)]
@@ -748,6 +819,93 @@ impl IntType {
     }
 }
 
+/// The hints given by the `#[repr]` attributes of an item, each along with the span of the
+/// argument it was parsed from.
+#[derive(Clone, Debug, Default)]
+pub struct Repr {
+    pub hints: Vec<(ReprAttr, Span)>,
+}
+
+impl Repr {
+    pub fn is_c(&self) -> bool {
+        self.hints.iter().any(|&(hint, _)| hint == ReprAttr::ReprC)
+    }
+
+    pub fn is_simd(&self) -> bool {
+        self.hints.iter().any(|&(hint, _)| hint == ReprAttr::ReprSimd)
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.hints.iter().any(|&(hint, _)| hint == ReprAttr::ReprTransparent)
+    }
+
+    /// The integer type of the discriminant, the last one given if there are several.
+    pub fn int(&self) -> Option<IntType> {
+        self.hints.iter().filter_map(|&(hint, _)| match hint {
+            ReprAttr::ReprInt(int) => Some(int),
+            _ => None,
+        }).last()
+    }
+
+    /// The smallest `packed` given, as it is the one applied.
+    pub fn packed(&self) -> Option<u32> {
+        self.hints.iter().filter_map(|&(hint, _)| match hint {
+            ReprAttr::ReprPacked(pack) => Some(pack),
+            _ => None,
+        }).min()
+    }
+
+    /// The largest `align` given, as it is the one applied.
+    pub fn align(&self) -> Option<u32> {
+        self.hints.iter().filter_map(|&(hint, _)| match hint {
+            ReprAttr::ReprAlign(align) => Some(align),
+            _ => None,
+        }).max()
+    }
+
+    fn spans(&self, f: impl Fn(ReprAttr) -> bool) -> Vec<Span> {
+        self.hints.iter().filter(|&&(hint, _)| f(hint)).map(|&(_, span)| span).collect()
+    }
+}
+
+/// Parses all the `#[repr]` attributes in `attrs`, reporting the hints that are malformed as
+/// `find_repr_attrs` does, as well as the hints that conflict with each other whatever the item
+/// is: `packed` together with `align`, and `packed` given with different alignments.
+///
+/// The conflicts that depend on the item, e.g., `C` together with an integer type on a C-like
+/// enum, are left to the HIR attribute checks.
+pub fn parse_repr(sess: &ParseSess, attrs: &[Attribute]) -> Repr {
+    let mut repr = Repr::default();
+    for attr in attrs {
+        repr.hints.extend(parse_repr_attr(sess, attr));
+    }
+
+    let diagnostic = &sess.span_diagnostic;
+    if repr.packed().is_some() && repr.align().is_some() {
+        let spans = repr.spans(|hint| match hint {
+            ReprAttr::ReprPacked(_) | ReprAttr::ReprAlign(_) => true,
+            _ => false,
+        });
+        span_err!(diagnostic, spans, E0587,
+                  "type has conflicting packed and align representation hints");
+    }
+    let packed = repr.spans(|hint| match hint {
+        ReprAttr::ReprPacked(_) => true,
+        _ => false,
+    });
+    let mut packs = repr.hints.iter().filter_map(|&(hint, _)| match hint {
+        ReprAttr::ReprPacked(pack) => Some(pack),
+        _ => None,
+    });
+    if let Some(first) = packs.next() {
+        if packs.any(|pack| pack != first) {
+            span_err!(diagnostic, packed, E0634,
+                      "type has conflicting packed representation hints");
+        }
+    }
+    repr
+}
+
 /// Parse #[repr(...)] forms.
 ///
 /// Valid repr contents: any of the primitive integral type names (see
@@ -756,6 +914,10 @@ impl IntType {
 /// structure layout, `packed` to remove padding, and `transparent` to elegate representation
 /// concerns to the only non-ZST field.
 pub fn find_repr_attrs(sess: &ParseSess, attr: &Attribute) -> Vec<ReprAttr> {
+    parse_repr_attr(sess, attr).into_iter().map(|(hint, _)| hint).collect()
+}
+
+fn parse_repr_attr(sess: &ParseSess, attr: &Attribute) -> Vec<(ReprAttr, Span)> {
     use ReprAttr::*;
 
     let mut acc = Vec::new();
@@ -788,7 +950,7 @@ pub fn find_repr_attrs(sess: &ParseSess, attr: &Attribute) -> Vec<ReprAttr> {
 
                     if let Some(h) = hint {
                         recognised = true;
-                        acc.push(h);
+                        acc.push((h, item.span()));
                     }
                 } else if let Some((name, value)) = item.name_value_literal() {
                     let parse_alignment = |node: &ast::LitKind| -> Result<u32, &'static str> {
@@ -812,14 +974,14 @@ pub fn find_repr_attrs(sess: &ParseSess, attr: &Attribute) -> Vec<ReprAttr> {
                     if name == sym::align {
                         recognised = true;
                         match parse_alignment(&value.node) {
-                            Ok(literal) => acc.push(ReprAlign(literal)),
+                            Ok(literal) => acc.push((ReprAlign(literal), item.span())),
                             Err(message) => literal_error = Some(message)
                         };
                     }
                     else if name == sym::packed {
                         recognised = true;
                         match parse_alignment(&value.node) {
-                            Ok(literal) => acc.push(ReprPacked(literal)),
+                            Ok(literal) => acc.push((ReprPacked(literal), item.span())),
                             Err(message) => literal_error = Some(message)
                         };
                     }
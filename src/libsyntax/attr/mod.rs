@@ -33,26 +33,26 @@ use std::ops::DerefMut;
 pub fn mark_used(attr: &Attribute) {
     debug!("marking {:?} as used", attr);
     GLOBALS.with(|globals| {
-        globals.used_attrs.lock().insert(attr.id);
+        globals.used_attrs.insert(attr.id);
     });
 }
 
 pub fn is_used(attr: &Attribute) -> bool {
     GLOBALS.with(|globals| {
-        globals.used_attrs.lock().contains(attr.id)
+        globals.used_attrs.contains(attr.id)
     })
 }
 
 pub fn mark_known(attr: &Attribute) {
     debug!("marking {:?} as known", attr);
     GLOBALS.with(|globals| {
-        globals.known_attrs.lock().insert(attr.id);
+        globals.known_attrs.insert(attr.id);
     });
 }
 
 pub fn is_known(attr: &Attribute) -> bool {
     GLOBALS.with(|globals| {
-        globals.known_attrs.lock().contains(attr.id)
+        globals.known_attrs.contains(attr.id)
     })
 }
 
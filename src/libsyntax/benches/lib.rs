@@ -0,0 +1,7 @@
+#![feature(test)]
+
+extern crate test;
+
+mod macro_parser;
+mod parse;
+mod pp;
@@ -0,0 +1,69 @@
+use syntax::ast;
+use syntax::ext::tt::macro_parser::{self, CompiledMatcher, Success};
+use syntax::ext::tt::quoted;
+use syntax::feature_gate::Features;
+use syntax::parse::{self, ParseSess};
+use syntax::source_map::FilePathMapping;
+use syntax::tokenstream::TokenStream;
+use syntax_pos::FileName;
+use syntax_pos::edition::Edition;
+use test::Bencher;
+
+// The matcher of a `lazy_static`-style macro, matched against many declarations. Macros like it
+// are invoked a lot, so the time matching their invocations takes adds up. `macro_rules` macros
+// compile their matchers when they're defined; compare with compiling it for each invocation.
+
+const MATCHER: &str = "$($(#[$attr:meta])* static ref $name:ident : $t:ty = $e:expr;)*";
+
+fn stream(sess: &ParseSess, name: &str, source: String) -> TokenStream {
+    let name = FileName::Custom(name.to_string());
+    parse::parse_stream_from_source_str(name, source, sess, None)
+}
+
+fn matcher(sess: &ParseSess) -> Vec<quoted::TokenTree> {
+    quoted::parse(
+        stream(sess, "matcher", MATCHER.to_string()),
+        true,
+        sess,
+        &Features::new(),
+        &[],
+        Edition::Edition2018,
+        ast::DUMMY_NODE_ID,
+    )
+}
+
+fn declarations(n: usize) -> String {
+    (0..n).map(|i| format!(
+        "#[doc = \"static {0}\"] static ref S{0}: Vec<u32> = vec![{0}, {0} + 1];\n", i,
+    )).collect()
+}
+
+#[bench]
+fn match_lazy_static(b: &mut Bencher) {
+    syntax::with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let input = stream(&sess, "input", declarations(50));
+        let matcher = CompiledMatcher::new(matcher(&sess));
+        b.iter(|| {
+            match macro_parser::parse_compiled(&sess, input.clone(), &matcher, None, true) {
+                Success(matches) => matches,
+                _ => panic!("the declarations don't match"),
+            }
+        });
+    });
+}
+
+#[bench]
+fn match_lazy_static_compiling_matcher(b: &mut Bencher) {
+    syntax::with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let input = stream(&sess, "input", declarations(50));
+        let matcher = matcher(&sess);
+        b.iter(|| {
+            match macro_parser::parse(&sess, input.clone(), &matcher, None, true) {
+                Success(matches) => matches,
+                _ => panic!("the declarations don't match"),
+            }
+        });
+    });
+}
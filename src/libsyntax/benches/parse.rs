@@ -0,0 +1,90 @@
+use syntax::parse::{self, ParseSess};
+use syntax::print::pprust::{self, PrintConfig};
+use syntax::source_map::FilePathMapping;
+use syntax::util::perf::{self, Corpus};
+use syntax_pos::FileName;
+use test::Bencher;
+
+fn bench_lex(b: &mut Bencher, corpus: Corpus) {
+    syntax::with_default_globals(|| {
+        b.iter(|| {
+            let sess = ParseSess::new(FilePathMapping::empty());
+            let name = FileName::Custom(corpus.name.to_string());
+            parse::parse_stream_from_source_str(name, corpus.source.clone(), &sess, None)
+        });
+    });
+}
+
+fn bench_parse(b: &mut Bencher, corpus: Corpus) {
+    syntax::with_default_globals(|| {
+        b.iter(|| {
+            let sess = ParseSess::new(FilePathMapping::empty());
+            let name = FileName::Custom(corpus.name.to_string());
+            parse::parse_crate_from_source_str(name, corpus.source.clone(), &sess).unwrap()
+        });
+    });
+}
+
+fn bench_print(b: &mut Bencher, corpus: Corpus) {
+    syntax::with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let name = FileName::Custom(corpus.name.to_string());
+        let krate = parse::parse_crate_from_source_str(name, corpus.source, &sess).unwrap();
+        b.iter(|| pprust::crate_to_string_with_config(&krate, PrintConfig::default()));
+    });
+}
+
+fn bench_expand(b: &mut Bencher, corpus: Corpus) {
+    syntax::with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let name = FileName::Custom(corpus.name.to_string());
+        let krate = parse::parse_crate_from_source_str(name, corpus.source, &sess).unwrap();
+        let macros = perf::Macros::collect(&sess, &krate);
+        b.iter(|| macros.expand());
+    });
+}
+
+#[bench]
+fn lex_nested_parens(b: &mut Bencher) {
+    bench_lex(b, perf::nested_parens(100));
+}
+
+#[bench]
+fn parse_nested_parens(b: &mut Bencher) {
+    bench_parse(b, perf::nested_parens(100));
+}
+
+#[bench]
+fn parse_binary_chain(b: &mut Bencher) {
+    bench_parse(b, perf::binary_chain(2000));
+}
+
+#[bench]
+fn print_binary_chain(b: &mut Bencher) {
+    bench_print(b, perf::binary_chain(2000));
+}
+
+#[bench]
+fn parse_huge_match(b: &mut Bencher) {
+    bench_parse(b, perf::huge_match(2000));
+}
+
+#[bench]
+fn print_huge_match(b: &mut Bencher) {
+    bench_print(b, perf::huge_match(2000));
+}
+
+#[bench]
+fn lex_macro_heavy(b: &mut Bencher) {
+    bench_lex(b, perf::macro_heavy(200));
+}
+
+#[bench]
+fn parse_macro_heavy(b: &mut Bencher) {
+    bench_parse(b, perf::macro_heavy(200));
+}
+
+#[bench]
+fn expand_macro_heavy(b: &mut Bencher) {
+    bench_expand(b, perf::macro_heavy(200));
+}
@@ -0,0 +1,59 @@
+use syntax::parse::{self, ParseSess};
+use syntax::print::pp::{self, Printer};
+use syntax::print::pprust;
+use syntax::source_map::FilePathMapping;
+use syntax_pos::FileName;
+use test::Bencher;
+
+// Printing large expanded crates shows up in profiles, both in the box/break
+// engine itself and in the AST printer driving it.
+
+fn print_nested_boxes(p: &mut Printer, depth: usize) {
+    p.ibox(4);
+    for i in 0..8 {
+        if i != 0 {
+            p.word(",");
+            p.space();
+        }
+        if depth < 4 && i % 3 == 0 {
+            print_nested_boxes(p, depth + 1);
+        } else {
+            p.word("identifier");
+        }
+    }
+    p.end();
+}
+
+#[bench]
+fn pp_nested_boxes(b: &mut Bencher) {
+    b.iter(|| {
+        let mut p = pp::mk_printer();
+        p.cbox(0);
+        for _ in 0..20 {
+            print_nested_boxes(&mut p, 0);
+            p.hardbreak();
+        }
+        p.end();
+        p.eof()
+    });
+}
+
+#[bench]
+fn pprust_crate(b: &mut Bencher) {
+    let src = (0..200).map(|i| format!(
+        "fn f{0}(a: u32, b: &[u32]) -> u32 {{ \
+            match a {{ 0 => b.iter().map(|x| x * {0}).sum(), n => f{0}(n - 1, b) + {0} }} \
+        }}\n",
+        i,
+    )).collect::<String>();
+
+    syntax::with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let krate = parse::parse_crate_from_source_str(
+            FileName::Custom("bench".to_string()), src, &sess,
+        ).unwrap();
+        b.iter(|| {
+            krate.module.items.iter().map(|item| pprust::item_to_string(item)).collect::<String>()
+        });
+    });
+}
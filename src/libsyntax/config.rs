@@ -8,15 +8,19 @@ use crate::feature_gate::{
 };
 use crate::attr;
 use crate::ast;
+use crate::early_buffered_lints::{BufferedEarlyLintDiagnostics, BufferedEarlyLintId};
 use crate::edition::Edition;
 use crate::mut_visit::*;
-use crate::parse::{token, ParseSess};
+use crate::parse::{self, token::{self, Token}, ParseSess};
+use crate::print::pprust;
 use crate::ptr::P;
-use crate::symbol::sym;
-use crate::util::map_in_place::MapInPlace;
+use crate::symbol::{Symbol, kw, sym};
+use crate::tokenstream::{DelimSpan, TokenStream, TokenTree, TreeAndJoint};
+use crate::util::map_in_place::{par_flat_map_in_place, MapInPlace, ITEM_CHUNK_LEN};
 
 use errors::Applicability;
 use smallvec::SmallVec;
+use syntax_pos::Span;
 
 /// A folder that strips out items that do not belong in the current configuration.
 pub struct StripUnconfigured<'a> {
@@ -145,6 +149,7 @@ impl<'a> StripUnconfigured<'a> {
         // At this point we know the attribute is considered used.
         attr::mark_used(&attr);
 
+        lint_cfg_predicate(self.sess, &cfg_predicate);
         if attr::cfg_matches(&cfg_predicate, self.sess, self.features) {
             // We call `process_cfg_attr` recursively in case there's a
             // `cfg_attr` inside of another `cfg_attr`. E.g.
@@ -204,15 +209,195 @@ impl<'a> StripUnconfigured<'a> {
             }
 
             match nested_meta_items[0].meta_item() {
-                Some(meta_item) => attr::cfg_matches(meta_item, self.sess, self.features),
+                Some(meta_item) => {
+                    lint_cfg_predicate(self.sess, meta_item);
+                    attr::cfg_matches(meta_item, self.sess, self.features)
+                }
                 None => error(nested_meta_items[0].span(),
                               "`cfg` predicate key cannot be a literal", ""),
             }
         })
     }
 
-    /// Visit attributes on expression and statements (but not attributes on items in blocks).
-    fn visit_expr_attrs(&mut self, attrs: &[ast::Attribute]) {
+    /// Expands `cfg_attr` and strips `cfg` in the tokens captured for an item, the same way the
+    /// attributes of the item's AST are configured, so that the tokens can still be handed to
+    /// procedural macros as they are, with their original spans.
+    ///
+    /// With `nested` set the whole item is configured, as by this folder. Otherwise only the
+    /// inner attributes of the item itself are, as by `configure`.
+    ///
+    /// The extent of a node stripped by `#[cfg]` is only known from its tokens, see `node_end`.
+    /// Where that guess is wrong the tokens no longer match the AST and `to_tokenstream` falls
+    /// back to pretty-printing the item.
+    pub fn configure_tokens(&mut self, tokens: TokenStream, nested: bool) -> TokenStream {
+        if nested {
+            return self.configure_nested_tokens(&tokens);
+        }
+
+        // Inner attributes are at the start of the body, the last braced group of the item.
+        let mut trees = tokens.0.as_ref().map_or(Vec::new(), |trees| trees.to_vec());
+        let body = trees.iter().rposition(|(tree, _)| match tree {
+            TokenTree::Delimited(_, token::Brace, _) => true,
+            _ => false,
+        });
+        if let Some(body) = body {
+            if let (TokenTree::Delimited(span, delim, stream), joint) = trees[body].clone() {
+                let stream = self.configure_inner_attr_tokens(&stream);
+                trees[body] = (TokenTree::Delimited(span, delim, stream), joint);
+            }
+        }
+        TokenStream::new(trees)
+    }
+
+    fn configure_inner_attr_tokens(&mut self, stream: &TokenStream) -> TokenStream {
+        let trees = stream.0.as_ref().map_or(&[][..], |trees| &trees[..]);
+        let mut configured = Vec::with_capacity(trees.len());
+        let mut i = 0;
+        while let Some((len, true, tokens)) = attr_tokens(&trees[i..]) {
+            self.push_attr_tokens(&mut configured, &trees[i..i + len], true, tokens);
+            i += len;
+        }
+        configured.extend_from_slice(&trees[i..]);
+        TokenStream::new(configured)
+    }
+
+    fn configure_nested_tokens(&mut self, stream: &TokenStream) -> TokenStream {
+        let trees = stream.0.as_ref().map_or(&[][..], |trees| &trees[..]);
+        let mut configured = Vec::with_capacity(trees.len());
+        let mut i = 0;
+        while i < trees.len() {
+            // Expand the attributes in front of the next node, then keep or strip the node.
+            let mut attrs = Vec::new();
+            while let Some((len, inner, tokens)) = attr_tokens(&trees[i..]) {
+                self.push_attr_tokens(&mut attrs, &trees[i..i + len], inner, tokens);
+                i += len;
+            }
+            if !self.in_cfg_tokens(&attrs) {
+                i = node_end(trees, i);
+                continue;
+            }
+            configured.append(&mut attrs);
+
+            if let Some((tree, joint)) = trees.get(i) {
+                // Arguments of macro invocations are left alone, as by `visit_mac`.
+                let tree = match tree {
+                    TokenTree::Delimited(span, delim, stream) if !is_mac_args(trees, i) => {
+                        TokenTree::Delimited(*span, *delim, self.configure_nested_tokens(stream))
+                    }
+                    _ => tree.clone(),
+                };
+                configured.push((tree, *joint));
+                i += 1;
+            }
+        }
+        TokenStream::new(configured)
+    }
+
+    /// Pushes the tokens of the attribute `attr` onto `trees`, after expanding it if it is a
+    /// `cfg_attr` whose syntax is correct. `tokens` are the tokens between the brackets.
+    fn push_attr_tokens(
+        &mut self,
+        trees: &mut Vec<TreeAndJoint>,
+        attr: &[TreeAndJoint],
+        inner: bool,
+        tokens: TokenStream,
+    ) {
+        let expanded_attrs = match self.expand_cfg_attr_tokens(&tokens) {
+            Some(expanded_attrs) => expanded_attrs,
+            None => return trees.extend_from_slice(attr),
+        };
+        for (span, tokens) in expanded_attrs {
+            // There are no `#` and brackets in the source for these, so we use the span of the
+            // expanded attribute for them, as `prepend_attrs` does.
+            trees.push(TokenTree::token(token::Pound, span).into());
+            if inner {
+                trees.push(TokenTree::token(token::Not, span).into());
+            }
+            let delim_span = DelimSpan::from_single(span);
+            trees.push(TokenTree::Delimited(delim_span, token::Bracket, tokens).into());
+        }
+    }
+
+    /// Expands the tokens `tokens` of a `cfg_attr` into the spans and tokens of the attributes
+    /// it expands to, following `process_cfg_attr`. Returns `None` for other attributes and for
+    /// the `cfg_attr`s that `process_cfg_attr` keeps as they are or reports as malformed.
+    fn expand_cfg_attr_tokens(&mut self, tokens: &TokenStream) -> Option<Vec<(Span, TokenStream)>> {
+        let args = match meta_list_tokens(tokens, sym::cfg_attr) {
+            Some(args) => args,
+            None => return None,
+        };
+        let mut args = split_at_commas(&args);
+        if args.last().map_or(false, |arg| arg.is_empty()) {
+            args.pop();
+        }
+        if args.len() < 2 || args.iter().any(|arg| arg.is_empty()) {
+            return None;
+        }
+
+        let cfg_predicate = match self.parse_cfg_predicate(args.remove(0)) {
+            Some(cfg_predicate) => cfg_predicate,
+            None => return None,
+        };
+        // The predicate has been linted and its errors reported when configuring the AST, and
+        // reporting them again here is deduplicated by the handler.
+        if !attr::cfg_matches(&cfg_predicate, self.sess, self.features) {
+            return Some(Vec::new());
+        }
+
+        let mut expanded_attrs = Vec::with_capacity(args.len());
+        for arg in args {
+            let span = arg[0].0.span().to(arg[arg.len() - 1].0.span());
+            let tokens = TokenStream::new(arg);
+            // A `cfg_attr` can expand to another `cfg_attr`, see `process_cfg_attr`.
+            match self.expand_cfg_attr_tokens(&tokens) {
+                Some(nested) => expanded_attrs.extend(nested),
+                None => expanded_attrs.push((span, tokens)),
+            }
+        }
+        Some(expanded_attrs)
+    }
+
+    /// Determines if a node with the attribute tokens `attrs` in front of it is included in this
+    /// configuration, following `in_cfg`.
+    fn in_cfg_tokens(&mut self, attrs: &[TreeAndJoint]) -> bool {
+        let mut i = 0;
+        while let Some((len, inner, tokens)) = attr_tokens(&attrs[i..]) {
+            i += len;
+            if inner {
+                continue;
+            }
+            let args = match meta_list_tokens(&tokens, sym::cfg) {
+                Some(args) => args,
+                None => continue,
+            };
+            let mut args = split_at_commas(&args);
+            if args.last().map_or(false, |arg| arg.is_empty()) {
+                args.pop();
+            }
+            if args.len() != 1 {
+                continue;
+            }
+            if let Some(cfg_predicate) = self.parse_cfg_predicate(args.remove(0)) {
+                if !attr::cfg_matches(&cfg_predicate, self.sess, self.features) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn parse_cfg_predicate(&self, tokens: Vec<TreeAndJoint>) -> Option<ast::MetaItem> {
+        let mut parser = parse::stream_to_parser(self.sess, TokenStream::new(tokens), None);
+        match parser.parse_meta_item() {
+            Ok(cfg_predicate) if parser.token == token::Eof => Some(cfg_predicate),
+            Ok(_) => None,
+            Err(mut err) => {
+                err.cancel();
+                None
+            }
+        }
+    }
+
         // flag the offending attributes
         for attr in attrs.iter() {
             self.maybe_emit_expr_attr_err(attr);
@@ -309,6 +494,18 @@ impl<'a> StripUnconfigured<'a> {
 }
 
 impl<'a> MutVisitor for StripUnconfigured<'a> {
+    fn visit_mod(&mut self, module: &mut ast::Mod) {
+        let ast::Mod { inner, items, inline: _, dangling_attrs } = module;
+        self.visit_span(inner);
+        // Configuring an item only takes the session and the features, so the items of large
+        // modules are configured in parallel.
+        let (sess, features) = (self.sess, self.features);
+        par_flat_map_in_place(items, ITEM_CHUNK_LEN, |item| {
+            StripUnconfigured { sess, features }.flat_map_item(item)
+        });
+        visit_attrs(dangling_attrs, self);
+    }
+
     fn visit_foreign_mod(&mut self, foreign_mod: &mut ast::ForeignMod) {
         self.configure_foreign_mod(foreign_mod);
         noop_visit_foreign_mod(foreign_mod, self);
@@ -337,15 +534,21 @@ impl<'a> MutVisitor for StripUnconfigured<'a> {
     }
 
     fn flat_map_item(&mut self, item: P<ast::Item>) -> SmallVec<[P<ast::Item>; 1]> {
-        noop_flat_map_item(configure!(self, item), self)
+        let mut item = configure!(self, item);
+        item.tokens = item.tokens.take().map(|tokens| self.configure_tokens(tokens, true));
+        noop_flat_map_item(item, self)
     }
 
     fn flat_map_impl_item(&mut self, item: ast::ImplItem) -> SmallVec<[ast::ImplItem; 1]> {
-        noop_flat_map_impl_item(configure!(self, item), self)
+        let mut item = configure!(self, item);
+        item.tokens = item.tokens.take().map(|tokens| self.configure_tokens(tokens, true));
+        noop_flat_map_impl_item(item, self)
     }
 
     fn flat_map_trait_item(&mut self, item: ast::TraitItem) -> SmallVec<[ast::TraitItem; 1]> {
-        noop_flat_map_trait_item(configure!(self, item), self)
+        let mut item = configure!(self, item);
+        item.tokens = item.tokens.take().map(|tokens| self.configure_tokens(tokens, true));
+        noop_flat_map_trait_item(item, self)
     }
 
     fn visit_mac(&mut self, _mac: &mut ast::Mac) {
@@ -367,3 +570,236 @@ impl<'a> MutVisitor for StripUnconfigured<'a> {
 fn is_cfg(attr: &ast::Attribute) -> bool {
     attr.check_name(sym::cfg)
 }
+
+/// Recognizes an attribute `#[...]` or `#![...]` at the start of `trees`, returning the number
+/// of trees it is made of, whether it is an inner attribute and the tokens between its brackets.
+fn attr_tokens(trees: &[TreeAndJoint]) -> Option<(usize, bool, TokenStream)> {
+    match trees.get(0) {
+        Some((TokenTree::Token(Token { kind: token::Pound, .. }), _)) => {}
+        _ => return None,
+    }
+    let inner = match trees.get(1) {
+        Some((TokenTree::Token(Token { kind: token::Not, .. }), _)) => true,
+        _ => false,
+    };
+    let len = if inner { 3 } else { 2 };
+    match trees.get(len - 1) {
+        Some((TokenTree::Delimited(_, token::Bracket, tokens), _)) => {
+            Some((len, inner, tokens.clone()))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the tokens between the parentheses of the attribute tokens `name(...)`.
+fn meta_list_tokens(tokens: &TokenStream, name: Symbol) -> Option<TokenStream> {
+    let mut trees = tokens.trees();
+    match (trees.next(), trees.next(), trees.next()) {
+        (Some(TokenTree::Token(Token { kind: token::Ident(ident, false), .. })),
+         Some(TokenTree::Delimited(_, token::Paren, args)),
+         None) if ident == name => Some(args),
+        _ => None,
+    }
+}
+
+/// Splits `tokens` at their top-level commas.
+fn split_at_commas(tokens: &TokenStream) -> Vec<Vec<TreeAndJoint>> {
+    let mut parts = vec![Vec::new()];
+    let mut trees = tokens.trees();
+    while let Some((tree, joint)) = trees.next_with_joint() {
+        match tree {
+            TokenTree::Token(Token { kind: token::Comma, .. }) => parts.push(Vec::new()),
+            tree => parts.last_mut().unwrap().push((tree, joint)),
+        }
+    }
+    parts
+}
+
+/// Finds where the node starting at `trees[start]` ends, to strip it along with its `#[cfg]`.
+///
+/// `let` statements and `const` and `static` items end right after their first top-level `;`.
+/// Other nodes are assumed to end right after the first top-level `;`, after the first braced
+/// group not followed by `else` along with a `,` or `;` following it, or before the first
+/// top-level `,`, which is stripped too. This holds for items, fields, variants, statements and
+/// most expressions.
+fn node_end(trees: &[TreeAndJoint], start: usize) -> usize {
+    let is_keyword = |i: usize, kw: Symbol| match trees.get(i) {
+        Some((TokenTree::Token(token), _)) => token.is_keyword(kw),
+        _ => false,
+    };
+    let mut i = start;
+    if is_keyword(i, kw::Pub) {
+        i += 1;
+        if let Some((TokenTree::Delimited(_, token::Paren, _), _)) = trees.get(i) {
+            i += 1;
+        }
+    }
+    let is_const_item = is_keyword(i, kw::Const) &&
+        ![kw::Fn, kw::Unsafe, kw::Extern, kw::Async].iter().any(|&kw| is_keyword(i + 1, kw));
+    let ends_at_semi = is_keyword(i, kw::Let) || is_keyword(i, kw::Static) || is_const_item;
+
+    let mut i = start;
+    while let Some((tree, _)) = trees.get(i) {
+        i += 1;
+        match tree {
+            TokenTree::Token(Token { kind: token::Semi, .. }) => break,
+            TokenTree::Token(Token { kind: token::Comma, .. }) if !ends_at_semi => break,
+            TokenTree::Delimited(_, token::Brace, _) if !ends_at_semi => {
+                match trees.get(i) {
+                    // `if` and `if let` expressions go on with their `else` blocks.
+                    Some(_) if is_keyword(i, kw::Else) => continue,
+                    Some((TokenTree::Token(Token { kind: token::Comma, .. }), _)) |
+                    Some((TokenTree::Token(Token { kind: token::Semi, .. }), _)) => i += 1,
+                    _ => {}
+                }
+                break;
+            }
+            _ => {}
+        }
+    }
+    i
+}
+
+/// Checks whether `trees[i]` holds the arguments of a macro invocation, `path!(...)`, or of a
+/// `macro_rules!` definition, `macro_rules! name { ... }`.
+fn is_mac_args(trees: &[TreeAndJoint], i: usize) -> bool {
+    let is_token = |j: usize, f: &dyn Fn(&Token) -> bool| match trees.get(j) {
+        Some((TokenTree::Token(token), _)) => f(token),
+        _ => false,
+    };
+    // Keywords are excluded for `if !(...)` and the like.
+    let is_ident = |token: &Token| token.is_ident() && !token.is_reserved_ident();
+    let is_not = |token: &Token| *token == token::Not;
+    let is_macro_rules = |token: &Token| *token == token::Ident(sym::macro_rules, false);
+    (i >= 2 && is_token(i - 2, &is_ident) && is_token(i - 1, &is_not)) ||
+    (i >= 3 && is_token(i - 3, &is_macro_rules) && is_token(i - 2, &is_not) &&
+        is_token(i - 1, &is_ident))
+}
+
+/// Lints the `any` and `all` predicates in `cfg`, including nested ones, that have duplicate
+/// clauses, and the `all` predicates whose clauses contradict each other.
+crate fn lint_cfg_predicate(sess: &ParseSess, cfg: &ast::MetaItem) {
+    let name = cfg.name_or_empty();
+    if ![sym::any, sym::all, sym::not].contains(&name) {
+        return;
+    }
+    let clauses: Vec<_> = match cfg.meta_item_list() {
+        Some(clauses) => clauses.iter().filter_map(|clause| clause.meta_item()).collect(),
+        None => return,
+    };
+    for clause in &clauses {
+        lint_cfg_predicate(sess, clause);
+    }
+    if name == sym::not {
+        return;
+    }
+
+    // The clauses by their printed form, with the spans of the duplicates of each.
+    let mut unique: Vec<(String, &ast::MetaItem, Vec<Span>)> = Vec::new();
+    for &clause in &clauses {
+        let printed = pprust::meta_item_to_string(clause);
+        match unique.iter_mut().find(|(other, ..)| *other == printed) {
+            Some((_, _, duplicates)) => duplicates.push(clause.span),
+            None => unique.push((printed, clause, Vec::new())),
+        }
+    }
+    if unique.len() < clauses.len() {
+        let mut labels = Vec::new();
+        for (_, clause, duplicates) in unique.iter().filter(|(.., d)| !d.is_empty()) {
+            labels.push((clause.span, "first specified here".to_string()));
+            labels.extend(duplicates.iter().map(|&span| (span, "duplicate clause".to_string())));
+        }
+        let simplified: Vec<_> = unique.iter().map(|(printed, clause, _)| {
+            sess.source_map().span_to_snippet(clause.span).unwrap_or_else(|_| printed.clone())
+        }).collect();
+        let suggestion = if simplified.len() == 1 {
+            simplified[0].clone()
+        } else {
+            format!("{}({})", name, simplified.join(", "))
+        };
+        sess.buffer_lint_with_diagnostic(
+            BufferedEarlyLintId::DuplicateCfgPredicates,
+            cfg.span,
+            ast::CRATE_NODE_ID,
+            &format!("`{}` predicate has duplicate clauses", name),
+            BufferedEarlyLintDiagnostics::CfgPredicate(labels, Some((cfg.span, suggestion))),
+        );
+    }
+
+    if name == sym::all {
+        if let Some((first, second)) = find_contradiction(&clauses) {
+            let (first_printed, second_printed) =
+                (pprust::meta_item_to_string(first), pprust::meta_item_to_string(second));
+            let labels = vec![
+                (first.span, format!("`{}` is required here", first_printed)),
+                (second.span, format!("but `{}` is also required", second_printed)),
+            ];
+            sess.buffer_lint_with_diagnostic(
+                BufferedEarlyLintId::ContradictoryCfgPredicates,
+                cfg.span,
+                ast::CRATE_NODE_ID,
+                "`cfg` predicate can never be satisfied",
+                BufferedEarlyLintDiagnostics::CfgPredicate(labels, None),
+            );
+        }
+    }
+}
+
+/// Configuration keys that only ever have a single value.
+const SINGLE_VALUED_CFG_KEYS: &[Symbol] = &[
+    sym::target_arch,
+    sym::target_endian,
+    sym::target_env,
+    sym::target_family,
+    sym::target_os,
+    sym::target_pointer_width,
+    sym::target_vendor,
+];
+
+/// Returns two clauses of an `all` predicate that can't both hold: a configuration key and its
+/// negation, or two different values of a key that only ever has a single value.
+fn find_contradiction<'a>(
+    clauses: &[&'a ast::MetaItem],
+) -> Option<(&'a ast::MetaItem, &'a ast::MetaItem)> {
+    let literals: Vec<_> = clauses.iter()
+        .filter_map(|&clause| cfg_literal(clause).map(|(positive, key)| (positive, key, clause)))
+        .collect();
+    for (i, &(positive, (name, value), clause)) in literals.iter().enumerate() {
+        for &(other_positive, (other_name, other_value), other) in &literals[i + 1..] {
+            let contradicts = if positive != other_positive {
+                (name, value) == (other_name, other_value)
+            } else {
+                positive && name == other_name && SINGLE_VALUED_CFG_KEYS.contains(&name) &&
+                    value.is_some() && other_value.is_some() && value != other_value
+            };
+            if contradicts {
+                return Some((clause, other));
+            }
+        }
+    }
+    None
+}
+
+/// Returns the configuration key and value tested by `clause`, with `false` if `clause` tests
+/// that they're not set. `unix` and `windows` are returned as the `target_family` they stand for.
+fn cfg_literal(clause: &ast::MetaItem) -> Option<(bool, (Symbol, Option<Symbol>))> {
+    if clause.check_name(sym::not) {
+        return match clause.meta_item_list()? {
+            [negated] => cfg_key(negated.meta_item()?).map(|key| (false, key)),
+            _ => None,
+        };
+    }
+    cfg_key(clause).map(|key| (true, key))
+}
+
+fn cfg_key(clause: &ast::MetaItem) -> Option<(Symbol, Option<Symbol>)> {
+    let name = clause.ident()?.name;
+    match clause.node {
+        ast::MetaItemKind::Word if name == sym::unix || name == sym::windows => {
+            Some((sym::target_family, Some(name)))
+        }
+        ast::MetaItemKind::Word => Some((name, None)),
+        ast::MetaItemKind::NameValue(..) => Some((name, Some(clause.value_str()?))),
+        ast::MetaItemKind::List(..) => None,
+    }
+}
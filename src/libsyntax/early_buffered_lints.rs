@@ -4,13 +4,31 @@
 //! redundant. Later, these types can be converted to types for use by the rest of the compiler.
 
 use crate::ast::NodeId;
-use syntax_pos::MultiSpan;
+use syntax_pos::{MultiSpan, Span};
 
 /// Since we cannot import `LintId`s from `rustc::lint`, we define some Ids here which can later be
 /// passed to `rustc::lint::Lint::from_parser_lint_id` to get a `rustc::lint::Lint`.
 pub enum BufferedEarlyLintId {
     IllFormedAttributeInput,
     MetaVariableMisuse,
+    ContradictoryCfgPredicates,
+    DuplicateCfgPredicates,
+    MisusedMacroSeparators,
+    MalformedSuppressionMarkers,
+}
+
+/// Labels and suggestions to add to a buffered lint, which can later be passed to
+/// `rustc::lint::BuiltinLintDiagnostics::from_parser_lint_diagnostics`.
+pub enum BufferedEarlyLintDiagnostics {
+    Normal,
+    /// Labels clauses of a `cfg` predicate, and suggests replacing the predicate at the span with
+    /// a simpler one, if there is one.
+    CfgPredicate(Vec<(Span, String)>, Option<(Span, String)>),
+    /// Labels and explains a misused separator in a macro matcher, and suggests replacing the
+    /// code at the span, with the given message, if there is a fix.
+    MacroSeparator(Vec<(Span, String)>, Vec<String>, Option<(Span, String, String)>),
+    /// Explains what diagnostic suppression markers look like.
+    MalformedSuppressionMarker,
 }
 
 /// Stores buffered lint info which can later be passed to `librustc`.
@@ -26,4 +44,7 @@ pub struct BufferedEarlyLint {
 
    /// A lint Id that can be passed to `rustc::lint::Lint::from_parser_lint_id`.
    pub lint_id: BufferedEarlyLintId,
+
+   /// Labels and suggestions to add to the lint.
+   pub diagnostic: BufferedEarlyLintDiagnostics,
 }
@@ -4,6 +4,8 @@ use crate::source_map::SourceMap;
 use crate::edition::Edition;
 use crate::ext::expand::{self, AstFragment, Invocation};
 use crate::ext::hygiene::ExpnId;
+use crate::ext::tt::macro_parser::MatcherMetrics;
+use crate::ext::tt::macro_rules::MacroGrammar;
 use crate::mut_visit::{self, MutVisitor};
 use crate::parse::{self, parser, ParseSess, DirectoryOwnership};
 use crate::parse::token;
@@ -25,6 +27,7 @@ use std::path::PathBuf;
 use std::rc::Rc;
 use std::default::Default;
 
+pub use crate::ext::placeholders::StalledInvocation;
 pub use syntax_pos::hygiene::MacroKind;
 
 #[derive(Debug,Clone)]
@@ -316,6 +319,16 @@ pub trait TTMacroExpander {
         span: Span,
         input: TokenStream,
     ) -> Box<dyn MacResult+'cx>;
+
+    /// Describes the input the macro accepts, if it's defined by `macro_rules`.
+    fn matcher_grammar(&self) -> Option<MacroGrammar> {
+        None
+    }
+
+    /// The size of each matcher of the macro, if it's defined by `macro_rules`.
+    fn matcher_metrics(&self) -> Option<Vec<MatcherMetrics>> {
+        None
+    }
 }
 
 pub type MacroExpanderFn =
@@ -895,6 +908,11 @@ pub trait Resolver {
 
     fn has_derives(&self, expn_id: ExpnId, derives: SpecialDerives) -> bool;
     fn add_derives(&mut self, expn_id: ExpnId, derives: SpecialDerives);
+
+    /// Called when expansion stops making progress and the `invocations` waiting for their
+    /// paths to resolve are about to be resolved with forced, possibly dummy, results. `dump`
+    /// describes them, see `ExtCtxt::dump_stalled_invocations`.
+    fn note_stalled_invocations(&mut self, invocations: &[StalledInvocation], dump: &str);
 }
 
 #[derive(Clone)]
@@ -922,6 +940,11 @@ pub struct ExtCtxt<'a> {
     pub resolver: &'a mut dyn Resolver,
     pub current_expansion: ExpansionData,
     pub expansions: FxHashMap<Span, Vec<String>>,
+    /// The invocations that were waiting for their paths to resolve the last time expansion
+    /// stopped making progress.
+    pub stalled_invocations: Vec<StalledInvocation>,
+    /// The expansions resolved by monotonic expanders, for `-Z dump-expansion-graph`.
+    pub resolved_expansions: Vec<ExpnId>,
 }
 
 impl<'a> ExtCtxt<'a> {
@@ -942,6 +965,8 @@ impl<'a> ExtCtxt<'a> {
                 prior_type_ascription: None,
             },
             expansions: FxHashMap::default(),
+            stalled_invocations: Vec::new(),
+            resolved_expansions: Vec::new(),
         }
     }
 
@@ -1071,6 +1096,28 @@ impl<'a> ExtCtxt<'a> {
     pub fn set_trace_macros(&mut self, x: bool) {
         self.ecfg.trace_mac = x
     }
+    crate fn record_stalled_invocations(&mut self, invocations: &[Invocation]) {
+        self.stalled_invocations = invocations.iter().map(StalledInvocation::new).collect();
+        let dump = self.dump_stalled_invocations();
+        self.resolver.note_stalled_invocations(&self.stalled_invocations, &dump);
+    }
+    /// Describes the invocations in `stalled_invocations`, one per line, with the placeholders
+    /// standing in for their output and the macro paths they are waiting on.
+    pub fn dump_stalled_invocations(&self) -> String {
+        let mut dump = String::new();
+        for invoc in &self.stalled_invocations {
+            let paths = invoc.paths.iter()
+                .map(|path| format!("`{}`", path))
+                .collect::<Vec<_>>();
+            dump.push_str(&format!("{:?}: {} at {}, waiting on the {} {}\n",
+                                   invoc.placeholder_id,
+                                   invoc.fragment_kind.name(),
+                                   self.source_map().span_to_string(invoc.span),
+                                   invoc.kind.descr(),
+                                   paths.join(", ")));
+        }
+        dump
+    }
     pub fn ident_of(&self, st: &str, sp: Span) -> ast::Ident {
         ast::Ident::from_str_and_span(st, sp)
     }
@@ -1171,7 +1218,15 @@ pub fn get_single_str_from_tts(cx: &mut ExtCtxt<'_>,
         cx.span_err(sp, &format!("{} takes 1 argument", name));
         return None
     }
-    let ret = panictry!(p.parse_expr());
+    let ret = match p.parse_expr() {
+        Ok(ret) => ret,
+        Err(mut err) => {
+            err.emit();
+            // In error-as-value mode, the error is propagated as `None`.
+            let _ = cx.parse_sess.span_diagnostic.fatal_error();
+            return None;
+        }
+    };
     let _ = p.eat(&token::Comma);
 
     if p.token != token::Eof {
@@ -1190,7 +1245,15 @@ pub fn get_exprs_from_tts(cx: &mut ExtCtxt<'_>,
     let mut p = cx.new_parser_from_tts(tts);
     let mut es = Vec::new();
     while p.token != token::Eof {
-        let expr = panictry!(p.parse_expr());
+        let expr = match p.parse_expr() {
+            Ok(expr) => expr,
+            Err(mut err) => {
+                err.emit();
+                // In error-as-value mode, the error is propagated as `None`.
+                let _ = cx.parse_sess.span_diagnostic.fatal_error();
+                return None;
+            }
+        };
 
         // Perform eager expansion on the expression.
         // We want to be able to handle e.g., `concat!("foo", "bar")`.
@@ -31,6 +31,9 @@ use std::ops::DerefMut;
 use std::rc::Rc;
 use std::path::PathBuf;
 
+#[cfg(test)]
+mod tests;
+
 macro_rules! ast_fragments {
     (
         $($Kind:ident($AstTy:ty) {
@@ -179,7 +182,7 @@ ast_fragments! {
 }
 
 impl AstFragmentKind {
-    fn dummy(self, span: Span) -> AstFragment {
+    crate fn dummy(self, span: Span) -> AstFragment {
         self.make_from(DummyResult::any(span)).expect("couldn't create a dummy AST fragment")
     }
 
@@ -276,7 +279,7 @@ impl<'a, 'b> MacroExpander<'a, 'b> {
         MacroExpander { cx, monotonic }
     }
 
-    pub fn expand_crate(&mut self, mut krate: ast::Crate) -> ast::Crate {
+    fn enter_crate_root(&mut self, krate: &ast::Crate) {
         let mut module = ModuleData {
             mod_path: vec![Ident::from_str(&self.cx.ecfg.crate_name)],
             directory: match self.cx.source_map().span_to_unmapped_path(krate.span) {
@@ -287,6 +290,10 @@ impl<'a, 'b> MacroExpander<'a, 'b> {
         module.directory.pop();
         self.cx.root_path = module.directory.clone();
         self.cx.current_expansion.module = Rc::new(module);
+    }
+
+    pub fn expand_crate(&mut self, mut krate: ast::Crate) -> ast::Crate {
+        self.enter_crate_root(&krate);
 
         let orig_mod_span = krate.module.inner;
 
@@ -312,14 +319,51 @@ impl<'a, 'b> MacroExpander<'a, 'b> {
                     inner: orig_mod_span,
                     items: vec![],
                     inline: true,
+                    dangling_attrs: vec![],
                 };
             },
             _ => unreachable!(),
         };
         self.cx.trace_macros_diag();
+        if self.cx.ecfg.dump_expansion_graph {
+            let resolved_expansions = mem::take(&mut self.cx.resolved_expansions);
+            print_expansion_graph(self.cx, &resolved_expansions);
+        }
         krate
     }
 
+    /// Expands statements parsed from an additional input chunk against an already expanded
+    /// `krate`, as done by interactive front ends evaluating their input piece by piece
+    /// (see `parse::parse_stmts_from_source_str`).
+    ///
+    /// Item statements are expanded in the crate root module and appended to `krate`, after the
+    /// items of earlier chunks. The remaining statements are expanded and returned for the caller
+    /// to evaluate. With a monotonic expander, all new nodes receive fresh `NodeId`s.
+    pub fn expand_appended_stmts(
+        &mut self,
+        krate: &mut ast::Crate,
+        stmts: Vec<ast::Stmt>,
+    ) -> Vec<ast::Stmt> {
+        self.enter_crate_root(krate);
+
+        let (items, stmts): (Vec<_>, Vec<_>) = stmts.into_iter().partition(|stmt| {
+            match stmt.node {
+                StmtKind::Item(..) => true,
+                _ => false,
+            }
+        });
+        let items = items.into_iter().map(|stmt| match stmt.node {
+            StmtKind::Item(item) => item,
+            _ => unreachable!(),
+        }).collect();
+
+        let items = self.fully_expand_fragment(AstFragment::Items(items)).make_items();
+        krate.module.items.extend(items);
+        let stmts = self.fully_expand_fragment(AstFragment::Stmts(stmts.into())).make_stmts();
+        self.cx.trace_macros_diag();
+        stmts.into_iter().collect()
+    }
+
     // Recursively expand all macro invocations in this AST fragment.
     pub fn fully_expand_fragment(&mut self, input_fragment: AstFragment) -> AstFragment {
         let orig_expansion_data = self.cx.current_expansion.clone();
@@ -350,6 +394,9 @@ impl<'a, 'b> MacroExpander<'a, 'b> {
                 if undetermined_invocations.is_empty() { break }
                 invocations = mem::take(&mut undetermined_invocations);
                 force = !mem::replace(&mut progress, false);
+                if force && self.monotonic {
+                    self.cx.record_stalled_invocations(&invocations);
+                }
                 continue
             };
 
@@ -367,6 +414,9 @@ impl<'a, 'b> MacroExpander<'a, 'b> {
 
             progress = true;
             let ExpansionData { depth, id: expn_id, .. } = invoc.expansion_data;
+            if self.cx.ecfg.dump_expansion_graph && self.monotonic {
+                self.cx.resolved_expansions.push(expn_id);
+            }
             self.cx.current_expansion = invoc.expansion_data.clone();
 
             // FIXME(jseyfried): Refactor out the following logic
@@ -554,6 +604,49 @@ impl<'a, 'b> MacroExpander<'a, 'b> {
         }
     }
 
+    /// Reports the chain of expansions leading from an earlier expansion of the attribute or
+    /// derive macro in `expn_data` to this one, if there is one. Such a macro produces another
+    /// invocation of itself, so its expansion never terminates.
+    /// Returns whether a cycle was reported.
+    fn report_expansion_cycle(&mut self, expn_data: &ExpnData) -> bool {
+        let invoked = match expn_data.kind {
+            ExpnKind::Macro(kind @ MacroKind::Attr, name) |
+            ExpnKind::Macro(kind @ MacroKind::Derive, name) => (kind, name),
+            _ => return false,
+        };
+
+        let mut cycle = Vec::new();
+        let mut parent = expn_data.parent;
+        loop {
+            if parent == ExpnId::root() {
+                return false;
+            }
+            let parent_data = parent.expn_data();
+            parent = parent_data.parent;
+            let completes_cycle = match parent_data.kind {
+                ExpnKind::Macro(kind, name) => (kind, name) == invoked,
+                _ => false,
+            };
+            cycle.push(parent_data);
+            if completes_cycle {
+                break;
+            }
+        }
+
+        let mut err = self.cx.struct_span_err(expn_data.call_site, &format!(
+            "cycle detected when expanding the {}", describe_expansion(&expn_data.kind)));
+        let (first, rest) = cycle.split_last().unwrap();
+        for step in rest {
+            err.span_note(step.call_site, &format!(
+                "...which was invoked by the output of the {}...", describe_expansion(&step.kind)));
+        }
+        err.span_note(first.call_site, &format!(
+            "...which was invoked by the output of the {} again, completing the cycle",
+            describe_expansion(&first.kind)));
+        err.emit();
+        true
+    }
+
     fn expand_invoc(&mut self, invoc: Invocation, ext: &SyntaxExtensionKind) -> AstFragment {
         let (fragment_kind, span) = (invoc.fragment_kind, invoc.span());
         if fragment_kind == AstFragmentKind::ForeignItems && !self.cx.ecfg.macros_in_extern() {
@@ -566,14 +659,16 @@ impl<'a, 'b> MacroExpander<'a, 'b> {
 
         if self.cx.current_expansion.depth > self.cx.ecfg.recursion_limit {
             let expn_data = self.cx.current_expansion.id.expn_data();
-            let suggested_limit = self.cx.ecfg.recursion_limit * 2;
-            let mut err = self.cx.struct_span_err(expn_data.call_site,
-                &format!("recursion limit reached while expanding the macro `{}`",
-                         expn_data.kind.descr()));
-            err.help(&format!(
-                "consider adding a `#![recursion_limit=\"{}\"]` attribute to your crate",
-                suggested_limit));
-            err.emit();
+            if !self.report_expansion_cycle(&expn_data) {
+                let suggested_limit = self.cx.ecfg.recursion_limit * 2;
+                let mut err = self.cx.struct_span_err(expn_data.call_site,
+                    &format!("recursion limit reached while expanding the macro `{}`",
+                             expn_data.kind.descr()));
+                err.help(&format!(
+                    "consider adding a `#![recursion_limit=\"{}\"]` attribute to your crate",
+                    suggested_limit));
+                err.emit();
+            }
             self.cx.trace_macros_diag();
             FatalError.raise();
         }
@@ -1240,6 +1335,7 @@ impl<'a, 'b> MutVisitor for InvocationCollector<'a, 'b> {
 
     fn flat_map_item(&mut self, item: P<ast::Item>) -> SmallVec<[P<ast::Item>; 1]> {
         let mut item = configure!(self, item);
+        item.tokens = item.tokens.take().map(|tokens| self.cfg.configure_tokens(tokens, false));
 
         let (attr, traits, after_derive) = self.classify_item(&mut item);
         if attr.is_some() || !traits.is_empty() {
@@ -1311,6 +1407,7 @@ impl<'a, 'b> MutVisitor for InvocationCollector<'a, 'b> {
 
     fn flat_map_trait_item(&mut self, item: ast::TraitItem) -> SmallVec<[ast::TraitItem; 1]> {
         let mut item = configure!(self, item);
+        item.tokens = item.tokens.take().map(|tokens| self.cfg.configure_tokens(tokens, false));
 
         let (attr, traits, after_derive) = self.classify_item(&mut item);
         if attr.is_some() || !traits.is_empty() {
@@ -1330,6 +1427,7 @@ impl<'a, 'b> MutVisitor for InvocationCollector<'a, 'b> {
 
     fn flat_map_impl_item(&mut self, item: ast::ImplItem) -> SmallVec<[ast::ImplItem; 1]> {
         let mut item = configure!(self, item);
+        item.tokens = item.tokens.take().map(|tokens| self.cfg.configure_tokens(tokens, false));
 
         let (attr, traits, after_derive) = self.classify_item(&mut item);
         if attr.is_some() || !traits.is_empty() {
@@ -1555,6 +1653,28 @@ impl<'a, 'b> MutVisitor for InvocationCollector<'a, 'b> {
     }
 }
 
+/// Describes the macro or compiler pass that produced an expansion of `kind`.
+fn describe_expansion(kind: &ExpnKind) -> String {
+    match *kind {
+        ExpnKind::Macro(macro_kind, name) => format!("{} `{}`", macro_kind.descr(), name),
+        _ => kind.descr().to_string(),
+    }
+}
+
+/// Prints the expansions in `expn_ids` as a Graphviz graph for `-Z dump-expansion-graph`,
+/// with an edge from every expansion to each expansion invoked by its output.
+fn print_expansion_graph(cx: &ExtCtxt<'_>, expn_ids: &[ExpnId]) {
+    println!("digraph expansions {{");
+    for &expn_id in expn_ids {
+        let expn_data = expn_id.expn_data();
+        let label = format!("{} at {}", describe_expansion(&expn_data.kind),
+                            cx.source_map().span_to_string(expn_data.call_site));
+        println!("    {} [label={:?}];", expn_id.as_u32(), label);
+        println!("    {} -> {};", expn_data.parent.as_u32(), expn_id.as_u32());
+    }
+    println!("}}");
+}
+
 pub struct ExpansionConfig<'feat> {
     pub crate_name: String,
     pub features: Option<&'feat Features>,
@@ -1563,6 +1683,7 @@ pub struct ExpansionConfig<'feat> {
     pub should_test: bool, // If false, strip `#[test]` nodes
     pub single_step: bool,
     pub keep_macs: bool,
+    pub dump_expansion_graph: bool,
 }
 
 impl<'feat> ExpansionConfig<'feat> {
@@ -1575,6 +1696,7 @@ impl<'feat> ExpansionConfig<'feat> {
             should_test: false,
             single_step: false,
             keep_macs: false,
+            dump_expansion_graph: false,
         }
     }
 
@@ -0,0 +1,57 @@
+use super::*;
+
+use crate::ext::macro_rules_resolver::MacroRulesResolver;
+use crate::parse;
+use crate::print::pprust;
+use crate::source_map::FilePathMapping;
+use crate::with_default_globals;
+
+fn chunk(sess: &ParseSess, name: &str, source: &str) -> Vec<ast::Stmt> {
+    let name = FileName::Custom(name.to_string());
+    parse::parse_stmts_from_source_str(name, source.to_string(), sess).unwrap()
+}
+
+fn expr(stmt: &ast::Stmt) -> String {
+    match stmt.node {
+        StmtKind::Local(ref local) => pprust::expr_to_string(local.init.as_ref().unwrap()),
+        StmtKind::Expr(ref expr) | StmtKind::Semi(ref expr) => pprust::expr_to_string(expr),
+        _ => panic!("expected a `let` or an expression statement"),
+    }
+}
+
+#[test]
+fn expand_appended_stmts() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let name = FileName::Custom("main".to_string());
+        let source = "macro_rules! double { ($e:expr) => { 2 * $e } }\n".to_string();
+        let mut krate = parse::parse_crate_from_source_str(name, source, &sess).unwrap();
+        let mut resolver = MacroRulesResolver::new(&sess, sess.edition);
+        resolver.define_macros(&krate.module.items);
+        let ecfg = ExpansionConfig::default("repl".to_string());
+        let mut cx = ExtCtxt::new(&sess, ecfg, &mut resolver);
+        let mut expander = cx.monotonic_expander();
+
+        let first = chunk(&sess, "chunk_1", "fn f() -> i32 { double!(1) } let x = double!(f());");
+        let stmts = expander.expand_appended_stmts(&mut krate, first);
+        assert_eq!(stmts.len(), 1);
+        assert_eq!(expr(&stmts[0]), "2 * f()");
+        assert_eq!(krate.module.items.len(), 2);
+        match krate.module.items[1].node {
+            ItemKind::Fn(_, _, _, ref body) => assert_eq!(expr(&body.stmts[0]), "2 * 1"),
+            _ => panic!("expected `f` to be appended to the crate"),
+        }
+
+        // Earlier items are kept, and the new nodes get fresh ids.
+        let second = chunk(&sess, "chunk_2", "struct S; x + double!(3)");
+        let stmts = expander.expand_appended_stmts(&mut krate, second);
+        assert_eq!(expr(&stmts[0]), "x + 2 * 3");
+        let names = krate.module.items.iter().map(|item| item.ident.to_string());
+        assert_eq!(names.collect::<Vec<_>>(), ["double", "f", "S"]);
+        let (f, s) = (&krate.module.items[1], &krate.module.items[2]);
+        assert!(f.id != ast::DUMMY_NODE_ID && s.id != ast::DUMMY_NODE_ID && f.id != s.id);
+        let loc = sess.source_map().lookup_char_pos(s.span.lo());
+        assert_eq!(loc.file.name, FileName::Custom("chunk_2".to_string()));
+        assert_eq!(sess.span_diagnostic.err_count(), 0);
+    })
+}
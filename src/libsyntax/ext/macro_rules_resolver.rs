@@ -0,0 +1,158 @@
+//! A `Resolver` for expanding code outside of a crate, e.g. single expressions evaluated by a
+//! debugger, where the only macros in scope are `macro_rules!` macros and built-in macros that
+//! are explicitly defined.
+//!
+//! Macros are resolved by the last segment of their paths. There's no name resolution otherwise,
+//! so imports, modules and `#[macro_export]` don't affect which macros are found, and all
+//! expansions are recorded as direct children of the root expansion.
+
+use crate::ast::{self, NodeId};
+use crate::edition::Edition;
+use crate::ext::base::{Indeterminate, InvocationRes, MacroKind, Resolver, SpecialDerives};
+use crate::ext::base::{StalledInvocation, SyntaxExtension};
+use crate::ext::expand::{AstFragment, Invocation, InvocationKind};
+use crate::ext::hygiene::{AstPass, ExpnData, ExpnId, ExpnKind};
+use crate::ext::tt::macro_rules;
+use crate::feature_gate::Features;
+use crate::parse::ParseSess;
+use crate::print::pprust;
+use crate::ptr::P;
+use crate::symbol::Symbol;
+
+use rustc_data_structures::fx::FxHashMap;
+use rustc_data_structures::sync::Lrc;
+use syntax_pos::Span;
+
+pub struct MacroRulesResolver<'a> {
+    sess: &'a ParseSess,
+    edition: Edition,
+    macros: FxHashMap<Symbol, Lrc<SyntaxExtension>>,
+    next_node_id: NodeId,
+}
+
+impl<'a> MacroRulesResolver<'a> {
+    pub fn new(sess: &'a ParseSess, edition: Edition) -> Self {
+        MacroRulesResolver {
+            sess,
+            edition,
+            macros: FxHashMap::default(),
+            next_node_id: NodeId::from_u32(ast::CRATE_NODE_ID.as_u32() + 1),
+        }
+    }
+
+    /// Defines the `macro_rules!` macros among `items`, shadowing earlier macros of the same
+    /// names. Other items are ignored.
+    pub fn define_macros(&mut self, items: &[P<ast::Item>]) {
+        let features = Features::new();
+        for item in items {
+            match item.node {
+                ast::ItemKind::MacroDef(ref def) if def.legacy => {
+                    let ext = macro_rules::compile(self.sess, &features, item, self.edition);
+                    self.macros.insert(item.ident.name, Lrc::new(ext));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Reports that `path` doesn't name a macro of `kind`, and returns the extension expanding
+    /// it instead.
+    fn unresolved(&self, path: &ast::Path, kind: MacroKind) -> Lrc<SyntaxExtension> {
+        let (msg, ext) = match kind {
+            MacroKind::Bang => (
+                format!("cannot find macro `{}!` in this scope", path),
+                SyntaxExtension::dummy_bang(self.edition),
+            ),
+            MacroKind::Attr => (
+                format!("cannot find attribute macro `{}` in this scope", path),
+                SyntaxExtension::non_macro_attr(true, self.edition),
+            ),
+            MacroKind::Derive => (
+                format!("cannot find derive macro `{}` in this scope", path),
+                SyntaxExtension::dummy_derive(self.edition),
+            ),
+        };
+        self.sess.span_diagnostic.span_err(path.span, &msg);
+        Lrc::new(ext)
+    }
+
+    fn resolve_macro_path(&self, path: &ast::Path, kind: MacroKind) -> Lrc<SyntaxExtension> {
+        let name = path.segments.last().map(|segment| segment.ident.name);
+        match name.and_then(|name| self.macros.get(&name)) {
+            Some(ext) if ext.macro_kind() == kind => ext.clone(),
+            _ => self.unresolved(path, kind),
+        }
+    }
+}
+
+impl Resolver for MacroRulesResolver<'_> {
+    fn next_node_id(&mut self) -> NodeId {
+        let id = self.next_node_id;
+        self.next_node_id = NodeId::from_u32(id.as_u32() + 1);
+        id
+    }
+
+    fn resolve_dollar_crates(&mut self) {}
+
+    fn visit_ast_fragment_with_placeholders(
+        &mut self,
+        _: ExpnId,
+        _: &AstFragment,
+        _: &[NodeId],
+    ) {}
+
+    /// Defines a built-in macro, see `syntax_ext::register_builtin_macros`.
+    fn register_builtin_macro(&mut self, ident: ast::Ident, ext: SyntaxExtension) {
+        self.macros.insert(ident.name, Lrc::new(ext));
+    }
+
+    fn expansion_for_ast_pass(
+        &mut self,
+        call_site: Span,
+        pass: AstPass,
+        features: &[Symbol],
+        _: Option<NodeId>,
+    ) -> ExpnId {
+        ExpnId::fresh(Some(ExpnData::allow_unstable(
+            ExpnKind::AstPass(pass),
+            call_site,
+            self.edition,
+            features.into(),
+        )))
+    }
+
+    fn resolve_imports(&mut self) {}
+
+    fn resolve_macro_invocation(
+        &mut self,
+        invoc: &Invocation,
+        _: ExpnId,
+        _: bool,
+    ) -> Result<InvocationRes, Indeterminate> {
+        let (path, kind) = match invoc.kind {
+            InvocationKind::Bang { ref mac, .. } => (&mac.path, MacroKind::Bang),
+            InvocationKind::Attr { ref attr, .. } => (&attr.path, MacroKind::Attr),
+            InvocationKind::Derive { ref path, .. } => (path, MacroKind::Derive),
+            InvocationKind::DeriveContainer { ref derives, .. } => {
+                let exts = derives.iter()
+                    .map(|path| self.resolve_macro_path(path, MacroKind::Derive))
+                    .collect();
+                return Ok(InvocationRes::DeriveContainer(exts));
+            }
+        };
+        let ext = self.resolve_macro_path(path, kind);
+        let descr = Symbol::intern(&pprust::path_to_string(path));
+        invoc.expansion_data.id.set_expn_data(ext.expn_data(ExpnId::root(), invoc.span(), descr));
+        Ok(InvocationRes::Single(ext))
+    }
+
+    fn check_unused_macros(&self) {}
+
+    fn has_derives(&self, _: ExpnId, _: SpecialDerives) -> bool {
+        false
+    }
+
+    fn add_derives(&mut self, _: ExpnId, _: SpecialDerives) {}
+
+    fn note_stalled_invocations(&mut self, _: &[StalledInvocation], _: &str) {}
+}
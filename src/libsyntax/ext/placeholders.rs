@@ -1,16 +1,49 @@
 use crate::ast::{self, NodeId};
 use crate::source_map::{DUMMY_SP, dummy_spanned};
-use crate::ext::base::ExtCtxt;
-use crate::ext::expand::{AstFragment, AstFragmentKind};
+use crate::ext::base::{ExtCtxt, MacroKind};
+use crate::ext::expand::{AstFragment, AstFragmentKind, Invocation, InvocationKind};
 use crate::tokenstream::TokenStream;
 use crate::mut_visit::*;
 use crate::ptr::P;
+use crate::util::map_in_place::{chunked_flat_map_in_place, ITEM_CHUNK_LEN};
 use crate::ThinVec;
 
 use smallvec::{smallvec, SmallVec};
+use syntax_pos::Span;
 
 use rustc_data_structures::fx::FxHashMap;
 
+/// A macro invocation that was still waiting for its path to resolve when expansion stopped
+/// making progress. Its output is represented by the placeholder `placeholder_id` in the AST.
+#[derive(Clone)]
+pub struct StalledInvocation {
+    pub placeholder_id: NodeId,
+    pub fragment_kind: AstFragmentKind,
+    pub kind: MacroKind,
+    pub span: Span,
+    /// The paths the invocation is waiting on, one per derive for a `#[derive]` attribute.
+    pub paths: Vec<ast::Path>,
+}
+
+impl StalledInvocation {
+    crate fn new(invoc: &Invocation) -> StalledInvocation {
+        let (kind, paths) = match &invoc.kind {
+            InvocationKind::Bang { mac, .. } => (MacroKind::Bang, vec![mac.path.clone()]),
+            InvocationKind::Attr { attr, .. } => (MacroKind::Attr, vec![attr.path.clone()]),
+            InvocationKind::Derive { path, .. } => (MacroKind::Derive, vec![path.clone()]),
+            InvocationKind::DeriveContainer { derives, .. } =>
+                (MacroKind::Derive, derives.clone()),
+        };
+        StalledInvocation {
+            placeholder_id: NodeId::placeholder_from_expn_id(invoc.expansion_data.id),
+            fragment_kind: invoc.fragment_kind,
+            kind,
+            span: invoc.span(),
+            paths,
+        }
+    }
+}
+
 pub fn placeholder(kind: AstFragmentKind, id: ast::NodeId) -> AstFragment {
     fn mac_placeholder() -> ast::Mac {
         ast::Mac {
@@ -336,7 +369,10 @@ impl<'a, 'b> MutVisitor for PlaceholderExpander<'a, 'b> {
     }
 
     fn visit_mod(&mut self, module: &mut ast::Mod) {
-        noop_visit_mod(module, self);
+        let ast::Mod { inner, items, inline: _, dangling_attrs } = module;
+        self.visit_span(inner);
+        chunked_flat_map_in_place(items, ITEM_CHUNK_LEN, |item| self.flat_map_item(item));
+        visit_attrs(dangling_attrs, self);
         module.items.retain(|item| match item.node {
             ast::ItemKind::Mac(_) if !self.cx.ecfg.keep_macs => false, // remove macro definitions
             _ => true,
@@ -2,14 +2,15 @@ use crate::ast;
 use crate::ext::base::ExtCtxt;
 use crate::parse::{self, token, ParseSess};
 use crate::parse::lexer::comments;
+use crate::source_map::SourceMap;
 use crate::tokenstream::{self, DelimSpan, IsJoint::*, TokenStream, TreeAndJoint};
 
-use errors::Diagnostic;
+use errors::{CodeSuggestion, Diagnostic, SubstitutionPart};
 use rustc_data_structures::sync::Lrc;
 use syntax_pos::{BytePos, FileName, MultiSpan, Pos, SourceFile, Span};
 use syntax_pos::symbol::{kw, sym, Symbol};
 
-use proc_macro::{Delimiter, Level, LineColumn, Spacing};
+use proc_macro::{Applicability, Delimiter, Level, LineColumn, Spacing};
 use proc_macro::bridge::{server, TokenTree};
 use std::{ascii, panic};
 use std::ops::Bound;
@@ -279,6 +280,18 @@ impl ToInternal<errors::Level> for Level {
     }
 }
 
+impl ToInternal<errors::Applicability> for Applicability {
+    fn to_internal(self) -> errors::Applicability {
+        match self {
+            Applicability::MachineApplicable => errors::Applicability::MachineApplicable,
+            Applicability::MaybeIncorrect => errors::Applicability::MaybeIncorrect,
+            Applicability::HasPlaceholders => errors::Applicability::HasPlaceholders,
+            Applicability::Unspecified => errors::Applicability::Unspecified,
+            _ => unreachable!("unknown proc_macro::Applicability variant: {:?}", self),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct TokenStreamIter {
     cursor: tokenstream::Cursor,
@@ -649,11 +662,55 @@ impl server::Diagnostic for Rustc<'_> {
     ) {
         diag.sub(level.to_internal(), msg, MultiSpan::from_spans(spans), None);
     }
-    fn emit(&mut self, diag: Self::Diagnostic) {
+    fn suggestion(
+        &mut self,
+        diag: &mut Self::Diagnostic,
+        msg: &str,
+        applicability: Applicability,
+    ) {
+        // The parts are added by the calls to `suggestion_part` that follow.
+        diag.multipart_suggestion(msg, vec![], applicability.to_internal());
+    }
+    fn suggestion_part(
+        &mut self,
+        diag: &mut Self::Diagnostic,
+        span: Self::Span,
+        replacement: &str,
+    ) {
+        let suggestion = diag.suggestions.last_mut()
+            .expect("proc_macro::Diagnostic suggestion part without a suggestion");
+        let part = SubstitutionPart { span, snippet: replacement.to_string() };
+        suggestion.substitutions[0].parts.push(part);
+    }
+    fn emit(&mut self, mut diag: Self::Diagnostic) {
+        // Suggestions that can't be applied would make the emitter ICE, so they are turned into
+        // plain help messages.
+        let source_map = self.sess.source_map();
+        let (valid, invalid): (Vec<_>, Vec<_>) = diag.suggestions.drain(..)
+            .partition(|suggestion| is_applicable_suggestion(source_map, suggestion));
+        diag.suggestions = valid;
+        for suggestion in invalid {
+            diag.help(&suggestion.msg);
+        }
         self.sess.span_diagnostic.emit_diagnostic(&diag);
     }
 }
 
+/// Returns whether the parts of each substitution of `suggestion` replace code of a single
+/// file without overlapping, as the emitter expects them to.
+fn is_applicable_suggestion(source_map: &SourceMap, suggestion: &CodeSuggestion) -> bool {
+    suggestion.substitutions.iter().all(|substitution| {
+        let mut spans: Vec<_> = substitution.parts.iter().map(|part| part.span).collect();
+        if spans.is_empty() || spans.iter().any(|span| span.is_dummy()) {
+            return false;
+        }
+        spans.sort_by_key(|span| span.lo());
+        let file = source_map.lookup_char_pos(spans[0].lo()).file;
+        spans.iter().all(|span| file.contains(span.lo()) && file.contains(span.hi()))
+            && spans.windows(2).all(|pair| pair[0].hi() <= pair[1].lo())
+    })
+}
+
 impl server::Span for Rustc<'_> {
     fn debug(&mut self, span: Self::Span) -> String {
         format!("{:?} bytes({}..{})", span.ctxt(), span.lo().0, span.hi().0)
@@ -276,6 +276,13 @@ fn check_binders(
                 binders.insert(name, BinderInfo { span, ops: ops.into() });
             }
         }
+        // Like a bound meta-variable, this can only be an occurrence, in the LHS of a nested macro.
+        TokenTree::MetaVarExpr(span, _) => {
+            if macros.is_empty() {
+                sess.span_diagnostic.span_bug(span.entire(), "unexpected MetaVarExpr in lhs");
+            }
+            check_occurrences(sess, node_id, lhs, macros, binders, ops, valid);
+        }
         TokenTree::Delimited(_, ref del) => {
             for tt in &del.tts {
                 check_binders(sess, node_id, tt, macros, binders, ops, valid);
@@ -332,6 +339,16 @@ fn check_occurrences(
         TokenTree::MetaVar(span, name) => {
             check_ops_is_prefix(sess, node_id, macros, binders, ops, span, name);
         }
+        // The meta-variable of a meta-variable expression may be used at any depth, but it must
+        // be bound.
+        TokenTree::MetaVarExpr(span, ref expr) => {
+            if let Some(name) = expr.ident() {
+                if get_binder_info(macros, binders, name).is_none() {
+                    let msg = format!("unknown macro variable `{}`", name);
+                    buffer_lint(sess, span.entire().into(), node_id, &msg);
+                }
+            }
+        }
         TokenTree::Delimited(_, ref del) => {
             check_nested_occurrences(sess, node_id, &del.tts, macros, binders, ops, valid);
         }
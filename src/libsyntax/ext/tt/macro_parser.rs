@@ -72,66 +72,99 @@
 
 pub use NamedMatch::*;
 pub use ParseResult::*;
-use TokenTreeOrTokenTreeSlice::*;
 
 use crate::ast::{Ident, Name};
 use crate::ext::tt::quoted::{self, TokenTree};
-use crate::parse::{Directory, ParseSess};
+use crate::parse::{Directory, PResult, ParseSess};
 use crate::parse::parser::{Parser, PathStyle};
 use crate::parse::token::{self, DocComment, Nonterminal, Token};
 use crate::print::pprust;
 use crate::symbol::{kw, sym, Symbol};
 use crate::tokenstream::{DelimSpan, TokenStream};
 
-use errors::FatalError;
 use smallvec::{smallvec, SmallVec};
 use syntax_pos::Span;
 
 use rustc_data_structures::fx::FxHashMap;
 use rustc_data_structures::sync::Lrc;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
-use std::mem;
 use std::ops::{Deref, DerefMut};
 
 // To avoid costly uniqueness checks, we require that `MatchSeq` always has a nonempty body.
 
-/// Either a sequence of token trees or a single one. This is used as the representation of the
-/// sequence of tokens that make up a matcher.
-#[derive(Clone)]
-enum TokenTreeOrTokenTreeSlice<'tt> {
-    Tt(TokenTree),
-    TtSeq(&'tt [TokenTree]),
+/// An element of a `CompiledMatcher`.
+#[derive(Clone, Debug)]
+enum MatcherElt {
+    /// A token to match, which may be a delimiter of a delimited tree of the matcher.
+    Token(Token),
+    /// A sequence, with its compiled body.
+    Sequence(DelimSpan, CompiledSequence),
+    /// e.g., `$var:expr`, with the name to bind and the kind of nonterminal.
+    MetaVarDecl(Span, Ident, Ident),
+    /// A metavar or metavar expression, which matchers can't contain and never match.
+    Unmatchable,
 }
 
-impl<'tt> TokenTreeOrTokenTreeSlice<'tt> {
-    /// Returns the number of constituent top-level token trees of `self` (top-level in that it
-    /// will not recursively descend into subtrees).
-    fn len(&self) -> usize {
-        match *self {
-            TtSeq(ref v) => v.len(),
-            Tt(ref tt) => tt.len(),
-        }
-    }
+/// A sequence of a `CompiledMatcher`, see `quoted::SequenceRepetition`.
+#[derive(Clone, Debug)]
+struct CompiledSequence {
+    elts: Vec<MatcherElt>,
+    separator: Option<Token>,
+    op: quoted::KleeneOp,
+    num_captures: usize,
+}
 
-    /// The `index`-th token tree of `self`.
-    fn get_tt(&self, index: usize) -> TokenTree {
-        match *self {
-            TtSeq(ref v) => v[index].clone(),
-            Tt(ref tt) => tt.get_tt(index),
-        }
+/// A matcher of a macro, compiled once when the macro is defined into the form its invocations
+/// are matched against, rather than each time one is. Delimited trees are flattened into the
+/// tokens of their delimiters and contents, so that matching never descends into them or
+/// clones them, and the metavars named in the matcher are counted.
+#[derive(Clone, Debug)]
+pub struct CompiledMatcher {
+    /// The token trees of the matcher, without its outer delimiters.
+    pub tts: Vec<TokenTree>,
+    pub metrics: MatcherMetrics,
+    elts: Vec<MatcherElt>,
+}
+
+impl CompiledMatcher {
+    pub fn new(tts: Vec<TokenTree>) -> CompiledMatcher {
+        let metrics = MatcherMetrics::of(&tts);
+        let mut elts = Vec::new();
+        compile_elts(&tts, &mut elts);
+        CompiledMatcher { tts, metrics, elts }
     }
 }
 
-/// An unzipping of `TokenTree`s... see the `stack` field of `MatcherPos`.
-///
-/// This is used by `inner_parse_loop` to keep track of delimited submatchers that we have
-/// descended into.
-#[derive(Clone)]
-struct MatcherTtFrame<'tt> {
-    /// The "parent" matcher that we are descending into.
-    elts: TokenTreeOrTokenTreeSlice<'tt>,
-    /// The position of the "dot" in `elts` at the time we descended.
-    idx: usize,
+/// Appends the compiled form of the matcher `tts` to `elts`.
+fn compile_elts(tts: &[TokenTree], elts: &mut Vec<MatcherElt>) {
+    for tt in tts {
+        match *tt {
+            // Doc comments in matchers match nothing.
+            TokenTree::Token(Token { kind: DocComment(..), .. }) => {}
+            TokenTree::Token(ref token) => elts.push(MatcherElt::Token(token.clone())),
+            TokenTree::Delimited(..) => {
+                // The delimiters, unless they're invisible, and the contents.
+                let tts = (0..tt.len()).map(|idx| tt.get_tt(idx)).collect::<Vec<_>>();
+                compile_elts(&tts, elts);
+            }
+            TokenTree::Sequence(span, ref seq) => {
+                let mut seq_elts = Vec::new();
+                compile_elts(&seq.tts, &mut seq_elts);
+                elts.push(MatcherElt::Sequence(span, CompiledSequence {
+                    elts: seq_elts,
+                    separator: seq.separator.clone(),
+                    op: seq.kleene.op,
+                    num_captures: seq.num_captures,
+                }));
+            }
+            TokenTree::MetaVarDecl(span, bind, kind) => {
+                elts.push(MatcherElt::MetaVarDecl(span, bind, kind))
+            }
+            TokenTree::MetaVar(..) | TokenTree::MetaVarExpr(..) => {
+                elts.push(MatcherElt::Unmatchable)
+            }
+        }
+    }
 }
 
 type NamedMatchVec = SmallVec<[NamedMatch; 4]>;
@@ -156,8 +189,8 @@ type NamedMatchVec = SmallVec<[NamedMatch; 4]>;
 /// lifetime. By separating `'tt` from `'root`, we can show that.
 #[derive(Clone)]
 struct MatcherPos<'root, 'tt> {
-    /// The token or sequence of tokens that make up the matcher
-    top_elts: TokenTreeOrTokenTreeSlice<'tt>,
+    /// The elements of the matcher, or of the body of the sequence being repeated
+    top_elts: &'tt [MatcherElt],
 
     /// The position of the "dot" in this matcher
     idx: usize,
@@ -198,21 +231,11 @@ struct MatcherPos<'root, 'tt> {
     seq_op: Option<quoted::KleeneOp>,
 
     /// The separator if we are in a repetition.
-    sep: Option<Token>,
+    sep: Option<&'tt Token>,
 
     /// The "parent" matcher position if we are in a repetition. That is, the matcher position just
     /// before we enter the sequence.
     up: Option<MatcherPosHandle<'root, 'tt>>,
-
-    /// Specifically used to "unzip" token trees. By "unzip", we mean to unwrap the delimiters from
-    /// a delimited token tree (e.g., something wrapped in `(` `)`) or to get the contents of a doc
-    /// comment...
-    ///
-    /// When matching against matchers with nested delimited submatchers (e.g., `pat ( pat ( .. )
-    /// pat ) pat`), we need to keep track of the matchers we are descending into. This stack does
-    /// that where the bottom of the stack is the outermost matcher.
-    /// Also, throughout the comments, this "descent" is often referred to as "unzipping"...
-    stack: SmallVec<[MatcherTtFrame<'tt>; 1]>,
 }
 
 impl<'root, 'tt> MatcherPos<'root, 'tt> {
@@ -275,19 +298,66 @@ pub enum ParseResult<T> {
     Failure(Token, &'static str),
     /// Fatal error (malformed macro?). Abort compilation.
     Error(syntax_pos::Span, String),
+    /// Fatal error that has already been reported, e.g. by the parser of a non-terminal.
+    /// Abort compilation, or propagate the error in error-as-value mode.
+    ErrorReported,
 }
 
 /// A `ParseResult` where the `Success` variant contains a mapping of `Ident`s to `NamedMatch`es.
 /// This represents the mapping of metavars to the token trees they bind to.
 pub type NamedParseResult = ParseResult<FxHashMap<Ident, NamedMatch>>;
 
+/// The size of a matcher, to find out why matching the invocations of a macro is slow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MatcherMetrics {
+    /// The number of positions a `MatcherPos` can be at in the matcher: before each of its token
+    /// trees, before the end of each delimited tree and sequence, and at its end.
+    pub states: usize,
+    /// The number of metavars named in the matcher.
+    pub names: usize,
+    /// The deepest nesting of sequences.
+    pub max_sequence_depth: usize,
+}
+
+impl MatcherMetrics {
+    /// Computes the metrics of the matcher `ms`, without its outer delimiters.
+    pub fn of(ms: &[TokenTree]) -> MatcherMetrics {
+        // One position is at the end of the matcher.
+        let names = count_names(ms);
+        let mut metrics = MatcherMetrics { states: 1, names, max_sequence_depth: 0 };
+        metrics.add_states(ms, 0);
+        metrics
+    }
+
+    fn add_states(&mut self, ms: &[TokenTree], depth: usize) {
+        for tt in ms {
+            self.states += 1;
+            match *tt {
+                TokenTree::Delimited(_, ref delim) => {
+                    self.states += 1;
+                    self.add_states(&delim.tts, depth);
+                }
+                TokenTree::Sequence(_, ref seq) => {
+                    self.states += 1;
+                    self.max_sequence_depth = self.max_sequence_depth.max(depth + 1);
+                    self.add_states(&seq.tts, depth + 1);
+                }
+                TokenTree::Token(..)
+                | TokenTree::MetaVar(..)
+                | TokenTree::MetaVarDecl(..)
+                | TokenTree::MetaVarExpr(..) => {}
+            }
+        }
+    }
+}
+
 /// Count how many metavars are named in the given matcher `ms`.
 pub fn count_names(ms: &[TokenTree]) -> usize {
     ms.iter().fold(0, |count, elt| {
         count + match *elt {
             TokenTree::Sequence(_, ref seq) => seq.num_captures,
             TokenTree::Delimited(_, ref delim) => count_names(&delim.tts),
-            TokenTree::MetaVar(..) => 0,
+            TokenTree::MetaVar(..) | TokenTree::MetaVarExpr(..) => 0,
             TokenTree::MetaVarDecl(..) => 1,
             TokenTree::Token(..) => 0,
         }
@@ -306,12 +376,15 @@ fn create_matches(len: usize) -> Box<[Lrc<NamedMatchVec>]> {
 
 /// Generates the top-level matcher position in which the "dot" is before the first token of the
 /// matcher `ms` and we are going to start matching at the span `open` in the source.
-fn initial_matcher_pos<'root, 'tt>(ms: &'tt [TokenTree], open: Span) -> MatcherPos<'root, 'tt> {
-    let match_idx_hi = count_names(ms);
+fn initial_matcher_pos<'root, 'tt>(
+    ms: &'tt CompiledMatcher,
+    open: Span,
+) -> MatcherPos<'root, 'tt> {
+    let match_idx_hi = ms.metrics.names;
     let matches = create_matches(match_idx_hi);
     MatcherPos {
         // Start with the top level matcher given to us
-        top_elts: TtSeq(ms), // "elts" is an abbr. for "elements"
+        top_elts: &ms.elts, // "elts" is an abbr. for "elements"
         // The "dot" is before the first token of the matcher
         idx: 0,
         // We start matching at the span `open` in the source code
@@ -325,9 +398,6 @@ fn initial_matcher_pos<'root, 'tt>(ms: &'tt [TokenTree], open: Span) -> MatcherP
         match_cur: 0,
         match_hi: match_idx_hi,
 
-        // Haven't descended into any delimiters, so empty stack
-        stack: smallvec![],
-
         // Haven't descended into any sequences, so both of these are `None`.
         seq_op: None,
         sep: None,
@@ -396,7 +466,7 @@ fn nameize<I: Iterator<Item = NamedMatch>>(
                     }
                 }
             }
-            TokenTree::MetaVar(..) | TokenTree::Token(..) => (),
+            TokenTree::MetaVar(..) | TokenTree::MetaVarExpr(..) | TokenTree::Token(..) => (),
         }
 
         Ok(())
@@ -468,19 +538,6 @@ fn inner_parse_loop<'root, 'tt>(
 ) -> ParseResult<()> {
     // Pop items from `cur_items` until it is empty.
     while let Some(mut item) = cur_items.pop() {
-        // When unzipped trees end, remove them. This corresponds to backtracking out of a
-        // delimited submatcher into which we already descended. In backtracking out again, we need
-        // to advance the "dot" past the delimiters in the outer matcher.
-        while item.idx >= item.top_elts.len() {
-            match item.stack.pop() {
-                Some(MatcherTtFrame { elts, idx }) => {
-                    item.top_elts = elts;
-                    item.idx = idx + 1;
-                }
-                None => break,
-            }
-        }
-
         // Get the current position of the "dot" (`idx`) in `item` and the number of token trees in
         // the matcher (`len`).
         let idx = item.idx;
@@ -521,11 +578,7 @@ fn inner_parse_loop<'root, 'tt>(
                 if idx == len && item.sep.is_some() {
                     // We have a separator, and it is the current token. We can advance past the
                     // separator token.
-                    if item.sep
-                        .as_ref()
-                        .map(|sep| token_name_eq(token, sep))
-                        .unwrap_or(false)
-                    {
+                    if item.sep.map(|sep| token_name_eq(token, sep)).unwrap_or(false) {
                         item.idx += 1;
                         next_items.push(item);
                     }
@@ -548,15 +601,16 @@ fn inner_parse_loop<'root, 'tt>(
         else {
             // Look at what token in the matcher we are trying to match the current token (`token`)
             // against. Depending on that, we may generate new items.
-            match item.top_elts.get_tt(idx) {
+            let elts = item.top_elts;
+            match elts[idx] {
                 // Need to descend into a sequence
-                TokenTree::Sequence(sp, seq) => {
+                MatcherElt::Sequence(sp, ref seq) => {
                     // Examine the case where there are 0 matches of this sequence. We are
                     // implicitly disallowing OneOrMore from having 0 matches here. Thus, that will
                     // result in a "no rules expected token" error by virtue of this matcher not
                     // working.
-                    if seq.kleene.op == quoted::KleeneOp::ZeroOrMore
-                        || seq.kleene.op == quoted::KleeneOp::ZeroOrOne
+                    if seq.op == quoted::KleeneOp::ZeroOrMore
+                        || seq.op == quoted::KleeneOp::ZeroOrOne
                     {
                         let mut new_item = item.clone();
                         new_item.match_cur += seq.num_captures;
@@ -569,9 +623,8 @@ fn inner_parse_loop<'root, 'tt>(
 
                     let matches = create_matches(item.matches.len());
                     cur_items.push(MatcherPosHandle::Box(Box::new(MatcherPos {
-                        stack: smallvec![],
-                        sep: seq.separator.clone(),
-                        seq_op: Some(seq.kleene.op),
+                        sep: seq.separator.as_ref(),
+                        seq_op: Some(seq.op),
                         idx: 0,
                         matches,
                         match_lo: item.match_cur,
@@ -579,12 +632,12 @@ fn inner_parse_loop<'root, 'tt>(
                         match_hi: item.match_cur + seq.num_captures,
                         up: Some(item),
                         sp_open: sp.open,
-                        top_elts: Tt(TokenTree::Sequence(sp, seq)),
+                        top_elts: &seq.elts,
                     })));
                 }
 
                 // We need to match a metavar (but the identifier is invalid)... this is an error
-                TokenTree::MetaVarDecl(span, _, id) if id.name == kw::Invalid => {
+                MatcherElt::MetaVarDecl(span, _, id) if id.name == kw::Invalid => {
                     if sess.missing_fragment_specifiers.borrow_mut().remove(&span) {
                         return Error(span, "missing fragment specifier".to_string());
                     }
@@ -592,7 +645,7 @@ fn inner_parse_loop<'root, 'tt>(
 
                 // We need to match a metavar with a valid ident... call out to the black-box
                 // parser by adding an item to `bb_items`.
-                TokenTree::MetaVarDecl(_, _, id) => {
+                MatcherElt::MetaVarDecl(_, _, id) => {
                     // Built-in nonterminals never start with these tokens,
                     // so we can eliminate them from consideration.
                     if may_begin_with(token, id.name) {
@@ -600,26 +653,9 @@ fn inner_parse_loop<'root, 'tt>(
                     }
                 }
 
-                // We need to descend into a delimited submatcher or a doc comment. To do this, we
-                // push the current matcher onto a stack and push a new item containing the
-                // submatcher onto `cur_items`.
-                //
-                // At the beginning of the loop, if we reach the end of the delimited submatcher,
-                // we pop the stack to backtrack out of the descent.
-                seq @ TokenTree::Delimited(..) |
-                seq @ TokenTree::Token(Token { kind: DocComment(..), .. }) => {
-                    let lower_elts = mem::replace(&mut item.top_elts, Tt(seq));
-                    let idx = item.idx;
-                    item.stack.push(MatcherTtFrame {
-                        elts: lower_elts,
-                        idx,
-                    });
-                    item.idx = 0;
-                    cur_items.push(item);
-                }
-
-                // We just matched a normal token. We can just advance the parser.
-                TokenTree::Token(t) if token_name_eq(&t, token) => {
+                // We just matched a normal token, or a delimiter of a delimited submatcher. We can
+                // just advance the parser.
+                MatcherElt::Token(ref t) if token_name_eq(t, token) => {
                     item.idx += 1;
                     next_items.push(item);
                 }
@@ -628,7 +664,7 @@ fn inner_parse_loop<'root, 'tt>(
                 // rules. NOTE that this is not necessarily an error unless _all_ items in
                 // `cur_items` end up doing this. There may still be some other matchers that do
                 // end up working out.
-                TokenTree::Token(..) | TokenTree::MetaVar(..) => {}
+                MatcherElt::Token(..) | MatcherElt::Unmatchable => {}
             }
         }
     }
@@ -654,6 +690,18 @@ pub fn parse(
     ms: &[TokenTree],
     directory: Option<Directory<'_>>,
     recurse_into_modules: bool,
+) -> NamedParseResult {
+    parse_compiled(sess, tts, &CompiledMatcher::new(ms.to_vec()), directory, recurse_into_modules)
+}
+
+/// Like `parse`, with a matcher compiled by `CompiledMatcher::new`, which can then be matched
+/// against many invocations.
+pub fn parse_compiled(
+    sess: &ParseSess,
+    tts: TokenStream,
+    ms: &CompiledMatcher,
+    directory: Option<Directory<'_>>,
+    recurse_into_modules: bool,
 ) -> NamedParseResult {
     // Create a parser that can be used for the "black box" parts.
     let mut parser = Parser::new(
@@ -699,6 +747,7 @@ pub fn parse(
             Success(_) => {}
             Failure(token, msg) => return Failure(token, msg),
             Error(sp, msg) => return Error(sp, msg),
+            ErrorReported => return ErrorReported,
         }
 
         // inner parse loop handled all cur_items, so it's empty
@@ -716,7 +765,7 @@ pub fn parse(
                     .matches
                     .iter_mut()
                     .map(|dv| Lrc::make_mut(dv).pop().unwrap());
-                return nameize(sess, ms, matches);
+                return nameize(sess, &ms.tts, matches);
             } else if eof_items.len() > 1 {
                 return Error(
                     parser.token.span,
@@ -743,8 +792,8 @@ pub fn parse(
         if (!bb_items.is_empty() && !next_items.is_empty()) || bb_items.len() > 1 {
             let nts = bb_items
                 .iter()
-                .map(|item| match item.top_elts.get_tt(item.idx) {
-                    TokenTree::MetaVarDecl(_, bind, name) => format!("{} ('{}')", name, bind),
+                .map(|item| match item.top_elts[item.idx] {
+                    MatcherElt::MetaVarDecl(_, bind, name) => format!("{} ('{}')", name, bind),
                     _ => panic!(),
                 })
                 .collect::<Vec<String>>()
@@ -782,12 +831,16 @@ pub fn parse(
             assert_eq!(bb_items.len(), 1);
 
             let mut item = bb_items.pop().unwrap();
-            if let TokenTree::MetaVarDecl(span, _, ident) = item.top_elts.get_tt(item.idx) {
+            if let MatcherElt::MetaVarDecl(span, _, ident) = item.top_elts[item.idx] {
                 let match_cur = item.match_cur;
-                item.push_match(
-                    match_cur,
-                    MatchedNonterminal(Lrc::new(parse_nt(&mut parser, span, ident.name))),
-                );
+                let nt = match parse_nt(&mut parser, span, ident.name) {
+                    Ok(nt) => nt,
+                    Err(mut err) => {
+                        err.emit();
+                        return ErrorReported;
+                    }
+                };
+                item.push_match(match_cur, MatchedNonterminal(Lrc::new(nt)));
                 item.idx += 1;
                 item.match_cur += 1;
             } else {
@@ -898,33 +951,27 @@ fn may_begin_with(token: &Token, name: Name) -> bool {
 ///
 /// # Returns
 ///
-/// The parsed non-terminal.
-fn parse_nt(p: &mut Parser<'_>, sp: Span, name: Symbol) -> Nonterminal {
+/// The parsed non-terminal, or the error that aborts the matching.
+fn parse_nt<'a>(p: &mut Parser<'a>, sp: Span, name: Symbol) -> PResult<'a, Nonterminal> {
     if name == sym::tt {
-        return token::NtTT(p.parse_token_tree());
+        return Ok(token::NtTT(p.parse_token_tree()));
     }
     // check at the beginning and the parser checks after each bump
     p.process_potential_macro_variable();
-    match name {
-        sym::item => match panictry!(p.parse_item()) {
+    Ok(match name {
+        sym::item => match p.parse_item()? {
             Some(i) => token::NtItem(i),
-            None => {
-                p.fatal("expected an item keyword").emit();
-                FatalError.raise();
-            }
+            None => return Err(p.fatal("expected an item keyword")),
         },
-        sym::block => token::NtBlock(panictry!(p.parse_block())),
-        sym::stmt => match panictry!(p.parse_stmt()) {
+        sym::block => token::NtBlock(p.parse_block()?),
+        sym::stmt => match p.parse_stmt()? {
             Some(s) => token::NtStmt(s),
-            None => {
-                p.fatal("expected a statement").emit();
-                FatalError.raise();
-            }
+            None => return Err(p.fatal("expected a statement")),
         },
-        sym::pat => token::NtPat(panictry!(p.parse_pat(None))),
-        sym::expr => token::NtExpr(panictry!(p.parse_expr())),
-        sym::literal => token::NtLiteral(panictry!(p.parse_literal_maybe_minus())),
-        sym::ty => token::NtTy(panictry!(p.parse_ty())),
+        sym::pat => token::NtPat(p.parse_pat(None)?),
+        sym::expr => token::NtExpr(p.parse_expr()?),
+        sym::literal => token::NtLiteral(p.parse_literal_maybe_minus()?),
+        sym::ty => token::NtTy(p.parse_ty()?),
         // this could be handled like a token, since it is one
         sym::ident => if let Some((name, is_raw)) = get_macro_name(&p.token) {
             let span = p.token.span;
@@ -932,21 +979,19 @@ fn parse_nt(p: &mut Parser<'_>, sp: Span, name: Symbol) -> Nonterminal {
             token::NtIdent(Ident::new(name, span), is_raw)
         } else {
             let token_str = pprust::token_to_string(&p.token);
-            p.fatal(&format!("expected ident, found {}", &token_str)).emit();
-            FatalError.raise()
+            return Err(p.fatal(&format!("expected ident, found {}", &token_str)));
         }
-        sym::path => token::NtPath(panictry!(p.parse_path(PathStyle::Type))),
-        sym::meta => token::NtMeta(panictry!(p.parse_meta_item())),
-        sym::vis => token::NtVis(panictry!(p.parse_visibility(true))),
+        sym::path => token::NtPath(p.parse_path(PathStyle::Type)?),
+        sym::meta => token::NtMeta(p.parse_meta_item()?),
+        sym::vis => token::NtVis(p.parse_visibility(true)?),
         sym::lifetime => if p.check_lifetime() {
             token::NtLifetime(p.expect_lifetime().ident)
         } else {
             let token_str = pprust::token_to_string(&p.token);
-            p.fatal(&format!("expected a lifetime, found `{}`", &token_str)).emit();
-            FatalError.raise();
+            return Err(p.fatal(&format!("expected a lifetime, found `{}`", &token_str)));
         }
         // this is not supposed to happen, since it has been checked
         // when compiling the macro.
         _ => p.span_bug(sp, "invalid fragment specifier"),
-    }
+    })
 }
@@ -5,8 +5,9 @@ use crate::ext::base::{DummyResult, ExtCtxt, MacResult, TTMacroExpander};
 use crate::ext::base::{SyntaxExtension, SyntaxExtensionKind};
 use crate::ext::expand::{AstFragment, AstFragmentKind};
 use crate::ext::tt::macro_check;
-use crate::ext::tt::macro_parser::{parse, parse_failure_msg};
-use crate::ext::tt::macro_parser::{Error, Failure, Success};
+use crate::ext::tt::macro_separators;
+use crate::ext::tt::macro_parser::{parse, parse_failure_msg, CompiledMatcher, MatcherMetrics};
+use crate::ext::tt::macro_parser::{Error, ErrorReported, Failure, Success};
 use crate::ext::tt::macro_parser::{MatchedNonterminal, MatchedSeq};
 use crate::ext::tt::quoted;
 use crate::ext::tt::transcribe::transcribe;
@@ -15,10 +16,11 @@ use crate::parse::parser::Parser;
 use crate::parse::token::TokenKind::*;
 use crate::parse::token::{self, NtTT, Token};
 use crate::parse::{Directory, ParseSess};
+use crate::print::pprust;
 use crate::symbol::{kw, sym, Symbol};
 use crate::tokenstream::{DelimSpan, TokenStream, TokenTree};
 
-use errors::{DiagnosticBuilder, FatalError};
+use errors::DiagnosticBuilder;
 use log::debug;
 use syntax_pos::hygiene::Transparency;
 use syntax_pos::Span;
@@ -26,11 +28,15 @@ use syntax_pos::Span;
 use rustc_data_structures::fx::FxHashMap;
 use std::borrow::Cow;
 use std::collections::hash_map::Entry;
+use std::fmt;
 use std::slice;
 
 use errors::Applicability;
 use rustc_data_structures::sync::Lrc;
 
+#[cfg(test)]
+mod tests;
+
 const VALID_FRAGMENT_NAMES_MSG: &str = "valid fragment specifiers are \
                                         `ident`, `block`, `stmt`, `expr`, `pat`, `ty`, `lifetime`, \
                                         `literal`, `path`, `meta`, `tt`, `item` and `vis`";
@@ -60,7 +66,7 @@ pub fn annotate_err_with_kind(err: &mut DiagnosticBuilder<'_>, kind: AstFragment
 impl<'a> ParserAnyMacro<'a> {
     pub fn make(mut self: Box<ParserAnyMacro<'a>>, kind: AstFragmentKind) -> AstFragment {
         let ParserAnyMacro { site_span, macro_ident, ref mut parser, arm_span } = *self;
-        let fragment = panictry!(parser.parse_ast_fragment(kind, true).map_err(|mut e| {
+        let fragment = match parser.parse_ast_fragment(kind, true).map_err(|mut e| {
             if parser.token == token::Eof && e.message().ends_with(", found `<eof>`") {
                 if !e.span.is_dummy() {
                     // early end of macro arm (#52866)
@@ -111,7 +117,15 @@ impl<'a> ParserAnyMacro<'a> {
                 _ => annotate_err_with_kind(&mut e, kind, site_span),
             };
             e
-        }));
+        }) {
+            Ok(fragment) => fragment,
+            Err(mut e) => {
+                e.emit();
+                // In error-as-value mode, the error is propagated as a dummy fragment.
+                let _ = parser.sess.span_diagnostic.fatal_error();
+                return kind.dummy(site_span);
+            }
+        };
 
         // We allow semicolons at the end of expressions -- e.g., the semicolon in
         // `macro_rules! m { () => { panic!(); } }` isn't parsed by `.parse_expr()`,
@@ -131,7 +145,8 @@ struct MacroRulesMacroExpander {
     name: ast::Ident,
     span: Span,
     transparency: Transparency,
-    lhses: Vec<quoted::TokenTree>,
+    /// The matchers of the rules, compiled when the macro is defined.
+    matchers: Vec<CompiledMatcher>,
     rhses: Vec<quoted::TokenTree>,
     valid: bool,
 }
@@ -147,9 +162,117 @@ impl TTMacroExpander for MacroRulesMacroExpander {
             return DummyResult::any(sp);
         }
         generic_extension(
-            cx, sp, self.span, self.name, self.transparency, input, &self.lhses, &self.rhses
+            cx, sp, self.span, self.name, self.transparency, input, &self.matchers, &self.rhses
         )
     }
+
+    fn matcher_grammar(&self) -> Option<MacroGrammar> {
+        let rules =
+            self.matchers.iter().map(|matcher| MatcherPiece::from_tts(&matcher.tts)).collect();
+        Some(MacroGrammar { rules })
+    }
+
+    fn matcher_metrics(&self) -> Option<Vec<MatcherMetrics>> {
+        Some(self.matchers.iter().map(|matcher| matcher.metrics).collect())
+    }
+}
+
+/// The input a `macro_rules` macro accepts, as described by the matchers of its rules, for
+/// tools like completion engines and documentation generators.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MacroGrammar {
+    /// The matcher of each rule, without its outer delimiters, which don't constrain the ones
+    /// the macro is invoked with.
+    pub rules: Vec<Vec<MatcherPiece>>,
+}
+
+impl MacroGrammar {
+    /// Shows how the macro `name` can be invoked with each rule, like `vec![$($e:expr),*]`.
+    pub fn invocations(&self, name: ast::Ident, delim: token::DelimToken) -> Vec<String> {
+        self.rules.iter().map(|pieces| {
+            let piece = MatcherPiece::Delimited(delim, pieces.clone());
+            format!("{}!{}", name, piece)
+        }).collect()
+    }
+}
+
+/// A piece of a matcher.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MatcherPiece {
+    /// A token that must be passed as is.
+    Token(token::TokenKind),
+    /// Pieces between delimiters.
+    Delimited(token::DelimToken, Vec<MatcherPiece>),
+    /// Pieces repeated as in `$($e:expr),*`, with an optional separator between the
+    /// repetitions.
+    Repetition {
+        pieces: Vec<MatcherPiece>,
+        separator: Option<token::TokenKind>,
+        op: quoted::KleeneOp,
+    },
+    /// A fragment like `$e:expr`. Its kind is `kw::Invalid` if it's missing.
+    Fragment { name: Symbol, kind: Symbol },
+}
+
+impl MatcherPiece {
+    fn from_tts(tts: &[quoted::TokenTree]) -> Vec<MatcherPiece> {
+        tts.iter().map(|tt| match *tt {
+            quoted::TokenTree::Token(ref token) => MatcherPiece::Token(token.kind.clone()),
+            quoted::TokenTree::Delimited(_, ref delimited) => {
+                MatcherPiece::Delimited(delimited.delim, MatcherPiece::from_tts(&delimited.tts))
+            }
+            quoted::TokenTree::Sequence(_, ref seq) => MatcherPiece::Repetition {
+                pieces: MatcherPiece::from_tts(&seq.tts),
+                separator: seq.separator.as_ref().map(|sep| sep.kind.clone()),
+                op: seq.kleene.op,
+            },
+            quoted::TokenTree::MetaVarDecl(_, name, kind) => {
+                MatcherPiece::Fragment { name: name.name, kind: kind.name }
+            }
+            // Matchers only have fragments declaring their kind, if only to report it missing.
+            quoted::TokenTree::MetaVar(_, name) => {
+                MatcherPiece::Fragment { name: name.name, kind: kw::Invalid }
+            }
+            quoted::TokenTree::MetaVarExpr(..) => {
+                panic!("unexpected meta-variable expression in a matcher")
+            }
+        }).collect()
+    }
+}
+
+fn pieces_to_string(pieces: &[MatcherPiece]) -> String {
+    pieces.iter().map(|piece| piece.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+impl fmt::Display for MatcherPiece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            MatcherPiece::Token(ref kind) => f.write_str(&pprust::token_kind_to_string(kind)),
+            MatcherPiece::Delimited(delim, ref pieces) => {
+                let (open, close) = (token::OpenDelim(delim), token::CloseDelim(delim));
+                write!(
+                    f,
+                    "{}{}{}",
+                    pprust::token_kind_to_string(&open),
+                    pieces_to_string(pieces),
+                    pprust::token_kind_to_string(&close),
+                )
+            }
+            MatcherPiece::Repetition { ref pieces, ref separator, op } => {
+                write!(f, "$({})", pieces_to_string(pieces))?;
+                if let Some(ref separator) = *separator {
+                    f.write_str(&pprust::token_kind_to_string(separator))?;
+                }
+                f.write_str(match op {
+                    quoted::KleeneOp::ZeroOrMore => "*",
+                    quoted::KleeneOp::OneOrMore => "+",
+                    quoted::KleeneOp::ZeroOrOne => "?",
+                })
+            }
+            MatcherPiece::Fragment { name, kind } if kind == kw::Invalid => write!(f, "${}", name),
+            MatcherPiece::Fragment { name, kind } => write!(f, "${}:{}", name, kind),
+        }
+    }
 }
 
 fn trace_macros_note(cx: &mut ExtCtxt<'_>, sp: Span, message: String) {
@@ -157,7 +280,7 @@ fn trace_macros_note(cx: &mut ExtCtxt<'_>, sp: Span, message: String) {
     cx.expansions.entry(sp).or_default().push(message);
 }
 
-/// Given `lhses` and `rhses`, this is the new macro we create
+/// Given `matchers` and `rhses`, this is the new macro we create
 fn generic_extension<'cx>(
     cx: &'cx mut ExtCtxt<'_>,
     sp: Span,
@@ -165,7 +288,7 @@ fn generic_extension<'cx>(
     name: ast::Ident,
     transparency: Transparency,
     arg: TokenStream,
-    lhses: &[quoted::TokenTree],
+    matchers: &[CompiledMatcher],
     rhses: &[quoted::TokenTree],
 ) -> Box<dyn MacResult + 'cx> {
     if cx.trace_macros() {
@@ -175,14 +298,9 @@ fn generic_extension<'cx>(
     // Which arm's failure should we report? (the one furthest along)
     let mut best_failure: Option<(Token, &str)> = None;
 
-    for (i, lhs) in lhses.iter().enumerate() {
+    for (i, matcher) in matchers.iter().enumerate() {
         // try each arm's matchers
-        let lhs_tt = match *lhs {
-            quoted::TokenTree::Delimited(_, ref delim) => &delim.tts[..],
-            _ => cx.span_bug(sp, "malformed macro lhs"),
-        };
-
-        match TokenTree::parse(cx, lhs_tt, arg.clone()) {
+        match TokenTree::parse(cx, matcher, arg.clone()) {
             Success(named_matches) => {
                 let rhs = match rhses[i] {
                     // ignore delimiters
@@ -193,7 +311,16 @@ fn generic_extension<'cx>(
 
                 let rhs_spans = rhs.iter().map(|t| t.span()).collect::<Vec<_>>();
                 // rhs has holes ( `$id` and `$(...)` that need filled)
-                let mut tts = transcribe(cx, &named_matches, rhs, transparency);
+                let tts = transcribe(cx, &matcher.tts, &named_matches, rhs, transparency);
+                let mut tts = match tts {
+                    Ok(tts) => tts,
+                    Err(mut err) => {
+                        err.emit();
+                        // In error-as-value mode, the error is propagated as a dummy result.
+                        let _ = cx.parse_sess.span_diagnostic.fatal_error();
+                        return DummyResult::any(sp);
+                    }
+                };
 
                 // Replace all the tokens for the corresponding positions in the macro, to maintain
                 // proper positions in error reporting, while maintaining the macro_backtrace.
@@ -237,7 +364,17 @@ fn generic_extension<'cx>(
                 Some((ref best_token, _)) if best_token.span.lo() >= token.span.lo() => {}
                 _ => best_failure = Some((token, msg)),
             },
-            Error(err_sp, ref msg) => cx.span_fatal(err_sp.substitute_dummy(sp), &msg[..]),
+            Error(err_sp, ref msg) => {
+                cx.struct_span_fatal(err_sp.substitute_dummy(sp), &msg[..]).emit();
+                // In error-as-value mode, the error is propagated as a dummy result.
+                let _ = cx.parse_sess.span_diagnostic.fatal_error();
+                return DummyResult::any(sp);
+            }
+            ErrorReported => {
+                // In error-as-value mode, the error is propagated as a dummy result.
+                let _ = cx.parse_sess.span_diagnostic.fatal_error();
+                return DummyResult::any(sp);
+            }
         }
     }
 
@@ -251,13 +388,9 @@ fn generic_extension<'cx>(
 
     // Check whether there's a missing comma in this macro call, like `println!("{}" a);`
     if let Some((arg, comma_span)) = arg.add_comma() {
-        for lhs in lhses {
+        for matcher in matchers {
             // try each arm's matchers
-            let lhs_tt = match *lhs {
-                quoted::TokenTree::Delimited(_, ref delim) => &delim.tts[..],
-                _ => continue,
-            };
-            match TokenTree::parse(cx, lhs_tt, arg.clone()) {
+            match TokenTree::parse(cx, matcher, arg.clone()) {
                 Success(_) => {
                     if comma_span.is_dummy() {
                         err.note("you might be missing a comma");
@@ -270,6 +403,12 @@ fn generic_extension<'cx>(
                         );
                     }
                 }
+                ErrorReported => {
+                    err.cancel();
+                    // In error-as-value mode, the error is propagated as a dummy result.
+                    let _ = cx.parse_sess.span_diagnostic.fatal_error();
+                    return DummyResult::any(sp);
+                }
                 _ => {}
             }
         }
@@ -341,16 +480,26 @@ pub fn compile(
 
     let argument_map = match parse(sess, body.stream(), &argument_gram, None, true) {
         Success(m) => m,
-        Failure(token, msg) => {
-            let s = parse_failure_msg(&token);
-            let sp = token.span.substitute_dummy(def.span);
-            let mut err = sess.span_diagnostic.struct_span_fatal(sp, &s);
-            err.span_label(sp, msg);
-            err.emit();
-            FatalError.raise();
-        }
-        Error(sp, s) => {
-            sess.span_diagnostic.span_fatal(sp.substitute_dummy(def.span), &s).raise();
+        failure => {
+            match failure {
+                Failure(token, msg) => {
+                    let s = parse_failure_msg(&token);
+                    let sp = token.span.substitute_dummy(def.span);
+                    let mut err = sess.span_diagnostic.struct_span_fatal(sp, &s);
+                    err.span_label(sp, msg);
+                    err.emit();
+                }
+                Error(sp, s) => {
+                    sess.span_diagnostic.struct_span_fatal(sp.substitute_dummy(def.span), &s)
+                        .emit();
+                }
+                // Already reported.
+                ErrorReported => {}
+                Success(_) => unreachable!(),
+            }
+            // In error-as-value mode, the error is propagated as a macro expanding to nothing.
+            let _ = sess.span_diagnostic.fatal_error();
+            return SyntaxExtension::dummy_bang(edition);
         }
     };
 
@@ -421,6 +570,12 @@ pub fn compile(
     // We use CRATE_NODE_ID instead of `def.id` otherwise we may emit buffered lints for a node id
     // that is not lint-checked and trigger the "failed to process buffered lint here" bug.
     valid &= macro_check::check_meta_variables(sess, ast::CRATE_NODE_ID, def.span, &lhses, &rhses);
+    // Macros loaded from other crates were checked when those crates were compiled, and lints
+    // on them can't be fixed nor allowed here. The definitions of local macros are items that
+    // are lint-checked, so the lints are emitted at them and can be allowed there.
+    if !sess.source_map().lookup_char_pos(def.span.lo()).file.is_imported() {
+        macro_separators::check_separators(sess, def.id, &lhses);
+    }
 
     let (transparency, transparency_error) = attr::find_transparency(&def.attrs, body.legacy);
     match transparency_error {
@@ -431,8 +586,20 @@ pub fn compile(
         None => {}
     }
 
+    // Compile the matchers once here, rather than each time the macro is invoked. A malformed
+    // lhs makes the macro invalid, so its matcher is never matched against.
+    let matchers = lhses.into_iter().map(|lhs| match lhs {
+        quoted::TokenTree::Delimited(_, delim) => CompiledMatcher::new(delim.tts.clone()),
+        _ => CompiledMatcher::new(Vec::new()),
+    }).collect::<Vec<_>>();
+    debug!(
+        "the matchers of `{}` have the metrics {:?}",
+        def.ident,
+        matchers.iter().map(|matcher| matcher.metrics).collect::<Vec<_>>(),
+    );
+
     let expander: Box<_> = Box::new(MacroRulesMacroExpander {
-        name: def.ident, span: def.span, transparency, lhses, rhses, valid
+        name: def.ident, span: def.span, transparency, matchers, rhses, valid
     });
 
     SyntaxExtension::new(
@@ -471,7 +638,10 @@ fn check_lhs_no_empty_seq(sess: &ParseSess, tts: &[quoted::TokenTree]) -> bool {
     use quoted::TokenTree;
     for tt in tts {
         match *tt {
-            TokenTree::Token(..) | TokenTree::MetaVar(..) | TokenTree::MetaVarDecl(..) => (),
+            TokenTree::Token(..)
+            | TokenTree::MetaVar(..)
+            | TokenTree::MetaVarDecl(..)
+            | TokenTree::MetaVarExpr(..) => (),
             TokenTree::Delimited(_, ref del) => {
                 if !check_lhs_no_empty_seq(sess, &del.tts) {
                     return false;
@@ -560,7 +730,10 @@ impl FirstSets {
             let mut first = TokenSet::empty();
             for tt in tts.iter().rev() {
                 match *tt {
-                    TokenTree::Token(..) | TokenTree::MetaVar(..) | TokenTree::MetaVarDecl(..) => {
+                    TokenTree::Token(..)
+                    | TokenTree::MetaVar(..)
+                    | TokenTree::MetaVarDecl(..)
+                    | TokenTree::MetaVarExpr(..) => {
                         first.replace_with(tt.clone());
                     }
                     TokenTree::Delimited(span, ref delimited) => {
@@ -622,7 +795,10 @@ impl FirstSets {
         for tt in tts.iter() {
             assert!(first.maybe_empty);
             match *tt {
-                TokenTree::Token(..) | TokenTree::MetaVar(..) | TokenTree::MetaVarDecl(..) => {
+                TokenTree::Token(..)
+                | TokenTree::MetaVar(..)
+                | TokenTree::MetaVarDecl(..)
+                | TokenTree::MetaVarExpr(..) => {
                     first.add_one(tt.clone());
                     return first;
                 }
@@ -798,7 +974,10 @@ fn check_matcher_core(
         // First, update `last` so that it corresponds to the set
         // of NT tokens that might end the sequence `... token`.
         match *token {
-            TokenTree::Token(..) | TokenTree::MetaVar(..) | TokenTree::MetaVarDecl(..) => {
+            TokenTree::Token(..)
+            | TokenTree::MetaVar(..)
+            | TokenTree::MetaVarDecl(..)
+            | TokenTree::MetaVarExpr(..) => {
                 let can_be_followed_by_any;
                 if let Err(bad_frag) = has_legal_fragment_specifier(sess, features, attrs, token) {
                     let msg = format!("invalid fragment specifier `{}`", bad_frag);
@@ -936,6 +1115,7 @@ fn check_matcher_core(
                                     ));
                                 }
                             }
+                            suggest_follow_fixes(&mut err, token, may_be == "is", possible);
                             err.emit();
                         }
                     }
@@ -946,6 +1126,42 @@ fn check_matcher_core(
     last
 }
 
+/// Suggests fixes for the fragment `token` of a matcher being followed by a token it can't be
+/// followed by: separating it from what follows with `,` if that's allowed, which only helps if
+/// nothing else may follow it, or matching a single token tree instead.
+fn suggest_follow_fixes(
+    err: &mut DiagnosticBuilder<'_>,
+    token: &quoted::TokenTree,
+    directly_followed: bool,
+    possible: &[&str],
+) {
+    let (span, name, frag_spec) = match *token {
+        quoted::TokenTree::MetaVarDecl(span, name, frag_spec) => (span, name, frag_spec),
+        _ => return,
+    };
+    // The matcher may have been written by a procedural macro, which the user can't edit.
+    if span.from_expansion() || frag_spec.span.from_expansion() {
+        return;
+    }
+    if directly_followed && possible.contains(&"`,`") {
+        err.span_suggestion_hidden(
+            span.shrink_to_hi(),
+            &format!("try separating `${}:{}` from what follows with `,`", name, frag_spec),
+            ",".to_string(),
+            Applicability::MaybeIncorrect,
+        );
+    }
+    err.span_suggestion_hidden(
+        frag_spec.span,
+        &format!(
+            "if `${}` only ever matches a single token tree, try matching it with `${}:tt`",
+            name, name,
+        ),
+        "tt".to_string(),
+        Applicability::MaybeIncorrect,
+    );
+}
+
 fn token_can_be_followed_by_any(tok: &quoted::TokenTree) -> bool {
     if let quoted::TokenTree::MetaVarDecl(_, _, frag_spec) = *tok {
         frag_can_be_followed_by_any(frag_spec.name)
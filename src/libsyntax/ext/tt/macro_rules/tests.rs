@@ -0,0 +1,80 @@
+use super::*;
+
+use crate::ext::base::{SyntaxExtension, SyntaxExtensionKind};
+use crate::feature_gate::Features;
+use crate::source_map::FilePathMapping;
+use crate::tests::with_error_checking_parse;
+use crate::with_default_globals;
+
+fn compile_str(source: &str) -> SyntaxExtension {
+    let sess = ParseSess::new(FilePathMapping::empty());
+    let item = with_error_checking_parse(source.to_string(), &sess, |p| p.parse_item()).unwrap();
+    compile(&sess, &Features::new(), &item, Edition::Edition2018)
+}
+
+fn grammar(source: &str) -> MacroGrammar {
+    match compile_str(source).kind {
+        SyntaxExtensionKind::LegacyBang(ref expander) => expander.matcher_grammar().unwrap(),
+        _ => panic!("`macro_rules` compiled to something else than a bang macro"),
+    }
+}
+
+fn metrics(source: &str) -> Vec<MatcherMetrics> {
+    match compile_str(source).kind {
+        SyntaxExtensionKind::LegacyBang(ref expander) => expander.matcher_metrics().unwrap(),
+        _ => panic!("`macro_rules` compiled to something else than a bang macro"),
+    }
+}
+
+#[test]
+fn matcher_grammar() {
+    with_default_globals(|| {
+        let grammar = grammar("macro_rules! m {
+            () => {};
+            ($x:expr; [$($y:ident),+ $(;)?]) => {};
+        }");
+        let fragment = |name, kind| MatcherPiece::Fragment {
+            name: Symbol::intern(name),
+            kind: Symbol::intern(kind),
+        };
+        assert_eq!(grammar.rules, vec![
+            vec![],
+            vec![
+                fragment("x", "expr"),
+                MatcherPiece::Token(token::Semi),
+                MatcherPiece::Delimited(token::Bracket, vec![
+                    MatcherPiece::Repetition {
+                        pieces: vec![fragment("y", "ident")],
+                        separator: Some(token::Comma),
+                        op: quoted::KleeneOp::OneOrMore,
+                    },
+                    MatcherPiece::Repetition {
+                        pieces: vec![MatcherPiece::Token(token::Semi)],
+                        separator: None,
+                        op: quoted::KleeneOp::ZeroOrOne,
+                    },
+                ]),
+            ],
+        ]);
+        assert_eq!(
+            grammar.invocations(ast::Ident::from_str("m"), token::Paren),
+            vec!["m!()", "m!($x:expr ; [$($y:ident),+ $(;)?])"],
+        );
+    })
+}
+
+#[test]
+fn matcher_metrics() {
+    with_default_globals(|| {
+        let metrics = metrics("macro_rules! m {
+            () => {};
+            ($x:expr; [$($y:ident),+ $(;)?]) => {};
+            ($($($z:tt)*);*) => {};
+        }");
+        assert_eq!(metrics, vec![
+            MatcherMetrics { states: 1, names: 0, max_sequence_depth: 0 },
+            MatcherMetrics { states: 11, names: 2, max_sequence_depth: 1 },
+            MatcherMetrics { states: 6, names: 1, max_sequence_depth: 2 },
+        ]);
+    })
+}
@@ -0,0 +1,314 @@
+//! Checks the separators of the repetitions in the left-hand sides of a macro definition for
+//! common mistakes. These are reported through the `misused_macro_separators` lint.
+//!
+//! # What is checked
+//!
+//! ## The separator of a repetition must not also begin or end each repetition
+//!
+//! ```
+//! macro_rules! foo { ($($x:expr;);*) => {}; }
+//! ```
+//!
+//! The repetitions must then be separated by `;;`, which is hardly ever what was meant.
+//!
+//! ## Trailing separators must not be repeated one or more times
+//!
+//! ```
+//! macro_rules! foo { ($($x:expr),+ $(,)+) => {}; }
+//! ```
+//!
+//! This requires a trailing `,` and accepts any number of them, where `$(,)?` was meant.
+//!
+//! ## Rules repeating with `;` must not make later rules unreachable
+//!
+//! ```
+//! macro_rules! foo { ($($s:stmt);*) => {}; ($a:stmt; $b:stmt) => {}; }
+//! ```
+//!
+//! Since rules are tried in order, the second rule is never matched. This check is sound but not
+//! complete: a later rule is only reported when it is made of the same pieces as the earlier one,
+//! with fragments of the same kinds, or of repetitions of them.
+
+use crate::ast::NodeId;
+use crate::early_buffered_lints::{BufferedEarlyLintDiagnostics, BufferedEarlyLintId};
+use crate::ext::tt::quoted::{KleeneOp, SequenceRepetition, TokenTree};
+use crate::parse::token;
+use crate::parse::ParseSess;
+use crate::print::pprust;
+use crate::symbol::sym;
+
+use syntax_pos::{MultiSpan, Span};
+
+/// Checks the repetitions of `lhses`, the left-hand sides of a macro definition.
+///
+/// Arguments:
+/// - `sess` is used to emit lints
+/// - `node_id` is used to emit lints
+/// - `lhses` are the left-hand sides of the macro definition, in order
+pub fn check_separators(sess: &ParseSess, node_id: NodeId, lhses: &[TokenTree]) {
+    for lhs in lhses {
+        check_repetitions(sess, node_id, matcher(lhs));
+    }
+
+    let mut unreachable = vec![false; lhses.len()];
+    for (i, earlier) in lhses.iter().enumerate() {
+        let separated = match semi_separated_repetition(matcher(earlier)) {
+            Some(span) => span,
+            None => continue,
+        };
+        for (j, later) in lhses.iter().enumerate().skip(i + 1) {
+            if unreachable[j] || !matches_all_of(matcher(earlier), matcher(later), true) {
+                continue;
+            }
+            unreachable[j] = true;
+            let mut labels = vec![
+                (earlier.span(), "this rule matches every input of the later one".to_string()),
+            ];
+            // The `;` of the later rule is only matched by the repetition when the earlier rule
+            // would no longer match it without repeating with `;`.
+            if !matches_all_of(matcher(earlier), matcher(later), false) {
+                let label = "this repetition also matches the `;` of the later rule";
+                labels.push((separated, label.to_string()));
+            }
+            let notes = vec![
+                "the rules of a macro are tried in order, and the first one matching is used"
+                    .to_string(),
+            ];
+            buffer_lint(
+                sess,
+                later.span(),
+                node_id,
+                "this rule is unreachable because of a previous rule repeating with `;`",
+                BufferedEarlyLintDiagnostics::MacroSeparator(labels, notes, None),
+            );
+        }
+    }
+}
+
+/// Returns the token trees matched by `lhs`, without its delimiters.
+fn matcher(lhs: &TokenTree) -> &[TokenTree] {
+    match *lhs {
+        TokenTree::Delimited(_, ref delimited) => &delimited.tts,
+        // Left-hand sides without delimiters were reported before.
+        _ => &[],
+    }
+}
+
+/// Checks every repetition of `tts`, including the nested ones.
+fn check_repetitions(sess: &ParseSess, node_id: NodeId, tts: &[TokenTree]) {
+    for tt in tts {
+        match *tt {
+            TokenTree::Delimited(_, ref delimited) => {
+                check_repetitions(sess, node_id, &delimited.tts);
+            }
+            TokenTree::Sequence(span, ref seq) => {
+                let span = span.entire().to(seq.kleene.span);
+                check_repeated_separator(sess, node_id, span, seq);
+                check_trailing_separators(sess, node_id, span, seq);
+                check_repetitions(sess, node_id, &seq.tts);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Checks that the separator of `seq`, if any, doesn't also begin or end each of its repetitions.
+fn check_repeated_separator(
+    sess: &ParseSess,
+    node_id: NodeId,
+    span: Span,
+    seq: &SequenceRepetition,
+) {
+    let separator = match seq.separator {
+        Some(ref separator) => separator,
+        None => return,
+    };
+    let is_separator = |tt: Option<&TokenTree>| match tt {
+        Some(TokenTree::Token(token)) if token.kind == separator.kind => Some(token.span),
+        _ => None,
+    };
+    let (repeated, position) = match (is_separator(seq.tts.first()), is_separator(seq.tts.last())) {
+        (_, Some(repeated)) => (repeated, "ends"),
+        (Some(repeated), None) => (repeated, "begins"),
+        (None, None) => return,
+    };
+
+    let separator_str = pprust::token_to_string(separator);
+    let labels = vec![(repeated, format!("`{}` also {} each repetition", separator_str, position))];
+    let notes = vec![format!(
+        "consecutive repetitions must then be separated by `{0}{0}`",
+        separator_str,
+    )];
+    let suggestion = (separator.span, "remove the separator".to_string(), String::new());
+    buffer_lint(
+        sess,
+        span,
+        node_id,
+        &format!("the separator of this repetition also {} each repetition", position),
+        BufferedEarlyLintDiagnostics::MacroSeparator(labels, notes, Some(suggestion)),
+    );
+}
+
+/// Checks that `seq` isn't a repetition of a trailing separator, like `$(,)+`.
+fn check_trailing_separators(
+    sess: &ParseSess,
+    node_id: NodeId,
+    span: Span,
+    seq: &SequenceRepetition,
+) {
+    if seq.separator.is_some() || seq.kleene.op != KleeneOp::OneOrMore {
+        return;
+    }
+    let separator = match seq.tts[..] {
+        [TokenTree::Token(ref token)] if token.kind == token::Comma || token.kind == token::Semi =>
+            pprust::token_to_string(token),
+        _ => return,
+    };
+
+    let notes = vec![
+        format!("this requires a trailing `{}`", separator),
+        format!("this also matches several trailing `{0}`, like `{0}{0}`", separator),
+    ];
+    let suggestion = (
+        seq.kleene.span,
+        format!("make the trailing `{}` optional", separator),
+        "?".to_string(),
+    );
+    buffer_lint(
+        sess,
+        span,
+        node_id,
+        &format!("this repetition matches one or more trailing `{}`", separator),
+        BufferedEarlyLintDiagnostics::MacroSeparator(vec![], notes, Some(suggestion)),
+    );
+}
+
+/// Returns the span of the first repetition of `tts` separated by `;`, including the nested ones.
+fn semi_separated_repetition(tts: &[TokenTree]) -> Option<Span> {
+    tts.iter().find_map(|tt| match *tt {
+        TokenTree::Delimited(_, ref delimited) => semi_separated_repetition(&delimited.tts),
+        TokenTree::Sequence(span, ref seq) => match seq.separator {
+            Some(ref separator) if separator.kind == token::Semi => {
+                Some(span.entire().to(seq.kleene.span))
+            }
+            _ => semi_separated_repetition(&seq.tts),
+        },
+        _ => None,
+    })
+}
+
+/// Returns whether the matcher `a` matches every input the matcher `b` matches.
+///
+/// This only returns `true` when `b` is made of the same pieces as `a`, where the repetitions of
+/// `a` may either be matched against repetitions like them or be unrolled. The repetitions of `a`
+/// separated by `;` are only repeated more than once with `semi_repeats` set.
+fn matches_all_of(a: &[TokenTree], b: &[TokenTree], semi_repeats: bool) -> bool {
+    let (first, rest) = match a.split_first() {
+        Some(split) => split,
+        None => return b.is_empty(),
+    };
+    if let TokenTree::Sequence(_, ref seq) = *first {
+        if let Some(TokenTree::Sequence(_, other)) = b.first() {
+            if repetition_matches_all_of(seq, other, semi_repeats)
+                && matches_all_of(rest, &b[1..], semi_repeats)
+            {
+                return true;
+            }
+        }
+        return (seq.kleene.op != KleeneOp::OneOrMore && matches_all_of(rest, b, semi_repeats))
+            || repetitions_match_all_of(seq, rest, b, semi_repeats);
+    }
+    match b.split_first() {
+        Some((other, b_rest)) => {
+            tree_matches_all_of(first, other, semi_repeats)
+                && matches_all_of(rest, b_rest, semi_repeats)
+        }
+        None => false,
+    }
+}
+
+/// Returns whether one or more repetitions of `seq` followed by `rest` match every input `b`
+/// matches.
+fn repetitions_match_all_of(
+    seq: &SequenceRepetition,
+    rest: &[TokenTree],
+    b: &[TokenTree],
+    semi_repeats: bool,
+) -> bool {
+    (0..=b.len()).any(|end| {
+        if !matches_all_of(&seq.tts, &b[..end], semi_repeats) {
+            return false;
+        }
+        let b = &b[end..];
+        if matches_all_of(rest, b, semi_repeats) {
+            return true;
+        }
+        if seq.kleene.op == KleeneOp::ZeroOrOne {
+            return false;
+        }
+        match seq.separator {
+            Some(ref separator) if separator.kind == token::Semi && !semi_repeats => false,
+            Some(ref separator) => match b.split_first() {
+                Some((TokenTree::Token(token), b)) if token.kind == separator.kind => {
+                    repetitions_match_all_of(seq, rest, b, semi_repeats)
+                }
+                _ => false,
+            },
+            // Repetitions without separators must consume something to make progress.
+            None => end > 0 && repetitions_match_all_of(seq, rest, b, semi_repeats),
+        }
+    })
+}
+
+/// Returns whether the repetition `seq` matches every input the repetition `other` matches.
+fn repetition_matches_all_of(
+    seq: &SequenceRepetition,
+    other: &SequenceRepetition,
+    semi_repeats: bool,
+) -> bool {
+    let same_separator = match (&seq.separator, &other.separator) {
+        (Some(separator), Some(other_separator)) => separator.kind == other_separator.kind,
+        (None, None) => true,
+        _ => false,
+    };
+    // Matching several repetitions of `other` separated by `;` repeats `seq` with `;` as well.
+    let semi_matches = semi_repeats || other.kleene.op == KleeneOp::ZeroOrOne
+        || other.separator.as_ref().map_or(true, |separator| separator.kind != token::Semi);
+    let op_matches = seq.kleene.op == KleeneOp::ZeroOrMore || seq.kleene.op == other.kleene.op;
+    same_separator && semi_matches && op_matches
+        && matches_all_of(&seq.tts, &other.tts, semi_repeats)
+}
+
+/// Returns whether the token tree `a` of a matcher matches every input `b` matches.
+fn tree_matches_all_of(a: &TokenTree, b: &TokenTree, semi_repeats: bool) -> bool {
+    match (a, b) {
+        (TokenTree::Token(token), TokenTree::Token(other)) => token.kind == other.kind,
+        (TokenTree::Delimited(_, delimited), TokenTree::Delimited(_, other)) => {
+            delimited.delim == other.delim
+                && matches_all_of(&delimited.tts, &other.tts, semi_repeats)
+        }
+        (TokenTree::MetaVarDecl(_, _, kind), TokenTree::MetaVarDecl(_, _, other)) => {
+            kind.name == other.name
+        }
+        // A `tt` fragment matches any single token or delimited group.
+        (TokenTree::MetaVarDecl(_, _, kind), TokenTree::Token(..))
+        | (TokenTree::MetaVarDecl(_, _, kind), TokenTree::Delimited(..)) => kind.name == sym::tt,
+        _ => false,
+    }
+}
+
+fn buffer_lint(
+    sess: &ParseSess,
+    span: impl Into<MultiSpan>,
+    node_id: NodeId,
+    message: &str,
+    diagnostic: BufferedEarlyLintDiagnostics,
+) {
+    sess.buffer_lint_with_diagnostic(
+        BufferedEarlyLintId::MisusedMacroSeparators,
+        span,
+        node_id,
+        message,
+        diagnostic,
+    );
+}
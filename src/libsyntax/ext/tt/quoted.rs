@@ -1,11 +1,11 @@
 use crate::ast;
 use crate::ast::NodeId;
 use crate::ext::tt::macro_parser;
-use crate::feature_gate::Features;
+use crate::feature_gate::{emit_feature_err, Features, GateIssue};
 use crate::parse::token::{self, Token, TokenKind};
 use crate::parse::ParseSess;
 use crate::print::pprust;
-use crate::symbol::kw;
+use crate::symbol::{kw, sym};
 use crate::tokenstream::{self, DelimSpan};
 
 use syntax_pos::{edition::Edition, BytePos, Span};
@@ -79,6 +79,29 @@ pub enum KleeneOp {
     ZeroOrOne,
 }
 
+/// An expression like `${count(x)}` in the body of a macro, which transcribes to a number about
+/// the repetitions being transcribed.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum MetaVarExpr {
+    /// `${count(x)}`, the number of times `$x` repeats in total in the sequences it's still
+    /// repeating in. With `${count(x, depth)}`, only the repetitions of the sequences `depth`
+    /// sequences deep are counted, `0` being the outermost sequence still repeating.
+    Count(ast::Ident, Option<usize>),
+    /// `${index()}`, the index of the current repetition of the innermost sequence being
+    /// transcribed. With `${index(depth)}`, of the sequence `depth` sequences out of it.
+    Index(usize),
+}
+
+impl MetaVarExpr {
+    /// The meta-variable the expression is about, if any.
+    pub fn ident(&self) -> Option<ast::Ident> {
+        match *self {
+            MetaVarExpr::Count(ident, _) => Some(ident),
+            MetaVarExpr::Index(_) => None,
+        }
+    }
+}
+
 /// Similar to `tokenstream::TokenTree`, except that `$i`, `$i:ident`, and `$(...)`
 /// are "first-class" token trees. Useful for parsing macros.
 #[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
@@ -95,6 +118,8 @@ pub enum TokenTree {
         ast::Ident, /* name to bind */
         ast::Ident, /* kind of nonterminal */
     ),
+    /// e.g., `${count(var)}`. This is only used in the right hand side of MBE macros.
+    MetaVarExpr(DelimSpan, MetaVarExpr),
 }
 
 impl TokenTree {
@@ -165,7 +190,9 @@ impl TokenTree {
             TokenTree::Token(Token { span, .. })
             | TokenTree::MetaVar(span, _)
             | TokenTree::MetaVarDecl(span, _, _) => span,
-            TokenTree::Delimited(span, _) | TokenTree::Sequence(span, _) => span.entire(),
+            TokenTree::Delimited(span, _)
+            | TokenTree::Sequence(span, _)
+            | TokenTree::MetaVarExpr(span, _) => span.entire(),
         }
     }
 
@@ -281,6 +308,27 @@ fn parse_tree(
     match tree {
         // `tree` is a `$` token. Look at the next token in `trees`
         tokenstream::TokenTree::Token(Token { kind: token::Dollar, span }) => match trees.next() {
+            // `tree` is followed by a block in the body of the macro. This is a meta-variable
+            // expression (e.g. `${count(x)}`).
+            Some(tokenstream::TokenTree::Delimited(span, token::Brace, tts))
+                if !expect_matchers =>
+            {
+                let entire = span.entire();
+                if !features.macro_metavar_expr && !entire.allows_unstable(sym::macro_metavar_expr)
+                {
+                    let explain = "meta-variable expressions are unstable";
+                    emit_feature_err(sess, sym::macro_metavar_expr, entire, GateIssue::Language,
+                                     explain);
+                }
+                match parse_meta_var_expr(tts, entire) {
+                    Ok(expr) => TokenTree::MetaVarExpr(span, expr),
+                    Err((sp, msg)) => {
+                        sess.span_diagnostic.span_err(sp, &msg);
+                        TokenTree::MetaVar(entire, ast::Ident::invalid())
+                    }
+                }
+            }
+
             // `tree` is followed by a delimited set of token trees. This indicates the beginning
             // of a repetition sequence in the macro (e.g. `$(pat)*`).
             Some(tokenstream::TokenTree::Delimited(span, delim, tts)) => {
@@ -362,6 +410,91 @@ fn parse_tree(
     }
 }
 
+/// Parses the contents `tts` of the block of a meta-variable expression, at `span`.
+fn parse_meta_var_expr(
+    tts: tokenstream::TokenStream,
+    span: Span,
+) -> Result<MetaVarExpr, (Span, String)> {
+    let mut trees = tts.trees();
+    let (name, name_span) = match trees.next() {
+        Some(tokenstream::TokenTree::Token(token)) => match token.ident() {
+            Some((ident, false)) => (ident.name, token.span),
+            _ => return Err((token.span, "expected `count` or `index`".to_string())),
+        },
+        tree => {
+            let sp = tree.as_ref().map_or(span, tokenstream::TokenTree::span);
+            return Err((sp, "expected `count` or `index`".to_string()));
+        }
+    };
+    let args = match trees.next() {
+        Some(tokenstream::TokenTree::Delimited(_, token::Paren, args)) => args,
+        tree => {
+            let sp = tree.as_ref().map_or(name_span, tokenstream::TokenTree::span);
+            return Err((sp, format!("expected `(` after `{}`", name)));
+        }
+    };
+    if let Some(tree) = trees.next() {
+        return Err((tree.span(), "unexpected token after meta-variable expression".to_string()));
+    }
+
+    let mut args = args.trees();
+    let expr = match name {
+        sym::count => {
+            let ident = match args.next() {
+                Some(tokenstream::TokenTree::Token(token)) => match token.ident() {
+                    Some((ident, _)) => ident,
+                    None => return Err((token.span, "expected the name of a meta-variable, \
+                                                     without `$`".to_string())),
+                },
+                tree => {
+                    let sp = tree.as_ref().map_or(name_span, tokenstream::TokenTree::span);
+                    return Err((sp, "expected the name of a meta-variable".to_string()));
+                }
+            };
+            let depth = match args.next() {
+                Some(tokenstream::TokenTree::Token(Token { kind: token::Comma, span })) => {
+                    Some(parse_depth(args.next(), span)?)
+                }
+                Some(tree) => return Err((tree.span(), "expected `,` or `)`".to_string())),
+                None => None,
+            };
+            MetaVarExpr::Count(ident, depth)
+        }
+        sym::index => match args.next() {
+            Some(tree) => MetaVarExpr::Index(parse_depth(Some(tree), span)?),
+            None => MetaVarExpr::Index(0),
+        },
+        _ => {
+            let msg = format!(
+                "unrecognized meta-variable expression `{}`, expected `count` or `index`",
+                name,
+            );
+            return Err((name_span, msg));
+        }
+    };
+    match args.next() {
+        Some(tree) => Err((tree.span(), "expected `)`".to_string())),
+        None => Ok(expr),
+    }
+}
+
+/// Parses the depth argument of a meta-variable expression, which must be an unsuffixed integer,
+/// from `tree`, expected after `span`.
+fn parse_depth(tree: Option<tokenstream::TokenTree>, span: Span) -> Result<usize, (Span, String)> {
+    let msg = "expected a depth, like `0` or `1`".to_string();
+    match tree {
+        Some(tokenstream::TokenTree::Token(Token { kind: token::Literal(lit), span })) => {
+            match (lit.kind, lit.suffix) {
+                (token::Integer, None) => {
+                    lit.symbol.as_str().parse().map_err(|_| (span, msg))
+                }
+                _ => Err((span, msg)),
+            }
+        }
+        tree => Err((tree.as_ref().map_or(span, tokenstream::TokenTree::span), msg)),
+    }
+}
+
 /// Takes a token and returns `Some(KleeneOp)` if the token is `+` `*` or `?`. Otherwise, return
 /// `None`.
 fn kleene_op(token: &Token) -> Option<KleeneOp> {
@@ -1,18 +1,21 @@
 use crate::ast::{Ident, Mac};
 use crate::ext::base::ExtCtxt;
 use crate::ext::tt::macro_parser::{MatchedNonterminal, MatchedSeq, NamedMatch};
+use crate::ext::tt::macro_rules::MatcherPiece;
 use crate::ext::tt::quoted;
 use crate::mut_visit::{self, MutVisitor};
+use crate::parse::PResult;
 use crate::parse::token::{self, NtTT, Token};
+use crate::symbol::{kw, Symbol};
 use crate::tokenstream::{DelimSpan, TokenStream, TokenTree, TreeAndJoint};
 
 use smallvec::{smallvec, SmallVec};
 
-use errors::pluralise;
+use errors::{pluralise, Applicability, DiagnosticBuilder};
 use rustc_data_structures::fx::FxHashMap;
 use rustc_data_structures::sync::Lrc;
 use syntax_pos::hygiene::{ExpnId, Transparency};
-use syntax_pos::Span;
+use syntax_pos::{BytePos, Span};
 
 use std::mem;
 
@@ -86,16 +89,19 @@ impl Iterator for Frame {
 ///
 /// `transcribe` would return a `TokenStream` containing `println!("{}", stringify!(bar));`.
 ///
-/// Along the way, we do some additional error checking.
-pub(super) fn transcribe(
-    cx: &ExtCtxt<'_>,
+/// Along the way, we do some additional error checking, pointing into the `matcher` the
+/// meta-variables were bound by when they are used at the wrong depth. The errors are returned
+/// for the caller to emit.
+pub(super) fn transcribe<'a>(
+    cx: &ExtCtxt<'a>,
+    matcher: &[quoted::TokenTree],
     interp: &FxHashMap<Ident, NamedMatch>,
     src: Vec<quoted::TokenTree>,
     transparency: Transparency,
-) -> TokenStream {
+) -> PResult<'a, TokenStream> {
     // Nothing for us to transcribe...
     if src.is_empty() {
-        return TokenStream::empty();
+        return Ok(TokenStream::empty());
     }
 
     // We descend into the RHS (`src`), expanding things as we go. This stack contains the things
@@ -159,7 +165,7 @@ pub(super) fn transcribe(
                 Frame::Delimited { forest, span, .. } => {
                     if result_stack.is_empty() {
                         // No results left to compute! We are back at the top-level.
-                        return TokenStream::new(result);
+                        return Ok(TokenStream::new(result));
                     }
 
                     // Step back into the parent Delimited.
@@ -181,11 +187,16 @@ pub(super) fn transcribe(
             seq @ quoted::TokenTree::Sequence(..) => {
                 match lockstep_iter_size(&seq, interp, &repeats) {
                     LockstepIterSize::Unconstrained => {
-                        cx.span_fatal(
+                        let mut err = cx.struct_span_fatal(
                             seq.span(), /* blame macro writer */
                             "attempted to repeat an expression containing no syntax variables \
                              matched as repeating at this depth",
                         );
+                        if let quoted::TokenTree::Sequence(sp, ref seq) = seq {
+                            let depth = repeats.len() + 1;
+                            explain_unconstrained_sequence(cx, &mut err, matcher, sp, seq, depth);
+                        }
+                        return Err(err);
                     }
 
                     LockstepIterSize::Contradiction(ref msg) => {
@@ -193,7 +204,7 @@ pub(super) fn transcribe(
                         // happens when two meta-variables are used in the same repetition in a
                         // sequence, but they come from different sequence matchers and repeat
                         // different amounts.
-                        cx.span_fatal(seq.span(), &msg[..]);
+                        return Err(cx.struct_span_fatal(seq.span(), &msg[..]));
                     }
 
                     LockstepIterSize::Constraint(len, _) => {
@@ -211,7 +222,8 @@ pub(super) fn transcribe(
                                 // FIXME: this really ought to be caught at macro definition
                                 // time... It happens when the Kleene operator in the matcher and
                                 // the body for the same meta-variable do not match.
-                                cx.span_fatal(sp.entire(), "this must repeat at least once");
+                                let msg = "this must repeat at least once";
+                                return Err(cx.struct_span_fatal(sp.entire(), msg));
                             }
                         } else {
                             // 0 is the initial counter (we have done 0 repretitions so far). `len`
@@ -249,10 +261,12 @@ pub(super) fn transcribe(
                         }
                     } else {
                         // We were unable to descend far enough. This is an error.
-                        cx.span_fatal(
+                        let mut err = cx.struct_span_fatal(
                             sp, /* blame the macro writer */
                             &format!("variable '{}' is still repeating at this depth", ident),
                         );
+                        explain_repeating_meta_var(&mut err, matcher, sp, ident, repeats.len());
+                        return Err(err);
                     }
                 } else {
                     // If we aren't able to match the meta-var, we push it back into the result but
@@ -283,12 +297,225 @@ pub(super) fn transcribe(
                 result.push(tt.into());
             }
 
+            // Replace the meta-var expression with the number it evaluates to.
+            quoted::TokenTree::MetaVarExpr(sp, expr) => {
+                let mut sp = sp.entire();
+                let n = eval_meta_var_expr(cx, interp, &repeats, &expr, sp)?;
+                marker.visit_span(&mut sp);
+                let lit = token::Lit::new(token::Integer, Symbol::intern(&n.to_string()), None);
+                result.push(TokenTree::token(token::Literal(lit), sp).into());
+            }
+
             // There should be no meta-var declarations in the invocation of a macro.
             quoted::TokenTree::MetaVarDecl(..) => panic!("unexpected `TokenTree::MetaVarDecl"),
         }
     }
 }
 
+/// Finds the declaration of the meta-var `name` in `matcher`, returning its span. `seqs` is
+/// left with the sequences of the matcher it's nested in, from the outermost one.
+fn find_binder<'a>(
+    matcher: &'a [quoted::TokenTree],
+    name: Ident,
+    seqs: &mut Vec<&'a quoted::SequenceRepetition>,
+) -> Option<Span> {
+    for tt in matcher {
+        match *tt {
+            quoted::TokenTree::MetaVarDecl(span, decl, _) if decl == name => return Some(span),
+            quoted::TokenTree::Delimited(_, ref delimited) => {
+                if let Some(span) = find_binder(&delimited.tts, name, seqs) {
+                    return Some(span);
+                }
+            }
+            quoted::TokenTree::Sequence(_, ref seq) => {
+                seqs.push(seq);
+                if let Some(span) = find_binder(&seq.tts, name, seqs) {
+                    return Some(span);
+                }
+                seqs.pop();
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Explains why the meta-var `ident`, used at `sp` in a transcriber `depth` sequences deep,
+/// is still repeating there, and suggests repeating it in as many sequences as in the matcher.
+fn explain_repeating_meta_var(
+    err: &mut DiagnosticBuilder<'_>,
+    matcher: &[quoted::TokenTree],
+    sp: Span,
+    ident: Ident,
+    depth: usize,
+) {
+    let mut seqs = Vec::new();
+    let binder = match find_binder(matcher, ident, &mut seqs) {
+        Some(binder) if seqs.len() > depth => binder,
+        _ => return,
+    };
+    err.span_label(binder, format!("bound at repetition depth {}", seqs.len()));
+    err.note(&format!(
+        "`${}` is used at repetition depth {} in the transcriber, but bound at depth {} in the \
+         matcher",
+        ident,
+        depth,
+        seqs.len(),
+    ));
+    // Repeat it like in the sequences of the matcher it's deeper in, from the innermost one.
+    let mut repeated = MatcherPiece::Fragment { name: ident.name, kind: kw::Invalid };
+    for seq in seqs[depth..].iter().rev() {
+        repeated = MatcherPiece::Repetition {
+            pieces: vec![repeated],
+            separator: seq.separator.as_ref().map(|sep| sep.kind.clone()),
+            op: seq.kleene.op,
+        };
+    }
+    err.span_suggestion(
+        sp,
+        "repeat it as deep as it is bound",
+        repeated.to_string(),
+        Applicability::MaybeIncorrect,
+    );
+}
+
+/// Pushes the meta-vars used in `tts` to `vars`.
+fn collect_meta_vars(tts: &[quoted::TokenTree], vars: &mut Vec<Ident>) {
+    for tt in tts {
+        match *tt {
+            quoted::TokenTree::MetaVar(_, ident) => vars.push(ident),
+            quoted::TokenTree::Delimited(_, ref delimited) => {
+                collect_meta_vars(&delimited.tts, vars)
+            }
+            quoted::TokenTree::Sequence(_, ref seq) => collect_meta_vars(&seq.tts, vars),
+            quoted::TokenTree::Token(..)
+            | quoted::TokenTree::MetaVarDecl(..)
+            | quoted::TokenTree::MetaVarExpr(..) => {}
+        }
+    }
+}
+
+/// Points at where the meta-vars used in the sequence `seq` of a transcriber, at `sp` and
+/// `depth` sequences deep, are bound, none of them being bound as deep, and suggests removing
+/// the sequence.
+fn explain_unconstrained_sequence(
+    cx: &ExtCtxt<'_>,
+    err: &mut DiagnosticBuilder<'_>,
+    matcher: &[quoted::TokenTree],
+    sp: DelimSpan,
+    seq: &quoted::SequenceRepetition,
+    depth: usize,
+) {
+    err.span_label(sp.entire(), format!("this repetition is at depth {}", depth));
+    let mut vars = Vec::new();
+    collect_meta_vars(&seq.tts, &mut vars);
+    let mut explained = Vec::new();
+    for ident in vars {
+        if explained.contains(&ident) {
+            continue;
+        }
+        let mut seqs = Vec::new();
+        if let Some(binder) = find_binder(matcher, ident, &mut seqs) {
+            err.span_label(
+                binder,
+                format!("`${}` is bound at repetition depth {}", ident, seqs.len()),
+            );
+        }
+        explained.push(ident);
+    }
+
+    // The sequence is written `$(...)` followed by its separator and Kleene operator.
+    if sp.open.is_dummy() || sp.open.from_expansion() {
+        return;
+    }
+    let source_map = cx.source_map();
+    let whole = sp.open.with_lo(sp.open.lo() - BytePos(1)).to(seq.kleene.span);
+    if !source_map.span_to_snippet(whole).map_or(false, |s| s.starts_with("$(")) {
+        return;
+    }
+    if let Ok(inner) = source_map.span_to_snippet(sp.open.between(sp.close)) {
+        err.span_suggestion_hidden(
+            whole,
+            "remove this repetition of the transcriber",
+            inner.trim().to_string(),
+            Applicability::MaybeIncorrect,
+        );
+    }
+}
+
+/// Evaluates the meta-var expression `expr` at `sp`, with `repeats` the repetitions being
+/// transcribed.
+fn eval_meta_var_expr<'a>(
+    cx: &ExtCtxt<'a>,
+    interp: &FxHashMap<Ident, NamedMatch>,
+    repeats: &[(usize, usize)],
+    expr: &quoted::MetaVarExpr,
+    sp: Span,
+) -> PResult<'a, usize> {
+    match *expr {
+        quoted::MetaVarExpr::Count(ident, depth) => {
+            let matched = match lookup_cur_matched(ident, interp, repeats) {
+                Some(MatchedSeq(ref matched, _)) => matched,
+                Some(MatchedNonterminal(_)) => return Err(cx.struct_span_fatal(
+                    sp,
+                    &format!(
+                        "variable '{}' is not repeating at this depth, so it can't be counted",
+                        ident,
+                    ),
+                )),
+                None => {
+                    let msg = format!("unknown macro variable `{}`", ident);
+                    return Err(cx.struct_span_fatal(sp, &msg));
+                }
+            };
+            match count_repetitions(matched, depth) {
+                Some(count) => Ok(count),
+                None => Err(cx.struct_span_fatal(
+                    sp,
+                    &format!(
+                        "variable '{}' doesn't repeat {} sequences deeper than this depth",
+                        ident,
+                        depth.unwrap_or(0) + 1,
+                    ),
+                )),
+            }
+        }
+        quoted::MetaVarExpr::Index(depth) => {
+            if depth >= repeats.len() {
+                let msg = if repeats.is_empty() {
+                    "`index` can only be used in a repetition".to_string()
+                } else {
+                    let n = repeats.len();
+                    format!(
+                        "`index` is used {} repetition{} deep, so its depth must be less than {}",
+                        n,
+                        pluralise!(n),
+                        n,
+                    )
+                };
+                return Err(cx.struct_span_fatal(sp, &msg));
+            }
+            Ok(repeats[repeats.len() - 1 - depth].0)
+        }
+    }
+}
+
+/// Counts the repetitions in `matched`, or `None` if it doesn't repeat `depth` sequences deep.
+/// Without a depth, the repetitions of the innermost sequences are counted.
+fn count_repetitions(matched: &[NamedMatch], depth: Option<usize>) -> Option<usize> {
+    match depth {
+        Some(0) => Some(matched.len()),
+        Some(depth) => matched.iter().map(|matched| match *matched {
+            MatchedSeq(ref matched, _) => count_repetitions(matched, Some(depth - 1)),
+            MatchedNonterminal(_) => None,
+        }).sum(),
+        None => matched.iter().map(|matched| match *matched {
+            MatchedSeq(ref matched, _) => count_repetitions(matched, None),
+            MatchedNonterminal(_) => Some(1),
+        }).sum(),
+    }
+}
+
 /// Lookup the meta-var named `ident` and return the matched token tree from the invocation using
 /// the set of matches `interpolations`.
 ///
@@ -393,6 +620,7 @@ fn lockstep_iter_size(
                 _ => LockstepIterSize::Unconstrained,
             }
         }
-        TokenTree::Token(..) => LockstepIterSize::Unconstrained,
+        // Meta-var expressions are about the repetitions, so they don't repeat with them.
+        TokenTree::Token(..) | TokenTree::MetaVarExpr(..) => LockstepIterSize::Unconstrained,
     }
 }
@@ -522,6 +522,9 @@ declare_features! (
     /// Allows the use of or-patterns (e.g., `0 | 1`).
     (active, or_patterns, "1.38.0", Some(54883), None),
 
+    /// Allows meta-variable expressions like `${count(x)}` in the body of `macro_rules`.
+    (active, macro_metavar_expr, "1.38.0", None, None),
+
     // -------------------------------------------------------------------------
     // feature-group-end: actual feature gates
     // -------------------------------------------------------------------------
@@ -5,10 +5,9 @@ use super::builtin_attrs::{AttributeGate, BUILTIN_ATTRIBUTE_MAP};
 
 use crate::ast::{
     self, AssocTyConstraint, AssocTyConstraintKind, NodeId, GenericParam, GenericParamKind,
-    PatKind, RangeEnd,
+    PatKind,
 };
 use crate::attr::{self, check_builtin_attribute};
-use crate::source_map::Spanned;
 use crate::edition::{ALL_EDITIONS, Edition};
 use crate::visit::{self, FnKind, Visitor};
 use crate::parse::{token, ParseSess};
@@ -16,10 +15,11 @@ use crate::parse::parser::Parser;
 use crate::symbol::{Symbol, sym};
 use crate::tokenstream::TokenTree;
 
-use errors::{Applicability, DiagnosticBuilder, Handler};
+use errors::{Applicability, DiagnosticBuilder, FeatureGate, Handler};
 use rustc_data_structures::fx::FxHashMap;
 use rustc_target::spec::abi::Abi;
 use syntax_pos::{Span, DUMMY_SP, MultiSpan};
+use syntax_pos::hygiene::{ExpnData, ExpnKind, MacroKind};
 use log::debug;
 
 use std::env;
@@ -38,9 +38,13 @@ macro_rules! gate_feature_fn {
              name, explain, level) = (&*$cx, $has_feature, $span, $name, $explain, $level);
         let has_feature: bool = has_feature(&$cx.features);
         debug!("gate_feature(feature = {:?}, span = {:?}); has? {}", name, span, has_feature);
-        if !has_feature && !span.allows_unstable($name) {
-            leveled_feature_err(cx.parse_sess, name, span, GateIssue::Language, explain, level)
-                .emit();
+        if !span.allows_unstable($name) {
+            if has_feature {
+                record_detected_feature(cx.parse_sess, name, span);
+            } else {
+                leveled_feature_err(cx.parse_sess, name, span, GateIssue::Language, explain, level)
+                    .emit();
+            }
         }
     }}
 }
@@ -60,19 +64,38 @@ crate fn check_attribute(attr: &ast::Attribute, parse_sess: &ParseSess, features
     PostExpansionVisitor { parse_sess, features }.visit_attribute(attr)
 }
 
-fn find_lang_feature_issue(feature: Symbol) -> Option<u32> {
-    if let Some(info) = ACTIVE_FEATURES.iter().find(|t| t.name == feature) {
-        // FIXME (#28244): enforce that active features have issue numbers
-        // assert!(info.issue.is_some())
-        info.issue
-    } else {
-        // search in Accepted, Removed, or Stable Removed features
-        let found = ACCEPTED_FEATURES.iter().chain(REMOVED_FEATURES).chain(STABLE_REMOVED_FEATURES)
-            .find(|t| t.name == feature);
-        match found {
-            Some(&Feature { issue, .. }) => issue,
-            None => panic!("Feature `{}` is not declared anywhere", feature),
-        }
+fn find_lang_feature(feature: Symbol) -> &'static Feature {
+    ACTIVE_FEATURES.iter().chain(ACCEPTED_FEATURES).chain(REMOVED_FEATURES)
+        .chain(STABLE_REMOVED_FEATURES)
+        .find(|t| t.name == feature)
+        .unwrap_or_else(|| panic!("Feature `{}` is not declared anywhere", feature))
+}
+
+/// Describes `feature` for the diagnostics gating it.
+fn feature_gate_info(feature: Symbol, issue: &GateIssue) -> FeatureGate {
+    match *issue {
+        GateIssue::Language => {
+            let info = find_lang_feature(feature);
+            FeatureGate {
+                feature: feature.to_string(),
+                state: match info.state {
+                    FeatureState::Accepted => errors::FeatureState::Accepted,
+                    FeatureState::Active { .. } => errors::FeatureState::Active,
+                    FeatureState::Removed { .. } => errors::FeatureState::Removed,
+                    FeatureState::Stabilized { .. } => errors::FeatureState::Stabilized,
+                },
+                since: Some(info.since.to_string()),
+                // FIXME (#28244): enforce that active features have issue numbers
+                issue: info.issue,
+            }
+        }
+        // Library features are declared with `#[unstable]` and are always active.
+        GateIssue::Library(issue) => FeatureGate {
+            feature: feature.to_string(),
+            state: errors::FeatureState::Active,
+            since: None,
+            issue,
+        },
     }
 }
 
@@ -118,18 +141,35 @@ fn leveled_feature_err<'a, S: Into<MultiSpan>>(
     level: GateStrength,
 ) -> DiagnosticBuilder<'a> {
     let diag = &sess.span_diagnostic;
+    let span = span.into();
+    if let Some(primary_span) = span.primary_span() {
+        record_detected_feature(sess, feature, primary_span);
+    }
 
-    let issue = match issue {
-        GateIssue::Language => find_lang_feature_issue(feature),
-        GateIssue::Library(lib) => lib,
-    };
+    let feature_gate = feature_gate_info(feature, &issue);
+    let issue = feature_gate.issue;
 
-    let mut err = match level {
+    let struct_err = |span: MultiSpan, msg: &str| match level {
         GateStrength::Hard => {
-            diag.struct_span_err_with_code(span, explain, stringify_error_code!(E0658))
+            diag.struct_span_err_with_code(span, msg, stringify_error_code!(E0658))
         }
-        GateStrength::Soft => diag.struct_span_warn(span, explain),
+        GateStrength::Soft => diag.struct_span_warn(span, msg),
+    };
+    // The user can't change code produced by a macro from another crate, so point at the
+    // macro invocation and its definition instead.
+    let external_macro = span.primary_span().and_then(|sp| external_macro_expansion(sess, sp));
+    let mut err = match external_macro {
+        Some(expn_data) => {
+            let msg = format!("the macro `{}!` uses unstable feature `{}`",
+                              expn_data.kind.descr(), feature);
+            let mut err = struct_err(expn_data.call_site.into(), &msg);
+            err.span_label(sess.source_map().def_span(expn_data.def_site), "macro defined here");
+            err.note(explain);
+            err
+        }
+        None => struct_err(span, explain),
     };
+    err.set_feature_gate(feature_gate);
 
     match issue {
         None | Some(0) => {}  // We still accept `0` as a stand-in for backwards compatibility
@@ -157,6 +197,30 @@ fn leveled_feature_err<'a, S: Into<MultiSpan>>(
 
 }
 
+/// Returns the outermost expansion of a `macro_rules` macro from another crate that `span` is
+/// produced by, if there is one.
+fn external_macro_expansion(sess: &ParseSess, span: Span) -> Option<ExpnData> {
+    let is_external = |expn_data: &ExpnData| match expn_data.kind {
+        ExpnKind::Macro(MacroKind::Bang, _) => {
+            !expn_data.def_site.is_dummy() &&
+                sess.source_map().lookup_char_pos(expn_data.def_site.lo()).file.is_imported()
+        }
+        _ => false,
+    };
+
+    let mut expn_data = span.ctxt().outer_expn_data();
+    if !is_external(&expn_data) {
+        return None;
+    }
+    loop {
+        let caller = expn_data.call_site.ctxt().outer_expn_data();
+        if !is_external(&caller) {
+            return Some(expn_data);
+        }
+        expn_data = caller;
+    }
+}
+
 const EXPLAIN_BOX_SYNTAX: &str =
     "box expression syntax is experimental; you can call `Box::new` instead";
 
@@ -365,7 +429,7 @@ impl<'a> Visitor<'a> for PostExpansionVisitor<'a> {
                                         use marker types for now");
                 }
 
-                if let ast::Defaultness::Default = defaultness {
+                if defaultness.is_default() {
                     gate_feature_post!(&self, specialization,
                                        i.span,
                                        "specialization is unstable");
@@ -505,15 +569,6 @@ impl<'a> Visitor<'a> for PostExpansionVisitor<'a> {
                     }
                 }
             }
-            PatKind::Box(..) => {
-                gate_feature_post!(&self, box_patterns,
-                                  pattern.span,
-                                  "box pattern syntax is experimental");
-            }
-            PatKind::Range(_, _, Spanned { node: RangeEnd::Excluded, .. }) => {
-                gate_feature_post!(&self, exclusive_range_pattern, pattern.span,
-                                   "exclusive range pattern syntax is experimental");
-            }
             _ => {}
         }
         visit::walk_pat(self, pattern)
@@ -594,7 +649,7 @@ impl<'a> Visitor<'a> for PostExpansionVisitor<'a> {
     }
 
     fn visit_impl_item(&mut self, ii: &'a ast::ImplItem) {
-        if ii.defaultness == ast::Defaultness::Default {
+        if ii.defaultness.is_default() {
             gate_feature_post!(&self, specialization,
                               ii.span,
                               "specialization is unstable");
@@ -796,6 +851,23 @@ fn active_features_up_to(edition: Edition) -> impl Iterator<Item=&'static Featur
     })
 }
 
+/// Returns the unstable features found to be used by the crate so far, each with the first
+/// span using it, sorted by name. Uses are found by the feature gate checks for language
+/// features and by the stability checks for library features, whether the feature is enabled
+/// or not, so this is complete after `check_crate` and the later passes.
+pub fn detected_features(parse_sess: &ParseSess) -> Vec<(Symbol, Span)> {
+    let detected_features = parse_sess.detected_features.borrow();
+    let mut features = detected_features.iter().map(|(&feature, &span)| (feature, span))
+        .collect::<Vec<_>>();
+    features.sort_by(|a, b| a.0.as_str().cmp(&b.0.as_str()));
+    features
+}
+
+/// Records a use of `feature` at `span`, unless it was found to be used before.
+pub fn record_detected_feature(parse_sess: &ParseSess, feature: Symbol, span: Span) {
+    parse_sess.detected_features.borrow_mut().entry(feature).or_insert(span);
+}
+
 pub fn check_crate(krate: &ast::Crate,
                    parse_sess: &ParseSess,
                    features: &Features,
@@ -804,18 +876,19 @@ pub fn check_crate(krate: &ast::Crate,
     let mut visitor = PostExpansionVisitor { parse_sess, features };
 
     macro_rules! gate_all {
-        ($gate:ident, $msg:literal) => { gate_all!($gate, $gate, $msg); };
-        ($spans:ident, $gate:ident, $msg:literal) => {
-            for span in &*parse_sess.gated_spans.$spans.borrow() {
-                gate_feature!(&visitor, $gate, *span, $msg);
+        ($gate:ident, $msg:literal) => {
+            for span in parse_sess.gated_spans.spans(sym::$gate) {
+                gate_feature!(&visitor, $gate, span, $msg);
             }
         }
     }
 
     gate_all!(let_chains, "`let` expressions in this position are experimental");
     gate_all!(async_closure, "async closures are unstable");
-    gate_all!(yields, generators, "yield syntax is experimental");
+    gate_all!(generators, "yield syntax is experimental");
     gate_all!(or_patterns, "or-patterns syntax is experimental");
+    gate_all!(box_patterns, "box pattern syntax is experimental");
+    gate_all!(exclusive_range_pattern, "exclusive range pattern syntax is experimental");
 
     visit::walk_crate(&mut visitor, krate);
 }
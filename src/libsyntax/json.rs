@@ -1,36 +1,55 @@
 //! A JSON emitter for errors.
 //!
 //! This works by converting errors to a simplified structural format (see the
-//! structs at the start of the file) and then serializing them. These should
+//! structs in the middle of the file) and then serializing them. These should
 //! contain as much information about the error as possible.
 //!
-//! The format of the JSON output should be considered *unstable*. For now the
-//! structs at the end of this file (Diagnostic*) specify the error format.
-
-// FIXME: spec the JSON output properly.
+//! The `Diagnostic*` structs specify the format of the JSON output, and can be
+//! used to deserialize it. Top-level diagnostics carry the version of the format
+//! in their `$schema` field, see `SCHEMA`.
+//!
+//! The emitter can also emit diagnostics shaped like the `textDocument/publishDiagnostics`
+//! notifications of the Language Server Protocol, see `LspPublishDiagnostics`.
 
 use crate::source_map::{SourceMap, FilePathMapping};
 
 use errors::registry::Registry;
-use errors::{SubDiagnostic, CodeSuggestion, SourceMapper};
-use errors::{DiagnosticId, Applicability};
-use errors::emitter::{Emitter, HumanReadableErrorType};
+use errors::{SubDiagnostic, CodeSuggestion, SourceMapper, SourceMapperDyn};
+use errors::{DiagnosticId, Applicability, FeatureGate};
+use errors::emitter::{Emitter, HumanReadableErrorType, StoredDiagnostic};
 
-use syntax_pos::{MacroBacktrace, Span, SpanLabel, MultiSpan};
+use syntax_pos::{FileName, MacroBacktrace, Span, SpanLabel, MultiSpan};
 use rustc_data_structures::sync::{self, Lrc};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::env;
 use std::io::{self, Write};
+use std::mem;
 use std::path::Path;
 use std::vec;
 use std::sync::{Arc, Mutex};
 
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
 use rustc_serialize::json::{as_json, as_pretty_json};
 
+/// The version of the format of the JSON diagnostics, emitted as the `$schema` field of
+/// top-level diagnostics. It changes whenever a field is removed or changes its meaning,
+/// but not when fields are added.
+pub const SCHEMA: &str = "rustc-diagnostic-v1";
+
 pub struct JsonEmitter {
     dst: Box<dyn Write + Send>,
     registry: Option<Registry>,
     sm: Lrc<dyn SourceMapper + sync::Send + sync::Sync>,
     pretty: bool,
     ui_testing: bool,
+    compact: bool,
+    byte_columns: bool,
+    utf16_columns: bool,
+    lsp: bool,
+    session_summary: bool,
+    /// The LSP diagnostics of each file, published at the end of the session.
+    lsp_diagnostics: BTreeMap<String, Vec<LspDiagnostic>>,
     json_rendered: HumanReadableErrorType,
     external_macro_backtrace: bool,
 }
@@ -49,6 +68,12 @@ impl JsonEmitter {
             sm: source_map,
             pretty,
             ui_testing: false,
+            compact: false,
+            byte_columns: false,
+            utf16_columns: false,
+            lsp: false,
+            session_summary: false,
+            lsp_diagnostics: BTreeMap::new(),
             json_rendered,
             external_macro_backtrace,
         }
@@ -78,6 +103,12 @@ impl JsonEmitter {
             sm: source_map,
             pretty,
             ui_testing: false,
+            compact: false,
+            byte_columns: false,
+            utf16_columns: false,
+            lsp: false,
+            session_summary: false,
+            lsp_diagnostics: BTreeMap::new(),
             json_rendered,
             external_macro_backtrace,
         }
@@ -86,11 +117,54 @@ impl JsonEmitter {
     pub fn ui_testing(self, ui_testing: bool) -> Self {
         Self { ui_testing, ..self }
     }
+
+    /// Leaves out the `rendered` and `children` fields of diagnostics, for tools that only
+    /// need their spans and codes. This also saves rendering them.
+    pub fn compact(self, compact: bool) -> Self {
+        Self { compact, ..self }
+    }
+
+    /// Also emits the zero-based columns of spans in bytes, as `byte_column_start` and
+    /// `byte_column_end`.
+    pub fn byte_columns(self, byte_columns: bool) -> Self {
+        Self { byte_columns, ..self }
+    }
+
+    /// Also emits the zero-based columns of spans in UTF-16 code units, as
+    /// `utf16_column_start` and `utf16_column_end`. These are the columns the Language
+    /// Server Protocol uses.
+    pub fn utf16_columns(self, utf16_columns: bool) -> Self {
+        Self { utf16_columns, ..self }
+    }
+
+    /// Emits diagnostics as the parameters of Language Server Protocol
+    /// `textDocument/publishDiagnostics` notifications instead, with the positions editors
+    /// expect. Diagnostics without a span in a file on disk are left out. A notification
+    /// replaces the diagnostics of its file, so the diagnostics are held back until the end of
+    /// the session, see `Handler::flush_diagnostics`, and published with one notification per
+    /// file.
+    pub fn lsp(self, lsp: bool) -> Self {
+        Self { lsp, ..self }
+    }
+
+    /// Follows the diagnostics that end the session with a record of the session summary, see
+    /// `Handler::emit_session_summary`.
+    pub fn session_summary(self, session_summary: bool) -> Self {
+        Self { session_summary, ..self }
+    }
 }
 
 impl Emitter for JsonEmitter {
     fn emit_diagnostic(&mut self, db: &errors::Diagnostic) {
+        if self.lsp {
+            if let Some(data) = LspPublishDiagnostics::from_errors_diagnostic(db, self) {
+                self.lsp_diagnostics.entry(data.uri).or_default().extend(data.diagnostics);
+            }
+            return;
+        }
+
         let data = Diagnostic::from_errors_diagnostic(db, self);
+        let data = EncodeDiagnostic { diagnostic: &data, compact: self.compact };
         let result = if self.pretty {
             writeln!(&mut self.dst, "{}", as_pretty_json(&data))
         } else {
@@ -112,81 +186,360 @@ impl Emitter for JsonEmitter {
             panic!("failed to print notification: {:?}", e);
         }
     }
+
+    fn emit_progress(&mut self, progress: &errors::Progress) {
+        let data = ProgressNotification {
+            progress: &progress.activity,
+            done: progress.done,
+            total: progress.total,
+        };
+        let result = if self.pretty {
+            writeln!(&mut self.dst, "{}", as_pretty_json(&data))
+        } else {
+            writeln!(&mut self.dst, "{}", as_json(&data))
+        };
+        if let Err(e) = result {
+            panic!("failed to print notification: {:?}", e);
+        }
+    }
+
+    fn emit_session_summary(&mut self, summary: &errors::SessionSummary) {
+        for diagnostic in summary.diagnostics(self.should_show_explain()) {
+            self.emit_diagnostic(&diagnostic);
+        }
+        if !self.session_summary {
+            return;
+        }
+        let data = SessionSummaryNotification { summary };
+        let result = if self.pretty {
+            writeln!(&mut self.dst, "{}", as_pretty_json(&data))
+        } else {
+            writeln!(&mut self.dst, "{}", as_json(&data))
+        };
+        if let Err(e) = result {
+            panic!("failed to print notification: {:?}", e);
+        }
+    }
+
+    fn flush_diagnostics(&mut self) {
+        for (uri, diagnostics) in mem::take(&mut self.lsp_diagnostics) {
+            let data = LspPublishDiagnostics { uri, diagnostics };
+            let result = if self.pretty {
+                writeln!(&mut self.dst, "{}", as_pretty_json(&data))
+            } else {
+                writeln!(&mut self.dst, "{}", as_json(&data))
+            };
+            if let Err(e) = result {
+                panic!("failed to print diagnostics: {:?}", e);
+            }
+        }
+    }
 }
 
-// The following data types are provided just for serialisation.
+// The following data types specify the format of the JSON output.
 
-#[derive(RustcEncodable)]
-struct Diagnostic {
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// The version of the format, `SCHEMA`, serialized as `$schema`. Only top-level
+    /// diagnostics have it, their children don't.
+    pub schema: Option<String>,
     /// The primary error message.
-    message: String,
-    code: Option<DiagnosticCode>,
+    pub message: String,
+    pub code: Option<DiagnosticCode>,
     /// "error: internal compiler error", "error", "warning", "note", "help".
-    level: &'static str,
-    spans: Vec<DiagnosticSpan>,
-    /// Associated diagnostic messages.
-    children: Vec<Diagnostic>,
-    /// The message as rustc would render it.
-    rendered: Option<String>,
+    pub level: String,
+    pub spans: Vec<DiagnosticSpan>,
+    /// Associated diagnostic messages. Left out by compact emitters.
+    pub children: Vec<Diagnostic>,
+    /// The message as rustc would render it. Left out by compact emitters.
+    pub rendered: Option<String>,
+    /// The unstable feature of a feature gate diagnostic.
+    pub feature_gate: Option<FeatureGate>,
 }
 
-#[derive(RustcEncodable)]
-struct DiagnosticSpan {
-    file_name: String,
-    byte_start: u32,
-    byte_end: u32,
+impl Diagnostic {
+    // Implemented by hand to name the `$schema` field, and to leave out the fields that are
+    // only there for some diagnostics or that compact emitters don't emit.
+    fn encode_fields<S: Encoder>(&self, s: &mut S, compact: bool) -> Result<(), S::Error> {
+        let len = 4
+            + self.schema.is_some() as usize
+            + if compact { 0 } else { 2 }
+            + self.feature_gate.is_some() as usize;
+        s.emit_struct("Diagnostic", len, |s| {
+            let mut idx = 0..;
+            if let Some(ref schema) = self.schema {
+                s.emit_struct_field("$schema", idx.next().unwrap(), |s| schema.encode(s))?;
+            }
+            s.emit_struct_field("message", idx.next().unwrap(), |s| self.message.encode(s))?;
+            s.emit_struct_field("code", idx.next().unwrap(), |s| self.code.encode(s))?;
+            s.emit_struct_field("level", idx.next().unwrap(), |s| self.level.encode(s))?;
+            s.emit_struct_field("spans", idx.next().unwrap(), |s| self.spans.encode(s))?;
+            if !compact {
+                s.emit_struct_field("children", idx.next().unwrap(), |s| {
+                    self.children.encode(s)
+                })?;
+                s.emit_struct_field("rendered", idx.next().unwrap(), |s| {
+                    self.rendered.encode(s)
+                })?;
+            }
+            if let Some(ref feature_gate) = self.feature_gate {
+                s.emit_struct_field("feature_gate", idx.next().unwrap(), |s| {
+                    feature_gate.encode(s)
+                })?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Encodable for Diagnostic {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        self.encode_fields(s, false)
+    }
+}
+
+impl Decodable for Diagnostic {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Diagnostic, D::Error> {
+        d.read_struct("Diagnostic", 8, |d| {
+            Ok(Diagnostic {
+                schema: d.read_struct_field("$schema", 0, Decodable::decode)?,
+                message: d.read_struct_field("message", 1, Decodable::decode)?,
+                code: d.read_struct_field("code", 2, Decodable::decode)?,
+                level: d.read_struct_field("level", 3, Decodable::decode)?,
+                spans: d.read_struct_field("spans", 4, Decodable::decode)?,
+                // Missing from compact diagnostics.
+                children: d.read_struct_field("children", 5, |d| {
+                    d.read_option(|d, present| {
+                        if present { Decodable::decode(d) } else { Ok(vec![]) }
+                    })
+                })?,
+                rendered: d.read_struct_field("rendered", 6, Decodable::decode)?,
+                feature_gate: d.read_struct_field("feature_gate", 7, Decodable::decode)?,
+            })
+        })
+    }
+}
+
+/// Encodes a diagnostic the way the emitter is configured to.
+struct EncodeDiagnostic<'a> {
+    diagnostic: &'a Diagnostic,
+    compact: bool,
+}
+
+impl Encodable for EncodeDiagnostic<'_> {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        self.diagnostic.encode_fields(s, self.compact)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, RustcDecodable)]
+pub struct DiagnosticSpan {
+    pub file_name: String,
+    /// 0-based, byte offset in the file as it is on disk, that is before its BOM was removed
+    /// and its `\r\n` line endings were normalized.
+    pub byte_start: u32,
+    pub byte_end: u32,
     /// 1-based.
-    line_start: usize,
-    line_end: usize,
+    pub line_start: usize,
+    pub line_end: usize,
     /// 1-based, character offset.
-    column_start: usize,
-    column_end: usize,
+    pub column_start: usize,
+    pub column_end: usize,
+    /// 0-based, byte offset in the line. Only emitted if the emitter is configured to.
+    pub byte_column_start: Option<usize>,
+    pub byte_column_end: Option<usize>,
+    /// 0-based, UTF-16 code unit offset in the line. Only emitted if the emitter is
+    /// configured to.
+    pub utf16_column_start: Option<usize>,
+    pub utf16_column_end: Option<usize>,
     /// Is this a "primary" span -- meaning the point, or one of the points,
     /// where the error occurred?
-    is_primary: bool,
+    pub is_primary: bool,
     /// Source text from the start of line_start to the end of line_end.
-    text: Vec<DiagnosticSpanLine>,
+    pub text: Vec<DiagnosticSpanLine>,
     /// Label that should be placed at this location (if any)
-    label: Option<String>,
+    pub label: Option<String>,
     /// If we are suggesting a replacement, this will contain text
     /// that should be sliced in atop this span.
-    suggested_replacement: Option<String>,
+    pub suggested_replacement: Option<String>,
     /// If the suggestion is approximate
-    suggestion_applicability: Option<Applicability>,
+    pub suggestion_applicability: Option<Applicability>,
     /// Macro invocations that created the code at this span, if any.
-    expansion: Option<Box<DiagnosticSpanMacroExpansion>>,
+    pub expansion: Option<Box<DiagnosticSpanMacroExpansion>>,
 }
 
-#[derive(RustcEncodable)]
-struct DiagnosticSpanLine {
-    text: String,
+// Implemented by hand to leave out the columns the emitter isn't configured to emit.
+impl Encodable for DiagnosticSpan {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        let byte_columns = self.byte_column_start.is_some() && self.byte_column_end.is_some();
+        let utf16_columns = self.utf16_column_start.is_some() && self.utf16_column_end.is_some();
+        let len = 13 + if byte_columns { 2 } else { 0 } + if utf16_columns { 2 } else { 0 };
+        s.emit_struct("DiagnosticSpan", len, |s| {
+            let mut idx = 0..;
+            s.emit_struct_field("file_name", idx.next().unwrap(), |s| self.file_name.encode(s))?;
+            s.emit_struct_field("byte_start", idx.next().unwrap(), |s| self.byte_start.encode(s))?;
+            s.emit_struct_field("byte_end", idx.next().unwrap(), |s| self.byte_end.encode(s))?;
+            s.emit_struct_field("line_start", idx.next().unwrap(), |s| self.line_start.encode(s))?;
+            s.emit_struct_field("line_end", idx.next().unwrap(), |s| self.line_end.encode(s))?;
+            s.emit_struct_field("column_start", idx.next().unwrap(), |s| {
+                self.column_start.encode(s)
+            })?;
+            s.emit_struct_field("column_end", idx.next().unwrap(), |s| self.column_end.encode(s))?;
+            if byte_columns {
+                s.emit_struct_field("byte_column_start", idx.next().unwrap(), |s| {
+                    self.byte_column_start.encode(s)
+                })?;
+                s.emit_struct_field("byte_column_end", idx.next().unwrap(), |s| {
+                    self.byte_column_end.encode(s)
+                })?;
+            }
+            if utf16_columns {
+                s.emit_struct_field("utf16_column_start", idx.next().unwrap(), |s| {
+                    self.utf16_column_start.encode(s)
+                })?;
+                s.emit_struct_field("utf16_column_end", idx.next().unwrap(), |s| {
+                    self.utf16_column_end.encode(s)
+                })?;
+            }
+            s.emit_struct_field("is_primary", idx.next().unwrap(), |s| self.is_primary.encode(s))?;
+            s.emit_struct_field("text", idx.next().unwrap(), |s| self.text.encode(s))?;
+            s.emit_struct_field("label", idx.next().unwrap(), |s| self.label.encode(s))?;
+            s.emit_struct_field("suggested_replacement", idx.next().unwrap(), |s| {
+                self.suggested_replacement.encode(s)
+            })?;
+            s.emit_struct_field("suggestion_applicability", idx.next().unwrap(), |s| {
+                self.suggestion_applicability.encode(s)
+            })?;
+            s.emit_struct_field("expansion", idx.next().unwrap(), |s| self.expansion.encode(s))
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct DiagnosticSpanLine {
+    pub text: String,
 
     /// 1-based, character offset in self.text.
-    highlight_start: usize,
+    pub highlight_start: usize,
 
-    highlight_end: usize,
+    pub highlight_end: usize,
 }
 
-#[derive(RustcEncodable)]
-struct DiagnosticSpanMacroExpansion {
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct DiagnosticSpanMacroExpansion {
     /// span where macro was applied to generate this code; note that
     /// this may itself derive from a macro (if
     /// `span.expansion.is_some()`)
-    span: DiagnosticSpan,
+    pub span: DiagnosticSpan,
 
     /// name of macro that was applied (e.g., "foo!" or "#[derive(Eq)]")
-    macro_decl_name: String,
+    pub macro_decl_name: String,
+
+    /// span where macro was defined (if known); if the macro was itself
+    /// generated by a macro, its own expansion is in `def_site_span.expansion`
+    pub def_site_span: DiagnosticSpan,
 
-    /// span where macro was defined (if known)
-    def_site_span: DiagnosticSpan,
+    /// whether the macro was defined outside of the current crate
+    pub external: bool,
 }
 
-#[derive(RustcEncodable)]
-struct DiagnosticCode {
-    /// The code itself.
-    code: String,
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct DiagnosticCode {
+    /// The code itself, as `tool::name` for the codes of external tools.
+    pub code: String,
     /// An explanation for the code.
-    explanation: Option<&'static str>,
+    pub explanation: Option<String>,
+}
+
+// The following data types specify the format of the LSP output, a subset of the one of the
+// Language Server Protocol.
+
+/// The parameters of a `textDocument/publishDiagnostics` notification. The emitter sends one for
+/// each file the primary span of a diagnostic points into, with all of those diagnostics.
+#[derive(RustcEncodable)]
+struct LspPublishDiagnostics {
+    /// The `file` URI of the file, with an absolute path.
+    uri: String,
+    diagnostics: Vec<LspDiagnostic>,
+}
+
+struct LspDiagnostic {
+    range: LspRange,
+    /// 1 for errors, 2 for warnings, 3 for notes and 4 for help.
+    severity: u8,
+    code: Option<String>,
+    /// Links to the explanation of error codes, serialized as `codeDescription`.
+    code_description: Option<LspCodeDescription>,
+    /// Always `rustc`.
+    source: &'static str,
+    /// The primary message, followed by the messages of the children without spans.
+    message: String,
+    /// The labels of the spans, the children with spans and the suggestions, serialized as
+    /// `relatedInformation`.
+    related_information: Vec<LspRelatedInformation>,
+}
+
+// Implemented by hand to name the fields like the protocol does, and to leave out the optional
+// ones, which some clients don't accept as `null`.
+impl Encodable for LspDiagnostic {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        let len = 4
+            + self.code.is_some() as usize
+            + self.code_description.is_some() as usize
+            + !self.related_information.is_empty() as usize;
+        s.emit_struct("LspDiagnostic", len, |s| {
+            let mut idx = 0..;
+            s.emit_struct_field("range", idx.next().unwrap(), |s| self.range.encode(s))?;
+            s.emit_struct_field("severity", idx.next().unwrap(), |s| self.severity.encode(s))?;
+            if let Some(ref code) = self.code {
+                s.emit_struct_field("code", idx.next().unwrap(), |s| code.encode(s))?;
+            }
+            if let Some(ref code_description) = self.code_description {
+                s.emit_struct_field("codeDescription", idx.next().unwrap(), |s| {
+                    code_description.encode(s)
+                })?;
+            }
+            s.emit_struct_field("source", idx.next().unwrap(), |s| self.source.encode(s))?;
+            s.emit_struct_field("message", idx.next().unwrap(), |s| self.message.encode(s))?;
+            if !self.related_information.is_empty() {
+                s.emit_struct_field("relatedInformation", idx.next().unwrap(), |s| {
+                    self.related_information.encode(s)
+                })?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(RustcEncodable)]
+struct LspCodeDescription {
+    href: String,
+}
+
+#[derive(RustcEncodable)]
+struct LspRelatedInformation {
+    location: LspLocation,
+    message: String,
+}
+
+#[derive(RustcEncodable)]
+struct LspLocation {
+    uri: String,
+    range: LspRange,
+}
+
+#[derive(RustcEncodable)]
+struct LspRange {
+    start: LspPosition,
+    end: LspPosition,
+}
+
+#[derive(RustcEncodable)]
+struct LspPosition {
+    /// 0-based.
+    line: usize,
+    /// 0-based, UTF-16 code unit offset in the line.
+    character: usize,
 }
 
 #[derive(RustcEncodable)]
@@ -197,18 +550,50 @@ struct ArtifactNotification<'a> {
     emit: &'a str,
 }
 
+#[derive(RustcEncodable)]
+struct SessionSummaryNotification<'a> {
+    /// What the session emitted.
+    summary: &'a errors::SessionSummary,
+}
+
+#[derive(RustcEncodable)]
+struct ProgressNotification<'a> {
+    /// What is being done.
+    progress: &'a str,
+    /// The number of units of work done so far.
+    done: usize,
+    /// The total number of units of work, if known.
+    total: Option<usize>,
+}
+
 impl Diagnostic {
     fn from_errors_diagnostic(db: &errors::Diagnostic,
                                je: &JsonEmitter)
                                -> Diagnostic {
+        let mut diagnostic = Diagnostic {
+            schema: Some(SCHEMA.to_string()),
+            message: db.message(),
+            code: DiagnosticCode::map_opt_string(db.code.clone(), je),
+            level: db.level.to_str().to_string(),
+            spans: DiagnosticSpan::from_multispan(&db.span, je),
+            children: vec![],
+            rendered: None,
+            feature_gate: db.feature_gate.clone(),
+        };
+        if je.compact {
+            return diagnostic;
+        }
+
         let sugg = db.suggestions.iter().map(|sugg| {
             Diagnostic {
+                schema: None,
                 message: sugg.msg.clone(),
                 code: None,
-                level: "help",
+                level: "help".to_string(),
                 spans: DiagnosticSpan::from_suggestion(sugg, je),
                 children: vec![],
                 rendered: None,
+                feature_gate: None,
             }
         });
 
@@ -234,32 +619,73 @@ impl Diagnostic {
         let output = Arc::try_unwrap(output.0).unwrap().into_inner().unwrap();
         let output = String::from_utf8(output).unwrap();
 
-        Diagnostic {
-            message: db.message(),
-            code: DiagnosticCode::map_opt_string(db.code.clone(), je),
-            level: db.level.to_str(),
-            spans: DiagnosticSpan::from_multispan(&db.span, je),
-            children: db.children.iter().map(|c| {
-                Diagnostic::from_sub_diagnostic(c, je)
-            }).chain(sugg).collect(),
-            rendered: Some(output),
-        }
+        diagnostic.children = db.children.iter().map(|c| {
+            Diagnostic::from_sub_diagnostic(c, je)
+        }).chain(sugg).collect();
+        diagnostic.rendered = Some(output);
+        diagnostic
     }
 
     fn from_sub_diagnostic(db: &SubDiagnostic, je: &JsonEmitter) -> Diagnostic {
         Diagnostic {
+            schema: None,
             message: db.message(),
             code: None,
-            level: db.level.to_str(),
+            level: db.level.to_str().to_string(),
             spans: db.render_span.as_ref()
                      .map(|sp| DiagnosticSpan::from_multispan(sp, je))
                      .unwrap_or_else(|| DiagnosticSpan::from_multispan(&db.span, je)),
             children: vec![],
             rendered: None,
+            feature_gate: None,
+        }
+    }
+
+    /// Parses `level`, as `errors::Level::to_str` formats it.
+    fn parse_level(level: &str) -> errors::Level {
+        match level {
+            "error: internal compiler error" => errors::Level::Bug,
+            "error" => errors::Level::Error,
+            "warning" => errors::Level::Warning,
+            "help" => errors::Level::Help,
+            "failure-note" => errors::Level::FailureNote,
+            _ => errors::Level::Note,
         }
     }
 }
 
+// The spans are resolved by their lines and columns, which unlike their byte offsets don't
+// depend on the line endings of their files. Their macro backtraces are left out.
+impl StoredDiagnostic for Diagnostic {
+    fn to_diagnostic(&self, source_map: Option<&SourceMapperDyn>) -> Cow<'_, errors::Diagnostic> {
+        let level = Diagnostic::parse_level(&self.level);
+        let code = self.code.as_ref().map(DiagnosticCode::to_diagnostic_id);
+        let mut diagnostic = errors::Diagnostic::new_with_code(level, code, &self.message);
+        diagnostic.set_span(DiagnosticSpan::to_multispan(&self.spans, source_map));
+        diagnostic.feature_gate = self.feature_gate.clone();
+        for child in &self.children {
+            let replacements = child.spans.iter()
+                .filter_map(|span| Some((span, span.suggested_replacement.as_ref()?)))
+                .collect::<Vec<_>>();
+            if replacements.is_empty() {
+                let span = DiagnosticSpan::to_multispan(&child.spans, source_map);
+                diagnostic.sub(Diagnostic::parse_level(&child.level), &child.message, span, None);
+                continue;
+            }
+            // Without the spans of all its parts, a suggestion can't be shown.
+            let parts = replacements.iter()
+                .map(|&(span, replacement)| Some((span.resolve(source_map)?, replacement.clone())))
+                .collect::<Option<Vec<_>>>();
+            if let Some(parts) = parts {
+                let applicability = replacements[0].0.suggestion_applicability
+                    .unwrap_or(Applicability::Unspecified);
+                diagnostic.multipart_suggestion(&child.message, parts, applicability);
+            }
+        }
+        Cow::Owned(diagnostic)
+    }
+}
+
 impl DiagnosticSpan {
     fn from_span_label(span: SpanLabel,
                        suggestion: Option<(&String, Applicability)>,
@@ -278,12 +704,10 @@ impl DiagnosticSpan {
                      suggestion: Option<(&String, Applicability)>,
                      je: &JsonEmitter)
                      -> DiagnosticSpan {
-        // obtain the full backtrace from the `macro_backtrace`
-        // helper; in some ways, it'd be better to expand the
-        // backtrace ourselves, but the `macro_backtrace` helper makes
-        // some decision, such as dropping some frames, and I don't
-        // want to duplicate that logic here.
-        let backtrace = span.macro_backtrace().into_iter();
+        // obtain the full backtrace, including the recursive invocations
+        // `macro_backtrace` leaves out, so that tools can fold it the way
+        // they want to.
+        let backtrace = span.full_macro_backtrace().into_iter();
         DiagnosticSpan::from_span_full(span,
                                        is_primary,
                                        label,
@@ -301,6 +725,16 @@ impl DiagnosticSpan {
                       -> DiagnosticSpan {
         let start = je.sm.lookup_char_pos(span.lo());
         let end = je.sm.lookup_char_pos(span.hi());
+        let byte_columns = if je.byte_columns {
+            Some((je.sm.lookup_byte_column(span.lo()), je.sm.lookup_byte_column(span.hi())))
+        } else {
+            None
+        };
+        let utf16_columns = if je.utf16_columns {
+            Some((je.sm.lookup_utf16_column(span.lo()), je.sm.lookup_utf16_column(span.hi())))
+        } else {
+            None
+        };
         let backtrace_step = backtrace.next().map(|bt| {
             let call_site =
                 Self::from_span_full(bt.call_site,
@@ -309,31 +743,43 @@ impl DiagnosticSpan {
                                      None,
                                      backtrace,
                                      je);
+            let external = bt.def_site_span.is_dummy() || {
+                let file = je.sm.lookup_char_pos(bt.def_site_span.lo()).file;
+                file.is_imported() || file.name.is_macros()
+            };
             let def_site_span =
-                Self::from_span_full(bt.def_site_span,
-                                     false,
-                                     None,
-                                     None,
-                                     vec![].into_iter(),
-                                     je);
+                Self::from_span_etc(bt.def_site_span,
+                                    false,
+                                    None,
+                                    None,
+                                    je);
             Box::new(DiagnosticSpanMacroExpansion {
                 span: call_site,
                 macro_decl_name: bt.macro_decl_name,
                 def_site_span,
+                external,
             })
         });
 
         DiagnosticSpan {
             file_name: start.file.name.to_string(),
-            byte_start: span.lo().0 - start.file.start_pos.0,
-            byte_end: span.hi().0 - start.file.start_pos.0,
+            byte_start: start.file.original_relative_byte_pos(span.lo()).0,
+            byte_end: start.file.original_relative_byte_pos(span.hi()).0,
             line_start: start.line,
             line_end: end.line,
             column_start: start.col.0 + 1,
             column_end: end.col.0 + 1,
+            byte_column_start: byte_columns.map(|(start, _)| start),
+            byte_column_end: byte_columns.map(|(_, end)| end),
+            utf16_column_start: utf16_columns.map(|(start, _)| start),
+            utf16_column_end: utf16_columns.map(|(_, end)| end),
             is_primary,
             text: DiagnosticSpanLine::from_span(span, je),
-            suggested_replacement: suggestion.map(|x| x.0.clone()),
+            // Tools apply the replacement to the file, so it's indented like the file and ends its
+            // lines like the file.
+            suggested_replacement: suggestion.map(|x| {
+                start.file.restore_line_endings(span.lo(), &start.file.reindent(span.lo(), x.0))
+            }),
             suggestion_applicability: suggestion.map(|x| x.1),
             expansion: backtrace_step,
             label,
@@ -357,6 +803,7 @@ impl DiagnosticSpan {
                                   span: suggestion_inner.span,
                                   is_primary: true,
                                   label: None,
+                                  role: None,
                               };
                               DiagnosticSpan::from_span_label(span_label,
                                                               Some((&suggestion_inner.snippet,
@@ -366,6 +813,142 @@ impl DiagnosticSpan {
                       })
                       .collect()
     }
+
+    fn resolve(&self, source_map: Option<&SourceMapperDyn>) -> Option<Span> {
+        let start = (self.line_start, self.column_start);
+        let end = (self.line_end, self.column_end);
+        source_map?.span_from_line_columns(&self.file_name, start, end)
+    }
+
+    /// Returns the spans of `spans` that can be resolved in `source_map`, with their labels.
+    fn to_multispan(spans: &[DiagnosticSpan], source_map: Option<&SourceMapperDyn>) -> MultiSpan {
+        let resolved = spans.iter()
+            .filter_map(|span| Some((span, span.resolve(source_map)?)))
+            .collect::<Vec<_>>();
+        let mut multispan = MultiSpan::from_spans(resolved.iter()
+            .filter(|(span, _)| span.is_primary)
+            .map(|&(_, sp)| sp)
+            .collect());
+        for (span, sp) in resolved {
+            if let Some(ref label) = span.label {
+                multispan.push_span_label(sp, label.clone());
+            }
+        }
+        multispan
+    }
+}
+
+impl LspPublishDiagnostics {
+    /// Returns `None` for diagnostics whose primary span isn't in a file on disk, even once
+    /// taken out of macros, since notifications must be about a file.
+    fn from_errors_diagnostic(db: &errors::Diagnostic, je: &JsonEmitter) -> Option<Self> {
+        let primary_span = db.span.primary_span()?;
+        let location = LspLocation::from_span(primary_span, je)
+            .or_else(|| LspLocation::from_span(primary_span.source_callsite(), je))?;
+
+        let mut message = db.message();
+        let mut related_information = vec![];
+        for span_label in db.span.span_labels() {
+            if let Some(label) = span_label.label {
+                if let Some(location) = LspLocation::from_span(span_label.span, je) {
+                    related_information.push(LspRelatedInformation { location, message: label });
+                }
+            }
+        }
+        for child in &db.children {
+            let child_message = format!("{}: {}", child.level.to_str(), child.message());
+            let span = child.render_span.as_ref().unwrap_or(&child.span);
+            let locations = span.primary_spans().iter()
+                .filter_map(|&span| LspLocation::from_span(span, je))
+                .collect::<Vec<_>>();
+            if locations.is_empty() {
+                message.push('\n');
+                message.push_str(&child_message);
+            }
+            for location in locations {
+                let message = child_message.clone();
+                related_information.push(LspRelatedInformation { location, message });
+            }
+        }
+        for suggestion in &db.suggestions {
+            for part in suggestion.substitutions.iter().flat_map(|sub| sub.parts.iter()) {
+                if let Some(location) = LspLocation::from_span(part.span, je) {
+                    let message = format!("help: {}: `{}`", suggestion.msg, part.snippet);
+                    related_information.push(LspRelatedInformation { location, message });
+                }
+            }
+        }
+
+        let severity = match db.level {
+            errors::Level::Bug | errors::Level::Fatal | errors::Level::Error => 1,
+            errors::Level::Warning => 2,
+            errors::Level::Note | errors::Level::FailureNote | errors::Level::Cancelled => 3,
+            errors::Level::Help => 4,
+        };
+        let code_description = match db.code {
+            Some(DiagnosticId::Error(ref code)) => Some(LspCodeDescription {
+                href: format!("https://doc.rust-lang.org/error-index.html#{}", code),
+            }),
+            _ => None,
+        };
+        let diagnostic = LspDiagnostic {
+            range: location.range,
+            severity,
+            code: db.code.as_ref().map(|code| code.to_string()),
+            code_description,
+            source: "rustc",
+            message,
+            related_information,
+        };
+        Some(LspPublishDiagnostics { uri: location.uri, diagnostics: vec![diagnostic] })
+    }
+}
+
+impl LspLocation {
+    /// Returns `None` for spans that aren't in a file on disk.
+    fn from_span(span: Span, je: &JsonEmitter) -> Option<LspLocation> {
+        if span.is_dummy() {
+            return None;
+        }
+        let start = je.sm.lookup_char_pos(span.lo());
+        let end = je.sm.lookup_char_pos(span.hi());
+        let uri = match start.file.name {
+            FileName::Real(ref path) => file_uri(path),
+            _ => return None,
+        };
+        let range = LspRange {
+            start: LspPosition {
+                line: start.line - 1,
+                character: je.sm.lookup_utf16_column(span.lo()),
+            },
+            end: LspPosition {
+                line: end.line - 1,
+                character: je.sm.lookup_utf16_column(span.hi()),
+            },
+        };
+        Some(LspLocation { uri, range })
+    }
+}
+
+/// Returns the `file` URI of `path`, which is made absolute from the current directory.
+fn file_uri(path: &Path) -> String {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().map(|dir| dir.join(path)).unwrap_or_else(|_| path.to_path_buf())
+    };
+    // Windows paths are written with forward slashes, after an additional one.
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from(if path.starts_with('/') { "file://" } else { "file:///" });
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
 }
 
 impl DiagnosticSpanLine {
@@ -401,18 +984,30 @@ impl DiagnosticSpanLine {
 }
 
 impl DiagnosticCode {
+    /// Returns the code as a `DiagnosticId`. Codes that aren't error codes are taken for the names
+    /// of lints, or for those of the diagnostics of external tools if they're namespaced.
+    fn to_diagnostic_id(&self) -> DiagnosticId {
+        let code = &self.code;
+        let is_error_code = code.len() == 5 && code.starts_with('E')
+            && code[1..].bytes().all(|b| b.is_ascii_digit());
+        if is_error_code {
+            DiagnosticId::Error(code.clone())
+        } else if let Some(separator) = code.find("::") {
+            DiagnosticId::tool(&code[..separator], &code[separator + 2..])
+        } else {
+            DiagnosticId::Lint(code.clone())
+        }
+    }
+
     fn map_opt_string(s: Option<DiagnosticId>, je: &JsonEmitter) -> Option<DiagnosticCode> {
         s.map(|s| {
-            let s = match s {
-                DiagnosticId::Error(s) => s,
-                DiagnosticId::Lint(s) => s,
-            };
             let explanation = je.registry
                                 .as_ref()
-                                .and_then(|registry| registry.find_description(&s));
+                                .and_then(|registry| registry.describe(&s))
+                                .map(|explanation| explanation.to_string());
 
             DiagnosticCode {
-                code: s,
+                code: s.to_string(),
                 explanation,
             }
         })
@@ -26,8 +26,7 @@
 extern crate proc_macro;
 
 pub use errors;
-use rustc_data_structures::sync::Lock;
-use rustc_data_structures::bit_set::GrowableBitSet;
+use rustc_data_structures::bit_set::AtomicBitSet;
 pub use rustc_data_structures::thin_vec::ThinVec;
 use ast::AttrId;
 use syntax_pos::edition::Edition;
@@ -84,18 +83,18 @@ macro_rules! unwrap_or {
 }
 
 pub struct Globals {
-    used_attrs: Lock<GrowableBitSet<AttrId>>,
-    known_attrs: Lock<GrowableBitSet<AttrId>>,
+    used_attrs: AtomicBitSet<AttrId>,
+    known_attrs: AtomicBitSet<AttrId>,
     syntax_pos_globals: syntax_pos::Globals,
 }
 
 impl Globals {
     fn new(edition: Edition) -> Globals {
         Globals {
-            // We have no idea how many attributes their will be, so just
-            // initiate the vectors with 0 bits. We'll grow them as necessary.
-            used_attrs: Lock::new(GrowableBitSet::new_empty()),
-            known_attrs: Lock::new(GrowableBitSet::new_empty()),
+            // These are checked for nearly every attribute, so they are
+            // updated without locking.
+            used_attrs: AtomicBitSet::new_empty(),
+            known_attrs: AtomicBitSet::new_empty(),
             syntax_pos_globals: syntax_pos::Globals::new(edition),
         }
     }
@@ -130,7 +129,12 @@ pub mod util {
     pub mod lev_distance;
     pub mod node_count;
     pub mod parser;
+    pub mod perf;
+    pub mod rename;
     pub mod map_in_place;
+    pub mod item_index;
+    pub mod metrics;
+    pub mod stable_ids;
 }
 
 pub mod json;
@@ -146,9 +150,12 @@ pub mod mut_visit;
 pub mod parse;
 pub mod ptr;
 pub mod show_span;
+pub mod suggestions;
+pub mod test_annotations;
 pub use syntax_pos::edition;
 pub use syntax_pos::symbol;
 pub mod tokenstream;
+pub mod validate;
 pub mod visit;
 
 pub mod print {
@@ -166,6 +173,7 @@ pub mod ext {
     pub mod base;
     pub mod build;
     pub mod expand;
+    pub mod macro_rules_resolver;
     pub mod proc_macro;
 
     pub mod tt {
@@ -173,6 +181,7 @@ pub mod ext {
         pub mod macro_check;
         pub mod macro_parser;
         pub mod macro_rules;
+        pub mod macro_separators;
         pub mod quoted;
     }
 }
@@ -1,12 +1,15 @@
 use crate::attr;
 use crate::ast;
+use crate::feature_gate::{AttributeType, BUILTIN_ATTRIBUTE_MAP};
 use crate::parse::{SeqSep, PResult};
 use crate::parse::token::{self, Nonterminal, DelimToken};
 use crate::parse::parser::{Parser, TokenType, PathStyle};
 use crate::tokenstream::{TokenStream, TokenTree};
 use crate::source_map::Span;
 
+use errors::Applicability;
 use log::debug;
+use syntax_pos::BytePos;
 use smallvec::smallvec;
 
 #[derive(Debug)]
@@ -18,6 +21,11 @@ enum InnerAttributeParsePolicy<'a> {
 const DEFAULT_UNEXPECTED_INNER_ATTR_ERR_MSG: &str = "an inner attribute is not \
                                                      permitted in this context";
 
+const INNER_VS_OUTER_ATTR_NOTE: &str = "inner attributes, like `#![no_std]`, annotate the item \
+                                        enclosing them, and are usually found at the beginning \
+                                        of source files. Outer attributes, like `#[test]`, \
+                                        annotate the item following them.";
+
 impl<'a> Parser<'a> {
     /// Parses attributes that appear before an item.
     crate fn parse_outer_attributes(&mut self) -> PResult<'a, Vec<ast::Attribute>> {
@@ -99,7 +107,8 @@ impl<'a> Parser<'a> {
                     self.expected_tokens.push(TokenType::Token(token::Not));
                 }
 
-                let style = if self.token == token::Not {
+                let not_span = self.token.span;
+                let mut style = if self.token == token::Not {
                     self.bump();
                     ast::AttrStyle::Inner
                 } else {
@@ -133,12 +142,30 @@ impl<'a> Parser<'a> {
                                 .span_label(prev_attr_sp, prev_attr_note);
                         }
 
-                        diagnostic
-                            .note("inner attributes, like `#![no_std]`, annotate the item \
-                                   enclosing them, and are usually found at the beginning of \
-                                   source files. Outer attributes, like `#[test]`, annotate the \
-                                   item following them.")
-                            .emit()
+                        diagnostic.note(INNER_VS_OUTER_ATTR_NOTE);
+
+                        // Unless nothing follows, the attribute is taken to apply to what
+                        // follows it, like an outer attribute.
+                        let at_end = self.token == token::CloseDelim(DelimToken::Brace) ||
+                            self.token == token::Eof;
+                        if !at_end {
+                            // Among the outer attributes of the item that follows, it can only
+                            // be meant for that item. Otherwise this moves the attribute from
+                            // the enclosing item, which may well be where it belongs.
+                            let applicability = if prev_attr_sp.is_some() {
+                                Applicability::MachineApplicable
+                            } else {
+                                Applicability::MaybeIncorrect
+                            };
+                            diagnostic.span_suggestion(
+                                not_span,
+                                "to annotate the item that follows, use an outer attribute",
+                                String::new(),
+                                applicability,
+                            );
+                            style = ast::AttrStyle::Outer;
+                        }
+                        diagnostic.emit()
                     }
                 }
 
@@ -220,9 +247,15 @@ impl<'a> Parser<'a> {
         loop {
             match self.token.kind {
                 token::Pound => {
-                    // Don't even try to parse if it's not an inner attribute.
+                    // Don't even try to parse if it's not an inner attribute, unless it's an
+                    // outer attribute directly followed by inner ones.
                     if !self.look_ahead(1, |t| t == &token::Not) {
-                        break;
+                        if !self.is_outer_attr_before_inner_attr() {
+                            break;
+                        }
+                        let attr = self.parse_attribute(false)?;
+                        attrs.push(self.recover_outer_attr_before_inner_attr(attr));
+                        continue;
                     }
 
                     let attr = self.parse_attribute(true)?;
@@ -245,6 +278,72 @@ impl<'a> Parser<'a> {
         Ok(attrs)
     }
 
+    /// Checks if the `#[...]` at the current token is followed by more of them and then by
+    /// an inner attribute, as in `#[feature(x)] #![no_std]`.
+    fn is_outer_attr_before_inner_attr(&self) -> bool {
+        // Each `[...]` is a single token tree, so looking ahead skips over it at once.
+        let mut dist = 0;
+        while self.look_ahead(dist, |t| t == &token::Pound) {
+            match self.look_ahead(dist + 1, |t| t.kind.clone()) {
+                token::Not => return dist > 0,
+                token::OpenDelim(token::Bracket) => dist += 2,
+                _ => return false,
+            }
+        }
+        false
+    }
+
+    /// Recovers from an outer attribute followed by inner attributes, which was most likely
+    /// meant to be an inner attribute as well, by turning it into one.
+    fn recover_outer_attr_before_inner_attr(&self, mut attr: ast::Attribute) -> ast::Attribute {
+        let mut err = self.struct_span_err(
+            attr.span,
+            "an outer attribute is not permitted before an inner attribute",
+        );
+        err.span_label(attr.span, "followed by an inner attribute")
+            .note(INNER_VS_OUTER_ATTR_NOTE)
+            .span_suggestion(
+                attr.span.with_lo(attr.span.lo() + BytePos(1)).shrink_to_lo(),
+                "if it's meant to annotate the enclosing item, make it an inner attribute",
+                "!".to_string(),
+                Applicability::MachineApplicable,
+            )
+            .emit();
+        attr.style = ast::AttrStyle::Inner;
+        attr
+    }
+
+    /// Recovers from outer attributes at the top of the crate root that only the crate can
+    /// have, as in `#[no_std] fn main() {}`, by making them inner attributes of the crate, that
+    /// is adding them to `crate_attrs`. Only the ones before any other attribute are taken, the
+    /// others are returned, they annotate the first item of the crate.
+    crate fn recover_crate_level_outer_attrs(
+        &self,
+        crate_attrs: &mut Vec<ast::Attribute>,
+        mut attrs: Vec<ast::Attribute>,
+    ) -> Vec<ast::Attribute> {
+        let crate_level = attrs.iter().take_while(|attr| {
+            attr.style == ast::AttrStyle::Outer && !attr.is_sugared_doc && attr.ident()
+                .and_then(|ident| BUILTIN_ATTRIBUTE_MAP.get(&ident.name))
+                .map_or(false, |&&(_, ty, ..)| ty == AttributeType::CrateLevel)
+        }).count();
+        for mut attr in attrs.drain(..crate_level) {
+            self.struct_span_err(attr.span, "crate-level attribute should be an inner attribute")
+                .span_label(attr.span, "only the crate can have this attribute")
+                .note(INNER_VS_OUTER_ATTR_NOTE)
+                .span_suggestion(
+                    attr.span.with_lo(attr.span.lo() + BytePos(1)).shrink_to_lo(),
+                    "to annotate the crate, use an inner attribute",
+                    "!".to_string(),
+                    Applicability::MachineApplicable,
+                )
+                .emit();
+            attr.style = ast::AttrStyle::Inner;
+            crate_attrs.push(attr);
+        }
+        attrs
+    }
+
     fn parse_unsuffixed_lit(&mut self) -> PResult<'a, ast::Lit> {
         let lit = self.parse_lit()?;
         debug!("checking if {:?} is unusuffixed", lit);
@@ -5,6 +5,7 @@ use crate::ast::{
 use crate::feature_gate::{feature_err, UnstableFeatures};
 use crate::parse::{SeqSep, PResult, Parser, ParseSess};
 use crate::parse::parser::{BlockMode, PathStyle, SemiColonMode, TokenType, TokenExpectType};
+use crate::parse::parser::RecoveryKind;
 use crate::parse::token::{self, TokenKind};
 use crate::print::pprust;
 use crate::ptr::P;
@@ -205,6 +206,41 @@ impl<'a> Parser<'a> {
         self.sess.source_map().span_to_snippet(span)
     }
 
+    /// Returns `span` if a suggestion can be made on it, or `None` if it points at code that
+    /// can't be edited. Code expanded from a `macro_rules` macro keeps its span in the
+    /// definition of the macro, which can be edited if it's in this crate, but the spans of
+    /// code produced by procedural macros or by macros of other crates can't.
+    crate fn suggestable_span(&self, span: Span) -> Option<Span> {
+        if span.is_dummy() {
+            return None;
+        }
+        if !span.from_expansion() {
+            return Some(span);
+        }
+        let def_site = span.ctxt().outer_expn_data().def_site;
+        let file = self.sess.source_map().lookup_char_pos(span.lo()).file;
+        if def_site.contains(span) && !file.is_imported() {
+            Some(span)
+        } else {
+            None
+        }
+    }
+
+    /// Downgrades the `applicability` of a suggestion on `span` if it's in the definition of a
+    /// macro, as the edit affects all of its uses and not only the one being parsed.
+    crate fn suggestion_applicability(
+        &self,
+        span: Span,
+        applicability: Applicability,
+    ) -> Applicability {
+        match applicability {
+            Applicability::MachineApplicable if span.from_expansion() => {
+                Applicability::MaybeIncorrect
+            }
+            applicability => applicability,
+        }
+    }
+
     crate fn expected_ident_found(&self) -> DiagnosticBuilder<'a> {
         let mut err = self.struct_span_err(
             self.token.span,
@@ -212,12 +248,14 @@ impl<'a> Parser<'a> {
         );
         if let token::Ident(name, false) = self.token.kind {
             if Ident::new(name, self.token.span).is_raw_guess() {
-                err.span_suggestion(
-                    self.token.span,
-                    "you can escape reserved keywords to use them as identifiers",
-                    format!("r#{}", name),
-                    Applicability::MaybeIncorrect,
-                );
+                if let Some(sp) = self.suggestable_span(self.token.span) {
+                    err.span_suggestion(
+                        sp,
+                        "you can escape reserved keywords to use them as identifiers",
+                        format!("r#{}", name),
+                        Applicability::MaybeIncorrect,
+                    );
+                }
             }
         }
         if let Some(token_descr) = self.token_descr() {
@@ -225,12 +263,14 @@ impl<'a> Parser<'a> {
         } else {
             err.span_label(self.token.span, "expected identifier");
             if self.token == token::Comma && self.look_ahead(1, |t| t.is_ident()) {
-                err.span_suggestion(
-                    self.token.span,
-                    "remove this comma",
-                    String::new(),
-                    Applicability::MachineApplicable,
-                );
+                if let Some(sp) = self.suggestable_span(self.token.span) {
+                    err.span_suggestion(
+                        sp,
+                        "remove this comma",
+                        String::new(),
+                        self.suggestion_applicability(sp, Applicability::MachineApplicable),
+                    );
+                }
             }
         }
         err
@@ -287,17 +327,18 @@ impl<'a> Parser<'a> {
         };
         self.last_unexpected_token_span = Some(self.token.span);
         let mut err = self.fatal(&msg_exp);
-        if self.token.is_ident_named(sym::and) {
+        let token_sp = self.suggestable_span(self.token.span);
+        if let Some(sp) = token_sp.filter(|_| self.token.is_ident_named(sym::and)) {
             err.span_suggestion_short(
-                self.token.span,
+                sp,
                 "use `&&` instead of `and` for the boolean operator",
                 "&&".to_string(),
                 Applicability::MaybeIncorrect,
             );
         }
-        if self.token.is_ident_named(sym::or) {
+        if let Some(sp) = token_sp.filter(|_| self.token.is_ident_named(sym::or)) {
             err.span_suggestion_short(
-                self.token.span,
+                sp,
                 "use `||` instead of `or` for the boolean operator",
                 "||".to_string(),
                 Applicability::MaybeIncorrect,
@@ -338,12 +379,14 @@ impl<'a> Parser<'a> {
             (Ok(ref a), Ok(ref b)) if a.line != b.line && is_semi_suggestable => {
                 // The spans are in different lines, expected `;` and found `let` or `return`.
                 // High likelihood that it is only a missing `;`.
-                err.span_suggestion_short(
-                    label_sp,
-                    "a semicolon may be missing here",
-                    ";".to_string(),
-                    Applicability::MaybeIncorrect,
-                );
+                if let Some(label_sp) = self.suggestable_span(label_sp) {
+                    err.span_suggestion_short(
+                        label_sp,
+                        "a semicolon may be missing here",
+                        ";".to_string(),
+                        Applicability::MaybeIncorrect,
+                    );
+                }
                 err.emit();
                 return Ok(true);
             }
@@ -385,18 +428,20 @@ impl<'a> Parser<'a> {
             let sm = self.sess.source_map();
             let next_pos = sm.lookup_char_pos(self.token.span.lo());
             let op_pos = sm.lookup_char_pos(sp.hi());
+            let suggestable = self.suggestable_span(sp).is_some();
 
-            if likely_path {
+            if likely_path && suggestable {
+                let applicability = match self.sess.unstable_features {
+                    UnstableFeatures::Disallow => Applicability::MachineApplicable,
+                    _ => Applicability::MaybeIncorrect,
+                };
                 err.span_suggestion(
                     sp,
                     "maybe write a path separator here",
                     "::".to_string(),
-                    match self.sess.unstable_features {
-                        UnstableFeatures::Disallow => Applicability::MachineApplicable,
-                        _ => Applicability::MaybeIncorrect,
-                    },
+                    self.suggestion_applicability(sp, applicability),
                 );
-            } else if op_pos.line != next_pos.line && maybe_expected_semicolon {
+            } else if op_pos.line != next_pos.line && maybe_expected_semicolon && suggestable {
                 err.span_suggestion(
                     sp,
                     "try using a semicolon",
@@ -411,10 +456,10 @@ impl<'a> Parser<'a> {
             if let UnstableFeatures::Disallow = self.sess.unstable_features {
                 // Give extra information about type ascription only if it's a nightly compiler.
             } else {
-                err.note("`#![feature(type_ascription)]` lets you annotate an expression with a \
-                          type: `<expr>: <type>`");
-                err.note("for more information, see \
-                          https://github.com/rust-lang/rust/issues/23416");
+                err.low_importance_note("`#![feature(type_ascription)]` lets you annotate an \
+                                         expression with a type: `<expr>: <type>`");
+                err.low_importance_note("for more information, see \
+                                         https://github.com/rust-lang/rust/issues/23416");
             }
         }
     }
@@ -422,6 +467,7 @@ impl<'a> Parser<'a> {
     /// Eats and discards tokens until one of `kets` is encountered. Respects token trees,
     /// passes through any errors encountered. Used for error recovery.
     crate fn eat_to_tokens(&mut self, kets: &[&TokenKind]) {
+        let lo = self.token.span;
         if let Err(ref mut err) = self.parse_seq_to_before_tokens(
             kets,
             SeqSep::none(),
@@ -430,6 +476,14 @@ impl<'a> Parser<'a> {
         ) {
             err.cancel();
         }
+        self.record_skipped_tokens(lo);
+    }
+
+    /// Records the tokens skipped from the one at `lo` up to the current one, if any.
+    fn record_skipped_tokens(&mut self, lo: Span) {
+        if self.token.span != lo {
+            self.record_recovery(RecoveryKind::SkippedTokens, lo.to(self.prev_span), None);
+        }
     }
 
     /// This function checks if there are trailing angle brackets and produces
@@ -527,18 +581,19 @@ impl<'a> Parser<'a> {
             let span = lo.until(self.token.span);
 
             let total_num_of_gt = number_of_gt + number_of_shr * 2;
-            self.diagnostic()
-                .struct_span_err(
-                    span,
-                    &format!("unmatched angle bracket{}", pluralise!(total_num_of_gt)),
-                )
-                .span_suggestion(
+            let mut err = self.diagnostic().struct_span_err(
+                span,
+                &format!("unmatched angle bracket{}", pluralise!(total_num_of_gt)),
+            );
+            if let Some(span) = self.suggestable_span(span) {
+                err.span_suggestion(
                     span,
                     &format!("remove extra angle bracket{}", pluralise!(total_num_of_gt)),
                     String::new(),
-                    Applicability::MachineApplicable,
-                )
-                .emit();
+                    self.suggestion_applicability(span, Applicability::MachineApplicable),
+                );
+            }
+            err.emit();
         }
     }
 
@@ -582,14 +637,16 @@ impl<'a> Parser<'a> {
     ) {
         if !allow_plus && impl_dyn_multi {
             let sum_with_parens = format!("({})", pprust::ty_to_string(&ty));
-            self.struct_span_err(ty.span, "ambiguous `+` in a type")
-                .span_suggestion(
-                    ty.span,
+            let mut err = self.struct_span_err(ty.span, "ambiguous `+` in a type");
+            if let Some(sp) = self.suggestable_span(ty.span) {
+                err.span_suggestion(
+                    sp,
                     "use parentheses to disambiguate",
                     sum_with_parens,
-                    Applicability::MachineApplicable,
-                )
-                .emit();
+                    self.suggestion_applicability(sp, Applicability::MachineApplicable),
+                );
+            }
+            err.emit();
         }
     }
 
@@ -664,7 +721,7 @@ impl<'a> Parser<'a> {
         );
 
         match ty.node {
-            TyKind::Rptr(ref lifetime, ref mut_ty) => {
+            TyKind::Rptr(ref lifetime, ref mut_ty) if self.suggestable_span(sum_span).is_some() => {
                 let sum_with_parens = pprust::to_string(|s| {
                     s.s.word("&");
                     s.print_opt_lifetime(lifetime);
@@ -678,7 +735,7 @@ impl<'a> Parser<'a> {
                     sum_span,
                     "try adding parentheses",
                     sum_with_parens,
-                    Applicability::MachineApplicable,
+                    self.suggestion_applicability(sum_span, Applicability::MachineApplicable),
                 );
             }
             TyKind::Ptr(..) | TyKind::BareFn(..) => {
@@ -728,16 +785,18 @@ impl<'a> Parser<'a> {
         let ty_str = self
             .span_to_snippet(ty_span)
             .unwrap_or_else(|_| pprust::ty_to_string(&ty));
-        self.diagnostic()
-            .struct_span_err(path.span, "missing angle brackets in associated item path")
-            .span_suggestion(
+        let mut err = self.diagnostic()
+            .struct_span_err(path.span, "missing angle brackets in associated item path");
+        if let Some(sp) = self.suggestable_span(path.span) {
+            err.span_suggestion(
                 // This is a best-effort recovery.
-                path.span,
+                sp,
                 "try",
                 format!("<{}>::{}", ty_str, path),
                 Applicability::MaybeIncorrect,
-            )
-            .emit();
+            );
+        }
+        err.emit();
 
         let path_span = ty_span.shrink_to_hi(); // Use an empty path since `position == 0`.
         Ok(P(T::recovered(
@@ -752,13 +811,16 @@ impl<'a> Parser<'a> {
 
     crate fn maybe_consume_incorrect_semicolon(&mut self, items: &[P<Item>]) -> bool {
         if self.eat(&token::Semi) {
+            self.record_recovery(RecoveryKind::IgnoredToken, self.prev_span, Some(String::new()));
             let mut err = self.struct_span_err(self.prev_span, "expected item, found `;`");
-            err.span_suggestion_short(
-                self.prev_span,
-                "remove this semicolon",
-                String::new(),
-                Applicability::MachineApplicable,
-            );
+            if let Some(sp) = self.suggestable_span(self.prev_span) {
+                err.span_suggestion_short(
+                    sp,
+                    "remove this semicolon",
+                    String::new(),
+                    self.suggestion_applicability(sp, Applicability::MachineApplicable),
+                );
+            }
             if !items.is_empty() {
                 let previous_item = &items[items.len() - 1];
                 let previous_item_kind_name = match previous_item.node {
@@ -919,9 +981,12 @@ impl<'a> Parser<'a> {
             ExprKind::Try(_) => Applicability::MaybeIncorrect, // `await <expr>?`
             _ => Applicability::MachineApplicable,
         };
-        self.struct_span_err(sp, "incorrect use of `await`")
-            .span_suggestion(sp, "`await` is a postfix operation", suggestion, app)
-            .emit();
+        let mut err = self.struct_span_err(sp, "incorrect use of `await`");
+        if let Some(sp) = self.suggestable_span(sp) {
+            let app = self.suggestion_applicability(sp, app);
+            err.span_suggestion(sp, "`await` is a postfix operation", suggestion, app);
+        }
+        err.emit();
         sp
     }
 
@@ -935,13 +1000,16 @@ impl<'a> Parser<'a> {
             self.bump(); // (
             let sp = lo.to(self.token.span);
             self.bump(); // )
-            self.struct_span_err(sp, "incorrect use of `await`")
-                .span_suggestion(
+            let mut err = self.struct_span_err(sp, "incorrect use of `await`");
+            if let Some(sp) = self.suggestable_span(sp) {
+                err.span_suggestion(
                     sp,
                     "`await` is not a method call, remove the parentheses",
                     String::new(),
-                    Applicability::MachineApplicable,
-                ).emit()
+                    self.suggestion_applicability(sp, Applicability::MachineApplicable),
+                );
+            }
+            err.emit()
         }
     }
 
@@ -964,18 +1032,20 @@ impl<'a> Parser<'a> {
                     .span_to_snippet(pat.span.trim_start(begin_par_sp).unwrap())
                     .unwrap_or_else(|_| pprust::pat_to_string(&pat));
 
-                self.struct_span_err(self.prev_span, "unexpected closing `)`")
-                    .span_label(begin_par_sp, "opening `(`")
-                    .span_suggestion(
-                        begin_par_sp.to(self.prev_span),
+                let mut err = self.struct_span_err(self.prev_span, "unexpected closing `)`");
+                err.span_label(begin_par_sp, "opening `(`");
+                if let Some(sp) = self.suggestable_span(begin_par_sp.to(self.prev_span)) {
+                    err.span_suggestion(
+                        sp,
                         "remove parenthesis in `for` loop",
                         format!("{} in {}", pat_str, pprust::expr_to_string(&expr)),
                         // With e.g. `for (x) in y)` this would replace `(x) in y)`
                         // with `x) in y)` which is syntactically invalid.
                         // However, this is prevented before we get here.
-                        Applicability::MachineApplicable,
-                    )
-                    .emit();
+                        self.suggestion_applicability(sp, Applicability::MachineApplicable),
+                    );
+                }
+                err.emit();
 
                 // Unwrap `(pat)` into `pat` to avoid the `unused_parens` lint.
                 pat.and_then(|pat| match pat.node {
@@ -1054,12 +1124,22 @@ impl<'a> Parser<'a> {
                 if let Some(sp) = unmatched.unclosed_span {
                     err.span_label(sp, "unclosed delimiter");
                 }
-                err.span_suggestion_short(
-                    self.sess.source_map().next_point(self.prev_span),
-                    &format!("{} may belong here", delim.to_string()),
-                    delim.to_string(),
-                    Applicability::MaybeIncorrect,
+                let close = token::CloseDelim(unmatched.expected_delim);
+                let fix = pprust::token_kind_to_string(&close);
+                self.record_recovery(
+                    RecoveryKind::ClosedDelimiter,
+                    self.prev_span.shrink_to_hi(),
+                    Some(fix),
                 );
+                let sp = self.sess.source_map().next_point(self.prev_span);
+                if let Some(sp) = self.suggestable_span(sp) {
+                    err.span_suggestion_short(
+                        sp,
+                        &format!("{} may belong here", delim.to_string()),
+                        delim.to_string(),
+                        Applicability::MaybeIncorrect,
+                    );
+                }
                 err.emit();
                 self.expected_tokens.clear();  // reduce errors
                 Ok(true)
@@ -1073,10 +1153,25 @@ impl<'a> Parser<'a> {
         if self.token.is_keyword(kw::Pub) {
             match self.parse_visibility(false) {
                 Ok(vis) => {
-                    self.diagnostic()
-                        .struct_span_err(vis.span, "unnecessary visibility qualifier")
-                        .span_label(vis.span, "`pub` not permitted here")
-                        .emit();
+                    // Remove the qualifier along with the whitespace up to the next token.
+                    let removal_span = vis.span.to(self.token.span.shrink_to_lo());
+                    self.record_recovery(
+                        RecoveryKind::IgnoredToken,
+                        removal_span,
+                        Some(String::new()),
+                    );
+                    let mut err = self.diagnostic()
+                        .struct_span_err(vis.span, "unnecessary visibility qualifier");
+                    err.span_label(vis.span, "`pub` not permitted here");
+                    if let Some(sp) = self.suggestable_span(removal_span) {
+                        err.tool_only_span_suggestion(
+                            sp,
+                            "remove the visibility qualifier",
+                            String::new(),
+                            self.suggestion_applicability(sp, Applicability::MachineApplicable),
+                        );
+                    }
+                    err.emit();
                 }
                 Err(mut err) => err.emit(),
             }
@@ -1088,31 +1183,56 @@ impl<'a> Parser<'a> {
     ///
     /// We terminate when we find an unmatched `}` (without consuming it).
     crate fn recover_stmt(&mut self) {
-        self.recover_stmt_(SemiColonMode::Ignore, BlockMode::Ignore)
+        self.recover_stmt_in_braces(SemiColonMode::Ignore, BlockMode::Ignore)
     }
 
     /// If `break_on_semi` is `Break`, then we will stop consuming tokens after
-    /// finding (and consuming) a `;` outside of `{}` or `[]` (note that this is
+    /// finding (and consuming) a `;` outside of `{}`, `[]` or `()` (note that this is
     /// approximate -- it can mean we break too early due to macros, but that
-    /// should only lead to sub-optimal recovery, not inaccurate parsing).
+    /// should only lead to sub-optimal recovery, not inaccurate parsing). If it's
+    /// `Comma`, we stop before a `,` outside of them, of generic arguments and of closure
+    /// parameters.
     ///
     /// If `break_on_block` is `Break`, then we will stop consuming tokens
-    /// after finding (and consuming) a brace-delimited block.
+    /// after finding (and consuming) a brace-delimited block outside of `[]` or `()`.
+    ///
+    /// An unmatched `)` is left to the caller, it closes the arguments or the parenthesized
+    /// expression the error was found in.
     crate fn recover_stmt_(&mut self, break_on_semi: SemiColonMode, break_on_block: BlockMode) {
         let mut brace_depth = 0;
         let mut bracket_depth = 0;
+        let mut paren_depth = 0;
+        // Generic arguments are only tracked where `<` can't be a less-than operator: right
+        // after `::`, as in `f::<A, B>`, and inside other generic arguments.
+        let mut angle_depth = 0;
+        let mut after_mod_sep = false;
+        // Closure parameters are only tracked where `|` can't be a bitwise or: right after an
+        // opening delimiter, a separator, `=`, `=>` or `move`, as in `f(|x, y| x)`.
+        let mut in_closure_params = false;
+        let mut before_expr = false;
         let mut in_block = false;
+        let lo = self.token.span;
         debug!("recover_stmt_ enter loop (semi={:?}, block={:?})",
                break_on_semi, break_on_block);
         loop {
             debug!("recover_stmt_ loop {:?}", self.token);
+            let opens_generic_args = after_mod_sep || angle_depth > 0;
+            after_mod_sep = self.token == token::ModSep;
+            let opens_closure_params = before_expr;
+            before_expr = match self.token.kind {
+                token::OpenDelim(_) | token::Comma | token::Semi | token::Eq | token::FatArrow => {
+                    true
+                }
+                _ => self.token.is_keyword(kw::Move),
+            };
             match self.token.kind {
                 token::OpenDelim(token::DelimToken::Brace) => {
                     brace_depth += 1;
                     self.bump();
                     if break_on_block == BlockMode::Break &&
                        brace_depth == 1 &&
-                       bracket_depth == 0 {
+                       bracket_depth == 0 &&
+                       paren_depth == 0 {
                         in_block = true;
                     }
                 }
@@ -1120,6 +1240,10 @@ impl<'a> Parser<'a> {
                     bracket_depth += 1;
                     self.bump();
                 }
+                token::OpenDelim(token::DelimToken::Paren) => {
+                    paren_depth += 1;
+                    self.bump();
+                }
                 token::CloseDelim(token::DelimToken::Brace) => {
                     if brace_depth == 0 {
                         debug!("recover_stmt_ return - close delim {:?}", self.token);
@@ -1127,7 +1251,7 @@ impl<'a> Parser<'a> {
                     }
                     brace_depth -= 1;
                     self.bump();
-                    if in_block && bracket_depth == 0 && brace_depth == 0 {
+                    if in_block && bracket_depth == 0 && paren_depth == 0 && brace_depth == 0 {
                         debug!("recover_stmt_ return - block end {:?}", self.token);
                         break;
                     }
@@ -1139,6 +1263,41 @@ impl<'a> Parser<'a> {
                     }
                     self.bump();
                 }
+                token::CloseDelim(token::DelimToken::Paren) => {
+                    if paren_depth == 0 {
+                        debug!("recover_stmt_ return - close delim {:?}", self.token);
+                        break;
+                    }
+                    paren_depth -= 1;
+                    self.bump();
+                }
+                token::BinOp(token::Or) if in_closure_params => {
+                    in_closure_params = false;
+                    self.bump();
+                }
+                token::BinOp(token::Or) if opens_closure_params => {
+                    in_closure_params = true;
+                    self.bump();
+                }
+                token::Lt if opens_generic_args => {
+                    angle_depth += 1;
+                    self.bump();
+                }
+                token::BinOp(token::Shl) if opens_generic_args => {
+                    angle_depth += 2;
+                    self.bump();
+                }
+                token::Gt if angle_depth > 0 => {
+                    angle_depth -= 1;
+                    self.bump();
+                }
+                token::BinOp(token::Shr) if angle_depth > 0 => {
+                    angle_depth -= 2;
+                    if angle_depth < 0 {
+                        angle_depth = 0;
+                    }
+                    self.bump();
+                }
                 token::Eof => {
                     debug!("recover_stmt_ return - Eof");
                     break;
@@ -1147,14 +1306,18 @@ impl<'a> Parser<'a> {
                     self.bump();
                     if break_on_semi == SemiColonMode::Break &&
                        brace_depth == 0 &&
-                       bracket_depth == 0 {
+                       bracket_depth == 0 &&
+                       paren_depth == 0 {
                         debug!("recover_stmt_ return - Semi");
                         break;
                     }
                 }
                 token::Comma if break_on_semi == SemiColonMode::Comma &&
                        brace_depth == 0 &&
-                       bracket_depth == 0 =>
+                       bracket_depth == 0 &&
+                       paren_depth == 0 &&
+                       angle_depth == 0 &&
+                       !in_closure_params =>
                 {
                     debug!("recover_stmt_ return - Semi");
                     break;
@@ -1164,19 +1327,36 @@ impl<'a> Parser<'a> {
                 }
             }
         }
+        self.record_skipped_tokens(lo);
+    }
+
+    /// Like `recover_stmt_`, but for callers in braces, which can't own an unmatched `)`: the
+    /// error was found in a parenthesized group, so the `)` closing it is skipped as well.
+    crate fn recover_stmt_in_braces(
+        &mut self,
+        break_on_semi: SemiColonMode,
+        break_on_block: BlockMode,
+    ) {
+        self.recover_stmt_(break_on_semi, break_on_block);
+        while self.eat(&token::CloseDelim(token::Paren)) {
+            self.recover_stmt_(break_on_semi, break_on_block);
+        }
     }
 
     crate fn check_for_for_in_in_typo(&mut self, in_span: Span) {
         if self.eat_keyword(kw::In) {
             // a common typo: `for _ in in bar {}`
-            self.struct_span_err(self.prev_span, "expected iterable, found keyword `in`")
-                .span_suggestion_short(
-                    in_span.until(self.prev_span),
+            let mut err =
+                self.struct_span_err(self.prev_span, "expected iterable, found keyword `in`");
+            if let Some(sp) = self.suggestable_span(in_span.until(self.prev_span)) {
+                err.span_suggestion_short(
+                    sp,
                     "remove the duplicated `in`",
                     String::new(),
-                    Applicability::MachineApplicable,
-                )
-                .emit();
+                    self.suggestion_applicability(sp, Applicability::MachineApplicable),
+                );
+            }
+            err.emit();
         }
     }
 
@@ -1230,12 +1410,14 @@ impl<'a> Parser<'a> {
             let ident = self.parse_ident().unwrap();
             let span = pat.span.with_hi(ident.span.hi());
 
-            err.span_suggestion(
-                span,
-                "declare the type after the parameter binding",
-                String::from("<identifier>: <type>"),
-                Applicability::HasPlaceholders,
-            );
+            if let Some(span) = self.suggestable_span(span) {
+                err.span_suggestion(
+                    span,
+                    "declare the type after the parameter binding",
+                    String::from("<identifier>: <type>"),
+                    Applicability::HasPlaceholders,
+                );
+            }
             return Some(ident);
         } else if let PatKind::Ident(_, ident, _) = pat.node {
             if require_name && (
@@ -1243,18 +1425,20 @@ impl<'a> Parser<'a> {
                 self.token == token::Comma ||
                 self.token == token::CloseDelim(token::Paren)
             ) { // `fn foo(a, b) {}` or `fn foo(usize, usize) {}`
-                err.span_suggestion(
-                    pat.span,
-                    "if this was a parameter name, give it a type",
-                    format!("{}: TypeName", ident),
-                    Applicability::HasPlaceholders,
-                );
-                err.span_suggestion(
-                    pat.span,
-                    "if this is a type, explicitly ignore the parameter name",
-                    format!("_: {}", ident),
-                    Applicability::MachineApplicable,
-                );
+                if let Some(sp) = self.suggestable_span(pat.span) {
+                    err.span_suggestion(
+                        sp,
+                        "if this was a parameter name, give it a type",
+                        format!("{}: TypeName", ident),
+                        Applicability::HasPlaceholders,
+                    );
+                    err.span_suggestion(
+                        sp,
+                        "if this is a type, explicitly ignore the parameter name",
+                        format!("_: {}", ident),
+                        self.suggestion_applicability(sp, Applicability::MachineApplicable),
+                    );
+                }
                 err.note("anonymous parameters are removed in the 2018 edition (see RFC 1685)");
                 return Some(ident);
             }
@@ -1267,19 +1451,20 @@ impl<'a> Parser<'a> {
         self.expect(&token::Colon)?;
         let ty = self.parse_ty()?;
 
-        self.diagnostic()
-            .struct_span_err_with_code(
-                pat.span,
-                "patterns aren't allowed in methods without bodies",
-                DiagnosticId::Error("E0642".into()),
-            )
-            .span_suggestion_short(
-                pat.span,
+        let mut err = self.diagnostic().struct_span_err_with_code(
+            pat.span,
+            "patterns aren't allowed in methods without bodies",
+            DiagnosticId::Error("E0642".into()),
+        );
+        if let Some(sp) = self.suggestable_span(pat.span) {
+            err.span_suggestion_short(
+                sp,
                 "give this argument a name or use an underscore to ignore it",
                 "_".to_owned(),
-                Applicability::MachineApplicable,
-            )
-            .emit();
+                self.suggestion_applicability(sp, Applicability::MachineApplicable),
+            );
+        }
+        err.emit();
 
         // Pretend the pattern is `_`, to avoid duplicate errors from AST validation.
         let pat = P(Pat {
@@ -1309,23 +1494,25 @@ impl<'a> Parser<'a> {
     }
 
     crate fn consume_block(&mut self, delim: token::DelimToken) {
+        let lo = self.token.span;
         let mut brace_depth = 0;
         loop {
             if self.eat(&token::OpenDelim(delim)) {
                 brace_depth += 1;
             } else if self.eat(&token::CloseDelim(delim)) {
                 if brace_depth == 0 {
-                    return;
+                    break;
                 } else {
                     brace_depth -= 1;
                     continue;
                 }
             } else if self.token == token::Eof || self.eat(&token::CloseDelim(token::NoDelim)) {
-                return;
+                break;
             } else {
                 self.bump();
             }
         }
+        self.record_skipped_tokens(lo);
     }
 
     crate fn expected_expression_found(&self) -> DiagnosticBuilder<'a> {
@@ -1374,4 +1561,157 @@ impl<'a> Parser<'a> {
             }
         }
     }
+
+    /// Recovers from a `where` clause written inside a generic parameter list, as in
+    /// `fn f<T: where T: Clone>()`. Predicates bounding one of the parameters are turned into
+    /// bounds of that parameter, and the parameter list they make up is suggested. The other
+    /// predicates are kept in the `where` clause of `generics`, and reported once the `where`
+    /// clause after the signature is parsed, see `report_where_clause_in_generics`.
+    crate fn recover_where_clause_in_generics(
+        &self,
+        generics: &mut ast::Generics,
+        where_clause: ast::WhereClause,
+    ) {
+        let mut unmerged = Vec::new();
+        for predicate in where_clause.predicates {
+            let param = match predicate {
+                ast::WherePredicate::BoundPredicate(ref pred) => match pred.bounded_ty.node {
+                    TyKind::Path(None, ref path) if pred.bound_generic_params.is_empty() &&
+                        path.segments.len() == 1 && path.segments[0].args.is_none() => {
+                        let name = path.segments[0].ident.name;
+                        generics.params.iter().position(|param| match param.kind {
+                            ast::GenericParamKind::Type { .. } => param.ident.name == name,
+                            _ => false,
+                        })
+                    }
+                    _ => None,
+                },
+                ast::WherePredicate::RegionPredicate(ref pred) => {
+                    let name = pred.lifetime.ident.name;
+                    generics.params.iter().position(|param| match param.kind {
+                        ast::GenericParamKind::Lifetime => param.ident.name == name,
+                        _ => false,
+                    })
+                }
+                _ => None,
+            };
+            match (param, predicate) {
+                (Some(idx), ast::WherePredicate::BoundPredicate(pred)) => {
+                    generics.params[idx].bounds.extend(pred.bounds);
+                }
+                (Some(idx), ast::WherePredicate::RegionPredicate(pred)) => {
+                    generics.params[idx].bounds.extend(pred.bounds);
+                }
+                (_, predicate) => unmerged.push(predicate),
+            }
+        }
+
+        if !unmerged.is_empty() {
+            generics.where_clause.predicates.extend(unmerged);
+            generics.where_clause.span = where_clause.span;
+            return;
+        }
+
+        let mut err = self.struct_span_err(
+            where_clause.span,
+            "`where` clauses are not allowed inside generic parameter lists",
+        );
+        err.span_label(where_clause.span, "misplaced `where` clause");
+        if let Some(sp) = self.suggestable_span(generics.span) {
+            err.span_suggestion(
+                sp,
+                "move the bounds in the `where` clause to the parameters they apply to",
+                pprust::generic_params_to_string(&generics.params),
+                self.suggestion_applicability(sp, Applicability::MachineApplicable),
+            );
+        }
+        err.emit();
+    }
+
+    /// Reports the `where` clause written inside the parameter list of `generics` whose
+    /// predicates that bound no parameter were kept in the `where` clause of `generics`.
+    /// `where_clause` is the one parsed after the signature, `has_where` tells whether it was
+    /// written at all. The parameter list is suggested along with those predicates moved to
+    /// the end of `where_clause`.
+    crate fn report_where_clause_in_generics(
+        &self,
+        generics: &ast::Generics,
+        where_clause: &ast::WhereClause,
+        has_where: bool,
+    ) {
+        let misplaced = &generics.where_clause;
+        let mut err = self.struct_span_err(
+            misplaced.span,
+            "`where` clauses are not allowed inside generic parameter lists",
+        );
+        err.span_label(misplaced.span, "misplaced `where` clause");
+
+        let source_map = self.sess.source_map();
+        let predicates = misplaced.predicates.iter()
+            .map(|predicate| source_map.span_to_snippet(predicate.span()).ok())
+            .collect::<Option<Vec<_>>>();
+        let separator = match where_clause.predicates.last() {
+            _ if !has_where => " where ",
+            // No trailing comma.
+            Some(last) if last.span().hi() == where_clause.span.hi() => ", ",
+            _ => " ",
+        };
+        let parts = predicates
+            .map(|predicates| vec![
+                (generics.span, pprust::generic_params_to_string(&generics.params)),
+                (
+                    where_clause.span.shrink_to_hi(),
+                    format!("{}{}", separator, predicates.join(", ")),
+                ),
+            ])
+            .filter(|parts| parts.iter().all(|&(sp, _)| self.suggestable_span(sp).is_some()));
+        if let Some(parts) = parts {
+            err.multipart_suggestion(
+                "move the predicates that bound no parameter after the signature",
+                parts,
+                self.suggestion_applicability(generics.span, Applicability::MachineApplicable),
+            );
+        } else {
+            err.help("`where` clauses go after the signature, before the body");
+        }
+        err.emit();
+    }
+
+    /// Recovers from the return type of a function written after its `where` clause, as in
+    /// `fn f<T>(t: T) where T: Clone -> T`.
+    crate fn recover_ret_ty_after_where_clause(
+        &mut self,
+        decl: &mut ast::FnDecl,
+        where_clause: &ast::WhereClause,
+    ) -> PResult<'a, ()> {
+        if self.token != token::RArrow || where_clause.predicates.is_empty() {
+            return Ok(());
+        }
+        if let ast::FunctionRetTy::Ty(_) = decl.output {
+            return Ok(());
+        }
+        let lo = self.token.span;
+        let output = self.parse_ret_ty(true)?;
+        let span = lo.to(self.prev_span);
+        if let ast::FunctionRetTy::Ty(ref ty) = output {
+            let mut err = self.struct_span_err(
+                span,
+                "return type should be specified before the `where` clause",
+            );
+            let parts = vec![
+                (where_clause.span.shrink_to_lo(), format!("-> {} ", pprust::ty_to_string(ty))),
+                (where_clause.span.shrink_to_hi().to(span), String::new()),
+            ];
+            if parts.iter().all(|&(sp, _)| self.suggestable_span(sp).is_some()) {
+                err.multipart_suggestion(
+                    "move the return type before the `where` clause",
+                    parts,
+                    self.suggestion_applicability(span, Applicability::MachineApplicable),
+                );
+            }
+            err.emit();
+        }
+        decl.output = output;
+        Ok(())
+    }
 }
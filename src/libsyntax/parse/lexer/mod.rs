@@ -1,9 +1,12 @@
+use crate::ast::CRATE_NODE_ID;
+use crate::early_buffered_lints::{BufferedEarlyLintDiagnostics, BufferedEarlyLintId};
 use crate::parse::ParseSess;
 use crate::parse::token::{self, Token, TokenKind};
 use crate::symbol::{sym, Symbol};
 use crate::parse::unescape_error_reporting::{emit_unescape_error, push_escaped_char};
 
-use errors::{FatalError, DiagnosticBuilder};
+use errors::{Applicability, FatalError, DiagnosticBuilder};
+use errors::suppression::Suppression;
 use syntax_pos::{BytePos, Pos, Span};
 use rustc_lexer::Base;
 use rustc_lexer::unescape;
@@ -182,6 +185,7 @@ impl<'a> StringReader<'a> {
                     self.forbid_bare_cr(start, string, "bare CR not allowed in doc-comment");
                     token::DocComment(Symbol::intern(string))
                 } else {
+                    self.record_suppression(start, string);
                     token::Comment
                 };
 
@@ -480,12 +484,78 @@ impl<'a> StringReader<'a> {
                 None => break,
                 Some(it) => idx + it + 1
             };
-            self.err_span_(start + BytePos(idx as u32 - 1),
-                           start + BytePos(idx as u32),
-                           errmsg);
+            let span = self.mk_sp(start + BytePos(idx as u32 - 1), start + BytePos(idx as u32));
+            self.sess.span_diagnostic.struct_span_err(span, errmsg)
+                .span_suggestion(
+                    span,
+                    "remove the carriage return",
+                    String::new(),
+                    Applicability::MaybeIncorrect,
+                ).emit();
         }
     }
 
+    /// Records the suppression marker in the line comment `comment`, if it is one. A marker
+    /// applies to its own line, or to the next one if it ends in `next-line`.
+    fn record_suppression(&self, start: BytePos, comment: &str) {
+        // Markers in code with overridden spans can't point at any line.
+        if self.override_span.is_some() {
+            return;
+        }
+        let marker = comment[2..].trim();
+        if !marker.starts_with("rustc:") {
+            return;
+        }
+        // Other comments starting with `rustc:`, like notes about the compiler, aren't meant
+        // as markers and are left alone.
+        let marker = marker["rustc:".len()..].trim_start();
+        if !marker.starts_with("allow") ||
+            marker["allow".len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+            return;
+        }
+        let marker = marker["allow".len()..].trim_start();
+        let parsed = if marker.starts_with('(') {
+            marker.find(')').map(|end| {
+                let codes = marker[1..end].split(',')
+                    .map(str::trim)
+                    .filter(|code| !code.is_empty())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>();
+                (codes, marker[end + 1..].trim_start())
+            })
+        } else {
+            None
+        };
+        let (codes, next_line) = match parsed {
+            Some((ref codes, "")) if !codes.is_empty() => (codes.clone(), false),
+            Some((ref codes, "next-line")) if !codes.is_empty() => (codes.clone(), true),
+            _ => {
+                self.sess.buffer_lint_with_diagnostic(
+                    BufferedEarlyLintId::MalformedSuppressionMarkers,
+                    self.mk_sp(start, self.pos),
+                    CRATE_NODE_ID,
+                    "malformed diagnostic suppression marker",
+                    BufferedEarlyLintDiagnostics::MalformedSuppressionMarker,
+                );
+                return;
+            }
+        };
+
+        let loc = self.sess.source_map().lookup_char_pos(start);
+        let file = loc.file;
+        // `loc.line` is 1-based, so it's the index of the next line.
+        let line_index = if next_line { loc.line } else { loc.line - 1 };
+        if line_index >= file.count_lines() {
+            return;
+        }
+        let (lo, mut hi) = file.line_bounds(line_index);
+        if line_index + 1 < file.count_lines() {
+            // Leave out the line break.
+            hi = hi - BytePos(1);
+        }
+        self.sess.suppressions.borrow_mut().add(file.start_pos, Suppression { lo, hi, codes });
+    }
+
     fn report_non_started_raw_string(&self, start: BytePos) -> ! {
         let bad_char = self.str_from(start).chars().last().unwrap();
         self
@@ -1,7 +1,12 @@
 //! The main parser interface.
 
 use crate::ast::{self, CrateConfig, NodeId};
+use crate::attr::CfgPredicate;
 use crate::early_buffered_lints::{BufferedEarlyLint, BufferedEarlyLintId};
+use crate::early_buffered_lints::BufferedEarlyLintDiagnostics;
+use crate::ext::base::ExtCtxt;
+use crate::ext::expand::{AstFragment, ExpansionConfig};
+use crate::ext::macro_rules_resolver::MacroRulesResolver;
 use crate::source_map::{SourceMap, FilePathMapping};
 use crate::feature_gate::UnstableFeatures;
 use crate::parse::parser::Parser;
@@ -9,13 +14,17 @@ use crate::parse::parser::emit_unclosed_delims;
 use crate::parse::token::TokenKind;
 use crate::tokenstream::{TokenStream, TokenTree};
 use crate::print::pprust;
-use crate::symbol::Symbol;
+use crate::ptr::P;
+use crate::util::item_index::ItemIndex;
+use crate::symbol::{Symbol, sym};
 
 use errors::{Applicability, FatalError, Level, Handler, ColorConfig, Diagnostic, DiagnosticBuilder};
+use errors::ErrorReported;
+use errors::suppression::SuppressionTable;
 use rustc_data_structures::fx::{FxHashSet, FxHashMap};
 #[cfg(target_arch = "x86_64")]
 use rustc_data_structures::static_assert_size;
-use rustc_data_structures::sync::{Lrc, Lock, Once};
+use rustc_data_structures::sync::{self, Lrc, Lock, Once};
 use syntax_pos::{Span, SourceFile, FileName, MultiSpan};
 use syntax_pos::edition::Edition;
 use syntax_pos::hygiene::ExpnId;
@@ -49,14 +58,31 @@ static_assert_size!(PResult<'_, bool>, 16);
 /// used and should be feature gated accordingly in `check_crate`.
 #[derive(Default)]
 pub struct GatedSpans {
-    /// Spans collected for gating `let_chains`, e.g. `if a && let b = c {}`.
-    pub let_chains: Lock<Vec<Span>>,
-    /// Spans collected for gating `async_closure`, e.g. `async || ..`.
-    pub async_closure: Lock<Vec<Span>>,
-    /// Spans collected for gating `yield e?` expressions (`generators` gate).
-    pub yields: Lock<Vec<Span>>,
-    /// Spans collected for gating `or_patterns`, e.g. `Some(Foo | Bar)`.
-    pub or_patterns: Lock<Vec<Span>>,
+    /// Spans collected for each feature, e.g. `let_chains` for `if a && let b = c {}`.
+    spans: Lock<FxHashMap<Symbol, Vec<Span>>>,
+}
+
+impl GatedSpans {
+    /// Feature gates `span` under `feature`.
+    pub fn gate(&self, feature: Symbol, span: Span) {
+        self.spans.borrow_mut().entry(feature).or_default().push(span);
+    }
+
+    /// Undoes the last `gate(feature, span)`, for constructs found to be stable in context.
+    pub fn ungate_last(&self, feature: Symbol, span: Span) {
+        let removed = self.spans.borrow_mut().entry(feature).or_default().pop();
+        debug_assert_eq!(removed, Some(span));
+    }
+
+    /// Returns whether nothing has been gated under `feature` yet.
+    pub fn is_ungated(&self, feature: Symbol) -> bool {
+        self.spans.borrow().get(&feature).map_or(true, |spans| spans.is_empty())
+    }
+
+    /// Returns the spans gated under `feature`, in the order they were gated.
+    pub fn spans(&self, feature: Symbol) -> Vec<Span> {
+        self.spans.borrow().get(&feature).cloned().unwrap_or_default()
+    }
 }
 
 /// Info about a parsing session.
@@ -78,8 +104,35 @@ pub struct ParseSess {
     pub ambiguous_block_expr_parse: Lock<FxHashMap<Span, Span>>,
     pub injected_crate_name: Once<Symbol>,
     pub gated_spans: GatedSpans,
+    /// The unstable features found to be used by feature gate checks, with their first use,
+    /// see `feature_gate::detected_features`.
+    pub detected_features: Lock<FxHashMap<Symbol, Span>>,
+    /// Sources registered by embedders whose items are injected at the crate root,
+    /// see `register_crate_root_items`.
+    pub injected_crate_root_items: Lock<Vec<(FileName, String)>>,
+    /// Tells the parser which names refer to macros, see `set_macro_registry`.
+    macro_registry: Once<Lrc<MacroRegistry>>,
+    /// How deeply expressions may be nested, so that the parser doesn't run out of stack.
+    /// Exceeding it is a fatal error.
+    pub expr_depth_limit: usize,
+    /// Additional `cfg` predicates, see `register_cfg_predicate`.
+    cfg_predicates: Lock<FxHashMap<Symbol, Lrc<CfgPredicate>>>,
+    /// Diagnostic suppression markers found by the lexer, shared with `span_diagnostic`,
+    /// see `errors::suppression`.
+    pub suppressions: Lrc<Lock<SuppressionTable>>,
+    /// The functions, `impl`s and traits of the crate by their spans, shared with the source
+    /// map. Only built after expansion if diagnostics show the item they point into, see
+    /// `util::item_index`.
+    pub item_index: Lrc<Lock<ItemIndex>>,
 }
 
+/// The default `ParseSess::expr_depth_limit`.
+pub const DEFAULT_EXPR_DEPTH_LIMIT: usize = 1024;
+
+/// Returns the delimiter a macro is conventionally invoked with if the given name refers to
+/// a macro in scope, and `None` otherwise.
+pub type MacroRegistry = dyn Fn(Symbol) -> Option<ast::MacDelimiter> + sync::Send + sync::Sync;
+
 impl ParseSess {
     pub fn new(file_path_mapping: FilePathMapping) -> Self {
         let cm = Lrc::new(SourceMap::new(file_path_mapping));
@@ -93,6 +146,8 @@ impl ParseSess {
     }
 
     pub fn with_span_handler(handler: Handler, source_map: Lrc<SourceMap>) -> Self {
+        let suppressions = handler.suppressions();
+        let item_index = source_map.item_index();
         Self {
             span_diagnostic: handler,
             unstable_features: UnstableFeatures::from_environment(),
@@ -106,6 +161,13 @@ impl ParseSess {
             ambiguous_block_expr_parse: Lock::new(FxHashMap::default()),
             injected_crate_name: Once::new(),
             gated_spans: GatedSpans::default(),
+            detected_features: Lock::new(FxHashMap::default()),
+            injected_crate_root_items: Lock::new(Vec::new()),
+            macro_registry: Once::new(),
+            expr_depth_limit: DEFAULT_EXPR_DEPTH_LIMIT,
+            cfg_predicates: Lock::new(FxHashMap::default()),
+            suppressions,
+            item_index,
         }
     }
 
@@ -119,6 +181,17 @@ impl ParseSess {
         span: S,
         id: NodeId,
         msg: &str,
+    ) {
+        let diagnostic = BufferedEarlyLintDiagnostics::Normal;
+        self.buffer_lint_with_diagnostic(lint_id, span, id, msg, diagnostic)
+    }
+
+    pub fn buffer_lint_with_diagnostic<S: Into<MultiSpan>>(&self,
+        lint_id: BufferedEarlyLintId,
+        span: S,
+        id: NodeId,
+        msg: &str,
+        diagnostic: BufferedEarlyLintDiagnostics,
     ) {
         self.buffered_lints.with_lock(|buffered_lints| {
             buffered_lints.push(BufferedEarlyLint{
@@ -126,10 +199,56 @@ impl ParseSess {
                 id,
                 msg: msg.into(),
                 lint_id,
+                diagnostic,
             });
         });
     }
 
+    /// Registers `source` to be parsed as a separate source file named `name`, with its items
+    /// (typically `macro_rules!` definitions and `use` declarations) injected at the crate root
+    /// before expansion. This lets tools embedding the compiler provide helpers to the crate
+    /// without concatenating source text, so diagnostics keep pointing at the right places.
+    pub fn register_crate_root_items(&self, name: FileName, source: String) {
+        self.injected_crate_root_items.lock().push((name, source));
+    }
+
+    /// Lets the parser recognize calls to macros that are missing their `!`, such as
+    /// `println("x")`, and recover from them as macro invocations. The registry is only
+    /// consulted for single-segment paths, since macros named by longer paths are rare.
+    ///
+    /// Parsing happens before name resolution, so the registry has to be provided by the
+    /// embedder, e.g. from the macros of a prelude it knows to be in scope.
+    pub fn set_macro_registry(&self, registry: Lrc<MacroRegistry>) {
+        self.macro_registry.set(registry);
+    }
+
+    /// Returns the conventional delimiter of the macro named `name`, if the macro registry
+    /// knows `name` as a macro.
+    crate fn known_macro(&self, name: Symbol) -> Option<ast::MacDelimiter> {
+        self.macro_registry.try_get().and_then(|registry| registry(name))
+    }
+
+    /// Registers `predicate` to evaluate `cfg` conditions like `name(...)`, so that predicates
+    /// such as `version("1.40")` can be experimented with without hardcoding them into
+    /// `attr::cfg_matches`. Only predicates with arguments are looked up, so `name` doesn't
+    /// shadow configuration keys like `unix`. Registering `any`, `all` or `not` panics.
+    pub fn register_cfg_predicate(&self, name: Symbol, predicate: CfgPredicate) {
+        assert!(![sym::any, sym::all, sym::not].contains(&name),
+                "`cfg({})` is a builtin predicate", name);
+        self.cfg_predicates.lock().insert(name, Lrc::new(predicate));
+    }
+
+    /// Returns the `cfg` predicate registered as `name`, if any.
+    crate fn cfg_predicate(&self, name: Symbol) -> Option<Lrc<CfgPredicate>> {
+        // Predicates may evaluate nested conditions, so don't keep the registry locked.
+        self.cfg_predicates.lock().get(&name).cloned()
+    }
+
+    /// Returns the names of the registered `cfg` predicates, in no particular order.
+    crate fn cfg_predicate_names(&self) -> Vec<Symbol> {
+        self.cfg_predicates.lock().keys().cloned().collect()
+    }
+
     /// Extend an error with a suggestion to wrap an expression with parentheses to allow the
     /// parser to continue parsing the following operation as part of the same expression.
     pub fn expr_parentheses_needed(
@@ -175,6 +294,20 @@ pub fn parse_crate_from_file<'a>(input: &Path, sess: &'a ParseSess) -> PResult<'
     parser.parse_crate_mod()
 }
 
+/// Parses the crate rooted at `input`, reporting any parse errors. Meant for library
+/// consumers whose handler operates in error-as-value mode, which get `ErrorReported`
+/// back instead of an unwinding `FatalError` (see `HandlerFlags::errors_as_values`).
+pub fn try_parse_crate_from_file(input: &Path, sess: &ParseSess)
+                                 -> Result<ast::Crate, ErrorReported> {
+    sess.span_diagnostic.catch_fatal_errors(|| {
+        let mut parser = try_new_parser_from_file(sess, input)?;
+        parser.parse_crate_mod().map_err(|mut err| {
+            err.emit();
+            sess.span_diagnostic.fatal_error()
+        })
+    })?
+}
+
 pub fn parse_crate_attrs_from_file<'a>(input: &Path, sess: &'a ParseSess)
                                        -> PResult<'a, Vec<ast::Attribute>> {
     let mut parser = new_parser_from_file(sess, input);
@@ -186,11 +319,80 @@ pub fn parse_crate_from_source_str(name: FileName, source: String, sess: &ParseS
     new_parser_from_source_str(sess, name, source).parse_crate_mod()
 }
 
+/// Like `try_parse_crate_from_file`, but for a crate given as a source string.
+pub fn try_parse_crate_from_source_str(name: FileName, source: String, sess: &ParseSess)
+                                       -> Result<ast::Crate, ErrorReported> {
+    sess.span_diagnostic.catch_fatal_errors(|| {
+        let mut parser = try_new_parser_from_source_str(sess, name, source)?;
+        parser.parse_crate_mod().map_err(|mut err| {
+            err.emit();
+            sess.span_diagnostic.fatal_error()
+        })
+    })?
+}
+
 pub fn parse_crate_attrs_from_source_str(name: FileName, source: String, sess: &ParseSess)
                                              -> PResult<'_, Vec<ast::Attribute>> {
     new_parser_from_source_str(sess, name, source).parse_inner_attributes()
 }
 
+/// Parses `source` as a sequence of statements, including items, in a fresh source file named
+/// `name`. Interactive front ends parse each input chunk this way, so that diagnostics point into
+/// the chunk itself rather than into a concatenation of all inputs seen so far.
+pub fn parse_stmts_from_source_str(name: FileName, source: String, sess: &ParseSess)
+                                   -> PResult<'_, Vec<ast::Stmt>> {
+    let mut parser = new_parser_from_source_str(sess, name, source);
+    let mut stmts = vec![];
+    while parser.token != token::Eof {
+        match parser.parse_full_stmt(false)? {
+            Some(stmt) => stmts.push(stmt),
+            // Found a stray `}`.
+            None => return parser.unexpected(),
+        }
+    }
+    Ok(stmts)
+}
+
+/// Parses `src` as a single expression in a fresh source file and expands all macro invocations
+/// within it, without requiring the expression to be wrapped into an item. This is meant for
+/// debugger expression evaluators and similar tools, whose spans then point directly into the
+/// evaluated input.
+///
+/// The macros in scope are the `macro_rules!` macros among the items registered with
+/// `ParseSess::register_crate_root_items`, see `MacroRulesResolver`. Parse errors, including
+/// tokens left over after the expression, are returned as buffered diagnostics. Errors during
+/// expansion, and in the registered items, are reported through `sess` as usual.
+pub fn parse_and_expand_expr(src: &str, sess: &ParseSess) -> Result<P<ast::Expr>, Vec<Diagnostic>> {
+    let name = FileName::anon_source_code(src);
+    let mut parser = maybe_new_parser_from_source_str(sess, name, src.to_string())?;
+    let expr = match parser.parse_expr() {
+        Ok(expr) => expr,
+        Err(err) => {
+            let mut buffer = Vec::with_capacity(1);
+            err.buffer(&mut buffer);
+            return Err(buffer);
+        }
+    };
+    if parser.token != token::Eof {
+        let mut buffer = Vec::with_capacity(1);
+        if let Err(err) = parser.unexpected::<()>() {
+            err.buffer(&mut buffer);
+        }
+        return Err(buffer);
+    }
+
+    let mut resolver = MacroRulesResolver::new(sess, sess.edition);
+    let registered_items = sess.injected_crate_root_items.lock().clone();
+    for (name, source) in registered_items {
+        match parse_crate_from_source_str(name, source, sess) {
+            Ok(krate) => resolver.define_macros(&krate.module.items),
+            Err(mut err) => err.emit(),
+        }
+    }
+    let mut cx = ExtCtxt::new(sess, ExpansionConfig::default("expr".to_string()), &mut resolver);
+    Ok(cx.monotonic_expander().fully_expand_fragment(AstFragment::Expr(expr)).make_expr())
+}
+
 pub fn parse_stream_from_source_str(
     name: FileName,
     source: String,
@@ -211,6 +413,17 @@ pub fn new_parser_from_source_str(sess: &ParseSess, name: FileName, source: Stri
     panictry_buffer!(&sess.span_diagnostic, maybe_new_parser_from_source_str(sess, name, source))
 }
 
+/// Creates a new parser from a source string, reporting any errors from lexing the initial
+/// token stream.
+pub fn try_new_parser_from_source_str(sess: &ParseSess, name: FileName, source: String)
+    -> Result<Parser<'_>, ErrorReported>
+{
+    sess.span_diagnostic.catch_fatal_errors(|| {
+        maybe_new_parser_from_source_str(sess, name, source)
+            .map_err(|errs| emit_fatal_errors(&sess.span_diagnostic, errs))
+    })?
+}
+
 /// Creates a new parser from a source string. Returns any buffered errors from lexing the initial
 /// token stream.
 pub fn maybe_new_parser_from_source_str(sess: &ParseSess, name: FileName, source: String)
@@ -227,6 +440,16 @@ pub fn new_parser_from_file<'a>(sess: &'a ParseSess, path: &Path) -> Parser<'a>
     source_file_to_parser(sess, file_to_source_file(sess, path, None))
 }
 
+/// Creates a new parser, reporting any errors if the file doesn't exist, or from lexing the
+/// initial token stream.
+pub fn try_new_parser_from_file<'a>(sess: &'a ParseSess, path: &Path)
+    -> Result<Parser<'a>, ErrorReported> {
+    sess.span_diagnostic.catch_fatal_errors(|| {
+        maybe_new_parser_from_file(sess, path)
+            .map_err(|errs| emit_fatal_errors(&sess.span_diagnostic, errs))
+    })?
+}
+
 /// Creates a new parser, returning buffered diagnostics if the file doesn't exist,
 /// or from lexing the initial token stream.
 pub fn maybe_new_parser_from_file<'a>(sess: &'a ParseSess, path: &Path)
@@ -319,6 +542,27 @@ pub fn source_file_to_stream(
     panictry_buffer!(&sess.span_diagnostic, maybe_file_to_stream(sess, source_file, override_span))
 }
 
+/// Given a `source_file`, produces a sequence of token trees, reporting any errors from
+/// parsing the token stream.
+pub fn try_source_file_to_stream(
+    sess: &ParseSess,
+    source_file: Lrc<SourceFile>,
+    override_span: Option<Span>,
+) -> Result<(TokenStream, Vec<lexer::UnmatchedBrace>), ErrorReported> {
+    sess.span_diagnostic.catch_fatal_errors(|| {
+        maybe_file_to_stream(sess, source_file, override_span)
+            .map_err(|errs| emit_fatal_errors(&sess.span_diagnostic, errs))
+    })?
+}
+
+/// Emits the buffered errors `errs`, which abort parsing.
+fn emit_fatal_errors(handler: &Handler, errs: Vec<Diagnostic>) -> ErrorReported {
+    for err in errs {
+        handler.emit_diagnostic(&err);
+    }
+    handler.fatal_error()
+}
+
 /// Given a source file, produces a sequence of token trees. Returns any buffered errors from
 /// parsing the token stream.
 pub fn maybe_file_to_stream(
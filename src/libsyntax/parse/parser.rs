@@ -105,6 +105,32 @@ enum PrevTokenKind {
     Other,
 }
 
+/// What the parser did to go on after a syntax error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecoveryKind {
+    /// A delimiter the lexer found unclosed was assumed to be closed where the parser
+    /// expected it.
+    ClosedDelimiter,
+    /// Tokens that couldn't be parsed were skipped, up to the end of the statement or of the
+    /// enclosing delimiters.
+    SkippedTokens,
+    /// A token that isn't allowed where it was found was ignored, like a `;` after an item.
+    IgnoredToken,
+    /// An error was reported while parsing an item or a statement, and parsing went on without
+    /// recording a more specific recovery. The span is the one of the item or statement.
+    Other,
+}
+
+/// A recovery from a syntax error, see `Parser::recovery_events`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecoveryEvent {
+    pub kind: RecoveryKind,
+    pub span: Span,
+    /// The text the parser acted as if `span` was replaced with, like the assumed closing
+    /// delimiter or nothing for an ignored token, if any.
+    pub fix: Option<String>,
+}
+
 // NOTE: `Ident`s are handled by `common.rs`.
 
 #[derive(Clone)]
@@ -151,6 +177,9 @@ pub struct Parser<'a> {
     crate last_type_ascription: Option<(Span, bool /* likely path typo */)>,
     /// If present, this `Parser` is not parsing Rust code but rather a macro call.
     crate subparser_name: Option<&'static str>,
+    /// How deeply the expression being parsed is nested, see `ParseSess::expr_depth_limit`.
+    crate expr_depth: usize,
+    crate recovery_events: Vec<RecoveryEvent>,
 }
 
 impl<'a> Drop for Parser<'a> {
@@ -368,6 +397,8 @@ impl<'a> Parser<'a> {
             last_unexpected_token_span: None,
             last_type_ascription: None,
             subparser_name,
+            expr_depth: 0,
+            recovery_events: Vec::new(),
         };
 
         parser.token = parser.next_tok();
@@ -400,6 +431,39 @@ impl<'a> Parser<'a> {
         next
     }
 
+    /// Returns the recoveries from syntax errors made so far, in the order they were made,
+    /// including the ones made while parsing out-of-line modules. Tools can tell from them
+    /// whether a file was parsed cleanly, without looking at the diagnostics emitted.
+    pub fn recovery_events(&self) -> &[RecoveryEvent] {
+        &self.recovery_events
+    }
+
+    /// Returns the delimiters the lexer found unclosed that weren't used for recovery yet.
+    /// They are reported when the parser is dropped.
+    pub fn unclosed_delims(&self) -> &[UnmatchedBrace] {
+        &self.unclosed_delims
+    }
+
+    crate fn record_recovery(&mut self, kind: RecoveryKind, span: Span, fix: Option<String>) {
+        self.recovery_events.push(RecoveryEvent { kind, span, fix });
+    }
+
+    /// Runs `f` to parse an item or a statement. If it reported errors that it recovered from
+    /// without recording more specific recoveries, records a `RecoveryKind::Other` over what
+    /// it parsed, so that no recovery goes unrecorded.
+    crate fn recording_recoveries<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let lo = self.token.span;
+        let err_count = self.sess.span_diagnostic.err_count();
+        let recovery_events = self.recovery_events.len();
+        let result = f(self);
+        if self.sess.span_diagnostic.err_count() > err_count
+            && self.recovery_events.len() == recovery_events
+        {
+            self.record_recovery(RecoveryKind::Other, lo.to(self.prev_span), None);
+        }
+        result
+    }
+
     /// Converts the current token to a string using `self`'s reader.
     pub fn this_token_to_string(&self) -> String {
         pprust::token_to_string(&self.token)
@@ -1213,13 +1277,14 @@ impl<'a> Parser<'a> {
             ) {
                 Ok(param) => {
                     if let TyKind::CVarArgs = param.ty.node {
-                        c_variadic = true;
                         if p.token != token::CloseDelim(token::Paren) {
-                            let span = p.token.span;
-                            p.span_err(span,
-                                "`...` must be the last argument of a C-variadic function");
+                            p.struct_span_err(param.ty.span,
+                                "`...` must be the last argument of a C-variadic function")
+                                .span_label(param.ty.span, "followed by other arguments")
+                                .emit();
                             Ok(None)
                         } else {
+                            c_variadic = true;
                             Ok(Some(param))
                         }
                     } else {
@@ -7,7 +7,7 @@ use super::pat::{GateOr, PARAM_EXPECTED};
 use crate::ast::{
     self, DUMMY_NODE_ID, Attribute, AttrStyle, Ident, CaptureBy, BlockCheckMode,
     Expr, ExprKind, RangeLimits, Label, Movability, IsAsync, Arm, Ty, TyKind,
-    FunctionRetTy, Param, FnDecl, BinOpKind, BinOp, UnOp, Mac, AnonConst, Field,
+    FunctionRetTy, Param, FnDecl, BinOpKind, BinOp, UnOp, Mac, AnonConst, Field, Mutability,
 };
 use crate::maybe_recover_from_interpolated_ty_qpath;
 use crate::parse::classify;
@@ -17,9 +17,10 @@ use crate::print::pprust;
 use crate::ptr::P;
 use crate::source_map::{self, Span};
 use crate::symbol::{kw, sym};
+use crate::tokenstream::TokenStream;
 use crate::util::parser::{AssocOp, Fixity, prec_let_scrutinee_needs_par};
 
-use errors::Applicability;
+use errors::{Applicability, FatalError};
 use std::mem;
 use rustc_data_structures::thin_vec::ThinVec;
 
@@ -65,6 +66,29 @@ pub(super) enum LhsExpr {
     AlreadyParsed(P<Expr>),
 }
 
+/// An operator whose right-hand side is being parsed, see `Parser::parse_assoc_expr_levels`.
+struct PendingAssocOp {
+    op: AssocOp,
+    fixity: Fixity,
+    lhs: P<Expr>,
+    lhs_span: Span,
+    cur_op_span: Span,
+    /// The `min_prec`, `restrictions` and `last_type_ascription_set` of the level of `lhs`.
+    min_prec: usize,
+    restrictions: Restrictions,
+    last_type_ascription_set: bool,
+}
+
+/// A prefix operator that's been parsed, but not applied to its operand yet, see
+/// `Parser::parse_prefix_expr`.
+enum PrefixOp {
+    Unary(UnOp),
+    /// `~`, which is recovered as `!`.
+    Tilde,
+    AddrOf(Mutability),
+    Box,
+}
+
 impl From<Option<ThinVec<Attribute>>> for LhsExpr {
     /// Converts `Some(attrs)` into `LhsExpr::AttributesParsed(attrs)`
     /// and `None` into `LhsExpr::NotYetParsed`.
@@ -143,6 +167,39 @@ impl<'a> Parser<'a> {
         &mut self,
         min_prec: usize,
         lhs: LhsExpr,
+    ) -> PResult<'a, P<Expr>> {
+        self.enter_nested_expr();
+        let restrictions = self.restrictions;
+        let result = self.parse_assoc_expr_levels(min_prec, lhs);
+        // The right-hand sides of operators are parsed with different restrictions.
+        self.restrictions = restrictions;
+        self.expr_depth -= 1;
+        result
+    }
+
+    /// Makes sure that the expression about to be parsed isn't nested too deeply, see
+    /// `ParseSess::expr_depth_limit`.
+    fn enter_nested_expr(&mut self) {
+        self.expr_depth += 1;
+        if self.expr_depth > self.sess.expr_depth_limit {
+            self.fatal("expression is nested too deeply")
+                .note(&format!(
+                    "expressions can be nested at most {} levels deep",
+                    self.sess.expr_depth_limit,
+                ))
+                .emit();
+            FatalError.raise();
+        }
+    }
+
+    /// Does the work of `parse_assoc_expr_with`. Rather than recursing to parse the right-hand
+    /// side of an operator, the operator and its left-hand side are pushed onto a stack, so that
+    /// long chains of operators don't overflow the stack. Each entry of the stack is a
+    /// precedence level, that ends when an operator of lower precedence is found.
+    fn parse_assoc_expr_levels(
+        &mut self,
+        mut min_prec: usize,
+        lhs: LhsExpr,
     ) -> PResult<'a, P<Expr>> {
         let mut lhs = if let LhsExpr::AlreadyParsed(expr) = lhs {
             expr
@@ -157,7 +214,7 @@ impl<'a> Parser<'a> {
                 self.parse_prefix_expr(attrs)?
             }
         };
-        let last_type_ascription_set = self.last_type_ascription.is_some();
+        let mut last_type_ascription_set = self.last_type_ascription.is_some();
 
         match (self.expr_is_complete(&lhs), AssocOp::from_token(&self.token)) {
             (true, None) => {
@@ -204,139 +261,200 @@ impl<'a> Parser<'a> {
             }
         }
         self.expected_tokens.push(TokenType::Operator);
-        while let Some(op) = AssocOp::from_token(&self.token) {
-
-            // Adjust the span for interpolated LHS to point to the `$lhs` token and not to what
-            // it refers to. Interpolated identifiers are unwrapped early and never show up here
-            // as `PrevTokenKind::Interpolated` so if LHS is a single identifier we always process
-            // it as "interpolated", it doesn't change the answer for non-interpolated idents.
-            let lhs_span = match (self.prev_token_kind, &lhs.node) {
-                (PrevTokenKind::Interpolated, _) => self.prev_span,
-                (PrevTokenKind::Ident, &ExprKind::Path(None, ref path))
-                    if path.segments.len() == 1 => self.prev_span,
-                _ => lhs.span,
-            };
 
-            let cur_op_span = self.token.span;
-            let restrictions = if op.is_assign_like() {
-                self.restrictions & Restrictions::NO_STRUCT_LITERAL
-            } else {
-                self.restrictions
-            };
-            let prec = op.precedence();
-            if prec < min_prec {
-                break;
-            }
-            // Check for deprecated `...` syntax
-            if self.token == token::DotDotDot && op == AssocOp::DotDotEq {
-                self.err_dotdotdot_syntax(self.token.span);
-            }
-
-            if self.token == token::LArrow {
-                self.err_larrow_operator(self.token.span);
-            }
+        // The operators whose right-hand sides are being parsed, innermost last.
+        let mut pending: Vec<PendingAssocOp> = Vec::new();
+        // Whether the current level ended without looking at the next operator.
+        let mut level_done = false;
+        loop {
+            while let Some(op) = AssocOp::from_token(&self.token) {
+                if level_done {
+                    break;
+                }
 
-            self.bump();
-            if op.is_comparison() {
-                self.check_no_chained_comparison(&lhs, &op)?;
-            }
-            // Special cases:
-            if op == AssocOp::As {
-                lhs = self.parse_assoc_op_cast(lhs, lhs_span, ExprKind::Cast)?;
-                continue
-            } else if op == AssocOp::Colon {
-                let maybe_path = self.could_ascription_be_path(&lhs.node);
-                self.last_type_ascription = Some((self.prev_span, maybe_path));
-
-                lhs = self.parse_assoc_op_cast(lhs, lhs_span, ExprKind::Type)?;
-                continue
-            } else if op == AssocOp::DotDot || op == AssocOp::DotDotEq {
-                // If we didn’t have to handle `x..`/`x..=`, it would be pretty easy to
-                // generalise it to the Fixity::None code.
-                //
-                // We have 2 alternatives here: `x..y`/`x..=y` and `x..`/`x..=` The other
-                // two variants are handled with `parse_prefix_range_expr` call above.
-                let rhs = if self.is_at_start_of_range_notation_rhs() {
-                    Some(self.parse_assoc_expr_with(prec + 1, LhsExpr::NotYetParsed)?)
-                } else {
-                    None
+                // Adjust the span for interpolated LHS to point to the `$lhs` token and not to
+                // what it refers to. Interpolated identifiers are unwrapped early and never show
+                // up here as `PrevTokenKind::Interpolated` so if LHS is a single identifier we
+                // always process it as "interpolated", it doesn't change the answer for
+                // non-interpolated idents.
+                let lhs_span = match (self.prev_token_kind, &lhs.node) {
+                    (PrevTokenKind::Interpolated, _) => self.prev_span,
+                    (PrevTokenKind::Ident, &ExprKind::Path(None, ref path))
+                        if path.segments.len() == 1 => self.prev_span,
+                    _ => lhs.span,
                 };
-                let (lhs_span, rhs_span) = (lhs.span, if let Some(ref x) = rhs {
-                    x.span
-                } else {
-                    cur_op_span
-                });
-                let limits = if op == AssocOp::DotDot {
-                    RangeLimits::HalfOpen
+
+                let cur_op_span = self.token.span;
+                let restrictions = if op.is_assign_like() {
+                    self.restrictions & Restrictions::NO_STRUCT_LITERAL
                 } else {
-                    RangeLimits::Closed
+                    self.restrictions
                 };
+                let prec = op.precedence();
+                if prec < min_prec {
+                    break;
+                }
+                // Check for deprecated `...` syntax
+                if self.token == token::DotDotDot && op == AssocOp::DotDotEq {
+                    self.err_dotdotdot_syntax(self.token.span);
+                }
 
-                let r = self.mk_range(Some(lhs), rhs, limits)?;
-                lhs = self.mk_expr(lhs_span.to(rhs_span), r, ThinVec::new());
-                break
-            }
+                if self.token == token::LArrow {
+                    self.err_larrow_operator(self.token.span);
+                }
 
-            let fixity = op.fixity();
-            let prec_adjustment = match fixity {
-                Fixity::Right => 0,
-                Fixity::Left => 1,
-                // We currently have no non-associative operators that are not handled above by
-                // the special cases. The code is here only for future convenience.
-                Fixity::None => 1,
-            };
-            let rhs = self.with_res(
-                restrictions - Restrictions::STMT_EXPR,
-                |this| this.parse_assoc_expr_with(prec + prec_adjustment, LhsExpr::NotYetParsed)
-            )?;
-
-            // Make sure that the span of the parent node is larger than the span of lhs and rhs,
-            // including the attributes.
-            let lhs_span = lhs
-                .attrs
-                .iter()
-                .filter(|a| a.style == AttrStyle::Outer)
-                .next()
-                .map_or(lhs_span, |a| a.span);
-            let span = lhs_span.to(rhs.span);
-            lhs = match op {
-                AssocOp::Add | AssocOp::Subtract | AssocOp::Multiply | AssocOp::Divide |
-                AssocOp::Modulus | AssocOp::LAnd | AssocOp::LOr | AssocOp::BitXor |
-                AssocOp::BitAnd | AssocOp::BitOr | AssocOp::ShiftLeft | AssocOp::ShiftRight |
-                AssocOp::Equal | AssocOp::Less | AssocOp::LessEqual | AssocOp::NotEqual |
-                AssocOp::Greater | AssocOp::GreaterEqual => {
-                    let ast_op = op.to_ast_binop().unwrap();
-                    let binary = self.mk_binary(source_map::respan(cur_op_span, ast_op), lhs, rhs);
-                    self.mk_expr(span, binary, ThinVec::new())
+                self.bump();
+                if op.is_comparison() {
+                    self.check_no_chained_comparison(&lhs, &op)?;
                 }
-                AssocOp::Assign => self.mk_expr(span, ExprKind::Assign(lhs, rhs), ThinVec::new()),
-                AssocOp::AssignOp(k) => {
-                    let aop = match k {
-                        token::Plus =>    BinOpKind::Add,
-                        token::Minus =>   BinOpKind::Sub,
-                        token::Star =>    BinOpKind::Mul,
-                        token::Slash =>   BinOpKind::Div,
-                        token::Percent => BinOpKind::Rem,
-                        token::Caret =>   BinOpKind::BitXor,
-                        token::And =>     BinOpKind::BitAnd,
-                        token::Or =>      BinOpKind::BitOr,
-                        token::Shl =>     BinOpKind::Shl,
-                        token::Shr =>     BinOpKind::Shr,
+                // Special cases:
+                if op == AssocOp::As {
+                    lhs = self.parse_assoc_op_cast(lhs, lhs_span, ExprKind::Cast)?;
+                    continue
+                } else if op == AssocOp::Colon {
+                    let maybe_path = self.could_ascription_be_path(&lhs.node);
+                    self.last_type_ascription = Some((self.prev_span, maybe_path));
+
+                    lhs = self.parse_assoc_op_cast(lhs, lhs_span, ExprKind::Type)?;
+                    continue
+                } else if op == AssocOp::DotDot || op == AssocOp::DotDotEq {
+                    // If we didn’t have to handle `x..`/`x..=`, it would be pretty easy to
+                    // generalise it to the Fixity::None code.
+                    //
+                    // We have 2 alternatives here: `x..y`/`x..=y` and `x..`/`x..=` The other
+                    // two variants are handled with `parse_prefix_range_expr` call above.
+                    let rhs = if self.is_at_start_of_range_notation_rhs() {
+                        Some(self.parse_assoc_expr_with(prec + 1, LhsExpr::NotYetParsed)?)
+                    } else {
+                        None
                     };
-                    let aopexpr = self.mk_assign_op(source_map::respan(cur_op_span, aop), lhs, rhs);
-                    self.mk_expr(span, aopexpr, ThinVec::new())
+                    let (lhs_span, rhs_span) = (lhs.span, if let Some(ref x) = rhs {
+                        x.span
+                    } else {
+                        cur_op_span
+                    });
+                    let limits = if op == AssocOp::DotDot {
+                        RangeLimits::HalfOpen
+                    } else {
+                        RangeLimits::Closed
+                    };
+
+                    let r = self.mk_range(Some(lhs), rhs, limits)?;
+                    lhs = self.mk_expr(lhs_span.to(rhs_span), r, ThinVec::new());
+                    break
                 }
-                AssocOp::As | AssocOp::Colon | AssocOp::DotDot | AssocOp::DotDotEq => {
-                    self.bug("AssocOp should have been handled by special case")
+
+                let fixity = op.fixity();
+                let prec_adjustment = match fixity {
+                    Fixity::Right => 0,
+                    Fixity::Left => 1,
+                    // We currently have no non-associative operators that are not handled above
+                    // by the special cases. The code is here only for future convenience.
+                    Fixity::None => 1,
+                };
+
+                // Start a new level for the right-hand side.
+                pending.push(PendingAssocOp {
+                    op,
+                    fixity,
+                    lhs,
+                    lhs_span,
+                    cur_op_span,
+                    min_prec,
+                    restrictions: self.restrictions,
+                    last_type_ascription_set,
+                });
+                min_prec = prec + prec_adjustment;
+                self.restrictions = restrictions - Restrictions::STMT_EXPR;
+                if [token::DotDot, token::DotDotDot, token::DotDotEq].contains(&self.token.kind) {
+                    lhs = self.parse_prefix_range_expr(None)?;
+                    last_type_ascription_set = false;
+                    level_done = true;
+                } else {
+                    lhs = self.parse_prefix_expr(None)?;
+                    last_type_ascription_set = self.last_type_ascription.is_some();
+                    self.expected_tokens.push(TokenType::Operator);
                 }
-            };
+            }
 
-            if let Fixity::None = fixity { break }
+            // The current level ends here.
+            if last_type_ascription_set {
+                self.last_type_ascription = None;
+            }
+            let PendingAssocOp {
+                op,
+                fixity,
+                lhs: outer_lhs,
+                lhs_span,
+                cur_op_span,
+                min_prec: outer_min_prec,
+                restrictions,
+                last_type_ascription_set: outer_last_type_ascription_set,
+            } = match pending.pop() {
+                Some(pending_op) => pending_op,
+                None => return Ok(lhs),
+            };
+            min_prec = outer_min_prec;
+            self.restrictions = restrictions;
+            last_type_ascription_set = outer_last_type_ascription_set;
+            let rhs = lhs;
+            let lhs_span = Self::assoc_lhs_span(&outer_lhs, lhs_span);
+            lhs = self.mk_assoc_expr(op, outer_lhs, rhs, lhs_span, cur_op_span);
+            level_done = fixity == Fixity::None;
         }
-        if last_type_ascription_set {
-            self.last_type_ascription = None;
+    }
+
+    /// Makes sure that the span of the parent node is larger than the span of lhs and rhs,
+    /// including the attributes.
+    fn assoc_lhs_span(lhs: &Expr, lhs_span: Span) -> Span {
+        lhs.attrs
+            .iter()
+            .filter(|a| a.style == AttrStyle::Outer)
+            .next()
+            .map_or(lhs_span, |a| a.span)
+    }
+
+    /// Combines the operands of a binary or assignment operator.
+    fn mk_assoc_expr(
+        &mut self,
+        op: AssocOp,
+        lhs: P<Expr>,
+        rhs: P<Expr>,
+        lhs_span: Span,
+        cur_op_span: Span,
+    ) -> P<Expr> {
+        let span = lhs_span.to(rhs.span);
+        match op {
+            AssocOp::Add | AssocOp::Subtract | AssocOp::Multiply | AssocOp::Divide |
+            AssocOp::Modulus | AssocOp::LAnd | AssocOp::LOr | AssocOp::BitXor |
+            AssocOp::BitAnd | AssocOp::BitOr | AssocOp::ShiftLeft | AssocOp::ShiftRight |
+            AssocOp::Equal | AssocOp::Less | AssocOp::LessEqual | AssocOp::NotEqual |
+            AssocOp::Greater | AssocOp::GreaterEqual => {
+                let ast_op = op.to_ast_binop().unwrap();
+                let binary = self.mk_binary(source_map::respan(cur_op_span, ast_op), lhs, rhs);
+                self.mk_expr(span, binary, ThinVec::new())
+            }
+            AssocOp::Assign => self.mk_expr(span, ExprKind::Assign(lhs, rhs), ThinVec::new()),
+            AssocOp::AssignOp(k) => {
+                let aop = match k {
+                    token::Plus =>    BinOpKind::Add,
+                    token::Minus =>   BinOpKind::Sub,
+                    token::Star =>    BinOpKind::Mul,
+                    token::Slash =>   BinOpKind::Div,
+                    token::Percent => BinOpKind::Rem,
+                    token::Caret =>   BinOpKind::BitXor,
+                    token::And =>     BinOpKind::BitAnd,
+                    token::Or =>      BinOpKind::BitOr,
+                    token::Shl =>     BinOpKind::Shl,
+                    token::Shr =>     BinOpKind::Shr,
+                };
+                let aopexpr = self.mk_assign_op(source_map::respan(cur_op_span, aop), lhs, rhs);
+                self.mk_expr(span, aopexpr, ThinVec::new())
+            }
+            AssocOp::As | AssocOp::Colon | AssocOp::DotDot | AssocOp::DotDotEq => {
+                self.bug("AssocOp should have been handled by special case")
+            }
         }
-        Ok(lhs)
     }
 
     /// Checks if this expression is a successfully parsed statement.
@@ -399,100 +517,112 @@ impl<'a> Parser<'a> {
     /// Parses a prefix-unary-operator expr.
     fn parse_prefix_expr(
         &mut self,
-        already_parsed_attrs: Option<ThinVec<Attribute>>
+        mut already_parsed_attrs: Option<ThinVec<Attribute>>
     ) -> PResult<'a, P<Expr>> {
-        let attrs = self.parse_or_use_outer_attributes(already_parsed_attrs)?;
-        let lo = self.token.span;
-        // Note: when adding new unary operators, don't forget to adjust TokenKind::can_begin_expr()
-        let (hi, ex) = match self.token.kind {
-            token::Not => {
-                self.bump();
-                let e = self.parse_prefix_expr(None);
-                let (span, e) = self.interpolated_or_expr_span(e)?;
-                (lo.to(span), self.mk_unary(UnOp::Not, e))
-            }
-            // Suggest `!` for bitwise negation when encountering a `~`
-            token::Tilde => {
-                self.bump();
-                let e = self.parse_prefix_expr(None);
-                let (span, e) = self.interpolated_or_expr_span(e)?;
-                let span_of_tilde = lo;
-                self.struct_span_err(span_of_tilde, "`~` cannot be used as a unary operator")
-                    .span_suggestion_short(
-                        span_of_tilde,
-                        "use `!` to perform bitwise negation",
-                        "!".to_owned(),
-                        Applicability::MachineApplicable
-                    )
-                    .emit();
-                (lo.to(span), self.mk_unary(UnOp::Not, e))
-            }
-            token::BinOp(token::Minus) => {
-                self.bump();
-                let e = self.parse_prefix_expr(None);
-                let (span, e) = self.interpolated_or_expr_span(e)?;
-                (lo.to(span), self.mk_unary(UnOp::Neg, e))
-            }
-            token::BinOp(token::Star) => {
-                self.bump();
-                let e = self.parse_prefix_expr(None);
-                let (span, e) = self.interpolated_or_expr_span(e)?;
-                (lo.to(span), self.mk_unary(UnOp::Deref, e))
-            }
-            token::BinOp(token::And) | token::AndAnd => {
-                self.expect_and()?;
-                let m = self.parse_mutability();
-                let e = self.parse_prefix_expr(None);
-                let (span, e) = self.interpolated_or_expr_span(e)?;
-                (lo.to(span), ExprKind::AddrOf(m, e))
-            }
-            token::Ident(..) if self.token.is_keyword(kw::Box) => {
-                self.bump();
-                let e = self.parse_prefix_expr(None);
-                let (span, e) = self.interpolated_or_expr_span(e)?;
-                (lo.to(span), ExprKind::Box(e))
-            }
-            token::Ident(..) if self.token.is_ident_named(sym::not) => {
-                // `not` is just an ordinary identifier in Rust-the-language,
-                // but as `rustc`-the-compiler, we can issue clever diagnostics
-                // for confused users who really want to say `!`
-                let token_cannot_continue_expr = |t: &Token| match t.kind {
-                    // These tokens can start an expression after `!`, but
-                    // can't continue an expression after an ident
-                    token::Ident(name, is_raw) => token::ident_can_begin_expr(name, t.span, is_raw),
-                    token::Literal(..) | token::Pound => true,
-                    _ => t.is_whole_expr(),
-                };
-                let cannot_continue_expr = self.look_ahead(1, token_cannot_continue_expr);
-                if cannot_continue_expr {
+        // The operators are collected first and applied to the operand afterwards, rather than
+        // parsing the operand recursively, so that long sequences of them can't overflow the
+        // stack.
+        let mut prefixes = Vec::new();
+        let mut expr = loop {
+            let attrs = self.parse_or_use_outer_attributes(already_parsed_attrs.take())?;
+            let lo = self.token.span;
+            // Note: when adding new unary operators, don't forget to adjust
+            // TokenKind::can_begin_expr()
+            let prefix = match self.token.kind {
+                token::Not => {
                     self.bump();
-                    // Emit the error ...
-                    self.struct_span_err(
-                        self.token.span,
-                        &format!("unexpected {} after identifier",self.this_token_descr())
-                    )
-                    .span_suggestion_short(
-                        // Span the `not` plus trailing whitespace to avoid
-                        // trailing whitespace after the `!` in our suggestion
-                        self.sess.source_map()
-                            .span_until_non_whitespace(lo.to(self.token.span)),
-                        "use `!` to perform logical negation",
-                        "!".to_owned(),
-                        Applicability::MachineApplicable
-                    )
-                    .emit();
-                    // —and recover! (just as if we were in the block
-                    // for the `token::Not` arm)
-                    let e = self.parse_prefix_expr(None);
-                    let (span, e) = self.interpolated_or_expr_span(e)?;
-                    (lo.to(span), self.mk_unary(UnOp::Not, e))
-                } else {
-                    return self.parse_dot_or_call_expr(Some(attrs));
+                    PrefixOp::Unary(UnOp::Not)
                 }
-            }
-            _ => { return self.parse_dot_or_call_expr(Some(attrs)); }
+                // Suggest `!` for bitwise negation when encountering a `~`
+                token::Tilde => {
+                    self.bump();
+                    PrefixOp::Tilde
+                }
+                token::BinOp(token::Minus) => {
+                    self.bump();
+                    PrefixOp::Unary(UnOp::Neg)
+                }
+                token::BinOp(token::Star) => {
+                    self.bump();
+                    PrefixOp::Unary(UnOp::Deref)
+                }
+                token::BinOp(token::And) | token::AndAnd => {
+                    self.expect_and()?;
+                    PrefixOp::AddrOf(self.parse_mutability())
+                }
+                token::Ident(..) if self.token.is_keyword(kw::Box) => {
+                    self.bump();
+                    PrefixOp::Box
+                }
+                token::Ident(..) if self.token.is_ident_named(sym::not) => {
+                    // `not` is just an ordinary identifier in Rust-the-language,
+                    // but as `rustc`-the-compiler, we can issue clever diagnostics
+                    // for confused users who really want to say `!`
+                    let token_cannot_continue_expr = |t: &Token| match t.kind {
+                        // These tokens can start an expression after `!`, but
+                        // can't continue an expression after an ident
+                        token::Ident(name, is_raw) => {
+                            token::ident_can_begin_expr(name, t.span, is_raw)
+                        }
+                        token::Literal(..) | token::Pound => true,
+                        _ => t.is_whole_expr(),
+                    };
+                    let cannot_continue_expr = self.look_ahead(1, token_cannot_continue_expr);
+                    if cannot_continue_expr {
+                        self.bump();
+                        // Emit the error ...
+                        self.struct_span_err(
+                            self.token.span,
+                            &format!("unexpected {} after identifier",self.this_token_descr())
+                        )
+                        .span_suggestion_short(
+                            // Span the `not` plus trailing whitespace to avoid
+                            // trailing whitespace after the `!` in our suggestion
+                            self.sess.source_map()
+                                .span_until_non_whitespace(lo.to(self.token.span)),
+                            "use `!` to perform logical negation",
+                            "!".to_owned(),
+                            Applicability::MachineApplicable
+                        )
+                        .emit();
+                        // —and recover! (just as if we were in the block
+                        // for the `token::Not` arm)
+                        PrefixOp::Unary(UnOp::Not)
+                    } else {
+                        break self.parse_dot_or_call_expr(Some(attrs))?;
+                    }
+                }
+                _ => break self.parse_dot_or_call_expr(Some(attrs))?,
+            };
+            prefixes.push((lo, prefix, attrs));
         };
-        return Ok(self.mk_expr(lo.to(hi), ex, attrs));
+
+        while let Some((lo, prefix, attrs)) = prefixes.pop() {
+            let span = if self.prev_token_kind == PrevTokenKind::Interpolated {
+                self.prev_span
+            } else {
+                expr.span
+            };
+            let kind = match prefix {
+                PrefixOp::Unary(op) => self.mk_unary(op, expr),
+                PrefixOp::Tilde => {
+                    let span_of_tilde = lo;
+                    self.struct_span_err(span_of_tilde, "`~` cannot be used as a unary operator")
+                        .span_suggestion_short(
+                            span_of_tilde,
+                            "use `!` to perform bitwise negation",
+                            "!".to_owned(),
+                            Applicability::MachineApplicable
+                        )
+                        .emit();
+                    self.mk_unary(UnOp::Not, expr)
+                }
+                PrefixOp::AddrOf(m) => ExprKind::AddrOf(m, expr),
+                PrefixOp::Box => ExprKind::Box(expr),
+            };
+            expr = self.mk_expr(lo.to(span), kind, attrs);
+        }
+        Ok(expr)
     }
 
     /// Returns the span of expr, if it was not interpolated or the span of the interpolated token.
@@ -903,6 +1033,17 @@ impl<'a> Parser<'a> {
                         // MACRO INVOCATION expression
                         let (delim, tts) = self.expect_delimited_token_tree()?;
                         hi = self.prev_span;
+                        self.check_macro_delimiter(&path, delim, hi);
+                        ex = ExprKind::Mac(Mac {
+                            path,
+                            tts,
+                            delim,
+                            span: lo.to(hi),
+                            prior_type_ascription: self.last_type_ascription,
+                        });
+                    } else if self.is_call_of_known_macro(&path) {
+                        let (delim, tts) = self.recover_missing_macro_bang(&path)?;
+                        hi = self.prev_span;
                         ex = ExprKind::Mac(Mac {
                             path,
                             tts,
@@ -1036,7 +1177,7 @@ impl<'a> Parser<'a> {
                     }
 
                     let span = lo.to(hi);
-                    self.sess.gated_spans.yields.borrow_mut().push(span);
+                    self.sess.gated_spans.gate(sym::generators, span);
                 } else if self.eat_keyword(kw::Let) {
                     return self.parse_let_expr(attrs);
                 } else if is_span_rust_2018 && self.eat_keyword(kw::Await) {
@@ -1067,6 +1208,71 @@ impl<'a> Parser<'a> {
         self.maybe_recover_from_bad_qpath(expr, true)
     }
 
+    /// Checks whether `path` is followed by call arguments and names a macro known to the
+    /// macro registry of the session, like `println` in `println("x")`.
+    pub(super) fn is_call_of_known_macro(&self, path: &ast::Path) -> bool {
+        match &path.segments[..] {
+            [segment] if segment.args.is_none() => {
+                self.token == token::OpenDelim(token::Paren) &&
+                    self.sess.known_macro(segment.ident.name).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    /// Parses the arguments of a call to a known macro that is missing its `!` as the
+    /// arguments of the macro invocation.
+    pub(super) fn recover_missing_macro_bang(
+        &mut self,
+        path: &ast::Path,
+    ) -> PResult<'a, (ast::MacDelimiter, TokenStream)> {
+        self.struct_span_err(path.span, &format!("macro `{}` invoked without `!`", path))
+            .span_suggestion(
+                path.span.shrink_to_hi(),
+                "add `!` to invoke the macro",
+                "!".to_string(),
+                Applicability::MachineApplicable,
+            )
+            .emit();
+        self.expect_delimited_token_tree()
+    }
+
+    /// Warns about invocations of known macros that use other delimiters than the macro is
+    /// conventionally invoked with, like `vec!(1, 2)`. `args_span` covers the delimited
+    /// arguments of the invocation.
+    pub(super) fn check_macro_delimiter(
+        &self,
+        path: &ast::Path,
+        delim: ast::MacDelimiter,
+        args_span: Span,
+    ) {
+        let expected = match &path.segments[..] {
+            [segment] => match self.sess.known_macro(segment.ident.name) {
+                Some(expected) if expected != delim => expected,
+                _ => return,
+            },
+            _ => return,
+        };
+        let (open, close, name) = match expected {
+            ast::MacDelimiter::Parenthesis => ("(", ")", "parentheses"),
+            ast::MacDelimiter::Bracket => ("[", "]", "square brackets"),
+            ast::MacDelimiter::Brace => ("{", "}", "braces"),
+        };
+        let open_span = self.sess.source_map().start_point(args_span);
+        let close_span = self.sess.source_map().end_point(args_span);
+        self.diagnostic()
+            .struct_span_warn(
+                args_span,
+                &format!("`{}!` is conventionally invoked with {}", path, name),
+            )
+            .multipart_suggestion(
+                &format!("use {}", name),
+                vec![(open_span, open.to_string()), (close_span, close.to_string())],
+                Applicability::MachineApplicable,
+            )
+            .emit();
+    }
+
     /// Matches `'-' lit | lit` (cf. `ast_validation::AstValidator::check_expr_within_pat`).
     crate fn parse_literal_maybe_minus(&mut self) -> PResult<'a, P<Expr>> {
         maybe_whole_expr!(self);
@@ -1121,7 +1327,7 @@ impl<'a> Parser<'a> {
         };
         if asyncness.is_async() {
             // Feature-gate `async ||` closures.
-            self.sess.gated_spans.async_closure.borrow_mut().push(self.prev_span);
+            self.sess.gated_spans.gate(sym::async_closure, self.prev_span);
         }
 
         let capture_clause = self.parse_capture_clause();
@@ -1244,8 +1450,7 @@ impl<'a> Parser<'a> {
 
         if let ExprKind::Let(..) = cond.node {
             // Remove the last feature gating of a `let` expression since it's stable.
-            let last = self.sess.gated_spans.let_chains.borrow_mut().pop();
-            debug_assert_eq!(cond.span, last.unwrap());
+            self.sess.gated_spans.ungate_last(sym::let_chains, cond.span);
         }
 
         Ok(cond)
@@ -1262,7 +1467,7 @@ impl<'a> Parser<'a> {
             |this| this.parse_assoc_expr_with(1 + prec_let_scrutinee_needs_par(), None.into())
         )?;
         let span = lo.to(expr.span);
-        self.sess.gated_spans.let_chains.borrow_mut().push(span);
+        self.sess.gated_spans.gate(sym::let_chains, span);
         Ok(self.mk_expr(span, ExprKind::Let(pat, expr), attrs))
     }
 
@@ -1635,7 +1840,7 @@ impl<'a> Parser<'a> {
                     // what comes next as additional fields, rather than
                     // bailing out until next `}`.
                     if self.token != token::Comma {
-                        self.recover_stmt_(SemiColonMode::Comma, BlockMode::Ignore);
+                        self.recover_stmt_in_braces(SemiColonMode::Comma, BlockMode::Ignore);
                         if self.token != token::Comma {
                             break;
                         }
@@ -1655,7 +1860,7 @@ impl<'a> Parser<'a> {
                     }
                     e.span_label(struct_sp, "while parsing this struct");
                     e.emit();
-                    self.recover_stmt_(SemiColonMode::Comma, BlockMode::Ignore);
+                    self.recover_stmt_in_braces(SemiColonMode::Comma, BlockMode::Ignore);
                     self.eat(&token::Comma);
                 }
             }
@@ -152,31 +152,57 @@ impl<'a> Parser<'a> {
 
     /// Parses a set of optional generic type parameter declarations. Where
     /// clauses are not parsed here, and must be added later via
-    /// `parse_where_clause()`.
+    /// `parse_where_clause_into()`.
     ///
     /// matches generics = ( ) | ( < > ) | ( < typaramseq ( , )? > ) | ( < lifetimes ( , )? > )
     ///                  | ( < lifetimes , typaramseq ( , )? > )
     /// where   typaramseq = ( typaram ) | ( typaram , typaramseq )
     pub(super) fn parse_generics(&mut self) -> PResult<'a, ast::Generics> {
         let span_lo = self.token.span;
+        let mut misplaced_where_clause = None;
         let (params, span) = if self.eat_lt() {
             let params = self.parse_generic_params()?;
+            if self.token.is_keyword(kw::Where) {
+                misplaced_where_clause = Some(self.parse_where_clause()?);
+            }
             self.expect_gt()?;
             (params, span_lo.to(self.prev_span))
         } else {
             (vec![], self.prev_span.between(self.token.span))
         };
-        Ok(ast::Generics {
+        let mut generics = ast::Generics {
             params,
             where_clause: WhereClause {
                 predicates: Vec::new(),
                 span: DUMMY_SP,
             },
             span,
-        })
+        };
+        if let Some(where_clause) = misplaced_where_clause {
+            self.recover_where_clause_in_generics(&mut generics, where_clause);
+        }
+        Ok(generics)
+    }
+
+    /// Parses an optional where-clause into `generics`, after the predicates recovered from a
+    /// `where` clause misplaced in its parameter list, if any. Those are reported here, where
+    /// they can be suggested after the signature.
+    pub(super) fn parse_where_clause_into(
+        &mut self,
+        generics: &mut ast::Generics,
+    ) -> PResult<'a, ()> {
+        let has_where = self.token.is_keyword(kw::Where);
+        let where_clause = self.parse_where_clause()?;
+        if generics.where_clause.predicates.is_empty() {
+            generics.where_clause = where_clause;
+        } else {
+            self.report_where_clause_in_generics(generics, &where_clause, has_where);
+            generics.where_clause.predicates.extend(where_clause.predicates);
+        }
+        Ok(())
     }
 
-    /// Parses an optional where-clause and places it in `generics`.
+    /// Parses an optional where-clause.
     ///
     /// ```ignore (only-for-syntax-highlight)
     /// where T : Trait<U, V> + 'b, 'a : 'b
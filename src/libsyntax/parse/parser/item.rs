@@ -3,7 +3,7 @@ use super::{Parser, PResult, PathStyle, SemiColonMode, BlockMode};
 use crate::maybe_whole;
 use crate::ptr::P;
 use crate::ast::{
-    self, DUMMY_NODE_ID, Ident, Attribute, AttrStyle,
+    self, DUMMY_NODE_ID, Ident, Attribute,
     Item, ItemKind, ImplItem, TraitItem, TraitItemKind,
     UseTree, UseTreeKind, PathSegment,
     IsAuto, Constness, IsAsync, Unsafety, Defaultness,
@@ -17,6 +17,7 @@ use crate::ext::base::DummyResult;
 use crate::parse::token;
 use crate::parse::parser::maybe_append;
 use crate::parse::diagnostics::Error;
+use crate::print::pprust;
 use crate::tokenstream::{TokenTree, TokenStream};
 use crate::source_map::{respan, Span, Spanned};
 use crate::symbol::{kw, sym};
@@ -58,24 +59,18 @@ impl<'a> Parser<'a> {
         self.unclosed_delims.append(&mut unclosed_delims);
 
         // Once we've parsed an item and recorded the tokens we got while
-        // parsing we may want to store `tokens` into the item we're about to
-        // return. Note, though, that we specifically didn't capture tokens
-        // related to outer attributes. The `tokens` field here may later be
-        // used with procedural macros to convert this item back into a token
-        // stream, but during expansion we may be removing attributes as we go
-        // along.
+        // parsing we store `tokens` into the item we're about to return.
+        // Note, though, that we specifically didn't capture tokens related to
+        // outer attributes. The `tokens` field here may later be used with
+        // procedural macros to convert this item back into a token stream, but
+        // during expansion we may be removing attributes as we go along.
         //
         // If we've got inner attributes then the `tokens` we've got above holds
-        // these inner attributes. If an inner attribute is expanded we won't
-        // actually remove it from the token stream, so we'll just keep yielding
-        // it (bad!). To work around this case for now we just avoid recording
-        // `tokens` if we detect any inner attributes. This should help keep
-        // expansion correct, but we should fix this bug one day!
+        // these inner attributes. `StripUnconfigured::configure_tokens` expands
+        // `cfg_attr`s in them along with the ones in the AST.
         Ok(ret.map(|item| {
             item.map(|mut i| {
-                if !i.attrs.iter().any(|attr| attr.style == AttrStyle::Inner) {
-                    i.tokens = Some(tokens);
-                }
+                i.tokens = Some(tokens);
                 i
             })
         }))
@@ -139,6 +134,8 @@ impl<'a> Parser<'a> {
                 return Ok(Some(
                     self.parse_item_foreign_mod(lo, opt_abi, visibility, attrs, extern_sp)?,
                 ));
+            } else if self.token.is_keyword(kw::Type) {
+                return Ok(Some(self.recover_item_extern_type(lo, opt_abi, visibility, attrs)?));
             }
 
             self.unexpected()?;
@@ -553,23 +550,27 @@ impl<'a> Parser<'a> {
         }
 
         if !attributes_allowed && !attrs.is_empty() {
-            self.expected_item_err(&attrs)?;
+            return Err(self.expected_item_err(&attrs));
         }
         Ok(None)
     }
 
-    /// Emits an expected-item-after-attributes error.
-    fn expected_item_err(&mut self, attrs: &[Attribute]) -> PResult<'a,  ()> {
-        let message = match attrs.last() {
-            Some(&Attribute { is_sugared_doc: true, .. }) => "expected item after doc comment",
-            _ => "expected item after attributes",
+    /// Builds an expected-item-after-attributes error, pointing at the non-empty `attrs`.
+    pub(super) fn expected_item_err(&self, attrs: &[Attribute]) -> DiagnosticBuilder<'a> {
+        let (last, others) = attrs.split_last().unwrap();
+        let mut err = if last.is_sugared_doc {
+            let mut err = self.struct_span_err(last.span, "expected item after doc comment");
+            err.span_label(last.span, "this doc comment doesn't document anything");
+            err
+        } else {
+            self.struct_span_err(last.span, "expected item after attributes")
         };
-
-        let mut err = self.diagnostic().struct_span_err(self.prev_span, message);
-        if attrs.last().unwrap().is_sugared_doc {
-            err.span_label(self.prev_span, "this doc comment doesn't document anything");
+        for attr in others {
+            err.span_label(attr.span, "other attributes here");
         }
-        Err(err)
+        err.note("attributes and doc comments apply to the item, statement, expression or \
+                  field right after them");
+        err
     }
 
     pub(super) fn is_async_fn(&self) -> bool {
@@ -697,7 +698,7 @@ impl<'a> Parser<'a> {
             None
         };
 
-        generics.where_clause = self.parse_where_clause()?;
+        self.parse_where_clause_into(&mut generics)?;
 
         let (impl_items, attrs) = self.parse_impl_body()?;
 
@@ -750,7 +751,7 @@ impl<'a> Parser<'a> {
                 Err(mut err) => {
                     err.emit();
                     if !at_end {
-                        self.recover_stmt_(SemiColonMode::Break, BlockMode::Break);
+                        self.recover_stmt_in_braces(SemiColonMode::Break, BlockMode::Break);
                     }
                 }
             }
@@ -770,10 +771,7 @@ impl<'a> Parser<'a> {
         })?;
         self.unclosed_delims.append(&mut unclosed_delims);
 
-        // See `parse_item` for why this clause is here.
-        if !item.attrs.iter().any(|attr| attr.style == AttrStyle::Inner) {
-            item.tokens = Some(tokens);
-        }
+        item.tokens = Some(tokens);
         Ok(item)
     }
 
@@ -836,7 +834,7 @@ impl<'a> Parser<'a> {
             ])
         {
             self.bump(); // `default`
-            Defaultness::Default
+            Defaultness::Default(self.prev_span)
         } else {
             Defaultness::Final
         }
@@ -861,10 +859,11 @@ impl<'a> Parser<'a> {
             let (constness, unsafety, asyncness, abi) = self.parse_fn_front_matter()?;
             let ident = self.parse_ident()?;
             let mut generics = self.parse_generics()?;
-            let decl = self.parse_fn_decl_with_self(|p| {
+            let mut decl = self.parse_fn_decl_with_self(|p| {
                 p.parse_param_general(true, false, |_| true)
             })?;
-            generics.where_clause = self.parse_where_clause()?;
+            self.parse_where_clause_into(&mut generics)?;
+            self.recover_ret_ty_after_where_clause(&mut decl, &generics.where_clause)?;
             *at_end = true;
             let (inner_attrs, body) = self.parse_inner_attrs_and_block()?;
             let header = ast::FnHeader { abi, unsafety, constness, asyncness };
@@ -933,7 +932,7 @@ impl<'a> Parser<'a> {
         if self.eat(&token::Eq) {
             // It's a trait alias.
             let bounds = self.parse_generic_bounds(None)?;
-            tps.where_clause = self.parse_where_clause()?;
+            self.parse_where_clause_into(&mut tps)?;
             self.expect(&token::Semi)?;
             if is_auto == IsAuto::Yes {
                 let msg = "trait aliases cannot be `auto`";
@@ -950,7 +949,7 @@ impl<'a> Parser<'a> {
             Ok((ident, ItemKind::TraitAlias(tps, bounds), None))
         } else {
             // It's a normal trait.
-            tps.where_clause = self.parse_where_clause()?;
+            self.parse_where_clause_into(&mut tps)?;
             self.expect(&token::OpenDelim(token::Brace))?;
             let mut trait_items = vec![];
             while !self.eat(&token::CloseDelim(token::Brace)) {
@@ -977,7 +976,7 @@ impl<'a> Parser<'a> {
                     Err(mut e) => {
                         e.emit();
                         if !at_end {
-                            self.recover_stmt_(SemiColonMode::Break, BlockMode::Break);
+                            self.recover_stmt_in_braces(SemiColonMode::Break, BlockMode::Break);
                         }
                     }
                 }
@@ -997,10 +996,7 @@ impl<'a> Parser<'a> {
             item
         })?;
         self.unclosed_delims.append(&mut unclosed_delims);
-        // See `parse_item` for why this clause is here.
-        if !item.attrs.iter().any(|attr| attr.style == AttrStyle::Inner) {
-            item.tokens = Some(tokens);
-        }
+        item.tokens = Some(tokens);
         Ok(item)
     }
 
@@ -1034,7 +1030,7 @@ impl<'a> Parser<'a> {
             let ident = self.parse_ident()?;
             let mut generics = self.parse_generics()?;
 
-            let decl = self.parse_fn_decl_with_self(|p: &mut Parser<'a>| {
+            let mut decl = self.parse_fn_decl_with_self(|p: &mut Parser<'a>| {
                 // This is somewhat dubious; We don't want to allow
                 // argument names to be left off if there is a
                 // definition...
@@ -1043,7 +1039,8 @@ impl<'a> Parser<'a> {
                 let is_name_required = p.token.span.rust_2018();
                 p.parse_param_general(true, false, |_| is_name_required)
             })?;
-            generics.where_clause = self.parse_where_clause()?;
+            self.parse_where_clause_into(&mut generics)?;
+            self.recover_ret_ty_after_where_clause(&mut decl, &generics.where_clause)?;
 
             let sig = ast::MethodSig {
                 header: FnHeader {
@@ -1114,7 +1111,7 @@ impl<'a> Parser<'a> {
         } else {
             Vec::new()
         };
-        generics.where_clause = self.parse_where_clause()?;
+        self.parse_where_clause_into(&mut generics)?;
 
         let default = if self.eat(&token::Eq) {
             Some(self.parse_ty()?)
@@ -1276,8 +1273,9 @@ impl<'a> Parser<'a> {
     ) -> PResult<'a, ItemInfo> {
         let (ident, mut generics) = self.parse_fn_header()?;
         let allow_c_variadic = abi == Abi::C && unsafety == Unsafety::Unsafe;
-        let decl = self.parse_fn_decl(allow_c_variadic)?;
-        generics.where_clause = self.parse_where_clause()?;
+        let mut decl = self.parse_fn_decl(allow_c_variadic)?;
+        self.parse_where_clause_into(&mut generics)?;
+        self.recover_ret_ty_after_where_clause(&mut decl, &generics.where_clause)?;
         let (inner_attrs, body) = self.parse_inner_attrs_and_block()?;
         let header = FnHeader { unsafety, asyncness, constness, abi };
         Ok((ident, ItemKind::Fn(decl, header, generics, body), Some(inner_attrs)))
@@ -1341,6 +1339,45 @@ impl<'a> Parser<'a> {
         Ok(self.mk_item(lo.to(prev_span), invalid, ItemKind::ForeignMod(m), visibility, attrs))
     }
 
+    /// Recovers from an extern type declared outside of an `extern` block, like
+    /// `extern type Foo;`, by parsing it as if it were declared in one.
+    ///
+    /// `extern` and the optional ABI are expected to have been consumed before calling this
+    /// method.
+    fn recover_item_extern_type(
+        &mut self,
+        lo: Span,
+        opt_abi: Option<Abi>,
+        vis: Visibility,
+        attrs: Vec<Attribute>,
+    ) -> PResult<'a, P<Item>> {
+        let type_lo = self.token.span;
+        let foreign_item = self.parse_item_foreign_type(vis, type_lo, Vec::new())?;
+        let span = lo.to(self.prev_span);
+        let abi = opt_abi.map_or(String::new(), |abi| format!("{} ", abi));
+        let suggestion = format!(
+            "extern {}{{ {}type {}; }}",
+            abi,
+            pprust::vis_to_string(&foreign_item.vis),
+            foreign_item.ident,
+        );
+        self.struct_span_err(span, "extern types must be declared in an `extern` block")
+            .span_suggestion(
+                span,
+                "declare the type in an `extern` block",
+                suggestion,
+                Applicability::MachineApplicable,
+            )
+            .emit();
+
+        let m = ast::ForeignMod {
+            abi: opt_abi.unwrap_or(Abi::C),
+            items: vec![foreign_item],
+        };
+        let vis = respan(lo.shrink_to_lo(), VisibilityKind::Inherited);
+        Ok(self.mk_item(span, Ident::invalid(), ItemKind::ForeignMod(m), vis, attrs))
+    }
+
     /// Parses a foreign item.
     crate fn parse_foreign_item(&mut self, extern_sp: Span) -> PResult<'a, ForeignItem> {
         maybe_whole!(self, NtForeignItem, |ni| ni);
@@ -1389,7 +1426,7 @@ impl<'a> Parser<'a> {
             }
             None => {
                 if !attrs.is_empty()  {
-                    self.expected_item_err(&attrs)?;
+                    return Err(self.expected_item_err(&attrs));
                 }
 
                 self.unexpected()
@@ -1408,8 +1445,9 @@ impl<'a> Parser<'a> {
         self.expect_keyword(kw::Fn)?;
 
         let (ident, mut generics) = self.parse_fn_header()?;
-        let decl = self.parse_fn_decl(true)?;
-        generics.where_clause = self.parse_where_clause()?;
+        let mut decl = self.parse_fn_decl(true)?;
+        self.parse_where_clause_into(&mut generics)?;
+        self.recover_ret_ty_after_where_clause(&mut decl, &generics.where_clause)?;
         let hi = self.token.span;
         self.parse_semi_or_incorrect_foreign_fn_body(&ident, extern_sp)?;
         Ok(ast::ForeignItem {
@@ -1448,6 +1486,29 @@ impl<'a> Parser<'a> {
         self.expect_keyword(kw::Type)?;
 
         let ident = self.parse_ident()?;
+        // Extern types are opaque, recover from generics and definitions given to them as if
+        // they were type aliases.
+        if self.token == token::Lt {
+            let generics = self.parse_generics()?;
+            self.struct_span_err(generics.span, "extern types cannot have generic parameters")
+                .span_suggestion(
+                    generics.span,
+                    "remove the generic parameters",
+                    String::new(),
+                    Applicability::MaybeIncorrect,
+                )
+                .emit();
+        }
+        if self.token == token::Eq {
+            let eq_span = self.token.span;
+            self.bump();
+            let ty = self.parse_ty()?;
+            let span = eq_span.to(ty.span);
+            self.struct_span_err(span, "extern types cannot have a definition")
+                .span_label(span, "extern types are defined by foreign code")
+                .help("type aliases must be declared outside of `extern` blocks")
+                .emit();
+        }
         let hi = self.token.span;
         self.expect(&token::Semi)?;
         Ok(ast::ForeignItem {
@@ -1507,7 +1568,7 @@ impl<'a> Parser<'a> {
     fn parse_type_alias(&mut self) -> PResult<'a, (Ident, AliasKind, Generics)> {
         let ident = self.parse_ident()?;
         let mut tps = self.parse_generics()?;
-        tps.where_clause = self.parse_where_clause()?;
+        self.parse_where_clause_into(&mut tps)?;
         self.expect(&token::Eq)?;
         let alias = if self.check_keyword(kw::Impl) {
             self.bump();
@@ -1525,7 +1586,7 @@ impl<'a> Parser<'a> {
     fn parse_item_enum(&mut self) -> PResult<'a, ItemInfo> {
         let id = self.parse_ident()?;
         let mut generics = self.parse_generics()?;
-        generics.where_clause = self.parse_where_clause()?;
+        self.parse_where_clause_into(&mut generics)?;
         self.expect(&token::OpenDelim(token::Brace))?;
 
         let enum_definition = self.parse_enum_def(&generics).map_err(|e| {
@@ -1621,7 +1682,7 @@ impl<'a> Parser<'a> {
         // struct.
 
         let vdata = if self.token.is_keyword(kw::Where) {
-            generics.where_clause = self.parse_where_clause()?;
+            self.parse_where_clause_into(&mut generics)?;
             if self.eat(&token::Semi) {
                 // If we see a: `struct Foo<T> where T: Copy;` style decl.
                 VariantData::Unit(DUMMY_NODE_ID)
@@ -1631,16 +1692,19 @@ impl<'a> Parser<'a> {
                 VariantData::Struct(fields, recovered)
             }
         // No `where` so: `struct Foo<T>;`
-        } else if self.eat(&token::Semi) {
+        } else if self.check(&token::Semi) {
+            self.parse_where_clause_into(&mut generics)?;
+            self.bump();
             VariantData::Unit(DUMMY_NODE_ID)
         // Record-style struct definition
         } else if self.token == token::OpenDelim(token::Brace) {
+            self.parse_where_clause_into(&mut generics)?;
             let (fields, recovered) = self.parse_record_struct_body()?;
             VariantData::Struct(fields, recovered)
         // Tuple-style struct definition with optional where-clause.
         } else if self.token == token::OpenDelim(token::Paren) {
             let body = VariantData::Tuple(self.parse_tuple_struct_body()?, DUMMY_NODE_ID);
-            generics.where_clause = self.parse_where_clause()?;
+            self.parse_where_clause_into(&mut generics)?;
             self.expect(&token::Semi)?;
             body
         } else {
@@ -1663,10 +1727,11 @@ impl<'a> Parser<'a> {
         let mut generics = self.parse_generics()?;
 
         let vdata = if self.token.is_keyword(kw::Where) {
-            generics.where_clause = self.parse_where_clause()?;
+            self.parse_where_clause_into(&mut generics)?;
             let (fields, recovered) = self.parse_record_struct_body()?;
             VariantData::Struct(fields, recovered)
         } else if self.token == token::OpenDelim(token::Brace) {
+            self.parse_where_clause_into(&mut generics)?;
             let (fields, recovered) = self.parse_record_struct_body()?;
             VariantData::Struct(fields, recovered)
         } else {
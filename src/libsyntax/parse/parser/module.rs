@@ -9,6 +9,7 @@ use crate::parse::diagnostics::{Error};
 use crate::source_map::{SourceMap, Span, DUMMY_SP, FileName};
 use crate::symbol::sym;
 
+use std::mem;
 use std::path::{self, Path, PathBuf};
 
 /// Information about the path to a module.
@@ -28,9 +29,12 @@ impl<'a> Parser<'a> {
     /// Parses a source module as a crate. This is the main entry point for the parser.
     pub fn parse_crate_mod(&mut self) -> PResult<'a, Crate> {
         let lo = self.token.span;
+        let mut attrs = self.parse_inner_attributes()?;
+        let first_item_attrs = self.parse_outer_attributes()?;
+        let first_item_attrs = self.recover_crate_level_outer_attrs(&mut attrs, first_item_attrs);
         let krate = Ok(ast::Crate {
-            attrs: self.parse_inner_attributes()?,
-            module: self.parse_mod_items(&token::Eof, lo)?,
+            attrs,
+            module: self.parse_mod_items(&token::Eof, lo, first_item_attrs)?,
             span: lo.to(self.token.span),
         });
         krate
@@ -69,7 +73,8 @@ impl<'a> Parser<'a> {
                 let placeholder = ast::Mod {
                     inner: DUMMY_SP,
                     items: Vec::new(),
-                    inline: false
+                    inline: false,
+                    dangling_attrs: Vec::new(),
                 };
                 Ok((id, ItemKind::Mod(placeholder), None))
             }
@@ -80,19 +85,40 @@ impl<'a> Parser<'a> {
             self.expect(&token::OpenDelim(token::Brace))?;
             let mod_inner_lo = self.token.span;
             let attrs = self.parse_inner_attributes()?;
-            let module = self.parse_mod_items(&token::CloseDelim(token::Brace), mod_inner_lo)?;
+            let module =
+                self.parse_mod_items(&token::CloseDelim(token::Brace), mod_inner_lo, vec![])?;
 
             self.directory = old_directory;
             Ok((id, ItemKind::Mod(module), Some(attrs)))
         }
     }
 
-    /// Given a termination token, parses all of the items in a module.
-    fn parse_mod_items(&mut self, term: &TokenKind, inner_lo: Span) -> PResult<'a, Mod> {
+    /// Given a termination token, parses all of the items in a module. `first_item_attrs` are
+    /// the outer attributes of the first item that were already parsed, if any.
+    fn parse_mod_items(
+        &mut self,
+        term: &TokenKind,
+        inner_lo: Span,
+        mut first_item_attrs: Vec<Attribute>,
+    ) -> PResult<'a, Mod> {
         let mut items = vec![];
-        while let Some(item) = self.parse_item()? {
-            items.push(item);
-            self.maybe_consume_incorrect_semicolon(&items);
+        let mut dangling_attrs = vec![];
+        loop {
+            let mut attrs = mem::take(&mut first_item_attrs);
+            attrs.extend(self.parse_outer_attributes()?);
+            if !attrs.is_empty() && self.token == *term {
+                // Nothing follows the attributes, so they're kept aside and parsing goes on.
+                self.expected_item_err(&attrs).emit();
+                dangling_attrs = attrs;
+                break;
+            }
+            match self.recording_recoveries(|this| this.parse_item_(attrs, true, false))? {
+                Some(item) => {
+                    items.push(item);
+                    self.maybe_consume_incorrect_semicolon(&items);
+                }
+                None => break,
+            }
         }
 
         if !self.eat(term) {
@@ -113,7 +139,8 @@ impl<'a> Parser<'a> {
         Ok(Mod {
             inner: inner_lo.to(hi),
             items,
-            inline: true
+            inline: true,
+            dangling_attrs,
         })
     }
 
@@ -304,8 +331,9 @@ impl<'a> Parser<'a> {
         p0.cfg_mods = self.cfg_mods;
         let mod_inner_lo = p0.token.span;
         let mod_attrs = p0.parse_inner_attributes()?;
-        let mut m0 = p0.parse_mod_items(&token::Eof, mod_inner_lo)?;
+        let mut m0 = p0.parse_mod_items(&token::Eof, mod_inner_lo, vec![])?;
         m0.inline = false;
+        self.recovery_events.append(&mut p0.recovery_events);
         self.sess.included_mod_stack.borrow_mut().pop();
         Ok((m0, mod_attrs))
     }
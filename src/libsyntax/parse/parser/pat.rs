@@ -8,7 +8,7 @@ use crate::mut_visit::{noop_visit_pat, MutVisitor};
 use crate::parse::token::{self};
 use crate::print::pprust;
 use crate::source_map::{respan, Span, Spanned};
-use crate::symbol::kw;
+use crate::symbol::{kw, sym};
 use crate::ThinVec;
 
 use errors::{Applicability, DiagnosticBuilder};
@@ -51,9 +51,8 @@ impl<'a> Parser<'a> {
         // then we should really gate the leading `|`.
         // This complicated procedure is done purely for diagnostics UX.
         if gated_leading_vert {
-            let mut or_pattern_spans = self.sess.gated_spans.or_patterns.borrow_mut();
-            if or_pattern_spans.is_empty() {
-                or_pattern_spans.push(leading_vert_span);
+            if self.sess.gated_spans.is_ungated(sym::or_patterns) {
+                self.sess.gated_spans.gate(sym::or_patterns, leading_vert_span);
             }
         }
 
@@ -114,7 +113,7 @@ impl<'a> Parser<'a> {
 
         // Feature gate the or-pattern if instructed:
         if gate_or == GateOr::Yes {
-            self.sess.gated_spans.or_patterns.borrow_mut().push(or_pattern_span);
+            self.sess.gated_spans.gate(sym::or_patterns, or_pattern_span);
         }
 
         Ok(self.mk_pat(or_pattern_span, PatKind::Or(pats)))
@@ -314,6 +313,7 @@ impl<'a> Parser<'a> {
 
         let pat = self.mk_pat(lo.to(self.prev_span), pat);
         let pat = self.maybe_recover_from_bad_qpath(pat, true)?;
+        self.gate_pat(&pat);
 
         if !allow_range_pat {
             self.ban_pat_range_if_ambiguous(&pat)?
@@ -322,6 +322,19 @@ impl<'a> Parser<'a> {
         Ok(pat)
     }
 
+    /// Feature gates `pat` if it is of an unstable form. Nested patterns are gated when they
+    /// are parsed, so only the outermost form needs to be looked at.
+    fn gate_pat(&self, pat: &Pat) {
+        let feature = match pat.node {
+            PatKind::Box(..) => sym::box_patterns,
+            PatKind::Range(.., Spanned { node: RangeEnd::Excluded, .. }) => {
+                sym::exclusive_range_pattern
+            }
+            _ => return,
+        };
+        self.sess.gated_spans.gate(feature, pat.span);
+    }
+
     /// Ban a range pattern if it has an ambiguous interpretation.
     fn ban_pat_range_if_ambiguous(&self, pat: &Pat) -> PResult<'a, ()> {
         match pat.node {
@@ -868,7 +881,9 @@ impl<'a> Parser<'a> {
 
             let fieldpat = self.mk_pat_ident(boxed_span.to(hi), bind_type, fieldname);
             let subpat = if is_box {
-                self.mk_pat(lo.to(hi), PatKind::Box(fieldpat))
+                let subpat = self.mk_pat(lo.to(hi), PatKind::Box(fieldpat));
+                self.gate_pat(&subpat);
+                subpat
             } else {
                 fieldpat
             };
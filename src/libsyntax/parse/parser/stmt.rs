@@ -71,7 +71,8 @@ impl<'a> Parser<'a> {
                   !self.is_async_fn() {
             let path = self.parse_path(PathStyle::Expr)?;
 
-            if !self.eat(&token::Not) {
+            let bang = self.eat(&token::Not);
+            if !bang && !self.is_call_of_known_macro(&path) {
                 let expr = if self.check(&token::OpenDelim(token::Brace)) {
                     self.parse_struct_expr(lo, path, ThinVec::new())?
                 } else {
@@ -91,7 +92,13 @@ impl<'a> Parser<'a> {
                 }));
             }
 
-            let (delim, tts) = self.expect_delimited_token_tree()?;
+            let (delim, tts) = if bang {
+                let (delim, tts) = self.expect_delimited_token_tree()?;
+                self.check_macro_delimiter(&path, delim, self.prev_span);
+                (delim, tts)
+            } else {
+                self.recover_missing_macro_bang(&path)?
+            };
             let hi = self.prev_span;
 
             let style = if delim == MacDelimiter::Brace {
@@ -394,18 +401,18 @@ impl<'a> Parser<'a> {
             if self.token == token::Eof {
                 break;
             }
-            let stmt = match self.parse_full_stmt(false) {
+            let stmt = self.recording_recoveries(|this| match this.parse_full_stmt(false) {
                 Err(mut err) => {
                     err.emit();
-                    self.recover_stmt_(SemiColonMode::Ignore, BlockMode::Ignore);
+                    this.recover_stmt();
                     Some(Stmt {
                         id: DUMMY_NODE_ID,
-                        node: StmtKind::Expr(DummyResult::raw_expr(self.token.span, true)),
-                        span: self.token.span,
+                        node: StmtKind::Expr(DummyResult::raw_expr(this.token.span, true)),
+                        span: this.token.span,
                     })
                 }
                 Ok(stmt) => stmt,
-            };
+            });
             if let Some(stmt) = stmt {
                 stmts.push(stmt);
             } else {
@@ -194,13 +194,19 @@ impl<'a> Parser<'a> {
                 }
             }
         } else if self.check(&token::DotDotDot) {
+            let span = self.token.span;
+            self.eat(&token::DotDotDot);
             if allow_c_variadic {
-                self.eat(&token::DotDotDot);
                 TyKind::CVarArgs
             } else {
-                return Err(self.fatal(
-                    "only foreign functions are allowed to be C-variadic"
-                ));
+                // Recover with an erroneous type, so that the parameter is kept.
+                self.struct_span_err(
+                    span,
+                    "only foreign or `unsafe extern \"C\"` functions may be C-variadic",
+                )
+                .span_label(span, "C-variadic parameter not allowed here")
+                .emit();
+                TyKind::Err
             }
         } else {
             let msg = format!("expected type, found {}", self.this_token_descr());
@@ -4,6 +4,7 @@ use crate::ast::{self, Name, PatKind};
 use crate::attr::first_attr_value_str_by_name;
 use crate::parse::{ParseSess, PResult};
 use crate::parse::new_parser_from_source_str;
+use crate::parse::parser::RecoveryKind;
 use crate::parse::token::Token;
 use crate::print::pprust::item_to_string;
 use crate::ptr::P;
@@ -11,7 +12,9 @@ use crate::source_map::FilePathMapping;
 use crate::symbol::{kw, sym};
 use crate::tests::{matches_codepattern, string_to_stream, with_error_checking_parse};
 use crate::tokenstream::{DelimSpan, TokenTree, TokenStream};
-use crate::with_default_globals;
+use crate::{panictry, with_default_globals};
+use errors::test_emitter::{TestEmitter, TestOutput};
+use errors::HandlerFlags;
 use syntax_pos::{Span, BytePos, Pos};
 
 use std::path::PathBuf;
@@ -307,6 +310,215 @@ fn out_of_line_mod() {
     });
 }
 
+#[test]
+fn stmts_chunks_get_own_source_files() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let first = parse_stmts_from_source_str(
+            FileName::Custom("chunk_1".to_string()),
+            "fn f() -> i32 { 1 } let x = f();".to_string(),
+            &sess,
+        ).unwrap();
+        let second = parse_stmts_from_source_str(
+            FileName::Custom("chunk_2".to_string()),
+            "x + 1".to_string(),
+            &sess,
+        ).unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(second.len(), 1);
+
+        let loc = sess.source_map().lookup_char_pos(second[0].span.lo());
+        assert_eq!(loc.file.name, FileName::Custom("chunk_2".to_string()));
+        assert_eq!(loc.line, 1);
+        assert_eq!(loc.col.0, 0);
+    });
+}
+
+#[test]
+fn stmts_chunk_stray_close_brace() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let res = parse_stmts_from_source_str(
+            FileName::Custom("chunk".to_string()),
+            "let x = 1; }".to_string(),
+            &sess,
+        );
+        match res {
+            Err(mut err) => err.cancel(),
+            Ok(_) => panic!("expected an error for a stray `}`"),
+        }
+    });
+}
+
+/// Creates a session capturing its diagnostics.
+fn captured_sess() -> (ParseSess, TestOutput) {
+    let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+    let emitter = TestEmitter::new(Some(source_map.clone()));
+    let output = emitter.output();
+    let handler = Handler::with_emitter(true, None, Box::new(emitter));
+    (ParseSess::with_span_handler(handler, source_map), output)
+}
+
+#[test]
+fn parse_and_expand_expr_expands_registered_macros() {
+    with_default_globals(|| {
+        let (sess, output) = captured_sess();
+        sess.register_crate_root_items(
+            FileName::Custom("prelude".to_string()),
+            "macro_rules! double { ($e:expr) => { 2 * $e } }
+             macro_rules! quadruple { ($e:expr) => { double!(double!($e)) } }".to_string(),
+        );
+
+        let expr = parse_and_expand_expr("1 + quadruple!(x)", &sess).unwrap();
+        assert_eq!(pprust::expr_to_string(&expr), "1 + 2 * (2 * x)");
+        let loc = sess.source_map().lookup_char_pos(expr.span.lo());
+        assert_eq!(loc.file.src.as_ref().map(|src| &src[..]), Some("1 + quadruple!(x)"));
+        assert_eq!(loc.col.0, 0);
+
+        // The registered macros are still there for the next expression.
+        let expr = parse_and_expand_expr("double!(y)", &sess).unwrap();
+        assert_eq!(pprust::expr_to_string(&expr), "2 * y");
+        assert!(output.diagnostics().is_empty());
+
+        let expr = parse_and_expand_expr("triple!(z)", &sess).unwrap();
+        assert!(match expr.node { ast::ExprKind::Err => true, _ => false });
+        assert!(output.rendered().contains("cannot find macro `triple!` in this scope"));
+    })
+}
+
+#[test]
+fn parse_and_expand_expr_returns_parse_errors() {
+    with_default_globals(|| {
+        let (sess, output) = captured_sess();
+        let diagnostics = parse_and_expand_expr("1 +", &sess).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message().contains("expected expression"));
+
+        // Tokens left over after the expression.
+        let diagnostics = parse_and_expand_expr("f(x) y", &sess).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message().contains("found `y`"));
+        assert!(output.diagnostics().is_empty());
+    })
+}
+
+/// Parses `source` as an expression in a session whose macro registry knows `println` and
+/// `vec`, returning the expression and the captured diagnostics.
+fn parse_expr_with_macro_registry(source: &str) -> (P<ast::Expr>, TestOutput) {
+    let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+    let emitter = TestEmitter::new(Some(source_map.clone()));
+    let output = emitter.output();
+    let handler = Handler::with_emitter(true, None, Box::new(emitter));
+    let sess = ParseSess::with_span_handler(handler, source_map);
+    sess.set_macro_registry(Lrc::new(|name: Symbol| match &*name.as_str() {
+        "println" => Some(ast::MacDelimiter::Parenthesis),
+        "vec" => Some(ast::MacDelimiter::Bracket),
+        _ => None,
+    }));
+    let name = PathBuf::from("test").into();
+    let mut parser = new_parser_from_source_str(&sess, name, source.to_string());
+    let expr = panictry!(parser.parse_expr());
+    (expr, output)
+}
+
+#[test]
+fn recover_missing_macro_bang() {
+    with_default_globals(|| {
+        let (expr, output) = parse_expr_with_macro_registry("println(\"{}\", x)");
+        match expr.node {
+            ast::ExprKind::Mac(ref mac) => assert_eq!(mac.delim, ast::MacDelimiter::Parenthesis),
+            _ => panic!("expected a macro invocation"),
+        }
+        assert!(output.rendered().contains("macro `println` invoked without `!`"));
+        output.assert_suggestion_applies_to("println!(\"{}\", x)");
+    })
+}
+
+#[test]
+fn calls_of_unknown_names_are_calls() {
+    with_default_globals(|| {
+        let (expr, output) = parse_expr_with_macro_registry("print(\"x\") + std::println(y)");
+        match expr.node {
+            ast::ExprKind::Binary(_, ref lhs, ref rhs) => {
+                assert!(match (&lhs.node, &rhs.node) {
+                    (ast::ExprKind::Call(..), ast::ExprKind::Call(..)) => true,
+                    _ => false,
+                });
+            }
+            _ => panic!("expected a binary expression"),
+        }
+        assert!(output.diagnostics().is_empty());
+    })
+}
+
+#[test]
+fn unconventional_macro_delimiters() {
+    with_default_globals(|| {
+        let (_, output) = parse_expr_with_macro_registry("vec!(1, 2)");
+        let rendered = output.rendered();
+        assert!(rendered.contains("`vec!` is conventionally invoked with square brackets"));
+        output.assert_suggestion_applies_to("vec![1, 2]");
+
+        let (_, output) = parse_expr_with_macro_registry("vec![1, 2]");
+        assert!(output.diagnostics().is_empty());
+    })
+}
+
+/// Parses `source` as a crate with a handler in error-as-value mode.
+fn try_parse_crate(source: &str) -> (Result<ast::Crate, ErrorReported>, TestOutput) {
+    let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+    let emitter = TestEmitter::new(Some(source_map.clone()));
+    let output = emitter.output();
+    let flags = HandlerFlags { errors_as_values: true, .. Default::default() };
+    let handler = Handler::with_emitter_and_flags(Box::new(emitter), flags);
+    let sess = ParseSess::with_span_handler(handler, source_map);
+    let name = PathBuf::from("test").into();
+    (try_parse_crate_from_source_str(name, source.to_string(), &sess), output)
+}
+
+#[test]
+fn errors_as_values() {
+    with_default_globals(|| {
+        let (krate, output) = try_parse_crate("fn f() {}");
+        assert_eq!(krate.unwrap().module.items.len(), 1);
+        assert!(output.diagnostics().is_empty());
+
+        // A parse error.
+        let (krate, output) = try_parse_crate("fn");
+        assert_eq!(krate.unwrap_err(), ErrorReported);
+        assert!(output.diagnostics().iter().any(|captured| captured.diagnostic.is_error()));
+
+        // An error from lexing the initial token stream.
+        let (krate, output) = try_parse_crate("fn f() )");
+        assert_eq!(krate.unwrap_err(), ErrorReported);
+        assert!(output.rendered().contains("unexpected close delimiter: `)`"));
+
+        // A fatal error raised by the lexer itself.
+        let (krate, output) = try_parse_crate("fn f() { r#\"x }");
+        assert_eq!(krate.unwrap_err(), ErrorReported);
+        assert!(output.rendered().contains("unterminated raw string"));
+    })
+}
+
+#[test]
+fn dangling_attrs() {
+    with_default_globals(|| {
+        let (krate, output) = try_parse_crate("mod m {\n    fn f() {}\n    #[inline]\n}\n#[cold]");
+        let module = krate.unwrap().module;
+        assert_eq!(module.items.len(), 1);
+        assert!(module.dangling_attrs.len() == 1 && module.dangling_attrs[0].path == sym::cold);
+        match module.items[0].node {
+            ast::ItemKind::Mod(ref m) => {
+                assert_eq!(m.items.len(), 1);
+                assert!(m.dangling_attrs.len() == 1 && m.dangling_attrs[0].path == sym::inline);
+            }
+            _ => panic!("expected a module"),
+        }
+        assert_eq!(output.diagnostics().len(), 2);
+        assert!(output.rendered().contains("expected item after attributes"));
+    })
+}
+
 #[test]
 fn eqmodws() {
     assert_eq!(matches_codepattern("",""),true);
@@ -337,3 +549,48 @@ fn non_pattern_whitespace() {
     assert_eq!(matches_codepattern("\u{205F}a   b","ab"), false);
     assert_eq!(matches_codepattern("a  \u{3000}b","ab"), false);
 }
+
+/// Parses `source` as a crate, returning the recoveries the parser made and the text of
+/// their spans.
+fn recovery_events(source: &str) -> Vec<(RecoveryKind, String, Option<String>)> {
+    with_default_globals(|| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let emitter = TestEmitter::new(Some(source_map.clone()));
+        let handler = Handler::with_emitter_and_flags(Box::new(emitter), Default::default());
+        let sess = ParseSess::with_span_handler(handler, source_map);
+        let mut parser =
+            new_parser_from_source_str(&sess, PathBuf::from("test").into(), source.to_string());
+        let unclosed_delims = parser.unclosed_delims().len();
+        if let Err(mut err) = parser.parse_crate_mod() {
+            err.cancel();
+        }
+        assert!(parser.unclosed_delims().len() <= unclosed_delims);
+        parser.recovery_events().iter().map(|event| {
+            let snippet = sess.source_map().span_to_snippet(event.span).unwrap();
+            (event.kind, snippet, event.fix.clone())
+        }).collect()
+    })
+}
+
+#[test]
+fn recovery_events_are_recorded() {
+    assert!(recovery_events("fn f() {}\nstruct S {}").is_empty());
+    assert_eq!(
+        recovery_events("struct S {};\nfn f() {}"),
+        vec![(RecoveryKind::IgnoredToken, ";".to_string(), Some(String::new()))],
+    );
+    let events = recovery_events("fn f() {\n    g(1;\n}\n");
+    assert_eq!(events[0], (RecoveryKind::ClosedDelimiter, String::new(), Some(")".to_string())));
+    let events = recovery_events("fn f() {\n    let = 1;\n}\n");
+    assert!(events.iter().any(|event| event.0 == RecoveryKind::SkippedTokens));
+    assert!(events.iter().all(|event| event.0 != RecoveryKind::Other));
+}
+
+#[test]
+fn recovery_events_are_recorded_for_any_reported_error() {
+    // The cast is reparsed as a comparison, which isn't recorded where it's recovered from.
+    assert_eq!(
+        recovery_events("fn f() {\n    let _ = 1 as usize < 2;\n}\n"),
+        vec![(RecoveryKind::Other, "let _ = 1 as usize < 2;".to_string(), None)],
+    );
+}
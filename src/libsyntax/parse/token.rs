@@ -824,10 +824,8 @@ fn prepend_attrs(sess: &ParseSess,
         return Some(tokens.clone())
     }
     let mut builder = tokenstream::TokenStreamBuilder::new();
-    for attr in attrs {
-        assert_eq!(attr.style, ast::AttrStyle::Outer,
-                   "inner attributes should prevent cached tokens from existing");
-
+    // Inner attributes are part of the cached tokens already.
+    for attr in attrs.iter().filter(|attr| attr.style == ast::AttrStyle::Outer) {
         let source = pprust::attribute_to_string(attr);
         let macro_filename = FileName::macro_expansion_source_code(&source);
         if attr.is_sugared_doc {
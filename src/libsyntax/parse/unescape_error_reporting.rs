@@ -77,12 +77,25 @@ pub(crate) fn emit_unescape_error(
             } else {
                 "character constant must be escaped: \\r"
             };
-            handler.span_err(span, msg);
+            handler.struct_span_err(span, msg)
+                .span_suggestion(
+                    span,
+                    "escape the carriage return",
+                    "\\r".to_string(),
+                    Applicability::MachineApplicable,
+                ).emit();
         }
         EscapeError::BareCarriageReturnInRawString => {
             assert!(mode.in_double_quotes());
             let msg = "bare CR not allowed in raw string";
-            handler.span_err(span, msg);
+            // Raw strings can't escape it, and it may have been meant as a line ending.
+            handler.struct_span_err(span, msg)
+                .span_suggestion(
+                    span,
+                    "remove the carriage return",
+                    String::new(),
+                    Applicability::MaybeIncorrect,
+                ).emit();
         }
         EscapeError::InvalidEscape => {
             let (c, span) = last_char();
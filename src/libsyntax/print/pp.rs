@@ -132,16 +132,42 @@
 //!
 //! In this implementation (following the paper, again) the SCAN process is the
 //! methods called `Printer::scan_*`, and the 'PRINT' process is the
-//! method called `Printer::print`.
+//! method called `Printer::print`. The ring buffer shared by the two lives in
+//! the `ring` module.
+//!
+//! # Interface
+//!
+//! Users of the printer only deal with the convenience methods at the bottom of
+//! `Printer`, which feed the SCAN process:
+//!
+//! * `word` emits a string that is never broken.
+//! * `space`, `zerobreak`, `hardbreak` and `break_offset` emit break
+//!   opportunities, which are printed either as blanks or as a newline followed
+//!   by the indentation of the enclosing box.
+//! * `ibox`, `cbox` and `rbox` open a box with a given `BoxKind` and
+//!   indentation, `end` closes the innermost one. Every box must be closed.
+//! * `eof` flushes the remaining tokens and returns the output.
+//!
+//! The printer never emits trailing whitespace and only breaks lines at break
+//! tokens, so a line is longer than the margin only if there was no break
+//! opportunity to shorten it.
 
 use std::collections::VecDeque;
-use std::fmt;
+use std::{cmp, fmt};
 use std::borrow::Cow;
 use log::debug;
 
-/// How to break. Described in more detail in the module docs.
-#[derive(Clone, Copy, PartialEq)]
-pub enum Breaks {
+use self::ring::RingBuffer;
+
+mod ring;
+
+#[cfg(test)]
+mod tests;
+
+/// How the breaks of a box are printed once the box does not fit on the
+/// remaining line. Described in more detail in the module docs.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BoxKind {
     Consistent,
     Inconsistent,
 }
@@ -155,7 +181,16 @@ pub struct BreakToken {
 #[derive(Clone, Copy)]
 pub struct BeginToken {
     offset: isize,
-    breaks: Breaks
+    breaks: BoxKind
+}
+
+/// What a mark resolves to, see `Printer::mark`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkKind {
+    /// The offset the next word is printed at, after any line break and indentation.
+    Start,
+    /// The end of the output printed so far, without trailing whitespace.
+    End,
 }
 
 #[derive(Clone)]
@@ -168,6 +203,8 @@ pub enum Token {
     Begin(BeginToken),
     End,
     Eof,
+    /// A position in the output that is recorded without printing anything.
+    Mark(usize, MarkKind),
 }
 
 impl Token {
@@ -199,23 +236,18 @@ impl fmt::Display for Token {
             Token::Begin(_) => f.write_str("BEGIN"),
             Token::End => f.write_str("END"),
             Token::Eof => f.write_str("EOF"),
+            Token::Mark(mark, _) => write!(f, "MARK({})", mark),
         }
     }
 }
 
-fn buf_str(buf: &[BufEntry], left: usize, right: usize, lim: usize) -> String {
-    let n = buf.len();
-    let mut i = left;
-    let mut l = lim;
+fn buf_str(buf: &RingBuffer<BufEntry>, lim: usize) -> String {
     let mut s = String::from("[");
-    while i != right && l != 0 {
-        l -= 1;
-        if i != left {
+    for (n, entry) in buf.iter().take(lim).enumerate() {
+        if n != 0 {
             s.push_str(", ");
         }
-        s.push_str(&format!("{}={}", buf[i].size, &buf[i].token));
-        i += 1;
-        i %= n;
+        s.push_str(&format!("{}={}", entry.size, &entry.token));
     }
     s.push(']');
     s
@@ -224,7 +256,7 @@ fn buf_str(buf: &[BufEntry], left: usize, right: usize, lim: usize) -> String {
 #[derive(Copy, Clone)]
 enum PrintStackBreak {
     Fits,
-    Broken(Breaks),
+    Broken(BoxKind),
 }
 
 #[derive(Copy, Clone)]
@@ -236,41 +268,46 @@ struct PrintStackElem {
 const SIZE_INFINITY: isize = 0xffff;
 
 pub fn mk_printer() -> Printer {
-    let linewidth = 78;
+    mk_printer_with_margin(78)
+}
+
+/// Creates a printer constrained to lines of `linewidth` columns.
+pub fn mk_printer_with_margin(linewidth: usize) -> Printer {
     // Yes 55, it makes the ring buffers big enough to never fall behind.
     let n: usize = 55 * linewidth;
     debug!("mk_printer {}", linewidth);
     Printer {
         out: String::new(),
-        buf_max_len: n,
         margin: linewidth as isize,
         space: linewidth as isize,
-        left: 0,
-        right: 0,
-        // Initialize a single entry; advance_right() will extend it on demand
-        // up to `buf_max_len` elements.
-        buf: vec![BufEntry::default()],
+        buf: RingBuffer::new(n),
         left_total: 0,
         right_total: 0,
         scan_stack: VecDeque::new(),
         print_stack: Vec::new(),
-        pending_indentation: 0
+        pending_indentation: 0,
+        marks: Vec::new(),
+        pending_marks: Vec::new(),
+        single_line: false,
     }
 }
 
+/// Creates a printer that only breaks lines outside of all boxes, so that everything printed
+/// in a box takes a single line. Breaks in boxes are printed as a single blank, or none for
+/// `zerobreak`, and consecutive ones share their blank.
+pub fn mk_single_line_printer() -> Printer {
+    Printer { single_line: true, ..mk_printer() }
+}
+
 pub struct Printer {
     out: String,
-    buf_max_len: usize,
     /// Width of lines we're constrained to
     margin: isize,
     /// Number of spaces left on line
     space: isize,
-    /// Index of left side of input stream
-    left: usize,
-    /// Index of right side of input stream
-    right: usize,
-    /// Ring-buffer of tokens and calculated sizes
-    buf: Vec<BufEntry>,
+    /// Ring-buffer of tokens and calculated sizes, between the left and
+    /// right side of the input stream
+    buf: RingBuffer<BufEntry>,
     /// Running size of stream "...left"
     left_total: isize,
     /// Running size of stream "...right"
@@ -286,6 +323,12 @@ pub struct Printer {
     print_stack: Vec<PrintStackElem> ,
     /// Buffered indentation to avoid writing trailing whitespace
     pending_indentation: isize,
+    /// The output offsets of the marks, by their index.
+    marks: Vec<usize>,
+    /// Marks of kind `MarkKind::Start` waiting for the next word to be printed.
+    pending_marks: Vec<usize>,
+    /// Whether breaks in boxes are never printed as newlines, see `mk_single_line_printer`.
+    single_line: bool,
 }
 
 #[derive(Clone)]
@@ -302,12 +345,26 @@ impl Default for BufEntry {
 
 impl Printer {
     pub fn last_token(&self) -> Token {
-        self.buf[self.right].token.clone()
+        self.buf[self.last_token_index()].token.clone()
     }
 
     /// Be very careful with this!
     pub fn replace_last_token(&mut self, t: Token) {
-        self.buf[self.right].token = t;
+        let index = self.last_token_index();
+        self.buf[index].token = t;
+    }
+
+    /// The index in the buffer of the last token, not counting marks so that they don't
+    /// change how what follows them is printed.
+    fn last_token_index(&self) -> usize {
+        let mut index = self.buf.right();
+        while index != self.buf.left() {
+            match self.buf[index].token {
+                Token::Mark(..) => index = self.buf.prev(index),
+                _ => break,
+            }
+        }
+        index
     }
 
     fn scan_eof(&mut self) {
@@ -321,23 +378,22 @@ impl Printer {
         if self.scan_stack.is_empty() {
             self.left_total = 1;
             self.right_total = 1;
-            self.left = 0;
-            self.right = 0;
+            self.buf.reset();
         } else {
-            self.advance_right();
+            self.buf.advance_right();
         }
         debug!("pp Begin({})/buffer Vec<{},{}>",
-               b.offset, self.left, self.right);
+               b.offset, self.buf.left(), self.buf.right());
         self.scan_push(BufEntry { token: Token::Begin(b), size: -self.right_total });
     }
 
     fn scan_end(&mut self) {
         if self.scan_stack.is_empty() {
-            debug!("pp End/print Vec<{},{}>", self.left, self.right);
+            debug!("pp End/print Vec<{},{}>", self.buf.left(), self.buf.right());
             self.print_end();
         } else {
-            debug!("pp End/buffer Vec<{},{}>", self.left, self.right);
-            self.advance_right();
+            debug!("pp End/buffer Vec<{},{}>", self.buf.left(), self.buf.right());
+            self.buf.advance_right();
             self.scan_push(BufEntry { token: Token::End, size: -1 });
         }
     }
@@ -346,13 +402,12 @@ impl Printer {
         if self.scan_stack.is_empty() {
             self.left_total = 1;
             self.right_total = 1;
-            self.left = 0;
-            self.right = 0;
+            self.buf.reset();
         } else {
-            self.advance_right();
+            self.buf.advance_right();
         }
         debug!("pp Break({})/buffer Vec<{},{}>",
-               b.offset, self.left, self.right);
+               b.offset, self.buf.left(), self.buf.right());
         self.check_stack(0);
         self.scan_push(BufEntry { token: Token::Break(b), size: -self.right_total });
         self.right_total += b.blank_space;
@@ -361,41 +416,50 @@ impl Printer {
     fn scan_string(&mut self, s: Cow<'static, str>) {
         if self.scan_stack.is_empty() {
             debug!("pp String('{}')/print Vec<{},{}>",
-                   s, self.left, self.right);
+                   s, self.buf.left(), self.buf.right());
             self.print_string(s);
         } else {
             debug!("pp String('{}')/buffer Vec<{},{}>",
-                   s, self.left, self.right);
-            self.advance_right();
+                   s, self.buf.left(), self.buf.right());
+            self.buf.advance_right();
             let len = s.len() as isize;
-            self.buf[self.right] = BufEntry { token: Token::String(s), size: len };
+            *self.buf.last_mut() = BufEntry { token: Token::String(s), size: len };
             self.right_total += len;
             self.check_stream();
         }
     }
 
+    fn scan_mark(&mut self, mark: usize, kind: MarkKind) {
+        if self.scan_stack.is_empty() {
+            self.print_mark(mark, kind);
+        } else {
+            self.buf.advance_right();
+            *self.buf.last_mut() = BufEntry { token: Token::Mark(mark, kind), size: 0 };
+        }
+    }
+
     fn check_stream(&mut self) {
         debug!("check_stream Vec<{}, {}> with left_total={}, right_total={}",
-               self.left, self.right, self.left_total, self.right_total);
+               self.buf.left(), self.buf.right(), self.left_total, self.right_total);
         if self.right_total - self.left_total > self.space {
             debug!("scan window is {}, longer than space on line ({})",
                    self.right_total - self.left_total, self.space);
-            if Some(&self.left) == self.scan_stack.back() {
-                debug!("setting {} to infinity and popping", self.left);
+            if Some(&self.buf.left()) == self.scan_stack.back() {
+                debug!("setting {} to infinity and popping", self.buf.left());
                 let scanned = self.scan_pop_bottom();
                 self.buf[scanned].size = SIZE_INFINITY;
             }
             self.advance_left();
-            if self.left != self.right {
+            if self.buf.left() != self.buf.right() {
                 self.check_stream();
             }
         }
     }
 
     fn scan_push(&mut self, entry: BufEntry) {
-        debug!("scan_push {}", self.right);
-        self.buf[self.right] = entry;
-        self.scan_stack.push_front(self.right);
+        debug!("scan_push {}", self.buf.right());
+        *self.buf.last_mut() = entry;
+        self.scan_stack.push_front(self.buf.right());
     }
 
     fn scan_pop(&mut self) -> usize {
@@ -410,24 +474,14 @@ impl Printer {
         self.scan_stack.pop_back().unwrap()
     }
 
-    fn advance_right(&mut self) {
-        self.right += 1;
-        self.right %= self.buf_max_len;
-        // Extend the buf if necessary.
-        if self.right == self.buf.len() {
-            self.buf.push(BufEntry::default());
-        }
-        assert_ne!(self.right, self.left);
-    }
-
     fn advance_left(&mut self) {
-        debug!("advance_left Vec<{},{}>, sizeof({})={}", self.left, self.right,
-               self.left, self.buf[self.left].size);
+        debug!("advance_left Vec<{},{}>, sizeof({})={}", self.buf.left(), self.buf.right(),
+               self.buf.left(), self.buf.first().size);
 
-        let mut left_size = self.buf[self.left].size;
+        let mut left_size = self.buf.first().size;
 
         while left_size >= 0 {
-            let left = self.buf[self.left].token.clone();
+            let left = self.buf.first().token.clone();
 
             let len = match left {
                 Token::Break(b) => b.blank_space,
@@ -443,14 +497,13 @@ impl Printer {
 
             self.left_total += len;
 
-            if self.left == self.right {
+            if self.buf.left() == self.buf.right() {
                 break;
             }
 
-            self.left += 1;
-            self.left %= self.buf_max_len;
+            self.buf.advance_left();
 
-            left_size = self.buf[self.left].size;
+            left_size = self.buf.first().size;
         }
     }
 
@@ -499,7 +552,7 @@ impl Printer {
             Some(el) => *el,
             None => PrintStackElem {
                 offset: 0,
-                pbreak: PrintStackBreak::Broken(Breaks::Inconsistent)
+                pbreak: PrintStackBreak::Broken(BoxKind::Inconsistent)
             }
         }
     }
@@ -527,6 +580,13 @@ impl Printer {
     }
 
     fn print_break(&mut self, b: BreakToken, l: isize) {
+        if self.single_line && !self.print_stack.is_empty() {
+            debug!("print Break({}) on a single line", b.blank_space);
+            let blank_space = cmp::min(b.blank_space, 1);
+            self.space -= blank_space;
+            self.pending_indentation = cmp::max(self.pending_indentation, blank_space);
+            return;
+        }
         let top = self.get_top();
         match top.pbreak {
             PrintStackBreak::Fits => {
@@ -534,13 +594,13 @@ impl Printer {
                 self.space -= b.blank_space;
                 self.indent(b.blank_space);
             }
-            PrintStackBreak::Broken(Breaks::Consistent) => {
+            PrintStackBreak::Broken(BoxKind::Consistent) => {
                 debug!("print Break({}+{}) in consistent block",
                        top.offset, b.offset);
                 self.print_newline(top.offset + b.offset);
                 self.space = self.margin - (top.offset + b.offset);
             }
-            PrintStackBreak::Broken(Breaks::Inconsistent) => {
+            PrintStackBreak::Broken(BoxKind::Inconsistent) => {
                 if l > self.space {
                     debug!("print Break({}+{}) w/ newline in inconsistent",
                            top.offset, b.offset);
@@ -571,16 +631,23 @@ impl Printer {
         self.out.reserve(self.pending_indentation as usize);
         self.out.extend(std::iter::repeat(' ').take(self.pending_indentation as usize));
         self.pending_indentation = 0;
+        for mark in self.pending_marks.drain(..) {
+            self.marks[mark] = self.out.len();
+        }
         self.out.push_str(&s);
     }
 
+    fn print_mark(&mut self, mark: usize, kind: MarkKind) {
+        match kind {
+            MarkKind::Start => self.pending_marks.push(mark),
+            MarkKind::End => self.marks[mark] = self.out.trim_end().len(),
+        }
+    }
+
     fn print(&mut self, token: Token, l: isize) {
         debug!("print {} {} (remaining line space={})", token, l,
                self.space);
-        debug!("{}", buf_str(&self.buf,
-                             self.left,
-                             self.right,
-                             6));
+        debug!("{}", buf_str(&self.buf, 6));
         match token {
             Token::Begin(b) => self.print_begin(b, l),
             Token::End => self.print_end(),
@@ -591,13 +658,14 @@ impl Printer {
                 self.print_string(s);
             }
             Token::Eof => panic!(), // Eof should never get here.
+            Token::Mark(mark, kind) => self.print_mark(mark, kind),
         }
     }
 
     // Convenience functions to talk to the printer.
 
     /// "raw box"
-    pub fn rbox(&mut self, indent: usize, b: Breaks) {
+    pub fn rbox(&mut self, indent: usize, b: BoxKind) {
         self.scan_begin(BeginToken {
             offset: indent as isize,
             breaks: b
@@ -606,12 +674,12 @@ impl Printer {
 
     /// Inconsistent breaking box
     pub fn ibox(&mut self, indent: usize) {
-        self.rbox(indent, Breaks::Inconsistent)
+        self.rbox(indent, BoxKind::Inconsistent)
     }
 
     /// Consistent breaking box
     pub fn cbox(&mut self, indent: usize) {
-        self.rbox(indent, Breaks::Consistent)
+        self.rbox(indent, BoxKind::Consistent)
     }
 
     pub fn break_offset(&mut self, n: usize, off: isize) {
@@ -630,6 +698,26 @@ impl Printer {
         self.out
     }
 
+    /// Inserts a mark in the stream, returning its index in the offsets returned by
+    /// `eof_with_marks`. Marks take no room and never cause line breaks.
+    pub fn mark(&mut self, kind: MarkKind) -> usize {
+        let mark = self.marks.len();
+        self.marks.push(0);
+        self.scan_mark(mark, kind);
+        mark
+    }
+
+    /// Like `eof`, but also returns the byte offsets in the output the marks resolved to, by
+    /// their index. Start marks not followed by any word resolve to the end of the output.
+    pub fn eof_with_marks(mut self) -> (String, Vec<usize>) {
+        self.scan_eof();
+        let end = self.out.len();
+        for mark in self.pending_marks.drain(..) {
+            self.marks[mark] = end;
+        }
+        (self.out, self.marks)
+    }
+
     pub fn word<S: Into<Cow<'static, str>>>(&mut self, wrd: S) {
         let s = wrd.into();
         self.scan_string(s)
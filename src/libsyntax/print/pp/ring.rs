@@ -0,0 +1,106 @@
+//! The ring buffer through which tokens flow from the SCAN to the PRINT process.
+
+use std::ops::{Index, IndexMut};
+
+/// A ring buffer whose live entries are those between `left` (the oldest entry
+/// not yet printed) and `right` (the most recently scanned entry), inclusive.
+///
+/// Indices wrap around at the capacity given to `new`, and the storage is only
+/// extended up to that capacity as entries are scanned. The SCAN process keeps
+/// indices into the buffer on its scan stack, so entries are never moved.
+pub struct RingBuffer<T> {
+    data: Vec<T>,
+    capacity: usize,
+    left: usize,
+    right: usize,
+}
+
+impl<T: Default> RingBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        RingBuffer {
+            // Initialize a single entry; `advance_right` will extend it on
+            // demand up to `capacity` elements.
+            data: vec![T::default()],
+            capacity,
+            left: 0,
+            right: 0,
+        }
+    }
+
+    /// Appends an entry at the right side, which must not catch up with the
+    /// left side.
+    pub fn advance_right(&mut self) {
+        self.right += 1;
+        self.right %= self.capacity;
+        // Extend the storage if necessary.
+        if self.right == self.data.len() {
+            self.data.push(T::default());
+        }
+        assert_ne!(self.right, self.left);
+    }
+}
+
+impl<T> RingBuffer<T> {
+    pub fn left(&self) -> usize {
+        self.left
+    }
+
+    pub fn right(&self) -> usize {
+        self.right
+    }
+
+    /// The entry at the left side.
+    pub fn first(&self) -> &T {
+        &self.data[self.left]
+    }
+
+    /// The entry at the right side.
+    pub fn last(&self) -> &T {
+        &self.data[self.right]
+    }
+
+    pub fn last_mut(&mut self) -> &mut T {
+        &mut self.data[self.right]
+    }
+
+    /// The index of the entry scanned before the one at `index`.
+    pub fn prev(&self, index: usize) -> usize {
+        if index == 0 { self.capacity - 1 } else { index - 1 }
+    }
+
+    /// Drops the entry at the left side.
+    pub fn advance_left(&mut self) {
+        self.left += 1;
+        self.left %= self.capacity;
+    }
+
+    /// Makes the buffer hold a single entry at index 0, reusing the storage.
+    pub fn reset(&mut self) {
+        self.left = 0;
+        self.right = 0;
+    }
+
+    /// Iterates over the live entries from the left side to the right side.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        let len = if self.right >= self.left {
+            self.right - self.left + 1
+        } else {
+            self.capacity - self.left + self.right + 1
+        };
+        (0..len).map(move |i| &self.data[(self.left + i) % self.capacity])
+    }
+}
+
+impl<T> Index<usize> for RingBuffer<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        &self.data[index]
+    }
+}
+
+impl<T> IndexMut<usize> for RingBuffer<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        &mut self.data[index]
+    }
+}
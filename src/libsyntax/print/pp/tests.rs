@@ -0,0 +1,129 @@
+use super::*;
+
+const MARGIN: usize = 40;
+
+fn print_with<F: FnOnce(&mut Printer)>(f: F) -> String {
+    let mut printer = mk_printer_with_margin(MARGIN);
+    f(&mut printer);
+    printer.eof()
+}
+
+/// A small xorshift generator, so that the generated token streams are reproducible.
+struct Rng(u32);
+
+impl Rng {
+    fn below(&mut self, bound: u32) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 % bound
+    }
+}
+
+/// Emits a box of random kind holding words and nested boxes separated by spaces,
+/// recording the printed words in `words`.
+fn random_box(p: &mut Printer, rng: &mut Rng, depth: usize, words: &mut Vec<String>) {
+    let kind = if rng.below(2) == 0 { BoxKind::Consistent } else { BoxKind::Inconsistent };
+    p.rbox(2, kind);
+    for i in 0..1 + rng.below(6) {
+        if i != 0 {
+            p.space();
+        }
+        if depth < 4 && rng.below(3) == 0 {
+            random_box(p, rng, depth + 1, words);
+        } else {
+            let word = "x".repeat(1 + rng.below(8) as usize);
+            words.push(word.clone());
+            p.word(word);
+        }
+    }
+    p.end();
+}
+
+#[test]
+fn fitting_box_stays_on_one_line() {
+    let out = print_with(|p| {
+        p.cbox(4);
+        p.word("foo(");
+        for (i, word) in ["a", "b", "c"].iter().enumerate() {
+            if i != 0 {
+                p.word(",");
+                p.space();
+            }
+            p.word(*word);
+        }
+        p.word(")");
+        p.end();
+    });
+    assert_eq!(out, "foo(a, b, c)");
+}
+
+#[test]
+fn consistent_box_breaks_everywhere() {
+    let words = ["aaaaaaaaaaaa", "bbbbbbbbbbbb", "cccccccccccc", "dd"];
+    let out = print_with(|p| {
+        p.cbox(4);
+        for (i, word) in words.iter().enumerate() {
+            if i != 0 {
+                p.space();
+            }
+            p.word(*word);
+        }
+        p.end();
+    });
+    assert_eq!(out, "aaaaaaaaaaaa\n    bbbbbbbbbbbb\n    cccccccccccc\n    dd");
+}
+
+#[test]
+fn inconsistent_box_fills_lines() {
+    let words = ["aaaaaaaaaaaa", "bbbbbbbbbbbb", "cccccccccccc", "dd"];
+    let out = print_with(|p| {
+        p.ibox(4);
+        for (i, word) in words.iter().enumerate() {
+            if i != 0 {
+                p.space();
+            }
+            p.word(*word);
+        }
+        p.end();
+    });
+    assert_eq!(out, "aaaaaaaaaaaa bbbbbbbbbbbb cccccccccccc\n    dd");
+}
+
+#[test]
+fn hardbreak_always_breaks() {
+    let out = print_with(|p| {
+        p.ibox(0);
+        p.word("a");
+        p.hardbreak();
+        p.word("b");
+        p.end();
+    });
+    assert_eq!(out, "a\nb");
+}
+
+#[test]
+fn random_streams_respect_margin() {
+    for seed in 1..200 {
+        let mut rng = Rng(seed);
+        let mut words = Vec::new();
+        let out = print_with(|p| random_box(p, &mut rng, 0, &mut words));
+
+        for line in out.lines() {
+            assert!(line.len() <= MARGIN, "seed {}: line too long: {:?}", seed, line);
+            assert!(!line.ends_with(' '), "seed {}: trailing whitespace: {:?}", seed, line);
+        }
+        assert_eq!(out.split_whitespace().collect::<Vec<_>>(), words, "seed {}", seed);
+    }
+}
+
+#[test]
+fn printing_is_deterministic() {
+    for seed in 1..50 {
+        let print = || {
+            let mut rng = Rng(seed);
+            print_with(|p| random_box(p, &mut rng, 0, &mut Vec::new()))
+        };
+        assert_eq!(print(), print());
+    }
+}
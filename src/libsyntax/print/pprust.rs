@@ -7,8 +7,8 @@ use crate::source_map::{self, SourceMap, Spanned};
 use crate::parse::token::{self, BinOpToken, DelimToken, Nonterminal, Token, TokenKind};
 use crate::parse::lexer::comments;
 use crate::parse::{self, ParseSess};
-use crate::print::pp::{self, Breaks};
-use crate::print::pp::Breaks::{Consistent, Inconsistent};
+use crate::print::pp::{self, BoxKind, MarkKind};
+use crate::print::pp::BoxKind::{Consistent, Inconsistent};
 use crate::ptr::P;
 use crate::symbol::{kw, sym};
 use crate::tokenstream::{self, TokenStream, TokenTree};
@@ -92,11 +92,66 @@ impl<'a> Comments<'a> {
     }
 }
 
+/// Options controlling the output of the pretty printer.
+#[derive(Copy, Clone, Default)]
+pub struct PrintConfig {
+    /// Prints a normalized form of the AST, meant for hashing and structural comparisons by
+    /// tools and for deterministic test expectations: comments are omitted, doc comments are
+    /// printed as `#[doc = "..."]` attributes, and every item printed at the top level takes a
+    /// single line along with its outer attributes, with single blanks between its tokens where
+    /// lines could be broken. The output then only depends on the AST.
+    pub normalized: bool,
+}
+
+fn printer_for(config: PrintConfig) -> pp::Printer {
+    if config.normalized {
+        pp::mk_single_line_printer()
+    } else {
+        pp::mk_printer()
+    }
+}
+
 pub struct State<'a> {
     pub s: pp::Printer,
     comments: Option<Comments<'a>>,
     ann: &'a (dyn PpAnn+'a),
-    is_expanded: bool
+    is_expanded: bool,
+    config: PrintConfig,
+    span_map: Option<SpanMapBuilder>,
+}
+
+/// A range of the output of `print_with_span_map` printed from an AST node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpanMapEntry {
+    /// The start of the range, a byte offset in the output.
+    pub lo: usize,
+    /// The end of the range, exclusive.
+    pub hi: usize,
+    pub id: ast::NodeId,
+    pub span: Span,
+}
+
+/// Maps the output of `print_with_span_map` back to the AST nodes it was printed from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SpanMap {
+    /// The ranges of items, trait and impl items, blocks, expressions and patterns, in the
+    /// order they were printed in, so that nodes come before the nodes they contain.
+    pub entries: Vec<SpanMapEntry>,
+}
+
+impl SpanMap {
+    /// Returns the innermost node whose output contains the byte at `pos`.
+    pub fn node_at(&self, pos: usize) -> Option<&SpanMapEntry> {
+        self.entries.iter().filter(|entry| entry.lo <= pos && pos < entry.hi).last()
+    }
+}
+
+#[derive(Default)]
+struct SpanMapBuilder {
+    /// The marks of the start of the nodes being printed.
+    open: Vec<usize>,
+    /// The marks of the start and end of the nodes printed, and the nodes.
+    nodes: Vec<(usize, usize, ast::NodeId, Span)>,
 }
 
 crate const INDENT_UNIT: usize = 4;
@@ -115,6 +170,8 @@ pub fn print_crate<'a>(cm: &'a SourceMap,
         comments: Some(Comments::new(cm, sess, filename, input)),
         ann,
         is_expanded,
+        config: PrintConfig::default(),
+        span_map: None,
     };
 
     if is_expanded && sess.injected_crate_name.try_get().is_some() {
@@ -147,17 +204,64 @@ pub fn print_crate<'a>(cm: &'a SourceMap,
 
 pub fn to_string<F>(f: F) -> String where
     F: FnOnce(&mut State<'_>),
+{
+    to_string_with_config(PrintConfig::default(), f)
+}
+
+pub fn to_string_with_config<F>(config: PrintConfig, f: F) -> String where
+    F: FnOnce(&mut State<'_>),
 {
     let mut printer = State {
-        s: pp::mk_printer(),
+        s: printer_for(config),
         comments: None,
         ann: &NoAnn,
-        is_expanded: false
+        is_expanded: false,
+        config,
+        span_map: None,
     };
     f(&mut printer);
     printer.s.eof()
 }
 
+/// Prints `krate` without consulting its source, so no comments are reproduced.
+pub fn crate_to_string_with_config(krate: &ast::Crate, config: PrintConfig) -> String {
+    to_string_with_config(config, |s| {
+        s.print_mod(&krate.module, &krate.attrs);
+        s.print_remaining_comments();
+    })
+}
+
+/// Prints `krate` like `crate_to_string_with_config`, along with a map from the ranges of the
+/// output back to the nodes they were printed from. This lets views of expanded code lead back
+/// to the code the nodes were expanded from, through their spans.
+pub fn print_with_span_map(krate: &ast::Crate, config: PrintConfig) -> (String, SpanMap) {
+    let mut printer = State {
+        s: printer_for(config),
+        comments: None,
+        ann: &NoAnn,
+        is_expanded: false,
+        config,
+        span_map: Some(SpanMapBuilder::default()),
+    };
+    printer.print_mod(&krate.module, &krate.attrs);
+    printer.print_remaining_comments();
+    let builder = printer.span_map.take().unwrap();
+    let (out, marks) = printer.s.eof_with_marks();
+
+    // Nodes are started in the order they are printed in, and finished in the reverse order
+    // for the ones containing others.
+    let mut nodes = builder.nodes;
+    nodes.sort_by_key(|&(start, ..)| start);
+    let entries = nodes.into_iter().map(|(start, end, id, span)| SpanMapEntry {
+        // Nodes printing nothing have their start after their end.
+        lo: std::cmp::min(marks[start], marks[end]),
+        hi: marks[end],
+        id,
+        span,
+    }).collect();
+    (out, SpanMap { entries })
+}
+
 // This makes comma-separated lists look slightly nicer,
 // and also addresses a specific regression described in issue #63896.
 fn tt_prepend_space(tt: &TokenTree) -> bool {
@@ -432,6 +536,20 @@ fn visibility_qualified(vis: &ast::Visibility, s: &str) -> String {
     format!("{}{}", to_string(|s| s.print_visibility(vis)), s)
 }
 
+/// Whether `expr` is printed starting with an operand that is not delimited, in which case
+/// the parser would attach outer attributes written in front of `expr` to that operand.
+fn expr_starts_with_operand(expr: &ast::Expr) -> bool {
+    match expr.node {
+        ast::ExprKind::Binary(..) |
+        ast::ExprKind::Cast(..) |
+        ast::ExprKind::Type(..) |
+        ast::ExprKind::Assign(..) |
+        ast::ExprKind::AssignOp(..) |
+        ast::ExprKind::Range(Some(_), ..) => true,
+        _ => false,
+    }
+}
+
 impl std::ops::Deref for State<'_> {
     type Target = pp::Printer;
     fn deref(&self) -> &Self::Target {
@@ -447,11 +565,12 @@ impl std::ops::DerefMut for State<'_> {
 
 pub trait PrintState<'a>: std::ops::Deref<Target = pp::Printer> + std::ops::DerefMut {
     fn comments(&mut self) -> &mut Option<Comments<'a>>;
+    fn config(&self) -> PrintConfig;
     fn print_ident(&mut self, ident: ast::Ident);
     fn print_generic_args(&mut self, args: &ast::GenericArgs, colons_before_params: bool);
 
     fn strsep<T, F>(&mut self, sep: &'static str, space_before: bool,
-                    b: Breaks, elts: &[T], mut op: F)
+                    b: BoxKind, elts: &[T], mut op: F)
         where F: FnMut(&mut Self, &T),
     {
         self.rbox(0, b);
@@ -468,7 +587,7 @@ pub trait PrintState<'a>: std::ops::Deref<Target = pp::Printer> + std::ops::Dere
         self.end();
     }
 
-    fn commasep<T, F>(&mut self, b: Breaks, elts: &[T], op: F)
+    fn commasep<T, F>(&mut self, b: BoxKind, elts: &[T], op: F)
         where F: FnMut(&mut Self, &T),
     {
         self.strsep(",", false, b, elts, op)
@@ -547,7 +666,13 @@ pub trait PrintState<'a>: std::ops::Deref<Target = pp::Printer> + std::ops::Dere
 
     fn print_literal(&mut self, lit: &ast::Lit) {
         self.maybe_print_comment(lit.span.lo());
-        self.word(lit.token.to_string())
+        match lit.node {
+            // Escapes the line breaks the literal may contain, to keep it on a single line.
+            ast::LitKind::Str(st, ast::StrStyle::Cooked) if self.config().normalized => {
+                self.print_string(&st.as_str(), ast::StrStyle::Cooked)
+            }
+            _ => self.word(lit.token.to_string()),
+        }
     }
 
     fn print_string(&mut self, st: &str,
@@ -596,6 +721,8 @@ pub trait PrintState<'a>: std::ops::Deref<Target = pp::Printer> + std::ops::Dere
                               kind: ast::AttrStyle,
                               is_inline: bool,
                               trailing_hardbreak: bool) {
+        // Normalized output keeps items on a single line along with their attributes.
+        let is_inline = is_inline || (kind == ast::AttrStyle::Outer && self.config().normalized);
         let mut count = 0;
         for attr in attrs {
             if attr.style == kind {
@@ -621,32 +748,38 @@ pub trait PrintState<'a>: std::ops::Deref<Target = pp::Printer> + std::ops::Dere
             self.hardbreak_if_not_bol();
         }
         self.maybe_print_comment(attr.span.lo());
-        if attr.is_sugared_doc {
+        if attr.is_sugared_doc && !self.config().normalized {
             self.word(attr.value_str().unwrap().as_str().to_string());
             self.hardbreak()
+        } else if attr.is_sugared_doc {
+            attr.with_desugared_doc(|attr| self.print_attribute_tokens(attr))
         } else {
-            match attr.style {
-                ast::AttrStyle::Inner => self.word("#!["),
-                ast::AttrStyle::Outer => self.word("#["),
-            }
-            self.ibox(0);
-            match attr.tokens.trees().next() {
-                Some(TokenTree::Delimited(_, delim, tts)) => {
-                    self.print_mac_common(
-                        Some(MacHeader::Path(&attr.path)), false, None, delim, tts, true, attr.span
-                    );
-                }
-                tree => {
-                    self.print_path(&attr.path, false, 0);
-                    if tree.is_some() {
-                        self.space();
-                        self.print_tts(attr.tokens.clone(), true);
-                    }
+            self.print_attribute_tokens(attr)
+        }
+    }
+
+    fn print_attribute_tokens(&mut self, attr: &ast::Attribute) {
+        match attr.style {
+            ast::AttrStyle::Inner => self.word("#!["),
+            ast::AttrStyle::Outer => self.word("#["),
+        }
+        self.ibox(0);
+        match attr.tokens.trees().next() {
+            Some(TokenTree::Delimited(_, delim, tts)) => {
+                self.print_mac_common(
+                    Some(MacHeader::Path(&attr.path)), false, None, delim, tts, true, attr.span
+                );
+            }
+            tree => {
+                self.print_path(&attr.path, false, 0);
+                if tree.is_some() {
+                    self.space();
+                    self.print_tts(attr.tokens.clone(), true);
                 }
             }
-            self.end();
-            self.word("]");
         }
+        self.end();
+        self.word("]");
     }
 
     fn print_meta_list_item(&mut self, item: &ast::NestedMetaItem) {
@@ -832,6 +965,10 @@ impl<'a> PrintState<'a> for State<'a> {
         &mut self.comments
     }
 
+    fn config(&self) -> PrintConfig {
+        self.config
+    }
+
     fn print_ident(&mut self, ident: ast::Ident) {
         self.s.word(ast_ident_to_string(ident, ident.is_raw_guess()));
         self.ann.post(self, AnnNode::Ident(&ident))
@@ -892,6 +1029,22 @@ impl<'a> PrintState<'a> for State<'a> {
 }
 
 impl<'a> State<'a> {
+    /// Marks the start of the output of a node if a span map is being built.
+    fn start_node(&mut self) {
+        if let Some(ref mut map) = self.span_map {
+            map.open.push(self.s.mark(MarkKind::Start));
+        }
+    }
+
+    /// Marks the end of the output of the node started last if a span map is being built.
+    fn end_node(&mut self, id: ast::NodeId, span: Span) {
+        if let Some(ref mut map) = self.span_map {
+            let end = self.s.mark(MarkKind::End);
+            let start = map.open.pop().unwrap();
+            map.nodes.push((start, end, id, span));
+        }
+    }
+
     // Synthesizes a comment that was not textually present in the original source
     // file.
     pub fn synth_comment(&mut self, text: String) {
@@ -903,7 +1056,7 @@ impl<'a> State<'a> {
     }
 
     crate fn commasep_cmnt<T, F, G>(&mut self,
-                                  b: Breaks,
+                                  b: BoxKind,
                                   elts: &[T],
                                   mut op: F,
                                   mut get_span: G) where
@@ -927,7 +1080,7 @@ impl<'a> State<'a> {
         self.end();
     }
 
-    crate fn commasep_exprs(&mut self, b: Breaks,
+    crate fn commasep_exprs(&mut self, b: BoxKind,
                             exprs: &[P<ast::Expr>]) {
         self.commasep_cmnt(b, exprs, |s, e| s.print_expr(e), |e| e.span)
     }
@@ -1141,6 +1294,7 @@ impl<'a> State<'a> {
         self.hardbreak_if_not_bol();
         self.maybe_print_comment(item.span.lo());
         self.print_outer_attributes(&item.attrs);
+        self.start_node();
         self.ann.pre(self, AnnNode::Item(item));
         match item.node {
             ast::ItemKind::ExternCrate(orig_name) => {
@@ -1388,7 +1542,8 @@ impl<'a> State<'a> {
                 );
             }
         }
-        self.ann.post(self, AnnNode::Item(item))
+        self.ann.post(self, AnnNode::Item(item));
+        self.end_node(item.id, item.span)
     }
 
     fn print_trait_ref(&mut self, t: &ast::TraitRef) {
@@ -1460,7 +1615,7 @@ impl<'a> State<'a> {
     }
 
     crate fn print_defaultness(&mut self, defaultness: ast::Defaultness) {
-        if let ast::Defaultness::Default = defaultness {
+        if defaultness.is_default() {
             self.word_nbsp("default");
         }
     }
@@ -1546,6 +1701,7 @@ impl<'a> State<'a> {
 
     crate fn print_trait_item(&mut self, ti: &ast::TraitItem)
                             {
+        self.start_node();
         self.ann.pre(self, AnnNode::SubItem(ti.id));
         self.hardbreak_if_not_bol();
         self.maybe_print_comment(ti.span.lo());
@@ -1588,10 +1744,12 @@ impl<'a> State<'a> {
                 }
             }
         }
-        self.ann.post(self, AnnNode::SubItem(ti.id))
+        self.ann.post(self, AnnNode::SubItem(ti.id));
+        self.end_node(ti.id, ti.span)
     }
 
     crate fn print_impl_item(&mut self, ii: &ast::ImplItem) {
+        self.start_node();
         self.ann.pre(self, AnnNode::SubItem(ii.id));
         self.hardbreak_if_not_bol();
         self.maybe_print_comment(ii.span.lo());
@@ -1625,7 +1783,8 @@ impl<'a> State<'a> {
                 }
             }
         }
-        self.ann.post(self, AnnNode::SubItem(ii.id))
+        self.ann.post(self, AnnNode::SubItem(ii.id));
+        self.end_node(ii.id, ii.span)
     }
 
     crate fn print_stmt(&mut self, st: &ast::Stmt) {
@@ -1697,6 +1856,7 @@ impl<'a> State<'a> {
             BlockCheckMode::Default => ()
         }
         self.maybe_print_comment(blk.span.lo());
+        self.start_node();
         self.ann.pre(self, AnnNode::Block(blk));
         self.bopen();
 
@@ -1715,7 +1875,8 @@ impl<'a> State<'a> {
         }
 
         self.bclose_maybe_open(blk.span, close_box);
-        self.ann.post(self, AnnNode::Block(blk))
+        self.ann.post(self, AnnNode::Block(blk));
+        self.end_node(blk.id, blk.span)
     }
 
     /// Print a `let pat = scrutinee` expression.
@@ -1864,6 +2025,7 @@ impl<'a> State<'a> {
             &fields[..],
             |s, field| {
                 s.ibox(INDENT_UNIT);
+                s.print_outer_attributes_inline(&field.attrs);
                 if !field.is_shorthand {
                     s.print_ident(field.ident);
                     s.word_space(":");
@@ -1998,7 +2160,16 @@ impl<'a> State<'a> {
             self.print_outer_attributes(attrs);
         }
 
+        // The parser attaches outer attributes written in front of `a + b` to `a`, so keep
+        // them on the whole expression by parenthesizing it.
+        let attrs_need_paren = attrs.iter().any(|attr| attr.style == ast::AttrStyle::Outer) &&
+            expr_starts_with_operand(expr);
+        if attrs_need_paren {
+            self.popen();
+        }
+
         self.ibox(INDENT_UNIT);
+        self.start_node();
         self.ann.pre(self, AnnNode::Expr(expr));
         match expr.node {
             ast::ExprKind::Box(ref expr) => {
@@ -2308,7 +2479,11 @@ impl<'a> State<'a> {
             }
         }
         self.ann.post(self, AnnNode::Expr(expr));
+        self.end_node(expr.id, expr.span);
         self.end();
+        if attrs_need_paren {
+            self.pclose();
+        }
     }
 
     crate fn print_local_decl(&mut self, loc: &ast::Local) {
@@ -2353,6 +2528,7 @@ impl<'a> State<'a> {
 
     crate fn print_pat(&mut self, pat: &ast::Pat) {
         self.maybe_print_comment(pat.span.lo());
+        self.start_node();
         self.ann.pre(self, AnnNode::Pat(pat));
         /* Pat isn't normalized, but the beauty of it
          is that it doesn't matter */
@@ -2398,6 +2574,7 @@ impl<'a> State<'a> {
                     Consistent, &fields[..],
                     |s, f| {
                         s.cbox(INDENT_UNIT);
+                        s.print_outer_attributes_inline(&f.attrs);
                         if !f.is_shorthand {
                             s.print_ident(f.ident);
                             s.word_nbsp(":");
@@ -2456,7 +2633,8 @@ impl<'a> State<'a> {
             }
             PatKind::Mac(ref m) => self.print_mac(m),
         }
-        self.ann.post(self, AnnNode::Pat(pat))
+        self.ann.post(self, AnnNode::Pat(pat));
+        self.end_node(pat.id, pat.span)
     }
 
     fn print_arm(&mut self, arm: &ast::Arm) {
@@ -1,7 +1,11 @@
 use super::*;
 
 use crate::ast;
-use crate::source_map;
+use crate::attr;
+use crate::parse::ParseSess;
+use crate::source_map::{self, FilePathMapping};
+use crate::tests::{string_to_crate, with_error_checking_parse};
+use crate::visit::{self, Visitor};
 use crate::with_default_globals;
 use syntax_pos;
 
@@ -68,3 +72,190 @@ fn test_variant_to_string() {
         assert_eq!(varstr, "principal_skinner");
     })
 }
+
+/// Records every attribute along with the nesting depth of the node it is attached to,
+/// looking through parentheses, to check that attributes are reparsed onto the same nodes
+/// they were printed from.
+struct AttrDepths {
+    depth: usize,
+    attrs: Vec<(usize, String)>,
+}
+
+impl AttrDepths {
+    fn collect(krate: &ast::Crate) -> Vec<(usize, String)> {
+        let mut collector = AttrDepths { depth: 0, attrs: Vec::new() };
+        visit::walk_crate(&mut collector, krate);
+        collector.attrs
+    }
+
+    fn nested<F: FnOnce(&mut Self)>(&mut self, f: F) {
+        self.depth += 1;
+        f(self);
+        self.depth -= 1;
+    }
+}
+
+impl<'ast> Visitor<'ast> for AttrDepths {
+    fn visit_attribute(&mut self, attr: &'ast ast::Attribute) {
+        self.attrs.push((self.depth, attribute_to_string(attr)));
+    }
+    fn visit_item(&mut self, i: &'ast ast::Item) {
+        self.nested(|this| visit::walk_item(this, i))
+    }
+    fn visit_local(&mut self, l: &'ast ast::Local) {
+        self.nested(|this| visit::walk_local(this, l))
+    }
+    fn visit_param(&mut self, p: &'ast ast::Param) {
+        self.nested(|this| visit::walk_param(this, p))
+    }
+    fn visit_generic_param(&mut self, p: &'ast ast::GenericParam) {
+        self.nested(|this| visit::walk_generic_param(this, p))
+    }
+    fn visit_arm(&mut self, a: &'ast ast::Arm) {
+        self.nested(|this| visit::walk_arm(this, a))
+    }
+    fn visit_field(&mut self, f: &'ast ast::Field) {
+        self.nested(|this| visit::walk_field(this, f))
+    }
+    fn visit_field_pattern(&mut self, fp: &'ast ast::FieldPat) {
+        self.nested(|this| visit::walk_field_pattern(this, fp))
+    }
+    fn visit_expr(&mut self, e: &'ast ast::Expr) {
+        match e.node {
+            ast::ExprKind::Paren(ref inner) => {
+                self.nested(|this| {
+                    for attr in e.attrs.iter() {
+                        this.visit_attribute(attr);
+                    }
+                });
+                self.visit_expr(inner);
+            }
+            _ => self.nested(|this| visit::walk_expr(this, e)),
+        }
+    }
+    fn visit_mac(&mut self, _mac: &'ast ast::Mac) {}
+}
+
+#[test]
+fn test_attributes_round_trip() {
+    with_default_globals(|| {
+        let src = "
+fn f<#[cfg(a)] T>(#[cfg(b)] x: T) {
+    #[cfg(c)]
+    let y = 1;
+    let s = S { #[cfg(d)] a: 1, b: #[cfg(e)] 2 };
+    let S { #[cfg(f)] a, .. } = s;
+    match y {
+        #[cfg(g)]
+        _ => #[cfg(h)] (),
+    }
+    let z = #[cfg(i)] [#[cfg(j)] 1, 2];
+    /// Doc comment on a statement.
+    #[cfg(k)]
+    f(y);
+}
+";
+        let krate = string_to_crate(src.to_string());
+        let printed = krate.module.items.iter().map(|i| item_to_string(i)).collect::<String>();
+        let reparsed = string_to_crate(printed);
+        let attrs = AttrDepths::collect(&krate);
+        assert_eq!(attrs.len(), 12);
+        assert_eq!(attrs, AttrDepths::collect(&reparsed));
+    })
+}
+
+#[test]
+fn test_attributes_on_operators() {
+    with_default_globals(|| {
+        let ps = ParseSess::new(FilePathMapping::empty());
+        let mut expr = with_error_checking_parse("a + b".to_string(), &ps, |p| p.parse_expr());
+        let attr = attr::mk_attr_outer(attr::mk_word_item(ast::Ident::from_str("attr")));
+        expr.attrs = vec![attr].into();
+        assert_eq!(expr_to_string(&expr), "#[attr] (a + b)");
+    })
+}
+
+#[test]
+fn test_normalized_printing() {
+    with_default_globals(|| {
+        let config = PrintConfig { normalized: true };
+        let first = string_to_crate("/// Doc.\nfn f() { g(1) } // Trailing.".to_string());
+        let second = string_to_crate(
+            "/** Doc.*/\nfn f()\n{\n    // Isolated.\n    g(1)\n}\n".to_string()
+        );
+
+        let printed = crate_to_string_with_config(&first, config);
+        assert_eq!(printed, "#[doc = \" Doc.\"] fn f() { g(1) }");
+        assert_eq!(printed, crate_to_string_with_config(&second, config));
+    })
+}
+
+#[test]
+fn test_normalized_printing_puts_items_on_single_lines() {
+    with_default_globals(|| {
+        let config = PrintConfig { normalized: true };
+        let krate = string_to_crate(
+            "#![allow(dead_code)]\n\
+             struct S {\n    a: u8,\n\n    b:   u16\n}\n\
+             impl S {\n    #[inline]\n    fn f(&self) -> &str {\n        \"a\nb\"\n    }\n}\n"
+                .to_string()
+        );
+        assert_eq!(
+            crate_to_string_with_config(&krate, config),
+            "#![allow(dead_code)]\n\
+             struct S { a: u8, b: u16, }\n\
+             impl S { #[inline] fn f(&self) -> &str { \"a\\nb\" } }"
+        );
+    })
+}
+
+#[test]
+fn test_printing_is_idempotent() {
+    with_default_globals(|| {
+        let sources = [
+            "fn f(a: u32) -> u32 { if a > 1 { a * f(a - 1) } else { 1 } }",
+            "struct S<'a, T: Clone + 'a> where T: Default { a: &'a T, b: Vec<Option<T>> }",
+            "impl<T> Tr for S<T> { type A = u8; fn g(&self) { let S { a, .. } = self; } }",
+            "fn h() { let v = vec![1, 2, 3]; for x in v.iter().map(|x| x + 1) { match x { \
+                0 ..= 9 => {} _ => return, } } }",
+            "mod m { pub(crate) use std::{fmt, io::{self, Write}}; const C: [u8; 2] = [0; 2]; }",
+        ];
+        let print = |src: String| {
+            crate_to_string_with_config(&string_to_crate(src), PrintConfig::default())
+        };
+        for src in sources.iter() {
+            let once = print(src.to_string());
+            assert_eq!(print(once.clone()), once);
+        }
+    })
+}
+
+#[test]
+fn test_span_map() {
+    with_default_globals(|| {
+        let src = "fn f(a: u32) -> u32 { let b = a; b + 1 }";
+        let krate = string_to_crate(src.to_string());
+        let (out, map) = print_with_span_map(&krate, PrintConfig::default());
+        assert_eq!(out, crate_to_string_with_config(&krate, PrintConfig::default()));
+
+        let source = |entry: &SpanMapEntry| {
+            &src[entry.span.lo().0 as usize..entry.span.hi().0 as usize]
+        };
+        let printed = |entry: &SpanMapEntry| &out[entry.lo..entry.hi];
+        assert_eq!(source(&map.entries[0]), src);
+        assert!(printed(&map.entries[0]).starts_with("fn f(a: u32) -> u32"));
+
+        // The innermost node printed at the given offset from `text`.
+        let node_at = |text: &str, offset: usize| {
+            map.node_at(out.find(text).unwrap() + offset).unwrap()
+        };
+        assert_eq!(printed(node_at("b + 1", 2)), "b + 1");
+        assert_eq!(source(node_at("b + 1", 2)), "b + 1");
+        assert_eq!(printed(node_at("b + 1", 4)), "1");
+        assert_eq!(source(node_at("= a", 2)), "a");
+        let block = node_at("let", 0);
+        assert!(printed(block).starts_with('{') && printed(block).ends_with('}'));
+        assert_eq!(source(block), "{ let b = a; b + 1 }");
+        assert!(map.node_at(out.len()).is_none());
+    })
+}
@@ -1,11 +1,14 @@
 //! Span debugger
 //!
 //! This module shows spans for all expressions in the crate
-//! to help with compiler debugging.
+//! to help with compiler debugging, along with the item they are in.
 
 use std::str::FromStr;
 
+use syntax_pos::Span;
+
 use crate::ast;
+use crate::util::item_index::ItemIndex;
 use crate::visit;
 use crate::visit::Visitor;
 
@@ -31,26 +34,39 @@ impl FromStr for Mode {
 struct ShowSpanVisitor<'a> {
     span_diagnostic: &'a errors::Handler,
     mode: Mode,
+    items: ItemIndex,
+}
+
+impl ShowSpanVisitor<'_> {
+    fn show(&self, span: Span, what: &str) {
+        match self.items.query(span.lo()) {
+            Some(path) => {
+                let msg = format!("{} in {}", what, path.innermost().descr);
+                self.span_diagnostic.span_warn(span, &msg);
+            }
+            None => self.span_diagnostic.span_warn(span, what),
+        }
+    }
 }
 
 impl<'a> Visitor<'a> for ShowSpanVisitor<'a> {
     fn visit_expr(&mut self, e: &'a ast::Expr) {
         if let Mode::Expression = self.mode {
-            self.span_diagnostic.span_warn(e.span, "expression");
+            self.show(e.span, "expression");
         }
         visit::walk_expr(self, e);
     }
 
     fn visit_pat(&mut self, p: &'a ast::Pat) {
         if let Mode::Pattern = self.mode {
-            self.span_diagnostic.span_warn(p.span, "pattern");
+            self.show(p.span, "pattern");
         }
         visit::walk_pat(self, p);
     }
 
     fn visit_ty(&mut self, t: &'a ast::Ty) {
         if let Mode::Type = self.mode {
-            self.span_diagnostic.span_warn(t.span, "type");
+            self.show(t.span, "type");
         }
         visit::walk_ty(self, t);
     }
@@ -70,6 +86,7 @@ pub fn run(span_diagnostic: &errors::Handler,
     let mut v = ShowSpanVisitor {
         span_diagnostic,
         mode,
+        items: ItemIndex::new(krate),
     };
     visit::walk_crate(&mut v, krate);
 }
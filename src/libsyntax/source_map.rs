@@ -11,10 +11,10 @@ pub use syntax_pos::*;
 pub use syntax_pos::hygiene::{ExpnKind, ExpnData};
 
 use rustc_data_structures::fx::FxHashMap;
-use rustc_data_structures::stable_hasher::StableHasher;
 use rustc_data_structures::sync::{Lrc, Lock, LockGuard, MappedLockGuard};
 use std::cmp;
-use std::hash::Hash;
+use std::iter;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
 use std::env;
@@ -22,7 +22,11 @@ use std::fs;
 use std::io;
 use log::debug;
 
-use errors::SourceMapper;
+use crate::util::item_index::ItemIndex;
+
+use errors::{Handler, SourceMapper, SpanMergeError};
+use errors::replay::DiagnosticReplayCache;
+use rustc_lexer::unescape;
 
 #[cfg(test)]
 mod tests;
@@ -71,6 +75,15 @@ pub trait FileLoader {
     fn read_file(&self, path: &Path) -> io::Result<String>;
 }
 
+/// Provides the sources of files that can't be read from disk, like the files of
+/// dependencies that only exist in a registry archive or a compressed cache, so that
+/// diagnostics pointing into them can still show snippets.
+pub trait SourceProvider {
+    /// Returns the source of `source_file`, or `None` if it isn't known to this provider.
+    /// A source that doesn't match the hash of the original source is ignored.
+    fn provide_source(&self, source_file: &SourceFile) -> Option<String>;
+}
+
 /// A FileLoader that uses std::fs to load real files.
 pub struct RealFileLoader;
 
@@ -94,30 +107,37 @@ impl FileLoader for RealFileLoader {
     }
 }
 
-// This is a `SourceFile` identifier that is used to correlate `SourceFile`s between
-// subsequent compilation sessions (which is something we need to do during
-// incremental compilation).
+/// A span described by where it is in its file instead of by its `BytePos`es, which
+/// depend on every file loaded before it. Spans stored in metadata or in incremental
+/// caches can be encoded like this, so that they still point at the same code when they
+/// are decoded against a `SourceMap` in which other files were added, removed or edited.
+/// The syntax context of the span isn't kept.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, RustcEncodable, RustcDecodable, Debug)]
-pub struct StableSourceFileId(u128);
-
-impl StableSourceFileId {
-    pub fn new(source_file: &SourceFile) -> StableSourceFileId {
-        StableSourceFileId::new_from_pieces(&source_file.name,
-                                            source_file.name_was_remapped,
-                                            source_file.unmapped_path.as_ref())
-    }
-
-    pub fn new_from_pieces(name: &FileName,
-                           name_was_remapped: bool,
-                           unmapped_path: Option<&FileName>) -> StableSourceFileId {
-        let mut hasher = StableHasher::new();
+pub struct StableSpan {
+    pub file: StableSourceFileId,
+    /// The 1-based line the span starts on.
+    pub line: u32,
+    /// The offset in bytes of the start of the span from the start of `line`.
+    pub col: u32,
+    /// The length of the span in bytes.
+    pub len: u32,
+}
 
-        name.hash(&mut hasher);
-        name_was_remapped.hash(&mut hasher);
-        unmapped_path.hash(&mut hasher);
+/// A position in a file as the Language Server Protocol describes it.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct LspPosition {
+    /// The 0-based line.
+    pub line: usize,
+    /// The 0-based offset from the start of `line`, in UTF-16 code units.
+    pub character: usize,
+}
 
-        StableSourceFileId(hasher.finish())
-    }
+/// A range in a file as the Language Server Protocol describes it, see `span_to_lsp_range`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct LspRange {
+    pub start: LspPosition,
+    /// The position right after the end of the range.
+    pub end: LspPosition,
 }
 
 // _____________________________________________________________________________
@@ -136,6 +156,10 @@ pub struct SourceMap {
     // This is used to apply the file path remapping as specified via
     // `--remap-path-prefix` to all `SourceFile`s allocated within this `SourceMap`.
     path_mapping: FilePathMapping,
+    /// Asked in order for the sources of files that aren't on disk.
+    source_providers: Lock<Vec<Lrc<dyn SourceProvider + Sync + Send>>>,
+    /// The items of the crate being compiled, shared with the `ParseSess`.
+    item_index: Lrc<Lock<ItemIndex>>,
 }
 
 impl SourceMap {
@@ -144,6 +168,8 @@ impl SourceMap {
             files: Default::default(),
             file_loader: Box::new(RealFileLoader),
             path_mapping,
+            source_providers: Default::default(),
+            item_index: Default::default(),
         }
     }
 
@@ -154,6 +180,8 @@ impl SourceMap {
             files: Default::default(),
             file_loader,
             path_mapping,
+            source_providers: Default::default(),
+            item_index: Default::default(),
         }
     }
 
@@ -161,6 +189,18 @@ impl SourceMap {
         &self.path_mapping
     }
 
+    /// Registers a provider to ask for the sources of files that can't be read from disk.
+    /// Providers are asked in the order they were registered.
+    pub fn register_source_provider(&self, provider: Lrc<dyn SourceProvider + Sync + Send>) {
+        self.source_providers.lock().push(provider);
+    }
+
+    /// Returns the index of the items of the crate being compiled, which is empty until it's
+    /// built after expansion, see `ParseSess::item_index`.
+    pub fn item_index(&self) -> Lrc<Lock<ItemIndex>> {
+        self.item_index.clone()
+    }
+
     pub fn file_exists(&self, path: &Path) -> bool {
         self.file_loader.file_exists(path)
     }
@@ -200,6 +240,80 @@ impl SourceMap {
         self.files.borrow().stable_id_to_source_file.get(&stable_id).map(|sf| sf.clone())
     }
 
+    /// Describes `sp` by its position in its file, to be re-resolved by
+    /// `resolve_stable_span`, possibly in a later session. Returns `None` for dummy spans
+    /// and for spans that don't start and end in the same file.
+    pub fn stable_span(&self, sp: Span) -> Option<StableSpan> {
+        if sp.is_dummy() {
+            return None;
+        }
+        let sp = sp.data();
+        let sf = self.lookup_byte_offset(sp.lo).sf;
+        if !sf.contains(sp.hi) {
+            return None;
+        }
+        let line_index = sf.lookup_line(sp.lo)?;
+        Some(StableSpan {
+            file: StableSourceFileId::new(&sf),
+            line: line_index as u32 + 1,
+            col: (sp.lo - sf.lines[line_index]).to_u32(),
+            len: (sp.hi - sp.lo).to_u32(),
+        })
+    }
+
+    /// Finds the span that `stable_span` describes in this `SourceMap`. Returns `None` if
+    /// its file isn't loaded, or if the file is now too short to contain it.
+    pub fn resolve_stable_span(&self, stable_span: StableSpan) -> Option<Span> {
+        let sf = self.source_file_by_stable_id(stable_span.file)?;
+        if stable_span.line == 0 {
+            return None;
+        }
+        let line_index = stable_span.line as usize - 1;
+        if line_index >= sf.lines.len() {
+            return None;
+        }
+        let (line_lo, line_hi) = sf.line_bounds(line_index);
+        let lo = line_lo + BytePos(stable_span.col);
+        // The column has to fall on the line, although the span may go on past it.
+        if lo > line_hi {
+            return None;
+        }
+        let hi = lo.to_u32().checked_add(stable_span.len).map(BytePos)?;
+        if hi > sf.end_pos {
+            return None;
+        }
+        Some(Span::with_root_ctxt(lo, hi))
+    }
+
+    /// Marks all the files of the crate being compiled as compiled in the diagnostic replay
+    /// cache `handler` is recording, if any.
+    pub fn mark_compiled_for_replay(&self, handler: &Handler) {
+        for source_file in self.files().iter().filter(|sf| !sf.is_imported()) {
+            handler.mark_compiled_for_replay(source_file);
+        }
+    }
+
+    /// Replays the diagnostics of the crate being compiled from `cache`, if all its files are
+    /// in it, i.e. none of them changed since the cache was recorded, and all its diagnostics
+    /// could be recorded. Returns `false`, without emitting anything, otherwise.
+    pub fn replay_diagnostics(&self, handler: &Handler, cache: &DiagnosticReplayCache) -> bool {
+        let local_files: Vec<_> = self.files().iter()
+            .filter(|sf| !sf.is_imported())
+            .cloned()
+            .collect();
+        if local_files.is_empty()
+            || !cache.is_crate_replayable()
+            || !local_files.iter().all(|sf| cache.contains(sf))
+        {
+            return false;
+        }
+        for source_file in &local_files {
+            handler.replay_diagnostics(cache, source_file);
+        }
+        handler.replay_crate_diagnostics(cache);
+        true
+    }
+
     fn next_start_pos(&self) -> usize {
         match self.files.borrow().source_files.last() {
             None => 0,
@@ -283,6 +397,7 @@ impl SourceMap {
         mut file_local_lines: Vec<BytePos>,
         mut file_local_multibyte_chars: Vec<MultiByteChar>,
         mut file_local_non_narrow_chars: Vec<NonNarrowChar>,
+        mut file_local_normalized_pos: Vec<NormalizedPos>,
     ) -> Lrc<SourceFile> {
         let start_pos = self.next_start_pos();
 
@@ -301,6 +416,10 @@ impl SourceMap {
             *swc = *swc + start_pos;
         }
 
+        for np in &mut file_local_normalized_pos {
+            np.pos = np.pos + start_pos;
+        }
+
         let source_file = Lrc::new(SourceFile {
             name: filename,
             name_was_remapped,
@@ -314,6 +433,7 @@ impl SourceMap {
             lines: file_local_lines,
             multibyte_chars: file_local_multibyte_chars,
             non_narrow_chars: file_local_non_narrow_chars,
+            normalized_pos: file_local_normalized_pos,
             name_hash,
         });
 
@@ -411,6 +531,64 @@ impl SourceMap {
         }
     }
 
+    /// Returns the zero-based column of `pos` in its line, in bytes.
+    pub fn lookup_byte_column(&self, pos: BytePos) -> usize {
+        let (_, line_start) = self.lookup_line_start(pos);
+        (pos - line_start).to_usize()
+    }
+
+    /// Returns the zero-based column of `pos` in its line, in UTF-16 code units, which is
+    /// how the Language Server Protocol counts columns.
+    pub fn lookup_utf16_column(&self, pos: BytePos) -> usize {
+        let (file, line_start) = self.lookup_line_start(pos);
+        let extra_units: usize = file.multibyte_chars.iter()
+            .filter(|mbc| mbc.pos >= line_start && mbc.pos < pos)
+            // Characters outside of the Basic Multilingual Plane are encoded in four bytes
+            // and two code units, the other multibyte characters in a single code unit.
+            .map(|mbc| mbc.bytes as usize - if mbc.bytes == 4 { 2 } else { 1 })
+            .sum();
+        (pos - line_start).to_usize() - extra_units
+    }
+
+    /// Returns where `pos` is in its file, as the Language Server Protocol counts positions.
+    pub fn lookup_lsp_position(&self, pos: BytePos) -> LspPosition {
+        let line = match self.lookup_line(pos) {
+            Ok(SourceFileAndLine { line, .. }) => line,
+            Err(_) => 0,
+        };
+        LspPosition { line, character: self.lookup_utf16_column(pos) }
+    }
+
+    /// Converts `sp` to the file it's in and a range in that file, as the Language Server
+    /// Protocol describes ranges.
+    ///
+    /// The columns are counted in UTF-16 code units, so characters outside of the Basic
+    /// Multilingual Plane count as the two halves of their surrogate pair. Files are loaded
+    /// without their byte order mark and with `\r\n` line endings normalized to `\n`, which
+    /// matches the documents editors send to language servers: the mark isn't counted in
+    /// the first line, and the `\r`s end lines without moving any position inside of them.
+    pub fn span_to_lsp_range(&self, sp: Span) -> (FileName, LspRange) {
+        let range = LspRange {
+            start: self.lookup_lsp_position(sp.lo()),
+            end: self.lookup_lsp_position(sp.hi()),
+        };
+        (self.span_to_filename(sp), range)
+    }
+
+    /// Returns the file `pos` is in and the position its line starts at.
+    fn lookup_line_start(&self, pos: BytePos) -> (Lrc<SourceFile>, BytePos) {
+        match self.lookup_line(pos) {
+            Ok(SourceFileAndLine { sf, line }) => {
+                let line_start = sf.lines[line];
+                (sf, line_start)
+            }
+            Err(sf) => {
+                let file_start = sf.start_pos;
+                (sf, file_start)
+            }
+        }
+    }
+
     // If the corresponding `SourceFile` is empty, does not return a line number.
     pub fn lookup_line(&self, pos: BytePos) -> Result<SourceFileAndLine, Lrc<SourceFile>> {
         let idx = self.lookup_source_file_idx(pos);
@@ -423,41 +601,71 @@ impl SourceMap {
         }
     }
 
-    /// Returns `Some(span)`, a union of the LHS and RHS span. The LHS must precede the RHS. If
-    /// there are gaps between LHS and RHS, the resulting union will cross these gaps.
+    /// Returns `Some(span)`, a union of the LHS and RHS span, if they can be merged according
+    /// to `try_merge_spans`.
+    pub fn merge_spans(&self, sp_lhs: Span, sp_rhs: Span) -> Option<Span> {
+        self.try_merge_spans(sp_lhs, sp_rhs).ok()
+    }
+
+    /// Returns the union of the LHS and RHS span. The LHS must precede the RHS. If there are
+    /// gaps between LHS and RHS, the resulting union will cross these gaps.
     /// For this to work,
     ///
-    ///    * the syntax contexts of both spans much match,
+    ///    * neither span may be dummy,
+    ///    * the syntax contexts of both spans must match,
+    ///    * both spans must be in the same file,
     ///    * the LHS span needs to end on the same line the RHS span begins,
-    ///    * the LHS span must start at or before the RHS span.
-    pub fn merge_spans(&self, sp_lhs: Span, sp_rhs: Span) -> Option<Span> {
+    ///    * the LHS span must end at or before the start of the RHS span.
+    ///
+    /// The first of these conditions that isn't met is returned otherwise.
+    pub fn try_merge_spans(&self, sp_lhs: Span, sp_rhs: Span) -> Result<Span, SpanMergeError> {
+        if sp_lhs.is_dummy() || sp_rhs.is_dummy() {
+            return Err(SpanMergeError::DummySpan);
+        }
         // Ensure we're at the same expansion ID.
         if sp_lhs.ctxt() != sp_rhs.ctxt() {
-            return None;
+            return Err(SpanMergeError::DifferentContexts);
         }
 
-        let lhs_end = match self.lookup_line(sp_lhs.hi()) {
-            Ok(x) => x,
-            Err(_) => return None
-        };
-        let rhs_begin = match self.lookup_line(sp_rhs.lo()) {
-            Ok(x) => x,
-            Err(_) => return None
+        if self.lookup_source_file_idx(sp_lhs.hi()) != self.lookup_source_file_idx(sp_rhs.lo()) {
+            return Err(SpanMergeError::DifferentFiles);
+        }
+        let lines = (self.lookup_line(sp_lhs.hi()), self.lookup_line(sp_rhs.lo()));
+        let (lhs_end, rhs_begin) = match lines {
+            (Ok(lhs_end), Ok(rhs_begin)) => (lhs_end, rhs_begin),
+            _ => return Err(SpanMergeError::NoLine),
         };
 
         // If we must cross lines to merge, don't merge.
         if lhs_end.line != rhs_begin.line {
-            return None;
+            return Err(SpanMergeError::DifferentLines);
         }
 
         // Ensure these follow the expected order and that we don't overlap.
         if (sp_lhs.lo() <= sp_rhs.lo()) && (sp_lhs.hi() <= sp_rhs.lo()) {
-            Some(sp_lhs.to(sp_rhs))
+            Ok(sp_lhs.to(sp_rhs))
         } else {
-            None
+            Err(SpanMergeError::Overlapping)
         }
     }
 
+    /// Merges each run of consecutive spans of `spans` that `try_merge_spans` can merge one
+    /// after the other, like the spans of the words of a line, into a single span. The spans
+    /// that can't be merged with the ones before them start new runs.
+    pub fn try_merge_ordered(&self, spans: Vec<Span>) -> Vec<Span> {
+        let mut merged: Vec<Span> = Vec::with_capacity(spans.len());
+        for span in spans {
+            if let Some(last) = merged.last_mut() {
+                if let Ok(run) = self.try_merge_spans(*last, span) {
+                    *last = run;
+                    continue;
+                }
+            }
+            merged.push(span);
+        }
+        merged
+    }
+
     pub fn span_to_string(&self, sp: Span) -> String {
         if self.files.borrow().source_files.is_empty() && sp.is_dummy() {
             return "no-location".to_string();
@@ -833,6 +1041,69 @@ impl SourceMap {
         }
     }
 
+    /// Returns the span of the bytes `cooked` of the value of the string or byte string literal
+    /// at `lit_span`, mapping indices into the unescaped value back through escapes and the
+    /// prefix and quotes of the literal. A range that starts or ends within the value of an
+    /// escape covers the whole escape.
+    ///
+    /// Returns `None` if `lit_span` does not cover exactly a well-formed string literal, or if
+    /// `cooked` is not a range within its value.
+    pub fn str_literal_sub_span(&self, lit_span: Span, cooked: Range<usize>) -> Option<Span> {
+        let snippet = self.span_to_snippet(lit_span).ok()?;
+        let (body, body_offset, is_byte, is_raw) = str_literal_body(&snippet)?;
+
+        // For every unescaped character, its range in the body and the length of its value.
+        let mut chars = Vec::new();
+        let mut well_formed = true;
+        {
+            let mut push = |range: Range<usize>, len: Option<usize>| match len {
+                Some(len) => chars.push((range, len)),
+                None => well_formed = false,
+            };
+            match (is_byte, is_raw) {
+                (false, false) => unescape::unescape_str(body, &mut |range, c| {
+                    push(range, c.ok().map(char::len_utf8))
+                }),
+                (false, true) => unescape::unescape_raw_str(body, &mut |range, c| {
+                    push(range, c.ok().map(char::len_utf8))
+                }),
+                (true, false) => unescape::unescape_byte_str(body, &mut |range, b| {
+                    push(range, b.ok().map(|_| 1))
+                }),
+                (true, true) => unescape::unescape_raw_byte_str(body, &mut |range, b| {
+                    push(range, b.ok().map(|_| 1))
+                }),
+            }
+        }
+        if !well_formed {
+            return None;
+        }
+
+        let cooked_len = chars.iter().map(|&(_, len)| len).sum::<usize>();
+        if cooked.start > cooked.end || cooked.end > cooked_len {
+            return None;
+        }
+
+        // Maps an index into the value to a position in the body, rounding down to the start of
+        // the character containing it if `round_down` is set, or up to its end otherwise.
+        let to_body = |index: usize, round_down: bool| {
+            let mut cooked_pos = 0;
+            for (range, len) in &chars {
+                if index == cooked_pos || (round_down && index < cooked_pos + len) {
+                    return range.start;
+                }
+                cooked_pos += len;
+                if index < cooked_pos || (!round_down && index == cooked_pos) {
+                    return range.end;
+                }
+            }
+            body.len()
+        };
+        let start = to_body(cooked.start, true);
+        let end = if cooked.start == cooked.end { start } else { to_body(cooked.end, false) };
+        Some(lit_span.from_inner(InnerSpan::new(body_offset + start, body_offset + end)))
+    }
+
     pub fn get_source_file(&self, filename: &FileName) -> Option<Lrc<SourceFile>> {
         for sf in self.files.borrow().source_files.iter() {
             if *filename == sf.name {
@@ -977,10 +1248,37 @@ impl SourceMap {
     }
 }
 
+/// Splits a string or byte string literal into its body and the offset of the body, and
+/// determines whether it is a byte string and whether it is raw.
+fn str_literal_body(literal: &str) -> Option<(&str, usize, bool, bool)> {
+    let is_byte = literal.starts_with('b');
+    let rest = if is_byte { &literal[1..] } else { literal };
+    let is_raw = rest.starts_with('r');
+    let rest = if is_raw { &rest[1..] } else { rest };
+    let hashes = rest.len() - rest.trim_start_matches('#').len();
+    let rest = &rest[hashes..];
+    if !is_raw && hashes != 0 {
+        return None;
+    }
+
+    let closing = format!("\"{}", "#".repeat(hashes));
+    if rest.len() < 1 + closing.len() || !rest.starts_with('"') || !rest.ends_with(&closing) {
+        return None;
+    }
+    let body_offset = literal.len() - rest.len() + 1;
+    Some((&rest[1..rest.len() - closing.len()], body_offset, is_byte, is_raw))
+}
+
 impl SourceMapper for SourceMap {
     fn lookup_char_pos(&self, pos: BytePos) -> Loc {
         self.lookup_char_pos(pos)
     }
+    fn lookup_byte_column(&self, pos: BytePos) -> usize {
+        self.lookup_byte_column(pos)
+    }
+    fn lookup_utf16_column(&self, pos: BytePos) -> usize {
+        self.lookup_utf16_column(pos)
+    }
     fn span_to_lines(&self, sp: Span) -> FileLinesResult {
         self.span_to_lines(sp)
     }
@@ -993,6 +1291,12 @@ impl SourceMapper for SourceMap {
     fn merge_spans(&self, sp_lhs: Span, sp_rhs: Span) -> Option<Span> {
         self.merge_spans(sp_lhs, sp_rhs)
     }
+    fn try_merge_spans(&self, sp_lhs: Span, sp_rhs: Span) -> Result<Span, SpanMergeError> {
+        self.try_merge_spans(sp_lhs, sp_rhs)
+    }
+    fn try_merge_ordered(&self, spans: Vec<Span>) -> Vec<Span> {
+        self.try_merge_ordered(spans)
+    }
     fn call_span_if_macro(&self, sp: Span) -> Span {
         if self.span_to_filename(sp.clone()).is_macros() {
             let v = sp.macro_backtrace();
@@ -1003,16 +1307,52 @@ impl SourceMapper for SourceMap {
         sp
     }
     fn ensure_source_file_source_present(&self, source_file: Lrc<SourceFile>) -> bool {
-        source_file.add_external_src(
-            || match source_file.name {
+        source_file.add_external_src(|| {
+            let from_disk = match source_file.name {
                 FileName::Real(ref name) => self.file_loader.read_file(name).ok(),
                 _ => None,
-            }
-        )
+            };
+            from_disk.filter(|src| source_file.src_hash_matches(src)).or_else(|| {
+                // Providers may look at the source map themselves, so don't keep it locked.
+                let providers = self.source_providers.lock().clone();
+                providers.iter()
+                    .filter_map(|provider| provider.provide_source(&source_file))
+                    .find(|src| source_file.src_hash_matches(src))
+            })
+        })
     }
     fn doctest_offset_line(&self, file: &FileName, line: usize) -> usize {
         self.doctest_offset_line(file, line)
     }
+    fn enclosing_item(&self, sp: Span) -> Option<String> {
+        self.item_index.lock().query(sp.lo()).map(|path| path.innermost().descr.clone())
+    }
+    fn span_from_line_columns(
+        &self,
+        file_name: &str,
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> Option<Span> {
+        let loaded = self.files().iter().find(|sf| sf.name.to_string() == file_name).cloned();
+        let sf = match loaded {
+            Some(sf) => sf,
+            None => self.load_file(Path::new(file_name)).ok()?,
+        };
+        if !self.ensure_source_file_source_present(sf.clone()) {
+            return None;
+        }
+        let pos = |(line, col): (usize, usize)| {
+            let line_index = line.checked_sub(1)?;
+            let text = sf.get_line(line_index)?;
+            // The column right past the last character is that of the line ending.
+            let offset = text.char_indices()
+                .map(|(offset, _)| offset)
+                .chain(iter::once(text.len()))
+                .nth(col.checked_sub(1)?)?;
+            Some(sf.line_bounds(line_index).0 + BytePos::from_usize(offset))
+        };
+        Some(Span::with_root_ctxt(pos(start)?, pos(end)?))
+    }
 }
 
 #[derive(Clone)]
@@ -173,6 +173,53 @@ fn span_merging_fail() {
     assert!(sm.merge_spans(span1, span2).is_none());
 }
 
+/// Tests why `try_merge_spans` fails to merge spans, and merging spans on the same line.
+#[test]
+fn span_merging_errors() {
+    crate::with_default_globals(|| {
+        let sm = init_source_map();
+        let span = |lo, hi| Span::with_root_ctxt(BytePos(lo), BytePos(hi));
+
+        // `first` and `line` in `blork.rs`.
+        assert_eq!(sm.try_merge_spans(span(0, 5), span(6, 10)), Ok(span(0, 10)));
+        assert_eq!(sm.try_merge_spans(span(0, 5), DUMMY_SP), Err(SpanMergeError::DummySpan));
+        assert_eq!(sm.try_merge_spans(span(0, 5), span(3, 10)), Err(SpanMergeError::Overlapping));
+        assert_eq!(sm.try_merge_spans(span(6, 10), span(0, 5)), Err(SpanMergeError::Overlapping));
+        // `first` and `second`, on the next line.
+        assert_eq!(
+            sm.try_merge_spans(span(0, 5), span(12, 18)),
+            Err(SpanMergeError::DifferentLines),
+        );
+        // `first` in `blork.rs` and `line` on the first line of `blork2.rs`.
+        assert_eq!(
+            sm.try_merge_spans(span(0, 5), span(31, 35)),
+            Err(SpanMergeError::DifferentFiles),
+        );
+
+        let expn_id = ExpnId::fresh(None);
+        let marked = span(6, 10).apply_mark(expn_id, hygiene::Transparency::Opaque);
+        assert_eq!(
+            sm.try_merge_spans(span(0, 5), marked),
+            Err(SpanMergeError::DifferentContexts),
+        );
+        assert_eq!(sm.merge_spans(span(0, 5), marked), None);
+    })
+}
+
+/// Tests merging the runs of consecutive spans that can be merged.
+#[test]
+fn span_merging_runs() {
+    let sm = init_source_map();
+    let span = |lo, hi| Span::with_root_ctxt(BytePos(lo), BytePos(hi));
+
+    // The words of the first line, of the second line, and the first word of `blork2.rs`.
+    let words = vec![span(0, 5), span(6, 10), span(12, 18), span(19, 23), span(25, 30)];
+    assert_eq!(sm.try_merge_ordered(words), [span(0, 10), span(12, 23), span(25, 30)]);
+    // `first`, twice.
+    assert_eq!(sm.try_merge_ordered(vec![span(0, 5), span(0, 5)]), [span(0, 5), span(0, 5)]);
+    assert!(sm.try_merge_ordered(Vec::new()).is_empty());
+}
+
 /// Returns the span corresponding to the `n`th occurrence of `substring` in `source_text`.
 trait SourceMapExtension {
     fn span_substr(
@@ -219,3 +266,248 @@ impl SourceMapExtension for SourceMap {
         }
     }
 }
+
+/// Returns the snippets covered by `str_literal_sub_span` for each of the `ranges` into the
+/// value of the string literal `literal`.
+fn literal_sub_snippets(literal: &str, ranges: &[Range<usize>]) -> Vec<Option<String>> {
+    let sm = SourceMap::new(FilePathMapping::empty());
+    let src = format!("let _ = {};", literal);
+    let file = sm.new_source_file(PathBuf::from("lit.rs").into(), src);
+    let lo = file.start_pos + BytePos(8);
+    let lit_span = Span::with_root_ctxt(lo, lo + BytePos(literal.len() as u32));
+    ranges.iter().map(|range| {
+        sm.str_literal_sub_span(lit_span, range.clone()).map(|span| {
+            sm.span_to_snippet(span).unwrap()
+        })
+    }).collect()
+}
+
+fn snippets(snippets: &[&str]) -> Vec<Option<String>> {
+    snippets.iter().map(|s| Some(s.to_string())).collect()
+}
+
+#[test]
+fn str_literal_sub_span_plain() {
+    assert_eq!(
+        literal_sub_snippets(r#""hello {}""#, &[0..5, 6..8, 0..0, 8..8]),
+        snippets(&["hello", "{}", "", ""]),
+    );
+}
+
+#[test]
+fn str_literal_sub_span_escapes() {
+    // The value is `a\n{}"b`.
+    let literal = r#""a\n{}\"b""#;
+    assert_eq!(
+        literal_sub_snippets(literal, &[0..1, 1..2, 2..4, 4..5, 5..6, 0..6]),
+        snippets(&["a", "\\n", "{}", "\\\"", "b", r#"a\n{}\"b"#]),
+    );
+}
+
+#[test]
+fn str_literal_sub_span_unicode_escapes() {
+    // `\u{1F600}` is four bytes long and `\u{e9}` two bytes long in the value.
+    let literal = r#""x\u{1F600}{0}\u{e9}""#;
+    assert_eq!(
+        literal_sub_snippets(literal, &[0..1, 1..5, 5..8, 8..10]),
+        snippets(&["x", "\\u{1F600}", "{0}", "\\u{e9}"]),
+    );
+    // Ranges starting or ending in the middle of an escaped character cover all of it.
+    assert_eq!(
+        literal_sub_snippets(literal, &[2..3, 3..6, 9..10]),
+        snippets(&["\\u{1F600}", "\\u{1F600}{", "\\u{e9}"]),
+    );
+}
+
+#[test]
+fn str_literal_sub_span_multibyte_source() {
+    assert_eq!(
+        literal_sub_snippets("\"é{}ü\"", &[0..2, 2..4, 4..6]),
+        snippets(&["é", "{}", "ü"]),
+    );
+}
+
+#[test]
+fn str_literal_sub_span_line_continuation() {
+    // The value is `ab{}`.
+    let literal = "\"ab\\\n    {}\"";
+    assert_eq!(
+        literal_sub_snippets(literal, &[0..2, 2..4, 1..3]),
+        snippets(&["ab", "{}", "b\\\n    {"]),
+    );
+}
+
+#[test]
+fn str_literal_sub_span_raw() {
+    assert_eq!(
+        literal_sub_snippets(r###"r#"a\n"{}"#"###, &[0..3, 3..4, 4..6]),
+        snippets(&["a\\n", "\"", "{}"]),
+    );
+    assert_eq!(
+        literal_sub_snippets(r#"r"{x}""#, &[0..3, 1..2]),
+        snippets(&["{x}", "x"]),
+    );
+}
+
+#[test]
+fn str_literal_sub_span_byte_strings() {
+    assert_eq!(
+        literal_sub_snippets(r#"b"a\x7f{}""#, &[0..1, 1..2, 2..4]),
+        snippets(&["a", "\\x7f", "{}"]),
+    );
+    assert_eq!(
+        literal_sub_snippets(r##"br#"\x{}"#"##, &[0..2, 2..4]),
+        snippets(&["\\x", "{}"]),
+    );
+}
+
+#[test]
+fn str_literal_sub_span_rejects_bad_input() {
+    assert_eq!(literal_sub_snippets(r#""abc""#, &[2..4, 3..2]), vec![None, None]);
+    assert_eq!(literal_sub_snippets("'a'", &[0..1]), vec![None]);
+    assert_eq!(literal_sub_snippets(r#"r#"a""#, &[0..1]), vec![None]);
+    assert_eq!(literal_sub_snippets(r#""\q""#, &[0..1]), vec![None]);
+}
+
+struct MapSourceProvider(Vec<(FileName, String)>);
+
+impl SourceProvider for MapSourceProvider {
+    fn provide_source(&self, source_file: &SourceFile) -> Option<String> {
+        self.0.iter().find(|(name, _)| *name == source_file.name).map(|(_, src)| src.clone())
+    }
+}
+
+/// Tests loading the source of an imported file that isn't on disk from a `SourceProvider`.
+#[test]
+fn source_provider_provides_missing_source() {
+    let src = "fn main() {\n    let x = 1;\n}\n";
+    let name: FileName = PathBuf::from("/nonexistent/dependency/src/lib.rs").into();
+    let original = SourceMap::new(FilePathMapping::empty())
+        .new_source_file(name.clone(), src.to_string());
+    let import = |sm: &SourceMap| sm.new_imported_source_file(
+        name.clone(),
+        false,
+        1,
+        original.src_hash,
+        original.name_hash,
+        src.len(),
+        original.lines.clone(),
+        original.multibyte_chars.clone(),
+        original.non_narrow_chars.clone(),
+        original.normalized_pos.clone(),
+    );
+
+    let sm = SourceMap::new(FilePathMapping::empty());
+    let file = import(&sm);
+    assert!(!sm.ensure_source_file_source_present(file.clone()));
+
+    // A provider whose source doesn't match the original one is ignored.
+    let sm = SourceMap::new(FilePathMapping::empty());
+    let file = import(&sm);
+    sm.register_source_provider(Lrc::new(MapSourceProvider(vec![
+        (name.clone(), "fn main() {}\n".to_string()),
+    ])));
+    assert!(!sm.ensure_source_file_source_present(file.clone()));
+
+    let sm = SourceMap::new(FilePathMapping::empty());
+    let file = import(&sm);
+    sm.register_source_provider(Lrc::new(MapSourceProvider(vec![])));
+    sm.register_source_provider(Lrc::new(MapSourceProvider(vec![
+        (name.clone(), src.to_string()),
+    ])));
+    assert!(sm.ensure_source_file_source_present(file.clone()));
+    let span = Span::with_root_ctxt(file.start_pos + BytePos(16), file.start_pos + BytePos(26));
+    assert_eq!(sm.span_to_snippet(span).unwrap(), "let x = 1;");
+}
+
+/// Tests re-resolving `StableSpan`s in a `SourceMap` in which the files are at other
+/// positions.
+#[test]
+fn stable_span_resolves_in_shifted_source_map() {
+    let src = "fn main() {\n    let x = 1;\n    let y = x;\n}\n";
+    let sm = init_source_map();
+    let sf = sm.new_source_file(PathBuf::from("main.rs").into(), src.to_string());
+    let span = Span::with_root_ctxt(sf.start_pos + BytePos(24), sf.start_pos + BytePos(40));
+    assert_eq!(sm.span_to_snippet(span).unwrap(), "1;\n    let y = x");
+
+    let stable = sm.stable_span(span).unwrap();
+    assert_eq!((stable.line, stable.col, stable.len), (2, 12, 16));
+    assert_eq!(sm.stable_span(DUMMY_SP), None);
+
+    let shifted = SourceMap::new(FilePathMapping::empty());
+    assert_eq!(shifted.resolve_stable_span(stable), None);
+    shifted.new_source_file(PathBuf::from("other.rs").into(), "// other\n".repeat(10));
+    shifted.new_source_file(PathBuf::from("main.rs").into(), src.to_string());
+    let resolved = shifted.resolve_stable_span(stable).unwrap();
+    assert_ne!(resolved, span);
+    assert_eq!(shifted.span_to_snippet(resolved).unwrap(), "1;\n    let y = x");
+
+    // The file was truncated since the span was encoded.
+    let truncated = SourceMap::new(FilePathMapping::empty());
+    truncated.new_source_file(PathBuf::from("main.rs").into(), "fn main() {}\n".to_string());
+    assert_eq!(truncated.resolve_stable_span(stable), None);
+}
+
+/// Tests `lookup_byte_column` and `lookup_utf16_column` in the presence of multi-byte chars.
+#[test]
+fn byte_and_utf16_columns() {
+    let sm = SourceMap::new(FilePathMapping::empty());
+    // "€" is three bytes and one UTF-16 code unit, "😀" four bytes and two code units.
+    sm.new_source_file(PathBuf::from("blork.rs").into(), "a€b😀c\n😀x".to_string());
+
+    assert_eq!(sm.lookup_byte_column(BytePos(4)), 4);
+    assert_eq!(sm.lookup_utf16_column(BytePos(4)), 2);
+    assert_eq!(sm.lookup_byte_column(BytePos(9)), 9);
+    assert_eq!(sm.lookup_utf16_column(BytePos(9)), 5);
+    assert_eq!(sm.lookup_byte_column(BytePos(15)), 4);
+    assert_eq!(sm.lookup_utf16_column(BytePos(15)), 2);
+}
+
+fn lsp_range(start: (usize, usize), end: (usize, usize)) -> LspRange {
+    LspRange {
+        start: LspPosition { line: start.0, character: start.1 },
+        end: LspPosition { line: end.0, character: end.1 },
+    }
+}
+
+/// Tests `span_to_lsp_range` with characters taking two UTF-16 code units.
+#[test]
+fn lsp_range_surrogate_pairs() {
+    let sm = SourceMap::new(FilePathMapping::empty());
+    let src = "//😀\nlet 😀 = \"€😀\";".to_string();
+    let sf = sm.new_source_file(PathBuf::from("main.rs").into(), src);
+    let start = sf.start_pos;
+    let span = |lo, hi| Span::with_root_ctxt(start + BytePos(lo), start + BytePos(hi));
+
+    let (name, range) = sm.span_to_lsp_range(span(2, 6));
+    assert_eq!(name, PathBuf::from("main.rs").into());
+    assert_eq!(range, lsp_range((0, 2), (0, 4)));
+    // `= "€😀"`, after the emoji of the second line.
+    assert_eq!(sm.span_to_lsp_range(span(16, 27)).1, lsp_range((1, 7), (1, 14)));
+    // From the emoji of the first line to the end of the file.
+    assert_eq!(sm.span_to_lsp_range(span(2, 28)).1, lsp_range((0, 2), (1, 15)));
+}
+
+/// Tests `span_to_lsp_range` in files that were loaded with a byte order mark and with
+/// `\r\n` line endings.
+#[test]
+fn lsp_range_bom_and_crlf() {
+    let sm = init_source_map();
+    let sf = sm.new_source_file(
+        PathBuf::from("crlf.rs").into(),
+        "\u{feff}fn f() {\r\n    g(\"😀\");\r\n}\r\n".to_string(),
+    );
+    assert_eq!(&sf.src.as_ref().unwrap()[..], "fn f() {\n    g(\"😀\");\n}\n");
+    let start = sf.start_pos;
+    let span = |lo, hi| Span::with_root_ctxt(start + BytePos(lo), start + BytePos(hi));
+
+    // `f`, which the byte order mark doesn't move.
+    assert_eq!(sm.span_to_lsp_range(span(3, 4)).1, lsp_range((0, 3), (0, 4)));
+    // `g("😀")`, on a line after a `\r\n`.
+    assert_eq!(sm.span_to_lsp_range(span(13, 22)).1, lsp_range((1, 4), (1, 11)));
+    // `;` to the end of the line, and the whole function.
+    assert_eq!(sm.span_to_lsp_range(span(22, 23)).1, lsp_range((1, 11), (1, 12)));
+    assert_eq!(sm.span_to_lsp_range(span(0, 25)).1, lsp_range((0, 0), (2, 1)));
+    // The end of the file.
+    assert_eq!(sm.span_to_lsp_range(span(26, 26)).1, lsp_range((3, 0), (3, 0)));
+}
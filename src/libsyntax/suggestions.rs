@@ -0,0 +1,115 @@
+//! Source edits for suggestions that need to change code away from the diagnosed span,
+//! such as adding the `use` declaration that brings a missing name into scope.
+
+use crate::ast::{self, ItemKind, UseTreeKind};
+use crate::print::pprust;
+use crate::source_map::SourceMap;
+
+use syntax_pos::Span;
+
+#[cfg(test)]
+mod tests;
+
+/// An edit inserting `text` at the empty `span`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Insertion {
+    pub span: Span,
+    pub text: String,
+}
+
+/// Computes how to import `path`, written like `std::collections::HashMap`, into `module`,
+/// following the style of the imports already present:
+///
+/// * If `module` imports from the parent of `path` through a group, like
+///   `use std::collections::{BTreeMap, HashSet};`, the name is added to the group, keeping
+///   the group sorted.
+/// * Otherwise, a new `use` declaration is added among the existing ones, keeping them sorted,
+///   or in front of the first item after any `extern crate` if there are none.
+///
+/// Items produced by macro expansion, like the injected prelude import, are never used as
+/// anchors. Returns `None` if `path` is already imported or if no suitable location exists.
+pub fn use_insertion(source_map: &SourceMap, module: &ast::Mod, path: &str) -> Option<Insertion> {
+    let (parent, name) = match path.rfind("::") {
+        Some(pos) => (&path[..pos], &path[pos + 2..]),
+        None => ("", path),
+    };
+    let uses: Vec<_> = module.items.iter().filter_map(|item| match item.node {
+        ItemKind::Use(ref tree) if !item.span.from_expansion() => Some((&**item, &**tree)),
+        _ => None,
+    }).collect();
+
+    for &(_, tree) in &uses {
+        let prefix = pprust::path_to_string(&tree.prefix);
+        match tree.kind {
+            UseTreeKind::Simple(None, ..) if prefix == path => return None,
+            UseTreeKind::Nested(ref trees) if prefix == parent && !trees.is_empty() => {
+                let names: Vec<_> = trees.iter()
+                    .map(|(tree, _)| pprust::path_to_string(&tree.prefix))
+                    .collect();
+                if names.iter().any(|n| n == name) {
+                    return None;
+                }
+                // `self` stays in front of the group.
+                let next = trees.iter().zip(&names)
+                    .find(|(_, n)| n.as_str() != "self" && n.as_str() > name);
+                return Some(match next {
+                    Some(((next, _), _)) => Insertion {
+                        span: next.span.shrink_to_lo(),
+                        text: format!("{}, ", name),
+                    },
+                    None => Insertion {
+                        span: trees[trees.len() - 1].0.span.shrink_to_hi(),
+                        text: format!(", {}", name),
+                    },
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let decl = format!("use {};", path);
+    let next = uses.iter().find(|(_, tree)| pprust::path_to_string(&tree.prefix).as_str() > path);
+    if let Some(&(item, _)) = next {
+        let lo = item_start(item);
+        let text = format!("{}\n{}", decl, indentation(source_map, lo));
+        return Some(new_lines(source_map, lo, text));
+    }
+    if let Some(&(item, _)) = uses.last() {
+        let text = format!("\n{}{}", indentation(source_map, item_start(item)), decl);
+        return Some(new_lines(source_map, item.span.shrink_to_hi(), text));
+    }
+
+    // Don't place the declaration before `extern crate` items, but before the first other item.
+    let item = module.items.iter().find(|item| match item.node {
+        ItemKind::ExternCrate(..) => false,
+        _ => !item.span.from_expansion(),
+    })?;
+    let lo = item_start(item);
+    Some(new_lines(source_map, lo, format!("{}\n\n{}", decl, indentation(source_map, lo))))
+}
+
+/// An insertion of `text` spanning several lines at the empty `span`, indented like the file.
+fn new_lines(source_map: &SourceMap, span: Span, text: String) -> Insertion {
+    let text = source_map.lookup_char_pos(span.lo()).file.reindent(span.lo(), &text);
+    Insertion { span, text }
+}
+
+/// The start of `item`, including its attributes so we don't insert between them.
+fn item_start(item: &ast::Item) -> Span {
+    item.attrs.iter()
+        .map(|attr| attr.span)
+        .filter(|span| !span.from_expansion())
+        .fold(item.span, |lo, span| if span.lo() < lo.lo() { span } else { lo })
+        .shrink_to_lo()
+}
+
+/// The whitespace preceding `span` on its line, or nothing if there is other code before it.
+fn indentation(source_map: &SourceMap, span: Span) -> String {
+    let prev = source_map.span_to_prev_source(span).unwrap_or_default();
+    let line = prev.rsplit('\n').next().unwrap_or("");
+    if line.chars().all(char::is_whitespace) {
+        line.to_string()
+    } else {
+        String::new()
+    }
+}
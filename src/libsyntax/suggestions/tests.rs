@@ -0,0 +1,84 @@
+use super::*;
+
+use crate::ast::ItemKind;
+use crate::parse::{self, ParseSess};
+use crate::source_map::FilePathMapping;
+use crate::with_default_globals;
+
+use syntax_pos::FileName;
+
+/// Applies the insertion computed for `path` to `src`, importing into the crate root or,
+/// with `in_mod`, into the first module of the crate.
+fn insert_use(src: &str, path: &str, in_mod: bool) -> Option<String> {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let krate = parse::parse_crate_from_source_str(
+            FileName::Custom("test".to_string()), src.to_string(), &sess,
+        ).unwrap();
+        let module = if in_mod {
+            krate.module.items.iter().filter_map(|item| match item.node {
+                ItemKind::Mod(ref module) => Some(module),
+                _ => None,
+            }).next().unwrap()
+        } else {
+            &krate.module
+        };
+        let insertion = use_insertion(sess.source_map(), module, path)?;
+        let pos = sess.source_map().lookup_byte_offset(insertion.span.lo()).pos.0 as usize;
+        Some(format!("{}{}{}", &src[..pos], insertion.text, &src[pos..]))
+    })
+}
+
+#[test]
+fn insert_into_group() {
+    let src = "use std::collections::{BTreeMap, HashSet};\nfn main() {}";
+    assert_eq!(
+        insert_use(src, "std::collections::HashMap", false).unwrap(),
+        "use std::collections::{BTreeMap, HashMap, HashSet};\nfn main() {}",
+    );
+    assert_eq!(
+        insert_use(src, "std::collections::VecDeque", false).unwrap(),
+        "use std::collections::{BTreeMap, HashSet, VecDeque};\nfn main() {}",
+    );
+    assert_eq!(
+        insert_use("use std::io::{self, Write};", "std::io::Read", false).unwrap(),
+        "use std::io::{self, Read, Write};",
+    );
+}
+
+#[test]
+fn insert_sorted_declaration() {
+    let src = "use a::b;\nuse c::d;\n\nfn main() {}";
+    assert_eq!(
+        insert_use(src, "b::c", false).unwrap(),
+        "use a::b;\nuse b::c;\nuse c::d;\n\nfn main() {}",
+    );
+    assert_eq!(
+        insert_use(src, "z::y", false).unwrap(),
+        "use a::b;\nuse c::d;\nuse z::y;\n\nfn main() {}",
+    );
+}
+
+#[test]
+fn insert_first_declaration() {
+    let src = "extern crate foo;\n\n/// Doc.\nfn main() {}";
+    assert_eq!(
+        insert_use(src, "a::b", false).unwrap(),
+        "extern crate foo;\n\nuse a::b;\n\n/// Doc.\nfn main() {}",
+    );
+}
+
+#[test]
+fn insert_into_nested_module() {
+    let src = "mod m {\n    use a::b;\n}";
+    assert_eq!(
+        insert_use(src, "c::d", true).unwrap(),
+        "mod m {\n    use a::b;\n    use c::d;\n}",
+    );
+}
+
+#[test]
+fn already_imported() {
+    assert_eq!(insert_use("use a::b;\nfn main() {}", "a::b", false), None);
+    assert_eq!(insert_use("use a::{b, c};\nfn main() {}", "a::c", false), None);
+}
@@ -0,0 +1,210 @@
+//! Expected diagnostics annotated in source code, as in the UI tests of the compiler.
+//!
+//! The annotations, like `//~ ERROR mismatched types`, are parsed by `rustc_test_annotations`,
+//! which compiletest parses them with too. Projects embedding the parser can check the
+//! diagnostics they emit against such annotations with `check_diagnostics`.
+
+use errors::{Diagnostic, Level, SubstitutionPart};
+use syntax_pos::{BytePos, MultiSpan, SourceFile, Span};
+
+use std::fmt;
+use std::ops::Range;
+
+pub use rustc_test_annotations::AnnotationKind;
+
+#[cfg(test)]
+mod tests;
+
+/// Returns the kind of annotations that match diagnostics with `level`.
+fn kind_of_level(level: Level) -> Option<AnnotationKind> {
+    match level {
+        Level::Bug | Level::Fatal | Level::Error => Some(AnnotationKind::Error),
+        Level::Warning => Some(AnnotationKind::Warning),
+        Level::Help => Some(AnnotationKind::Help),
+        Level::Note => Some(AnnotationKind::Note),
+        Level::FailureNote | Level::Cancelled => None,
+    }
+}
+
+/// A diagnostic expected by an annotation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExpectedDiagnostic {
+    /// The 1-based line the diagnostic is expected on.
+    pub line: usize,
+    /// The expected level, or `None` if the annotation doesn't start with one, in which case
+    /// any level matches.
+    pub kind: Option<AnnotationKind>,
+    /// Text the message of the diagnostic is expected to contain.
+    pub msg: String,
+    /// The annotation, from its `//~` to the end of its line.
+    pub span: Span,
+}
+
+/// A malformed annotation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnnotationError {
+    pub span: Span,
+    pub msg: String,
+}
+
+impl fmt::Display for AnnotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+/// Parses the annotations in `file`. With `revision`, only the annotations for it are
+/// returned. Files whose source isn't available have no annotations.
+pub fn parse_annotations(
+    file: &SourceFile,
+    revision: Option<&str>,
+) -> Result<Vec<ExpectedDiagnostic>, AnnotationError> {
+    let src = match file.src {
+        Some(ref src) => src,
+        None => return Ok(Vec::new()),
+    };
+    let span = |range: Range<usize>| {
+        let lo = file.start_pos + BytePos(range.start as u32);
+        Span::with_root_ctxt(lo, file.start_pos + BytePos(range.end as u32))
+    };
+    match rustc_test_annotations::parse_annotations(src, revision) {
+        Ok(annotations) => Ok(annotations.into_iter().map(|annotation| ExpectedDiagnostic {
+            line: annotation.line,
+            kind: annotation.kind,
+            msg: annotation.msg,
+            span: span(annotation.range),
+        }).collect()),
+        Err(error) => Err(AnnotationError { span: span(error.range), msg: error.msg }),
+    }
+}
+
+/// A diagnostic, or a note, help or suggestion attached to it, as matched against
+/// annotations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActualDiagnostic {
+    /// The 1-based line in the annotated file the diagnostic points at, if any.
+    pub line: Option<usize>,
+    pub kind: AnnotationKind,
+    /// The message, followed by the code of the diagnostic in brackets if it has one.
+    pub msg: String,
+}
+
+/// The differences between annotations and the diagnostics actually emitted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AnnotationMismatches {
+    /// Diagnostics that aren't expected by any annotation.
+    pub unexpected: Vec<ActualDiagnostic>,
+    /// Annotations that no diagnostic matches.
+    pub missing: Vec<ExpectedDiagnostic>,
+}
+
+impl AnnotationMismatches {
+    pub fn is_empty(&self) -> bool {
+        self.unexpected.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// Matches the `diagnostics` emitted for `file` against the annotations `expected` in it.
+///
+/// A diagnostic matches an annotation on the line it points at, with its level, and with a
+/// message containing the annotation's message. Errors and warnings must all be annotated,
+/// while notes and helps only must be if at least one of them is. Suggestions never have to
+/// be. Diagnostics pointing into the expansion of a macro point at its invocation instead.
+pub fn check_diagnostics(
+    file: &SourceFile,
+    expected: &[ExpectedDiagnostic],
+    diagnostics: &[Diagnostic],
+) -> AnnotationMismatches {
+    let mut actual = Vec::new();
+    for diagnostic in diagnostics.iter().filter(|diagnostic| !diagnostic.cancelled()) {
+        push_actual(&mut actual, file, diagnostic);
+    }
+
+    let expect_help = expected.iter().any(|e| e.kind == Some(AnnotationKind::Help));
+    let expect_note = expected.iter().any(|e| e.kind == Some(AnnotationKind::Note));
+    let mut matched = vec![false; expected.len()];
+    let mut mismatches = AnnotationMismatches::default();
+    for actual in actual {
+        let found = expected.iter().zip(&mut matched).find(|(expected, matched)| {
+            !**matched &&
+                actual.line == Some(expected.line) &&
+                expected.kind.map_or(true, |kind| kind == actual.kind) &&
+                actual.msg.contains(&expected.msg)
+        });
+        match found {
+            Some((_, matched)) => *matched = true,
+            None => {
+                let must_be_annotated = match actual.kind {
+                    AnnotationKind::Error | AnnotationKind::Warning => true,
+                    AnnotationKind::Help => expect_help,
+                    AnnotationKind::Note => expect_note,
+                    AnnotationKind::Suggestion => false,
+                };
+                if must_be_annotated {
+                    mismatches.unexpected.push(actual);
+                }
+            }
+        }
+    }
+    mismatches.missing = expected.iter().zip(matched)
+        .filter(|&(_, matched)| !matched)
+        .map(|(expected, _)| expected.clone())
+        .collect();
+    mismatches
+}
+
+/// Pushes `diagnostic`, its children and its suggestions to `actual`.
+fn push_actual(actual: &mut Vec<ActualDiagnostic>, file: &SourceFile, diagnostic: &Diagnostic) {
+    let kind = match kind_of_level(diagnostic.level) {
+        Some(kind) => kind,
+        None => return,
+    };
+    let line = line_of(file, &diagnostic.span);
+    let msg = match diagnostic.code {
+        Some(ref code) => format!("{} [{}]", diagnostic.message(), code),
+        None => diagnostic.message(),
+    };
+    actual.push(ActualDiagnostic { line, kind, msg });
+
+    // Children without a span of their own are about the span of their parent.
+    for child in &diagnostic.children {
+        if let Some(kind) = kind_of_level(child.level) {
+            let line = line_of(file, &child.span).or(line);
+            actual.push(ActualDiagnostic { line, kind, msg: child.message() });
+        }
+    }
+    for suggestion in &diagnostic.suggestions {
+        let part_line = |part: &SubstitutionPart| {
+            line_of(file, &MultiSpan::from_span(part.span)).or(line)
+        };
+        let first_part = suggestion.substitutions.iter().flat_map(|s| &s.parts).next();
+        actual.push(ActualDiagnostic {
+            line: first_part.map_or(line, &part_line),
+            kind: AnnotationKind::Help,
+            msg: suggestion.msg.clone(),
+        });
+        for part in suggestion.substitutions.iter().flat_map(|s| &s.parts) {
+            actual.push(ActualDiagnostic {
+                line: part_line(part),
+                kind: AnnotationKind::Suggestion,
+                msg: part.snippet.clone(),
+            });
+        }
+    }
+}
+
+/// Returns the 1-based line in `file` of the first primary span of `msp`, following spans in
+/// macro expansions back to the invocation in `file`.
+fn line_of(file: &SourceFile, msp: &MultiSpan) -> Option<usize> {
+    msp.primary_spans().iter().filter(|span| !span.is_dummy()).find_map(|&span| {
+        let mut span = span;
+        while !file.contains(span.lo()) {
+            let callsite = span.ctxt().outer_expn_data().call_site;
+            if callsite.is_dummy() || callsite == span {
+                return None;
+            }
+            span = callsite;
+        }
+        file.lookup_line(span.lo()).map(|line_index| line_index + 1)
+    })
+}
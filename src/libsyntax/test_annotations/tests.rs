@@ -0,0 +1,115 @@
+use super::*;
+
+use crate::source_map::{FilePathMapping, SourceMap};
+use crate::with_default_globals;
+
+use errors::{Applicability, DiagnosticId};
+use rustc_data_structures::sync::Lrc;
+use syntax_pos::FileName;
+
+const SOURCE: &str = r#"fn main() {
+    let x: u8 = ""; //~ ERROR mismatched types
+    //~| NOTE expected `u8`
+    let y = 1;
+    //~^ WARN unused variable
+    //[a]~^^ WARN: only in a
+    z; //~ cannot find value
+    //~| ERROR never emitted
+}
+"#;
+
+fn source_file() -> (SourceMap, Lrc<SourceFile>) {
+    let source_map = SourceMap::new(FilePathMapping::empty());
+    let file = source_map.new_source_file(FileName::Custom("test".into()), SOURCE.to_string());
+    (source_map, file)
+}
+
+fn span_of(file: &SourceFile, text: &str) -> Span {
+    let lo = file.start_pos + BytePos(SOURCE.find(text).unwrap() as u32);
+    Span::with_root_ctxt(lo, lo + BytePos(text.len() as u32))
+}
+
+fn expected(line: usize, kind: Option<AnnotationKind>, msg: &str)
+    -> (usize, Option<AnnotationKind>, String)
+{
+    (line, kind, msg.to_string())
+}
+
+#[test]
+fn parse() {
+    with_default_globals(|| {
+        let (source_map, file) = source_file();
+        let annotations = parse_annotations(&file, None).unwrap();
+        let parsed = annotations.iter()
+            .map(|e| (e.line, e.kind, e.msg.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(parsed, vec![
+            expected(2, Some(AnnotationKind::Error), "mismatched types"),
+            expected(2, Some(AnnotationKind::Note), "expected `u8`"),
+            expected(4, Some(AnnotationKind::Warning), "unused variable"),
+            expected(7, None, "cannot find value"),
+            expected(7, Some(AnnotationKind::Error), "never emitted"),
+        ]);
+        assert_eq!(source_map.span_to_snippet(annotations[0].span).unwrap(),
+                   "//~ ERROR mismatched types");
+
+        let annotations = parse_annotations(&file, Some("a")).unwrap();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!((annotations[0].line, annotations[0].kind), (4, Some(AnnotationKind::Warning)));
+        assert_eq!(annotations[0].msg, "only in a");
+    })
+}
+
+#[test]
+fn parse_error_span() {
+    with_default_globals(|| {
+        let source_map = SourceMap::new(FilePathMapping::empty());
+        let file = source_map.new_source_file(FileName::Custom("test".into()), "x; //~".into());
+        let error = parse_annotations(&file, None).unwrap_err();
+        assert_eq!(error.msg, "annotation without a message");
+        assert_eq!(source_map.span_to_snippet(error.span).unwrap(), "//~");
+    })
+}
+
+#[test]
+fn check() {
+    with_default_globals(|| {
+        let (_, file) = source_file();
+        let annotations = parse_annotations(&file, None).unwrap();
+
+        let mut mismatched = Diagnostic::new_with_code(
+            Level::Error, Some(DiagnosticId::Error("E0308".into())), "mismatched types",
+        );
+        mismatched.set_span(span_of(&file, "\"\"")).note("expected `u8`, found `&str`");
+        let mut unused = Diagnostic::new_with_code(
+            Level::Warning, Some(DiagnosticId::Lint("unused_variables".into())),
+            "unused variable: `y`",
+        );
+        let y = span_of(&file, "y = 1");
+        unused.set_span(y).span_suggestion(
+            y.with_hi(y.lo() + BytePos(1)),
+            "consider prefixing with an underscore",
+            "_y".to_string(),
+            Applicability::MachineApplicable,
+        );
+        let mut unresolved = Diagnostic::new_with_code(
+            Level::Error, Some(DiagnosticId::Error("E0425".into())),
+            "cannot find value `z` in this scope",
+        );
+        unresolved.set_span(span_of(&file, "z;"));
+        let mut unexpected = Diagnostic::new(Level::Error, "unexpected");
+        unexpected.set_span(span_of(&file, "main"));
+        let mut cancelled = Diagnostic::new(Level::Error, "cancelled");
+        cancelled.set_span(span_of(&file, "main")).cancel();
+
+        let mismatches = check_diagnostics(
+            &file, &annotations, &[mismatched, unused, unresolved, unexpected, cancelled],
+        );
+        assert_eq!(mismatches.unexpected, vec![ActualDiagnostic {
+            line: Some(1),
+            kind: AnnotationKind::Error,
+            msg: "unexpected".to_string(),
+        }]);
+        assert_eq!(mismatches.missing, vec![annotations[4].clone()]);
+    })
+}
@@ -1,22 +1,39 @@
-use crate::{ast, panictry};
+use crate::{ast, attr, panictry};
+use crate::attr::CfgPredicate;
+use crate::config::StripUnconfigured;
+use crate::early_buffered_lints::BufferedEarlyLintDiagnostics;
+use crate::feature_gate::{self, Features, GateIssue};
+use crate::json::{self as json_format, JsonEmitter};
 use crate::parse::{ParseSess, PResult, source_file_to_stream};
-use crate::parse::new_parser_from_source_str;
+use crate::parse::{new_parser_from_source_str, parse_crate_from_source_str};
 use crate::parse::parser::Parser;
 use crate::source_map::{SourceMap, FilePathMapping};
-use crate::tokenstream::TokenStream;
+use crate::symbol::{Symbol, sym};
+use crate::tokenstream::{TokenStream, TokenTree};
+use crate::util::perf;
 use crate::with_default_globals;
 
-use errors::emitter::EmitterWriter;
-use errors::Handler;
+use errors::compat_emitter::{CompatEmitter, CompatStyle};
+use errors::emitter::{ColorConfig, EmitterWriter, HumanReadableErrorType};
+use errors::markup::Markup;
+use errors::registry::Registry;
+use errors::suppression::Suppression;
+use errors::replay::DiagnosticReplayCache;
+use errors::test_emitter::{TestEmitter, TestOutput};
+use errors::{Applicability, Diagnostic, DiagnosticId, FeatureGate, FeatureState, Handler};
+use errors::{CodeSuggestion, Substitution, SubstitutionPart, SuggestionStyle};
+use errors::{subscribers, HandlerFlags, Level, WarningRateLimit};
 use rustc_data_structures::sync::Lrc;
-use syntax_pos::{BytePos, Span, MultiSpan};
+use rustc_serialize::json;
+use syntax_pos::{BytePos, Span, MultiSpan, DUMMY_SP};
+use syntax_pos::edition::Edition;
+use syntax_pos::hygiene::{ExpnData, ExpnKind, MacroKind};
 
-use std::io;
-use std::io::prelude::*;
+use std::cell::{Cell, RefCell};
 use std::iter::Peekable;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::str;
-use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 /// Map string to parser (via tts).
 fn string_to_parser(ps: &ParseSess, source_str: String) -> Parser<'_> {
@@ -110,24 +127,8 @@ struct SpanLabel {
     label: &'static str,
 }
 
-struct Shared<T: Write> {
-    data: Arc<Mutex<T>>,
-}
-
-impl<T: Write> Write for Shared<T> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.data.lock().unwrap().write(buf)
-    }
-
-    fn flush(&mut self) -> io::Result<()> {
-        self.data.lock().unwrap().flush()
-    }
-}
-
 fn test_harness(file_text: &str, span_labels: Vec<SpanLabel>, expected_output: &str) {
     with_default_globals(|| {
-        let output = Arc::new(Mutex::new(Vec::new()));
-
         let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
         source_map.new_source_file(Path::new("test.rs").to_owned().into(), file_text.to_owned());
 
@@ -140,29 +141,25 @@ fn test_harness(file_text: &str, span_labels: Vec<SpanLabel>, expected_output: &
             println!("text: {:?}", source_map.span_to_snippet(span));
         }
 
-        let emitter = EmitterWriter::new(
-            Box::new(Shared { data: output.clone() }),
-            Some(source_map.clone()),
-            false,
-            false,
-            false,
-            None,
-            false,
-        );
-        let handler = Handler::with_emitter(true, None, Box::new(emitter));
-        handler.span_err(msp, "foo");
+        assert_rendering(source_map, msp, expected_output);
+    })
+}
 
-        assert!(expected_output.chars().next() == Some('\n'),
-                "expected output should begin with newline");
-        let expected_output = &expected_output[1..];
+fn assert_rendering(source_map: Lrc<SourceMap>, msp: MultiSpan, expected_output: &str) {
+    let emitter = TestEmitter::new(Some(source_map));
+    let output = emitter.output();
+    let handler = Handler::with_emitter(true, None, Box::new(emitter));
+    handler.span_err(msp, "foo");
 
-        let bytes = output.lock().unwrap();
-        let actual_output = str::from_utf8(&bytes).unwrap();
-        println!("expected output:\n------\n{}------", expected_output);
-        println!("actual output:\n------\n{}------", actual_output);
+    assert!(expected_output.chars().next() == Some('\n'),
+            "expected output should begin with newline");
+    let expected_output = &expected_output[1..];
 
-        assert!(expected_output == actual_output)
-    })
+    let actual_output = output.rendered();
+    println!("expected output:\n------\n{}------", expected_output);
+    println!("actual output:\n------\n{}------", actual_output);
+
+    assert!(expected_output == actual_output)
 }
 
 fn make_span(file_text: &str, start: &Position, end: &Position) -> Span {
@@ -1252,3 +1249,1132 @@ error: foo
 
 "#);
 }
+
+#[test]
+fn primary_roles() {
+    with_default_globals(|| {
+        let file_text = r#"
+fn foo(a: u8, a: u8) {}
+"#;
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        source_map.new_source_file(Path::new("test.rs").to_owned().into(), file_text.to_owned());
+
+        let first = Span::with_root_ctxt(BytePos(8), BytePos(9));
+        let second = Span::with_root_ctxt(BytePos(15), BytePos(16));
+        let mut msp = MultiSpan::new();
+        msp.push_primary_role(second, "duplicate".to_string());
+        msp.push_primary_role(first, "first".to_string());
+
+        assert_rendering(source_map, msp, r#"
+error: foo
+ --> test.rs:2:15
+  |
+2 | fn foo(a: u8, a: u8) {}
+  |        ~      ^ duplicate
+  |        |
+  |        first
+
+"#);
+    })
+}
+
+#[test]
+fn test_emitter_captures_suggestions() {
+    with_default_globals(|| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let name = Path::new("test.rs").to_owned().into();
+        source_map.new_source_file(name, "let x = 1\n".to_owned());
+
+        let json_source_map = source_map.clone();
+        let emitter = TestEmitter::new(Some(source_map)).with_json(|dst| {
+            let rendered = HumanReadableErrorType::Default(ColorConfig::Never);
+            Box::new(JsonEmitter::new(dst, None, json_source_map, false, rendered, false))
+        });
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+
+        let span = Span::with_root_ctxt(BytePos(9), BytePos(9));
+        handler.struct_span_err_with_code(span, "expected `;`", DiagnosticId::Error("E0000".into()))
+            .span_suggestion(span, "add `;`", ";".to_string(), Applicability::MachineApplicable)
+            .emit();
+
+        output.assert_error_with_code("E0000");
+        output.assert_suggestion_applies_to("let x = 1;\n");
+        assert!(output.json().contains("\"code\":\"E0000\""));
+    })
+}
+
+#[test]
+fn suggestion_audit() {
+    with_default_globals(|| {
+        let emitter = TestEmitter::new(None);
+        let flags = HandlerFlags { audit_suggestions: true, .. Default::default() };
+        let handler = Handler::with_emitter_and_flags(Box::new(emitter), flags);
+
+        let mut diagnostic = Diagnostic::new(Level::Error, "added to a diagnostic");
+        let unspecified = Applicability::Unspecified;
+        diagnostic.span_suggestion(DUMMY_SP, "unclassified", String::new(), unspecified);
+        handler.emit_diagnostic(&diagnostic);
+        let suggestion = vec![(DUMMY_SP, String::new())];
+        handler.struct_err("added to a builder")
+            .multipart_suggestion("classified", suggestion, Applicability::MachineApplicable)
+            .emit();
+        handler.struct_err("cancelled")
+            .span_suggestion_short(DUMMY_SP, "cancelled", String::new(), unspecified)
+            .cancel();
+
+        let audited = handler.audited_suggestions();
+        let messages = audited.iter().map(|suggestion| &suggestion.msg[..]).collect::<Vec<_>>();
+        assert_eq!(messages, ["unclassified", "classified"]);
+        assert!(audited[0].site.contains("suggestion_audit"), "{}", audited[0].site);
+        let unspecified = handler.unspecified_suggestions();
+        assert_eq!(unspecified.len(), 1);
+        assert_eq!(unspecified[0].msg, "unclassified");
+    })
+}
+
+#[test]
+fn render_stored_diagnostic() {
+    with_default_globals(|| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let name = Path::new("test.rs").to_owned().into();
+        source_map.new_source_file(name, "let x = 1\n".to_owned());
+        let emitter = TestEmitter::new(Some(source_map.clone()));
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+
+        let span = Span::with_root_ctxt(BytePos(9), BytePos(9));
+        handler.struct_span_err_with_code(span, "expected `;`", DiagnosticId::Error("E0000".into()))
+            .span_suggestion(span, "add `;`", ";".to_string(), Applicability::MachineApplicable)
+            .emit();
+
+        let stored = json::encode(&output.diagnostics()[0].diagnostic).unwrap();
+        let diagnostic: Diagnostic = json::decode(&stored).unwrap();
+        let rendered = EmitterWriter::render_to_string(&diagnostic, Some(source_map.clone()));
+        assert_eq!(rendered, output.rendered());
+        assert!(rendered.starts_with("error[E0000]: expected `;`\n --> test.rs:1:10\n"));
+
+        let json = errors::emitter::render_with(&diagnostic, |dst| {
+            let rendered = HumanReadableErrorType::Default(ColorConfig::Never);
+            Box::new(JsonEmitter::new(dst, None, source_map, false, rendered, false))
+        });
+        assert!(json.contains("\"code\":\"E0000\""));
+
+        // The emitted JSON form resolves its spans by lines and columns, so the file may be at
+        // another position in another source map.
+        let emitted: json_format::Diagnostic = json::decode(&json).unwrap();
+        let other_source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let other_name = Path::new("other.rs").to_owned().into();
+        other_source_map.new_source_file(other_name, "fn main() {}\n".to_owned());
+        let name = Path::new("test.rs").to_owned().into();
+        other_source_map.new_source_file(name, "let x = 1\n".to_owned());
+        let rendered = EmitterWriter::render_to_string(&emitted, Some(other_source_map));
+        assert_eq!(rendered, output.rendered());
+    })
+}
+
+#[test]
+fn short_diagnostics() {
+    with_default_globals(|| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let name = Path::new("test.rs").to_owned().into();
+        source_map.new_source_file(name, "return x\n".to_owned());
+        let emitter = TestEmitter::new(Some(source_map.clone()));
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+
+        let span = Span::with_root_ctxt(BytePos(8), BytePos(8));
+        handler.struct_span_err_with_code(span, "expected `;`", DiagnosticId::Error("E0000".into()))
+            .span_suggestion(span, "add `;`", ";".to_string(), Applicability::MachineApplicable)
+            .emit();
+        let span = Span::with_root_ctxt(BytePos(0), BytePos(6));
+        let code = DiagnosticId::tool("clippy", "needless_return");
+        handler.struct_span_warn_with_code(span, "unneeded\n`return`", code)
+            .span_suggestion(span, "remove it", String::new(), Applicability::MaybeIncorrect)
+            .note("a note")
+            .emit();
+
+        let render = |kind: HumanReadableErrorType| -> String {
+            output.diagnostics().iter().map(|captured| {
+                errors::emitter::render_with(&captured.diagnostic, |dst| {
+                    Box::new(kind.new_emitter(dst, Some(source_map.clone()), false, None, false))
+                })
+            }).collect()
+        };
+        assert_eq!(
+            render(HumanReadableErrorType::Short(ColorConfig::Never)),
+            "test.rs:1:9: error[E0000]: expected `;`\n\
+             test.rs:1:1: warning[clippy::needless_return]: unneeded `return`\n",
+        );
+        assert_eq!(
+            render(HumanReadableErrorType::ShortWithFixes(ColorConfig::Never)),
+            "test.rs:1:9: error[E0000]: expected `;` [fix available]\n\
+             test.rs:1:1: warning[clippy::needless_return]: unneeded `return`\n",
+        );
+    })
+}
+
+#[test]
+fn compat_diagnostics() {
+    with_default_globals(|| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let name = Path::new("test.rs").to_owned().into();
+        source_map.new_source_file(name, "let x = y;\n".to_owned());
+        let emitter = TestEmitter::new(Some(source_map.clone()));
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+
+        let (x, y) = (Span::with_root_ctxt(BytePos(4), BytePos(5)),
+                      Span::with_root_ctxt(BytePos(8), BytePos(9)));
+        let code = DiagnosticId::Error("E0425".into());
+        handler.struct_span_err_with_code(y, "cannot find value `y` in this scope", code)
+            .span_note(x, "`x` is declared\nhere")
+            .help("consider importing it")
+            .emit();
+        handler.warn("unused crate");
+
+        let render = |style: CompatStyle| -> String {
+            output.diagnostics().iter().map(|captured| {
+                errors::emitter::render_with(&captured.diagnostic, |dst| {
+                    Box::new(CompatEmitter::new(dst, Some(source_map.clone()), style))
+                })
+            }).collect()
+        };
+        assert_eq!(render(CompatStyle::Gcc), "\
+            test.rs:1:9: error: cannot find value `y` in this scope [E0425]\n\
+            test.rs:1:5: note: `x` is declared here\n\
+            note: consider importing it\n\
+            warning: unused crate\n");
+        assert_eq!(render(CompatStyle::Msvc), "\
+            test.rs(1,9): error E0425: cannot find value `y` in this scope\n\
+            test.rs(1,5): note: `x` is declared here\n\
+            note: consider importing it\n\
+            warning: unused crate\n");
+    })
+}
+
+#[test]
+fn emit_ordered_diagnostics() {
+    with_default_globals(|| {
+        let emitter = TestEmitter::new(None);
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+
+        // Two units of work, the second of which buffers its diagnostics first.
+        let (first, second) = (handler.diagnostic_token(), handler.diagnostic_token());
+        assert!(first < second);
+        let mut buffered = Vec::new();
+        handler.struct_err("second unit").buffer_ordered(second, &mut buffered);
+        handler.struct_err("first unit, 1").buffer_ordered(first, &mut buffered);
+        handler.struct_warn("first unit, 2").buffer_ordered(first, &mut buffered);
+        assert!(output.diagnostics().is_empty());
+
+        handler.emit_ordered(buffered);
+        let messages = output.diagnostics().iter()
+            .map(|captured| captured.diagnostic.message())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, ["first unit, 1", "first unit, 2", "second unit"]);
+        assert_eq!(handler.err_count(), 2);
+    })
+}
+
+/// Emits an error with a suggestion through a JSON emitter, and decodes what it wrote.
+fn emit_json_diagnostic(pretty: bool, compact: bool) -> json_format::Diagnostic {
+    let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+    let name = Path::new("test.rs").to_owned().into();
+    source_map.new_source_file(name, "let x = 1\n".to_owned());
+    let emitter = TestEmitter::new(None).with_json(|dst| {
+        let rendered = HumanReadableErrorType::Default(ColorConfig::Never);
+        Box::new(JsonEmitter::new(dst, None, source_map, pretty, rendered, false).compact(compact))
+    });
+    let output = emitter.output();
+    let handler = Handler::with_emitter(true, None, Box::new(emitter));
+
+    let span = Span::with_root_ctxt(BytePos(9), BytePos(9));
+    handler.struct_span_err_with_code(span, "expected `;`", DiagnosticId::Error("E0000".into()))
+        .span_suggestion(span, "add `;`", ";".to_string(), Applicability::MachineApplicable)
+        .emit();
+
+    let emitted = output.json();
+    assert!(emitted.contains("\"$schema\""));
+    json::decode(&emitted).unwrap()
+}
+
+#[test]
+fn json_diagnostics_round_trip() {
+    with_default_globals(|| {
+        let diagnostic = emit_json_diagnostic(false, false);
+        assert_eq!(diagnostic.schema.as_ref().map(|s| &s[..]), Some(json_format::SCHEMA));
+        assert_eq!(diagnostic.message, "expected `;`");
+        assert_eq!(diagnostic.code.as_ref().unwrap().code, "E0000");
+        assert_eq!(diagnostic.level, "error");
+        assert_eq!(diagnostic.spans[0].file_name, "test.rs");
+        assert_eq!(diagnostic.spans[0].column_start, 10);
+        assert_eq!(diagnostic.children.len(), 1);
+        assert_eq!(diagnostic.children[0].schema, None);
+        assert_eq!(diagnostic.children[0].spans[0].suggested_replacement, Some(";".to_string()));
+        assert_eq!(diagnostic.children[0].spans[0].suggestion_applicability,
+                   Some(Applicability::MachineApplicable));
+        assert!(diagnostic.rendered.as_ref().unwrap().starts_with("error[E0000]: expected `;`"));
+
+        // The pretty form decodes to the same diagnostic, and re-encoding it is lossless.
+        assert_eq!(emit_json_diagnostic(true, false), diagnostic);
+        let encoded = json::encode(&diagnostic).unwrap();
+        assert!(encoded.starts_with(r#"{"$schema":"rustc-diagnostic-v1","message":"#));
+        assert_eq!(json::decode::<json_format::Diagnostic>(&encoded).unwrap(), diagnostic);
+    })
+}
+
+#[test]
+fn compact_json_diagnostics() {
+    with_default_globals(|| {
+        let full = emit_json_diagnostic(false, false);
+        let compact = emit_json_diagnostic(false, true);
+        assert_eq!(compact, json_format::Diagnostic {
+            children: vec![],
+            rendered: None,
+            ..full
+        });
+    })
+}
+
+#[test]
+fn json_macro_backtraces() {
+    with_default_globals(|| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let name = Path::new("test.rs").to_owned().into();
+        let file_text = "macro_rules! n { () => { 1 } }\nfn f() { m!() }\n";
+        source_map.new_source_file(name, file_text.to_owned());
+        let span = |text: &str| {
+            let lo = file_text.find(text).unwrap() as u32;
+            Span::with_root_ctxt(BytePos(lo), BytePos(lo + text.len() as u32))
+        };
+        let expn_data = |name: &str, call_site: Span, def_site: Span| ExpnData {
+            def_site,
+            ..ExpnData::default(
+                ExpnKind::Macro(MacroKind::Bang, Symbol::intern(name)),
+                call_site,
+                Edition::Edition2018,
+            )
+        };
+
+        // `m` comes from another crate, and expands to `n!()`, which expands to `1`. The
+        // tokens `m` expands to are attributed to its invocation.
+        let m_call = span("m!()");
+        let n_call = m_call.fresh_expansion(expn_data("m", m_call, Span::default()));
+        let n_def = span("macro_rules! n { () => { 1 } }");
+        let one = span("1").fresh_expansion(expn_data("n", n_call, n_def));
+
+        let emitter = TestEmitter::new(None).with_json(|dst| {
+            let rendered = HumanReadableErrorType::Default(ColorConfig::Never);
+            Box::new(JsonEmitter::new(dst, None, source_map, false, rendered, false))
+        });
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+        handler.span_err(one, "mismatched types");
+
+        let diagnostic: json_format::Diagnostic = json::decode(&output.json()).unwrap();
+        let n = diagnostic.spans[0].expansion.as_ref().unwrap();
+        assert_eq!(n.macro_decl_name, "n!");
+        assert_eq!(n.def_site_span.byte_start, 0);
+        assert!(!n.external);
+        let m = n.span.expansion.as_ref().unwrap();
+        assert_eq!(m.macro_decl_name, "m!");
+        assert_eq!((m.span.line_start, m.span.column_start), (2, 10));
+        assert!(m.external);
+        assert!(m.span.expansion.is_none());
+    })
+}
+
+#[test]
+fn json_span_columns() {
+    with_default_globals(|| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let name = Path::new("test.rs").to_owned().into();
+        source_map.new_source_file(name, "let s = \"😀\"; s.foo();\n".to_owned());
+        let json_source_map = source_map.clone();
+        let emitter = TestEmitter::new(None).with_json(|dst| {
+            let rendered = HumanReadableErrorType::Default(ColorConfig::Never);
+            Box::new(JsonEmitter::new(dst, None, json_source_map, false, rendered, false)
+                .byte_columns(true)
+                .utf16_columns(true))
+        });
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+
+        // `foo`, after a four-byte character.
+        let span = Span::with_root_ctxt(BytePos(18), BytePos(21));
+        assert_eq!(source_map.span_to_snippet(span).unwrap(), "foo");
+        handler.span_err(span, "no method named `foo` found");
+
+        let emitted = output.json();
+        assert!(emitted.contains(r#""column_start":16,"column_end":19,"byte_column_start":18,"#));
+        let diagnostic: json_format::Diagnostic = json::decode(&emitted).unwrap();
+        let span = &diagnostic.spans[0];
+        assert_eq!((span.byte_column_start, span.byte_column_end), (Some(18), Some(21)));
+        assert_eq!((span.utf16_column_start, span.utf16_column_end), (Some(16), Some(19)));
+    })
+}
+
+#[test]
+#[cfg(unix)] // The path of the file is only absolute on Unix.
+fn json_lsp_diagnostics() {
+    with_default_globals(|| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let name = PathBuf::from("/src/my crate/test.rs").into();
+        source_map.new_source_file(name, "let s = \"😀\"; s.foo();\n".to_owned());
+        let other_name = PathBuf::from("/src/my crate/other.rs").into();
+        let other = source_map.new_source_file(other_name, "x\n".to_owned());
+        let json_source_map = source_map.clone();
+        let emitter = TestEmitter::new(None).with_json(|dst| {
+            let rendered = HumanReadableErrorType::Default(ColorConfig::Never);
+            Box::new(JsonEmitter::new(dst, None, json_source_map, false, rendered, false)
+                .lsp(true))
+        });
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+
+        // `s` and `foo`, after a four-byte character.
+        let receiver = Span::with_root_ctxt(BytePos(16), BytePos(17));
+        let method = Span::with_root_ctxt(BytePos(18), BytePos(21));
+        handler.struct_span_err(method, "no method named `foo` found")
+            .code(DiagnosticId::Error("E0599".to_string()))
+            .span_label(receiver, "method not found for this")
+            .note("`&str` has no method `foo`")
+            .emit();
+        handler.struct_span_warn(receiver, "unused variable").emit();
+        let x = Span::with_root_ctxt(other.start_pos, other.start_pos + BytePos(1));
+        handler.struct_span_err(x, "cannot find value `x`").emit();
+        // Diagnostics that don't point into a file are left out.
+        handler.err("aborting due to previous error");
+        // The diagnostics are published at the end of the session, one file at a time.
+        assert_eq!(output.json(), "");
+        handler.flush_diagnostics();
+
+        let uri = "file:///src/my%20crate/test.rs";
+        let other_uri = "file:///src/my%20crate/other.rs";
+        let range = |start, end| format!(
+            r#"{{"start":{{"line":0,"character":{}}},"end":{{"line":0,"character":{}}}}}"#,
+            start, end,
+        );
+        assert_eq!(output.flushed_json(), format!(
+            concat!(
+                r#"{{"uri":"{3}","diagnostics":[{{"range":{4},"severity":1,"source":"rustc","#,
+                r#""message":"cannot find value `x`"}}]}}"#,
+                "\n",
+                r#"{{"uri":"{0}","diagnostics":[{{"range":{1},"severity":1,"code":"E0599","#,
+                r#""codeDescription":{{"#,
+                r#""href":"https://doc.rust-lang.org/error-index.html#E0599""#,
+                r#"}},"source":"rustc","message":"no method named `foo` found\nnote: `&str` "#,
+                r#"has no method `foo`","relatedInformation":[{{"#,
+                r#""location":{{"uri":"{0}","range":{2}}},"message":"method not found for this""#,
+                r#"}}]}},{{"range":{2},"severity":2,"source":"rustc","message":"unused variable""#,
+                r#"}}]}}"#,
+                "\n",
+            ),
+            uri, range(16, 19), range(14, 15), other_uri, range(0, 1),
+        ));
+    })
+}
+
+#[test]
+fn warning_rate_limit() {
+    with_default_globals(|| {
+        let emitter = TestEmitter::new(None);
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+        handler.set_warning_rate_limit(Some(WarningRateLimit {
+            max_warnings: 2,
+            interval: Duration::from_secs(3600),
+        }));
+
+        for i in 0..5 {
+            handler.warn(&format!("warning {}", i));
+        }
+        handler.err("an error");
+        assert_eq!(output.diagnostics().len(), 3);
+        assert!(!output.rendered().contains("warning 2"));
+
+        handler.report_suppressed_warnings();
+        assert_eq!(output.diagnostics().len(), 4);
+        assert!(output.rendered().contains(
+            "note: 3 warnings suppressed to limit the rate of warnings"
+        ));
+    })
+}
+
+#[test]
+fn tool_diagnostic_codes() {
+    with_default_globals(|| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let mut registry = Registry::new(&[]);
+        registry.register_tool("clippy", &[("needless_return", "Returns at the end of blocks.")]);
+        let json_registry = registry.clone();
+        let emitter = TestEmitter::new(Some(source_map.clone())).with_json(|dst| {
+            let rendered = HumanReadableErrorType::Default(ColorConfig::Never);
+            Box::new(JsonEmitter::new(dst, Some(json_registry), source_map, false, rendered, false))
+        });
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+
+        let code = DiagnosticId::tool("clippy", "needless_return");
+        assert_eq!(code.to_string(), "clippy::needless_return");
+        assert_eq!(registry.find_description("clippy::needless_return"),
+                   Some("Returns at the end of blocks."));
+        for _ in 0..2 {
+            handler.struct_warn("unneeded `return` statement").code(code.clone()).emit();
+        }
+        handler.struct_err("unneeded `return` statement").code(code.clone()).emit();
+        assert_eq!(output.diagnostics().len(), 2);
+        assert!(output.json().contains(
+            r#""code":{"code":"clippy::needless_return","explanation":"Returns at the end"#
+        ));
+
+        handler.set_tool_warnings_suppressed("clippy", true);
+        handler.struct_warn("`return` in a closure").code(code.clone()).emit();
+        handler.struct_warn("`return` in a closure")
+            .code(DiagnosticId::tool("other", "needless_return"))
+            .emit();
+        assert_eq!(output.diagnostics().len(), 3);
+
+        let summary = handler.session_summary(&registry);
+        assert_eq!(summary.codes["clippy::needless_return"], 2);
+        assert_eq!(summary.codes["other::needless_return"], 1);
+        assert_eq!(summary.explained_codes, vec!["clippy::needless_return".to_string()]);
+        assert_eq!(summary.duplicates, 1);
+        assert_eq!(summary.suppressed_warnings, 1);
+    })
+}
+
+#[test]
+fn markup_in_messages() {
+    with_default_globals(|| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let json_source_map = source_map.clone();
+        let emitter = TestEmitter::new(Some(source_map)).with_json(|dst| {
+            let rendered = HumanReadableErrorType::Default(ColorConfig::Never);
+            Box::new(JsonEmitter::new(dst, None, json_source_map, false, rendered, false))
+        });
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+
+        handler.struct_err("mismatched types")
+            .help_markup(Markup::new()
+                .text("constrain `T`:")
+                .code_block("impl Trait<String> for X {}")
+                .item("or use `U`"))
+            .emit();
+
+        let rendered = output.rendered();
+        assert!(rendered.contains("= help: constrain `T`:\n"));
+        assert!(rendered.contains("    impl Trait<String> for X {}\n"));
+        assert!(rendered.contains("- or use `U`\n"));
+        assert!(!rendered.contains("```"));
+        assert!(output.json().contains(
+            r#""message":"constrain `T`:\n```\nimpl Trait<String> for X {}\n```\n- or use `U`""#
+        ));
+    })
+}
+
+#[test]
+fn feature_gate_metadata() {
+    with_default_globals(|| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let name = Path::new("test.rs").to_owned().into();
+        source_map.new_source_file(name, "box 1\n".to_owned());
+        let json_source_map = source_map.clone();
+        let emitter = TestEmitter::new(Some(source_map.clone())).with_json(|dst| {
+            let rendered = HumanReadableErrorType::Default(ColorConfig::Never);
+            Box::new(JsonEmitter::new(dst, None, json_source_map, false, rendered, false))
+        });
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+        let sess = ParseSess::with_span_handler(handler, source_map);
+
+        let span = Span::with_root_ctxt(BytePos(0), BytePos(3));
+        let explain = "box expression syntax is experimental";
+        feature_gate::emit_feature_err(&sess, sym::box_syntax, span, GateIssue::Language, explain);
+
+        let diagnostics = output.diagnostics();
+        assert_eq!(diagnostics[0].diagnostic.feature_gate, Some(FeatureGate {
+            feature: "box_syntax".to_string(),
+            state: FeatureState::Active,
+            since: Some("1.0.0".to_string()),
+            issue: Some(49733),
+        }));
+        let json = output.json();
+        assert!(json.contains(r#""feature_gate":{"feature":"box_syntax","state":"Active","#));
+        assert!(json.contains(r#""since":"1.0.0","issue":49733}"#));
+        assert_eq!(feature_gate::detected_features(&sess), vec![(sym::box_syntax, span)]);
+    })
+}
+
+#[test]
+fn library_features_are_detected() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let first = Span::with_root_ctxt(BytePos(0), BytePos(3));
+        let second = Span::with_root_ctxt(BytePos(4), BytePos(7));
+
+        // The stability checks record the uses of enabled library features...
+        feature_gate::record_detected_feature(&sess, sym::rustc_private, first);
+        // ...and the ones of library features that aren't enabled are recorded when reported.
+        let explain = "use of unstable library feature 'test'";
+        let issue = GateIssue::Library(Some(50297));
+        feature_gate::feature_err(&sess, sym::test, second, issue, explain).cancel();
+        // Only the first use of a feature is kept.
+        feature_gate::record_detected_feature(&sess, sym::rustc_private, second);
+
+        assert_eq!(
+            feature_gate::detected_features(&sess),
+            vec![(sym::rustc_private, first), (sym::test, second)],
+        );
+    })
+}
+
+#[test]
+fn perf_corpora_parse() {
+    with_default_globals(|| {
+        let corpora = vec![
+            perf::nested_parens(10),
+            perf::binary_chain(10),
+            perf::huge_match(10),
+            perf::macro_heavy(10),
+        ];
+        let measurements = perf::run(&corpora, 1);
+        let phases: Vec<_> = measurements.iter().map(|m| (m.corpus, m.phase)).collect();
+        assert_eq!(&phases[..3], &[
+            ("nested_parens", perf::Phase::Lex),
+            ("nested_parens", perf::Phase::Parse),
+            ("nested_parens", perf::Phase::Print),
+        ]);
+        // Only `macro_heavy` has macros to expand.
+        assert_eq!(phases.len(), 13);
+        assert_eq!(phases.last(), Some(&("macro_heavy", perf::Phase::Expand)));
+    })
+}
+
+#[test]
+fn replay_diagnostics_of_unchanged_files() {
+    with_default_globals(|| {
+        let main_rs = "fn main() {\n    let x = 1;\n}\n";
+        let other_rs = "// other\n";
+        // Compiles `files`, replaying their diagnostics from `cache` if possible, and returns
+        // whether they were replayed, the rendered diagnostics and the recorded cache.
+        let compile = |files: &[(&str, &str)], cache: &DiagnosticReplayCache| {
+            let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+            for &(name, src) in files {
+                source_map.new_source_file(PathBuf::from(name).into(), src.to_string());
+            }
+            let emitter = TestEmitter::new(Some(source_map.clone()));
+            let output = emitter.output();
+            let handler = Handler::with_emitter(true, None, Box::new(emitter));
+            handler.record_for_replay(source_map.clone(), DiagnosticReplayCache::new());
+            let replayed = source_map.replay_diagnostics(&handler, cache);
+            if !replayed {
+                let main_rs = source_map.files().iter()
+                    .find(|sf| sf.src.as_ref().map_or(false, |src| src.starts_with("fn main")))
+                    .unwrap()
+                    .start_pos;
+                let span = Span::with_root_ctxt(main_rs + BytePos(20), main_rs + BytePos(21));
+                handler.struct_span_warn(span, "unused variable: `x`")
+                    .span_label(span, "help: consider prefixing it with an underscore")
+                    .emit();
+                handler.warn("crate-level warning");
+                source_map.mark_compiled_for_replay(&handler);
+            }
+            (replayed, output.rendered(), handler.take_replay_cache().unwrap())
+        };
+
+        let (replayed, rendered, cache) =
+            compile(&[("other.rs", other_rs), ("main.rs", main_rs)], &DiagnosticReplayCache::new());
+        assert!(!replayed);
+        assert!(rendered.contains("--> main.rs:2:9"));
+        assert!(rendered.contains("crate-level warning"));
+
+        // The diagnostics point at the same code after `main.rs` moved in the source map, and
+        // are recorded again.
+        let (replayed, replayed_rendered, replayed_cache) =
+            compile(&[("main.rs", main_rs), ("other.rs", other_rs)], &cache);
+        assert!(replayed);
+        assert_eq!(replayed_rendered, rendered);
+        let (replayed, replayed_rendered, _) =
+            compile(&[("other.rs", other_rs), ("main.rs", main_rs)], &replayed_cache);
+        assert!(replayed);
+        assert_eq!(replayed_rendered, rendered);
+
+        let changed_main_rs = "fn main() {\n    let y = 1;\n}\n";
+        let (replayed, _, _) =
+            compile(&[("other.rs", other_rs), ("main.rs", changed_main_rs)], &cache);
+        assert!(!replayed);
+        // The same source in another file isn't replayed either.
+        let (replayed, _, _) =
+            compile(&[("other.rs", other_rs), ("lib.rs", main_rs)], &cache);
+        assert!(!replayed);
+    })
+}
+
+/// Evaluates `source` as a `cfg` condition, with `unix` set and a `version(..)` predicate
+/// gated by `cfg_doctest` registered, returning the result and the captured diagnostics.
+fn eval_cfg_with_version_predicate(source: &str, gate_enabled: bool) -> (bool, TestOutput) {
+    let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+    let emitter = TestEmitter::new(Some(source_map.clone()));
+    let output = emitter.output();
+    let handler = Handler::with_emitter(true, None, Box::new(emitter));
+    let mut sess = ParseSess::with_span_handler(handler, source_map);
+    sess.config.insert((sym::unix, None));
+    sess.register_cfg_predicate(Symbol::intern("version"), CfgPredicate {
+        gate: Some((sym::cfg_doctest, |features: &Features| features.cfg_doctest)),
+        eval: Box::new(|cfg, sess| {
+            match cfg.meta_item_list().and_then(|list| list.get(0)?.literal()) {
+                Some(ast::Lit { node: ast::LitKind::Str(version, _), .. }) => {
+                    &*version.as_str() == "1.40"
+                }
+                _ => {
+                    sess.span_diagnostic.span_err(cfg.span, "expected a version");
+                    false
+                }
+            }
+        }),
+    });
+    let mut features = Features::new();
+    features.cfg_doctest = gate_enabled;
+
+    let name = PathBuf::from("test").into();
+    let mut parser = new_parser_from_source_str(&sess, name, source.to_string());
+    let cfg = panictry!(parser.parse_meta_item());
+    (attr::cfg_matches(&cfg, &sess, Some(&features)), output)
+}
+
+#[test]
+fn registered_cfg_predicates() {
+    with_default_globals(|| {
+        let source = r#"all(unix, version("1.40"))"#;
+        let (matches, output) = eval_cfg_with_version_predicate(source, true);
+        assert!(matches);
+        assert!(output.diagnostics().is_empty());
+        let (matches, output) = eval_cfg_with_version_predicate(r#"not(version("1.41"))"#, true);
+        assert!(matches);
+        assert!(output.diagnostics().is_empty());
+
+        let (matches, output) = eval_cfg_with_version_predicate("version(unix)", true);
+        assert!(!matches);
+        assert!(output.rendered().contains("expected a version"));
+
+        let (_, output) = eval_cfg_with_version_predicate(r#"version("1.40")"#, false);
+        assert!(output.rendered().contains("`cfg(version)` is experimental and subject to change"));
+    })
+}
+
+#[test]
+fn invalid_cfg_predicates() {
+    with_default_globals(|| {
+        let (matches, output) = eval_cfg_with_version_predicate(r#"versoin("1.40")"#, true);
+        assert!(!matches);
+        output.assert_error_with_code("E0537");
+        output.assert_suggestion_applies_to(r#"version("1.40")"#);
+
+        let (matches, output) = eval_cfg_with_version_predicate("any(unix, foo(unix))", true);
+        assert!(matches);
+        assert!(output.rendered().contains("invalid predicate `foo`"));
+        assert!(output.diagnostics()[0].diagnostic.suggestions.is_empty());
+    })
+}
+
+/// Configures the items of `source`, returning the lints buffered for their `cfg` predicates,
+/// as their messages, their labels and their suggestions.
+fn cfg_predicate_lints(source: &str) -> Vec<(String, Vec<String>, Option<String>)> {
+    let sess = ParseSess::new(FilePathMapping::empty());
+    let name = PathBuf::from("test.rs").into();
+    let krate = panictry!(parse_crate_from_source_str(name, source.to_string(), &sess));
+    let mut strip_unconfigured = StripUnconfigured { sess: &sess, features: None };
+    for item in krate.module.items {
+        strip_unconfigured.configure(item);
+    }
+    let lints = sess.buffered_lints.lock().drain(..).collect::<Vec<_>>();
+    lints.into_iter().map(|lint| {
+        let (labels, suggestion) = match lint.diagnostic {
+            BufferedEarlyLintDiagnostics::CfgPredicate(labels, suggestion) => (labels, suggestion),
+            _ => (vec![], None),
+        };
+        let labels = labels.into_iter().map(|(span, label)| {
+            format!("{}: {}", sess.source_map().span_to_snippet(span).unwrap(), label)
+        }).collect();
+        (lint.msg, labels, suggestion.map(|(_, suggestion)| suggestion))
+    }).collect()
+}
+
+#[test]
+fn duplicate_cfg_predicates() {
+    with_default_globals(|| {
+        let lints = cfg_predicate_lints("#[cfg(any(foo, foo))] struct S;");
+        assert_eq!(lints, vec![(
+            "`any` predicate has duplicate clauses".to_string(),
+            vec!["foo: first specified here".to_string(), "foo: duplicate clause".to_string()],
+            Some("foo".to_string()),
+        )]);
+
+        let lints = cfg_predicate_lints(
+            r#"#[cfg_attr(not(all(unix, feature = "a", unix)), derive(Debug))] struct S;"#
+        );
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].2, Some(r#"all(unix, feature = "a")"#.to_string()));
+
+        assert!(cfg_predicate_lints("#[cfg(any(foo, bar, not(foo)))] struct S;").is_empty());
+    })
+}
+
+#[test]
+fn contradictory_cfg_predicates() {
+    with_default_globals(|| {
+        let lints = cfg_predicate_lints("#[cfg(all(unix, foo, windows))] struct S;");
+        assert_eq!(lints, vec![(
+            "`cfg` predicate can never be satisfied".to_string(),
+            vec![
+                "unix: `unix` is required here".to_string(),
+                "windows: but `windows` is also required".to_string(),
+            ],
+            None,
+        )]);
+
+        let contradictions = [
+            r#"all(target_os = "linux", not(target_os = "linux"))"#,
+            r#"all(target_pointer_width = "32", target_pointer_width = "64")"#,
+            r#"all(not(windows), target_family = "windows")"#,
+            r#"any(foo, all(foo, not(foo)))"#,
+        ];
+        for contradiction in &contradictions {
+            let lints = cfg_predicate_lints(&format!("#[cfg({})] struct S;", contradiction));
+            assert_eq!(lints.len(), 1, "{} isn't linted", contradiction);
+        }
+
+        let satisfiable = [
+            r#"all(feature = "a", feature = "b")"#,
+            r#"all(unix, target_os = "linux")"#,
+            r#"all(not(windows), not(unix))"#,
+            r#"any(unix, windows)"#,
+        ];
+        for satisfiable in &satisfiable {
+            let lints = cfg_predicate_lints(&format!("#[cfg({})] struct S;", satisfiable));
+            assert!(lints.is_empty(), "{} is linted", satisfiable);
+        }
+    })
+}
+
+/// Configures the tokens of the item in `source`, returning them along with the snippets of the
+/// spans of their leaves.
+fn configured_item_tokens(source: &str, nested: bool) -> (TokenStream, Vec<String>) {
+    fn snippets(sess: &ParseSess, tokens: &TokenStream, out: &mut Vec<String>) {
+        for tree in tokens.trees() {
+            match tree {
+                TokenTree::Token(token) => {
+                    out.push(sess.source_map().span_to_snippet(token.span).unwrap());
+                }
+                TokenTree::Delimited(_, _, tokens) => snippets(sess, &tokens, out),
+            }
+        }
+    }
+
+    let sess = ParseSess::new(FilePathMapping::empty());
+    let item = with_error_checking_parse(source.to_string(), &sess, |p| p.parse_item()).unwrap();
+    let mut strip_unconfigured = StripUnconfigured { sess: &sess, features: None };
+    let tokens = strip_unconfigured.configure_tokens(item.tokens.clone().unwrap(), nested);
+    let mut out = Vec::new();
+    snippets(&sess, &tokens, &mut out);
+    (tokens, out)
+}
+
+#[test]
+fn configure_item_tokens() {
+    with_default_globals(|| {
+        let (tokens, snippets) = configured_item_tokens(
+            "struct S { #[cfg_attr(all(), allow(dead_code))] a: u8, #[cfg(FALSE)] b: u8, c: u8 }",
+            true,
+        );
+        let expected = string_to_stream("struct S { #[allow(dead_code)] a: u8, c: u8 }".into());
+        assert!(tokens.eq_unspanned(&expected), "{}", tokens);
+        assert_eq!(snippets, [
+            "struct", "S", "allow(dead_code)", "allow", "dead_code", "a", ":", "u8", ",",
+            "c", ":", "u8",
+        ]);
+
+        // Only the inner attributes of the item itself are configured unless `nested` is set.
+        let source = "mod m { #![cfg_attr(all(), cfg_attr(all(), allow(dead_code)), test)] \
+                      #[cfg(FALSE)] fn f() {} }";
+        let (tokens, _) = configured_item_tokens(source, false);
+        let expected = "mod m { #![allow(dead_code)] #![test] #[cfg(FALSE)] fn f() {} }";
+        assert!(tokens.eq_unspanned(&string_to_stream(expected.into())), "{}", tokens);
+        let (tokens, _) = configured_item_tokens(source, true);
+        let expected = "mod m { #![allow(dead_code)] #![test] }";
+        assert!(tokens.eq_unspanned(&string_to_stream(expected.into())), "{}", tokens);
+
+        // The arguments of macro invocations are left alone.
+        let source = "fn f() { #[cfg_attr(FALSE, inline)] let x = m!(#[cfg(FALSE)] a); }";
+        let (tokens, _) = configured_item_tokens(source, true);
+        let expected = "fn f() { let x = m!(#[cfg(FALSE)] a); }";
+        assert!(tokens.eq_unspanned(&string_to_stream(expected.into())), "{}", tokens);
+
+        // Stripped statements go on past the blocks of `if` expressions followed by `else`.
+        let source = "fn f() { #[cfg(FALSE)] let a = if c { 1 } else { 2 }; \
+                      #[cfg(FALSE)] if c {} else if d {} else {} g(); }";
+        let (tokens, _) = configured_item_tokens(source, true);
+        let expected = "fn f() { g(); }";
+        assert!(tokens.eq_unspanned(&string_to_stream(expected.into())), "{}", tokens);
+    })
+}
+
+#[test]
+fn parse_repr_hints() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let source = "#[repr(C, u8)] #[repr(align(4), align(8))] #[repr(u16)] enum E { A }";
+        let krate = panictry!(parse_crate_from_source_str(
+            PathBuf::from("test.rs").into(), source.to_string(), &sess,
+        ));
+        let repr = attr::parse_repr(&sess, &krate.module.items[0].attrs);
+        assert!(repr.is_c() && !repr.is_simd() && !repr.is_transparent());
+        assert_eq!(repr.int(), Some(attr::UnsignedInt(ast::UintTy::U16)));
+        assert_eq!((repr.packed(), repr.align()), (None, Some(8)));
+        let snippets = repr.hints.iter().map(|&(_, span)| {
+            sess.source_map().span_to_snippet(span).unwrap()
+        }).collect::<Vec<_>>();
+        assert_eq!(snippets, ["C", "u8", "align(4)", "align(8)", "u16"]);
+        assert_eq!(sess.span_diagnostic.err_count(), 0);
+    })
+}
+
+#[test]
+fn suggestions_in_crlf_files() {
+    with_default_globals(|| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let json_source_map = source_map.clone();
+        let emitter = TestEmitter::new(Some(source_map.clone())).with_json(|dst| {
+            let rendered = HumanReadableErrorType::Default(ColorConfig::Never);
+            Box::new(JsonEmitter::new(dst, None, json_source_map, false, rendered, false))
+        });
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+        let sess = ParseSess::with_span_handler(handler, source_map);
+
+        // A string literal with a lone `\r`, after a BOM and `\r\n` line endings.
+        let source = "\u{feff}fn main() {\r\n    let s = \"a\rb\";\r\n}\r\n";
+        panictry!(parse_crate_from_source_str(
+            PathBuf::from("test.rs").into(), source.to_string(), &sess,
+        ));
+        assert_eq!(sess.span_diagnostic.err_count(), 1);
+
+        // Applied to the normalized source, which spans point into.
+        output.assert_suggestion_applies_to("fn main() {\n    let s = \"a\\rb\";\n}\n");
+
+        // Applied to the source on disk, which the JSON byte offsets point into.
+        let diagnostic: json_format::Diagnostic = json::decode(&output.json()).unwrap();
+        let span = &diagnostic.children[0].spans[0];
+        let (start, end) = (span.byte_start as usize, span.byte_end as usize);
+        assert_eq!(&source[start..end], "\r");
+        let fixed = format!("{}{}{}",
+                            &source[..start],
+                            span.suggested_replacement.as_ref().unwrap(),
+                            &source[end..]);
+        assert_eq!(fixed, "\u{feff}fn main() {\r\n    let s = \"a\\rb\";\r\n}\r\n");
+    })
+}
+
+/// Splices a suggestion replacing `lo..hi` of the normalized `src` by `snippet`.
+fn splice_suggestion(src: &str, lo: u32, hi: u32, snippet: &str) -> String {
+    let source_map = SourceMap::new(FilePathMapping::empty());
+    let file = source_map.new_source_file(PathBuf::from("test.rs").into(), src.to_owned());
+    let span = Span::with_root_ctxt(file.start_pos + BytePos(lo), file.start_pos + BytePos(hi));
+    let suggestion = CodeSuggestion {
+        substitutions: vec![Substitution {
+            parts: vec![SubstitutionPart { span, snippet: snippet.to_owned() }],
+        }],
+        msg: "try".to_owned(),
+        style: SuggestionStyle::ShowCode,
+        applicability: Applicability::MachineApplicable,
+    };
+    suggestion.splice_lines(&source_map).remove(0).0
+}
+
+#[test]
+fn splice_lines_line_endings() {
+    with_default_globals(|| {
+        let crlf = "let x = 1;\r\nx\r\n";
+        assert_eq!(splice_suggestion(crlf, 11, 12, "y"), "y\r\n");
+        assert_eq!(splice_suggestion(crlf, 8, 9, "{\n    1\n}"), "let x = {\r\n    1\r\n};\r\n");
+
+        // Spans covering a line break.
+        let crlf = "a\r\nb\r\nc";
+        assert_eq!(splice_suggestion(crlf, 0, 3, "d"), "d\r\n");
+
+        // The last line of a file without a final newline.
+        assert_eq!(splice_suggestion("a\nb", 2, 3, "c"), "c");
+        assert_eq!(splice_suggestion(crlf, 4, 5, "d"), "d");
+
+        // Each line keeps its own line ending.
+        let mixed = "a\nb\r\nc\n";
+        assert_eq!(splice_suggestion(mixed, 0, 3, "d\ne"), "d\ne\r\n");
+        assert_eq!(splice_suggestion(mixed, 2, 5, "d\ne"), "d\r\ne\n");
+    })
+}
+
+#[test]
+fn suppression_markers() {
+    with_default_globals(|| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let emitter = TestEmitter::new(Some(source_map.clone()));
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+        let sess = ParseSess::with_span_handler(handler, source_map);
+
+        let source = "fn f() {\n\
+                      \x20   // rustc: allow(E0308) next-line\n\
+                      \x20   let x: u8 = \"\";\n\
+                      \x20   let y = 1; // rustc: allow(unused_variables, E0308)\n\
+                      \x20   // rustc: allow E0308\n\
+                      \x20   // rustc: allowed to be left alone\n\
+                      }\n";
+        let name = PathBuf::from("test.rs").into();
+        panictry!(parse_crate_from_source_str(name, source.to_string(), &sess));
+
+        let line = |text: &str| {
+            let lo = source.find(text).unwrap();
+            let hi = lo + source[lo..].find('\n').unwrap();
+            (BytePos(lo as u32), BytePos(hi as u32))
+        };
+        let (x_lo, x_hi) = line("    let x");
+        let (y_lo, y_hi) = line("    let y");
+        assert_eq!(sess.suppressions.borrow().file_suppressions(BytePos(0)), &[
+            Suppression { lo: x_lo, hi: x_hi, codes: vec!["E0308".to_string()] },
+            Suppression {
+                lo: y_lo,
+                hi: y_hi,
+                codes: vec!["unused_variables".to_string(), "E0308".to_string()],
+            },
+        ]);
+
+        let span_of = |text: &str| {
+            let lo = BytePos(source.find(text).unwrap() as u32);
+            Span::with_root_ctxt(lo, lo + BytePos(text.len() as u32))
+        };
+        // The malformed marker is linted against instead of being reported right away, unlike
+        // the comment that isn't a marker.
+        assert!(output.diagnostics().is_empty());
+        let lints = sess.buffered_lints.lock().drain(..).collect::<Vec<_>>();
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].msg, "malformed diagnostic suppression marker");
+        assert_eq!(lints[0].span.primary_span(), Some(span_of("// rustc: allow E0308")));
+
+        let mismatched_types = || DiagnosticId::Error("E0308".into());
+        let handler = &sess.span_diagnostic;
+        handler.struct_span_err_with_code(span_of("\"\""), "mismatched types", mismatched_types())
+            .emit();
+        handler.struct_span_err_with_code(span_of("f()"), "mismatched types", mismatched_types())
+            .emit();
+        handler.struct_span_warn_with_code(
+            span_of("y = 1"),
+            "unused variable: `y`",
+            DiagnosticId::Lint("unused_variables".into()),
+        ).emit();
+
+        // Only the error outside of the marked lines is emitted, but both count as errors.
+        let diagnostics = output.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].diagnostic.span.primary_span(), Some(span_of("f()")));
+        assert_eq!(handler.err_count(), 2);
+        assert_eq!(handler.session_summary(&Registry::new(&[])).silenced_by_markers, 2);
+    })
+}
+
+#[test]
+fn splice_lines_indentation() {
+    with_default_globals(|| {
+        let src = "fn f() {\n\tx;\n}\n";
+        assert_eq!(
+            splice_suggestion(src, 10, 12, "if y {\n\t    x;\n\t}"),
+            "\tif y {\n\t\tx;\n\t}\n",
+        );
+    })
+}
+
+thread_local! {
+    static SUBSCRIBER_CALLS: Cell<usize> = Cell::new(0);
+}
+
+fn count_subscriber_calls(_: &Diagnostic) {
+    SUBSCRIBER_CALLS.with(|calls| calls.set(calls.get() + 1));
+}
+
+#[test]
+fn nested_scoped_subscribers() {
+    with_default_globals(|| {
+        let handler = Handler::with_emitter(true, None, Box::new(TestEmitter::new(None)));
+        subscribers::with_subscriber(count_subscriber_calls, || {
+            subscribers::with_subscriber(count_subscriber_calls, || handler.err("inner"));
+            // The inner call must leave the outer registration in place.
+            handler.err("outer");
+        });
+        handler.err("unobserved");
+        assert_eq!(SUBSCRIBER_CALLS.with(|calls| calls.get()), 2);
+    })
+}
+
+thread_local! {
+    static TRACKED_BUGS: RefCell<Vec<String>> = RefCell::new(Vec::new());
+}
+
+#[test]
+fn tracked_bugs() {
+    with_default_globals(|| {
+        let subscriber = subscribers::subscribe_bugs(|diagnostic, backtrace| {
+            assert!(!backtrace.frames().is_empty());
+            TRACKED_BUGS.with(|bugs| bugs.borrow_mut().push(diagnostic.message()));
+        });
+
+        let handler = Handler::with_emitter(true, None, Box::new(TestEmitter::new(None)));
+        handler.warn("not a bug");
+        handler.emit_diagnostic(&Diagnostic::new(Level::Bug, "emitted bug"));
+
+        // Delayed bugs are reported once the handler is dropped without any error.
+        let handler = Handler::with_emitter(true, None, Box::new(TestEmitter::new(None)));
+        handler.delay_span_bug(DUMMY_SP, "delayed bug");
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| drop(handler))).is_err());
+
+        subscribers::unsubscribe(subscriber);
+        let handler = Handler::with_emitter(true, None, Box::new(TestEmitter::new(None)));
+        handler.emit_diagnostic(&Diagnostic::new(Level::Bug, "untracked bug"));
+
+        TRACKED_BUGS.with(|bugs| assert_eq!(*bugs.borrow(), ["emitted bug", "delayed bug"]));
+    })
+}
+
+#[test]
+fn handler_sessions() {
+    with_default_globals(|| {
+        let emitter = TestEmitter::new(None);
+        let output = emitter.output();
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+        handler.err("repeated error");
+
+        let session = handler.begin_session();
+        assert_eq!(handler.err_count(), 0);
+        // Diagnostics from before the session don't deduplicate those of the session.
+        handler.err("repeated error");
+        handler.delay_span_bug(DUMMY_SP, "discarded along with the session's errors");
+        assert_eq!(handler.err_count(), 1);
+        handler.end_session(session);
+
+        assert_eq!(handler.err_count(), 1);
+        assert_eq!(output.diagnostics().len(), 2);
+    })
+}
+
+#[test]
+#[should_panic(expected = "no errors encountered even though `delay_span_bug` issued")]
+fn handler_session_with_delayed_bugs() {
+    with_default_globals(|| {
+        let handler = Handler::with_emitter(true, None, Box::new(TestEmitter::new(None)));
+        let session = handler.begin_session();
+        handler.delay_span_bug(DUMMY_SP, "never reported");
+        handler.end_session(session);
+    })
+}
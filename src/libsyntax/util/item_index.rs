@@ -0,0 +1,171 @@
+//! An index of the functions, `impl`s and traits of a crate by their spans, answering which
+//! items contain a position in logarithmic time. It's built after expansion and shared by the
+//! `ParseSess` and the `SourceMap`, so that emitters can show the item a diagnostic points into,
+//! like `in fn main`, and tools can query it without walking the AST again.
+
+use crate::ast::{self, ImplItemKind, Item, ItemKind, Mac, TraitItemKind};
+use crate::print::pprust;
+use crate::visit::{self, Visitor};
+
+use syntax_pos::{BytePos, Span};
+
+#[cfg(test)]
+mod tests;
+
+/// An item in an `ItemIndex`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedItem {
+    pub span: Span,
+    /// How the item is shown to the user, e.g. `fn main` or `impl Default for S`.
+    pub descr: String,
+    /// The index of the innermost item containing this one.
+    parent: Option<usize>,
+}
+
+/// The items containing a position, from the outermost to the innermost one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemPath {
+    pub items: Vec<IndexedItem>,
+}
+
+impl ItemPath {
+    /// The innermost item containing the position.
+    pub fn innermost(&self) -> &IndexedItem {
+        self.items.last().unwrap()
+    }
+}
+
+/// The functions, methods, `impl`s and traits of a crate, sorted by their start.
+///
+/// Since items are properly nested, each of them is linked to the innermost item containing
+/// it, and the items containing a position are found from the last item starting before it.
+#[derive(Clone, Debug, Default)]
+pub struct ItemIndex {
+    items: Vec<IndexedItem>,
+}
+
+impl ItemIndex {
+    /// Indexes the items of `krate`. Macro invocations are not looked into, and the items
+    /// they expanded to are left out, as their spans aren't nested in the ones of the others.
+    pub fn new(krate: &ast::Crate) -> ItemIndex {
+        let mut collector = ItemCollector { items: Vec::new(), prefix: None };
+        visit::walk_crate(&mut collector, krate);
+        // The items are collected in pre-order, which is sorted by their start except for the
+        // items of out-of-line modules. The sort is stable so that an item starting at the
+        // same position as the one containing it still comes after it.
+        let mut items = collector.items;
+        items.sort_by_key(|item| item.span.lo());
+
+        // The items that contain the one being linked, from the outermost one.
+        let mut stack: Vec<usize> = Vec::new();
+        for idx in 0..items.len() {
+            let lo = items[idx].span.lo();
+            while stack.last().map_or(false, |&parent| items[parent].span.hi() <= lo) {
+                stack.pop();
+            }
+            items[idx].parent = stack.last().cloned();
+            stack.push(idx);
+        }
+        ItemIndex { items }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the items containing `pos`, or `None` if no item does.
+    pub fn query(&self, pos: BytePos) -> Option<ItemPath> {
+        // The last item starting at or before `pos`, or one of the items containing it, is the
+        // innermost item containing `pos`, if any.
+        let mut idx = match self.items.binary_search_by_key(&pos, |item| item.span.lo()) {
+            // Of several items starting at `pos`, the last one is the innermost.
+            Ok(idx) => idx + self.items[idx + 1..].iter()
+                .take_while(|item| item.span.lo() == pos)
+                .count(),
+            Err(0) => return None,
+            Err(idx) => idx - 1,
+        };
+        while pos >= self.items[idx].span.hi() {
+            idx = self.items[idx].parent?;
+        }
+
+        let mut items = vec![self.items[idx].clone()];
+        while let Some(parent) = items.last().unwrap().parent {
+            items.push(self.items[parent].clone());
+        }
+        items.reverse();
+        Some(ItemPath { items })
+    }
+}
+
+struct ItemCollector {
+    items: Vec<IndexedItem>,
+    /// How the methods of the `impl` or trait being visited are prefixed, like `S` in
+    /// `fn S::new` or `<S as Default>` in `fn <S as Default>::default`.
+    prefix: Option<String>,
+}
+
+impl ItemCollector {
+    fn add(&mut self, span: Span, descr: String) {
+        if !span.is_dummy() && !span.from_expansion() {
+            self.items.push(IndexedItem { span, descr, parent: None });
+        }
+    }
+
+    fn add_method(&mut self, ident: ast::Ident, span: Span) {
+        let descr = match self.prefix {
+            Some(ref prefix) => format!("fn {}::{}", prefix, ident),
+            None => format!("fn {}", ident),
+        };
+        self.add(span, descr);
+    }
+}
+
+impl<'ast> Visitor<'ast> for ItemCollector {
+    fn visit_item(&mut self, item: &'ast Item) {
+        let (descr, prefix) = match item.node {
+            ItemKind::Fn(..) => (format!("fn {}", item.ident), None),
+            ItemKind::Impl(.., Some(ref trait_ref), ref self_ty, _) => {
+                let self_ty = pprust::ty_to_string(self_ty);
+                let trait_path = pprust::path_to_string(&trait_ref.path);
+                (
+                    format!("impl {} for {}", trait_path, self_ty),
+                    Some(format!("<{} as {}>", self_ty, trait_path)),
+                )
+            }
+            ItemKind::Impl(.., None, ref self_ty, _) => {
+                let self_ty = pprust::ty_to_string(self_ty);
+                (format!("impl {}", self_ty), Some(self_ty))
+            }
+            ItemKind::Trait(..) => (format!("trait {}", item.ident), Some(item.ident.to_string())),
+            _ => return visit::walk_item(self, item),
+        };
+        self.add(item.span, descr);
+        // The items nested in a function are not prefixed, while the ones in an `impl` or a
+        // trait get its prefix.
+        let outer_prefix = std::mem::replace(&mut self.prefix, prefix);
+        visit::walk_item(self, item);
+        self.prefix = outer_prefix;
+    }
+
+    fn visit_trait_item(&mut self, item: &'ast ast::TraitItem) {
+        if let TraitItemKind::Method(..) = item.node {
+            self.add_method(item.ident, item.span);
+        }
+        // The functions nested in a method are not methods themselves.
+        let prefix = self.prefix.take();
+        visit::walk_trait_item(self, item);
+        self.prefix = prefix;
+    }
+
+    fn visit_impl_item(&mut self, item: &'ast ast::ImplItem) {
+        if let ImplItemKind::Method(..) = item.node {
+            self.add_method(item.ident, item.span);
+        }
+        let prefix = self.prefix.take();
+        visit::walk_impl_item(self, item);
+        self.prefix = prefix;
+    }
+
+    fn visit_mac(&mut self, _mac: &'ast Mac) {}
+}
@@ -0,0 +1,78 @@
+use super::*;
+
+use crate::parse::{self, ParseSess};
+use crate::source_map::FilePathMapping;
+use crate::with_default_globals;
+
+use syntax_pos::{BytePos, FileName};
+
+const SOURCE: &str = "fn main() {
+    fn helper() { a }
+    b
+}
+struct S;
+impl S {
+    fn new() -> S { c }
+}
+impl Default for S {
+    fn default() -> S { d }
+}
+trait T {
+    fn required();
+    fn provided() { e }
+    const C: u8 = f;
+}
+static X: u8 = g;
+";
+
+fn index(sess: &ParseSess) -> ItemIndex {
+    let name = FileName::Custom("item_index".to_string());
+    let krate = parse::parse_crate_from_source_str(name, SOURCE.to_string(), sess).unwrap();
+    ItemIndex::new(&krate)
+}
+
+/// The position of the first occurrence of `text`.
+fn pos(sess: &ParseSess, text: &str) -> BytePos {
+    sess.source_map().files()[0].start_pos + BytePos(SOURCE.find(text).unwrap() as u32)
+}
+
+#[test]
+fn innermost_items() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let index = index(&sess);
+        let expected = [
+            ("main", Some("fn main")),
+            ("a }", Some("fn helper")),
+            ("b\n", Some("fn main")),
+            ("struct", None),
+            ("c }", Some("fn S::new")),
+            ("fn default", Some("fn <S as Default>::default")),
+            ("d }", Some("fn <S as Default>::default")),
+            ("impl Default", Some("impl Default for S")),
+            ("e }", Some("fn T::provided")),
+            ("f;", Some("trait T")),
+            ("g;", None),
+        ];
+        for &(text, item) in &expected {
+            let path = index.query(pos(&sess, text));
+            assert_eq!(path.as_ref().map(|path| &path.innermost().descr[..]), item, "{}", text);
+        }
+    })
+}
+
+#[test]
+fn item_paths() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let index = index(&sess);
+        let descrs = |text| {
+            index.query(pos(&sess, text)).unwrap().items.into_iter()
+                .map(|item| item.descr)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(descrs("a }"), ["fn main", "fn helper"]);
+        assert_eq!(descrs("d }"), ["impl Default for S", "fn <S as Default>::default"]);
+        assert_eq!(descrs("required"), ["trait T", "fn T::required"]);
+    })
+}
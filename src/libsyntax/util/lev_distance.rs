@@ -1,4 +1,4 @@
-use std::cmp;
+use std::{cmp, mem};
 use crate::symbol::Symbol;
 
 #[cfg(test)]
@@ -35,48 +35,111 @@ pub fn lev_distance(a: &str, b: &str) -> usize {
     dcol[t_last + 1]
 }
 
-/// Finds the best match for a given word in the given iterator
-///
-/// As a loose rule to avoid the obviously incorrect suggestions, it takes
-/// an optional limit for the maximum allowable edit distance, which defaults
-/// to one-third of the given word.
-///
-/// Besides Levenshtein, we use case insensitive comparison to improve accuracy on an edge case with
-/// a lower(upper)case letters mismatch.
+/// The cost of an edit in `weighted_distance`, except for the cheap substitutions, which
+/// cost 1, see `substitution_cost`.
+const EDIT_COST: usize = 2;
+
+/// Finds the Damerau-Levenshtein distance between two strings, in its optimal string alignment
+/// form, so that transposing two adjacent characters is a single edit. Each edit costs
+/// `EDIT_COST`, but for the substitutions `substitution_cost` considers cheap.
+fn weighted_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // The rows of the distance matrix for the two previous characters of `a` and for the
+    // current one.
+    let mut prev2 = vec![0; b.len() + 1];
+    let mut prev: Vec<_> = (0..=b.len()).map(|j| j * EDIT_COST).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i * EDIT_COST;
+        for j in 1..=b.len() {
+            let substitution = prev[j - 1] + substitution_cost(a[i - 1], b[j - 1]);
+            let insertion_or_deletion = cmp::min(prev[j], current[j - 1]) + EDIT_COST;
+            current[j] = cmp::min(substitution, insertion_or_deletion);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = cmp::min(current[j], prev2[j - 2] + EDIT_COST);
+            }
+        }
+        mem::swap(&mut prev2, &mut prev);
+        mem::swap(&mut prev, &mut current);
+    }
+    prev[b.len()]
+}
+
+/// Substituting a letter for the same letter in another case, or `_` for `-`, only costs half
+/// an edit, as names are often misspelled that way.
+fn substitution_cost(a: char, b: char) -> usize {
+    if a == b {
+        0
+    } else if a.to_lowercase().eq(b.to_lowercase()) || (a == '_' && b == '-') ||
+              (a == '-' && b == '_') {
+        EDIT_COST / 2
+    } else {
+        EDIT_COST
+    }
+}
+
+/// Finds the best match for a given word in the given iterator, see
+/// `find_best_matches_for_name`.
 pub fn find_best_match_for_name<'a, T>(iter_names: T,
                                        lookup: &str,
                                        dist: Option<usize>) -> Option<Symbol>
     where T: Iterator<Item = &'a Symbol> {
-    let max_dist = dist.map_or_else(|| cmp::max(lookup.len(), 3) / 3, |d| d);
+    find_best_matches_for_name(iter_names, lookup, dist).into_iter().next()
+}
+
+/// Finds the names in the given iterator that are close enough to a given word to be suggested
+/// instead of it, the best match first.
+///
+/// The names are compared to the word by their Damerau-Levenshtein distance, where changing the
+/// case of a letter or writing `-` for `_` is only half an edit. As a loose rule to avoid the
+/// obviously incorrect suggestions, it takes an optional limit for the maximum allowable number
+/// of edits, which defaults to one-third of the number of characters of the given word.
+///
+/// The names equal to the word when compared case insensitively come first, then the others in
+/// the order of their distance to the word. Ties are kept in the order of the iterator.
+pub fn find_best_matches_for_name<'a, T>(iter_names: T,
+                                         lookup: &str,
+                                         dist: Option<usize>) -> Vec<Symbol>
+    where T: Iterator<Item = &'a Symbol> {
+    let lookup_len = lookup.chars().count();
+    let max_dist = dist.map_or_else(|| cmp::max(lookup_len, 3) / 3, |d| d) * EDIT_COST;
+    let lookup_uppercase = lookup.to_uppercase();
 
-    let (case_insensitive_match, levenstein_match) = iter_names
-    .filter_map(|&name| {
-        let dist = lev_distance(lookup, &name.as_str());
+    let mut matches: Vec<_> = iter_names.filter_map(|&name| {
+        let name_str = name.as_str();
+        // The distance is at least the difference in length, which is cheaper to compute.
+        let len_diff = (name_str.chars().count() as isize - lookup_len as isize).abs() as usize;
+        if len_diff * EDIT_COST > max_dist {
+            return None;
+        }
+        let dist = weighted_distance(lookup, &name_str);
         if dist <= max_dist {
-            Some((name, dist))
+            let case_insensitive_match = name_str.to_uppercase() == lookup_uppercase;
+            Some((!case_insensitive_match, dist, name))
         } else {
             None
         }
-    })
-    // Here we are collecting the next structure:
-    // (case_insensitive_match, (levenstein_match, levenstein_distance))
-    .fold((None, None), |result, (candidate, dist)| {
-        (
-            if candidate.as_str().to_uppercase() == lookup.to_uppercase() {
-                Some(candidate)
-            } else {
-                result.0
-            },
-            match result.1 {
-                None => Some((candidate, dist)),
-                Some((c, d)) => Some(if dist < d { (candidate, dist) } else { (c, d) })
-            }
-        )
-    });
+    }).collect();
+    // Sorting is stable, so ties are kept in order.
+    matches.sort_by_key(|&(case_insensitive_mismatch, dist, _)| (case_insensitive_mismatch, dist));
+    matches.into_iter().map(|(_, _, name)| name).collect()
+}
 
-    if let Some(candidate) = case_insensitive_match {
-        Some(candidate) // exact case insensitive match has a higher priority
-    } else {
-        if let Some((candidate, _)) = levenstein_match { Some(candidate) } else { None }
-    }
+/// Like `find_best_matches_for_name`, but ranks the names `is_preferred` holds for before the
+/// others, keeping the ranking by distance within both groups. This lets callers that know more
+/// about the names than their spelling, e.g., the types of the fields they are the names of,
+/// favor the names that fit where the word is used.
+pub fn find_best_matches_for_name_by<'a, T, F>(iter_names: T,
+                                               lookup: &str,
+                                               dist: Option<usize>,
+                                               mut is_preferred: F) -> Vec<Symbol>
+    where T: Iterator<Item = &'a Symbol>,
+          F: FnMut(Symbol) -> bool {
+    let mut matches = find_best_matches_for_name(iter_names, lookup, dist);
+    // Sorting is stable, so the ranking by distance is kept.
+    matches.sort_by_cached_key(|&name| !is_preferred(name));
+    matches
 }
@@ -56,3 +56,66 @@ fn test_find_best_match_for_name() {
         );
     })
 }
+
+#[test]
+fn test_weighted_distance() {
+    assert_eq!(weighted_distance("", "abc"), 3 * EDIT_COST);
+    assert_eq!(weighted_distance("abc", "abc"), 0);
+    assert_eq!(weighted_distance("abc", "acb"), EDIT_COST);
+    assert_eq!(weighted_distance("abcd", "badc"), 2 * EDIT_COST);
+    assert_eq!(weighted_distance("foo_bar", "foo-bar"), EDIT_COST / 2);
+    assert_eq!(weighted_distance("FooBar", "foobar"), EDIT_COST);
+    assert_eq!(weighted_distance("kitten", "sitting"), 3 * EDIT_COST);
+}
+
+#[test]
+fn test_find_best_matches_for_name() {
+    use crate::with_default_globals;
+    with_default_globals(|| {
+        let input = vec![
+            Symbol::intern("recieve"),
+            Symbol::intern("receiver"),
+            Symbol::intern("Receive"),
+            Symbol::intern("deceive"),
+        ];
+        // The transposition in `recieve` is a single edit.
+        assert_eq!(
+            find_best_matches_for_name(input.iter(), "receive", None),
+            vec![
+                Symbol::intern("Receive"),
+                Symbol::intern("recieve"),
+                Symbol::intern("receiver"),
+                Symbol::intern("deceive"),
+            ]
+        );
+
+        let input = vec![Symbol::intern("target_os"), Symbol::intern("target_env")];
+        assert_eq!(
+            find_best_match_for_name(input.iter(), "target-os", None),
+            Some(Symbol::intern("target_os"))
+        );
+
+        // The default limit counts characters, not bytes.
+        let input = vec![Symbol::intern("äb")];
+        assert_eq!(find_best_match_for_name(input.iter(), "äöü", None), None);
+    })
+}
+
+#[test]
+fn test_find_best_matches_for_name_by() {
+    use crate::with_default_globals;
+    with_default_globals(|| {
+        let input = vec![Symbol::intern("fob"), Symbol::intern("foo"), Symbol::intern("bar")];
+        let foo = Symbol::intern("foo");
+        assert_eq!(
+            find_best_matches_for_name_by(input.iter(), "fox", None, |name| name == foo),
+            vec![Symbol::intern("foo"), Symbol::intern("fob")]
+        );
+        // Names too far from the word are not suggested, even when preferred.
+        let bar = Symbol::intern("bar");
+        assert_eq!(
+            find_best_matches_for_name_by(input.iter(), "fox", None, |name| name == bar),
+            vec![Symbol::intern("fob"), Symbol::intern("foo")]
+        );
+    })
+}
@@ -1,102 +1,214 @@
-use std::ptr;
+use std::{mem, ptr};
+use rustc_data_structures::sync::{self, ParallelIterator, Send, Sync};
 use smallvec::{Array, SmallVec};
 
+#[cfg(test)]
+mod tests;
+
 pub trait MapInPlace<T>: Sized {
     fn map_in_place<F>(&mut self, mut f: F) where F: FnMut(T) -> T {
         self.flat_map_in_place(|e| Some(f(e)))
     }
 
-    fn flat_map_in_place<F, I>(&mut self, f: F)
+    fn flat_map_in_place<F, I>(&mut self, mut f: F)
         where F: FnMut(T) -> I,
+              I: IntoIterator<Item=T>
+    {
+        match self.try_flat_map_in_place(|e| Ok::<_, Never>(f(e))) {
+            Ok(()) => {}
+            Err(never) => match never {},
+        }
+    }
+
+    /// Like `flat_map_in_place`, but stops at the first element `f` fails on, returning its
+    /// error. The elements mapped so far are kept, followed by the ones `f` was not called on.
+    fn try_flat_map_in_place<F, I, E>(&mut self, f: F) -> Result<(), E>
+        where F: FnMut(T) -> Result<I, E>,
               I: IntoIterator<Item=T>;
 }
 
+enum Never {}
+
 impl<T> MapInPlace<T> for Vec<T> {
-    fn flat_map_in_place<F, I>(&mut self, mut f: F)
-        where F: FnMut(T) -> I,
+    fn try_flat_map_in_place<F, I, E>(&mut self, f: F) -> Result<(), E>
+        where F: FnMut(T) -> Result<I, E>,
               I: IntoIterator<Item=T>
     {
-        let mut read_i = 0;
-        let mut write_i = 0;
-        unsafe {
-            let mut old_len = self.len();
-            self.set_len(0); // make sure we just leak elements in case of panic
-
-            while read_i < old_len {
-                // move the read_i'th item out of the vector and map it
-                // to an iterator
-                let e = ptr::read(self.get_unchecked(read_i));
-                let iter = f(e).into_iter();
-                read_i += 1;
-
-                for e in iter {
-                    if write_i < read_i {
-                        ptr::write(self.get_unchecked_mut(write_i), e);
-                        write_i += 1;
-                    } else {
-                        // If this is reached we ran out of space
-                        // in the middle of the vector.
-                        // However, the vector is in a valid state here,
-                        // so we just do a somewhat inefficient insert.
-                        self.set_len(old_len);
-                        self.insert(write_i, e);
-
-                        old_len = self.len();
-                        self.set_len(0);
-
-                        read_i += 1;
-                        write_i += 1;
-                    }
-                }
-            }
-
-            // write_i tracks the number of actually written new items.
-            self.set_len(write_i);
-        }
+        try_flat_map_in_place(self, f)
     }
 }
 
 impl<T, A: Array<Item = T>> MapInPlace<T> for SmallVec<A> {
-    fn flat_map_in_place<F, I>(&mut self, mut f: F)
-        where F: FnMut(T) -> I,
+    fn try_flat_map_in_place<F, I, E>(&mut self, f: F) -> Result<(), E>
+        where F: FnMut(T) -> Result<I, E>,
               I: IntoIterator<Item=T>
     {
-        let mut read_i = 0;
-        let mut write_i = 0;
-        unsafe {
-            let mut old_len = self.len();
-            self.set_len(0); // make sure we just leak elements in case of panic
-
-            while read_i < old_len {
-                // move the read_i'th item out of the vector and map it
-                // to an iterator
-                let e = ptr::read(self.get_unchecked(read_i));
-                let iter = f(e).into_iter();
-                read_i += 1;
-
-                for e in iter {
-                    if write_i < read_i {
-                        ptr::write(self.get_unchecked_mut(write_i), e);
-                        write_i += 1;
-                    } else {
-                        // If this is reached we ran out of space
-                        // in the middle of the vector.
-                        // However, the vector is in a valid state here,
-                        // so we just do a somewhat inefficient insert.
-                        self.set_len(old_len);
-                        self.insert(write_i, e);
-
-                        old_len = self.len();
-                        self.set_len(0);
-
-                        read_i += 1;
-                        write_i += 1;
-                    }
+        try_flat_map_in_place(self, f)
+    }
+}
+
+/// The vectors `MapInPlace` is implemented for.
+trait RawVec<T> {
+    fn len(&self) -> usize;
+    unsafe fn set_len(&mut self, len: usize);
+    fn reserve(&mut self, additional: usize);
+    fn as_mut_ptr(&mut self) -> *mut T;
+}
+
+impl<T> RawVec<T> for Vec<T> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    unsafe fn set_len(&mut self, len: usize) {
+        Vec::set_len(self, len)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional)
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        Vec::as_mut_ptr(self)
+    }
+}
+
+impl<T, A: Array<Item = T>> RawVec<T> for SmallVec<A> {
+    fn len(&self) -> usize {
+        SmallVec::len(self)
+    }
+
+    unsafe fn set_len(&mut self, len: usize) {
+        SmallVec::set_len(self, len)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        SmallVec::reserve(self, additional)
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        self.as_mut_slice().as_mut_ptr()
+    }
+}
+
+fn try_flat_map_in_place<T, V, F, I, E>(vec: &mut V, mut f: F) -> Result<(), E>
+    where V: RawVec<T>,
+          F: FnMut(T) -> Result<I, E>,
+          I: IntoIterator<Item=T>
+{
+    let mut read_i = 0;
+    let mut write_i = 0;
+    unsafe {
+        let mut old_len = vec.len();
+        vec.set_len(0); // make sure we just leak elements in case of panic
+
+        while read_i < old_len {
+            // move the read_i'th item out of the vector and map it
+            // to an iterator
+            let e = ptr::read(vec.as_mut_ptr().add(read_i));
+            read_i += 1;
+            let mut iter = match f(e) {
+                Ok(iter) => iter.into_iter(),
+                Err(err) => {
+                    // Close the gap between the items written and the ones not read yet.
+                    let tail = old_len - read_i;
+                    let ptr = vec.as_mut_ptr();
+                    ptr::copy(ptr.add(read_i), ptr.add(write_i), tail);
+                    vec.set_len(write_i + tail);
+                    return Err(err);
                 }
-            }
+            };
+
+            while let Some(e) = iter.next() {
+                if write_i == read_i {
+                    // If this is reached we ran out of space
+                    // in the middle of the vector. We make room
+                    // for this item and for the ones the iterator
+                    // says are left at once, rather than shifting
+                    // the rest of the vector for each of them.
+                    let gap = 1 + iter.size_hint().0;
+                    vec.set_len(old_len);
+                    vec.reserve(gap);
+                    let ptr = vec.as_mut_ptr();
+                    ptr::copy(ptr.add(read_i), ptr.add(read_i + gap), old_len - read_i);
+                    vec.set_len(0);
 
-            // write_i tracks the number of actually written new items.
-            self.set_len(write_i);
+                    old_len += gap;
+                    read_i += gap;
+                }
+                ptr::write(vec.as_mut_ptr().add(write_i), e);
+                write_i += 1;
+            }
         }
+
+        // write_i tracks the number of actually written new items.
+        vec.set_len(write_i);
+    }
+    Ok(())
+}
+
+/// The length of the chunks `chunked_flat_map_in_place` and `par_flat_map_in_place` split the
+/// top-level items of a module into.
+pub const ITEM_CHUNK_LEN: usize = 256;
+
+/// Does what `flat_map_in_place` does, but maps `vec` in chunks of `chunk_len` elements, so that
+/// an element mapped to several ones only moves the rest of its chunk rather than the rest of
+/// `vec`.
+///
+/// This is meant for the top-level items of large crates, where moving the tail of the
+/// vector whenever an item expands to several ones is costly.
+pub fn chunked_flat_map_in_place<T, F, I>(vec: &mut Vec<T>, chunk_len: usize, mut f: F)
+    where F: FnMut(T) -> I,
+          I: IntoIterator<Item=T>
+{
+    assert!(chunk_len > 0, "chunks must not be empty");
+    if vec.len() <= chunk_len {
+        return vec.flat_map_in_place(f);
+    }
+
+    let chunks = split_into_chunks(vec, chunk_len).into_iter().map(|mut chunk| {
+        chunk.flat_map_in_place(&mut f);
+        chunk
+    }).collect();
+    append_chunks(vec, chunks);
+}
+
+/// Does what `chunked_flat_map_in_place` does, but maps the chunks in parallel when the
+/// compiler is built with `parallel_compiler`.
+pub fn par_flat_map_in_place<T, F, I>(vec: &mut Vec<T>, chunk_len: usize, f: F)
+    where T: Send,
+          F: Fn(T) -> I + Sync + Send,
+          I: IntoIterator<Item=T>
+{
+    assert!(chunk_len > 0, "chunks must not be empty");
+    if vec.len() <= chunk_len {
+        return vec.flat_map_in_place(f);
+    }
+
+    let chunks = sync::par_iter(split_into_chunks(vec, chunk_len)).map(|mut chunk| {
+        chunk.flat_map_in_place(&f);
+        chunk
+    }).collect();
+    append_chunks(vec, chunks);
+}
+
+/// Moves the elements of `vec` into chunks of `chunk_len` elements, in order.
+fn split_into_chunks<T>(vec: &mut Vec<T>, chunk_len: usize) -> Vec<Vec<T>> {
+    // Split from the end, so that the elements are only moved once.
+    let mut rest = mem::take(vec);
+    let mut chunks = Vec::with_capacity(rest.len() / chunk_len + 1);
+    while rest.len() > chunk_len {
+        let at = rest.len() - chunk_len;
+        chunks.push(rest.split_off(at));
+    }
+    chunks.push(rest);
+    chunks.reverse();
+    chunks
+}
+
+fn append_chunks<T>(vec: &mut Vec<T>, chunks: Vec<Vec<T>>) {
+    vec.reserve(chunks.iter().map(|chunk| chunk.len()).sum());
+    for mut chunk in chunks {
+        vec.append(&mut chunk);
     }
 }
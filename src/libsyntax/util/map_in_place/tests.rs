@@ -0,0 +1,49 @@
+use super::*;
+
+#[test]
+fn flat_map_grows_and_shrinks() {
+    let mut vec: Vec<u32> = (0..6).collect();
+    vec.flat_map_in_place(|e| match e % 3 {
+        0 => vec![],
+        1 => vec![e],
+        _ => vec![e; 3],
+    });
+    assert_eq!(vec, [1, 2, 2, 2, 4, 5, 5, 5]);
+
+    let mut vec: SmallVec<[u32; 2]> = (0..3).collect();
+    vec.flat_map_in_place(|e| (0..e).map(move |i| e * 10 + i));
+    assert_eq!(vec.into_vec(), [10, 20, 21]);
+}
+
+#[test]
+fn try_flat_map_stops_at_the_first_error() {
+    let mut vec = vec![String::from("a"), "b".into(), "c".into(), "d".into()];
+    let result = vec.try_flat_map_in_place(|e| match &*e {
+        "a" => Ok(vec![e.clone(), e]),
+        "c" => Err(e),
+        _ => Ok(vec![]),
+    });
+    assert_eq!(result, Err("c".to_string()));
+    assert_eq!(vec, ["a", "a", "d"]);
+}
+
+#[test]
+fn par_flat_map_keeps_the_order() {
+    let mut vec: Vec<u32> = (0..100).collect();
+    par_flat_map_in_place(&mut vec, 7, |e| if e % 2 == 0 { vec![e, e] } else { vec![] });
+    let expected: Vec<u32> = (0..100).filter(|e| e % 2 == 0).flat_map(|e| vec![e, e]).collect();
+    assert_eq!(vec, expected);
+}
+
+#[test]
+fn chunked_flat_map_keeps_the_order() {
+    let mut vec: Vec<u32> = (0..100).collect();
+    let mut calls = 0;
+    chunked_flat_map_in_place(&mut vec, 7, |e| {
+        calls += 1;
+        if e % 2 == 0 { vec![e, e] } else { vec![] }
+    });
+    let expected: Vec<u32> = (0..100).filter(|e| e % 2 == 0).flat_map(|e| vec![e, e]).collect();
+    assert_eq!(vec, expected);
+    assert_eq!(calls, 100);
+}
@@ -0,0 +1,146 @@
+//! Size and complexity metrics of functions, computed over the AST, for lint-like tools and for
+//! diagnostics deciding whether a function is small enough to be shown inline.
+
+use crate::ast::{self, Block, Expr, ExprKind, FnDecl, Ident, Item, Mac, NodeId, Stmt, StmtKind};
+use crate::visit::{self, FnKind, Visitor};
+
+use syntax_pos::Span;
+
+#[cfg(test)]
+mod tests;
+
+/// The metrics of a function.
+///
+/// The closures in the function are measured as part of it, while the items nested in it,
+/// including other functions, are not.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FnMetrics {
+    pub ident: Ident,
+    pub span: Span,
+    /// The number of statements, including the ones of nested blocks.
+    pub statements: usize,
+    /// The largest number of blocks and control flow expressions nested in one another in the
+    /// body, with a chain of `else if` counted as a single level.
+    pub max_nesting: usize,
+    /// The number of arms of all the `match` expressions.
+    pub match_arms: usize,
+    /// An estimate of the cyclomatic complexity: one, plus one for each `if`, `while`, `for`,
+    /// `&&`, `||` and `?`, plus one for each `match` arm after the first one.
+    pub cyclomatic: usize,
+}
+
+impl FnMetrics {
+    /// Computes the metrics of the function `ident` spanning `span` and with the body `body`.
+    pub fn new(ident: Ident, span: Span, body: &Block) -> FnMetrics {
+        let mut visitor = BodyMetrics {
+            metrics: FnMetrics {
+                ident,
+                span,
+                statements: 0,
+                max_nesting: 0,
+                match_arms: 0,
+                cyclomatic: 1,
+            },
+            nesting: 0,
+        };
+        visitor.visit_block(body);
+        visitor.metrics
+    }
+
+    /// Whether the function is small and flat enough for diagnostics to print it inline.
+    pub fn fits_inline(&self) -> bool {
+        self.statements <= 3 && self.max_nesting <= 1 && self.cyclomatic <= 2
+    }
+}
+
+/// Computes the metrics of all the functions and methods with a body in `krate`, in the order
+/// they start in. The macro invocations in `krate` are not looked into, so it is usually
+/// expanded first.
+pub fn crate_fn_metrics(krate: &ast::Crate) -> Vec<FnMetrics> {
+    let mut collector = FnMetricsCollector { metrics: Vec::new() };
+    visit::walk_crate(&mut collector, krate);
+    collector.metrics
+}
+
+struct FnMetricsCollector {
+    metrics: Vec<FnMetrics>,
+}
+
+impl<'ast> Visitor<'ast> for FnMetricsCollector {
+    fn visit_fn(&mut self, kind: FnKind<'ast>, decl: &'ast FnDecl, span: Span, _: NodeId) {
+        match kind {
+            FnKind::ItemFn(ident, _, _, body) | FnKind::Method(ident, _, _, body) => {
+                self.metrics.push(FnMetrics::new(ident, span, body));
+            }
+            FnKind::Closure(_) => {}
+        }
+        visit::walk_fn(self, kind, decl, span)
+    }
+
+    fn visit_mac(&mut self, _mac: &'ast Mac) {}
+}
+
+struct BodyMetrics {
+    metrics: FnMetrics,
+    nesting: usize,
+}
+
+impl BodyMetrics {
+    fn nested(&mut self, f: impl FnOnce(&mut Self)) {
+        self.nesting += 1;
+        self.metrics.max_nesting = self.metrics.max_nesting.max(self.nesting);
+        f(self);
+        self.nesting -= 1;
+    }
+}
+
+impl<'ast> Visitor<'ast> for BodyMetrics {
+    fn visit_item(&mut self, _item: &'ast Item) {
+        // Nested items are measured on their own.
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        match stmt.node {
+            StmtKind::Item(..) => {}
+            _ => self.metrics.statements += 1,
+        }
+        visit::walk_stmt(self, stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match expr.node {
+            ExprKind::If(ref cond, ref then, ref els) => {
+                self.metrics.cyclomatic += 1;
+                self.nested(|this| {
+                    this.visit_expr(cond);
+                    this.visit_block(then);
+                });
+                // An `else if` is at the same level as the `if` it follows.
+                if let Some(els) = els {
+                    self.visit_expr(els);
+                }
+                return;
+            }
+            ExprKind::While(..) | ExprKind::ForLoop(..) | ExprKind::Try(..) => {
+                self.metrics.cyclomatic += 1;
+            }
+            ExprKind::Binary(op, ..) if op.node.lazy() => self.metrics.cyclomatic += 1,
+            ExprKind::Match(_, ref arms) => {
+                self.metrics.match_arms += arms.len();
+                self.metrics.cyclomatic += arms.len().saturating_sub(1);
+            }
+            _ => {}
+        }
+
+        match expr.node {
+            ExprKind::While(..) | ExprKind::ForLoop(..) | ExprKind::Loop(..) |
+            ExprKind::Match(..) | ExprKind::Block(..) | ExprKind::Async(..) |
+            ExprKind::TryBlock(..) => {
+                self.nested(|this| visit::walk_expr(this, expr))
+            }
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+
+    fn visit_mac(&mut self, _mac: &'ast Mac) {}
+}
@@ -0,0 +1,64 @@
+use super::*;
+
+use crate::tests::string_to_crate;
+use crate::with_default_globals;
+
+fn metrics(src: &str) -> Vec<(String, usize, usize, usize, usize)> {
+    let krate = string_to_crate(src.to_string());
+    crate_fn_metrics(&krate).into_iter().map(|metrics| {
+        (
+            metrics.ident.to_string(),
+            metrics.statements,
+            metrics.max_nesting,
+            metrics.match_arms,
+            metrics.cyclomatic,
+        )
+    }).collect()
+}
+
+#[test]
+fn straight_line_function() {
+    with_default_globals(|| {
+        assert_eq!(metrics("fn f() { let x = 1; g(x); }"), [("f".to_string(), 2, 0, 0, 1)]);
+    })
+}
+
+#[test]
+fn branches_and_nesting() {
+    with_default_globals(|| {
+        let src = "fn f(x: Option<u8>) -> Result<u8, ()> {
+            if a && b {
+                for i in 0..3 {
+                    g(i);
+                }
+            } else if c {
+                h();
+            } else {
+                match x {
+                    Some(0) => {}
+                    Some(_) => {}
+                    None => {}
+                }
+            }
+            Ok(k()?)
+        }";
+        // `if`, `&&`, `for`, `else if`, two more `match` arms and `?`.
+        assert_eq!(metrics(src), [("f".to_string(), 6, 3, 3, 8)]);
+    })
+}
+
+#[test]
+fn nested_items_are_measured_apart() {
+    with_default_globals(|| {
+        let src = "impl S {
+            fn f(&self) {
+                fn g() { if a { b(); } }
+                let c = || { if d { e(); } };
+            }
+        }";
+        assert_eq!(metrics(src), [
+            ("f".to_string(), 3, 2, 0, 2),
+            ("g".to_string(), 2, 1, 0, 2),
+        ]);
+    })
+}
@@ -402,3 +402,18 @@ pub fn contains_exterior_struct_lit(value: &ast::Expr) -> bool {
         _ => false,
     }
 }
+
+/// If `arg` is an argument of a call that looks like a named argument, e.g. `x = 1`, returns
+/// the name. Rust has no named arguments, so this is an assignment, of type `()`.
+pub fn named_argument(arg: &ast::Expr) -> Option<ast::Ident> {
+    match arg.node {
+        ast::ExprKind::Assign(ref lhs, _) => match lhs.node {
+            ast::ExprKind::Path(None, ref path) if path.segments.len() == 1 => {
+                let segment = &path.segments[0];
+                if segment.args.is_none() { Some(segment.ident) } else { None }
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
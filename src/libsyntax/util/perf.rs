@@ -0,0 +1,288 @@
+//! Synthetic inputs that stress the front end, and a driver that times lexing, parsing,
+//! pretty-printing and expanding them.
+//!
+//! The `syntaxbenches` benchmarks are built on these, and tools that track the performance of
+//! the front end over time can call `run` directly. All of it has to be run inside
+//! `with_default_globals` (or an equivalent), like any other parsing.
+
+use crate::ast::{self, NodeId};
+use crate::edition::Edition;
+use crate::ext::base::{ExtCtxt, Indeterminate, InvocationRes, Resolver, SpecialDerives};
+use crate::ext::base::{StalledInvocation, SyntaxExtension, SyntaxExtensionKind};
+use crate::ext::expand::{AstFragment, AstFragmentKind, ExpansionConfig, Invocation};
+use crate::ext::hygiene::{AstPass, ExpnId};
+use crate::ext::tt::macro_rules;
+use crate::feature_gate::Features;
+use crate::parse::{self, ParseSess};
+use crate::print::pprust::{self, PrintConfig};
+use crate::source_map::FilePathMapping;
+use crate::symbol::Symbol;
+use crate::visit::{self, Visitor};
+
+use rustc_data_structures::fx::FxHashMap;
+use syntax_pos::{FileName, Span};
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// A named source file to benchmark the front end on.
+#[derive(Clone, Debug)]
+pub struct Corpus {
+    pub name: &'static str,
+    pub source: String,
+}
+
+/// `depth` nested parentheses around a literal, in a function body.
+pub fn nested_parens(depth: usize) -> Corpus {
+    let expr = format!("{}0{}", "(".repeat(depth), ")".repeat(depth));
+    Corpus { name: "nested_parens", source: format!("fn f() -> u32 {{ {} }}\n", expr) }
+}
+
+/// A chain of `len` binary operations of mixed precedence.
+pub fn binary_chain(len: usize) -> Corpus {
+    let ops = ["+", "*", "-", "<<", "&", "/"];
+    let expr = (0..len).fold(String::from("a"), |mut expr, i| {
+        expr.push_str(&format!(" {} a{}", ops[i % ops.len()], i));
+        expr
+    });
+    Corpus { name: "binary_chain", source: format!("fn f() -> u32 {{ {} }}\n", expr) }
+}
+
+/// A `match` with `arms` arms, each with a pattern, a guard and a block.
+pub fn huge_match(arms: usize) -> Corpus {
+    let arms = (0..arms).map(|i| format!(
+        "        Some(({0}, x)) if x > {0} => {{ let y = x * {0}; y + {0} }}\n",
+        i,
+    )).collect::<String>();
+    Corpus {
+        name: "huge_match",
+        source: format!("fn f(v: Option<(u32, u32)>) -> u32 {{\n    match v {{\n{}        \
+                         _ => 0,\n    }}\n}}\n", arms),
+    }
+}
+
+/// `macros` macro definitions with several rules, each invoked a few times, so that most of
+/// the file ends up as token trees.
+pub fn macro_heavy(macros: usize) -> Corpus {
+    let source = (0..macros).map(|i| format!(
+        "macro_rules! m{0} {{\n    \
+             () => {{}};\n    \
+             ($e:expr) => {{ $e + {0} }};\n    \
+             ($($k:ident => $v:expr),* $(,)?) => {{ [$(($k, $v)),*] }};\n\
+         }}\n\
+         fn f{0}() {{\n    \
+             m{0}!();\n    \
+             let _ = m{0}!(1 + 2 * {0});\n    \
+             let _ = m{0}!(a => 1, b => {{ {0} }}, c => [1, 2, 3],);\n\
+         }}\n",
+        i,
+    )).collect();
+    Corpus { name: "macro_heavy", source }
+}
+
+/// The parts of the front end that `run` measures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Turning the source into a token stream.
+    Lex,
+    /// Turning the source into an AST, including lexing.
+    Parse,
+    /// Printing the AST back out.
+    Print,
+    /// Expanding the invocations of the `macro_rules!` macros defined in the source, without
+    /// parsing the source or compiling the macros.
+    Expand,
+}
+
+/// The fastest of the timings of `phase` on `corpus`.
+#[derive(Clone, Debug)]
+pub struct Measurement {
+    pub corpus: &'static str,
+    pub phase: Phase,
+    pub duration: Duration,
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<16}{:<8}{:?}", self.corpus, format!("{:?}", self.phase), self.duration)
+    }
+}
+
+/// Lexes, parses, prints and expands each of `corpora` `iterations` times, and returns the
+/// fastest time of each phase on each corpus. Corpora without macro invocations aren't
+/// expanded. Panics if a corpus doesn't parse.
+pub fn run(corpora: &[Corpus], iterations: usize) -> Vec<Measurement> {
+    let mut measurements = Vec::new();
+    for corpus in corpora {
+        let mut measure = |phase, f: &mut dyn FnMut()| {
+            let duration = (0..iterations.max(1)).map(|_| {
+                let start = Instant::now();
+                f();
+                start.elapsed()
+            }).min().unwrap();
+            measurements.push(Measurement { corpus: corpus.name, phase, duration });
+        };
+
+        let name = || FileName::Custom(corpus.name.to_string());
+        measure(Phase::Lex, &mut || {
+            let sess = ParseSess::new(FilePathMapping::empty());
+            parse::parse_stream_from_source_str(name(), corpus.source.clone(), &sess, None);
+        });
+        measure(Phase::Parse, &mut || {
+            let sess = ParseSess::new(FilePathMapping::empty());
+            parse_corpus(corpus, &sess);
+        });
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let krate = parse_corpus(corpus, &sess);
+        measure(Phase::Print, &mut || {
+            pprust::crate_to_string_with_config(&krate, PrintConfig::default());
+        });
+        let macros = Macros::collect(&sess, &krate);
+        if !macros.invocations.is_empty() {
+            measure(Phase::Expand, &mut || macros.expand());
+        }
+    }
+    measurements
+}
+
+fn parse_corpus(corpus: &Corpus, sess: &ParseSess) -> crate::ast::Crate {
+    let name = FileName::Custom(corpus.name.to_string());
+    match parse::parse_crate_from_source_str(name, corpus.source.clone(), sess) {
+        Ok(krate) => krate,
+        Err(mut err) => {
+            err.emit();
+            panic!("failed to parse the `{}` corpus", corpus.name)
+        }
+    }
+}
+
+/// The `macro_rules!` macros defined in a crate, and the invocations of macros in it, to time
+/// expanding them.
+pub struct Macros<'a> {
+    sess: &'a ParseSess,
+    definitions: FxHashMap<Symbol, SyntaxExtension>,
+    invocations: Vec<(ast::Mac, AstFragmentKind)>,
+}
+
+impl<'a> Macros<'a> {
+    pub fn collect(sess: &'a ParseSess, krate: &ast::Crate) -> Macros<'a> {
+        let mut macros = Macros { sess, definitions: Default::default(), invocations: Vec::new() };
+        visit::walk_crate(&mut macros, krate);
+        macros
+    }
+
+    /// Expands each invocation of a macro defined in the crate once. Invocations in item and
+    /// statement position are expanded as such, all others as expressions.
+    pub fn expand(&self) {
+        let mut resolver = NoResolver;
+        let ecfg = ExpansionConfig::default("perf".to_string());
+        let mut cx = ExtCtxt::new(self.sess, ecfg, &mut resolver);
+        for (mac, kind) in &self.invocations {
+            let name = mac.path.segments.last().map(|segment| segment.ident.name);
+            let expander = match name.and_then(|name| self.definitions.get(&name)) {
+                Some(SyntaxExtension { kind: SyntaxExtensionKind::LegacyBang(expander), .. }) => {
+                    expander
+                }
+                _ => continue,
+            };
+            let result = expander.expand(&mut cx, mac.span, mac.stream());
+            match kind {
+                AstFragmentKind::Items => drop(result.make_items()),
+                AstFragmentKind::Stmts => drop(result.make_stmts()),
+                _ => drop(result.make_expr()),
+            }
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for Macros<'_> {
+    fn visit_item(&mut self, item: &'ast ast::Item) {
+        match item.node {
+            ast::ItemKind::MacroDef(_) => {
+                let features = Features::new();
+                let ext = macro_rules::compile(self.sess, &features, item, Edition::Edition2018);
+                self.definitions.insert(item.ident.name, ext);
+            }
+            ast::ItemKind::Mac(ref mac) => {
+                self.invocations.push((mac.clone(), AstFragmentKind::Items));
+                return;
+            }
+            _ => {}
+        }
+        visit::walk_item(self, item);
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast ast::Stmt) {
+        match stmt.node {
+            ast::StmtKind::Mac(ref mac) => {
+                self.invocations.push((mac.0.clone(), AstFragmentKind::Stmts));
+            }
+            _ => visit::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_mac(&mut self, mac: &'ast ast::Mac) {
+        self.invocations.push((mac.clone(), AstFragmentKind::Expr));
+    }
+}
+
+/// Expanding `macro_rules!` macros by calling their expanders directly doesn't involve name
+/// resolution, so none of these is ever called.
+struct NoResolver;
+
+impl Resolver for NoResolver {
+    fn next_node_id(&mut self) -> NodeId {
+        unreachable!()
+    }
+
+    fn resolve_dollar_crates(&mut self) {
+        unreachable!()
+    }
+
+    fn visit_ast_fragment_with_placeholders(&mut self, _: ExpnId, _: &AstFragment, _: &[NodeId]) {
+        unreachable!()
+    }
+
+    fn register_builtin_macro(&mut self, _: ast::Ident, _: SyntaxExtension) {
+        unreachable!()
+    }
+
+    fn expansion_for_ast_pass(
+        &mut self,
+        _: Span,
+        _: AstPass,
+        _: &[Symbol],
+        _: Option<NodeId>,
+    ) -> ExpnId {
+        unreachable!()
+    }
+
+    fn resolve_imports(&mut self) {
+        unreachable!()
+    }
+
+    fn resolve_macro_invocation(
+        &mut self,
+        _: &Invocation,
+        _: ExpnId,
+        _: bool,
+    ) -> Result<InvocationRes, Indeterminate> {
+        unreachable!()
+    }
+
+    fn check_unused_macros(&self) {
+        unreachable!()
+    }
+
+    fn has_derives(&self, _: ExpnId, _: SpecialDerives) -> bool {
+        unreachable!()
+    }
+
+    fn add_derives(&mut self, _: ExpnId, _: SpecialDerives) {
+        unreachable!()
+    }
+
+    fn note_stalled_invocations(&mut self, _: &[StalledInvocation], _: &str) {
+        unreachable!()
+    }
+}
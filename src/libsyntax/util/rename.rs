@@ -0,0 +1,78 @@
+//! Finding every occurrence of a name that has to change when a definition is renamed,
+//! for fix-its that rename at the definition rather than at a single use.
+//!
+//! This works on identifiers alone: occurrences are the identifiers with the same name
+//! *and* the same syntax context as the definition, so that identical names introduced
+//! by macro expansions are left alone. It does not consult name resolution, so an
+//! unrelated binding of the same name in the same context is reported as well; callers
+//! that need precision should filter the result with their own resolution results.
+
+use crate::ast;
+use crate::visit::{self, Visitor};
+
+use syntax_pos::{Span, Symbol};
+use syntax_pos::symbol::Ident;
+
+#[cfg(test)]
+mod tests;
+
+/// Returns the replacements renaming the identifier defined at `def_span` to `new_name`
+/// throughout the expanded crate, sorted by position and suitable for
+/// `DiagnosticBuilder::multipart_suggestion`.
+///
+/// Returns `None` if no identifier in `krate` has exactly the span `def_span`.
+pub fn rename_occurrences(
+    krate: &ast::Crate,
+    def_span: Span,
+    new_name: Symbol,
+) -> Option<Vec<(Span, String)>> {
+    let mut finder = DefinitionFinder { span: def_span, found: None };
+    visit::walk_crate(&mut finder, krate);
+    let def_ident = finder.found?;
+
+    let mut collector = OccurrenceCollector { ident: def_ident, spans: Vec::new() };
+    visit::walk_crate(&mut collector, krate);
+
+    // Expansions such as `#[derive]` copy identifiers from their input, so the same
+    // source span can be reached more than once.
+    let mut spans = collector.spans;
+    spans.sort_by_key(|span| (span.lo(), span.hi()));
+    spans.dedup_by_key(|span| (span.lo(), span.hi()));
+    Some(spans.into_iter().map(|span| (span, new_name.to_string())).collect())
+}
+
+struct DefinitionFinder {
+    span: Span,
+    found: Option<Ident>,
+}
+
+impl<'ast> Visitor<'ast> for DefinitionFinder {
+    fn visit_ident(&mut self, ident: Ident) {
+        if self.found.is_none() && ident.span == self.span {
+            self.found = Some(ident);
+        }
+    }
+
+    fn visit_mac(&mut self, _mac: &'ast ast::Mac) {
+        // Unexpanded macro invocations only contain tokens.
+    }
+}
+
+struct OccurrenceCollector {
+    ident: Ident,
+    spans: Vec<Span>,
+}
+
+impl<'ast> Visitor<'ast> for OccurrenceCollector {
+    fn visit_ident(&mut self, ident: Ident) {
+        // `Ident` equality compares syntax contexts, which is what keeps identifiers
+        // introduced by hygienic expansions out of the result.
+        if ident == self.ident {
+            self.spans.push(ident.span);
+        }
+    }
+
+    fn visit_mac(&mut self, _mac: &'ast ast::Mac) {
+        // Unexpanded macro invocations only contain tokens.
+    }
+}
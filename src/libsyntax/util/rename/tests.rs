@@ -0,0 +1,94 @@
+use super::*;
+
+use crate::mut_visit::{self, MutVisitor};
+use crate::parse::{self, ParseSess};
+use crate::source_map::FilePathMapping;
+use crate::with_default_globals;
+
+use syntax_pos::FileName;
+use syntax_pos::hygiene::{ExpnId, Transparency};
+
+fn parse(sess: &ParseSess, src: &str) -> ast::Crate {
+    let name = FileName::Custom("rename".to_string());
+    parse::parse_crate_from_source_str(name, src.to_string(), sess).unwrap()
+}
+
+/// Byte ranges of the spans, relative to the start of the single source file.
+fn offsets(sess: &ParseSess, replacements: &[(Span, String)]) -> Vec<(usize, usize)> {
+    replacements.iter().map(|(span, _)| {
+        let lo = sess.source_map().lookup_byte_offset(span.lo()).pos.0 as usize;
+        let hi = sess.source_map().lookup_byte_offset(span.hi()).pos.0 as usize;
+        (lo, hi)
+    }).collect()
+}
+
+/// Marks the identifiers with the given name, as if they had been produced by a hygienic macro.
+struct MarkInBody(Symbol, ExpnId);
+
+impl MutVisitor for MarkInBody {
+    fn visit_ident(&mut self, ident: &mut Ident) {
+        if ident.name == self.0 {
+            ident.span = ident.span.apply_mark(self.1, Transparency::Opaque);
+        }
+    }
+
+    fn visit_mac(&mut self, mac: &mut ast::Mac) {
+        mut_visit::noop_visit_mac(mac, self)
+    }
+}
+
+#[test]
+fn renames_all_occurrences() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let src = "struct Foo { x: u8 } fn f(foo: Foo) -> Foo { Foo { x: foo.x } }";
+        let krate = parse(&sess, src);
+
+        let def_span = krate.module.items[0].ident.span;
+        let replacements = rename_occurrences(&krate, def_span, Symbol::intern("Bar")).unwrap();
+        let expected: Vec<_> = src.match_indices("Foo").map(|(i, _)| (i, i + 3)).collect();
+        assert_eq!(offsets(&sess, &replacements), expected);
+        assert!(replacements.iter().all(|(_, name)| name == "Bar"));
+    })
+}
+
+#[test]
+fn renames_fields() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let src = "struct S { x: u8 } fn f(s: S) -> u8 { let S { x } = s; x + s.x }";
+        let krate = parse(&sess, src);
+
+        let def_span = match krate.module.items[0].node {
+            ast::ItemKind::Struct(ref data, _) => data.fields()[0].ident.unwrap().span,
+            _ => panic!("expected a struct"),
+        };
+        let replacements = rename_occurrences(&krate, def_span, Symbol::intern("y")).unwrap();
+        let expected: Vec<_> = src.match_indices('x').map(|(i, _)| (i, i + 1)).collect();
+        assert_eq!(offsets(&sess, &replacements), expected);
+    })
+}
+
+#[test]
+fn skips_hygienic_occurrences() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let src = "fn x() {} fn f() { let x = 1; x; }";
+        let mut krate = parse(&sess, src);
+        let mut marker = MarkInBody(Symbol::intern("x"), ExpnId::fresh(None));
+        krate.module.items[1] = marker.flat_map_item(krate.module.items[1].clone()).pop().unwrap();
+
+        let def_span = krate.module.items[0].ident.span;
+        let replacements = rename_occurrences(&krate, def_span, Symbol::intern("y")).unwrap();
+        assert_eq!(offsets(&sess, &replacements), vec![(3, 4)]);
+    })
+}
+
+#[test]
+fn unknown_definition() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let krate = parse(&sess, "fn f() {}");
+        assert!(rename_occurrences(&krate, krate.span, Symbol::intern("g")).is_none());
+    })
+}
@@ -0,0 +1,64 @@
+//! Deterministic `NodeId`s and file-relative span positions for tools comparing AST dumps
+//! across runs or machines, such as golden-file tests and distributed build caches.
+//!
+//! Renumbering is only meant for crates that are not resolved afterwards, since the resolver
+//! keeps tables keyed by the `NodeId`s assigned during expansion.
+
+use crate::ast::{self, NodeId, CRATE_NODE_ID};
+use crate::mut_visit::{self, MutVisitor};
+use crate::source_map::SourceMap;
+
+use rustc_data_structures::sync::Lrc;
+use syntax_pos::{FileName, Span};
+
+#[cfg(test)]
+mod tests;
+
+/// Assigns `NodeId`s to all nodes of `krate` in pre-order, starting right after
+/// `CRATE_NODE_ID`, so that the numbering only depends on the shape of the AST.
+pub fn renumber_node_ids(krate: &mut ast::Crate) {
+    PreOrderNumbering { next: CRATE_NODE_ID.as_u32() + 1 }.visit_crate(krate);
+}
+
+struct PreOrderNumbering {
+    next: u32,
+}
+
+impl MutVisitor for PreOrderNumbering {
+    fn visit_id(&mut self, id: &mut NodeId) {
+        *id = NodeId::from_u32(self.next);
+        self.next += 1;
+    }
+
+    fn visit_mac(&mut self, mac: &mut ast::Mac) {
+        mut_visit::noop_visit_mac(mac, self)
+    }
+}
+
+/// A span expressed as byte offsets into its source file, independent of the position the
+/// file was given in the `SourceMap`, which depends on the order files were loaded in.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, RustcEncodable, RustcDecodable)]
+pub struct FileRelativeSpan {
+    pub file: FileName,
+    pub lo: u32,
+    pub hi: u32,
+}
+
+impl FileRelativeSpan {
+    /// Returns `None` for dummy spans and for spans that do not lie within a single file.
+    pub fn new(source_map: &SourceMap, span: Span) -> Option<FileRelativeSpan> {
+        if span.is_dummy() {
+            return None;
+        }
+        let lo = source_map.lookup_byte_offset(span.lo());
+        let hi = source_map.lookup_byte_offset(span.hi());
+        if !Lrc::ptr_eq(&lo.sf, &hi.sf) {
+            return None;
+        }
+        Some(FileRelativeSpan {
+            file: lo.sf.name.clone(),
+            lo: lo.pos.0,
+            hi: hi.pos.0,
+        })
+    }
+}
@@ -0,0 +1,60 @@
+use super::*;
+
+use crate::ast::ItemKind;
+use crate::parse::{self, ParseSess};
+use crate::source_map::FilePathMapping;
+use crate::tests::string_to_crate;
+use crate::with_default_globals;
+
+#[test]
+fn renumbering_is_pre_order() {
+    with_default_globals(|| {
+        let mut krate = string_to_crate("struct A; fn f() { let x = 1; }".to_string());
+        renumber_node_ids(&mut krate);
+
+        let ids: Vec<_> = krate.module.items.iter().map(|item| item.id.as_u32()).collect();
+        assert_eq!(ids[0], 1);
+        assert!(ids[0] < ids[1]);
+        match krate.module.items[1].node {
+            ItemKind::Fn(_, _, _, ref body) => assert!(body.id.as_u32() > ids[1]),
+            _ => panic!("expected a function"),
+        }
+    })
+}
+
+#[test]
+fn renumbering_is_stable() {
+    with_default_globals(|| {
+        let src = "mod m { pub fn g() {} } fn f() { m::g() }";
+        let mut first = string_to_crate(src.to_string());
+        let mut second = string_to_crate(src.to_string());
+        renumber_node_ids(&mut first);
+        renumber_node_ids(&mut second);
+
+        let first: Vec<_> = first.module.items.iter().map(|item| item.id).collect();
+        let second: Vec<_> = second.module.items.iter().map(|item| item.id).collect();
+        assert_eq!(first, second);
+    })
+}
+
+#[test]
+fn file_relative_spans() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let name = |s: &str| FileName::Custom(s.to_string());
+        let first = parse::parse_crate_from_source_str(name("a"), "fn f() {}".to_string(), &sess)
+            .unwrap();
+        let second = parse::parse_crate_from_source_str(name("b"), "fn f() {}".to_string(), &sess)
+            .unwrap();
+
+        let span_a = FileRelativeSpan::new(sess.source_map(), first.module.items[0].span).unwrap();
+        let span_b = FileRelativeSpan::new(sess.source_map(), second.module.items[0].span).unwrap();
+        assert_eq!(span_a.file, name("a"));
+        assert_eq!(span_b.file, name("b"));
+        assert_eq!((span_a.lo, span_a.hi), (0, 9));
+        assert_eq!((span_a.lo, span_a.hi), (span_b.lo, span_b.hi));
+        assert!(FileRelativeSpan::new(sess.source_map(), first.module.items[0].span.to(
+            second.module.items[0].span,
+        )).is_none());
+    })
+}
@@ -0,0 +1,478 @@
+//! Purely syntactic validation of the expanded AST.
+//!
+//! The parser accepts some constructs the language doesn't permit, so that it can keep going
+//! past them, and macros may produce such constructs as well. They are reported here once
+//! expansion is complete, with a suggestion when the fix is clear. Checks that need more than
+//! the syntax of a single item live in `rustc_passes::ast_validation`, which runs after these.
+
+use crate::ast::{self, Defaultness, ExprKind, FunctionRetTy, ImplPolarity, ItemKind, NodeId};
+use crate::ast::{TyKind, Visibility, VisibilityKind};
+use crate::feature_gate::Features;
+use crate::parse::ParseSess;
+use crate::print::pprust;
+use crate::struct_span_err;
+use crate::symbol::Symbol;
+use crate::visit::{self, Visitor};
+
+use errors::{Applicability, DiagnosticId};
+use syntax_pos::{BytePos, Span};
+
+use std::{cmp, mem};
+
+/// Validates all items in `krate`.
+pub fn check_crate(sess: &ParseSess, features: &Features, krate: &ast::Crate) {
+    visit::walk_crate(&mut ItemValidator { sess }, krate);
+    visit::walk_crate(&mut ImplTraitValidator::new(sess, features), krate);
+}
+
+/// Validates `item`, not including the items nested in it.
+fn check_item(sess: &ParseSess, item: &ast::Item) {
+    let diag = &sess.span_diagnostic;
+    match item.node {
+        ItemKind::Impl(_, _, _, _, Some(..), ref ty, ref impl_items) => {
+            invalid_visibility(sess, &item.vis, item.span, None);
+            if let TyKind::Err = ty.node {
+                diag.struct_span_err(item.span, "`impl Trait for .. {}` is an obsolete syntax")
+                    .help("use `auto trait Trait {}` instead").emit();
+            }
+            for impl_item in impl_items {
+                invalid_visibility(sess, &impl_item.vis, impl_item.span, None);
+            }
+        }
+        ItemKind::Impl(_, polarity, defaultness, _, None, _, _) => {
+            invalid_visibility(sess, &item.vis, item.span,
+                               Some("place qualifiers on individual impl items instead"));
+            if polarity == ImplPolarity::Negative {
+                diag.span_err(item.span, "inherent impls cannot be negative");
+            }
+            if let Defaultness::Default(default_span) = defaultness {
+                let mut err = diag.struct_span_err(item.span, "inherent impls cannot be default");
+                err.note("only trait implementations may be annotated with default");
+                err.tool_only_span_suggestion(
+                    removal_span(sess, default_span, item.span),
+                    "remove `default`",
+                    String::new(),
+                    Applicability::MachineApplicable,
+                );
+                err.emit();
+            }
+        }
+        ItemKind::ForeignMod(..) => {
+            invalid_visibility(sess, &item.vis, item.span,
+                               Some("place qualifiers on individual foreign items instead"));
+        }
+        ItemKind::Enum(ref def, _) => {
+            for variant in &def.variants {
+                for field in variant.data.fields() {
+                    invalid_visibility(sess, &field.vis, field.span, None);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Reports `vis` if it is explicit; `owner_span` is the span of the item or field it's on.
+fn invalid_visibility(sess: &ParseSess, vis: &Visibility, owner_span: Span, note: Option<&str>) {
+    if let VisibilityKind::Inherited = vis.node {
+        return
+    }
+
+    let mut err = struct_span_err!(sess.span_diagnostic,
+                                   vis.span,
+                                   E0449,
+                                   "unnecessary visibility qualifier");
+    if vis.node.is_pub() {
+        err.span_label(vis.span, "`pub` not permitted here because it's implied");
+    }
+    if let Some(note) = note {
+        err.note(note);
+    }
+    err.tool_only_span_suggestion(
+        removal_span(sess, vis.span, owner_span),
+        "remove the visibility qualifier",
+        String::new(),
+        Applicability::MachineApplicable,
+    );
+    err.emit();
+}
+
+/// Extends `span`, which starts a part of `owner_span`, over the whitespace following it, so
+/// that removing it leaves the rest of the item formatted as it was.
+fn removal_span(sess: &ParseSess, span: Span, owner_span: Span) -> Span {
+    let extended = sess.source_map().span_until_non_whitespace(span.with_hi(owner_span.hi()));
+    if extended.hi() >= span.hi() && extended.hi() < owner_span.hi() {
+        extended
+    } else {
+        span
+    }
+}
+
+struct ItemValidator<'a> {
+    sess: &'a ParseSess,
+}
+
+impl<'a> Visitor<'a> for ItemValidator<'a> {
+    fn visit_item(&mut self, item: &'a ast::Item) {
+        check_item(self.sess, item);
+        visit::walk_item(self, item);
+    }
+
+    fn visit_mac(&mut self, _mac: &'a ast::Mac) {
+        // Macro invocations are expanded before validation, so any left are in dead code
+        // like `macro_rules!` definitions.
+    }
+}
+
+/// Position in which `impl Trait` is never allowed. Lowering turns these `impl Trait` types into
+/// errors without reporting them again.
+#[derive(Clone, Copy, PartialEq)]
+enum ImplTraitPosition {
+    LetBinding,
+    FnPointer,
+    ClosureParam,
+    ClosureReturn,
+}
+
+impl ImplTraitPosition {
+    fn descr(self) -> &'static str {
+        match self {
+            ImplTraitPosition::LetBinding => "the type of `let` bindings",
+            ImplTraitPosition::FnPointer => "function pointer types",
+            ImplTraitPosition::ClosureParam => "closure parameter types",
+            ImplTraitPosition::ClosureReturn => "closure return types",
+        }
+    }
+}
+
+/// The generics of a function, to which a type parameter can be added in place of an
+/// `impl Trait` in a function pointer type of its signature.
+struct FnGenerics {
+    span: Span,
+    has_params: bool,
+    /// The names of its parameters and of those of the enclosing impl or trait.
+    names: Vec<Symbol>,
+}
+
+impl FnGenerics {
+    fn new(generics: &ast::Generics, outer_names: &[Symbol]) -> FnGenerics {
+        let names = generics.params.iter().map(|param| param.ident.name);
+        FnGenerics {
+            span: generics.span,
+            has_params: !generics.params.is_empty(),
+            names: outer_names.iter().cloned().chain(names).collect(),
+        }
+    }
+
+    /// Returns the name of a new type parameter with `bounds`, and where and how to declare
+    /// it.
+    fn add_param(&mut self, bounds: &ast::GenericBounds) -> (Symbol, Span, String) {
+        let name = fresh_name(&["T", "U", "V", "W"], "T", &mut self.names);
+        let bounds = pprust::bounds_to_string(bounds);
+        if self.has_params {
+            // Right before the closing `>`.
+            let hi = self.span.hi() - BytePos(1);
+            (self.span.with_lo(hi).with_hi(hi), format!(", {}: {}", name, bounds))
+        } else {
+            (self.span, format!("<{}: {}>", name, bounds))
+        }
+    }
+}
+
+/// Returns the first of `candidates`, or else `base` followed by a number, that isn't `taken`,
+/// and takes it.
+fn fresh_name(candidates: &[&str], base: &str, taken: &mut Vec<Symbol>) -> Symbol {
+    let name = candidates.iter()
+        .map(|name| name.to_string())
+        .chain((1..).map(|n| format!("{}{}", base, n)))
+        .map(|name| Symbol::intern(&name))
+        .find(|name| !taken.contains(name))
+        .unwrap();
+    taken.push(name);
+    name
+}
+
+/// Returns the name to suggest for a named existential type with `bounds`, like `ImplDebug`
+/// for `impl Debug`.
+fn existential_type_name(bounds: &ast::GenericBounds) -> String {
+    let trait_ref = bounds.iter().filter_map(|bound| match bound {
+        ast::GenericBound::Trait(poly_trait_ref, _) => Some(&poly_trait_ref.trait_ref),
+        ast::GenericBound::Outlives(_) => None,
+    }).next();
+    match trait_ref.and_then(|trait_ref| trait_ref.path.segments.last()) {
+        Some(segment) => format!("Impl{}", segment.ident),
+        None => "Opaque".to_string(),
+    }
+}
+
+/// Reports `impl Trait` types in positions where they're never allowed: the types of `let`
+/// bindings, function pointer types and the types in closure signatures.
+struct ImplTraitValidator<'a> {
+    sess: &'a ParseSess,
+    features: &'a Features,
+    /// Set while visiting a type in which `impl Trait` isn't allowed.
+    position: Option<ImplTraitPosition>,
+    /// Set while visiting the signature of a function.
+    fn_generics: Option<FnGenerics>,
+    /// The names of the generic parameters of the impl or trait whose items are visited.
+    outer_generic_names: Vec<Symbol>,
+    /// Where to declare a named existential type, right before the item of the current module
+    /// that is visited, and the indentation to follow the declaration with.
+    existential_type_site: Option<(Span, String)>,
+    /// The names of the items in the current module, and of the existential types suggested
+    /// for it.
+    module_names: Vec<Symbol>,
+}
+
+impl<'a> ImplTraitValidator<'a> {
+    fn new(sess: &'a ParseSess, features: &'a Features) -> Self {
+        ImplTraitValidator {
+            sess,
+            features,
+            position: None,
+            fn_generics: None,
+            outer_generic_names: Vec::new(),
+            existential_type_site: None,
+            module_names: Vec::new(),
+        }
+    }
+
+    fn with_position(&mut self, position: Option<ImplTraitPosition>, f: impl FnOnce(&mut Self)) {
+        let old_position = mem::replace(&mut self.position, position);
+        f(self);
+        self.position = old_position;
+    }
+
+    fn with_fn_generics(&mut self, fn_generics: Option<FnGenerics>, f: impl FnOnce(&mut Self)) {
+        let old_fn_generics = mem::replace(&mut self.fn_generics, fn_generics);
+        f(self);
+        self.fn_generics = old_fn_generics;
+    }
+
+    /// Visits `decl`, whose parameter and return types are in `params` and `output`.
+    fn visit_fn_decl(
+        &mut self,
+        decl: &'a ast::FnDecl,
+        params: Option<ImplTraitPosition>,
+        output: Option<ImplTraitPosition>,
+    ) {
+        for param in &decl.inputs {
+            self.visit_pat(&param.pat);
+            self.with_position(params, |this| this.visit_ty(&param.ty));
+        }
+        if let FunctionRetTy::Ty(ref ty) = decl.output {
+            self.with_position(output, |this| this.visit_ty(ty));
+        }
+    }
+
+    /// Returns where to declare an item right before `item`, and the indentation to follow the
+    /// declaration with.
+    fn site_before(&self, item: &ast::Item) -> Option<(Span, String)> {
+        if item.span.from_expansion() {
+            return None;
+        }
+        let lo = item.attrs.iter()
+            .filter(|attr| attr.style == ast::AttrStyle::Outer)
+            .map(|attr| attr.span.lo())
+            .fold(item.span.lo(), cmp::min);
+        let site = item.span.with_lo(lo).shrink_to_lo();
+        let source = self.sess.source_map().span_to_prev_source(site).ok()?;
+        let line = source.rsplit('\n').next().unwrap_or("");
+        Some((site, line[..line.len() - line.trim_start().len()].to_string()))
+    }
+
+    fn report(&mut self, ty: &ast::Ty, bounds: &ast::GenericBounds, position: ImplTraitPosition) {
+        let mut err = self.sess.span_diagnostic.struct_span_err_with_code(
+            ty.span,
+            &format!("`impl Trait` is not allowed in {}", position.descr()),
+            DiagnosticId::Error("E0562".to_owned()),
+        );
+        let is_nightly_build = self.sess.unstable_features.is_nightly_build();
+        if position == ImplTraitPosition::LetBinding && is_nightly_build {
+            err.help("add `#![feature(impl_trait_in_bindings)]` to the crate attributes to enable");
+        }
+        // A nested `impl Trait` can be neither the bound of a parameter nor an existential type.
+        if contains_impl_trait(bounds) {
+            err.emit();
+            return;
+        }
+        match (position, &mut self.fn_generics) {
+            (ImplTraitPosition::FnPointer, Some(fn_generics)) => {
+                let (name, decl_span, decl) = fn_generics.add_param(bounds);
+                err.multipart_suggestion(
+                    "introduce a generic parameter",
+                    vec![(decl_span, decl), (ty.span, name.to_string())],
+                    Applicability::MaybeIncorrect,
+                );
+            }
+            _ => {}
+        }
+        // Existential types are unstable.
+        match &self.existential_type_site {
+            Some((site, indent)) if is_nightly_build => {
+                let base = existential_type_name(bounds);
+                let name = fresh_name(&[base.as_str()], &base, &mut self.module_names);
+                let decl = format!("type {} = {};\n{}", name, pprust::ty_to_string(ty), indent);
+                err.multipart_suggestion(
+                    "introduce a named existential type",
+                    vec![(*site, decl), (ty.span, name.to_string())],
+                    Applicability::MaybeIncorrect,
+                );
+            }
+            _ => {}
+        }
+        err.emit();
+    }
+}
+
+impl<'a> Visitor<'a> for ImplTraitValidator<'a> {
+    fn visit_mod(&mut self, module: &'a ast::Mod, _: Span, _: &[ast::Attribute], _: NodeId) {
+        let names = module.items.iter().map(|item| item.ident.name).collect();
+        let old_module_names = mem::replace(&mut self.module_names, names);
+        let old_site = self.existential_type_site.take();
+        for item in &module.items {
+            self.existential_type_site = self.site_before(item);
+            self.visit_item(item);
+        }
+        self.existential_type_site = old_site;
+        self.module_names = old_module_names;
+    }
+
+    fn visit_item(&mut self, item: &'a ast::Item) {
+        let outer_generic_names = match item.node {
+            ItemKind::Impl(_, _, _, ref generics, ..) | ItemKind::Trait(_, _, ref generics, ..) => {
+                generics.params.iter().map(|param| param.ident.name).collect()
+            }
+            _ => Vec::new(),
+        };
+        let fn_generics = match item.node {
+            ItemKind::Fn(_, _, ref generics, _) => Some(FnGenerics::new(generics, &[])),
+            _ => None,
+        };
+        let old_outer_generic_names =
+            mem::replace(&mut self.outer_generic_names, outer_generic_names);
+        self.with_fn_generics(fn_generics, |this| visit::walk_item(this, item));
+        self.outer_generic_names = old_outer_generic_names;
+    }
+
+    fn visit_trait_item(&mut self, item: &'a ast::TraitItem) {
+        let fn_generics = match item.node {
+            ast::TraitItemKind::Method(..) => {
+                Some(FnGenerics::new(&item.generics, &self.outer_generic_names))
+            }
+            _ => None,
+        };
+        self.with_fn_generics(fn_generics, |this| visit::walk_trait_item(this, item));
+    }
+
+    fn visit_impl_item(&mut self, item: &'a ast::ImplItem) {
+        let fn_generics = match item.node {
+            ast::ImplItemKind::Method(..) => {
+                Some(FnGenerics::new(&item.generics, &self.outer_generic_names))
+            }
+            _ => None,
+        };
+        self.with_fn_generics(fn_generics, |this| visit::walk_impl_item(this, item));
+    }
+
+    fn visit_block(&mut self, block: &'a ast::Block) {
+        // Function bodies aren't part of their signatures.
+        self.with_fn_generics(None, |this| visit::walk_block(this, block));
+    }
+
+    fn visit_anon_const(&mut self, constant: &'a ast::AnonConst) {
+        self.with_position(None, |this| visit::walk_anon_const(this, constant));
+    }
+
+    fn visit_local(&mut self, local: &'a ast::Local) {
+        let position = if self.features.impl_trait_in_bindings {
+            None
+        } else {
+            Some(ImplTraitPosition::LetBinding)
+        };
+        for attr in local.attrs.iter() {
+            self.visit_attribute(attr);
+        }
+        self.visit_pat(&local.pat);
+        if let Some(ref ty) = local.ty {
+            self.with_position(position, |this| this.visit_ty(ty));
+        }
+        if let Some(ref init) = local.init {
+            self.visit_expr(init);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'a ast::Expr) {
+        match expr.node {
+            ExprKind::Closure(_, asyncness, _, ref decl, ref body, _) => {
+                // The return type of an `async` closure is the output of its future, which
+                // lowering checks.
+                let output = if asyncness.is_async() {
+                    None
+                } else {
+                    Some(ImplTraitPosition::ClosureReturn)
+                };
+                self.visit_fn_decl(decl, Some(ImplTraitPosition::ClosureParam), output);
+                self.visit_expr(body);
+            }
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+
+    fn visit_ty(&mut self, ty: &'a ast::Ty) {
+        match ty.node {
+            TyKind::ImplTrait(_, ref bounds) => match self.position {
+                Some(position) => self.report(ty, bounds, position),
+                None => visit::walk_ty(self, ty),
+            },
+            TyKind::BareFn(ref bare_fn) => {
+                for param in &bare_fn.generic_params {
+                    self.visit_generic_param(param);
+                }
+                let position = Some(ImplTraitPosition::FnPointer);
+                self.visit_fn_decl(&bare_fn.decl, position, position);
+            }
+            _ => visit::walk_ty(self, ty),
+        }
+    }
+
+    fn visit_generic_args(&mut self, path_span: Span, generic_args: &'a ast::GenericArgs) {
+        match *generic_args {
+            // Like the parameters of functions, those of the `Fn` traits are checked by lowering.
+            ast::GenericArgs::Parenthesized(..) => self.with_position(None, |this| {
+                visit::walk_generic_args(this, path_span, generic_args)
+            }),
+            ast::GenericArgs::AngleBracketed(..) => {
+                visit::walk_generic_args(self, path_span, generic_args)
+            }
+        }
+    }
+
+    fn visit_mac(&mut self, _mac: &'a ast::Mac) {
+        // Macro invocations are expanded before validation, so any left are in dead code
+        // like `macro_rules!` definitions.
+    }
+}
+
+fn contains_impl_trait(bounds: &ast::GenericBounds) -> bool {
+    struct ImplTraitFinder {
+        found: bool,
+    }
+
+    impl<'a> Visitor<'a> for ImplTraitFinder {
+        fn visit_ty(&mut self, ty: &'a ast::Ty) {
+            match ty.node {
+                TyKind::ImplTrait(..) => self.found = true,
+                _ => visit::walk_ty(self, ty),
+            }
+        }
+
+        fn visit_mac(&mut self, _mac: &'a ast::Mac) {}
+    }
+
+    let mut finder = ImplTraitFinder { found: false };
+    for bound in bounds {
+        visit::walk_param_bound(&mut finder, bound);
+    }
+    finder.found
+}
@@ -2,7 +2,6 @@
 
 use syntax::ast::{self, AttrStyle};
 use syntax::attr::mk_attr;
-use syntax::panictry;
 use syntax::parse::{self, token, ParseSess};
 use syntax_pos::FileName;
 
@@ -15,7 +14,15 @@ pub fn inject(mut krate: ast::Crate, parse_sess: &ParseSess, attrs: &[String]) -
         );
 
         let start_span = parser.token.span;
-        let (path, tokens) = panictry!(parser.parse_meta_item_unrestricted());
+        let (path, tokens) = match parser.parse_meta_item_unrestricted() {
+            Ok(meta) => meta,
+            Err(mut err) => {
+                err.emit();
+                // In error-as-value mode, the attribute is skipped.
+                let _ = parse_sess.span_diagnostic.fatal_error();
+                continue;
+            }
+        };
         let end_span = parser.token.span;
         if parser.token != token::Eof {
             parse_sess.span_diagnostic
@@ -1,4 +1,4 @@
-use crate::deriving::path_std;
+use crate::deriving::{path_std, report_unsized_field};
 use crate::deriving::generic::*;
 use crate::deriving::generic::ty::*;
 
@@ -11,6 +11,10 @@ pub fn expand_deriving_copy(cx: &mut ExtCtxt<'_>,
                             mitem: &MetaItem,
                             item: &Annotatable,
                             push: &mut dyn FnMut(Annotatable)) {
+    if report_unsized_field(cx, mitem, "Copy", item) {
+        return;
+    }
+
     let trait_def = TraitDef {
         span,
         attributes: Vec::new(),
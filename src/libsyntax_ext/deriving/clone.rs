@@ -1,4 +1,4 @@
-use crate::deriving::path_std;
+use crate::deriving::{path_std, report_unsized_field};
 use crate::deriving::generic::*;
 use crate::deriving::generic::ty::*;
 
@@ -13,6 +13,10 @@ pub fn expand_deriving_clone(cx: &mut ExtCtxt<'_>,
                              mitem: &MetaItem,
                              item: &Annotatable,
                              push: &mut dyn FnMut(Annotatable)) {
+    if report_unsized_field(cx, mitem, "Clone", item) {
+        return;
+    }
+
     // check if we can use a short form
     //
     // the short form is `fn clone(&self) -> Self { *self }`
@@ -1,6 +1,6 @@
 //! The compiler code necessary for `#[derive(RustcDecodable)]`. See encodable.rs for more.
 
-use crate::deriving::pathvec_std;
+use crate::deriving::{pathvec_std, report_unsized_field};
 use crate::deriving::generic::*;
 use crate::deriving::generic::ty::*;
 
@@ -16,6 +16,10 @@ pub fn expand_deriving_rustc_decodable(cx: &mut ExtCtxt<'_>,
                                        mitem: &MetaItem,
                                        item: &Annotatable,
                                        push: &mut dyn FnMut(Annotatable)) {
+    if report_unsized_field(cx, mitem, "RustcDecodable", item) {
+        return;
+    }
+
     let krate = "rustc_serialize";
     let typaram = "__D";
 
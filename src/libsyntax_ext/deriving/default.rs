@@ -1,4 +1,4 @@
-use crate::deriving::path_std;
+use crate::deriving::{path_std, report_unsized_field};
 use crate::deriving::generic::*;
 use crate::deriving::generic::ty::*;
 
@@ -14,6 +14,10 @@ pub fn expand_deriving_default(cx: &mut ExtCtxt<'_>,
                                mitem: &MetaItem,
                                item: &Annotatable,
                                push: &mut dyn FnMut(Annotatable)) {
+    if report_unsized_field(cx, mitem, "Default", item) {
+        return;
+    }
+
     let inline = cx.meta_word(span, sym::inline);
     let attrs = vec![cx.attribute(inline)];
     let trait_def = TraitDef {
@@ -1,5 +1,6 @@
 //! The compiler code necessary to implement the `#[derive]` extensions.
 
+use errors::Applicability;
 use syntax::ast::{self, MetaItem};
 use syntax::ext::base::{Annotatable, ExtCtxt, MultiItemModifier};
 use syntax::ptr::P;
@@ -74,3 +75,53 @@ fn call_intrinsic(cx: &ExtCtxt<'_>,
         span,
     }))
 }
+
+/// Reports the last field of the struct `item` if it is dynamically sized, since the derive
+/// of `trait_name` has to move or create the value of every field. Returns whether an error
+/// was emitted, in which case nothing should be derived.
+///
+/// Only slices and trait objects are recognized here, other unsized types can't be told
+/// apart from sized ones before type checking.
+fn report_unsized_field(cx: &ExtCtxt<'_>,
+                        mitem: &MetaItem,
+                        trait_name: &str,
+                        item: &Annotatable)
+                        -> bool {
+    fn is_unsized(ty: &ast::Ty) -> bool {
+        match ty.node {
+            ast::TyKind::Slice(..) |
+            ast::TyKind::TraitObject(_, ast::TraitObjectSyntax::Dyn) => true,
+            ast::TyKind::Paren(ref ty) => is_unsized(ty),
+            _ => false,
+        }
+    }
+
+    let field = match *item {
+        Annotatable::Item(ref item) => match item.node {
+            ast::ItemKind::Struct(ref struct_def, _) => struct_def.fields().last(),
+            _ => None,
+        },
+        _ => None,
+    };
+    let ty = match field {
+        Some(field) if is_unsized(&field.ty) => &field.ty,
+        _ => return false,
+    };
+
+    let msg = format!("`#[derive({})]` cannot be used on structs with a dynamically sized \
+                       field", trait_name);
+    let mut err = cx.struct_span_err(ty.span, &msg);
+    err.span_label(mitem.span, format!("`{}` derived here", trait_name));
+    err.note(&format!("the derived `{}` impl has to move or create the value of every field",
+                      trait_name));
+    if let Ok(snippet) = cx.source_map().span_to_snippet(ty.span) {
+        err.span_suggestion(
+            ty.span,
+            "consider storing the field behind a pointer",
+            format!("Box<{}>", snippet),
+            Applicability::MaybeIncorrect,
+        );
+    }
+    err.emit();
+    true
+}
@@ -0,0 +1,68 @@
+//! Items registered by tools embedding the compiler through
+//! `ParseSess::register_crate_root_items` are parsed from their own source files
+//! and injected at the crate root, before any of the crate's own items.
+
+use syntax::ast;
+use syntax::ext::base::Resolver;
+use syntax::ext::hygiene::AstPass;
+use syntax::mut_visit::{self, MutVisitor};
+use syntax::parse::{self, token, ParseSess};
+use syntax_pos::{Span, DUMMY_SP};
+use syntax_pos::hygiene::ExpnId;
+
+use std::mem;
+
+#[cfg(test)]
+mod tests;
+
+/// Gives every span a transparent mark of the injection, so the items resolve exactly like
+/// code written at the crate root while still being recognizable as injected.
+struct CallSiteMarker(ExpnId);
+
+impl MutVisitor for CallSiteMarker {
+    fn visit_span(&mut self, span: &mut Span) {
+        *span = span.with_call_site_ctxt(self.0);
+    }
+
+    fn visit_mac(&mut self, mac: &mut ast::Mac) {
+        mut_visit::noop_visit_mac(mac, self)
+    }
+}
+
+pub fn inject(krate: &mut ast::Crate, resolver: &mut dyn Resolver, sess: &ParseSess) {
+    let sources = mem::take(&mut *sess.injected_crate_root_items.lock());
+    if sources.is_empty() {
+        return;
+    }
+
+    let expn_id = resolver.expansion_for_ast_pass(DUMMY_SP, AstPass::EmbedderItems, &[], None);
+    let mut marker = CallSiteMarker(expn_id);
+
+    let mut extra_items = Vec::new();
+    for (name, source) in sources {
+        let mut parser = parse::new_parser_from_source_str(sess, name, source);
+        while parser.token != token::Eof {
+            match parser.parse_item() {
+                Ok(Some(item)) => extra_items.extend(marker.flat_map_item(item)),
+                Err(mut err) => {
+                    err.emit();
+                    // In error-as-value mode, the rest of the source is skipped.
+                    let _ = sess.span_diagnostic.fatal_error();
+                    break;
+                }
+                Ok(None) => {
+                    sess.span_diagnostic.span_err(
+                        parser.token.span,
+                        "expected item in source injected at the crate root",
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    // The injected items must precede the crate's own items, so that `macro_rules!`
+    // definitions among them are in scope for the whole crate.
+    mem::swap(&mut extra_items, &mut krate.module.items);
+    krate.module.items.append(&mut extra_items);
+}
@@ -0,0 +1,71 @@
+use super::*;
+
+use syntax::ext::macro_rules_resolver::MacroRulesResolver;
+use syntax::source_map::FilePathMapping;
+use syntax::with_default_globals;
+use syntax_pos::FileName;
+use syntax_pos::hygiene::ExpnKind;
+
+fn parse_crate(sess: &ParseSess, source: &str) -> ast::Crate {
+    let name = FileName::Custom("main".to_string());
+    parse::parse_crate_from_source_str(name, source.to_string(), sess).unwrap()
+}
+
+#[test]
+fn injected_items_precede_the_crate_items() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let helpers = FileName::Custom("helpers".to_string());
+        sess.register_crate_root_items(
+            helpers.clone(),
+            "macro_rules! m { () => {} }\nfn helper() {}\n".to_string(),
+        );
+        let mut krate = parse_crate(&sess, "fn main() { m!(); helper(); }");
+        let mut resolver = MacroRulesResolver::new(&sess, sess.edition);
+        inject(&mut krate, &mut resolver, &sess);
+
+        let names = krate.module.items.iter().map(|item| item.ident.to_string());
+        assert_eq!(names.collect::<Vec<_>>(), ["m", "helper", "main"]);
+        assert!(sess.injected_crate_root_items.lock().is_empty());
+
+        // The injected items keep pointing into their own source, but are marked as injected.
+        let span = krate.module.items[1].span;
+        let loc = sess.source_map().lookup_char_pos(span.lo());
+        assert_eq!((loc.file.name.clone(), loc.line), (helpers, 2));
+        match span.ctxt().outer_expn_data().kind {
+            ExpnKind::AstPass(pass) => assert_eq!(pass, AstPass::EmbedderItems),
+            kind => panic!("unexpected expansion kind {:?}", kind),
+        }
+        assert!(!krate.module.items[2].span.from_expansion());
+        assert_eq!(sess.span_diagnostic.err_count(), 0);
+    })
+}
+
+#[test]
+fn nothing_is_injected_without_registered_items() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        let mut krate = parse_crate(&sess, "fn main() {}");
+        let mut resolver = MacroRulesResolver::new(&sess, sess.edition);
+        inject(&mut krate, &mut resolver, &sess);
+        assert_eq!(krate.module.items.len(), 1);
+    })
+}
+
+#[test]
+fn injected_sources_must_only_contain_items() {
+    with_default_globals(|| {
+        let sess = ParseSess::new(FilePathMapping::empty());
+        sess.register_crate_root_items(
+            FileName::Custom("helpers".to_string()),
+            "fn helper() {}\nlet x = 1;\nfn unreachable() {}\n".to_string(),
+        );
+        let mut krate = parse_crate(&sess, "fn main() {}");
+        let mut resolver = MacroRulesResolver::new(&sess, sess.edition);
+        inject(&mut krate, &mut resolver, &sess);
+
+        let names = krate.module.items.iter().map(|item| item.ident.to_string());
+        assert_eq!(names.collect::<Vec<_>>(), ["helper", "main"]);
+        assert_eq!(sess.span_diagnostic.err_count(), 1);
+    })
+}
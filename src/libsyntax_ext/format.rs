@@ -13,7 +13,7 @@ use syntax::parse::token;
 use syntax::ptr::P;
 use syntax::symbol::{Symbol, sym};
 use syntax::tokenstream::TokenStream;
-use syntax_pos::{MultiSpan, Span};
+use syntax_pos::{BytePos, MultiSpan, Span};
 
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
 use std::borrow::Cow;
@@ -834,6 +834,121 @@ pub fn expand_format_args_nl<'cx>(
     expand_format_args_impl(ecx, sp, tts, true)
 }
 
+/// A brace in a format string that is not part of an escape or an argument.
+enum UnbalancedBrace {
+    /// A `}` that doesn't close an argument, at the given index.
+    Unmatched(usize),
+    /// A `{` at the given index, followed only by an argument name or position and whitespace up
+    /// to the end of the string.
+    Unclosed(usize),
+}
+
+/// Scans the value of a format string for unbalanced braces, mirroring how `fmt_macros`
+/// delimits arguments: `{{` and `}}` are escapes, and an argument starting with `{` ends
+/// at the next `}` that isn't its fill character.
+///
+/// An opening brace followed by anything else than an argument name, like in `{name`, and
+/// whitespace is left for the format string parser, which can point at what it expected a `}`
+/// instead of.
+fn find_unbalanced_braces(fmt_str: &str) -> Vec<UnbalancedBrace> {
+    let mut unbalanced = vec![];
+    let mut chars = fmt_str.char_indices().peekable();
+    while let Some((pos, c)) = chars.next() {
+        match c {
+            '{' if chars.peek().map(|&(_, c)| c) == Some('{') => {
+                chars.next();
+            }
+            '{' => {
+                let mut closed = false;
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '}' => {
+                            closed = true;
+                            break;
+                        }
+                        ':' => {
+                            // Skip the fill character, which may itself be a brace.
+                            let mut spec = chars.clone();
+                            if let (Some(_), Some((_, '<'))) |
+                                   (Some(_), Some((_, '>'))) |
+                                   (Some(_), Some((_, '^'))) = (spec.next(), spec.next()) {
+                                chars.next();
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                let is_argument = |c: char| c.is_alphanumeric() || c == '_';
+                if !closed && fmt_str[pos + 1..].trim_end().chars().all(is_argument) {
+                    unbalanced.push(UnbalancedBrace::Unclosed(pos));
+                }
+            }
+            '}' if chars.peek().map(|&(_, c)| c) == Some('}') => {
+                chars.next();
+            }
+            '}' => unbalanced.push(UnbalancedBrace::Unmatched(pos)),
+            _ => {}
+        }
+    }
+    unbalanced
+}
+
+/// Reports every unbalanced brace of the format string literal at `fmt_span` with a
+/// suggestion to escape it, before the format string is parsed. Returns whether any
+/// errors were emitted.
+///
+/// The format string parser only reports the first error it encounters, and its spans
+/// are computed from an approximation of the escapes in the literal.
+fn report_unbalanced_braces(ecx: &ExtCtxt<'_>, fmt_span: Span, fmt_str: &str) -> bool {
+    let source_map = ecx.source_map();
+    let mut reported = false;
+    for brace in find_unbalanced_braces(fmt_str) {
+        match brace {
+            UnbalancedBrace::Unmatched(pos) => {
+                let sp = match source_map.str_literal_sub_span(fmt_span, pos..pos + 1) {
+                    Some(sp) => sp,
+                    None => return reported,
+                };
+                ecx.struct_span_err(sp, "invalid format string: unmatched `}` found")
+                    .span_label(sp, "unmatched `}` in format string")
+                    .note("if you intended to print `}`, you can escape it using `}}`")
+                    .tool_only_span_suggestion(
+                        sp,
+                        "escape the closing brace",
+                        "}}".to_string(),
+                        Applicability::MachineApplicable,
+                    )
+                    .emit();
+            }
+            UnbalancedBrace::Unclosed(pos) => {
+                let (brace_sp, end_sp) = match (
+                    source_map.str_literal_sub_span(fmt_span, pos..pos + 1),
+                    source_map.str_literal_sub_span(fmt_span, fmt_str.len()..fmt_str.len()),
+                ) {
+                    (Some(brace_sp), Some(end_sp)) => (brace_sp, end_sp),
+                    _ => return reported,
+                };
+                // Point at the closing quote of the literal.
+                let sp = end_sp.with_hi(end_sp.hi() + BytePos(1));
+                let msg = "invalid format string: expected `'}'` but string was terminated";
+                ecx.struct_span_err(sp, msg)
+                    .span_label(sp, "expected `'}'` in format string")
+                    .span_label(brace_sp, "because of this opening brace")
+                    .note("if you intended to print `{`, you can escape it using `{{`")
+                    .tool_only_span_suggestion(
+                        brace_sp,
+                        "escape the opening brace",
+                        "{{".to_string(),
+                        Applicability::MachineApplicable,
+                    )
+                    .emit();
+            }
+        }
+        reported = true;
+    }
+    reported
+}
+
 /// Take the various parts of `format_args!(efmt, args..., name=names...)`
 /// and construct the appropriate formatting expression.
 pub fn expand_preparsed_format_args(
@@ -990,6 +1105,12 @@ pub fn expand_preparsed_format_args(
     };
 
     let fmt_str = &*fmt_str.as_str();  // for the suggestions below
+    if is_literal {
+        let value = if append_newline { &fmt_str[..fmt_str.len() - 1] } else { fmt_str };
+        if report_unbalanced_braces(ecx, fmt_span, value) {
+            return DummyResult::raw_expr(sp, true);
+        }
+    }
     let mut parser = parse::Parser::new(fmt_str, str_style, skips, append_newline);
 
     let mut unverified_pieces = Vec::new();
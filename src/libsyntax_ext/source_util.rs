@@ -1,4 +1,4 @@
-use syntax::{ast, panictry};
+use syntax::ast;
 use syntax::ext::base::{self, *};
 use syntax::parse::{self, token, DirectoryOwnership};
 use syntax::print::pprust;
@@ -80,28 +80,45 @@ pub fn expand_include<'cx>(cx: &'cx mut ExtCtxt<'_>, sp: Span, tts: TokenStream)
 
     struct ExpandResult<'a> {
         p: parse::parser::Parser<'a>,
+        span: Span,
     }
     impl<'a> base::MacResult for ExpandResult<'a> {
         fn make_expr(mut self: Box<ExpandResult<'a>>) -> Option<P<ast::Expr>> {
-            Some(panictry!(self.p.parse_expr()))
+            match self.p.parse_expr() {
+                Ok(expr) => Some(expr),
+                Err(mut err) => {
+                    err.emit();
+                    // In error-as-value mode, the error is propagated as a dummy expression.
+                    let _ = self.p.sess.span_diagnostic.fatal_error();
+                    Some(DummyResult::raw_expr(self.span, true))
+                }
+            }
         }
 
         fn make_items(mut self: Box<ExpandResult<'a>>) -> Option<SmallVec<[P<ast::Item>; 1]>> {
             let mut ret = SmallVec::new();
             while self.p.token != token::Eof {
-                match panictry!(self.p.parse_item()) {
-                    Some(item) => ret.push(item),
-                    None => self.p.sess.span_diagnostic.span_fatal(self.p.token.span,
-                                                           &format!("expected item, found `{}`",
-                                                                    self.p.this_token_to_string()))
-                                               .raise()
-                }
+                let mut err = match self.p.parse_item() {
+                    Ok(Some(item)) => {
+                        ret.push(item);
+                        continue;
+                    }
+                    Ok(None) => {
+                        let token = self.p.this_token_to_string();
+                        self.p.fatal(&format!("expected item, found `{}`", token))
+                    }
+                    Err(err) => err,
+                };
+                err.emit();
+                // In error-as-value mode, the items parsed so far are kept.
+                let _ = self.p.sess.span_diagnostic.fatal_error();
+                break;
             }
             Some(ret)
         }
     }
 
-    Box::new(ExpandResult { p })
+    Box::new(ExpandResult { p, span: sp })
 }
 
 // include_str! : read the given file, insert it as a literal string expr
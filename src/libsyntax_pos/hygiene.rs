@@ -731,6 +731,7 @@ pub enum AstPass {
     TestHarness,
     ProcMacroHarness,
     PluginMacroDefs,
+    EmbedderItems,
 }
 
 impl AstPass {
@@ -740,6 +741,7 @@ impl AstPass {
             AstPass::TestHarness => "test harness",
             AstPass::ProcMacroHarness => "proc macro harness",
             AstPass::PluginMacroDefs => "plugin macro definitions",
+            AstPass::EmbedderItems => "embedder-provided items",
         }
     }
 }
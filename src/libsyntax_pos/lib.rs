@@ -25,13 +25,14 @@ pub use hygiene::{ExpnId, SyntaxContext, ExpnData, ExpnKind, MacroKind, Desugari
 use hygiene::Transparency;
 
 mod span_encoding;
-pub use span_encoding::{Span, DUMMY_SP};
+pub use span_encoding::{Span, SpanInternerStats, DUMMY_SP};
 
 pub mod symbol;
 pub use symbol::{Symbol, sym};
 
 mod analyze_source_file;
 
+use rustc_data_structures::fx::FxHashSet;
 use rustc_data_structures::stable_hasher::StableHasher;
 use rustc_data_structures::sync::{Lrc, Lock};
 
@@ -47,7 +48,7 @@ use std::path::PathBuf;
 mod tests;
 
 pub struct Globals {
-    symbol_interner: Lock<symbol::Interner>,
+    symbol_interner: symbol::Interner,
     span_interner: Lock<span_encoding::SpanInterner>,
     hygiene_data: Lock<hygiene::HygieneData>,
 }
@@ -55,7 +56,7 @@ pub struct Globals {
 impl Globals {
     pub fn new(edition: Edition) -> Globals {
         Globals {
-            symbol_interner: Lock::new(symbol::Interner::fresh()),
+            symbol_interner: symbol::Interner::fresh(),
             span_interner: Lock::new(span_encoding::SpanInterner::default()),
             hygiene_data: Lock::new(hygiene::HygieneData::new(edition)),
         }
@@ -248,10 +249,15 @@ impl Ord for Span {
 ///   the error, and would be rendered with `^^^`.
 /// - They can have a *label*. In this case, the label is written next
 ///   to the mark in the snippet when we render.
+///
+/// When an error is inherently about several equally important locations,
+/// primary spans can also be given a *role*, which is used as their label
+/// and rendered with a marker and color of its own.
 #[derive(Clone, Debug, Hash, PartialEq, Eq, RustcEncodable, RustcDecodable)]
 pub struct MultiSpan {
     primary_spans: Vec<Span>,
     span_labels: Vec<(Span, String)>,
+    primary_roles: Vec<(Span, String)>,
 }
 
 impl Span {
@@ -429,7 +435,17 @@ impl Span {
         self.ctxt().outer_expn_data().allow_internal_unsafe
     }
 
-    pub fn macro_backtrace(mut self) -> Vec<MacroBacktrace> {
+    pub fn macro_backtrace(self) -> Vec<MacroBacktrace> {
+        self.macro_backtrace_frames(true)
+    }
+
+    /// Like `macro_backtrace`, but also keeps the frames of recursive invocations, for
+    /// consumers that fold the backtrace themselves.
+    pub fn full_macro_backtrace(self) -> Vec<MacroBacktrace> {
+        self.macro_backtrace_frames(false)
+    }
+
+    fn macro_backtrace_frames(mut self, skip_recursive: bool) -> Vec<MacroBacktrace> {
         let mut prev_span = DUMMY_SP;
         let mut result = vec![];
         loop {
@@ -438,7 +454,7 @@ impl Span {
                 break;
             }
             // Don't print recursive invocations.
-            if !expn_data.call_site.source_equal(&prev_span) {
+            if !skip_recursive || !expn_data.call_site.source_equal(&prev_span) {
                 let (pre, post) = match expn_data.kind {
                     ExpnKind::Root => break,
                     ExpnKind::Desugaring(..) => ("desugaring of ", ""),
@@ -604,6 +620,10 @@ pub struct SpanLabel {
 
     /// What label should we attach to this span (if any)?
     pub label: Option<String>,
+
+    /// For primary spans given a role, the index of that role, which
+    /// selects the marker and color the span is rendered with.
+    pub role: Option<usize>,
 }
 
 impl Default for Span {
@@ -662,21 +682,24 @@ impl MultiSpan {
     pub fn new() -> MultiSpan {
         MultiSpan {
             primary_spans: vec![],
-            span_labels: vec![]
+            span_labels: vec![],
+            primary_roles: vec![],
         }
     }
 
     pub fn from_span(primary_span: Span) -> MultiSpan {
         MultiSpan {
             primary_spans: vec![primary_span],
-            span_labels: vec![]
+            span_labels: vec![],
+            primary_roles: vec![],
         }
     }
 
     pub fn from_spans(vec: Vec<Span>) -> MultiSpan {
         MultiSpan {
             primary_spans: vec,
-            span_labels: vec![]
+            span_labels: vec![],
+            primary_roles: vec![],
         }
     }
 
@@ -684,6 +707,19 @@ impl MultiSpan {
         self.span_labels.push((span, label));
     }
 
+    /// Adds a primary span playing the given role in the error, such as
+    /// "first use" and "second use". Roles are numbered in the order they
+    /// are added, and each one is rendered with a marker of its own.
+    pub fn push_primary_role(&mut self, span: Span, role: String) {
+        self.primary_spans.push(span);
+        self.primary_roles.push((span, role));
+    }
+
+    /// Returns the primary spans that were given a role, with their roles.
+    pub fn primary_roles(&self) -> &[(Span, String)] {
+        &self.primary_roles
+    }
+
     /// Selects the first primary span (if any).
     pub fn primary_span(&self) -> Option<Span> {
         self.primary_spans.first().cloned()
@@ -720,7 +756,7 @@ impl MultiSpan {
                 replacements_occurred = true;
             }
         }
-        for span_label in &mut self.span_labels {
+        for span_label in self.span_labels.iter_mut().chain(&mut self.primary_roles) {
             if span_label.0 == before {
                 span_label.0 = after;
                 replacements_occurred = true;
@@ -729,28 +765,43 @@ impl MultiSpan {
         replacements_occurred
     }
 
+    /// Replaces every span, including those of the labels and roles, with `f(span)`.
+    pub fn map_spans(&mut self, mut f: impl FnMut(Span) -> Span) {
+        for primary_span in &mut self.primary_spans {
+            *primary_span = f(*primary_span);
+        }
+        for span_label in self.span_labels.iter_mut().chain(&mut self.primary_roles) {
+            span_label.0 = f(span_label.0);
+        }
+    }
+
     /// Returns the strings to highlight. We always ensure that there
     /// is an entry for each of the primary spans -- for each primary
     /// span `P`, if there is at least one label with span `P`, we return
     /// those labels (marked as primary). But otherwise we return
-    /// `SpanLabel` instances with empty labels.
+    /// `SpanLabel` instances with empty labels, or labeled with their
+    /// role if they were given one.
     pub fn span_labels(&self) -> Vec<SpanLabel> {
         let is_primary = |span| self.primary_spans.contains(&span);
+        let role = |span| self.primary_roles.iter().position(|&(sp, _)| sp == span);
 
         let mut span_labels = self.span_labels.iter().map(|&(span, ref label)|
             SpanLabel {
                 span,
                 is_primary: is_primary(span),
-                label: Some(label.clone())
+                label: Some(label.clone()),
+                role: role(span),
             }
         ).collect::<Vec<_>>();
 
         for &span in &self.primary_spans {
             if !span_labels.iter().any(|sl| sl.span == span) {
+                let role = role(span);
                 span_labels.push(SpanLabel {
                     span,
                     is_primary: true,
-                    label: None
+                    label: role.map(|role| self.primary_roles[role].1.clone()),
+                    role,
                 });
             }
         }
@@ -785,6 +836,97 @@ pub struct MultiByteChar {
     pub bytes: u8,
 }
 
+/// Identifies an offset in a `SourceFile` from which on its normalized source is `diff` bytes
+/// behind the original one, because a UTF-8 BOM or the `\r` of a `\r\n` line ending was
+/// removed in front of it.
+#[derive(Copy, Clone, RustcEncodable, RustcDecodable, Eq, PartialEq, Debug)]
+pub struct NormalizedPos {
+    /// The absolute offset in the `SourceMap` from which on `diff` applies: the start of the
+    /// file for a BOM, the start of the next line for a `\r\n` line ending.
+    pub pos: BytePos,
+    /// The total number of bytes removed from the original source before `pos`.
+    pub diff: u32,
+}
+
+/// The line ending of a line of a `SourceFile`, before its line endings were normalized.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LineEnding {
+    /// `\n`
+    Lf,
+    /// `\r\n`
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// How a line of a `SourceFile` is indented.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum IndentStyle {
+    /// The line only consists of whitespace.
+    Blank,
+    /// The line isn't indented.
+    None,
+    Tabs,
+    Spaces,
+    /// The line is indented with both tabs and spaces.
+    Mixed,
+}
+
+/// The indentation of a line of a `SourceFile`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct LineIndent {
+    pub style: IndentStyle,
+    /// The number of tabs and spaces the line starts with.
+    pub len: usize,
+}
+
+impl LineIndent {
+    fn of_line(line: &str) -> LineIndent {
+        let len = line.len() - line.trim_start_matches(|c| c == ' ' || c == '\t').len();
+        let indent = &line[..len];
+        let style = if len == line.len() {
+            return LineIndent { style: IndentStyle::Blank, len: 0 };
+        } else if len == 0 {
+            IndentStyle::None
+        } else if !indent.contains(' ') {
+            IndentStyle::Tabs
+        } else if !indent.contains('\t') {
+            IndentStyle::Spaces
+        } else {
+            IndentStyle::Mixed
+        };
+        LineIndent { style, len }
+    }
+}
+
+/// What a `SourceFile` indents its lines by for each level of nesting.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum IndentUnit {
+    Tab,
+    /// The given number of spaces.
+    Spaces(usize),
+}
+
+impl IndentUnit {
+    /// Returns the indentation spanning `levels` levels of nesting, followed by `spaces`
+    /// spaces for alignment.
+    pub fn indent(self, levels: usize, spaces: usize) -> String {
+        let mut indent = match self {
+            IndentUnit::Tab => "\t".repeat(levels),
+            IndentUnit::Spaces(width) => " ".repeat(levels * width),
+        };
+        indent.push_str(&" ".repeat(spaces));
+        indent
+    }
+}
+
 /// Identifies an offset of a non-narrow character in a `SourceFile`.
 #[derive(Copy, Clone, RustcEncodable, RustcDecodable, Eq, PartialEq, Debug)]
 pub enum NonNarrowChar {
@@ -881,6 +1023,32 @@ impl ExternalSource {
 #[derive(Debug)]
 pub struct OffsetOverflowError;
 
+// This is a `SourceFile` identifier that is used to correlate `SourceFile`s between
+// subsequent compilation sessions (which is something we need to do during
+// incremental compilation).
+#[derive(Copy, Clone, PartialEq, Eq, Hash, RustcEncodable, RustcDecodable, Debug)]
+pub struct StableSourceFileId(u128);
+
+impl StableSourceFileId {
+    pub fn new(source_file: &SourceFile) -> StableSourceFileId {
+        StableSourceFileId::new_from_pieces(&source_file.name,
+                                            source_file.name_was_remapped,
+                                            source_file.unmapped_path.as_ref())
+    }
+
+    pub fn new_from_pieces(name: &FileName,
+                           name_was_remapped: bool,
+                           unmapped_path: Option<&FileName>) -> StableSourceFileId {
+        let mut hasher = StableHasher::new();
+
+        name.hash(&mut hasher);
+        name_was_remapped.hash(&mut hasher);
+        unmapped_path.hash(&mut hasher);
+
+        StableSourceFileId(hasher.finish())
+    }
+}
+
 /// A single source in the `SourceMap`.
 #[derive(Clone)]
 pub struct SourceFile {
@@ -912,6 +1080,9 @@ pub struct SourceFile {
     pub multibyte_chars: Vec<MultiByteChar>,
     /// Width of characters that are not narrow in the source code.
     pub non_narrow_chars: Vec<NonNarrowChar>,
+    /// Locations where the source was changed by removing a BOM or normalizing a `\r\n`
+    /// line ending, that is one per line ending with `\r\n` in the original source.
+    pub normalized_pos: Vec<NormalizedPos>,
     /// A hash of the filename, used for speeding up hashing in incremental compilation.
     pub name_hash: u128,
 }
@@ -978,6 +1149,9 @@ impl Encodable for SourceFile {
             })?;
             s.emit_struct_field("name_hash", 9, |s| {
                 self.name_hash.encode(s)
+            })?;
+            s.emit_struct_field("normalized_pos", 10, |s| {
+                self.normalized_pos.encode(s)
             })
         })
     }
@@ -1029,6 +1203,8 @@ impl Decodable for SourceFile {
                 d.read_struct_field("non_narrow_chars", 8, |d| Decodable::decode(d))?;
             let name_hash: u128 =
                 d.read_struct_field("name_hash", 9, |d| Decodable::decode(d))?;
+            let normalized_pos: Vec<NormalizedPos> =
+                d.read_struct_field("normalized_pos", 10, |d| Decodable::decode(d))?;
             Ok(SourceFile {
                 name,
                 name_was_remapped,
@@ -1045,6 +1221,7 @@ impl Decodable for SourceFile {
                 lines,
                 multibyte_chars,
                 non_narrow_chars,
+                normalized_pos,
                 name_hash,
             })
         })
@@ -1063,8 +1240,12 @@ impl SourceFile {
                unmapped_path: FileName,
                mut src: String,
                start_pos: BytePos) -> Result<SourceFile, OffsetOverflowError> {
-        remove_bom(&mut src);
-        normalize_newlines(&mut src);
+        let mut normalized_pos = vec![];
+        remove_bom(&mut src, &mut normalized_pos);
+        normalize_newlines(&mut src, &mut normalized_pos);
+        for np in &mut normalized_pos {
+            np.pos = np.pos + start_pos;
+        }
 
         let src_hash = {
             let mut hasher: StableHasher<u128> = StableHasher::new();
@@ -1097,6 +1278,7 @@ impl SourceFile {
             lines,
             multibyte_chars,
             non_narrow_chars,
+            normalized_pos,
             name_hash,
         })
     }
@@ -1107,6 +1289,13 @@ impl SourceFile {
         self.lines[line_index]
     }
 
+    /// Returns `true` if `src` is the source this file was created from.
+    pub fn src_hash_matches(&self, src: &str) -> bool {
+        let mut hasher: StableHasher<u128> = StableHasher::new();
+        hasher.write(src.as_bytes());
+        hasher.finish() == self.src_hash
+    }
+
     /// Add externally loaded source.
     /// If the hash of the input doesn't match or no input is supplied via None,
     /// it is interpreted as an error and the corresponding enum variant is set.
@@ -1120,10 +1309,7 @@ impl SourceFile {
             // Check that no-one else have provided the source while we were getting it
             if *external_src == ExternalSource::AbsentOk {
                 if let Some(src) = src {
-                    let mut hasher: StableHasher<u128> = StableHasher::new();
-                    hasher.write(src.as_bytes());
-
-                    if hasher.finish() == self.src_hash {
+                    if self.src_hash_matches(&src) {
                         *external_src = ExternalSource::Present(src);
                         return true;
                     }
@@ -1223,20 +1409,256 @@ impl SourceFile {
     pub fn contains(&self, byte_pos: BytePos) -> bool {
         byte_pos >= self.start_pos && byte_pos <= self.end_pos
     }
+
+    /// Returns the line ending the line with the given 0-based index had before normalization,
+    /// or `None` for the last line, which doesn't end with a line break.
+    pub fn line_ending(&self, line_index: usize) -> Option<LineEnding> {
+        let next_line_start = *self.lines.get(line_index + 1)?;
+        // Every `\r\n` line ending is recorded at the start of the next line.
+        match self.normalized_pos.binary_search_by(|np| np.pos.cmp(&next_line_start)) {
+            Ok(_) => Some(LineEnding::CrLf),
+            Err(_) => Some(LineEnding::Lf),
+        }
+    }
+
+    /// Restores the line endings of the lines `snippet` inserts at `pos`, which are normalized
+    /// to `\n`, to the one of the line containing `pos`, or of the line before it if it's the
+    /// last line.
+    pub fn restore_line_endings(&self, pos: BytePos, snippet: &str) -> String {
+        let line_index = self.lookup_line(pos).unwrap_or(0);
+        let line_ending = self.line_ending(line_index)
+            .or_else(|| self.line_ending(line_index.checked_sub(1)?))
+            .unwrap_or(LineEnding::Lf);
+        match line_ending {
+            LineEnding::Lf => snippet.to_string(),
+            LineEnding::CrLf => snippet.replace('\n', line_ending.as_str()),
+        }
+    }
+
+    /// Returns the indentation of each line of this file, or `None` if its source isn't
+    /// available.
+    pub fn line_indents(&self) -> Option<Vec<LineIndent>> {
+        (0..self.lines.len())
+            .map(|line_index| self.get_line(line_index).map(|line| LineIndent::of_line(&line)))
+            .collect()
+    }
+
+    /// Returns what this file indents its lines by, or `None` if no line is indented or its
+    /// source isn't available. Tabs are used if more lines are indented with them than with
+    /// spaces. Otherwise the number of spaces is the most common increase of indentation from
+    /// one non-blank line to the next.
+    pub fn indent_unit(&self) -> Option<IndentUnit> {
+        let indents = self.line_indents()?;
+        let lines_with = |style: IndentStyle| {
+            indents.iter().filter(|indent| indent.style == style).count()
+        };
+        let (tabs, spaces) = (lines_with(IndentStyle::Tabs), lines_with(IndentStyle::Spaces));
+        if tabs == 0 && spaces == 0 {
+            return None;
+        } else if tabs > spaces {
+            return Some(IndentUnit::Tab);
+        }
+
+        let mut increases = Vec::new();
+        let mut prev_len = 0;
+        for indent in indents.iter().filter(|indent| indent.style != IndentStyle::Blank) {
+            if indent.style == IndentStyle::Spaces && indent.len > prev_len {
+                increases.push(indent.len - prev_len);
+            }
+            prev_len = indent.len;
+        }
+        // Of increases occurring as often, the smallest one wins.
+        increases.sort();
+        let mut most_common: Option<(usize, usize)> = None;
+        let mut start = 0;
+        while start < increases.len() {
+            let width = increases[start];
+            let count = increases[start..].iter().take_while(|&&w| w == width).count();
+            if most_common.map_or(true, |(most, _)| count > most) {
+                most_common = Some((count, width));
+            }
+            start += count;
+        }
+        most_common.map(|(_, width)| IndentUnit::Spaces(width))
+    }
+
+    /// Adapts the indentation of the lines `snippet` inserts at `pos` to the one of this file,
+    /// so that fixes don't mix tabs and spaces or indentation widths. The compiler indents the
+    /// code it suggests by four spaces per level, usually after copying the indentation of the
+    /// line containing `pos`. That copy is kept as is, and only the rest is adapted.
+    ///
+    /// Only the lines the compiler generated are adapted: lines copied from this file are
+    /// already indented like it, and lines starting inside a string literal are part of its
+    /// value.
+    pub fn reindent(&self, pos: BytePos, snippet: &str) -> String {
+        if !snippet.contains('\n') {
+            return snippet.to_string();
+        }
+        let unit = match self.indent_unit() {
+            // Files indented like the compiler indents are left alone, as are files without
+            // indentation.
+            Some(IndentUnit::Spaces(4)) | None => return snippet.to_string(),
+            Some(unit) => unit,
+        };
+        let file_lines = (0..self.lines.len())
+            .filter_map(|line_index| self.get_line(line_index))
+            .collect::<FxHashSet<_>>();
+        let base = self.lookup_line(pos)
+            .and_then(|line_index| self.get_line(line_index))
+            .map(|line| line[..LineIndent::of_line(&line).len].to_string())
+            .unwrap_or_default();
+
+        let mut lines = snippet.split('\n');
+        let mut reindented = lines.next().unwrap().to_string();
+        for (line, in_literal) in lines.zip(lines_in_string_literals(snippet)) {
+            reindented.push('\n');
+            if in_literal
+                || LineIndent::of_line(line).style == IndentStyle::Blank
+                || file_lines.contains(line)
+            {
+                reindented.push_str(line);
+                continue;
+            }
+            let (kept, rest) = if line.starts_with(&base) {
+                line.split_at(base.len())
+            } else {
+                ("", line)
+            };
+            let code = rest.trim_start_matches(|c| c == ' ' || c == '\t');
+            let columns: usize = rest[..rest.len() - code.len()].chars()
+                .map(|c| if c == '\t' { 4 } else { 1 })
+                .sum();
+            reindented.push_str(kept);
+            reindented.push_str(&unit.indent(columns / 4, columns % 4));
+            reindented.push_str(code);
+        }
+        reindented
+    }
+
+    /// Returns the number of bytes removed by normalization in front of `pos`.
+    fn normalized_diff(&self, pos: BytePos) -> u32 {
+        match self.normalized_pos.binary_search_by(|np| np.pos.cmp(&pos)) {
+            Ok(i) => self.normalized_pos[i].diff,
+            Err(0) => 0,
+            Err(i) => self.normalized_pos[i - 1].diff,
+        }
+    }
+
+    /// Calculates the offset of `pos` relative to the start of the file as it was read, before
+    /// its BOM was removed and its `\r\n` line endings were normalized. An offset at the end
+    /// of a line maps to the `\r` of its line ending, so spans of tokens next to line endings
+    /// cover the same text in both sources, and the span of a `\n` covers the whole `\r\n`.
+    pub fn original_relative_byte_pos(&self, pos: BytePos) -> BytePos {
+        let diff = self.normalized_diff(pos);
+        BytePos::from_u32(pos.0 - self.start_pos.0 + diff)
+    }
+}
+
+/// Returns whether each line of the code `snippet` but the first starts inside a string
+/// literal. This only needs to tell strings apart from comments and character literals, so
+/// the snippet isn't lexed.
+fn lines_in_string_literals(snippet: &str) -> Vec<bool> {
+    #[derive(Clone, Copy)]
+    enum State {
+        Code,
+        Str,
+        /// A raw string literal, closed by a `"` and this number of `#`s.
+        RawStr(usize),
+        LineComment,
+        BlockComment,
+    }
+
+    let mut in_literals = Vec::new();
+    let mut state = State::Code;
+    let mut chars = snippet.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            in_literals.push(match state {
+                State::Str | State::RawStr(_) => true,
+                State::Code | State::BlockComment => false,
+                State::LineComment => {
+                    state = State::Code;
+                    false
+                }
+            });
+            continue;
+        }
+        match state {
+            State::Code => match c {
+                '"' => state = State::Str,
+                'r' if chars.peek() == Some(&'"') || chars.peek() == Some(&'#') => {
+                    let mut hashes = 0;
+                    while chars.peek() == Some(&'#') {
+                        chars.next();
+                        hashes += 1;
+                    }
+                    // Otherwise, this is a raw identifier.
+                    if chars.peek() == Some(&'"') {
+                        chars.next();
+                        state = State::RawStr(hashes);
+                    }
+                }
+                '/' if chars.peek() == Some(&'/') => state = State::LineComment,
+                '/' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    state = State::BlockComment;
+                }
+                // Skip character literals, which may be `'"'`, but not lifetimes.
+                '\'' if chars.peek() == Some(&'\\') => {
+                    chars.next();
+                    chars.next();
+                    while chars.peek().map_or(false, |&c| c != '\'' && c != '\n') {
+                        chars.next();
+                    }
+                    chars.next();
+                }
+                '\'' => {
+                    let mut ahead = chars.clone();
+                    if ahead.next().is_some() && ahead.next() == Some('\'') {
+                        chars = ahead;
+                    }
+                }
+                _ => {}
+            },
+            State::Str => match c {
+                // An escaped line break is still inside the literal.
+                '\\' if chars.peek() != Some(&'\n') => {
+                    chars.next();
+                }
+                '"' => state = State::Code,
+                _ => {}
+            },
+            State::RawStr(hashes) => if c == '"' {
+                let mut ahead = chars.clone();
+                if (0..hashes).all(|_| ahead.next() == Some('#')) {
+                    chars = ahead;
+                    state = State::Code;
+                }
+            },
+            State::LineComment => {}
+            State::BlockComment => if c == '*' && chars.peek() == Some(&'/') {
+                chars.next();
+                state = State::Code;
+            },
+        }
+    }
+    in_literals
 }
 
-/// Removes UTF-8 BOM, if any.
-fn remove_bom(src: &mut String) {
+/// Removes UTF-8 BOM, if any, recording its removal in `normalized_pos`.
+fn remove_bom(src: &mut String, normalized_pos: &mut Vec<NormalizedPos>) {
     if src.starts_with("\u{feff}") {
         src.drain(..3);
+        normalized_pos.push(NormalizedPos { pos: BytePos(0), diff: 3 });
     }
 }
 
 
-/// Replaces `\r\n` with `\n` in-place in `src`.
+/// Replaces `\r\n` with `\n` in-place in `src`, recording the start of the line after every
+/// `\n` whose `\r` was removed in `normalized_pos`, relative to the start of `src`.
 ///
-/// Returns error if there's a lone `\r` in the string
-fn normalize_newlines(src: &mut String) {
+/// Lone `\r`s are kept, the lexer reports them.
+fn normalize_newlines(src: &mut String, normalized_pos: &mut Vec<NormalizedPos>) {
     if !src.as_bytes().contains(&b'\r') {
         return;
     }
@@ -1249,6 +1671,10 @@ fn normalize_newlines(src: &mut String) {
     let mut buf = std::mem::replace(src, String::new()).into_bytes();
     let mut gap_len = 0;
     let mut tail = buf.as_mut_slice();
+    // The offset of `tail` in the normalized source.
+    let mut cursor = 0;
+    // The bytes removed by `remove_bom`, if any.
+    let original_gap = normalized_pos.last().map_or(0, |np| np.diff);
     loop {
         let idx = match find_crlf(&tail[gap_len..]) {
             None => tail.len(),
@@ -1256,10 +1682,17 @@ fn normalize_newlines(src: &mut String) {
         };
         tail.copy_within(gap_len..idx, 0);
         tail = &mut tail[idx - gap_len..];
+        cursor += idx - gap_len;
         if tail.len() == gap_len {
             break;
         }
         gap_len += 1;
+        // The offsets up to and including the `\n` map to the `\r\n` in the original
+        // source, the new difference starts on the next line.
+        normalized_pos.push(NormalizedPos {
+            pos: BytePos::from_usize(cursor + 1),
+            diff: original_gap + gap_len as u32,
+        });
     }
 
     // Account for removed `\r`.
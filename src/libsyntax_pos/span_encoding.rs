@@ -104,36 +104,86 @@ impl Span {
             // Interned format.
             debug_assert!(self.ctxt_or_zero == 0);
             let index = self.base_or_index;
-            with_span_interner(|interner| *interner.get(index))
+            with_span_interner(|interner| interner.get(index))
         }
     }
+
+    /// Returns statistics about the use of the span interner so far.
+    pub fn interner_stats() -> SpanInternerStats {
+        with_span_interner(|interner| interner.stats())
+    }
 }
 
+/// The number of entries in `SpanInterner::recent`.
+const RECENT_SPANS: usize = 16;
+
 #[derive(Default)]
 pub struct SpanInterner {
     spans: FxHashMap<SpanData, u32>,
     span_data: Vec<SpanData>,
+    /// The last span interned for each context, indexed by the context modulo
+    /// `RECENT_SPANS`. An expansion tends to intern the same spans over and over.
+    recent: [Option<(SpanData, u32)>; RECENT_SPANS],
+    /// The number of calls to `intern`, including those for spans already interned.
+    interned: usize,
+    recent_hits: usize,
+}
+
+/// Statistics about the use of the span interner, see `Span::interner_stats`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SpanInternerStats {
+    /// The number of distinct spans that were too large to be stored inline in a `Span`.
+    pub spans: usize,
+    /// The number of times such spans were interned, including those that already were.
+    pub interned: usize,
+    /// The number of bytes the interner allocated for its tables.
+    pub bytes: usize,
+    /// The number of spans that were found in the cache of recently interned spans.
+    pub cache_hits: usize,
 }
 
 impl SpanInterner {
     fn intern(&mut self, span_data: &SpanData) -> u32 {
-        if let Some(index) = self.spans.get(span_data) {
-            return *index;
+        self.interned += 1;
+        let slot = span_data.ctxt.as_u32() as usize % RECENT_SPANS;
+        if let Some((recent, index)) = self.recent[slot] {
+            if recent == *span_data {
+                self.recent_hits += 1;
+                return index;
+            }
         }
 
-        let index = self.spans.len() as u32;
-        self.span_data.push(*span_data);
-        self.spans.insert(*span_data, index);
+        let index = match self.spans.get(span_data) {
+            Some(&index) => index,
+            None => {
+                let index = self.span_data.len() as u32;
+                self.span_data.push(*span_data);
+                self.spans.insert(*span_data, index);
+                index
+            }
+        };
+        self.recent[slot] = Some((*span_data, index));
         index
     }
 
     #[inline]
-    fn get(&self, index: u32) -> &SpanData {
-        &self.span_data[index as usize]
+    fn get(&self, index: u32) -> SpanData {
+        self.span_data[index as usize]
+    }
+
+    fn stats(&self) -> SpanInternerStats {
+        // Each bucket of the map also has a control byte.
+        let bucket_size = std::mem::size_of::<(SpanData, u32)>() + 1;
+        SpanInternerStats {
+            spans: self.span_data.len(),
+            interned: self.interned,
+            bytes: self.span_data.capacity() * std::mem::size_of::<SpanData>()
+                + self.spans.capacity() * bucket_size,
+            cache_hits: self.recent_hits,
+        }
     }
 }
 
-// If an interner exists, return it. Otherwise, prepare a fresh one.
 #[inline]
 fn with_span_interner<T, F: FnOnce(&mut SpanInterner) -> T>(f: F) -> T {
     GLOBALS.with(|globals| f(&mut *globals.span_interner.lock()))
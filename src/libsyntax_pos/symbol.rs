@@ -6,6 +6,8 @@ use arena::DroplessArena;
 use rustc_data_structures::fx::FxHashMap;
 use rustc_data_structures::indexed_vec::Idx;
 use rustc_data_structures::newtype_index;
+use rustc_data_structures::sharded::{Sharded, SHARDS};
+use rustc_data_structures::sync::{Lock, LockGuard};
 use rustc_macros::symbols;
 use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
 use rustc_serialize::{UseSpecializedDecodable, UseSpecializedEncodable};
@@ -13,7 +15,10 @@ use rustc_serialize::{UseSpecializedDecodable, UseSpecializedEncodable};
 use std::cmp::{PartialEq, Ordering, PartialOrd, Ord};
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::ptr;
+use std::slice;
 use std::str;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering as AtomicOrdering};
 
 use crate::{Span, DUMMY_SP, GLOBALS};
 
@@ -139,9 +144,11 @@ symbols! {
         any,
         arbitrary_enum_discriminant,
         arbitrary_self_types,
+        args,
         Arguments,
         ArgumentV1,
         arm_target_feature,
+        as_ref,
         asm,
         assert,
         associated_consts,
@@ -188,6 +195,7 @@ symbols! {
         cmp,
         cmpxchg16b_target_feature,
         cold,
+        collect,
         column,
         compile_error,
         compiler_builtins,
@@ -207,6 +215,7 @@ symbols! {
         const_raw_ptr_deref,
         const_raw_ptr_to_usize_cast,
         const_transmute,
+        contains,
         contents,
         context,
         convert,
@@ -214,6 +223,7 @@ symbols! {
         copy_closures,
         core,
         core_intrinsics,
+        count,
         crate_id,
         crate_in_paths,
         crate_local,
@@ -225,6 +235,7 @@ symbols! {
         custom_inner_attributes,
         custom_test_frameworks,
         c_variadic,
+        data,
         debug_trait,
         declare_lint_pass,
         decl_macro,
@@ -241,6 +252,7 @@ symbols! {
         derive,
         diagnostic,
         direct,
+        Display,
         doc,
         doc_alias,
         doc_cfg,
@@ -273,11 +285,13 @@ symbols! {
         Err,
         Eq,
         Equal,
+        error,
         except,
         exclusive_range_pattern,
         exhaustive_integer_patterns,
         exhaustive_patterns,
         existential_type,
+        expect,
         expected,
         export_name,
         expr,
@@ -319,6 +333,7 @@ symbols! {
         generators,
         generic_associated_types,
         generic_param_attrs,
+        get,
         global_allocator,
         global_asm,
         globs,
@@ -358,12 +373,15 @@ symbols! {
         infer_outlives_requirements,
         infer_static_outlives_requirements,
         inline,
+        insert,
         intel,
+        into,
         into_iter,
         IntoIterator,
         into_result,
         intrinsics,
         irrefutable_let_patterns,
+        is_empty,
         isize,
         issue,
         issue_5723_bootstrap,
@@ -372,12 +390,14 @@ symbols! {
         item_like_imports,
         iter,
         Iterator,
+        key,
         keyword,
         kind,
         label,
         label_break_value,
         lang,
         lang_items,
+        len,
         let_chains,
         lhs,
         lib,
@@ -401,6 +421,7 @@ symbols! {
         macro_export,
         macro_lifetime_matcher,
         macro_literal_matcher,
+        macro_metavar_expr,
         macro_reexport,
         macro_rules,
         macros_in_extern,
@@ -408,6 +429,7 @@ symbols! {
         macro_vis_matcher,
         main,
         managed_boxes,
+        map,
         marker,
         marker_trait_attr,
         masked,
@@ -521,6 +543,7 @@ symbols! {
         proc_macro_path_invoc,
         profiler_runtime,
         pub_restricted,
+        push,
         pushpop_unsafe,
         quad_precision_float,
         question_mark,
@@ -576,6 +599,7 @@ symbols! {
         rustc_dirty,
         rustc_dummy,
         rustc_dump_env_program_clauses,
+        rustc_dump_pat_shapes,
         rustc_dump_program_clauses,
         rustc_dump_user_substs,
         rustc_error,
@@ -642,6 +666,7 @@ symbols! {
         std,
         std_inject,
         str,
+        String,
         stringify,
         stmt,
         stmt_expr_attributes,
@@ -652,9 +677,16 @@ symbols! {
         struct_variant,
         sty,
         suggestion,
+        target_arch,
+        target_endian,
+        target_env,
+        target_family,
         target_feature,
         target_has_atomic,
+        target_os,
+        target_pointer_width,
         target_thread_local,
+        target_vendor,
         task,
         tbm_target_feature,
         termination_trait,
@@ -667,6 +699,7 @@ symbols! {
         test_runner,
         then_with,
         thread_local,
+        to_string,
         tool_attributes,
         tool_lints,
         trace_macros,
@@ -702,6 +735,7 @@ symbols! {
         uniform_paths,
         uninitialized,
         universal_impl_trait,
+        unix,
         unmarked_api,
         unreachable_code,
         unrestricted_attribute_tokens,
@@ -712,6 +746,7 @@ symbols! {
         untagged_unions,
         unwind,
         unwind_attributes,
+        unwrap,
         unwrap_or,
         used,
         use_extern_macros,
@@ -719,6 +754,7 @@ symbols! {
         usize,
         v1,
         val,
+        value,
         vec,
         Vec,
         vis,
@@ -731,6 +767,7 @@ symbols! {
         while_let,
         windows,
         windows_subsystem,
+        write,
         Yield,
         zeroed,
     }
@@ -956,6 +993,11 @@ impl Symbol {
     pub fn as_u32(self) -> u32 {
         self.0.as_u32()
     }
+
+    /// Returns statistics about the use of the symbol interner so far.
+    pub fn interner_stats() -> InternerStats {
+        with_interner(|interner| interner.stats())
+    }
 }
 
 impl fmt::Debug for Symbol {
@@ -987,77 +1029,229 @@ impl Decodable for Symbol {
     }
 }
 
-// The `&'static str`s in this type actually point into the arena.
+// The `&'static str`s in this type actually point into the arenas of the shards,
+// or are the strings of the pre-interned symbols.
 //
 // Note that normal symbols are indexed upward from 0, and gensyms are indexed
-// downward from SymbolIndex::MAX_AS_U32.
-#[derive(Default)]
+// downward from SymbolIndex::MAX_AS_U32. The pre-interned symbols come first,
+// and the symbols interned after them are spread over the shards by the hash of
+// their string, so that threads interning different strings rarely contend for
+// the same lock: the `i`th symbol of shard `s` has the index
+// `prefilled_strings.len() + i * SHARDS + s`. The strings of the symbols of
+// shard `s` are kept in `strings[s]` rather than in the shard, so that getting
+// the string of a symbol, which happens a lot more often than interning one,
+// doesn't lock anything.
 pub struct Interner {
+    prefilled_names: FxHashMap<&'static str, Symbol>,
+    prefilled_strings: Vec<&'static str>,
+    shards: Sharded<InternerShard>,
+    strings: Vec<ShardStrings>,
+    gensyms: Lock<Vec<Symbol>>,
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Interner {
+            prefilled_names: Default::default(),
+            prefilled_strings: Default::default(),
+            shards: Default::default(),
+            strings: (0..SHARDS).map(|_| ShardStrings::default()).collect(),
+            gensyms: Default::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct InternerShard {
     arena: DroplessArena,
     names: FxHashMap<&'static str, Symbol>,
-    strings: Vec<&'static str>,
-    gensyms: Vec<Symbol>,
+    locks: usize,
+    contended_locks: usize,
+}
+
+/// The number of segments of a `ShardStrings`, enough for every index of a symbol.
+const SEGMENTS: usize = 32;
+
+/// The strings of the symbols of a shard of the `Interner`, in order, which can be read
+/// without locking the shard. They are stored in segments that are never moved or freed
+/// before the interner is, the `k`th one holding `2^k` strings, and only appended to while
+/// the shard is locked.
+#[derive(Default)]
+struct ShardStrings {
+    segments: [AtomicPtr<&'static str>; SEGMENTS],
+    /// The number of strings, stored once the string it counts is written.
+    len: AtomicUsize,
+}
+
+impl ShardStrings {
+    /// Returns the segment holding the string with the given index, and its index there.
+    fn position(index: usize) -> (usize, usize) {
+        let segment = 63 - (index as u64 + 1).leading_zeros() as usize;
+        (segment, index + 1 - (1 << segment))
+    }
+
+    fn len(&self) -> usize {
+        self.len.load(AtomicOrdering::Acquire)
+    }
+
+    fn get(&self, index: usize) -> Option<&'static str> {
+        if index >= self.len() {
+            return None;
+        }
+        let (segment, offset) = Self::position(index);
+        let segment = self.segments[segment].load(AtomicOrdering::Acquire);
+        // The string was written before `len` was stored past it, and is never written again.
+        Some(unsafe { *segment.add(offset) })
+    }
+
+    /// Appends `string`. This must only be called while the shard is locked.
+    fn push(&self, string: &'static str) {
+        let index = self.len.load(AtomicOrdering::Relaxed);
+        let (segment, offset) = Self::position(index);
+        let mut ptr = self.segments[segment].load(AtomicOrdering::Acquire);
+        if ptr.is_null() {
+            let strings = vec![""; 1 << segment].into_boxed_slice();
+            ptr = Box::into_raw(strings) as *mut &'static str;
+            self.segments[segment].store(ptr, AtomicOrdering::Release);
+        }
+        // Other threads only read the strings before `len`.
+        unsafe {
+            ptr::write(ptr.add(offset), string);
+        }
+        self.len.store(index + 1, AtomicOrdering::Release);
+    }
+}
+
+impl Drop for ShardStrings {
+    fn drop(&mut self) {
+        for (segment, ptr) in self.segments.iter_mut().enumerate() {
+            let ptr = *ptr.get_mut();
+            if !ptr.is_null() {
+                unsafe {
+                    drop(Box::from_raw(slice::from_raw_parts_mut(ptr, 1 << segment)));
+                }
+            }
+        }
+    }
+}
+
+/// Statistics about the use of the symbol interner, see `Symbol::interner_stats`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InternerStats {
+    /// The number of symbols that were interned in addition to the pre-interned ones.
+    pub symbols: usize,
+    pub gensyms: usize,
+    /// The number of times a shard of the interner was locked.
+    pub locks: usize,
+    /// The number of those times the shard was already locked by another thread.
+    pub contended_locks: usize,
 }
 
 impl Interner {
     fn prefill(init: &[&'static str]) -> Self {
         Interner {
-            strings: init.into(),
-            names: init.iter().copied().zip((0..).map(Symbol::new)).collect(),
+            prefilled_strings: init.into(),
+            prefilled_names: init.iter().copied().zip((0..).map(Symbol::new)).collect(),
             ..Default::default()
         }
     }
 
-    pub fn intern(&mut self, string: &str) -> Symbol {
-        if let Some(&name) = self.names.get(string) {
+    fn lock_shard(&self, index: usize) -> LockGuard<'_, InternerShard> {
+        let lock = self.shards.get_shard_by_index(index);
+        let (mut shard, contended) = match lock.try_lock() {
+            Some(shard) => (shard, false),
+            None => (lock.lock(), true),
+        };
+        shard.locks += 1;
+        if contended {
+            shard.contended_locks += 1;
+        }
+        shard
+    }
+
+    pub fn intern(&self, string: &str) -> Symbol {
+        if let Some(&name) = self.prefilled_names.get(string) {
             return name;
         }
 
-        let name = Symbol::new(self.strings.len() as u32);
+        let index = self.shards.get_shard_index_by_value(string);
+        let mut shard = self.lock_shard(index);
+        if let Some(&name) = shard.names.get(string) {
+            return name;
+        }
+
+        let strings = &self.strings[index];
+        let name = Symbol::new(
+            (self.prefilled_strings.len() + strings.len() * SHARDS + index) as u32
+        );
 
         // `from_utf8_unchecked` is safe since we just allocated a `&str` which is known to be
         // UTF-8.
         let string: &str = unsafe {
-            str::from_utf8_unchecked(self.arena.alloc_slice(string.as_bytes()))
+            str::from_utf8_unchecked(shard.arena.alloc_slice(string.as_bytes()))
         };
         // It is safe to extend the arena allocation to `'static` because we only access
         // these while the arena is still alive.
         let string: &'static str =  unsafe {
             &*(string as *const str)
         };
-        self.strings.push(string);
-        self.names.insert(string, name);
+        strings.push(string);
+        shard.names.insert(string, name);
         name
     }
 
+    // Get the string of a symbol that isn't gensymed, without locking anything.
+    fn lookup(&self, symbol: Symbol) -> Option<&'static str> {
+        let index = symbol.0.as_usize();
+        if let Some(&string) = self.prefilled_strings.get(index) {
+            return Some(string);
+        }
+        let index = index - self.prefilled_strings.len();
+        self.strings[index % SHARDS].get(index / SHARDS)
+    }
+
     fn interned(&self, symbol: Symbol) -> Symbol {
-        if (symbol.0.as_usize()) < self.strings.len() {
+        if self.lookup(symbol).is_some() {
             symbol
         } else {
-            self.gensyms[(SymbolIndex::MAX_AS_U32 - symbol.0.as_u32()) as usize]
+            self.gensyms.lock()[(SymbolIndex::MAX_AS_U32 - symbol.0.as_u32()) as usize]
         }
     }
 
-    fn gensymed(&mut self, symbol: Symbol) -> Symbol {
-        self.gensyms.push(symbol);
-        Symbol::new(SymbolIndex::MAX_AS_U32 - self.gensyms.len() as u32 + 1)
+    fn gensymed(&self, symbol: Symbol) -> Symbol {
+        let mut gensyms = self.gensyms.lock();
+        gensyms.push(symbol);
+        Symbol::new(SymbolIndex::MAX_AS_U32 - gensyms.len() as u32 + 1)
     }
 
-    fn is_gensymed(&mut self, symbol: Symbol) -> bool {
-        symbol.0.as_usize() >= self.strings.len()
+    fn is_gensymed(&self, symbol: Symbol) -> bool {
+        self.lookup(symbol).is_none()
     }
 
     // Get the symbol as a string. `Symbol::as_str()` should be used in
     // preference to this function.
     pub fn get(&self, symbol: Symbol) -> &str {
-        match self.strings.get(symbol.0.as_usize()) {
+        match self.lookup(symbol) {
             Some(string) => string,
             None => {
-                let symbol = self.gensyms[(SymbolIndex::MAX_AS_U32 - symbol.0.as_u32()) as usize];
-                self.strings[symbol.0.as_usize()]
+                let symbol = self.interned(symbol);
+                self.lookup(symbol).unwrap()
             }
         }
     }
+
+    fn stats(&self) -> InternerStats {
+        let mut stats = InternerStats {
+            gensyms: self.gensyms.lock().len(),
+            ..Default::default()
+        };
+        stats.symbols = self.strings.iter().map(|strings| strings.len()).sum();
+        for shard in self.shards.lock_shards() {
+            stats.locks += shard.locks;
+            stats.contended_locks += shard.contended_locks;
+        }
+        stats
+    }
 }
 
 // This module has a very short name because it's used a lot.
@@ -1160,8 +1354,8 @@ impl Ident {
 
 // If an interner exists, return it. Otherwise, prepare a fresh one.
 #[inline]
-fn with_interner<T, F: FnOnce(&mut Interner) -> T>(f: F) -> T {
-    GLOBALS.with(|globals| f(&mut *globals.symbol_interner.lock()))
+fn with_interner<T, F: FnOnce(&Interner) -> T>(f: F) -> T {
+    GLOBALS.with(|globals| f(&globals.symbol_interner))
 }
 
 /// An alternative to `Symbol` and `InternedString`, useful when the chars
@@ -4,16 +4,19 @@ use crate::{edition, Globals};
 
 #[test]
 fn interner_tests() {
-    let mut i: Interner = Interner::default();
-    // first one is zero:
-    assert_eq!(i.intern("dog"), Symbol::new(0));
+    let i: Interner = Interner::default();
+    // The index of a symbol depends on the shard its string is interned into.
+    let dog = i.intern("dog");
     // re-use gets the same entry:
-    assert_eq!(i.intern("dog"), Symbol::new(0));
+    assert_eq!(i.intern("dog"), dog);
     // different string gets a different #:
-    assert_eq!(i.intern("cat"), Symbol::new(1));
-    assert_eq!(i.intern("cat"), Symbol::new(1));
-    // dog is still at zero
-    assert_eq!(i.intern("dog"), Symbol::new(0));
+    let cat = i.intern("cat");
+    assert_ne!(cat, dog);
+    assert_eq!(i.intern("cat"), cat);
+    // dog is still the same
+    assert_eq!(i.intern("dog"), dog);
+    assert_eq!(i.get(dog), "dog");
+    assert_eq!(i.get(cat), "cat");
     let z = i.intern("zebra");
     assert_eq!(i.gensymed(z), Symbol::new(SymbolIndex::MAX_AS_U32));
     // gensym of same string gets new number:
@@ -23,6 +26,33 @@ fn interner_tests() {
     assert_eq!(i.gensymed(d), Symbol::new(SymbolIndex::MAX_AS_U32 - 2));
 }
 
+#[test]
+fn interner_stats_test() {
+    let i = Interner::prefill(&["dog"]);
+    assert_eq!(i.intern("dog"), Symbol::new(0));
+    let cat = i.intern("cat");
+    assert_eq!(i.intern("cat"), cat);
+    assert_eq!(i.get(cat), "cat");
+    i.gensymed(cat);
+    let stats = i.stats();
+    assert_eq!(stats.symbols, 1);
+    assert_eq!(stats.gensyms, 1);
+    // Getting the string of a symbol doesn't lock the shard.
+    assert_eq!(stats.locks, 2);
+    assert_eq!(stats.contended_locks, 0);
+}
+
+#[test]
+fn interner_many_symbols() {
+    let i = Interner::default();
+    let symbols: Vec<_> = (0..1000).map(|n| i.intern(&n.to_string())).collect();
+    for (n, &symbol) in symbols.iter().enumerate() {
+        assert_eq!(i.get(symbol), n.to_string());
+        assert!(!i.is_gensymed(symbol));
+    }
+    assert_eq!(i.stats().symbols, 1000);
+}
+
 #[test]
 fn without_first_quote_test() {
     GLOBALS.set(&Globals::new(edition::DEFAULT_EDITION), || {
@@ -21,7 +21,7 @@ fn test_lookup_line() {
 fn test_normalize_newlines() {
     fn check(before: &str, after: &str) {
         let mut actual = before.to_string();
-        normalize_newlines(&mut actual);
+        normalize_newlines(&mut actual, &mut vec![]);
         assert_eq!(actual.as_str(), after);
     }
     check("", "");
@@ -36,3 +36,196 @@ fn test_normalize_newlines() {
     check("\r\r\n", "\r\n");
     check("hello\rworld", "hello\rworld");
 }
+
+#[test]
+fn test_normalized_pos() {
+    fn check(before: &str, expected: &[(u32, u32)]) {
+        let name = FileName::Custom("test".to_string());
+        let file = SourceFile::new(name.clone(), false, name, before.to_string(), BytePos(10))
+            .unwrap();
+        let actual: Vec<_> = file.normalized_pos.iter().map(|np| (np.pos.0, np.diff)).collect();
+        assert_eq!(actual, expected);
+    }
+    check("hello\nworld\n", &[]);
+    check("a\r\nb\r\n", &[(12, 1), (14, 2)]);
+    check("a\rb\r\n", &[(14, 1)]);
+    check("\u{feff}a\r\n", &[(10, 3), (12, 4)]);
+    check("\u{feff}\r\n", &[(10, 3), (11, 4)]);
+}
+
+#[test]
+fn test_original_relative_byte_pos() {
+    let src = "\u{feff}fn f() {\r\n    x\r\n}\n";
+    let name = FileName::Custom("test".to_string());
+    let file = SourceFile::new(name.clone(), false, name, src.to_string(), BytePos(10)).unwrap();
+    let normalized = file.src.as_ref().unwrap();
+    assert_eq!(normalized.as_str(), "fn f() {\n    x\n}\n");
+
+    // Maps a span in the normalized source to the original source.
+    let original = |lo: usize, hi: usize| {
+        let lo = file.original_relative_byte_pos(BytePos(10 + lo as u32)).to_usize();
+        let hi = file.original_relative_byte_pos(BytePos(10 + hi as u32)).to_usize();
+        &src[lo..hi]
+    };
+    assert_eq!(original(0, 2), "fn");
+    // The `{` and `x` tokens before the line endings.
+    assert_eq!(original(7, 8), "{");
+    assert_eq!(original(13, 14), "x");
+    // The `}` right after a line ending and the plain `\n` following it.
+    assert_eq!(original(15, 16), "}");
+    assert_eq!(original(16, 17), "\n");
+    // The normalized line endings.
+    assert_eq!(original(8, 9), "\r\n");
+    assert_eq!(original(8, 14), "\r\n    x");
+}
+
+#[test]
+fn test_line_ending() {
+    fn check(src: &str, line_endings: &[Option<LineEnding>]) {
+        let name = FileName::Custom("test".to_string());
+        let file = SourceFile::new(name.clone(), false, name, src.to_string(), BytePos(10))
+            .unwrap();
+        let actual: Vec<_> = (0..file.lines.len()).map(|i| file.line_ending(i)).collect();
+        assert_eq!(actual, line_endings, "{:?}", src);
+    }
+    let (lf, crlf) = (Some(LineEnding::Lf), Some(LineEnding::CrLf));
+    check("", &[None]);
+    check("a", &[None]);
+    check("a\nb\n", &[lf, lf, None]);
+    check("a\r\nb", &[crlf, None]);
+    check("\u{feff}a\r\nb\r\n", &[crlf, crlf, None]);
+    check("a\r\nb\nc\r\n", &[crlf, lf, crlf, None]);
+    check("\r\n\n", &[crlf, lf, None]);
+    check("\u{feff}a\nb\n", &[lf, lf, None]);
+}
+
+#[test]
+fn test_restore_line_endings() {
+    let name = FileName::Custom("test".to_string());
+    let src = "a\r\nb\nc\r\nd".to_string();
+    let file = SourceFile::new(name.clone(), false, name, src, BytePos(10)).unwrap();
+    let restore = |pos: u32| file.restore_line_endings(BytePos(10 + pos), "x\ny\n");
+    assert_eq!(restore(0), "x\r\ny\r\n");
+    assert_eq!(restore(2), "x\ny\n");
+    assert_eq!(restore(4), "x\r\ny\r\n");
+    // The last line takes the line ending of the line before it.
+    assert_eq!(restore(6), "x\r\ny\r\n");
+
+    let name = FileName::Custom("test".to_string());
+    let file = SourceFile::new(name.clone(), false, name, "a".to_string(), BytePos(10)).unwrap();
+    assert_eq!(file.restore_line_endings(BytePos(10), "x\ny"), "x\ny");
+}
+
+#[test]
+fn test_line_indents() {
+    let name = FileName::Custom("test".to_string());
+    let src = "fn f() {\n\tx;\n  \n    y;\n\t  z;\n}";
+    let file = SourceFile::new(name.clone(), false, name, src.to_string(), BytePos(10)).unwrap();
+    let indent = |style, len| LineIndent { style, len };
+    assert_eq!(file.line_indents().unwrap(), vec![
+        indent(IndentStyle::None, 0),
+        indent(IndentStyle::Tabs, 1),
+        indent(IndentStyle::Blank, 0),
+        indent(IndentStyle::Spaces, 4),
+        indent(IndentStyle::Mixed, 3),
+        indent(IndentStyle::None, 0),
+    ]);
+}
+
+#[test]
+fn test_indent_unit() {
+    fn check(src: &str, unit: Option<IndentUnit>) {
+        let name = FileName::Custom("test".to_string());
+        let file = SourceFile::new(name.clone(), false, name, src.to_string(), BytePos(10))
+            .unwrap();
+        assert_eq!(file.indent_unit(), unit, "{:?}", src);
+    }
+    check("a\nb\n", None);
+    check("a {\n  b {\n    c\n\n  }\n}\n", Some(IndentUnit::Spaces(2)));
+    check("a {\n    b(c,\n      d)\n    e {\n        f\n    }\n}", Some(IndentUnit::Spaces(4)));
+    check("a {\n\tb {\n\t\tc\n    d\n}", Some(IndentUnit::Tab));
+}
+
+#[test]
+fn test_reindent() {
+    fn reindent(src: &str, line: &str, snippet: &str) -> String {
+        let name = FileName::Custom("test".to_string());
+        let file = SourceFile::new(name.clone(), false, name, src.to_string(), BytePos(10))
+            .unwrap();
+        file.reindent(BytePos(10 + src.find(line).unwrap() as u32), snippet)
+    }
+    let tabs = "fn f() {\n\tif x {\n\t\ty;\n\t}\n}\n";
+    assert_eq!(reindent(tabs, "y;", "y;"), "y;");
+    // The copied indentation of the line is kept, and the rest uses tabs.
+    assert_eq!(
+        reindent(tabs, "y;", "y;\n\t\tif z {\n\t\t    w;\n\t\t}"),
+        "y;\n\t\tif z {\n\t\t\tw;\n\t\t}",
+    );
+    assert_eq!(reindent(tabs, "y;", "{\n        w;\n    }"), "{\n\t\tw;\n\t}");
+
+    let two_spaces = "fn f() {\n  if x {\n    y;\n  }\n}\n";
+    assert_eq!(
+        reindent(two_spaces, "y;", "y;\n    if z {\n        w(a,\n          b);\n    }\n"),
+        "y;\n    if z {\n      w(a,\n        b);\n    }\n",
+    );
+
+    // Files without indentation are left alone, and so are files indented by four spaces.
+    assert_eq!(reindent("a\nb\n", "b", "b\n    c"), "b\n    c");
+    let four_spaces = "fn f() {\n    if x {\n\ty;\n    }\n}\n";
+    assert_eq!(reindent(four_spaces, "y;", "y;\n\t\tz;"), "y;\n\t\tz;");
+
+    // Lines copied from the file are already indented like it.
+    let nested = concat!(
+        "fn f() {\n  if x {\n    if y {\n      if z {\n",
+        "        w;\n      }\n    }\n  }\n}\n",
+    );
+    assert_eq!(reindent(nested, "fn", "fn g() {\n        w;\n}\n"), "fn g() {\n        w;\n}\n");
+    assert_eq!(reindent(nested, "fn", "fn g() {\n        v;\n}\n"), "fn g() {\n    v;\n}\n");
+
+    // The contents of string literals are left alone.
+    let snippet = "y;\n    let s = \"a\n        b\";\n        z;";
+    assert_eq!(reindent(two_spaces, "y;", snippet), "y;\n    let s = \"a\n        b\";\n      z;");
+}
+
+#[test]
+fn test_lines_in_string_literals() {
+    let snippet = r##"f("a
+b", '"', '\'',
+r#"c"
+d"#, // "
+/* " */ e
+"##;
+    assert_eq!(lines_in_string_literals(snippet), [true, false, true, false, false]);
+}
+
+#[test]
+fn test_span_interner() {
+    GLOBALS.set(&Globals::new(edition::DEFAULT_EDITION), || {
+        // Too long to be stored inline.
+        let (lo, hi) = (BytePos(0), BytePos(1 << 20));
+        let expanded_ctxt = SyntaxContext::from_u32(1);
+        let root = Span::new(lo, hi, SyntaxContext::root());
+        let expanded = Span::new(lo, hi, expanded_ctxt);
+        assert_eq!(Span::new(lo, hi, SyntaxContext::root()), root);
+        assert_ne!(root, expanded);
+        assert!(expanded.data() == SpanData { lo, hi, ctxt: expanded_ctxt });
+
+        let stats = Span::interner_stats();
+        assert_eq!((stats.spans, stats.interned, stats.cache_hits), (2, 3, 1));
+
+        // An expansion interning the same spans over and over doesn't take more memory.
+        let spans = (0..100).map(|i| (BytePos(i), BytePos(i + (1 << 20)))).collect::<Vec<_>>();
+        for &(lo, hi) in &spans {
+            Span::new(lo, hi, expanded_ctxt);
+        }
+        let bytes = Span::interner_stats().bytes;
+        assert!(bytes >= 101 * std::mem::size_of::<SpanData>());
+        for _ in 0..10 {
+            for &(lo, hi) in &spans {
+                assert_eq!(Span::new(lo, hi, expanded_ctxt).data().lo, lo);
+            }
+        }
+        let stats = Span::interner_stats();
+        assert_eq!((stats.spans, stats.interned, stats.bytes), (101, 1103, bytes));
+    });
+}
@@ -0,0 +1,9 @@
+// pp-exact
+#![feature(extern_types)]
+
+extern "C" {
+    type Opaque;
+    pub type PubOpaque;
+}
+
+fn main() { }
@@ -1,4 +1,4 @@
-#[crate_type="lib"]
+#![crate_type="lib"]
 
 // #13544
 
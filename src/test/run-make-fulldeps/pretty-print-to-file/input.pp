@@ -1,3 +1,3 @@
 
-#[crate_type = "lib"]
+#![crate_type = "lib"]
 pub fn foo() -> i32 { 45 }
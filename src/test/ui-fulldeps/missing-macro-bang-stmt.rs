@@ -0,0 +1,70 @@
+// run-pass
+
+// ignore-cross-compile
+
+// Checks that a statement calling a macro known to the macro registry of the session without
+// its `!`, like `println("x");`, is recovered from as a macro invocation statement, and that
+// the macro delimiter is checked on statements too.
+
+#![feature(rustc_private)]
+
+extern crate rustc_data_structures;
+extern crate syntax;
+
+use rustc_data_structures::sync::Lrc;
+use syntax::ast::{MacDelimiter, Stmt, StmtKind};
+use syntax::parse::{new_parser_from_source_str, ParseSess};
+use syntax::source_map::{FileName, FilePathMapping};
+use syntax::symbol::Symbol;
+
+fn parse_stmt(source: &str) -> (Stmt, usize) {
+    let ps = ParseSess::new(FilePathMapping::empty());
+    ps.set_macro_registry(Lrc::new(|name: Symbol| match &*name.as_str() {
+        "println" => Some(MacDelimiter::Parenthesis),
+        "vec" => Some(MacDelimiter::Bracket),
+        _ => None,
+    }));
+    let name = FileName::Custom(source.to_string());
+    let mut parser = new_parser_from_source_str(&ps, name, source.to_string());
+    let stmt = match parser.parse_stmt() {
+        Ok(stmt) => stmt.unwrap(),
+        Err(mut err) => {
+            err.emit();
+            panic!("failed to parse `{}`", source);
+        }
+    };
+    (stmt, ps.span_diagnostic.err_count())
+}
+
+fn mac_delim(stmt: &Stmt) -> MacDelimiter {
+    match stmt.node {
+        StmtKind::Mac(ref mac) => mac.0.delim,
+        _ => panic!("expected a macro invocation statement"),
+    }
+}
+
+fn main() {
+    syntax::with_default_globals(|| run());
+}
+
+fn run() {
+    // The missing `!` is an error, recovered from as if it was there.
+    let (stmt, errors) = parse_stmt("println(\"x\");");
+    assert_eq!(mac_delim(&stmt), MacDelimiter::Parenthesis);
+    assert_eq!(errors, 1);
+
+    // An unconventional delimiter is only warned about.
+    let (stmt, errors) = parse_stmt("vec!(1, 2);");
+    assert_eq!(mac_delim(&stmt), MacDelimiter::Parenthesis);
+    assert_eq!(errors, 0);
+
+    // Calls of other names, or of longer paths, are left alone.
+    for source in &["print(\"x\");", "std::println(\"x\");"] {
+        let (stmt, errors) = parse_stmt(source);
+        match stmt.node {
+            StmtKind::Expr(_) => {}
+            _ => panic!("expected `{}` to be parsed as a call", source),
+        }
+        assert_eq!(errors, 0);
+    }
+}
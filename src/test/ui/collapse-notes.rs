@@ -0,0 +1,6 @@
+// compile-flags: -Z collapse-notes
+
+fn main() {
+    Box:new("foo".to_string())
+    //~^ ERROR expected type, found
+}
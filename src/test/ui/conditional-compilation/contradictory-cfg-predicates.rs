@@ -0,0 +1,26 @@
+// Check that `all` predicates whose clauses contradict each other are linted.
+
+#![deny(contradictory_cfg_predicates)]
+
+#[cfg(all(unix, windows))] //~ ERROR `cfg` predicate can never be satisfied
+fn f() {}
+
+#[cfg(all(foo, not(foo)))] //~ ERROR `cfg` predicate can never be satisfied
+fn g() {}
+
+#[cfg(all(target_os = "linux", target_os = "macos"))] //~ ERROR `cfg` predicate can never be
+fn h() {}
+
+#[cfg_attr(all(feature = "a", not(feature = "a")), derive(Debug))] //~ ERROR `cfg` predicate
+struct S;
+
+#[cfg(any(unix, windows))]
+fn i() {}
+
+#[cfg(all(unix, not(windows), target_os = "linux"))]
+fn j() {}
+
+#[cfg(all(feature = "a", feature = "b"))]
+fn k() {}
+
+fn main() {}
@@ -0,0 +1,24 @@
+// run-rustfix
+// Check that `any` and `all` predicates with duplicate clauses are linted.
+
+#![deny(duplicate_cfg_predicates)]
+
+#[cfg(any(foo, foo))] //~ ERROR `any` predicate has duplicate clauses
+fn f() {}
+
+#[cfg(all(unix, not(foo), unix))] //~ ERROR `all` predicate has duplicate clauses
+fn g() {}
+
+#[cfg(any(foo, bar = "baz", foo, bar = "baz"))] //~ ERROR `any` predicate has duplicate clauses
+fn h() {}
+
+#[cfg(not(any(foo, foo)))] //~ ERROR `any` predicate has duplicate clauses
+fn i() {}
+
+#[cfg_attr(all(bar, bar), derive(Debug))] //~ ERROR `all` predicate has duplicate clauses
+struct S;
+
+#[cfg(any(foo, bar = "foo", not(foo)))]
+fn j() {}
+
+fn main() {}
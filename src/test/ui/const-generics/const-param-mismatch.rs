@@ -0,0 +1,18 @@
+#![feature(const_generics)]
+//~^ WARN the feature `const_generics` is incomplete and may cause the compiler to crash
+
+struct Foo<const N: usize>;
+
+fn concrete<const N: usize>(x: Foo<N>) {
+    let _: Foo<3> = x; //~ ERROR mismatched types
+}
+
+fn array<const N: usize>(x: [u8; N]) {
+    let _: [u8; 3] = x; //~ ERROR mismatched types
+}
+
+fn params<const N: usize, const M: usize>(x: Foo<N>) {
+    let _: Foo<M> = x; //~ ERROR mismatched types
+}
+
+fn main() {}
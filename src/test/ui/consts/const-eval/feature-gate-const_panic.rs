@@ -1,10 +1,10 @@
 fn main() {}
 
 const Z: () = panic!("cheese");
-//~^ ERROR panicking in constants is unstable
+//~^ ERROR the macro `panic!` uses unstable feature `const_panic`
 
 const Y: () = unreachable!();
-//~^ ERROR panicking in constants is unstable
+//~^ ERROR the macro `unreachable!` uses unstable feature `const_panic`
 
 const X: () = unimplemented!();
-//~^ ERROR panicking in constants is unstable
+//~^ ERROR the macro `unimplemented!` uses unstable feature `const_panic`
@@ -0,0 +1,14 @@
+// Built-in derives that move or create field values reject dynamically sized fields.
+
+#[derive(Clone)]
+struct Slice {
+    len: usize,
+    data: [u8],
+    //~^ ERROR `#[derive(Clone)]` cannot be used on structs with a dynamically sized
+}
+
+#[derive(Default)]
+struct Object(u8, dyn std::fmt::Debug);
+//~^ ERROR `#[derive(Default)]` cannot be used on structs with a dynamically sized
+
+fn main() {}
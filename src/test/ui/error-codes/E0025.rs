@@ -7,4 +7,6 @@ fn main() {
     let x = Foo { a:1, b:2 };
     let Foo { a: x, a: y, b: 0 } = x;
     //~^ ERROR field `a` bound multiple times in the pattern
+    let Foo { a: x, b: y, a: _, b: _ } = Foo { a: 1, b: 2 };
+    //~^ ERROR fields `a`, `b` bound multiple times in the pattern
 }
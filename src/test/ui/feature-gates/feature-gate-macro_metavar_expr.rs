@@ -0,0 +1,7 @@
+macro_rules! count {
+    ($($x:ident)*) => { ${count(x)} }; //~ ERROR meta-variable expressions are unstable
+}
+
+fn main() {
+    let _ = count!(a b);
+}
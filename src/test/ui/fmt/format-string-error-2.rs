@@ -80,7 +80,9 @@ raw  { \n
     // note: raw strings don't escape `\xFF` and `\u{FF}` sequences
     println!(r#"\x7B}\u{8} {"#, 1);
     //~^ ERROR invalid format string: unmatched `}` found
+    //~| ERROR invalid format string: expected `'}'` but string was terminated
 
     println!(r#"\x7B}\u8 {"#, 1);
     //~^ ERROR invalid format string: unmatched `}` found
+    //~| ERROR invalid format string: expected `'}'` but string was terminated
 }
@@ -0,0 +1,10 @@
+// Check that a `{` followed only by an argument up to the end of the format string points at
+// the brace that isn't closed.
+
+fn main() {
+    let name = 1;
+    println!("{name", name = name);
+    //~^ ERROR invalid format string: expected `'}'` but string was terminated
+    println!("{} {0  ", 1);
+    //~^ ERROR invalid format string: expected `'}'` but string was terminated
+}
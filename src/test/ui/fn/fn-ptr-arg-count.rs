@@ -0,0 +1,10 @@
+fn two(a: u8, b: u8) -> u8 { a + b }
+
+fn none() -> u8 { 0 }
+
+fn main() {
+    let _: fn(u8) -> u8 = two;
+    //~^ ERROR mismatched types
+    let _: fn(u8) -> u8 = none;
+    //~^ ERROR mismatched types
+}
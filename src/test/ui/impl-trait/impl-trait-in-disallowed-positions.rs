@@ -0,0 +1,28 @@
+// Checks the position-specific errors and suggestions for `impl Trait` in positions where it's
+// never allowed.
+
+#![allow(dead_code)]
+
+use std::fmt::Debug;
+
+fn with_generics<T: Copy>(_: T, _: fn(impl Debug)) {}
+//~^ ERROR `impl Trait` is not allowed in function pointer types
+
+struct S;
+
+impl S {
+    fn method(&self, _: fn() -> impl Debug) {}
+    //~^ ERROR `impl Trait` is not allowed in function pointer types
+}
+
+struct Wrapper<T>(T);
+
+impl<T> Wrapper<T> {
+    fn method<U>(&self, _: U, _: fn(impl Debug)) {}
+    //~^ ERROR `impl Trait` is not allowed in function pointer types
+}
+
+fn main() {
+    let _ = |x: impl Debug| x;
+    //~^ ERROR `impl Trait` is not allowed in closure parameter types
+}
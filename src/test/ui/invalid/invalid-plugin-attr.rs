@@ -1,7 +1,8 @@
 #![deny(unused_attributes)]
 #![feature(plugin)]
 
+mod m {}
+
 #[plugin(bla)]  //~ ERROR unused attribute
                 //~^ ERROR should be an inner attribute
-
 fn main() {}
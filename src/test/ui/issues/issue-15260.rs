@@ -18,8 +18,7 @@ fn main() {
     let Foo {
         a,
         a: _,
-        //~^ ERROR field `a` bound multiple times in the pattern
         a: x
-        //~^ ERROR field `a` bound multiple times in the pattern
+        //~^^ ERROR field `a` bound multiple times in the pattern
     } = Foo { a: 29 };
 }
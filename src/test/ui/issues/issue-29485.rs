@@ -1,10 +1,7 @@
 // run-pass
-#![allow(unused_attributes)]
 // aux-build:issue-29485.rs
 // ignore-emscripten no threads
 
-#[feature(recover)]
-
 extern crate a;
 
 fn main() {
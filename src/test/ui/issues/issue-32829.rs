@@ -1,5 +1,5 @@
 static S : u64 = { { panic!("foo"); 0 } };
-//~^ ERROR panicking in statics is unstable
+//~^ ERROR the macro `panic!` uses unstable feature `const_panic`
 
 fn main() {
     println!("{:?}", S);
@@ -0,0 +1,23 @@
+// compile-flags: -Z item-headers
+
+struct S;
+
+impl S {
+    fn new() -> S {
+        no_such_value //~ ERROR cannot find value
+    }
+}
+
+impl Default for S {
+    fn default() -> S {
+        no_such_value //~ ERROR cannot find value
+    }
+}
+
+static X: u8 = no_such_value; //~ ERROR cannot find value
+
+fn main() {
+    fn helper() {
+        no_such_value; //~ ERROR cannot find value
+    }
+}
@@ -0,0 +1,8 @@
+// compile-flags: --json=compact,byte-columns,utf16-columns --error-format=json
+
+#![crate_type = "lib"]
+#![deny(unused_variables)]
+
+pub fn f() {
+    let _s = "é𝄞"; let x = 1;
+}
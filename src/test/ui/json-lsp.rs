@@ -0,0 +1,9 @@
+// compile-flags: --json=lsp --error-format=json
+// ignore-windows: the URI of the file has forward slashes, which `$DIR` does not match
+
+#![crate_type = "lib"]
+#![deny(unused_variables)]
+
+pub fn f() {
+    let _s = "é𝄞"; let x = 1;
+}
@@ -0,0 +1,8 @@
+// compile-flags: --json=summary,compact --error-format=json
+
+#![crate_type = "lib"]
+#![deny(unused_variables)]
+
+pub fn f() {
+    let x = 1;
+}
@@ -0,0 +1,4 @@
+// check-pass
+// compile-flags: --json=summary --error-format=json
+
+#![crate_type = "lib"]
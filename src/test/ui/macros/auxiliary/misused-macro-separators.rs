@@ -0,0 +1,6 @@
+#![allow(misused_macro_separators)]
+
+#[macro_export]
+macro_rules! trailing_commas {
+    ($($x:expr),+ $(,)+) => {};
+}
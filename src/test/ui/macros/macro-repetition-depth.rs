@@ -0,0 +1,7 @@
+macro_rules! shallow {
+    ($x:ident) => { $($x)* }; //~ ERROR attempted to repeat an expression containing no syntax
+}
+
+fn main() {
+    shallow!(a);
+}
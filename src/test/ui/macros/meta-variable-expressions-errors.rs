@@ -0,0 +1,20 @@
+#![feature(macro_metavar_expr)]
+#![allow(unused_macros)]
+
+macro_rules! unknown {
+    () => { ${length()} }; //~ ERROR unrecognized meta-variable expression `length`
+}
+
+macro_rules! no_args {
+    ($($x:ident)*) => { ${count} }; //~ ERROR expected `(` after `count`
+}
+
+macro_rules! dollar {
+    ($($x:ident)*) => { ${count($x)} }; //~ ERROR expected the name of a meta-variable, without `$`
+}
+
+macro_rules! bad_depth {
+    ($($x:ident)*) => { $(${index(x)})* }; //~ ERROR expected a depth
+}
+
+fn main() {}
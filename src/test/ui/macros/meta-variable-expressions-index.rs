@@ -0,0 +1,9 @@
+#![feature(macro_metavar_expr)]
+
+macro_rules! index {
+    () => { ${index()} }; //~ ERROR `index` can only be used in a repetition
+}
+
+fn main() {
+    index!();
+}
@@ -0,0 +1,27 @@
+// run-pass
+
+#![feature(macro_metavar_expr)]
+
+macro_rules! count {
+    ($($x:expr),*) => { ${count(x)} };
+}
+
+macro_rules! enumerate {
+    ($($x:ident),*) => { [$((stringify!($x), ${index()})),*] };
+}
+
+macro_rules! nested_count {
+    ($($($x:ident)*);*) => { (${count(x)}, ${count(x, 0)}, ${count(x, 1)}) };
+}
+
+macro_rules! nested_index {
+    ($($($x:ident)*);*) => { [$($((stringify!($x), ${index(1)}, ${index()})),*),*] };
+}
+
+fn main() {
+    assert_eq!(count!(), 0);
+    assert_eq!(count!(1, 2, 3), 3);
+    assert_eq!(enumerate!(a, b, c), [("a", 0), ("b", 1), ("c", 2)]);
+    assert_eq!(nested_count!(a b; c; d e f), (6, 3, 6));
+    assert_eq!(nested_index!(a b; c), [("a", 0, 0), ("b", 0, 1), ("c", 1, 0)]);
+}
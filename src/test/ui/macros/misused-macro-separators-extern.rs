@@ -0,0 +1,11 @@
+// Macros loaded from other crates aren't checked by the `misused_macro_separators` lint.
+
+// check-pass
+// aux-build:misused-macro-separators.rs
+
+#[macro_use]
+extern crate misused_macro_separators;
+
+fn main() {
+    trailing_commas!(1, 2,);
+}
@@ -0,0 +1,31 @@
+// Checks the `misused_macro_separators` lint on the matchers of `macro_rules!` definitions.
+
+macro_rules! separated_and_terminated {
+    ($($x:expr;);*) => {}; //~ ERROR the separator of this repetition also ends each repetition
+}
+
+macro_rules! trailing_commas {
+    ($($x:expr),+ $(,)+) => {}; //~ ERROR this repetition matches one or more trailing `,`
+}
+
+macro_rules! statements {
+    ($($s:stmt);*) => {};
+    ($a:stmt; $b:stmt) => {}; //~ ERROR this rule is unreachable
+}
+
+macro_rules! single_statement {
+    ($($s:stmt);*) => {};
+    ($a:stmt) => {}; //~ ERROR this rule is unreachable
+}
+
+#[allow(misused_macro_separators)]
+macro_rules! allowed_at_definition {
+    ($($x:expr;);*) => {};
+}
+
+macro_rules! allowed {
+    ($($x:expr),* $(,)?) => {};
+    ($($s:stmt);* $(;)?) => {};
+}
+
+fn main() {}
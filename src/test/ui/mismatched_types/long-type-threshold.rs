@@ -0,0 +1,7 @@
+// compile-flags: -Z long-type-threshold=16
+// normalize-stderr-test: "written to `.*long-type-[0-9a-f]+\.txt`" -> "written to `$$FILE`"
+
+fn main() {
+    let _: (u8, u16, u32, u64, i8) = ();
+    //~^ ERROR mismatched types
+}
@@ -0,0 +1,18 @@
+// compile-flags: -Z trim-diagnostic-paths
+
+use std::rc::Rc;
+
+mod a {
+    pub struct Foo;
+}
+
+mod b {
+    pub struct Foo;
+}
+
+fn main() {
+    let _: Rc<u8> = Box::new(0u8);
+    //~^ ERROR mismatched types
+    let _: a::Foo = b::Foo;
+    //~^ ERROR mismatched types
+}
@@ -0,0 +1,11 @@
+// Attributes at the end of a module are reported, and the rest of the crate is still checked.
+
+mod m {
+    #[inline]
+    //~^ ERROR expected item after attributes
+}
+
+fn main() {
+    let _: () = 0;
+    //~^ ERROR mismatched types
+}
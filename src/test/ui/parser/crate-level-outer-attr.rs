@@ -0,0 +1,11 @@
+// run-rustfix
+// rustfix-only-machine-applicable
+
+#[crate_type = "lib"] //~ ERROR crate-level attribute should be an inner attribute
+#[feature(rustc_attrs)] //~ ERROR crate-level attribute should be an inner attribute
+#[inline]
+pub fn f() {}
+
+#[inline]
+#![cold] //~ ERROR an inner attribute is not permitted following an outer attribute
+pub fn g() {}
@@ -0,0 +1,7 @@
+// compile-flags: -Z parse-expr-depth-limit=10
+
+fn main() {
+    let _ = !!!!!!!!!!!!!!!!!!!!!!!!!!!!!!true; // prefix operators don't nest the parser
+    let _ = 1 = 2 = 3 = 4 = 5 = 6 = 7 = 8 = 9 = 10 = 11 = 12; // neither do binary operators
+    let _ = ((((((((((((1)))))))))))); //~ ERROR expression is nested too deeply
+}
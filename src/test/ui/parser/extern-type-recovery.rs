@@ -0,0 +1,12 @@
+#![feature(extern_types)]
+
+extern type A; //~ ERROR extern types must be declared in an `extern` block
+
+pub extern "C" type B; //~ ERROR extern types must be declared in an `extern` block
+
+extern {
+    type C<T>; //~ ERROR extern types cannot have generic parameters
+    type D = u8; //~ ERROR extern types cannot have a definition
+}
+
+fn main() {}
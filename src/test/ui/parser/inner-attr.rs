@@ -1,4 +1,4 @@
-#[feature(lang_items)]
+#[feature(lang_items)] //~ ERROR an outer attribute is not permitted before an inner attribute
 
-#![recursion_limit="100"] //~ ERROR an inner attribute is not permitted following an outer attribute
+#![recursion_limit="100"]
 fn main() {}
@@ -0,0 +1,20 @@
+// run-rustfix
+
+#![allow(dead_code)]
+
+fn f<T: where Vec<T>: Clone>(t: T) -> Vec<T> { vec![t].clone() }
+//~^ ERROR `where` clauses are not allowed inside generic parameter lists
+
+fn g<T: where T: Copy, Vec<T>: Clone>(t: T) where T: Default { drop(t) }
+//~^ ERROR `where` clauses are not allowed inside generic parameter lists
+
+fn h<T: where Vec<T>: Clone>(t: T) where T: Default, { drop(t) }
+//~^ ERROR `where` clauses are not allowed inside generic parameter lists
+
+struct S<T: where Vec<T>: Clone> { v: Vec<T> }
+//~^ ERROR `where` clauses are not allowed inside generic parameter lists
+
+struct U<T: where Vec<T>: Clone>(Vec<T>);
+//~^ ERROR `where` clauses are not allowed inside generic parameter lists
+
+fn main() {}
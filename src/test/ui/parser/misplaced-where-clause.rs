@@ -0,0 +1,15 @@
+fn f<T>(t: T) where T: Clone -> T { t }
+//~^ ERROR return type should be specified before the `where` clause
+
+fn g<T: where T: Clone>(t: T) -> T { t.clone() }
+//~^ ERROR `where` clauses are not allowed inside generic parameter lists
+
+// The predicates that don't bound a parameter are kept in the `where` clause.
+fn h<T: where Vec<T>: Clone>(t: T) -> Vec<T> { vec![t].clone() }
+//~^ ERROR `where` clauses are not allowed inside generic parameter lists
+
+fn main() {
+    f(1);
+    g(1);
+    h(1);
+}
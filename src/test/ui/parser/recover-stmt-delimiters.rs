@@ -0,0 +1,24 @@
+// Recovery from an error in a struct field skips over the parenthesized and generic arguments
+// and the closure parameters after it as a whole, without stopping at the commas in them. An
+// error in call arguments stops the recovery at their `)`, which the struct skips.
+
+struct S {
+    foo: (),
+    bar: (),
+}
+
+fn f<T>(_: T, _: T) {}
+
+fn main() {
+    let a = S { foo: (), bar: () };
+    let b = S { foo: () with f(a, a), bar: () };
+    //~^ ERROR expected one of `,`, `.`, `?`, `}`, or an operator, found `with`
+    let c = S { foo: () with f::<S, S>, bar: () };
+    //~^ ERROR expected one of `,`, `.`, `?`, `}`, or an operator, found `with`
+    let d = S { foo: () with f(|x, y| { x; y }, ()), bar: () };
+    //~^ ERROR expected one of `,`, `.`, `?`, `}`, or an operator, found `with`
+    let e = S { foo: () with |x, y| x, bar: () };
+    //~^ ERROR expected one of `,`, `.`, `?`, `}`, or an operator, found `with`
+    let g = S { foo: f((), () with a), bar: () };
+    //~^ ERROR expected one of `)`, `,`, `.`, `?`, or an operator, found `with`
+}
@@ -0,0 +1,13 @@
+// compile-flags: --error-format=short-fixes -Z unstable-options
+
+// Suggestions on code expanded from a macro edit the definition of the macro, so they are
+// not marked as fixes that can be applied automatically.
+
+macro_rules! in_in {
+    ($e:expr) => { for _ in in $e {} }
+}
+
+fn main() {
+    for _ in in 0..3 {}
+    in_in!(0..3);
+}
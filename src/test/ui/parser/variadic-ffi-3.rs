@@ -1,5 +1,5 @@
 fn foo(x: isize, ...) {
-    //~^ ERROR: only foreign functions are allowed to be C-variadic
+    //~^ ERROR: only foreign or `unsafe extern "C"` functions may be C-variadic
 }
 
 fn main() {}
@@ -0,0 +1,6 @@
+extern {
+    fn foo(x: i32, ..., y: i32);
+    //~^ ERROR `...` must be the last argument of a C-variadic function
+}
+
+fn main() {}
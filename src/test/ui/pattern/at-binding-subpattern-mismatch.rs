@@ -0,0 +1,17 @@
+// Check that a subpattern of `x @ subpattern` not matching the value points at `x`.
+
+struct S(u8);
+enum E { C(u8) }
+
+fn main() {
+    match 0u16 {
+        x @ 1u8 => {}
+        //~^ ERROR mismatched types
+        _ => {}
+    }
+    match S(0) {
+        s @ E::C(_) => {}
+        //~^ ERROR mismatched types
+        _ => {}
+    }
+}
@@ -0,0 +1,35 @@
+// Unit test for the shapes of type-checked patterns, as computed by `pat_shape`.
+
+#![feature(rustc_attrs)]
+#![allow(dead_code, unused_variables)]
+
+struct Point { x: i32, y: i32 }
+
+type Alias = Point;
+
+const ORIGIN: i32 = 0;
+
+#[rustc_dump_pat_shapes]
+fn shapes(opt: &Option<(u8, char)>, p: Point, s: &[i32]) {
+    //~^ ERROR pat shape: _
+    //~| ERROR pat shape: _
+    //~| ERROR pat shape: _
+    match opt {
+        Some((1..=9, 'a')) => {}
+        //~^ ERROR pat shape: (std::option::Option::Some((1..=9, 'a')),)
+        None | Some(_) => {}
+        //~^ ERROR pat shape: (std::option::Option::None,)
+        //~| ERROR pat shape: (std::option::Option::Some(_),)
+    }
+    let Alias { y, .. } = p;
+    //~^ ERROR pat shape: Point(_, _)
+    match s {
+        [ORIGIN, .., -1] | [] => {}
+        //~^ ERROR pat shape: ([ORIGIN, .., -1],)
+        //~| ERROR pat shape: ([],)
+        _ => {}
+        //~^ ERROR pat shape: _
+    }
+}
+
+fn main() {}
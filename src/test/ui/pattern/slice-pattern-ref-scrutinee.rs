@@ -0,0 +1,33 @@
+// check-pass
+
+// Slice patterns look through references to arrays and slices, binding the elements by
+// reference.
+
+#![feature(slice_patterns)]
+
+fn first<T: Copy>(xs: &[T]) -> Option<T> {
+    match xs {
+        [x, ..] => Some(*x),
+        [] => None,
+    }
+}
+
+fn swap_ends(xs: &mut [u8; 3]) {
+    match xs {
+        [a, _, b] => std::mem::swap(a, b),
+    }
+}
+
+fn main() {
+    let mut arr = [1, 2, 3];
+    swap_ends(&mut arr);
+    assert_eq!(first(&arr), Some(3));
+
+    let borrowed = &&arr;
+    let [x, y, z] = borrowed;
+    let _: (&u8, &u8, &u8) = (x, y, z);
+
+    let inferred = Default::default();
+    let [a, b]: &[u8; 2] = inferred;
+    let _: (&u8, &u8) = (a, b);
+}
@@ -0,0 +1,11 @@
+// Slice patterns need the type they match to be known before looking through references to
+// it, and say so when it isn't.
+
+#![feature(slice_patterns)]
+
+fn main() {
+    match Default::default() {
+        [first, ..] => {}
+        //~^ ERROR type annotations needed
+    }
+}
@@ -0,0 +1,21 @@
+// run-rustfix
+
+#![allow(dead_code)]
+
+trait Tr {
+    fn f();
+}
+
+struct S;
+
+pub impl Tr for S { //~ ERROR unnecessary visibility qualifier
+    pub fn f() {} //~ ERROR unnecessary visibility qualifier
+}
+
+pub(crate) extern "C" {} //~ ERROR unnecessary visibility qualifier
+
+enum E {
+    V(pub u8), //~ ERROR unnecessary visibility qualifier
+}
+
+fn main() {}
@@ -0,0 +1,34 @@
+// force-host
+// no-prefer-dynamic
+
+#![crate_type = "proc-macro"]
+
+extern crate proc_macro;
+
+use proc_macro::*;
+
+/// Refers to the last field of the struct it's derived for, with the span of the field's name,
+/// from a function where no such value is in scope.
+#[proc_macro_derive(LastField)]
+pub fn derive_last_field(input: TokenStream) -> TokenStream {
+    let fields = input.into_iter().filter_map(|tree| match tree {
+        TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => Some(group.stream()),
+        _ => None,
+    }).last().unwrap();
+    // The names of the fields are the identifiers followed by a `:`.
+    let fields = fields.into_iter().collect::<Vec<_>>();
+    let name = fields.windows(2).filter_map(|pair| match (&pair[0], &pair[1]) {
+        (TokenTree::Ident(name), TokenTree::Punct(colon)) if colon.as_char() == ':' => {
+            Some(name.clone())
+        }
+        _ => None,
+    }).last().unwrap();
+
+    let body = vec![
+        TokenTree::Ident(name),
+        TokenTree::Punct(Punct::new(';', Spacing::Alone)),
+    ];
+    let mut output: TokenStream = "fn last_field()".parse().unwrap();
+    output.extend(vec![TokenTree::Group(Group::new(Delimiter::Brace, body.into_iter().collect()))]);
+    output
+}
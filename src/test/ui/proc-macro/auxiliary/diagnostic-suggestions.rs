@@ -0,0 +1,42 @@
+// force-host
+// no-prefer-dynamic
+
+#![crate_type = "proc-macro"]
+#![feature(proc_macro_diagnostic)]
+
+extern crate proc_macro;
+
+use proc_macro::{Applicability, Diagnostic, Level, TokenStream, TokenTree};
+
+#[proc_macro]
+pub fn compare(input: TokenStream) -> TokenStream {
+    let tokens = input.into_iter().collect::<Vec<_>>();
+    if let [TokenTree::Ident(ref lhs), TokenTree::Punct(ref op), TokenTree::Ident(ref rhs)] =
+        tokens[..]
+    {
+        if op.as_char() == '=' {
+            op.span().error("expected a comparison, found `=`")
+                .span_note(lhs.span(), "the left operand is here")
+                .span_suggestion(op.span(), "compare with `==`", "==",
+                                 Applicability::MachineApplicable)
+                .emit();
+        } else if op.as_char() == '>' {
+            let parts = vec![
+                (lhs.span(), rhs.to_string()),
+                (op.span(), "<".to_string()),
+                (rhs.span(), lhs.to_string()),
+            ];
+            Diagnostic::spanned(vec![lhs.span(), rhs.span()], Level::Warning,
+                                "comparisons are written smaller first")
+                .multipart_suggestion("swap the operands", parts, Applicability::MaybeIncorrect)
+                .emit();
+        } else if op.as_char() == '<' {
+            // The parts overlap, so the suggestion is rejected.
+            let parts = vec![(op.span(), ">".to_string()), (op.span(), "<".to_string())];
+            op.span().warning("overlapping suggestion parts")
+                .multipart_suggestion("replace `<` twice", parts, Applicability::MaybeIncorrect)
+                .emit();
+        }
+    }
+    TokenStream::new()
+}
@@ -0,0 +1,13 @@
+// force-host
+// no-prefer-dynamic
+
+#![crate_type = "proc-macro"]
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+
+#[proc_macro_attribute]
+pub fn reapply(_: TokenStream, item: TokenStream) -> TokenStream {
+    format!("#[reapply] {}", item).parse().unwrap()
+}
@@ -0,0 +1,19 @@
+// aux-build:derive-last-field.rs
+
+// The tokens handed to derives keep their spans when a statement stripped by `#[cfg]` goes on
+// past the block of an `if` expression.
+
+#[macro_use]
+extern crate derive_last_field;
+
+#[derive(LastField)]
+struct S {
+    a: [u8; {
+        #[cfg(FALSE)]
+        let a = if true { 1 } else { 2 };
+        3
+    }],
+    last_field_of_s: u8, //~ ERROR cannot find value `last_field_of_s` in this scope
+}
+
+fn main() {}
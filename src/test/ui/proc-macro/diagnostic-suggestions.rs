@@ -0,0 +1,13 @@
+// aux-build:diagnostic-suggestions.rs
+
+#![feature(proc_macro_hygiene)]
+
+extern crate diagnostic_suggestions;
+
+use diagnostic_suggestions::compare;
+
+fn main() {
+    compare!(a = b); //~ ERROR expected a comparison, found `=`
+    compare!(a > b); //~ WARN comparisons are written smaller first
+    compare!(a < b); //~ WARN overlapping suggestion parts
+}
@@ -0,0 +1,13 @@
+// aux-build:reapply.rs
+
+#![recursion_limit = "16"]
+
+extern crate reapply;
+
+use reapply::reapply;
+
+#[reapply]
+//~^ ERROR cycle detected when expanding the attribute macro `reapply`
+struct S;
+
+fn main() {}
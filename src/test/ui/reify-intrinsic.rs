@@ -1,6 +1,6 @@
 // check-fail
 
-#![feature(intrinsics)]
+#![feature(core_intrinsics, intrinsics)]
 
 fn a() {
     let _: unsafe extern "rust-intrinsic" fn(isize) -> usize = std::mem::transmute;
@@ -12,4 +12,14 @@ fn b() {
     //~^ ERROR casting
 }
 
+fn c() {
+    let _: fn(u8) -> u8 = std::intrinsics::bswap::<u8>;
+    //~^ ERROR cannot coerce
+}
+
+fn d() {
+    let _: unsafe fn(*const u8) -> u8 = std::intrinsics::volatile_load::<u8>;
+    //~^ ERROR cannot coerce
+}
+
 fn main() {}
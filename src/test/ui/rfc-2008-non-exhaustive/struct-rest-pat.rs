@@ -0,0 +1,27 @@
+// run-rustfix
+// aux-build:structs.rs
+// Check that `..` is suggested after the trailing comma of a field list, if there is one.
+
+extern crate structs;
+
+use structs::{NormalStruct, UnitStruct};
+
+fn trailing_comma(ns: NormalStruct) {
+    let NormalStruct { first_field, second_field, } = ns;
+    //~^ ERROR `..` required with struct marked as non-exhaustive
+}
+
+fn multiline(ns: NormalStruct) {
+    let NormalStruct {
+        first_field,
+        second_field,
+    } = ns;
+    //~^^^^ ERROR `..` required with struct marked as non-exhaustive
+}
+
+fn empty(us: UnitStruct) {
+    let UnitStruct {} = us;
+    //~^ ERROR `..` required with struct marked as non-exhaustive
+}
+
+fn main() {}
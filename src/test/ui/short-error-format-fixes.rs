@@ -0,0 +1,8 @@
+// check-pass
+// compile-flags: --error-format=short-fixes -Z unstable-options
+
+fn unused() {}
+
+fn main() {
+    let x = 1;
+}
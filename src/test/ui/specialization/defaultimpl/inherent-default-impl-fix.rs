@@ -0,0 +1,10 @@
+// run-rustfix
+
+#![feature(specialization)]
+#![allow(dead_code)]
+
+struct S;
+
+default impl S {} //~ ERROR inherent impls cannot be default
+
+fn main() {}
@@ -0,0 +1,24 @@
+// Checks that each malformed argument of a stability attribute is reported, and that
+// versions are written as `major.minor.patch`
+
+#![feature(staged_api)]
+
+#![stable(feature = "stable_test_feature", since = "1.0.0")]
+
+#[stable(feature = "a", since = "1.0")] //~ ERROR malformed version in 'since'
+fn f1() { }
+
+#[stable(feature = "a", since = "v1.2.3")] //~ ERROR malformed version in 'since'
+fn f2() { }
+
+#[stable(feature = "a", since = "1.2.3")]
+#[rustc_deprecated(since = "1.3", reason = "text")] //~ ERROR malformed version in 'since'
+fn f3() { }
+
+#[unstable(feature(a), issue = "no", reason)]
+//~^ ERROR incorrect meta item
+//~| ERROR incorrect 'issue'
+//~| ERROR incorrect meta item
+fn f4() { }
+
+fn main() { }
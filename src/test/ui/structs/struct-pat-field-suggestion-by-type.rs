@@ -0,0 +1,25 @@
+// Among fields with equally similar names, the one whose type the pattern can match is
+// suggested.
+
+struct S {
+    fob: String,
+    foo: u8,
+}
+
+struct T {
+    foo: u8,
+    fob: char,
+}
+
+fn main() {
+    match (S { fob: String::new(), foo: 0 }) {
+        S { fox: 0, .. } => {}
+        //~^ ERROR struct `S` does not have a field named `fox`
+        _ => {}
+    }
+    match (T { foo: 0, fob: 'a' }) {
+        T { fox: 'a', .. } => {}
+        //~^ ERROR struct `T` does not have a field named `fox`
+        _ => {}
+    }
+}
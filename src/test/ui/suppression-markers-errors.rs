@@ -0,0 +1,6 @@
+// Errors silenced by suppression markers still fail compilation.
+
+fn main() {
+    // rustc: allow(E0308) next-line
+    let _x: u8 = "";
+}
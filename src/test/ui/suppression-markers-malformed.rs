@@ -0,0 +1,8 @@
+// check-pass
+
+fn main() {
+    // rustc: allow unused_variables
+    let _x = 1;
+    // rustc: allowing anything here would be a mistake
+    let _y = 2;
+}
@@ -0,0 +1,9 @@
+// check-pass
+// compile-flags: --error-format=short
+
+fn main() {
+    // rustc: allow(unused_variables) next-line
+    let x = 1;
+    let y = 2; // rustc: allow(unused_variables)
+    let z = 3;
+}
@@ -5,6 +5,5 @@ enum X {
 fn main() {
     match X::Y(0) {
         X::Y { number } => {} //~ ERROR does not have a field named `number`
-        //~^ ERROR pattern does not mention field `0`
     }
 }
@@ -0,0 +1,29 @@
+fn point(x: i32, y: i32) -> (i32, i32) { (x, y) }
+
+struct Point(i32, i32);
+
+fn main() {
+    let (x, y);
+    let _ = point(x = 1, y = 2);
+    //~^ ERROR mismatched types
+    //~| ERROR mismatched types
+    let mut s = String::new();
+    let c;
+    s.push(c = 'a');
+    //~^ ERROR mismatched types
+    // Assignments are still valid arguments, of type `()`.
+    let z;
+    drop(z = 1);
+    let _ = (x, y, c, z);
+
+    // Undeclared names are reported by resolve, and the values are checked as the arguments.
+    let _ = point(a = 1, b = "two");
+    //~^ ERROR cannot find value `a` in this scope
+    //~| ERROR cannot find value `b` in this scope
+    //~| ERROR mismatched types
+    let _ = Point(first = 1, second = 2);
+    //~^ ERROR cannot find value `first` in this scope
+    //~| ERROR cannot find value `second` in this scope
+    s.push(letter = 'b');
+    //~^ ERROR cannot find value `letter` in this scope
+}
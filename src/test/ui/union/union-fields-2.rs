@@ -18,6 +18,5 @@ fn main() {
     let U { a, b, c } = u; //~ ERROR union patterns should have exactly one field
                            //~^ ERROR union `U` does not have a field named `c`
     let U { .. } = u; //~ ERROR union patterns should have exactly one field
-                      //~^ ERROR `..` cannot be used in union patterns
     let U { a, .. } = u; //~ ERROR `..` cannot be used in union patterns
 }